@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::archive::{SessionArchive, ARCHIVE_EXTENSION, MAX_ARCHIVE_LEN};
+use crate::audit::{self, AuditQuery, AUDIT_FILE_NAME};
+use crate::diff::diff_objects;
+use crate::gltf::{export_scene, import_objects, Gltf, GltfFormat, MAX_GLTF_LEN};
+use crate::handlers::helpers::{add_imported_objects, insert_new_session, now_ms};
+use crate::messages::AuditLogPayload;
+use crate::storage::encode_session_id;
+use crate::types::{AppState, SceneObject, SessionHandle};
+use crate::usd::{self, Layer, MAX_USDA_LEN, USDA_CONTENT_TYPE, USDA_EXTENSION};
+
+/// Bearer token for the admin HTTP API. The `/admin` routes are not mounted when it is unset.
+pub const ADMIN_TOKEN_ENV: &str = "MEERKAT_ADMIN_TOKEN";
+
+/// Password for a session created by `POST /admin/sessions/{id}/import`; a header keeps it out
+/// of URLs and access logs.
+pub const SESSION_PASSWORD_HEADER: &str = "x-meerkat-session-password";
+
+#[derive(Clone)]
+pub struct AdminState {
+    pub app: AppState,
+    token: Arc<SecretString>,
+}
+
+/// Admin routes, all behind `Authorization: Bearer <token>`.
+pub fn router(app: AppState, token: SecretString) -> Router {
+    let admin = AdminState { app, token: Arc::new(token) };
+    Router::new()
+        .route("/admin/sessions/{session_id}/diff", get(session_diff))
+        .route("/admin/sessions/{session_id}/audit", get(session_audit))
+        .route("/admin/sessions/{session_id}/export", get(session_export))
+        .route(
+            "/admin/sessions/{session_id}/gltf",
+            get(session_gltf).post(session_gltf_import).layer(DefaultBodyLimit::max(MAX_GLTF_LEN)),
+        )
+        .route(
+            "/admin/sessions/{session_id}/usd",
+            get(session_usd).post(session_usd_import).layer(DefaultBodyLimit::max(MAX_USDA_LEN)),
+        )
+        .route(
+            "/admin/sessions/{session_id}/import",
+            post(session_import).layer(DefaultBodyLimit::max(MAX_ARCHIVE_LEN)),
+        )
+        .route_layer(middleware::from_fn_with_state(admin.clone(), require_token))
+        .with_state(admin)
+}
+
+/// Read the admin token from the environment; empty counts as unset.
+pub fn token_from_env() -> Option<SecretString> {
+    std::env::var(ADMIN_TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .map(SecretString::from)
+}
+
+async fn require_token(State(admin): State<AdminState>, request: Request, next: Next) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match presented {
+        Some(token) if constant_time_eq(token.as_bytes(), admin.token.expose_secret().as_bytes()) => {
+            next.run(request).await
+        }
+        _ => {
+            tracing::warn!(path = %request.uri().path(), "admin request rejected: bad or missing token");
+            StatusCode::UNAUTHORIZED.into_response()
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Admin failures, returned to the caller as `{"error": "..."}` with the given status.
+pub(crate) struct AdminError(StatusCode, String);
+
+impl IntoResponse for AdminError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+pub(crate) fn error(status: StatusCode, message: impl Into<String>) -> AdminError {
+    AdminError(status, message.into())
+}
+
+pub(crate) fn find_session(admin: &AdminState, session_id: &str) -> Result<Arc<SessionHandle>, AdminError> {
+    admin
+        .app
+        .sessions
+        .get(session_id)
+        .map(|session| session.clone())
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, format!("session '{session_id}' not found")))
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    /// `current`, a checkpoint id, or a checkpoint name.
+    from: String,
+    #[serde(default = "current")]
+    to: String,
+    /// `json` (default) or `text`.
+    #[serde(default)]
+    format: Option<String>,
+}
+
+fn current() -> String {
+    "current".to_string()
+}
+
+/// Resolve one side of a diff request to the objects it names.
+fn snapshot(session: &SessionHandle, spec: &str) -> Result<HashMap<Uuid, SceneObject>, AdminError> {
+    if spec == "current" {
+        let objects = match session.objects.read() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!(session_id = %session.session_id, "objects lock was poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        return Ok(objects.clone());
+    }
+    let checkpoints = match session.checkpoints.read() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            tracing::warn!(session_id = %session.session_id, "checkpoints lock was poisoned, recovering");
+            poisoned.into_inner()
+        }
+    };
+    let by_id = Uuid::parse_str(spec).ok();
+    checkpoints
+        .iter()
+        .find(|c| Some(c.checkpoint_id) == by_id || c.name == spec)
+        .map(|c| c.objects.clone())
+        .ok_or_else(|| error(StatusCode::NOT_FOUND, format!("checkpoint '{spec}' not found")))
+}
+
+/// `GET /admin/sessions/{id}/diff?from=<checkpoint|current>&to=<checkpoint|current>&format=json|text`
+async fn session_diff(
+    State(admin): State<AdminState>,
+    Path(session_id): Path<String>,
+    Query(query): Query<DiffQuery>,
+) -> Result<Response, AdminError> {
+    let session = find_session(&admin, &session_id)?;
+    let before = snapshot(&session, &query.from)?;
+    let after = snapshot(&session, &query.to)?;
+    let diff = diff_objects(&before, &after);
+
+    tracing::info!(
+        event_type = "AdminSceneDiff",
+        session_id = %session_id,
+        from = %query.from,
+        to = %query.to,
+        added = diff.added.len(),
+        removed = diff.removed.len(),
+        changed = diff.changed.len(),
+        "scene diff requested"
+    );
+
+    match query.format.as_deref() {
+        None | Some("json") => Ok(Json(diff).into_response()),
+        Some("text") => Ok(diff.summary().into_response()),
+        Some(other) => Err(error(StatusCode::BAD_REQUEST, format!("unknown format '{other}', expected json or text"))),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct GltfQuery {
+    /// `glb` (default) or `gltf`.
+    #[serde(default)]
+    format: Option<String>,
+    /// `current` (default), a checkpoint id, or a checkpoint name.
+    #[serde(default = "current")]
+    at: String,
+}
+
+/// `GET /admin/sessions/{id}/gltf?format=glb|gltf&at=<checkpoint|current>`: the scene as a
+/// glTF 2.0 download.
+async fn session_gltf(
+    State(admin): State<AdminState>,
+    Path(session_id): Path<String>,
+    Query(query): Query<GltfQuery>,
+) -> Result<Response, AdminError> {
+    let format = match query.format.as_deref() {
+        None => GltfFormat::Glb,
+        Some(name) => GltfFormat::parse(name)
+            .ok_or_else(|| error(StatusCode::BAD_REQUEST, format!("unknown format '{name}', expected glb or gltf")))?,
+    };
+    let session = find_session(&admin, &session_id)?;
+    let objects = snapshot(&session, &query.at)?;
+    // Materials are not checkpointed, so every export uses the session's current ones.
+    let materials = match session.materials.read() {
+        Ok(guard) => guard.clone(),
+        Err(poisoned) => {
+            tracing::warn!(session_id = %session.session_id, "materials lock was poisoned, recovering");
+            poisoned.into_inner().clone()
+        }
+    };
+    let bytes = export_scene(&session_id, &objects, &materials).to_bytes(format).map_err(|err| {
+        tracing::error!(session_id = %session_id, error = %err, "failed to export glTF");
+        error(StatusCode::INTERNAL_SERVER_ERROR, "failed to export glTF")
+    })?;
+
+    tracing::info!(
+        event_type = "AdminGltfExport",
+        session_id = %session_id,
+        at = %query.at,
+        format = format.extension(),
+        objects = objects.len(),
+        "glTF export requested"
+    );
+    let disposition = format!("attachment; filename=\"{}.{}\"", encode_session_id(&session_id), format.extension());
+    Ok(([(header::CONTENT_TYPE, format.content_type().to_string()), (header::CONTENT_DISPOSITION, disposition)], bytes)
+        .into_response())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SceneImportSummary {
+    pub session_id: String,
+    /// The session was created by this import.
+    pub created: bool,
+    pub object_ids: Vec<Uuid>,
+}
+
+/// Add imported objects to a session, broadcasting them to everyone in it. When the session
+/// does not exist it is created, with the password from the `X-Meerkat-Session-Password` header.
+async fn import_scene(
+    admin: &AdminState,
+    session_id: &str,
+    headers: &HeaderMap,
+    objects: Vec<SceneObject>,
+    event_type: &'static str,
+    source: &str,
+) -> Result<(Arc<SessionHandle>, bool, Vec<SceneObject>), AdminError> {
+    let (session, created) = match find_session(admin, session_id) {
+        Ok(session) => (session, false),
+        Err(not_found) => {
+            let session_id = session_id.trim();
+            let Some(password) = headers
+                .get(SESSION_PASSWORD_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .filter(|password| !password.is_empty())
+            else {
+                return Err(not_found);
+            };
+            if session_id.is_empty() {
+                return Err(error(StatusCode::BAD_REQUEST, "session id must not be empty"));
+            }
+            let password_hash = bcrypt::hash(password, bcrypt::DEFAULT_COST).map_err(|err| {
+                tracing::error!(error = %err, "failed to hash password");
+                error(StatusCode::INTERNAL_SERVER_ERROR, "failed to hash password")
+            })?;
+            let session = insert_new_session(&admin.app, SessionHandle::new(session_id.to_string(), password_hash))
+                .await
+                .ok_or_else(|| error(StatusCode::CONFLICT, format!("session '{session_id}' already exists")))?;
+            (session, true)
+        }
+    };
+    let added = add_imported_objects(&admin.app, &session, objects, event_type, source).await;
+    Ok((session, created, added))
+}
+
+fn import_summary(session: &SessionHandle, created: bool, added: &[SceneObject]) -> Response {
+    let status = if created { StatusCode::CREATED } else { StatusCode::OK };
+    let summary = SceneImportSummary {
+        session_id: session.session_id.clone(),
+        created,
+        object_ids: added.iter().map(|object| object.object_id).collect(),
+    };
+    (status, Json(summary)).into_response()
+}
+
+/// `POST /admin/sessions/{id}/gltf` with a `.glb` or `.gltf` body: add its nodes to the session,
+/// creating it when given a password (see `import_scene`).
+async fn session_gltf_import(
+    State(admin): State<AdminState>,
+    Path(session_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, AdminError> {
+    let gltf = Gltf::from_bytes(&body).map_err(|err| error(StatusCode::BAD_REQUEST, format!("invalid glTF: {err}")))?;
+    let objects = import_objects(&gltf.document, now_ms());
+    let (session, created, added) = import_scene(&admin, &session_id, &headers, objects, "ImportGltf", "glTF import").await?;
+
+    tracing::info!(
+        event_type = "AdminGltfImport",
+        session_id = %session.session_id,
+        created,
+        object_count = added.len(),
+        "glTF imported"
+    );
+    Ok(import_summary(&session, created, &added))
+}
+
+#[derive(Deserialize)]
+pub struct UsdQuery {
+    /// `current` (default), a checkpoint id, or a checkpoint name.
+    #[serde(default = "current")]
+    at: String,
+}
+
+/// `GET /admin/sessions/{id}/usd?at=<checkpoint|current>`: the scene as a `.usda` layout layer.
+async fn session_usd(
+    State(admin): State<AdminState>,
+    Path(session_id): Path<String>,
+    Query(query): Query<UsdQuery>,
+) -> Result<Response, AdminError> {
+    let session = find_session(&admin, &session_id)?;
+    let objects = snapshot(&session, &query.at)?;
+    let text = usd::export_objects(&session_id, &objects).to_usda();
+
+    tracing::info!(
+        event_type = "AdminUsdExport",
+        session_id = %session_id,
+        at = %query.at,
+        objects = objects.len(),
+        "USD export requested"
+    );
+    let disposition = format!("attachment; filename=\"{}.{USDA_EXTENSION}\"", encode_session_id(&session_id));
+    Ok(([(header::CONTENT_TYPE, USDA_CONTENT_TYPE.to_string()), (header::CONTENT_DISPOSITION, disposition)], text)
+        .into_response())
+}
+
+/// `POST /admin/sessions/{id}/usd` with a `.usda` body: add its prims to the session, creating
+/// it when given a password (see `import_scene`).
+async fn session_usd_import(
+    State(admin): State<AdminState>,
+    Path(session_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, AdminError> {
+    let layer = std::str::from_utf8(&body)
+        .map_err(|err| err.to_string())
+        .and_then(|text| Layer::parse(text).map_err(|err| err.to_string()))
+        .map_err(|err| error(StatusCode::BAD_REQUEST, format!("invalid USD layer: {err}")))?;
+    let objects = usd::import_objects(&layer, now_ms());
+    let (session, created, added) = import_scene(&admin, &session_id, &headers, objects, "ImportUsd", "USD import").await?;
+
+    tracing::info!(
+        event_type = "AdminUsdImport",
+        session_id = %session.session_id,
+        created,
+        object_count = added.len(),
+        "USD imported"
+    );
+    Ok(import_summary(&session, created, &added))
+}
+
+/// `GET /admin/sessions/{id}/audit?object_id=&user_id=&since=&until=&limit=`. Sessions no longer
+/// live are answered from their persisted log when there is one.
+async fn session_audit(
+    State(admin): State<AdminState>,
+    Path(session_id): Path<String>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<AuditLogPayload>, AdminError> {
+    let (entries, truncated) = match find_session(&admin, &session_id) {
+        Ok(session) => session.lock_audit().query(&query),
+        Err(not_live) => {
+            let path = admin
+                .app
+                .config
+                .session_dir(&session_id)
+                .map(|dir| dir.join(AUDIT_FILE_NAME))
+                .filter(|path| path.exists())
+                .ok_or(not_live)?;
+            let persisted = audit::read_file(&path, admin.app.config.encryption.as_ref()).map_err(|err| {
+                tracing::error!(session_id = %session_id, path = %path.display(), error = %err, "failed to read audit log");
+                error(StatusCode::INTERNAL_SERVER_ERROR, "failed to read audit log")
+            })?;
+            query.run(persisted.iter())
+        }
+    };
+
+    tracing::info!(
+        event_type = "AdminAuditQuery",
+        session_id = %session_id,
+        entry_count = entries.len(),
+        truncated,
+        "audit log queried"
+    );
+    Ok(Json(AuditLogPayload { entries, truncated }))
+}
+
+/// `GET /admin/sessions/{id}/export`: the session as a `.mka` archive download.
+async fn session_export(State(admin): State<AdminState>, Path(session_id): Path<String>) -> Result<Response, AdminError> {
+    let session = find_session(&admin, &session_id)?;
+    let bytes = SessionArchive::of_session(&session, &admin.app.config)
+        .and_then(|archive| archive.to_bytes())
+        .map_err(|err| {
+            tracing::error!(session_id = %session_id, error = %err, "failed to export session");
+            error(StatusCode::INTERNAL_SERVER_ERROR, "failed to export session")
+        })?;
+
+    tracing::info!(event_type = "AdminSessionExport", session_id = %session_id, bytes = bytes.len(), "session exported");
+    let disposition = format!("attachment; filename=\"{}.{ARCHIVE_EXTENSION}\"", encode_session_id(&session_id));
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream".to_string()), (header::CONTENT_DISPOSITION, disposition)], bytes)
+        .into_response())
+}
+
+/// `POST /admin/sessions/{id}/import` with a `.mka` archive as the body and the new session's
+/// password in the `X-Meerkat-Session-Password` header. Recreates the archived session under `id`.
+async fn session_import(
+    State(admin): State<AdminState>,
+    Path(session_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, AdminError> {
+    let session_id = session_id.trim().to_string();
+    if session_id.is_empty() {
+        return Err(error(StatusCode::BAD_REQUEST, "session id must not be empty"));
+    }
+    let password = headers
+        .get(SESSION_PASSWORD_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|password| !password.is_empty())
+        .ok_or_else(|| error(StatusCode::BAD_REQUEST, format!("missing {SESSION_PASSWORD_HEADER} header")))?;
+    if admin.app.sessions.contains_key(&session_id) {
+        return Err(error(StatusCode::CONFLICT, format!("session '{session_id}' already exists")));
+    }
+    let archive = SessionArchive::from_bytes(&body).map_err(|err| error(StatusCode::BAD_REQUEST, format!("invalid archive: {err}")))?;
+    let password_hash = bcrypt::hash(password, bcrypt::DEFAULT_COST).map_err(|err| {
+        tracing::error!(error = %err, "failed to hash password");
+        error(StatusCode::INTERNAL_SERVER_ERROR, "failed to hash password")
+    })?;
+
+    let metadata = archive.metadata.clone();
+    let session = archive.into_session(session_id.clone(), password_hash);
+    if insert_new_session(&admin.app, session).await.is_none() {
+        return Err(error(StatusCode::CONFLICT, format!("session '{session_id}' already exists")));
+    }
+
+    tracing::info!(
+        event_type = "AdminSessionImport",
+        session_id = %session_id,
+        source_session_id = %metadata.source_session_id,
+        object_count = metadata.object_count,
+        "session imported"
+    );
+    Ok((StatusCode::CREATED, Json(metadata)).into_response())
+}
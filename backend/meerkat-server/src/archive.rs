@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::audit::{self, AuditEntry, AuditLog, AUDIT_FILE_NAME};
+use crate::config::ServerConfig;
+use crate::handlers::helpers::now_ms;
+use crate::mesh::StoredMesh;
+use crate::snapshot::{SessionSnapshot, SNAPSHOT_FORMAT_VERSION};
+use crate::types::{Checkpoint, Session, SessionHandle, TrashedObject, User};
+
+/// `.mka` layout: `MKAR`, a little-endian u16 format version, then one raw deflate stream of
+/// the `SessionArchive` JSON. Archives are meant to travel between servers, so they are never
+/// encrypted with a server's key.
+pub const ARCHIVE_MAGIC: &[u8; 4] = b"MKAR";
+pub const ARCHIVE_FORMAT_VERSION: u16 = 1;
+pub const ARCHIVE_EXTENSION: &str = "mka";
+
+/// Largest archive accepted, compressed (the admin upload limit) and inflated.
+pub const MAX_ARCHIVE_LEN: usize = 256 * 1024 * 1024;
+const MAX_INFLATED_LEN: u64 = 1024 * 1024 * 1024;
+
+/// Where an archive came from. Informational; import does not depend on it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ArchiveMetadata {
+    pub source_session_id: String,
+    pub exported_at: u64, // unix timestamp ms
+    pub server_version: String,
+    pub object_count: usize,
+    pub checkpoint_count: usize,
+    pub trash_count: usize,
+    pub audit_entry_count: usize,
+}
+
+/// Everything a session holds except its password and undo stacks. `session.users` records who
+/// was connected at export; an imported session starts empty.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionArchive {
+    pub metadata: ArchiveMetadata,
+    pub session: Session,
+    /// Mesh data the scene references, by content hash.
+    #[serde(default)]
+    pub meshes: HashMap<String, StoredMesh>,
+    pub checkpoints: Vec<Checkpoint>,
+    pub trash: Vec<TrashedObject>,
+    pub audit: Vec<AuditEntry>,
+}
+
+impl SessionArchive {
+    pub fn new(snapshot: SessionSnapshot, users: HashMap<Uuid, User>, audit: Vec<AuditEntry>) -> Self {
+        let metadata = ArchiveMetadata {
+            source_session_id: snapshot.session_id.clone(),
+            exported_at: now_ms(),
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            object_count: snapshot.objects.len(),
+            checkpoint_count: snapshot.checkpoints.len(),
+            trash_count: snapshot.trash.len(),
+            audit_entry_count: audit.len(),
+        };
+        SessionArchive {
+            metadata,
+            session: Session {
+                session_id: snapshot.session_id,
+                objects: snapshot.objects,
+                collections: snapshot.collections,
+                materials: snapshot.materials,
+                users,
+                version: snapshot.version,
+            },
+            meshes: snapshot.meshes,
+            checkpoints: snapshot.checkpoints,
+            trash: snapshot.trash,
+            audit,
+        }
+    }
+
+    /// Archive a live session. The audit history comes from its persisted log when there is
+    /// one (it keeps more than memory does).
+    pub fn of_session(session: &SessionHandle, config: &ServerConfig) -> io::Result<Self> {
+        let snapshot = SessionSnapshot::of(session, 0);
+        let users = match session.users.read() {
+            Ok(users) => users.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+        let audit_path = config
+            .session_dir(&session.session_id)
+            .map(|dir| dir.join(AUDIT_FILE_NAME))
+            .filter(|path| path.exists());
+        let audit = match audit_path {
+            Some(path) => audit::read_file(&path, config.encryption.as_ref())?,
+            None => session.lock_audit().entries().cloned().collect(),
+        };
+        Ok(SessionArchive::new(snapshot, users, audit))
+    }
+
+    /// A session holding this archive's scene, checkpoints, trash and audit history under
+    /// `session_id`, not yet attached to storage or published.
+    pub fn into_session(self, session_id: String, password_hash: String) -> SessionHandle {
+        let snapshot = SessionSnapshot {
+            session_id,
+            password_hash,
+            version: self.session.version,
+            objects: self.session.objects,
+            collections: self.session.collections,
+            materials: self.session.materials,
+            meshes: self.meshes,
+            checkpoints: self.checkpoints,
+            trash: self.trash,
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            taken_at: now_ms(),
+            journal_seq: 0,
+        };
+        let mut session = snapshot.into_session();
+        session.audit = std::sync::Mutex::new(AuditLog::with_entries(self.audit));
+        session
+    }
+
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(ARCHIVE_MAGIC);
+        bytes.extend_from_slice(&ARCHIVE_FORMAT_VERSION.to_le_bytes());
+        let mut encoder = DeflateEncoder::new(bytes, Compression::default());
+        serde_json::to_writer(&mut encoder, self)?;
+        encoder.finish()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        if bytes.len() < 6 || &bytes[..4] != ARCHIVE_MAGIC {
+            return Err(invalid("not a session archive (bad magic)".to_string()));
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != ARCHIVE_FORMAT_VERSION {
+            return Err(invalid(format!(
+                "unsupported archive format version {version} (expected {ARCHIVE_FORMAT_VERSION})"
+            )));
+        }
+        let mut json = Vec::new();
+        DeflateDecoder::new(&bytes[6..]).take(MAX_INFLATED_LEN + 1).read_to_end(&mut json)?;
+        if json.len() as u64 > MAX_INFLATED_LEN {
+            return Err(invalid(format!("archive inflates to more than {MAX_INFLATED_LEN} bytes")));
+        }
+        Ok(serde_json::from_slice(&json)?)
+    }
+}
+
+pub fn write_archive(path: &Path, archive: &SessionArchive) -> io::Result<()> {
+    let mut file = std::fs::File::create_new(path)?;
+    file.write_all(&archive.to_bytes()?)?;
+    file.sync_all()
+}
+
+pub fn read_archive(path: &Path) -> io::Result<SessionArchive> {
+    SessionArchive::from_bytes(&std::fs::read(path)?)
+}
+
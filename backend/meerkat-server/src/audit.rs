@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::encryption::{decode_line, encode_line, is_key_error, Keyring};
+use crate::history::{ObjectChange, TRANSFORM_UNDO_MERGE_MS};
+use crate::types::SceneObject;
+
+/// File name of a session's audit log inside its storage directory.
+pub const AUDIT_FILE_NAME: &str = "audit.jsonl";
+/// Associated data for sealed audit log lines.
+const AUDIT_AAD: &[u8] = b"meerkat audit";
+
+/// Entries kept in memory per session for queries; the file keeps everything.
+pub const AUDIT_LOG_CAPACITY: usize = 50_000;
+
+pub const DEFAULT_AUDIT_QUERY_LIMIT: usize = 200;
+pub const MAX_AUDIT_QUERY_LIMIT: usize = 1_000;
+
+/// One object touched by one mutation. An edit that touches several objects (a transaction,
+/// a checkpoint restore) produces one entry per object, all with the same `event_type` and
+/// `timestamp`. `before`/`after` are `None` where the object did not exist.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub timestamp: u64, // unix timestamp ms
+    pub user_id: Uuid,
+    pub display_name: String,
+    pub event_type: String,
+    pub object_id: Uuid,
+    pub before: Option<SceneObject>,
+    pub after: Option<SceneObject>,
+}
+
+impl AuditEntry {
+    /// Drag ticks by the same user on the same object collapse into one entry, like undo steps.
+    fn absorbs(&self, next: &AuditEntry) -> bool {
+        self.event_type == "UpdateTransform"
+            && next.event_type == "UpdateTransform"
+            && self.user_id == next.user_id
+            && self.object_id == next.object_id
+            && next.timestamp.saturating_sub(self.timestamp) < TRANSFORM_UNDO_MERGE_MS
+    }
+}
+
+/// Filter for QueryAuditLog and `GET /admin/sessions/{id}/audit`. Every field is optional;
+/// `since`/`until` are inclusive unix timestamps in ms.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AuditQuery {
+    #[serde(default)]
+    pub object_id: Option<Uuid>,
+    #[serde(default)]
+    pub user_id: Option<Uuid>,
+    #[serde(default)]
+    pub since: Option<u64>,
+    #[serde(default)]
+    pub until: Option<u64>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.object_id.is_none_or(|id| entry.object_id == id)
+            && self.user_id.is_none_or(|id| entry.user_id == id)
+            && self.since.is_none_or(|t| entry.timestamp >= t)
+            && self.until.is_none_or(|t| entry.timestamp <= t)
+    }
+
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_AUDIT_QUERY_LIMIT).clamp(1, MAX_AUDIT_QUERY_LIMIT)
+    }
+
+    /// Matching entries, newest first, plus whether more matched than the limit allowed.
+    pub fn run<'a>(&self, entries: impl DoubleEndedIterator<Item = &'a AuditEntry>) -> (Vec<AuditEntry>, bool) {
+        let limit = self.limit();
+        let mut matched = entries.rev().filter(|entry| self.matches(entry));
+        let found: Vec<AuditEntry> = matched.by_ref().take(limit).cloned().collect();
+        let truncated = matched.next().is_some();
+        (found, truncated)
+    }
+}
+
+/// Per-session audit trail. Entries are appended under the objects write lock, so `seq`
+/// follows the order mutations were applied.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    entries: VecDeque<AuditEntry>,
+    next_seq: u64,
+    file: Option<BufWriter<File>>,
+    keyring: Option<Keyring>,
+    /// Trailing entries not yet written to `file`: at most the drag still being merged.
+    unwritten: usize,
+}
+
+impl AuditLog {
+    /// A log holding history brought over from elsewhere (an imported archive), oldest first.
+    pub fn with_entries(entries: Vec<AuditEntry>) -> Self {
+        let mut log = AuditLog::default();
+        log.load(entries);
+        log
+    }
+
+    fn load(&mut self, mut entries: Vec<AuditEntry>) {
+        self.next_seq = entries.last().map_or(0, |entry| entry.seq + 1);
+        if entries.len() > AUDIT_LOG_CAPACITY {
+            entries.drain(..entries.len() - AUDIT_LOG_CAPACITY);
+        }
+        self.entries = entries.into();
+        self.unwritten = 0;
+    }
+
+    /// Persist to `path` from now on, loading the history already in it so sequence numbers
+    /// carry on. Entries already in memory (from `with_entries`) are renumbered after that
+    /// history and written out. New lines are sealed with `keyring` when given. Call before
+    /// anything is recorded.
+    pub fn attach(&mut self, path: &Path, keyring: Option<Keyring>) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let existing = if path.exists() { read_file(path, keyring.as_ref())? } else { Vec::new() };
+        let carried = std::mem::take(&mut self.entries);
+        self.load(existing);
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.file = Some(BufWriter::new(file));
+        self.keyring = keyring;
+        for mut entry in carried {
+            entry.seq = self.next_seq;
+            self.next_seq += 1;
+            self.entries.push_back(entry);
+            self.unwritten = 1;
+            self.write_pending();
+            if self.entries.len() > AUDIT_LOG_CAPACITY {
+                self.entries.pop_front();
+            }
+        }
+        Ok(())
+    }
+
+    pub fn record(&mut self, mut entry: AuditEntry) {
+        if self.unwritten > 0
+            && let Some(last) = self.entries.back_mut()
+            && last.absorbs(&entry)
+        {
+            last.timestamp = entry.timestamp;
+            last.after = entry.after;
+            return;
+        }
+        // Anything still open is final now.
+        self.write_pending();
+        entry.seq = self.next_seq;
+        self.next_seq += 1;
+        let open = entry.event_type == "UpdateTransform";
+        self.entries.push_back(entry);
+        self.unwritten += 1;
+        while self.entries.len() > AUDIT_LOG_CAPACITY {
+            self.entries.pop_front();
+        }
+        if !open {
+            self.write_pending();
+        }
+    }
+
+    /// Write entries recorded since the last write to the file, if there is one.
+    fn write_pending(&mut self) {
+        let count = std::mem::take(&mut self.unwritten).min(self.entries.len());
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+        let start = self.entries.len() - count;
+        let result = self.entries.range(start..).try_for_each(|entry| {
+            file.write_all(&encode_line(self.keyring.as_ref(), AUDIT_AAD, serde_json::to_vec(entry)?)?)?;
+            file.write_all(b"\n")
+        });
+        if let Err(err) = result.and_then(|_| file.flush()) {
+            tracing::error!(error = %err, "failed to append to audit log file");
+        }
+    }
+
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &AuditEntry> {
+        self.entries.iter()
+    }
+
+    pub fn query(&self, query: &AuditQuery) -> (Vec<AuditEntry>, bool) {
+        query.run(self.entries.iter())
+    }
+}
+
+impl Drop for AuditLog {
+    fn drop(&mut self) {
+        self.write_pending();
+    }
+}
+
+/// Replace the audit log at `path` with `entries`, sealed with `keyring` when given. How
+/// rewrap re-encrypts a log.
+pub fn write_file(path: &Path, entries: &[AuditEntry], keyring: Option<&Keyring>) -> io::Result<()> {
+    let tmp = path.with_extension("jsonl.tmp");
+    let mut file = BufWriter::new(File::create(&tmp)?);
+    for entry in entries {
+        file.write_all(&encode_line(keyring, AUDIT_AAD, serde_json::to_vec(entry)?)?)?;
+        file.write_all(b"\n")?;
+    }
+    file.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+/// One entry per change, attributed to `user_id`/`display_name`.
+pub fn entries_for(
+    user_id: Uuid,
+    display_name: &str,
+    event_type: &str,
+    timestamp: u64,
+    changes: &[ObjectChange],
+) -> Vec<AuditEntry> {
+    changes
+        .iter()
+        .map(|change| AuditEntry {
+            seq: 0,
+            timestamp,
+            user_id,
+            display_name: display_name.to_string(),
+            event_type: event_type.to_string(),
+            object_id: change.object_id,
+            before: change.before.clone(),
+            after: change.after.clone(),
+        })
+        .collect()
+}
+
+/// Read a persisted audit log, decrypting it with `keyring` where it is sealed. A torn final
+/// line (crash mid-append) is skipped with a warning; lines the keyring cannot read are an error.
+pub fn read_file(path: &Path, keyring: Option<&Keyring>) -> io::Result<Vec<AuditEntry>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let entry = decode_line(keyring, AUDIT_AAD, line)
+            .and_then(|json| serde_json::from_slice::<AuditEntry>(&json).map_err(io::Error::from));
+        match entry {
+            Ok(entry) => entries.push(entry),
+            Err(err) if is_key_error(&err) => return Err(err),
+            Err(err) => tracing::warn!(
+                path = %path.display(),
+                line = index + 1,
+                error = %err,
+                "skipping unreadable audit log line"
+            ),
+        }
+    }
+    Ok(entries)
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::handlers::helpers::broadcast;
+use crate::messages::{ServerEvent, StateChecksumPayload};
+use crate::types::{AppState, SceneObject, SessionHandle};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Transform components are quantized to 1/10000 of a unit before hashing so that the
+/// f64 -> f32 -> f64 round trip through Blender never registers as drift.
+pub const TRANSFORM_QUANTIZATION: f64 = 10_000.0;
+
+/// How often every active session gets a StateChecksum broadcast (skipped when nothing changed).
+pub const CHECKSUM_BROADCAST_INTERVAL: Duration = Duration::from_secs(10);
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = FNV_OFFSET_BASIS;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Hashes the synced identity of an object: id, name, type under its wire name, asset
+/// reference, quantized transform, for a parented object its parent id, for a collection member
+/// its collection ids in sorted order, for an object with mesh data `mesh:` and the mesh hash,
+/// then `slot:` and the material id (empty for an empty slot) per material slot, `prop:` and
+/// the key per custom property in key order, and `modifier:` and the id per modifier in stack
+/// order, joined with `|` and run through FNV-1a 64. Custom property values and modifier
+/// settings are left out: floats print differently in Rust and Python, so they would read as
+/// drift on every check. The Blender plugin rebuilds the same string from its scene to detect
+/// drift (event_handlers._object_hash), so change both together.
+pub fn object_hash(object: &SceneObject) -> u64 {
+    let mut canonical = format!(
+        "{}|{}|{}|{}|{}",
+        object.object_id,
+        object.name,
+        object.object_type.wire_name(),
+        object.asset_id.as_deref().unwrap_or(""),
+        object.asset_library.as_deref().unwrap_or(""),
+    );
+    let transform = &object.transform;
+    for value in transform.position.iter().chain(&transform.rotation).chain(&transform.scale) {
+        let _ = write!(canonical, "|{}", (value * TRANSFORM_QUANTIZATION).round() as i64);
+    }
+    if let Some(parent_id) = object.parent_id {
+        let _ = write!(canonical, "|{parent_id}");
+    }
+    let mut collections = object.collections.clone();
+    collections.sort();
+    for collection_id in collections {
+        let _ = write!(canonical, "|{collection_id}");
+    }
+    if let Some(mesh) = &object.mesh {
+        let _ = write!(canonical, "|mesh:{}", mesh.hash);
+    }
+    for slot in &object.material_slots {
+        let _ = write!(canonical, "|slot:{}", slot.map(|id| id.to_string()).unwrap_or_default());
+    }
+    for key in object.custom_properties.keys() {
+        let _ = write!(canonical, "|prop:{key}");
+    }
+    for modifier in &object.modifiers {
+        let _ = write!(canonical, "|modifier:{}", modifier.modifier_id);
+    }
+    fnv1a(canonical.as_bytes())
+}
+
+/// Order-independent checksum over a set of objects. Per-object hashes are summed,
+/// so iteration order of the underlying HashMap does not matter.
+pub fn objects_checksum<'a>(objects: impl IntoIterator<Item = &'a SceneObject>) -> u64 {
+    objects
+        .into_iter()
+        .fold(0u64, |acc, object| acc.wrapping_add(object_hash(object)))
+}
+
+/// Convenience wrapper for the session objects map.
+pub fn session_checksum(objects: &HashMap<Uuid, SceneObject>) -> u64 {
+    objects_checksum(objects.values())
+}
+
+/// Checksums go over the wire as fixed-width hex so JSON number precision never comes into play.
+pub fn format_checksum(checksum: u64) -> String {
+    format!("{:016x}", checksum)
+}
+
+/// Build a StateChecksum for the session, optionally with the per-object hashes a client
+/// needs to pinpoint which objects drifted.
+pub fn checksum_payload(session: &SessionHandle, include_objects: bool) -> StateChecksumPayload {
+    let objects = match session.objects.read() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            tracing::warn!("Session objects lock poisoned, recovering");
+            poisoned.into_inner()
+        }
+    };
+    let object_hashes = include_objects.then(|| {
+        objects
+            .values()
+            .map(|object| (object.object_id, format_checksum(object_hash(object))))
+            .collect()
+    });
+    StateChecksumPayload {
+        version: session.current_version(),
+        object_count: objects.len(),
+        checksum: format_checksum(session_checksum(&objects)),
+        object_hashes,
+    }
+}
+
+/// Broadcast a StateChecksum to every session whose version moved since the last round.
+/// `last_sent` maps session_id -> version last broadcast and is pruned of reclaimed sessions.
+pub fn broadcast_session_checksums(state: &AppState, last_sent: &mut HashMap<String, u64>) {
+    let sessions: Vec<_> = state
+        .sessions
+        .iter()
+        .map(|entry| (entry.key().clone(), entry.value().clone()))
+        .collect();
+    last_sent.retain(|sid, _| state.sessions.contains_key(sid));
+
+    for (sid, session) in sessions {
+        if !state.session_connections.contains_key(&sid) {
+            continue;
+        }
+        let version = session.current_version();
+        if last_sent.get(&sid) == Some(&version) {
+            continue;
+        }
+
+        let payload = checksum_payload(&session, false);
+        let json = match serde_json::to_string(&ServerEvent::StateChecksum(payload)) {
+            Ok(json) => json,
+            Err(err) => {
+                tracing::error!(
+                    event_type = "StateChecksum",
+                    session_id = %sid,
+                    error = %err,
+                    "failed to serialize StateChecksum"
+                );
+                continue;
+            }
+        };
+        let count = broadcast(state, &sid, &json, None);
+        last_sent.insert(sid.clone(), version);
+        tracing::debug!(
+            event_type = "StateChecksum",
+            session_id = %sid,
+            version,
+            recipient_count = count,
+            "broadcast periodic StateChecksum"
+        );
+    }
+}
+
+/// Background task spawned from main: periodically broadcasts session checksums.
+pub async fn run_checksum_broadcaster(state: AppState) {
+    let mut ticker = tokio::time::interval(CHECKSUM_BROADCAST_INTERVAL);
+    let mut last_sent = HashMap::new();
+    loop {
+        ticker.tick().await;
+        broadcast_session_checksums(&state, &mut last_sent);
+    }
+}
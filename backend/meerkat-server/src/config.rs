@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::encryption::Keyring;
+use crate::storage;
+
+/// Directory sessions are persisted under. Nothing is written to disk when unset.
+pub const DATA_DIR_ENV: &str = "MEERKAT_DATA_DIR";
+
+/// Seconds a deleted object stays in its session's trash before it is purged for good.
+pub const TRASH_RETENTION_ENV: &str = "MEERKAT_TRASH_RETENTION_SECS";
+pub const DEFAULT_TRASH_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Seconds between snapshots of each persisted session; each snapshot compacts its journal.
+pub const SNAPSHOT_INTERVAL_ENV: &str = "MEERKAT_SNAPSHOT_INTERVAL_SECS";
+pub const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Address the WebSocket and admin listener binds to.
+pub const BIND_ADDR_ENV: &str = "MEERKAT_BIND_ADDR";
+pub const DEFAULT_BIND_ADDR: &str = "0.0.0.0:8000";
+
+/// Set to `1`/`true` to write a `.mrec` recording of every session into its storage directory.
+pub const RECORD_SESSIONS_ENV: &str = "MEERKAT_RECORD_SESSIONS";
+
+/// Server-wide settings, read once at startup.
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    pub trash_retention: Duration,
+    /// Sessions are snapshotted and journaled here so they survive a restart.
+    pub data_dir: Option<PathBuf>,
+    pub snapshot_interval: Duration,
+    pub bind_addr: String,
+    /// Encrypt snapshots, journals and recordings with these keys. Loaded separately from the
+    /// other settings (`Keyring::from_env`), since a bad key must stop the server.
+    pub encryption: Option<Keyring>,
+    /// Record sessions to `<session dir>/recordings`. Needs `data_dir`.
+    pub record_sessions: bool,
+    /// Refuse every ClientEvent that would change a scene or create a session (replay mode).
+    pub read_only: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            trash_retention: DEFAULT_TRASH_RETENTION,
+            data_dir: None,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            bind_addr: DEFAULT_BIND_ADDR.to_string(),
+            encryption: None,
+            record_sessions: false,
+            read_only: false,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Defaults overridden by any `MEERKAT_*` variables that are set. Unparseable values are
+    /// logged and ignored rather than refusing to start.
+    pub fn from_env() -> Self {
+        let mut config = ServerConfig::default();
+        if let Some(secs) = env_u64(TRASH_RETENTION_ENV) {
+            config.trash_retention = Duration::from_secs(secs);
+        }
+        if let Some(dir) = std::env::var_os(DATA_DIR_ENV).filter(|dir| !dir.is_empty()) {
+            config.data_dir = Some(PathBuf::from(dir));
+        }
+        if let Some(secs) = env_u64(SNAPSHOT_INTERVAL_ENV).filter(|secs| *secs > 0) {
+            config.snapshot_interval = Duration::from_secs(secs);
+        }
+        if let Ok(addr) = std::env::var(BIND_ADDR_ENV)
+            && !addr.trim().is_empty()
+        {
+            config.bind_addr = addr.trim().to_string();
+        }
+        if let Some(record) = env_bool(RECORD_SESSIONS_ENV) {
+            config.record_sessions = record;
+            if record && config.data_dir.is_none() {
+                tracing::warn!("{RECORD_SESSIONS_ENV} is set but {DATA_DIR_ENV} is not; sessions will not be recorded");
+            }
+        }
+        config
+    }
+
+    /// The session's storage directory, when persistence is enabled.
+    pub fn session_dir(&self, session_id: &str) -> Option<PathBuf> {
+        self.data_dir.as_deref().map(|dir| storage::session_dir(dir, session_id))
+    }
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    let raw = std::env::var(name).ok()?;
+    match raw.trim().parse() {
+        Ok(value) => Some(value),
+        Err(err) => {
+            tracing::warn!(variable = name, value = %raw, error = %err, "ignoring invalid setting");
+            None
+        }
+    }
+}
+
+fn env_bool(name: &str) -> Option<bool> {
+    let raw = std::env::var(name).ok()?;
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" | "" => Some(false),
+        _ => {
+            tracing::warn!(variable = name, value = %raw, "ignoring invalid setting");
+            None
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::types::{
+    CustomValue, MAX_CUSTOM_LIST_LEN, MAX_CUSTOM_PROPERTIES, MAX_CUSTOM_PROPERTY_KEY_LEN, MAX_CUSTOM_STRING_LEN,
+    MAX_CUSTOM_VECTOR_LEN,
+};
+
+// Rules for the custom properties an object carries, shared by the live handlers and the
+// importers. Keys are identifiers so they are valid as Blender ID property names and as USD
+// attribute names alike.
+
+/// Keys starting with this (in any case) are the plugin's own bookkeeping, such as `meerkat_id`.
+pub const RESERVED_KEY_PREFIX: &str = "meerkat";
+
+/// Namespace custom properties are written under in USD, as Blender's exporter does.
+pub const USD_NAMESPACE: &str = "userProperties:";
+
+pub fn validate_key(key: &str) -> Result<(), String> {
+    if key.is_empty() || key.len() > MAX_CUSTOM_PROPERTY_KEY_LEN {
+        return Err(format!("keys must be 1 to {MAX_CUSTOM_PROPERTY_KEY_LEN} characters"));
+    }
+    if !key.starts_with(|c: char| c.is_ascii_alphabetic()) || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("key '{key}' must be a letter followed by letters, digits or underscores"));
+    }
+    if key.get(..RESERVED_KEY_PREFIX.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(RESERVED_KEY_PREFIX)) {
+        return Err(format!("key '{key}' uses the reserved '{RESERVED_KEY_PREFIX}' prefix"));
+    }
+    Ok(())
+}
+
+pub fn validate_value(value: &CustomValue) -> Result<(), String> {
+    let check_string = |string: &String| {
+        if string.len() > MAX_CUSTOM_STRING_LEN {
+            Err(format!("strings must be at most {MAX_CUSTOM_STRING_LEN} bytes"))
+        } else {
+            Ok(())
+        }
+    };
+    match value {
+        CustomValue::String(string) => check_string(string),
+        CustomValue::Number(number) if !number.is_finite() => Err("numbers must be finite".to_string()),
+        CustomValue::Number(_) | CustomValue::Bool(_) => Ok(()),
+        CustomValue::Vector(values) if values.is_empty() || values.len() > MAX_CUSTOM_VECTOR_LEN => {
+            Err(format!("vectors must have 1 to {MAX_CUSTOM_VECTOR_LEN} components"))
+        }
+        CustomValue::Vector(values) if !values.iter().all(|value| value.is_finite()) => {
+            Err("vector components must be finite".to_string())
+        }
+        CustomValue::Vector(_) => Ok(()),
+        CustomValue::StringList(strings) if strings.len() > MAX_CUSTOM_LIST_LEN => {
+            Err(format!("string lists must have at most {MAX_CUSTOM_LIST_LEN} entries"))
+        }
+        CustomValue::StringList(strings) => strings.iter().try_for_each(check_string),
+    }
+}
+
+pub fn validate(key: &str, value: &CustomValue) -> Result<(), String> {
+    validate_key(key)?;
+    validate_value(value)
+}
+
+/// The value as plain JSON, the way glTF extras hold custom properties.
+pub fn to_json(value: &CustomValue) -> Value {
+    match value {
+        CustomValue::String(string) => Value::String(string.clone()),
+        CustomValue::Number(number) => serde_json::Number::from_f64(*number).map_or(Value::Null, Value::Number),
+        CustomValue::Bool(flag) => Value::Bool(*flag),
+        CustomValue::Vector(values) => Value::Array(values.iter().map(|value| to_json(&CustomValue::Number(*value))).collect()),
+        CustomValue::StringList(strings) => Value::Array(strings.iter().cloned().map(Value::String).collect()),
+    }
+}
+
+/// A plain JSON value as a custom property, when it is one of the supported shapes. An empty
+/// array is taken for an empty string list.
+pub fn from_json(value: &Value) -> Option<CustomValue> {
+    match value {
+        Value::String(string) => Some(CustomValue::String(string.clone())),
+        Value::Number(number) => number.as_f64().map(CustomValue::Number),
+        Value::Bool(flag) => Some(CustomValue::Bool(*flag)),
+        Value::Array(values) if values.iter().all(Value::is_string) => {
+            Some(CustomValue::StringList(values.iter().filter_map(Value::as_str).map(str::to_string).collect()))
+        }
+        Value::Array(values) => values.iter().map(Value::as_f64).collect::<Option<_>>().map(CustomValue::Vector),
+        _ => None,
+    }
+}
+
+/// Keep the entries that are valid custom properties, up to MAX_CUSTOM_PROPERTIES in key
+/// order; importers use this on whatever metadata the source file carried.
+pub fn collect_valid(entries: impl IntoIterator<Item = (String, CustomValue)>) -> BTreeMap<String, CustomValue> {
+    let mut valid: BTreeMap<String, CustomValue> =
+        entries.into_iter().filter(|(key, value)| validate(key, value).is_ok()).collect();
+    while valid.len() > MAX_CUSTOM_PROPERTIES {
+        valid.pop_last();
+    }
+    valid
+}
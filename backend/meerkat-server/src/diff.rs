@@ -0,0 +1,183 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::types::{SceneObject, Session};
+
+/// Numbers closer than this compare equal, so Blender's f32 round trip is not reported as a change.
+pub const DIFF_FLOAT_TOLERANCE: f64 = 1e-5;
+
+/// Bookkeeping fields that change on every edit; they are reported via `last_updated_by`
+/// on the changed object rather than as field differences.
+const IGNORED_FIELDS: [&str; 4] = ["object_id", "created_by", "last_updated_by", "last_updated_at"];
+
+/// One differing leaf value. Paths look like `transform.position.x`, `name` or
+/// `properties.Camera.focal_length`; a side that lacks the field is `null`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FieldChange {
+    pub path: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChangedObject {
+    pub object_id: Uuid,
+    pub name: String,
+    /// Who last touched the object on the `after` side.
+    pub last_updated_by: Uuid,
+    pub fields: Vec<FieldChange>,
+}
+
+/// Differences between two scene states. Lists are sorted by name, then id, for stable output.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SceneDiff {
+    pub added: Vec<SceneObject>,
+    pub removed: Vec<SceneObject>,
+    pub changed: Vec<ChangedObject>,
+}
+
+impl SceneDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Plain-text report for supervisors reviewing a layout pass.
+    pub fn summary(&self) -> String {
+        let mut out = format!(
+            "{} added, {} removed, {} changed\n",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        );
+        for object in &self.added {
+            let _ = writeln!(out, "  + {} ({:?}, {})", object.name, object.object_type, object.object_id);
+        }
+        for object in &self.removed {
+            let _ = writeln!(out, "  - {} ({:?}, {})", object.name, object.object_type, object.object_id);
+        }
+        for object in &self.changed {
+            let _ = writeln!(out, "  ~ {} ({}, by {})", object.name, object.object_id, object.last_updated_by);
+            for field in &object.fields {
+                let _ = writeln!(out, "      {}: {} -> {}", field.path, field.before, field.after);
+            }
+        }
+        out
+    }
+}
+
+pub fn diff_sessions(before: &Session, after: &Session) -> SceneDiff {
+    diff_objects(&before.objects, &after.objects)
+}
+
+pub fn diff_objects(before: &HashMap<Uuid, SceneObject>, after: &HashMap<Uuid, SceneObject>) -> SceneDiff {
+    let mut diff = SceneDiff::default();
+    for (object_id, old) in before {
+        match after.get(object_id) {
+            None => diff.removed.push(old.clone()),
+            Some(new) => {
+                let fields = diff_object(old, new);
+                if !fields.is_empty() {
+                    diff.changed.push(ChangedObject {
+                        object_id: *object_id,
+                        name: new.name.clone(),
+                        last_updated_by: new.last_updated_by,
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    diff.added = after
+        .iter()
+        .filter(|(object_id, _)| !before.contains_key(*object_id))
+        .map(|(_, object)| object.clone())
+        .collect();
+
+    diff.added.sort_by(|a, b| (&a.name, a.object_id).cmp(&(&b.name, b.object_id)));
+    diff.removed.sort_by(|a, b| (&a.name, a.object_id).cmp(&(&b.name, b.object_id)));
+    diff.changed.sort_by(|a, b| (&a.name, a.object_id).cmp(&(&b.name, b.object_id)));
+    diff
+}
+
+/// Per-field differences between two versions of the same object.
+pub fn diff_object(before: &SceneObject, after: &SceneObject) -> Vec<FieldChange> {
+    let (Ok(Value::Object(mut old)), Ok(Value::Object(mut new))) =
+        (serde_json::to_value(before), serde_json::to_value(after))
+    else {
+        return Vec::new();
+    };
+    for field in IGNORED_FIELDS {
+        old.remove(field);
+        new.remove(field);
+    }
+    let mut changes = Vec::new();
+    diff_values("", &Value::Object(old), &Value::Object(new), &mut changes);
+    changes
+}
+
+fn child_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() { key.to_string() } else { format!("{parent}.{key}") }
+}
+
+fn diff_values(path: &str, before: &Value, after: &Value, out: &mut Vec<FieldChange>) {
+    match (before, after) {
+        (Value::Object(old), Value::Object(new)) => {
+            let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                diff_values(
+                    &child_path(path, key),
+                    old.get(key).unwrap_or(&Value::Null),
+                    new.get(key).unwrap_or(&Value::Null),
+                    out,
+                );
+            }
+        }
+        (Value::Array(old), Value::Array(new)) if old.len() == new.len() => {
+            // Transform vectors read better as axes than as indices.
+            let axes = path.starts_with("transform.") && old.len() == 3;
+            for (index, (a, b)) in old.iter().zip(new).enumerate() {
+                let child = if axes {
+                    child_path(path, ["x", "y", "z"][index])
+                } else {
+                    format!("{path}[{index}]")
+                };
+                diff_values(&child, a, b, out);
+            }
+        }
+        (Value::Number(a), Value::Number(b)) => {
+            let (a_f, b_f) = (a.as_f64().unwrap_or_default(), b.as_f64().unwrap_or_default());
+            if (a_f - b_f).abs() > DIFF_FLOAT_TOLERANCE {
+                out.push(FieldChange { path: path.to_string(), before: before.clone(), after: after.clone() });
+            }
+        }
+        _ if before != after => {
+            out.push(FieldChange { path: path.to_string(), before: before.clone(), after: after.clone() });
+        }
+        _ => {}
+    }
+}
+
+#[derive(Deserialize)]
+struct ObjectsOnly {
+    objects: HashMap<Uuid, SceneObject>,
+}
+
+#[derive(Deserialize)]
+struct WrappedSession {
+    session: ObjectsOnly,
+}
+
+/// Read the objects out of a saved snapshot: a `Session`, a `Checkpoint`, or a
+/// FullStateSync payload (`{"session": {...}}`).
+pub fn objects_from_json(json: &str) -> Result<HashMap<Uuid, SceneObject>, serde_json::Error> {
+    match serde_json::from_str::<ObjectsOnly>(json) {
+        Ok(snapshot) => Ok(snapshot.objects),
+        Err(err) => serde_json::from_str::<WrappedSession>(json)
+            .map(|wrapped| wrapped.session.objects)
+            .map_err(|_| err),
+    }
+}
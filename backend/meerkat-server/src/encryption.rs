@@ -0,0 +1,267 @@
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use sha2::{Digest, Sha256};
+
+/// Base64 encryption key(s) for persisted sessions. The first key encrypts; any further keys
+/// (comma- or newline-separated) are previous keys, only used to read what they encrypted.
+pub const ENCRYPTION_KEY_ENV: &str = "MEERKAT_ENCRYPTION_KEY";
+/// File holding the keys instead, one per line, current key first. `#` starts a comment line.
+pub const ENCRYPTION_KEY_FILE_ENV: &str = "MEERKAT_ENCRYPTION_KEY_FILE";
+
+pub const KEY_LEN: usize = 32;
+
+/// Sealed layout: `MENC`, a format byte, the 8-byte id of the key used, a 24-byte random
+/// nonce, then the XChaCha20-Poly1305 ciphertext and tag.
+pub const SEALED_MAGIC: &[u8; 4] = b"MENC";
+const SEALED_FORMAT_VERSION: u8 = 1;
+const KEY_ID_LEN: usize = 8;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = SEALED_MAGIC.len() + 1 + KEY_ID_LEN + NONCE_LEN;
+
+type KeyId = [u8; KEY_ID_LEN];
+
+#[derive(Debug)]
+pub enum EncryptionError {
+    /// Sealed with a key that is not configured: the wrong key, or one rotated out too early.
+    UnknownKey(String),
+    /// Sealed, but the server has no key at all.
+    NoKey,
+    /// The key matched but authentication failed: the data is corrupted or was tampered with.
+    Corrupt,
+    /// Plaintext where a key is configured: written before encryption was turned on, or
+    /// slipped in by someone without the key.
+    Unsealed,
+    Malformed(&'static str),
+}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionError::UnknownKey(id) => write!(
+                f,
+                "data is encrypted with key {id}, which is not configured; \
+                 configure that key (as a previous key if it was rotated out) to read it"
+            ),
+            EncryptionError::NoKey => write!(
+                f,
+                "data is encrypted but no key is configured; set {ENCRYPTION_KEY_ENV} or {ENCRYPTION_KEY_FILE_ENV}"
+            ),
+            EncryptionError::Corrupt => write!(f, "encrypted data failed authentication; it is corrupted or was tampered with"),
+            EncryptionError::Unsealed => write!(
+                f,
+                "data is not encrypted but an encryption key is configured; if it predates encryption, \
+                 run `meerkat-server rewrap` once with the server stopped to encrypt it"
+            ),
+            EncryptionError::Malformed(what) => write!(f, "malformed encrypted data: {what}"),
+        }
+    }
+}
+
+impl std::error::Error for EncryptionError {}
+
+impl From<EncryptionError> for io::Error {
+    fn from(err: EncryptionError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// True when `err` means the configured keys cannot read the data at all, as opposed to
+/// one damaged file.
+pub fn is_key_error(err: &io::Error) -> bool {
+    matches!(
+        err.get_ref().and_then(|inner| inner.downcast_ref::<EncryptionError>()),
+        Some(EncryptionError::UnknownKey(_) | EncryptionError::NoKey | EncryptionError::Unsealed)
+    )
+}
+
+struct Key {
+    id: KeyId,
+    cipher: XChaCha20Poly1305,
+}
+
+/// The keys persisted sessions are encrypted with. Cheap to clone; never prints key material.
+#[derive(Clone)]
+pub struct Keyring {
+    /// Current key first.
+    keys: Arc<Vec<Key>>,
+    /// Let plaintext through instead of refusing it; only for the `rewrap` migration.
+    accept_plaintext: bool,
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keyring").field("key_ids", &self.key_ids()).finish()
+    }
+}
+
+impl Keyring {
+    /// Parse base64 keys separated by commas or newlines, current key first.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut keys: Vec<Key> = Vec::new();
+        let tokens = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'))
+            .flat_map(|line| line.split(','))
+            .map(str::trim)
+            .filter(|token| !token.is_empty());
+        for (index, token) in tokens.enumerate() {
+            let bytes = BASE64
+                .decode(token)
+                .ok()
+                .filter(|bytes| bytes.len() == KEY_LEN)
+                .ok_or_else(|| format!("encryption key #{} is not {KEY_LEN} bytes of base64", index + 1))?;
+            let id = key_id(&bytes);
+            if keys.iter().any(|key| key.id == id) {
+                continue;
+            }
+            let cipher = XChaCha20Poly1305::new_from_slice(&bytes).map_err(|err| err.to_string())?;
+            keys.push(Key { id, cipher });
+        }
+        if keys.is_empty() {
+            return Err("no encryption key given".to_string());
+        }
+        Ok(Keyring { keys: Arc::new(keys), accept_plaintext: false })
+    }
+
+    /// Keys from `MEERKAT_ENCRYPTION_KEY` or `MEERKAT_ENCRYPTION_KEY_FILE`; `None` when neither
+    /// is set. Unlike other settings a bad value is an error: falling back to plaintext would
+    /// defeat the point.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let inline = std::env::var(ENCRYPTION_KEY_ENV).ok().filter(|value| !value.trim().is_empty());
+        let file = std::env::var_os(ENCRYPTION_KEY_FILE_ENV).filter(|value| !value.is_empty());
+        match (inline, file) {
+            (None, None) => Ok(None),
+            (Some(_), Some(_)) => Err(format!("set only one of {ENCRYPTION_KEY_ENV} and {ENCRYPTION_KEY_FILE_ENV}")),
+            (Some(keys), None) => Keyring::parse(&keys).map(Some).map_err(|err| format!("{ENCRYPTION_KEY_ENV}: {err}")),
+            (None, Some(path)) => {
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("{ENCRYPTION_KEY_FILE_ENV}: cannot read {}: {err}", path.to_string_lossy()))?;
+                Keyring::parse(&text).map(Some).map_err(|err| format!("{ENCRYPTION_KEY_FILE_ENV}: {err}"))
+            }
+        }
+    }
+
+    /// The same keys, reading plaintext as it is instead of refusing it. Only for a one-shot
+    /// migration that writes everything it reads back sealed (see rewrap).
+    pub fn accepting_plaintext(mut self) -> Self {
+        self.accept_plaintext = true;
+        self
+    }
+
+    /// Whether plaintext input is an error rather than data, as it is outside a migration.
+    pub fn refuses_plaintext(&self) -> bool {
+        !self.accept_plaintext
+    }
+
+    /// A fresh random key, base64 encoded as `parse` expects.
+    pub fn generate_key() -> String {
+        BASE64.encode(XChaCha20Poly1305::generate_key(&mut OsRng))
+    }
+
+    /// Fingerprints of the configured keys, current first. Safe to log.
+    pub fn key_ids(&self) -> Vec<String> {
+        self.keys.iter().map(|key| format_key_id(&key.id)).collect()
+    }
+
+    /// Encrypt with the current key. `aad` names what the data is, so a sealed snapshot cannot
+    /// be passed off as a journal record.
+    pub fn seal(&self, aad: &[u8], plaintext: &[u8]) -> io::Result<Vec<u8>> {
+        let key = &self.keys[0];
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = key
+            .cipher
+            .encrypt(&nonce, Payload { msg: plaintext, aad })
+            .map_err(|_| io::Error::other("encryption failed"))?;
+        let mut sealed = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        sealed.extend_from_slice(SEALED_MAGIC);
+        sealed.push(SEALED_FORMAT_VERSION);
+        sealed.extend_from_slice(&key.id);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt data sealed with any configured key.
+    pub fn open(&self, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        if sealed.len() < HEADER_LEN || !is_sealed(sealed) {
+            return Err(EncryptionError::Malformed("missing header"));
+        }
+        if sealed[SEALED_MAGIC.len()] != SEALED_FORMAT_VERSION {
+            return Err(EncryptionError::Malformed("unsupported format version"));
+        }
+        let id_start = SEALED_MAGIC.len() + 1;
+        let id = &sealed[id_start..id_start + KEY_ID_LEN];
+        let nonce = XNonce::from_slice(&sealed[id_start + KEY_ID_LEN..HEADER_LEN]);
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.id == id)
+            .ok_or_else(|| EncryptionError::UnknownKey(format_key_id(id)))?;
+        key.cipher
+            .decrypt(nonce, Payload { msg: &sealed[HEADER_LEN..], aad })
+            .map_err(|_| EncryptionError::Corrupt)
+    }
+}
+
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(SEALED_MAGIC)
+}
+
+/// Seal `data` when a keyring is configured, otherwise pass it through.
+pub fn seal_with(keyring: Option<&Keyring>, aad: &[u8], data: Vec<u8>) -> io::Result<Vec<u8>> {
+    match keyring {
+        Some(keyring) => keyring.seal(aad, &data),
+        None => Ok(data),
+    }
+}
+
+/// Decrypt sealed `data`. Plaintext passes through only without a keyring: once one is
+/// configured, unsealed input is refused, or anyone able to write the data dir could feed in
+/// unauthenticated records. A keyring made with `accepting_plaintext` lets it through.
+pub fn open_with(keyring: Option<&Keyring>, aad: &[u8], data: Vec<u8>) -> Result<Vec<u8>, EncryptionError> {
+    if !is_sealed(&data) {
+        return match keyring {
+            Some(keyring) if keyring.refuses_plaintext() => Err(EncryptionError::Unsealed),
+            _ => Ok(data),
+        };
+    }
+    keyring.ok_or(EncryptionError::NoKey)?.open(aad, &data)
+}
+
+/// One record of a line-based log (journal, audit log): the JSON itself, or base64 of the
+/// sealed JSON when a keyring is configured. Either way it holds no newline.
+pub fn encode_line(keyring: Option<&Keyring>, aad: &[u8], json: Vec<u8>) -> io::Result<Vec<u8>> {
+    match keyring {
+        Some(keyring) => Ok(BASE64.encode(keyring.seal(aad, &json)?).into_bytes()),
+        None => Ok(json),
+    }
+}
+
+/// Inverse of `encode_line`. Plain JSON lines are returned as they are, unless the keyring
+/// refuses plaintext (see `open_with`).
+pub fn decode_line(keyring: Option<&Keyring>, aad: &[u8], line: &str) -> io::Result<Vec<u8>> {
+    if line.starts_with('{') {
+        return open_with(keyring, aad, line.as_bytes().to_vec()).map_err(io::Error::from);
+    }
+    let sealed = BASE64
+        .decode(line)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(open_with(keyring, aad, sealed)?)
+}
+
+fn key_id(key: &[u8]) -> KeyId {
+    let digest = Sha256::new().chain_update(b"meerkat key id").chain_update(key).finalize();
+    let mut id = [0u8; KEY_ID_LEN];
+    id.copy_from_slice(&digest[..KEY_ID_LEN]);
+    id
+}
+
+fn format_key_id(id: &[u8]) -> String {
+    id.iter().map(|byte| format!("{byte:02x}")).collect()
+}
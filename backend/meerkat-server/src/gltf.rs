@@ -0,0 +1,1008 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::custom_properties;
+use crate::handlers::helpers::IMPORT_USER_ID;
+use crate::hierarchy;
+use crate::primitive_mesh::{primitive_mesh, MeshData};
+use crate::types::{
+    CameraProperties, CustomValue, LensType, ObjectProperties, ObjectType, PointLightProperties, SceneObject, SensorFit,
+    SpotLightProperties, SunLightProperties, TextureRef, Transform,
+};
+use crate::xform::{
+    decompose, euler_xyz_to_quat, mat_mul, quat_mul, trs_matrix, IDENTITY, Y_UP_TO_Z_UP, Z_UP_TO_Y_UP, Z_UP_TO_Y_UP_MATRIX,
+};
+
+pub const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+pub const KHR_LIGHTS_PUNCTUAL: &str = "KHR_lights_punctual";
+pub const KHR_MATERIALS_EMISSIVE_STRENGTH: &str = "KHR_materials_emissive_strength";
+
+/// Largest glTF file accepted for import (the admin upload limit).
+pub const MAX_GLTF_LEN: usize = 256 * 1024 * 1024;
+
+/// Luminous efficacy used to turn Blender's radiometric light units (W, W/m²) into glTF's
+/// photometric ones (cd, lx), as Blender's own glTF exporter does.
+pub const WATTS_TO_LUMENS: f64 = 683.0;
+/// glTF cameras need an aspect ratio and sessions do not carry the render resolution; this is
+/// Blender's default 1920x1080.
+pub const DEFAULT_ASPECT_RATIO: f64 = 16.0 / 9.0;
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const MODE_LINE_LOOP: u32 = 2;
+
+/// Which file a glTF export is written as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GltfFormat {
+    /// `.gltf`: JSON with the binary buffer embedded as a data URI.
+    Gltf,
+    /// `.glb`: the binary container.
+    Glb,
+}
+
+impl GltfFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "gltf" => Some(GltfFormat::Gltf),
+            "glb" => Some(GltfFormat::Glb),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            GltfFormat::Gltf => "gltf",
+            GltfFormat::Glb => "glb",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            GltfFormat::Gltf => "model/gltf+json",
+            GltfFormat::Glb => "model/gltf-binary",
+        }
+    }
+}
+
+// The subset of the glTF 2.0 schema Meerkat reads and writes. Unknown fields are ignored.
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Document {
+    pub asset: Asset,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scene: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scenes: Vec<Scene>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<Node>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub meshes: Vec<Mesh>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cameras: Vec<Camera>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub materials: Vec<Material>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub textures: Vec<Texture>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<Image>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accessors: Vec<Accessor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buffer_views: Vec<BufferView>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buffers: Vec<Buffer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions_used: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<DocumentExtensions>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Asset {
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Scene {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub nodes: Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Node {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation: Option<[f64; 3]>,
+    /// `[x, y, z, w]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<[f64; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<[f64; 3]>,
+    /// Column-major; used instead of translation/rotation/scale when present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<[f64; 16]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<NodeExtensions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extras: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Mesh {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub primitives: Vec<Primitive>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Primitive {
+    pub attributes: BTreeMap<String, usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indices: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Material {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pbr_metallic_roughness: Option<PbrMetallicRoughness>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normal_texture: Option<TextureInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emissive_texture: Option<TextureInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emissive_factor: Option<[f32; 3]>,
+    /// `OPAQUE` (the default), `MASK` or `BLEND`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<MaterialExtensions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extras: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PbrMetallicRoughness {
+    /// Linear RGBA; alpha is the material's opacity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_color_factor: Option<[f32; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_color_texture: Option<TextureInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metallic_factor: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roughness_factor: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metallic_roughness_texture: Option<TextureInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TextureInfo {
+    pub index: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MaterialExtensions {
+    #[serde(rename = "KHR_materials_emissive_strength", default, skip_serializing_if = "Option::is_none")]
+    pub emissive_strength: Option<EmissiveStrength>,
+}
+
+/// `KHR_materials_emissive_strength`: scales `emissiveFactor` past 1.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EmissiveStrength {
+    pub emissive_strength: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Texture {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Image {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Camera {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perspective: Option<Perspective>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orthographic: Option<Orthographic>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Perspective {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<f64>,
+    pub yfov: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zfar: Option<f64>,
+    pub znear: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Orthographic {
+    pub xmag: f64,
+    pub ymag: f64,
+    pub zfar: f64,
+    pub znear: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Accessor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buffer_view: Option<usize>,
+    #[serde(default)]
+    pub byte_offset: usize,
+    pub component_type: u32,
+    pub count: usize,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<Vec<f32>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BufferView {
+    pub buffer: usize,
+    #[serde(default)]
+    pub byte_offset: usize,
+    pub byte_length: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Buffer {
+    pub byte_length: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DocumentExtensions {
+    #[serde(rename = "KHR_lights_punctual", default, skip_serializing_if = "Option::is_none")]
+    pub lights_punctual: Option<LightsPunctual>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LightsPunctual {
+    pub lights: Vec<Light>,
+}
+
+/// A `KHR_lights_punctual` light. Lights shine down their node's -Z axis.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Light {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// `point`, `spot` or `directional`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[f32; 3]>,
+    /// Candela for point and spot lights, lux for directional ones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intensity: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spot: Option<Spot>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Spot {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inner_cone_angle: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outer_cone_angle: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct NodeExtensions {
+    #[serde(rename = "KHR_lights_punctual", default, skip_serializing_if = "Option::is_none")]
+    pub lights_punctual: Option<NodeLight>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NodeLight {
+    pub light: usize,
+}
+
+/// A glTF document with its binary buffer (buffer 0).
+#[derive(Clone, Debug, Default)]
+pub struct Gltf {
+    pub document: Document,
+    pub binary: Vec<u8>,
+}
+
+impl Gltf {
+    /// Parse a `.glb` container or `.gltf` JSON. Only the document and the `.glb` binary chunk
+    /// are read; external buffers are not fetched.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let gltf = if bytes.starts_with(GLB_MAGIC) {
+            let word = |at: usize| -> io::Result<usize> {
+                bytes
+                    .get(at..at + 4)
+                    .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]) as usize)
+                    .ok_or_else(|| invalid("truncated .glb".to_string()))
+            };
+            if word(4)? != GLB_VERSION as usize {
+                return Err(invalid(format!("unsupported .glb container version {}", word(4)?)));
+            }
+            let mut gltf = Gltf::default();
+            let mut document = None;
+            let mut at = 12;
+            while at + 8 <= bytes.len().min(word(8)?) {
+                let (length, kind) = (word(at)?, word(at + 4)?);
+                let chunk = bytes.get(at + 8..at + 8 + length).ok_or_else(|| invalid("truncated .glb chunk".to_string()))?;
+                match kind as u32 {
+                    GLB_CHUNK_JSON if document.is_none() => document = Some(serde_json::from_slice(chunk)?),
+                    GLB_CHUNK_BIN if gltf.binary.is_empty() => gltf.binary = chunk.to_vec(),
+                    _ => {}
+                }
+                at += 8 + length.next_multiple_of(4);
+            }
+            gltf.document = document.ok_or_else(|| invalid(".glb has no JSON chunk".to_string()))?;
+            gltf
+        } else {
+            Gltf { document: serde_json::from_slice(bytes)?, binary: Vec::new() }
+        };
+        if !gltf.document.asset.version.starts_with("2.") {
+            return Err(invalid(format!("unsupported glTF version {:?}", gltf.document.asset.version)));
+        }
+        Ok(gltf)
+    }
+
+    /// Serialize as `.gltf` JSON or a `.glb` container.
+    pub fn to_bytes(&self, format: GltfFormat) -> io::Result<Vec<u8>> {
+        match format {
+            GltfFormat::Gltf => self.to_gltf_json(),
+            GltfFormat::Glb => self.to_glb(),
+        }
+    }
+
+    fn to_gltf_json(&self) -> io::Result<Vec<u8>> {
+        let mut document = self.document.clone();
+        if let Some(buffer) = document.buffers.first_mut() {
+            buffer.uri = Some(format!("data:application/octet-stream;base64,{}", BASE64.encode(&self.binary)));
+        }
+        Ok(serde_json::to_vec_pretty(&document)?)
+    }
+
+    fn to_glb(&self) -> io::Result<Vec<u8>> {
+        let mut json = serde_json::to_vec(&self.document)?;
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut binary = self.binary.clone();
+        binary.resize(binary.len().next_multiple_of(4), 0);
+        let has_binary = !self.document.buffers.is_empty();
+        let length = 12 + 8 + json.len() + if has_binary { 8 + binary.len() } else { 0 };
+        let length = u32::try_from(length).map_err(|_| io::Error::other("glTF export is larger than 4 GiB"))?;
+
+        let mut glb = Vec::with_capacity(length as usize);
+        glb.extend_from_slice(GLB_MAGIC);
+        glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+        glb.extend_from_slice(&length.to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&GLB_CHUNK_JSON.to_le_bytes());
+        glb.extend_from_slice(&json);
+        if has_binary {
+            glb.extend_from_slice(&(binary.len() as u32).to_le_bytes());
+            glb.extend_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+            glb.extend_from_slice(&binary);
+        }
+        Ok(glb)
+    }
+
+    /// Append a tightly packed buffer view and return its index.
+    fn push_view(&mut self, bytes: &[u8], target: u32) -> usize {
+        let byte_offset = self.binary.len();
+        self.binary.extend_from_slice(bytes);
+        self.binary.resize(self.binary.len().next_multiple_of(4), 0);
+        self.document.buffer_views.push(BufferView { buffer: 0, byte_offset, byte_length: bytes.len(), target: Some(target) });
+        self.document.buffer_views.len() - 1
+    }
+
+    fn push_accessor(&mut self, accessor: Accessor) -> usize {
+        self.document.accessors.push(accessor);
+        self.document.accessors.len() - 1
+    }
+
+    fn push_vec3s(&mut self, values: &[[f32; 3]], bounds: Option<([f32; 3], [f32; 3])>) -> usize {
+        let bytes: Vec<u8> = values.iter().flatten().flat_map(|v| v.to_le_bytes()).collect();
+        let buffer_view = self.push_view(&bytes, ARRAY_BUFFER);
+        self.push_accessor(Accessor {
+            buffer_view: Some(buffer_view),
+            byte_offset: 0,
+            component_type: FLOAT,
+            count: values.len(),
+            kind: "VEC3".to_string(),
+            min: bounds.map(|(min, _)| min.to_vec()),
+            max: bounds.map(|(_, max)| max.to_vec()),
+        })
+    }
+
+    fn push_mesh(&mut self, name: &str, mesh: &MeshData, material: Option<usize>) -> usize {
+        let mut attributes = BTreeMap::new();
+        attributes.insert("POSITION".to_string(), self.push_vec3s(&mesh.positions, mesh.bounds()));
+        if !mesh.normals.is_empty() {
+            attributes.insert("NORMAL".to_string(), self.push_vec3s(&mesh.normals, None));
+        }
+        let bytes: Vec<u8> = mesh.indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let buffer_view = self.push_view(&bytes, ELEMENT_ARRAY_BUFFER);
+        let indices = self.push_accessor(Accessor {
+            buffer_view: Some(buffer_view),
+            byte_offset: 0,
+            component_type: UNSIGNED_INT,
+            count: mesh.indices.len(),
+            kind: "SCALAR".to_string(),
+            min: None,
+            max: None,
+        });
+        let mode = mesh.line_loop.then_some(MODE_LINE_LOOP);
+        self.document.meshes.push(Mesh {
+            name: Some(name.to_string()),
+            primitives: vec![Primitive { attributes, indices: Some(indices), mode, material }],
+        });
+        self.document.meshes.len() - 1
+    }
+
+    /// A texture for an asset reference. The image URI is `<asset_library>/<asset_id>`, left for
+    /// the importing side to resolve against its own asset libraries.
+    fn push_texture(&mut self, texture: &TextureRef) -> TextureInfo {
+        let uri = format!("{}/{}", texture.asset_library, texture.asset_id);
+        let source = match self.document.images.iter().position(|image| image.uri.as_deref() == Some(uri.as_str())) {
+            Some(source) => source,
+            None => {
+                self.document.images.push(Image { name: Some(texture.asset_id.clone()), uri: Some(uri) });
+                self.document.images.len() - 1
+            }
+        };
+        self.document.textures.push(Texture { source: Some(source) });
+        TextureInfo { index: self.document.textures.len() - 1 }
+    }
+
+    fn push_material(&mut self, material: &crate::types::Material) -> usize {
+        let settings = &material.settings;
+        let textures = &settings.textures;
+        let [r, g, b] = settings.base_color;
+        let emissive = settings.emission_color.map(|c| c * settings.emission_strength.min(1.0));
+        let exported = Material {
+            name: Some(material.name.clone()),
+            pbr_metallic_roughness: Some(PbrMetallicRoughness {
+                base_color_factor: Some([r, g, b, settings.alpha]),
+                base_color_texture: textures.base_color.as_ref().map(|t| self.push_texture(t)),
+                metallic_factor: Some(settings.metallic),
+                roughness_factor: Some(settings.roughness),
+                metallic_roughness_texture: textures.metallic_roughness.as_ref().map(|t| self.push_texture(t)),
+            }),
+            normal_texture: textures.normal.as_ref().map(|t| self.push_texture(t)),
+            emissive_texture: textures.emission.as_ref().map(|t| self.push_texture(t)),
+            emissive_factor: (emissive != [0.0; 3]).then_some(emissive),
+            alpha_mode: (settings.alpha < 1.0).then(|| "BLEND".to_string()),
+            extensions: (settings.emission_strength > 1.0).then_some(MaterialExtensions {
+                emissive_strength: Some(EmissiveStrength { emissive_strength: settings.emission_strength }),
+            }),
+            extras: Some(serde_json::json!({ "material_id": material.material_id.to_string() })),
+        };
+        self.document.materials.push(exported);
+        self.document.materials.len() - 1
+    }
+
+    fn push_light(&mut self, light: Light) -> usize {
+        let lights = &mut self.document.extensions.get_or_insert_with(Default::default).lights_punctual;
+        let lights = &mut lights.get_or_insert_with(Default::default).lights;
+        lights.push(light);
+        lights.len() - 1
+    }
+}
+
+/// Convert a session's objects into a glTF 2.0 scene named `scene_name`.
+///
+/// Every object becomes a node carrying its transform, nested under its parent's node; root
+/// nodes are converted from Blender's Z-up world to glTF's Y-up one. Primitives get generated
+/// meshes (shared between objects of the same type), cameras and point/spot/sun lights map
+/// onto glTF cameras and `KHR_lights_punctual`, and asset references and area lights (which
+/// glTF cannot express) become empty nodes. Each node's `extras` keep the object's id, type, asset reference,
+/// full properties and custom properties, so nothing Meerkat knows is lost.
+pub fn export_objects(scene_name: &str, objects: &HashMap<Uuid, SceneObject>) -> Gltf {
+    export_scene(scene_name, objects, &HashMap::new())
+}
+
+/// `export_objects` with the session's materials, exported as glTF PBR metallic-roughness
+/// materials. A primitive takes the material in its object's first slot; generated meshes have
+/// a single primitive, so later slots have nothing to land on.
+pub fn export_scene(
+    scene_name: &str,
+    objects: &HashMap<Uuid, SceneObject>,
+    materials: &HashMap<Uuid, crate::types::Material>,
+) -> Gltf {
+    let mut gltf = Gltf {
+        document: Document {
+            asset: Asset { version: "2.0".to_string(), generator: Some(format!("Meerkat {}", env!("CARGO_PKG_VERSION"))) },
+            ..Document::default()
+        },
+        binary: Vec::new(),
+    };
+    let mut sorted: Vec<&SceneObject> = objects.values().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name).then(a.object_id.cmp(&b.object_id)));
+
+    let node_index: HashMap<Uuid, usize> = sorted.iter().enumerate().map(|(index, object)| (object.object_id, index)).collect();
+    let parents: Vec<Option<usize>> = sorted
+        .iter()
+        .map(|object| hierarchy::acyclic_parent(objects, object).and_then(|parent_id| node_index.get(&parent_id).copied()))
+        .collect();
+    let mut sorted_materials: Vec<&crate::types::Material> = materials.values().collect();
+    sorted_materials.sort_by(|a, b| a.name.cmp(&b.name).then(a.material_id.cmp(&b.material_id)));
+    let material_index: HashMap<Uuid, usize> =
+        sorted_materials.into_iter().map(|material| (material.material_id, gltf.push_material(material))).collect();
+
+    let mut meshes: HashMap<(&str, Option<usize>), usize> = HashMap::new();
+    for (index, object) in sorted.iter().enumerate() {
+        let (translation, rotation, scale) = node_transform(object, parents[index].is_none());
+        let mut node = Node {
+            name: Some(object.name.clone()),
+            translation: Some(translation),
+            rotation: Some(rotation),
+            scale: Some(scale),
+            extras: Some(object_extras(object)),
+            ..Node::default()
+        };
+        let type_name = object.object_type.wire_name();
+        if let Some(mesh) = primitive_mesh(&object.object_type) {
+            let material = object.material_slots.first().copied().flatten().and_then(|id| material_index.get(&id).copied());
+            let index = match meshes.get(&(type_name, material)) {
+                Some(&index) => index,
+                None => {
+                    let index = gltf.push_mesh(type_name, &mesh, material);
+                    meshes.insert((type_name, material), index);
+                    index
+                }
+            };
+            node.mesh = Some(index);
+        }
+        match &object.properties {
+            Some(ObjectProperties::Camera(camera)) => {
+                gltf.document.cameras.push(export_camera(&object.name, camera));
+                node.camera = Some(gltf.document.cameras.len() - 1);
+            }
+            Some(properties) => {
+                if let Some(light) = export_light(&object.name, properties) {
+                    let light = gltf.push_light(light);
+                    node.extensions = Some(NodeExtensions { lights_punctual: Some(NodeLight { light }) });
+                }
+            }
+            None => {}
+        }
+        gltf.document.nodes.push(node);
+    }
+    for (index, parent) in parents.iter().enumerate() {
+        if let Some(parent) = parent {
+            gltf.document.nodes[*parent].children.push(index);
+        }
+    }
+
+    if !gltf.binary.is_empty() {
+        gltf.document.buffers.push(Buffer { byte_length: gltf.binary.len(), uri: None });
+    }
+    if gltf.document.extensions.is_some() {
+        gltf.document.extensions_used.push(KHR_LIGHTS_PUNCTUAL.to_string());
+    }
+    if gltf.document.materials.iter().any(|material| material.extensions.is_some()) {
+        gltf.document.extensions_used.push(KHR_MATERIALS_EMISSIVE_STRENGTH.to_string());
+    }
+    let roots = (0..gltf.document.nodes.len()).filter(|&index| parents[index].is_none()).collect();
+    gltf.document.scenes.push(Scene { name: Some(scene_name.to_string()), nodes: roots });
+    gltf.document.scene = Some(0);
+    gltf
+}
+
+fn object_extras(object: &SceneObject) -> Value {
+    let mut extras = serde_json::Map::new();
+    extras.insert("object_id".to_string(), Value::String(object.object_id.to_string()));
+    extras.insert("object_type".to_string(), Value::String(object.object_type.wire_name().to_string()));
+    if let Some(asset_id) = &object.asset_id {
+        extras.insert("asset_id".to_string(), Value::String(asset_id.clone()));
+    }
+    if let Some(asset_library) = &object.asset_library {
+        extras.insert("asset_library".to_string(), Value::String(asset_library.clone()));
+    }
+    if let Some(properties) = &object.properties
+        && let Ok(properties) = serde_json::to_value(properties)
+    {
+        extras.insert("properties".to_string(), properties);
+    }
+    if !object.custom_properties.is_empty() {
+        let custom = object.custom_properties.iter().map(|(key, value)| (key.clone(), custom_properties::to_json(value)));
+        extras.insert("custom_properties".to_string(), Value::Object(custom.collect()));
+    }
+    if !object.extra_properties.is_empty() {
+        extras.insert("extra_properties".to_string(), Value::Object(object.extra_properties.clone()));
+    }
+    Value::Object(extras)
+}
+
+/// glTF translation, rotation and scale for an object. The Z-up to Y-up conversion is applied
+/// to a root node's placement only, so its local axes stay Blender's: meshes keep their Z-up
+/// geometry, and cameras and lights, which look down local -Z in both, need no correction.
+/// Child nodes are in their parent's Blender space already and are written as they are.
+fn node_transform(object: &SceneObject, root: bool) -> ([f64; 3], [f64; 4], [f64; 3]) {
+    let Transform { position: [x, y, z], rotation, scale } = object.transform;
+    if !root {
+        return ([x, y, z], euler_xyz_to_quat(rotation), scale);
+    }
+    ([x, z, -y], quat_mul(Z_UP_TO_Y_UP, euler_xyz_to_quat(rotation)), scale)
+}
+
+/// The sensor dimension a camera's lens applies to, as its sensor fit picks it.
+pub fn fitted_sensor(camera: &CameraProperties) -> f64 {
+    if camera.sensor_fit == SensorFit::Vertical { camera.sensor_height } else { camera.sensor_width }
+}
+
+/// Width and height of the film at the default aspect ratio when `size`, a sensor dimension or
+/// the ortho scale, spans the dimension the camera's sensor fit picks. Blender's sensor fit
+/// decides which film dimension the lens and ortho scale apply to; Auto uses the wider one,
+/// which at 16:9 is the width.
+pub fn fitted_film(camera: &CameraProperties, size: f64) -> (f64, f64) {
+    if camera.sensor_fit == SensorFit::Vertical {
+        (size * DEFAULT_ASPECT_RATIO, size)
+    } else {
+        (size, size / DEFAULT_ASPECT_RATIO)
+    }
+}
+
+fn export_camera(name: &str, camera: &CameraProperties) -> Camera {
+    match camera.lens_type {
+        LensType::Perspective => {
+            let focal_length = camera.focal_length.max(f64::EPSILON);
+            let (_, film_height) = fitted_film(camera, fitted_sensor(camera));
+            let yfov = 2.0 * (film_height / 2.0 / focal_length).atan();
+            Camera {
+                name: Some(name.to_string()),
+                kind: "perspective".to_string(),
+                perspective: Some(Perspective {
+                    aspect_ratio: Some(DEFAULT_ASPECT_RATIO),
+                    yfov,
+                    zfar: Some(camera.clip_end),
+                    znear: camera.clip_start,
+                }),
+                orthographic: None,
+            }
+        }
+        LensType::Orthographic => {
+            let (xmag, ymag) = fitted_film(camera, camera.orthographic_scale / 2.0);
+            Camera {
+                name: Some(name.to_string()),
+                kind: "orthographic".to_string(),
+                perspective: None,
+                orthographic: Some(Orthographic { xmag, ymag, zfar: camera.clip_end, znear: camera.clip_start }),
+            }
+        }
+    }
+}
+
+/// Blender light strength scaled by its exposure (stops).
+fn exposed(strength: f32, exposure: f32) -> f64 {
+    strength as f64 * 2f64.powf(exposure as f64)
+}
+
+/// The `KHR_lights_punctual` light for a point, spot or sun light; `None` for anything else.
+/// Point and spot power in watts becomes candela spread over the full sphere; sun strength in
+/// W/m² becomes lux.
+fn export_light(name: &str, properties: &ObjectProperties) -> Option<Light> {
+    let candela = |watts: f64| (watts * WATTS_TO_LUMENS / (4.0 * std::f64::consts::PI)) as f32;
+    let light = match properties {
+        ObjectProperties::PointLight(point) => Light {
+            name: Some(name.to_string()),
+            kind: "point".to_string(),
+            color: Some(point.color),
+            intensity: Some(candela(exposed(point.power, point.exposure))),
+            range: point.use_custom_distance.then_some(point.cutoff_distance),
+            spot: None,
+        },
+        ObjectProperties::SpotLight(spot) => {
+            // Blender's spot size is the full cone angle; blend softens it from the edge inwards.
+            let outer = (spot.angle / 2.0).clamp(0.0, std::f32::consts::FRAC_PI_2);
+            Light {
+                name: Some(name.to_string()),
+                kind: "spot".to_string(),
+                color: Some(spot.color),
+                intensity: Some(candela(exposed(spot.power, spot.exposure))),
+                range: spot.use_custom_distance.then_some(spot.cutoff_distance),
+                spot: Some(Spot {
+                    inner_cone_angle: Some(outer * (1.0 - spot.blend.clamp(0.0, 1.0))),
+                    outer_cone_angle: Some(outer),
+                }),
+            }
+        }
+        ObjectProperties::SunLight(sun) => Light {
+            name: Some(name.to_string()),
+            kind: "directional".to_string(),
+            color: Some(sun.color),
+            intensity: Some((exposed(sun.strength, sun.exposure) * WATTS_TO_LUMENS) as f32),
+            range: None,
+            spot: None,
+        },
+        ObjectProperties::Camera(_) | ObjectProperties::AreaLight(_) => return None,
+    };
+    Some(light)
+}
+
+/// The objects a glTF scene describes, in the order its nodes are listed.
+///
+/// Node hierarchies carry over: each object is parented to the nearest ancestor node that
+/// became an object too, with its transform made relative to it after converting from glTF's
+/// Y-up to Blender's Z-up. Cameras and `KHR_lights_punctual` lights become cameras and lights, filling in
+/// Blender's defaults for what glTF does not carry, and other meshes become asset references
+/// named after the mesh. Empty nodes are skipped, except ones written by `export_objects`,
+/// which come back as the objects they were, ids included; callers must give any ids already
+/// taken in the target session fresh ones.
+pub fn import_objects(document: &Document, now: u64) -> Vec<SceneObject> {
+    let roots: Vec<usize> = match document.scene.or((!document.scenes.is_empty()).then_some(0)) {
+        Some(scene) => document.scenes.get(scene).map(|scene| scene.nodes.clone()).unwrap_or_default(),
+        None => {
+            let children: std::collections::HashSet<usize> =
+                document.nodes.iter().flat_map(|node| node.children.iter().copied()).collect();
+            (0..document.nodes.len()).filter(|index| !children.contains(index)).collect()
+        }
+    };
+
+    let mut world = vec![None; document.nodes.len()];
+    let mut parent_node = vec![None; document.nodes.len()];
+    let mut stack: Vec<(usize, [f64; 16], Option<usize>)> =
+        roots.into_iter().rev().map(|index| (index, IDENTITY, None)).collect();
+    while let Some((index, parent, parent_index)) = stack.pop() {
+        // Each node is placed once, which also stops cycles in a malformed file.
+        let Some(node) = document.nodes.get(index) else { continue };
+        if world[index].is_some() {
+            continue;
+        }
+        let matrix = mat_mul(&parent, &local_matrix(node));
+        world[index] = Some(matrix);
+        parent_node[index] = parent_index;
+        stack.extend(node.children.iter().rev().map(|&child| (child, matrix, Some(index))));
+    }
+
+    let mut objects: Vec<SceneObject> = Vec::new();
+    let mut object_nodes = Vec::new();
+    let mut node_object = vec![None; document.nodes.len()];
+    let mut seen = std::collections::HashSet::new();
+    for (index, node) in document.nodes.iter().enumerate() {
+        let Some(matrix) = world[index] else { continue };
+        let Some(mut object) = import_node(document, index, node, &matrix, now) else { continue };
+        if !seen.insert(object.object_id) {
+            object.object_id = Uuid::new_v4();
+        }
+        node_object[index] = Some(object.object_id);
+        object_nodes.push(index);
+        objects.push(object);
+    }
+    // Each object is parented to the nearest ancestor node that became one.
+    for (object, &index) in objects.iter_mut().zip(&object_nodes) {
+        let mut ancestor = parent_node[index];
+        while let Some(node) = ancestor {
+            if let Some(parent_id) = node_object[node] {
+                object.parent_id = Some(parent_id);
+                break;
+            }
+            ancestor = parent_node[node];
+        }
+    }
+    hierarchy::link_imported(&mut objects);
+    objects
+}
+
+fn import_node(document: &Document, index: usize, node: &Node, world: &[f64; 16], now: u64) -> Option<SceneObject> {
+    let mesh = node.mesh.and_then(|mesh| document.meshes.get(mesh));
+    let name = node
+        .name
+        .clone()
+        .or_else(|| mesh.and_then(|mesh| mesh.name.clone()))
+        .unwrap_or_else(|| format!("glTF node {index}"));
+    let mut object = SceneObject::new(Uuid::new_v4(), name, ObjectType::AssetRef, IMPORT_USER_ID, now);
+    let light = node
+        .extensions
+        .as_ref()
+        .and_then(|extensions| extensions.lights_punctual.as_ref())
+        .and_then(|light| document.extensions.as_ref()?.lights_punctual.as_ref()?.lights.get(light.light));
+
+    // Cameras and lights look down local -Z in both conventions, so only their placement is
+    // converted; mesh geometry is Y-up in its own space too, so the whole node is.
+    let mut local_axes_converted = false;
+    if let Some(imported) = meerkat_object(node) {
+        object.object_id = imported.object_id;
+        object.object_type = imported.object_type;
+        object.asset_id = imported.asset_id;
+        object.asset_library = imported.asset_library;
+        object.properties = imported.properties;
+        object.custom_properties = imported.custom_properties;
+        object.extra_properties = imported.extra_properties;
+    } else if let Some(camera) = node.camera.and_then(|camera| document.cameras.get(camera)) {
+        object.object_type = ObjectType::Camera;
+        object.properties = Some(ObjectProperties::Camera(import_camera(camera)));
+    } else if let Some((object_type, properties)) = light.and_then(import_light) {
+        object.object_type = object_type;
+        object.properties = Some(properties);
+    } else if let Some(mesh) = mesh {
+        object.asset_id = Some(mesh.name.clone().unwrap_or_else(|| object.name.clone()));
+        local_axes_converted = true;
+    } else {
+        return None;
+    }
+
+    // Blender's exporter writes custom properties straight into extras.
+    if let Some(extras) = &node.extras
+        && extras.get("object_type").is_none()
+    {
+        object.custom_properties = custom_properties_from_json(extras);
+    }
+
+    let mut matrix = mat_mul(&Y_UP_TO_Z_UP, world);
+    if local_axes_converted {
+        matrix = mat_mul(&matrix, &Z_UP_TO_Y_UP_MATRIX);
+    }
+    object.transform = decompose(&matrix);
+    Some(object)
+}
+
+/// What `export_objects` wrote into a node's extras, when it did.
+struct MeerkatExtras {
+    object_id: Uuid,
+    object_type: ObjectType,
+    asset_id: Option<String>,
+    asset_library: Option<String>,
+    properties: Option<ObjectProperties>,
+    custom_properties: BTreeMap<String, CustomValue>,
+    extra_properties: serde_json::Map<String, Value>,
+}
+
+/// The valid custom properties among a JSON object's entries.
+fn custom_properties_from_json(value: &Value) -> BTreeMap<String, CustomValue> {
+    let entries = value.as_object().into_iter().flatten();
+    custom_properties::collect_valid(
+        entries.filter_map(|(key, value)| Some((key.clone(), custom_properties::from_json(value)?))),
+    )
+}
+
+fn meerkat_object(node: &Node) -> Option<MeerkatExtras> {
+    let extras = node.extras.as_ref()?;
+    let object_type: ObjectType = serde_json::from_value(extras.get("object_type")?.clone()).ok()?;
+    let string = |key: &str| extras.get(key).and_then(Value::as_str).map(str::to_string);
+    Some(MeerkatExtras {
+        object_id: string("object_id").and_then(|id| Uuid::parse_str(&id).ok()).unwrap_or_else(Uuid::new_v4),
+        object_type,
+        asset_id: string("asset_id"),
+        asset_library: string("asset_library"),
+        properties: extras.get("properties").and_then(|properties| serde_json::from_value(properties.clone()).ok()),
+        custom_properties: extras.get("custom_properties").map(custom_properties_from_json).unwrap_or_default(),
+        extra_properties: extras.get("extra_properties").and_then(Value::as_object).cloned().unwrap_or_default(),
+    })
+}
+
+/// Inverse of `export_camera`, keeping Blender's 36 mm sensor.
+fn import_camera(camera: &Camera) -> CameraProperties {
+    let mut properties = CameraProperties::default();
+    if let Some(orthographic) = &camera.orthographic {
+        properties.lens_type = LensType::Orthographic;
+        properties.orthographic_scale = 2.0 * orthographic.xmag.abs().max(orthographic.ymag.abs());
+        properties.clip_start = orthographic.znear;
+        properties.clip_end = orthographic.zfar;
+    } else if let Some(perspective) = &camera.perspective {
+        let aspect = perspective.aspect_ratio.filter(|aspect| *aspect > 0.0).unwrap_or(DEFAULT_ASPECT_RATIO);
+        let half_width = (perspective.yfov / 2.0).tan() * aspect;
+        if half_width > 0.0 {
+            properties.focal_length = properties.sensor_width / 2.0 / half_width;
+        }
+        properties.clip_start = perspective.znear;
+        if let Some(zfar) = perspective.zfar {
+            properties.clip_end = zfar;
+        }
+    }
+    properties
+}
+
+/// Inverse of `export_light`.
+fn import_light(light: &Light) -> Option<(ObjectType, ObjectProperties)> {
+    let color = light.color.unwrap_or([1.0, 1.0, 1.0]);
+    let intensity = light.intensity.unwrap_or(1.0) as f64;
+    let watts = (intensity * 4.0 * std::f64::consts::PI / WATTS_TO_LUMENS) as f32;
+    let imported = match light.kind.as_str() {
+        "point" => (
+            ObjectType::PointLight,
+            ObjectProperties::PointLight(PointLightProperties {
+                color,
+                power: watts,
+                use_custom_distance: light.range.is_some(),
+                cutoff_distance: light.range.unwrap_or(PointLightProperties::default().cutoff_distance),
+                ..PointLightProperties::default()
+            }),
+        ),
+        "spot" => {
+            let cone = light.spot.clone().unwrap_or_default();
+            let outer = cone.outer_cone_angle.unwrap_or(std::f32::consts::FRAC_PI_4);
+            let inner = cone.inner_cone_angle.unwrap_or(0.0);
+            (
+                ObjectType::SpotLight,
+                ObjectProperties::SpotLight(SpotLightProperties {
+                    color,
+                    power: watts,
+                    angle: 2.0 * outer,
+                    blend: if outer > 0.0 { (1.0 - inner / outer).clamp(0.0, 1.0) } else { 0.0 },
+                    use_custom_distance: light.range.is_some(),
+                    cutoff_distance: light.range.unwrap_or(SpotLightProperties::default().cutoff_distance),
+                    ..SpotLightProperties::default()
+                }),
+            )
+        }
+        "directional" => (
+            ObjectType::SunLight,
+            ObjectProperties::SunLight(SunLightProperties {
+                color,
+                strength: (intensity / WATTS_TO_LUMENS) as f32,
+                ..SunLightProperties::default()
+            }),
+        ),
+        _ => return None,
+    };
+    Some(imported)
+}
+
+fn local_matrix(node: &Node) -> [f64; 16] {
+    if let Some(matrix) = node.matrix {
+        return matrix;
+    }
+    trs_matrix(
+        node.translation.unwrap_or([0.0; 3]),
+        node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]),
+        node.scale.unwrap_or([1.0; 3]),
+    )
+}
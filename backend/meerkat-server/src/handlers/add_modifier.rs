@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    history::ObjectChange,
+    messages::{AddModifierPayload, ObjectModifiersUpdatedPayload, ServerEvent},
+    modifiers::{self, StackError},
+    types::{AppState, Modifier},
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+/// Apply `edit` to a copy of an object's modifier stack and, when the result is valid and
+/// differs, store it, record it for undo and broadcast the whole stack. Shared by the
+/// modifier handlers, which only say how the stack changes.
+pub(super) async fn edit_modifiers(
+    state: &AppState,
+    connection_id: Uuid,
+    object_id: Uuid,
+    event_type: &'static str,
+    edit: impl FnOnce(&mut Vec<Modifier>) -> Result<(), StackError>,
+) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let now = now_ms();
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let outcome = {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let Some(object) = objects.get(&object_id) else {
+            tracing::debug!(
+                object_id = %object_id,
+                session_id = %sid,
+                "object not found for modifier update"
+            );
+            return;
+        };
+        let mut stack = object.modifiers.clone();
+        match edit(&mut stack).and_then(|()| modifiers::validate_stack(object, &stack, &objects)) {
+            Err(err) => Err(err),
+            Ok(()) if stack == object.modifiers => Ok(None),
+            Ok(()) => {
+                let Some(object) = objects.get_mut(&object_id) else { return };
+                let before = object.clone();
+                object.modifiers = stack.clone();
+                object.last_updated_by = uid;
+                object.last_updated_at = now;
+                let after = object.clone();
+                session.record_edit(uid, event_type, now, vec![ObjectChange {
+                    object_id,
+                    before: Some(before),
+                    after: Some(after),
+                }]);
+                Ok(Some(stack))
+            }
+        }
+    };
+
+    let modifiers = match outcome {
+        Ok(Some(modifiers)) => modifiers,
+        Ok(None) => return,
+        Err(err) => {
+            send_error(state, connection_id, err.code(), format!("{} rejected: {}", event_type, err.message()));
+            return;
+        }
+    };
+
+    tracing::info!(
+        event_type,
+        session_id = %sid,
+        user_id = %uid,
+        object_id = %object_id,
+        modifier_count = modifiers.len(),
+        "object modifiers updated"
+    );
+
+    let json = match serde_json::to_string(&ServerEvent::ObjectModifiersUpdated(ObjectModifiersUpdatedPayload {
+        object_id,
+        modifiers,
+        updated_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "ObjectModifiersUpdated",
+                session_id = %sid,
+                user_id = %uid,
+                object_id = %object_id,
+                error = %err,
+                "failed to serialize ObjectModifiersUpdated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "ObjectModifiersUpdated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast ObjectModifiersUpdated"
+    );
+}
+
+/// Insert a new modifier into an object's stack, at the end unless an index is given.
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: AddModifierPayload) {
+    let AddModifierPayload { object_id, modifier, index } = payload;
+    edit_modifiers(state, connection_id, object_id, "AddModifier", |stack| {
+        if stack.iter().any(|existing| existing.modifier_id == modifier.modifier_id) {
+            return Err(StackError::Duplicate(modifier.modifier_id));
+        }
+        let index = index.unwrap_or(stack.len());
+        if index > stack.len() {
+            return Err(StackError::Invalid(format!(
+                "index {} is past the end of a stack of {}",
+                index,
+                stack.len()
+            )));
+        }
+        stack.insert(index, modifier);
+        Ok(())
+    })
+    .await;
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    journal::JournalOp,
+    messages::{CheckpointInfo, CreateCheckpointPayload, ServerEvent},
+    types::{AppState, Checkpoint, MAX_CHECKPOINTS, MAX_CHECKPOINT_NAME_LEN},
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: CreateCheckpointPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let name = payload.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_CHECKPOINT_NAME_LEN {
+        send_error(
+            state,
+            connection_id,
+            "INVALID_CHECKPOINT_NAME",
+            format!("Checkpoint names must be 1 to {} characters", MAX_CHECKPOINT_NAME_LEN),
+        );
+        return;
+    }
+
+    let created = {
+        let objects = match session.objects.read() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let mut checkpoints = match session.checkpoints.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session checkpoints lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        if checkpoints.len() >= MAX_CHECKPOINTS {
+            None
+        } else {
+            let checkpoint = Checkpoint {
+                checkpoint_id: Uuid::new_v4(),
+                name,
+                created_by: uid,
+                created_at: now_ms(),
+                version: session.current_version(),
+                objects: objects.clone(),
+            };
+            let info = CheckpointInfo::from(&checkpoint);
+            session.append_journal(|| JournalOp::Checkpoint { checkpoint: checkpoint.clone() });
+            checkpoints.push(checkpoint);
+            Some(info)
+        }
+    };
+
+    let Some(info) = created else {
+        send_error(
+            state,
+            connection_id,
+            "CHECKPOINT_LIMIT",
+            format!("Session {} already holds {} checkpoints", sid, MAX_CHECKPOINTS),
+        );
+        return;
+    };
+
+    tracing::info!(
+        event_type = "CreateCheckpoint",
+        session_id = %sid,
+        user_id = %uid,
+        checkpoint_id = %info.checkpoint_id,
+        object_count = info.object_count,
+        version = info.version,
+        "checkpoint created"
+    );
+
+    let checkpoint_id = info.checkpoint_id;
+    let json = match serde_json::to_string(&ServerEvent::CheckpointCreated(info)) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "CheckpointCreated",
+                session_id = %sid,
+                checkpoint_id = %checkpoint_id,
+                error = %err,
+                "failed to serialize CheckpointCreated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "CheckpointCreated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast CheckpointCreated"
+    );
+}
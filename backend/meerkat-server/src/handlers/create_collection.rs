@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    journal::JournalOp,
+    messages::{CollectionCreatedPayload, CreateCollectionPayload, ServerEvent},
+    types::{AppState, Collection, MAX_COLLECTIONS, MAX_COLLECTION_NAME_LEN},
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+/// A collection name trimmed, or None when it is empty or longer than MAX_COLLECTION_NAME_LEN.
+pub(super) fn collection_name(name: &str) -> Option<String> {
+    let name = name.trim();
+    (!name.is_empty() && name.chars().count() <= MAX_COLLECTION_NAME_LEN).then(|| name.to_string())
+}
+
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: CreateCollectionPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let Some(name) = collection_name(&payload.name) else {
+        send_error(
+            state,
+            connection_id,
+            "INVALID_COLLECTION_NAME",
+            format!("Collection names must be 1 to {} characters", MAX_COLLECTION_NAME_LEN),
+        );
+        return;
+    };
+
+    let created = {
+        let mut collections = match session.collections.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session collections lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        if collections.contains_key(&payload.collection_id) {
+            Err(("DUPLICATE_COLLECTION", format!("Collection {} already exists", payload.collection_id)))
+        } else if collections.len() >= MAX_COLLECTIONS {
+            Err(("COLLECTION_LIMIT", format!("Session {} already holds {} collections", sid, MAX_COLLECTIONS)))
+        } else {
+            let now = now_ms();
+            let collection = Collection {
+                collection_id: payload.collection_id,
+                name,
+                color_tag: payload.color_tag,
+                visible: payload.visible,
+                selectable: payload.selectable,
+                created_by: uid,
+                last_updated_by: uid,
+                last_updated_at: now,
+            };
+            session.append_journal(|| JournalOp::Collection {
+                collection_id: collection.collection_id,
+                after: Some(collection.clone()),
+            });
+            collections.insert(collection.collection_id, collection.clone());
+            Ok(collection)
+        }
+    };
+
+    let collection = match created {
+        Ok(collection) => collection,
+        Err((code, message)) => {
+            tracing::warn!(
+                event_type = "CreateCollection",
+                session_id = %sid,
+                user_id = %uid,
+                collection_id = %payload.collection_id,
+                code,
+                "collection rejected"
+            );
+            send_error(state, connection_id, code, message);
+            return;
+        }
+    };
+
+    tracing::info!(
+        event_type = "CreateCollection",
+        session_id = %sid,
+        user_id = %uid,
+        collection_id = %collection.collection_id,
+        "collection created"
+    );
+
+    let collection_id = collection.collection_id;
+    let json = match serde_json::to_string(&ServerEvent::CollectionCreated(CollectionCreatedPayload {
+        collection,
+        created_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "CollectionCreated",
+                session_id = %sid,
+                collection_id = %collection_id,
+                error = %err,
+                "failed to serialize CollectionCreated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "CollectionCreated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast CollectionCreated"
+    );
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    journal::JournalOp,
+    messages::{CreateMaterialPayload, MaterialCreatedPayload, ServerEvent},
+    types::{AppState, Material, MaterialSettings, MAX_MATERIALS, MAX_MATERIAL_NAME_LEN},
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+/// A material name trimmed, or None when it is empty or longer than MAX_MATERIAL_NAME_LEN.
+pub(super) fn material_name(name: &str) -> Option<String> {
+    let name = name.trim();
+    (!name.is_empty() && name.chars().count() <= MAX_MATERIAL_NAME_LEN).then(|| name.to_string())
+}
+
+/// Colors and factors must be within 0..=1 and emission strength finite and not negative;
+/// texture references need both a library and an asset id.
+pub(super) fn validate_settings(settings: &MaterialSettings) -> Result<(), String> {
+    let unit = |value: f32| (0.0..=1.0).contains(&value);
+    if !settings.base_color.into_iter().chain(settings.emission_color).all(unit) {
+        return Err("color components must be between 0 and 1".to_string());
+    }
+    if ![settings.metallic, settings.roughness, settings.alpha].into_iter().all(unit) {
+        return Err("metallic, roughness and alpha must be between 0 and 1".to_string());
+    }
+    if !settings.emission_strength.is_finite() || settings.emission_strength < 0.0 {
+        return Err("emission strength must be a finite, non-negative number".to_string());
+    }
+    if let Some((slot, _)) = settings
+        .textures
+        .iter()
+        .find(|(_, texture)| texture.asset_library.trim().is_empty() || texture.asset_id.trim().is_empty())
+    {
+        return Err(format!("the {slot} texture needs an asset library and an asset id"));
+    }
+    Ok(())
+}
+
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: CreateMaterialPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let Some(name) = material_name(&payload.name) else {
+        send_error(
+            state,
+            connection_id,
+            "INVALID_MATERIAL",
+            format!("Material names must be 1 to {} characters", MAX_MATERIAL_NAME_LEN),
+        );
+        return;
+    };
+    if let Err(reason) = validate_settings(&payload.settings) {
+        send_error(state, connection_id, "INVALID_MATERIAL", format!("CreateMaterial rejected: {}", reason));
+        return;
+    }
+
+    let created = {
+        let mut materials = match session.materials.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session materials lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        if materials.contains_key(&payload.material_id) {
+            Err(("DUPLICATE_MATERIAL", format!("Material {} already exists", payload.material_id)))
+        } else if materials.len() >= MAX_MATERIALS {
+            Err(("MATERIAL_LIMIT", format!("Session {} already holds {} materials", sid, MAX_MATERIALS)))
+        } else {
+            let now = now_ms();
+            let material = Material {
+                material_id: payload.material_id,
+                name,
+                settings: payload.settings,
+                created_by: uid,
+                last_updated_by: uid,
+                last_updated_at: now,
+            };
+            session.append_journal(|| JournalOp::Material { material: material.clone() });
+            materials.insert(material.material_id, material.clone());
+            Ok(material)
+        }
+    };
+
+    let material = match created {
+        Ok(material) => material,
+        Err((code, message)) => {
+            tracing::warn!(
+                event_type = "CreateMaterial",
+                session_id = %sid,
+                user_id = %uid,
+                material_id = %payload.material_id,
+                code,
+                "material rejected"
+            );
+            send_error(state, connection_id, code, message);
+            return;
+        }
+    };
+
+    tracing::info!(
+        event_type = "CreateMaterial",
+        session_id = %sid,
+        user_id = %uid,
+        material_id = %material.material_id,
+        "material created"
+    );
+
+    let material_id = material.material_id;
+    let json = match serde_json::to_string(&ServerEvent::MaterialCreated(MaterialCreatedPayload {
+        material,
+        created_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "MaterialCreated",
+                session_id = %sid,
+                material_id = %material_id,
+                error = %err,
+                "failed to serialize MaterialCreated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "MaterialCreated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast MaterialCreated"
+    );
+}
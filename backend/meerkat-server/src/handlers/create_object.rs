@@ -3,11 +3,13 @@ use uuid::Uuid;
 use std::collections::hash_map::Entry;
 
 use crate::{
+    hierarchy,
     messages::{CreateObjectPayload, ErrorPayload, ObjectCreatedPayload, ServerEvent},
-    types::{AppState, SceneObject},
+    history::ObjectChange,
+    types::AppState,
 };
 
-use super::helpers::{broadcast, now_ms};
+use super::helpers::{broadcast, created_object, now_ms, send_error};
 
 pub async fn handle(state: &AppState, connection_id: Uuid, payload: CreateObjectPayload) {
     let Some((sid, uid)) = state
@@ -23,20 +25,9 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: CreateObject
         None => return,
     };
 
-    let object = SceneObject {
-        object_id: payload.object_id,
-        name: payload.name.clone(),
-        object_type: payload.object_type.clone(),
-        asset_id: payload.asset_id.clone(),
-        asset_library: payload.asset_library.clone(),
-        transform: payload.transform.clone(),
-        properties: payload.properties.clone(),
-        created_by: uid,
-        last_updated_by: uid,
-        last_updated_at: now,
-    };
+    let object = created_object(&payload, uid, now);
 
-    let inserted: bool = {
+    let inserted: Result<bool, String> = {
         let mut objects = match session.objects.write() {
             Ok(guard) => guard,
             Err(poisoned) => {
@@ -44,12 +35,34 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: CreateObject
                 poisoned.into_inner()
             }
         };
+        let parent = hierarchy::validate_parent(&*objects, object.object_id, object.parent_id);
         match objects.entry(object.object_id) {
-            Entry::Vacant(v) => {
+            Entry::Vacant(v) => parent.map(|()| {
                 v.insert(object.clone());
+                session.record_edit(uid, "CreateObject", now, vec![ObjectChange {
+                    object_id: object.object_id,
+                    before: None,
+                    after: Some(object.clone()),
+                }]);
                 true
-            }
-            Entry::Occupied(_) => false,
+            }),
+            Entry::Occupied(_) => Ok(false),
+        }
+    };
+
+    let inserted = match inserted {
+        Ok(inserted) => inserted,
+        Err(reason) => {
+            tracing::warn!(
+                event_type = "CreateObject",
+                session_id = %sid,
+                user_id = %uid,
+                object_id = %payload.object_id,
+                reason = %reason,
+                "parent rejected on create"
+            );
+            send_error(state, connection_id, "INVALID_PARENT", format!("CreateObject rejected: {}", reason));
+            return;
         }
     };
 
@@ -109,6 +122,7 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: CreateObject
     let json = match serde_json::to_string(&ServerEvent::ObjectCreated(ObjectCreatedPayload {
         object,
         created_by: uid,
+        restored_by: None,
     })) {
         Ok(json) => json,
         Err(err) => {
@@ -124,6 +138,7 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: CreateObject
         }
     };
 
+    session.commit_journal().await;
     let count = broadcast(state, &sid, &json, None);
     tracing::info!(
         event_type = "ObjectCreated",
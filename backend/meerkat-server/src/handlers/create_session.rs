@@ -1,13 +1,12 @@
 use axum::extract::ws::{Message, WebSocket};
-use std::sync::{Arc, RwLock};
-use std::collections::{HashMap};
+use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
-    messages::{CreateSessionPayload, FullStateSyncPayload, ServerEvent, ErrorPayload,},
+    messages::{CreateSessionPayload, ServerEvent, ErrorPayload,},
     types::{AppState, SessionHandle},
 };
-use super::helpers::{cleanup_stale_membership, add_user_to_session};
+use super::helpers::{attach_session_storage, cleanup_stale_membership, add_user_to_session, send_state_sync};
 
 pub async fn handle (socket: &mut WebSocket, state: &AppState, connection_id: Uuid, payload: CreateSessionPayload) {
     if state.sessions.contains_key(&payload.session_id) {
@@ -38,12 +37,8 @@ pub async fn handle (socket: &mut WebSocket, state: &AppState, connection_id: Uu
 
     cleanup_stale_membership(state, connection_id, &payload.session_id);
 
-    let session_handle = Arc::new(SessionHandle {
-        objects: RwLock::new(HashMap::new()),
-        users: RwLock::new(HashMap::new()),
-        session_id: payload.session_id.clone(),
-        password_hash: hashed,
-    });
+    let session_handle = Arc::new(SessionHandle::new(payload.session_id.clone(), hashed));
+    attach_session_storage(state, &session_handle, 0);
 
     state.sessions.insert(payload.session_id.clone(), session_handle.clone());
 
@@ -51,31 +46,7 @@ pub async fn handle (socket: &mut WebSocket, state: &AppState, connection_id: Uu
         state, &session_handle, connection_id, &payload.session_id, &payload.display_name,
     );
 
-    let sync_json = match serde_json::to_string(&ServerEvent::FullStateSync(FullStateSyncPayload {
-        session: session_handle.session_snapshot(),
-        your_user_id: user_id,
-    })) {
-        Ok(json) => json,
-        Err(err) => {
-            tracing::error!(
-                session_id = %payload.session_id,
-                user_id = %user_id,
-                connection_id = %connection_id,
-                error = %err,
-                "failed to serialize FullStateSync"
-            );
-            return;
-        }
-    };
-    if let Err(err) = socket.send(Message::Text(sync_json.into())).await {
-        tracing::warn!(
-            session_id = %payload.session_id,
-            user_id = %user_id,
-            connection_id = %connection_id,
-            error = %err,
-            "failed to send FullStateSync to session creator"
-        );
-    }
+    send_state_sync(socket, state, connection_id, &session_handle, user_id).await;
 }
 
 
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    history::{CollectionChange, ObjectChange},
+    journal::JournalOp,
+    messages::{CollectionDeletedPayload, DeleteCollectionPayload, ServerEvent},
+    types::AppState,
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+/// Delete a collection and take every object out of it, as one undo step that brings the
+/// collection and its memberships back. The objects themselves stay where they are.
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: DeleteCollectionPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let member_count = {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let mut collections = match session.collections.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session collections lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        collections.remove(&payload.collection_id).map(|collection| {
+            session.append_journal(|| JournalOp::Collection { collection_id: payload.collection_id, after: None });
+            let now = now_ms();
+            let mut changes = Vec::new();
+            for object in objects.values_mut().filter(|object| object.collections.contains(&payload.collection_id)) {
+                let before = object.clone();
+                object.collections.retain(|id| *id != payload.collection_id);
+                object.last_updated_by = uid;
+                object.last_updated_at = now;
+                changes.push(ObjectChange { object_id: object.object_id, before: Some(before), after: Some(object.clone()) });
+            }
+            let member_count = changes.len();
+            session.record_collection_edit(uid, "DeleteCollection", now, changes, vec![CollectionChange {
+                collection_id: payload.collection_id,
+                before: Some(collection),
+                after: None,
+            }]);
+            member_count
+        })
+    };
+
+    let Some(member_count) = member_count else {
+        send_error(
+            state,
+            connection_id,
+            "COLLECTION_NOT_FOUND",
+            format!("Collection {} not found", payload.collection_id),
+        );
+        return;
+    };
+
+    tracing::info!(
+        event_type = "DeleteCollection",
+        session_id = %sid,
+        user_id = %uid,
+        collection_id = %payload.collection_id,
+        member_count,
+        "collection deleted"
+    );
+
+    let json = match serde_json::to_string(&ServerEvent::CollectionDeleted(CollectionDeletedPayload {
+        collection_id: payload.collection_id,
+        deleted_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "CollectionDeleted",
+                session_id = %sid,
+                collection_id = %payload.collection_id,
+                error = %err,
+                "failed to serialize CollectionDeleted event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "CollectionDeleted",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast CollectionDeleted"
+    );
+}
@@ -2,11 +2,13 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
-    messages::{DeleteObjectPayload, ObjectDeletedPayload, ServerEvent},
+    hierarchy,
+    messages::{DeleteObjectPayload, ObjectDeletedPayload, ParentUpdatedPayload, ServerEvent},
+    history::ObjectChange,
     types::AppState,
 };
 
-use super::helpers::broadcast;
+use super::helpers::{broadcast, now_ms};
 
 pub async fn handle(state: &AppState, connection_id: Uuid, payload: DeleteObjectPayload) {
     let Some((sid, uid)) = state
@@ -21,7 +23,7 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: DeleteObject
         None => return,
     };
 
-    {
+    let (reparented, deleted) = {
         let mut objects = match session.objects.write() {
             Ok(guard) => guard,
             Err(poisoned) => {
@@ -29,7 +31,7 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: DeleteObject
                 poisoned.into_inner()
             }
         };
-        if objects.remove(&payload.object_id).is_none() {
+        if !objects.contains_key(&payload.object_id) {
             tracing::debug!(
                 object_id = %payload.object_id,
                 session_id = %sid,
@@ -37,39 +39,81 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: DeleteObject
             );
             return;
         }
-    }
+        let now = now_ms();
+        let cascade = hierarchy::delete_cascade(&*objects, payload.object_id, payload.delete_children);
+        let mut changes = Vec::new();
+        for (child_id, parent_id, transform) in &cascade.reparented {
+            let Some(child) = objects.get_mut(child_id) else { continue };
+            let before = child.clone();
+            child.parent_id = *parent_id;
+            child.transform = transform.clone();
+            child.last_updated_by = uid;
+            child.last_updated_at = now;
+            changes.push(ObjectChange { object_id: *child_id, before: Some(before), after: Some(child.clone()) });
+        }
+        // Children before their parents, the object itself last.
+        let deleted: Vec<Uuid> = cascade.deleted.iter().copied().chain([payload.object_id]).collect();
+        let mut removed = Vec::new();
+        for object_id in &deleted {
+            let Some(object) = objects.remove(object_id) else { continue };
+            changes.push(ObjectChange { object_id: *object_id, before: Some(object.clone()), after: None });
+            removed.push(object);
+        }
+        session.record_edit(uid, "DeleteObject", now, changes);
+        for object in removed {
+            session.move_to_trash(object, uid, now);
+        }
+        let touched: Vec<Uuid> = cascade.reparented.iter().map(|(id, _, _)| *id).chain(deleted.iter().copied()).collect();
+        session.discard_pending_transforms(&touched);
+        (cascade.reparented, deleted)
+    };
 
     tracing::info!(
         event_type = "DeleteObject",
         session_id = %sid,
         user_id = %uid,
         object_id = %payload.object_id,
+        reparented_count = reparented.len(),
+        deleted_count = deleted.len(),
         "object deleted"
     );
 
-    let json = match serde_json::to_string(&ServerEvent::ObjectDeleted(ObjectDeletedPayload {
-        object_id: payload.object_id,
-        deleted_by: uid,
-    })) {
-        Ok(json) => json,
-        Err(err) => {
-            tracing::error!(
-                event_type = "ObjectDeleted",
-                session_id = %sid,
-                user_id = %uid,
-                object_id = %payload.object_id,
-                error = %err,
-                "failed to serialize ObjectDeleted event"
-            );
-            return;
-        }
-    };
+    let mut events: Vec<(Uuid, ServerEvent)> = reparented
+        .into_iter()
+        .map(|(object_id, parent_id, transform)| {
+            (object_id, ServerEvent::ParentUpdated(ParentUpdatedPayload { object_id, parent_id, transform, updated_by: uid }))
+        })
+        .collect();
+    events.extend(
+        deleted
+            .into_iter()
+            .map(|object_id| (object_id, ServerEvent::ObjectDeleted(ObjectDeletedPayload { object_id, deleted_by: uid }))),
+    );
 
-    let count = broadcast(state, &sid, &json, None);
+    session.commit_journal().await;
+    let mut count = 0;
+    for (object_id, event) in &events {
+        let json = match serde_json::to_string(event) {
+            Ok(json) => json,
+            Err(err) => {
+                tracing::error!(
+                    event_type = "ObjectDeleted",
+                    session_id = %sid,
+                    user_id = %uid,
+                    object_id = %object_id,
+                    error = %err,
+                    "failed to serialize DeleteObject broadcast"
+                );
+                continue;
+            }
+        };
+        count = broadcast(state, &sid, &json, None);
+    }
     tracing::info!(
         event_type = "ObjectDeleted",
         session_id = %sid,
         recipient_count = count,
+        event_count = events.len(),
         "broadcast ObjectDeleted"
     );
 }
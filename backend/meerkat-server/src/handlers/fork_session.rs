@@ -0,0 +1,113 @@
+use axum::extract::ws::{Message, WebSocket};
+use std::sync::{Arc, RwLock};
+use uuid::Uuid;
+
+use crate::{
+    messages::{ErrorPayload, ForkSessionPayload, ServerEvent, SessionForkedPayload},
+    types::{AppState, SessionHandle},
+};
+use super::helpers::{add_user_to_session, broadcast, cleanup_stale_membership, insert_new_session, send_state_sync};
+
+async fn send_error(socket: &mut WebSocket, code: &str, message: String) {
+    let err_json = serde_json::to_string(&ServerEvent::Error(ErrorPayload {
+        code: code.to_string(),
+        message,
+    }));
+    if let Ok(json) = err_json {
+        let _ = socket.send(Message::Text(json.into())).await;
+    }
+}
+
+/// Branch the sender's session: copy its objects (ids and attribution intact) into a new
+/// session with its own password, then move the sender into it the way CreateSession would.
+/// Undo history and checkpoints stay with the source session.
+pub async fn handle(socket: &mut WebSocket, state: &AppState, connection_id: Uuid, payload: ForkSessionPayload) {
+    let Some((source_sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        send_error(socket, "NOT_IN_SESSION", "ForkSession requires joining a session first".to_string()).await;
+        return;
+    };
+    let Some(source) = state.sessions.get(&source_sid).map(|s| Arc::clone(s.value())) else {
+        return;
+    };
+
+    let new_sid = payload.new_session_id.trim().to_string();
+    if new_sid.is_empty() || state.sessions.contains_key(&new_sid) {
+        send_error(socket, "SESSION_ALREADY_EXISTS", format!("Session with id '{}' already exists", new_sid)).await;
+        return;
+    }
+
+    let hashed = match bcrypt::hash(&payload.password, bcrypt::DEFAULT_COST) {
+        Ok(h) => h,
+        Err(err) => {
+            tracing::error!(error=%err, "failed to hash password");
+            send_error(socket, "InternalError", "Failed to fork session due to internal error".to_string()).await;
+            return;
+        }
+    };
+
+    let display_name = match source.users.read() {
+        Ok(users) => users.get(&uid).map(|u| u.display_name.clone()),
+        Err(poisoned) => poisoned.into_inner().get(&uid).map(|u| u.display_name.clone()),
+    }
+    .unwrap_or_default();
+
+    let snapshot = source.session_snapshot();
+    let object_count = snapshot.objects.len();
+    let mut fork = SessionHandle::new(new_sid.clone(), hashed);
+    fork.objects = RwLock::new(snapshot.objects);
+    fork.collections = RwLock::new(snapshot.collections);
+    fork.materials = RwLock::new(snapshot.materials);
+    // Read after the objects, so every mesh the copied objects reference is included.
+    let meshes = match source.meshes.read() {
+        Ok(meshes) => meshes.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    };
+    fork.meshes = RwLock::new(meshes);
+    let Some(fork) = insert_new_session(state, fork).await else {
+        send_error(socket, "SESSION_ALREADY_EXISTS", format!("Session with id '{}' already exists", new_sid)).await;
+        return;
+    };
+
+    tracing::info!(
+        event_type = "ForkSession",
+        source_session_id = %source_sid,
+        session_id = %new_sid,
+        user_id = %uid,
+        object_count,
+        "session forked"
+    );
+
+    // Tell the source session before the sender leaves it, so the notice reaches them too.
+    match serde_json::to_string(&ServerEvent::SessionForked(SessionForkedPayload {
+        source_session_id: source_sid.clone(),
+        new_session_id: new_sid.clone(),
+        forked_by: uid,
+        object_count,
+    })) {
+        Ok(json) => {
+            let count = broadcast(state, &source_sid, &json, None);
+            tracing::info!(
+                event_type = "SessionForked",
+                session_id = %source_sid,
+                recipient_count = count,
+                "broadcast SessionForked"
+            );
+        }
+        Err(err) => {
+            tracing::error!(
+                event_type = "SessionForked",
+                session_id = %source_sid,
+                error = %err,
+                "failed to serialize SessionForked event"
+            );
+        }
+    }
+
+    cleanup_stale_membership(state, connection_id, &new_sid);
+    let (user_id, _) = add_user_to_session(state, &fork, connection_id, &new_sid, &display_name);
+    send_state_sync(socket, state, connection_id, &fork, user_id).await;
+}
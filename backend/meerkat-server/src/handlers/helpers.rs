@@ -1,15 +1,38 @@
+use axum::extract::ws::{Message, WebSocket};
+use dashmap::mapref::entry::Entry;
 use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, error::TrySendError};
 use uuid::Uuid;
 
+use std::path::Path;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
-use crate::messages::{ServerEvent, UserLeftPayload};
-use crate::types::{AppState, LagState, SessionHandle, User, COLOR_PALETTE};
+use crate::audit::{self, AUDIT_FILE_NAME};
+use crate::checksum::{format_checksum, objects_checksum};
+use crate::history::ObjectChange;
+use crate::messages::{
+    ClientEvent, CreateObjectPayload, ErrorPayload, FullStateSyncPayload, ObjectCreatedPayload, ServerEvent, StateSyncBeginPayload, StateSyncChunkPayload, StateSyncEndPayload,
+    UserLeftPayload,
+};
+use crate::recording::{RecordedFrame, Recorder, RECORDINGS_DIR_NAME};
+use crate::snapshot::{self, SessionSnapshot};
+use crate::types::{AppState, LagState, SceneObject, SessionHandle, SyncBacklog, User, COLOR_PALETTE};
 
 const BACKPRESSURE_RESET_MS: u64 = 5_000;
 const BACKPRESSURE_EVICT_STRIKES: u8 = 3;
 
+/// Scenes with more objects than this are streamed as StateSyncBegin/Chunk/End
+/// instead of a single FullStateSync frame.
+pub const STATE_SYNC_CHUNK_SIZE: usize = 256;
+
+/// Broadcasts held for one connection while a state sync is written to it. Past this the backlog
+/// is dropped and the sync sent again, rather than holding an unbounded queue.
+pub const SYNC_BACKLOG_LIMIT: usize = 4_096;
+
+/// Objects brought in from a file (glTF, USD) are attributed to this id, which no user has.
+pub const IMPORT_USER_ID: Uuid = Uuid::nil();
+
 pub fn now_ms() -> u64 {
     match SystemTime::now().duration_since(UNIX_EPOCH) {
         Ok(duration) => duration.as_millis() as u64,
@@ -21,6 +44,8 @@ pub fn now_ms() -> u64 {
 }
 
 pub fn broadcast(state: &AppState, session_id: &str, json: &str, exclude: Option<Uuid>) -> usize {
+    record_server_event(state, session_id, json);
+
     let mut delivered = 0;
     let mut dropped_full = 0;
     let mut dropped_closed = 0;
@@ -39,6 +64,19 @@ pub fn broadcast(state: &AppState, session_id: &str, json: &str, exclude: Option
         if exclude == Some(conn_id) {
             continue;
         }
+        if let Some(mut backlog) = state.sync_backlogs.get_mut(&conn_id) {
+            // The connection's event loop is busy writing a state sync; hold the message so a
+            // large sync does not fill its queue and get it evicted.
+            if let Some(held) = backlog.held.as_mut() {
+                if held.len() < SYNC_BACKLOG_LIMIT {
+                    held.push(json.to_owned());
+                } else {
+                    backlog.held = None;
+                }
+            }
+            delivered += 1;
+            continue;
+        }
         if let Some(tx) = state.connections.get(&conn_id) {
             match tx.try_send(json.to_owned()) {
                 Ok(()) => {
@@ -103,10 +141,43 @@ pub fn broadcast(state: &AppState, session_id: &str, json: &str, exclude: Option
     delivered
 }
 
+/// Append a broadcast to the session's recording, if it is being recorded.
+fn record_server_event(state: &AppState, session_id: &str, json: &str) {
+    if !state.config.record_sessions {
+        return;
+    }
+    let Some(session) = state.sessions.get(session_id).map(|s| Arc::clone(s.value())) else {
+        return;
+    };
+    if let Some(recorder) = session.lock_recorder().as_mut() {
+        recorder.record_server(now_ms(), json);
+    }
+}
+
+/// Append an accepted client event to the recording of the sender's session, before it is
+/// handled. Events from connections not yet in a session (Join/Create) show up as the
+/// UserJoined they cause instead.
+pub fn record_client_event(state: &AppState, connection_id: Uuid, event: &ClientEvent) {
+    if !state.config.record_sessions {
+        return;
+    }
+    let Some((sid, uid)) = state.connection_meta.get(&connection_id).map(|r| r.value().clone()) else {
+        return;
+    };
+    let Some(session) = state.sessions.get(&sid).map(|s| Arc::clone(s.value())) else {
+        return;
+    };
+    if let Some(recorder) = session.lock_recorder().as_mut() {
+        recorder.record_client(now_ms(), uid, event);
+    }
+}
+
 pub fn evict_connection(state: &AppState, connection_ids: &[Uuid]) {
     for conn_id in connection_ids {
         state.connections.remove(conn_id);
         state.connection_backpressure.remove(conn_id);
+        state.rate_limits.remove(conn_id);
+        state.sync_backlogs.remove(conn_id);
 
         if let Some((_, (session_id, _user_id))) = state.connection_meta.remove(conn_id) {
             let mut remove_session_entry = false;
@@ -175,11 +246,13 @@ pub fn cleanup_stale_membership(state: &AppState, connection_id: Uuid, new_sessi
                 }
             };
             users.remove(&old_uid);
-            reclaim_old_session = users.is_empty() && old_sid != new_session_id;
+            old_session.lock_history().forget_user(old_uid);
+            old_session.forget_uploads(connection_id);
+            reclaim_old_session = users.is_empty() && !old_session.pinned && old_sid != new_session_id;
         }
 
         if reclaim_old_session {
-            state.sessions.remove(&old_sid);
+            reclaim_session(state, &old_sid);
             tracing::info!(
                 event_type = "SessionReclaimed",
                 session_id = %old_sid,
@@ -213,6 +286,215 @@ pub fn cleanup_stale_membership(state: &AppState, connection_id: Uuid, new_sessi
     }
 }
 
+/// Drop an empty session from memory along with its snapshot and journal, so it does not come
+/// back on the next start. Its audit log and recordings are kept.
+pub fn reclaim_session(state: &AppState, session_id: &str) {
+    let Some((_, session)) = state.sessions.remove(session_id) else {
+        return;
+    };
+    let Some(dir) = state.config.session_dir(session_id) else {
+        return;
+    };
+    if let Err(err) = snapshot::discard(&session, &dir) {
+        tracing::error!(
+            session_id = %session_id,
+            path = %dir.display(),
+            error = %err,
+            "failed to delete persisted state of reclaimed session"
+        );
+    }
+}
+
+/// Persist and publish a session nobody has seen yet. Returns `None`, leaving storage alone,
+/// when another session already holds its id.
+pub async fn insert_new_session(state: &AppState, session: SessionHandle) -> Option<Arc<SessionHandle>> {
+    let dir = state.config.session_dir(&session.session_id);
+    // Taken before the session is shared, so it is exactly what journal seq 0 covers.
+    let initial = dir.as_ref().map(|_| SessionSnapshot::of(&session, 0));
+    let session = Arc::new(session);
+    // Held until the snapshot is on disk: an edit made in the meantime is journaled, but
+    // commit_journal waits here before it can be acknowledged.
+    let sync = session.journal_sync.lock().await;
+    match state.sessions.entry(session.session_id.clone()) {
+        Entry::Occupied(_) => return None,
+        Entry::Vacant(entry) => {
+            // Only the cheap part happens under the map's shard lock; writing a large
+            // snapshot there would stall every session sharing the shard.
+            if let Some(dir) = &dir
+                && let Err(err) = snapshot::open_journal(&session, dir, state.config.encryption.as_ref())
+            {
+                tracing::error!(
+                    session_id = %session.session_id,
+                    path = %dir.display(),
+                    error = %err,
+                    "failed to start session journal, session will not survive a restart"
+                );
+            }
+            entry.insert(Arc::clone(&session));
+        }
+    }
+    if let (Some(dir), Some(initial)) = (dir, initial) {
+        let (task_state, task_session) = (state.clone(), Arc::clone(&session));
+        let written = tokio::task::spawn_blocking(move || {
+            if let Err(err) = snapshot::write_snapshot(&dir, &initial, task_state.config.encryption.as_ref()) {
+                *task_session.lock_journal() = None;
+                tracing::error!(
+                    session_id = %task_session.session_id,
+                    path = %dir.display(),
+                    error = %err,
+                    "failed to write session snapshot, session will not survive a restart"
+                );
+            }
+            attach_session_logs(&task_state, &task_session, &dir);
+        })
+        .await;
+        if let Err(err) = written {
+            tracing::error!(session_id = %session.session_id, error = %err, "session storage task failed");
+        }
+    }
+    drop(sync);
+    Some(session)
+}
+
+/// Add objects read from a file to a live session and broadcast each as ObjectCreated. Ids
+/// already taken in the session are replaced. The import is journaled and audited under
+/// `source` (e.g. "glTF import") but, having no user, is not on anyone's undo stack. Returns
+/// the objects as added.
+pub async fn add_imported_objects(
+    state: &AppState,
+    session: &SessionHandle,
+    mut imported: Vec<SceneObject>,
+    event_type: &'static str,
+    source: &str,
+) -> Vec<SceneObject> {
+    let now = now_ms();
+    {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        for object in &mut imported {
+            if objects.contains_key(&object.object_id) {
+                object.object_id = Uuid::new_v4();
+            }
+            object.created_by = IMPORT_USER_ID;
+            object.last_updated_by = IMPORT_USER_ID;
+            object.last_updated_at = now;
+            objects.insert(object.object_id, object.clone());
+        }
+        let changes: Vec<ObjectChange> = imported
+            .iter()
+            .map(|object| ObjectChange { object_id: object.object_id, before: None, after: Some(object.clone()) })
+            .collect();
+        if !changes.is_empty() {
+            session.record_object_changes(&changes);
+            let mut log = session.lock_audit();
+            for entry in audit::entries_for(IMPORT_USER_ID, source, event_type, now, &changes) {
+                log.record(entry);
+            }
+        }
+    }
+
+    tracing::info!(
+        event_type,
+        session_id = %session.session_id,
+        object_count = imported.len(),
+        "objects imported"
+    );
+
+    session.commit_journal().await;
+    for object in &imported {
+        let json = match serde_json::to_string(&ServerEvent::ObjectCreated(ObjectCreatedPayload {
+            object: object.clone(),
+            created_by: IMPORT_USER_ID,
+            restored_by: None,
+        })) {
+            Ok(json) => json,
+            Err(err) => {
+                tracing::error!(
+                    event_type = "ObjectCreated",
+                    session_id = %session.session_id,
+                    object_id = %object.object_id,
+                    error = %err,
+                    "failed to serialize ObjectCreated event"
+                );
+                continue;
+            }
+        };
+        broadcast(state, &session.session_id, &json, None);
+    }
+    imported
+}
+
+/// Hook a session that is not yet shared up to its storage directory, when persistence is
+/// enabled: its current state becomes the snapshot covering journal seq `journal_seq` (0 for a
+/// new session) and its journal starts from there. Failures are logged and the session carries
+/// on in memory only.
+pub fn attach_session_storage(state: &AppState, session: &SessionHandle, journal_seq: u64) {
+    let Some(dir) = state.config.session_dir(&session.session_id) else {
+        return;
+    };
+    if let Err(err) = snapshot::start_journal(session, &dir, journal_seq, state.config.encryption.as_ref()) {
+        tracing::error!(
+            session_id = %session.session_id,
+            path = %dir.display(),
+            error = %err,
+            "failed to start session journal, session will not survive a restart"
+        );
+    }
+    attach_session_logs(state, session, &dir);
+}
+
+/// Open a session's audit log file and, when enabled, start recording it. Failures are logged
+/// and the session carries on without them.
+fn attach_session_logs(state: &AppState, session: &SessionHandle, dir: &Path) {
+    let path = dir.join(AUDIT_FILE_NAME);
+    if let Err(err) = session.lock_audit().attach(&path, state.config.encryption.clone()) {
+        tracing::error!(
+            session_id = %session.session_id,
+            path = %path.display(),
+            error = %err,
+            "failed to open audit log file, auditing in memory only"
+        );
+    }
+
+    if state.config.record_sessions {
+        let start = RecordedFrame::Start {
+            session_id: session.session_id.clone(),
+            started_at: now_ms(),
+            objects: recover_clone(&session.objects),
+            users: recover_clone(&session.users),
+        };
+        let recordings = dir.join(RECORDINGS_DIR_NAME);
+        match Recorder::create(&recordings, start, state.config.encryption.clone()) {
+            Ok(recorder) => {
+                tracing::info!(
+                    session_id = %session.session_id,
+                    path = %recorder.path().display(),
+                    "recording session"
+                );
+                *session.lock_recorder() = Some(recorder);
+            }
+            Err(err) => tracing::error!(
+                session_id = %session.session_id,
+                path = %recordings.display(),
+                error = %err,
+                "failed to start session recording"
+            ),
+        }
+    }
+}
+
+fn recover_clone<T: Clone>(lock: &std::sync::RwLock<T>) -> T {
+    match lock.read() {
+        Ok(guard) => guard.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
 /// Add a user to a session and track the connection.
 /// Returns (user_id, color) for use in FullStateSync and UserJoined broadcasts.
 pub fn add_user_to_session(state: &AppState, session: &Arc<SessionHandle>, connection_id: Uuid, session_id: &str, display_name: &str,) -> (Uuid, [u8; 3]) {
@@ -278,3 +560,257 @@ fn decay_lag_strikes_on_ok_send(state: &AppState, connection_id: Uuid, now_ms: u
             .remove_if(&connection_id, |_, lag| lag.strikes == 0);
     }
 }
+
+/// Tell a session's clients, once, that its journal failed and edits are now refused.
+pub fn announce_storage_failure(state: &AppState, session: &SessionHandle) {
+    if !session.storage_failed() || session.storage_failure_announced.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let json = match serde_json::to_string(&ServerEvent::Error(ErrorPayload {
+        code: "SESSION_STORAGE_FAILED".to_string(),
+        message: "The server can no longer save this session; it is read-only until restarted. \
+                  Fork it to keep working on a copy."
+            .to_string(),
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(error = %err, "failed to serialize SESSION_STORAGE_FAILED error");
+            return;
+        }
+    };
+    let recipient_count = broadcast(state, &session.session_id, &json, None);
+    tracing::warn!(session_id = %session.session_id, recipient_count, "announced session storage failure");
+}
+
+/// The object a CreateObject from `user_id` makes, on its own or as a transaction step.
+pub fn created_object(payload: &CreateObjectPayload, user_id: Uuid, now: u64) -> SceneObject {
+    SceneObject {
+        asset_id: payload.asset_id.clone(),
+        asset_library: payload.asset_library.clone(),
+        parent_id: payload.parent_id,
+        transform: payload.transform.clone(),
+        properties: payload.properties.clone(),
+        ..SceneObject::new(payload.object_id, payload.name.clone(), payload.object_type.clone(), user_id, now)
+    }
+}
+
+/// Queue an Error event for one connection from a handler that has no socket of its own.
+pub fn send_error(state: &AppState, connection_id: Uuid, code: &str, message: String) {
+    let Some(tx) = state.connections.get(&connection_id) else {
+        return;
+    };
+    let json = match serde_json::to_string(&ServerEvent::Error(ErrorPayload {
+        code: code.to_string(),
+        message,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(code, error = %err, "failed to serialize error event");
+            return;
+        }
+    };
+    if tx.try_send(json).is_err() {
+        tracing::warn!(code, connection_id = %connection_id, "failed to deliver error event");
+    }
+}
+
+async fn send_event(socket: &mut WebSocket, event: &ServerEvent) -> Result<(), String> {
+    let json = serde_json::to_string(event).map_err(|err| format!("serialize failed: {err}"))?;
+    socket
+        .send(Message::Text(json.into()))
+        .await
+        .map_err(|err| format!("send failed: {err}"))
+}
+
+/// Send the current session state to a single client.
+///
+/// Small scenes go out as one FullStateSync. Larger scenes are streamed as StateSyncBegin,
+/// StateSyncChunk pages of STATE_SYNC_CHUNK_SIZE objects, and a StateSyncEnd carrying a checksum.
+/// The connection gets a sync backlog before the state is snapshotted; broadcasts applied after
+/// that point are held there instead of filling its queue, and the event loop replays them with
+/// flush_sync_backlog once this returns, so the client always applies live updates on top of a
+/// complete snapshot. Broadcasts already on its queue are counted as stale, since the snapshot
+/// includes them.
+/// Returns false if anything failed to serialize or send (already logged).
+pub async fn send_state_sync(
+    socket: &mut WebSocket,
+    state: &AppState,
+    connection_id: Uuid,
+    session: &SessionHandle,
+    user_id: Uuid,
+) -> bool {
+    state
+        .sync_backlogs
+        .insert(connection_id, SyncBacklog { stale: 0, held: Some(Vec::new()) });
+    // Counted once broadcasts are held, so nothing reaches the queue uncounted in between.
+    let queued = state
+        .connections
+        .get(&connection_id)
+        .map_or(0, |tx| tx.max_capacity() - tx.capacity());
+    if let Some(mut backlog) = state.sync_backlogs.get_mut(&connection_id) {
+        backlog.stale = queued;
+    }
+    let snapshot = session.session_snapshot();
+
+    if snapshot.objects.len() <= STATE_SYNC_CHUNK_SIZE {
+        let event = ServerEvent::FullStateSync(FullStateSyncPayload {
+            session: snapshot,
+            your_user_id: user_id,
+        });
+        if let Err(err) = send_event(socket, &event).await {
+            tracing::warn!(
+                event_type = "FullStateSync",
+                session_id = %session.session_id,
+                user_id = %user_id,
+                error = %err,
+                "failed to deliver FullStateSync"
+            );
+            return false;
+        }
+        return true;
+    }
+
+    let objects: Vec<SceneObject> = snapshot.objects.into_values().collect();
+    let object_count = objects.len();
+    let chunk_count = object_count.div_ceil(STATE_SYNC_CHUNK_SIZE);
+    let checksum = format_checksum(objects_checksum(&objects));
+
+    let begin = ServerEvent::StateSyncBegin(StateSyncBeginPayload {
+        session_id: snapshot.session_id,
+        your_user_id: user_id,
+        users: snapshot.users,
+        object_count,
+        chunk_count,
+        version: snapshot.version,
+        collections: snapshot.collections,
+        materials: snapshot.materials,
+    });
+    if let Err(err) = send_event(socket, &begin).await {
+        tracing::warn!(
+            event_type = "StateSyncBegin",
+            session_id = %session.session_id,
+            user_id = %user_id,
+            error = %err,
+            "failed to deliver StateSyncBegin"
+        );
+        return false;
+    }
+
+    for (chunk_index, chunk) in objects.chunks(STATE_SYNC_CHUNK_SIZE).enumerate() {
+        let event = ServerEvent::StateSyncChunk(StateSyncChunkPayload {
+            chunk_index,
+            objects: chunk.to_vec(),
+        });
+        if let Err(err) = send_event(socket, &event).await {
+            tracing::warn!(
+                event_type = "StateSyncChunk",
+                session_id = %session.session_id,
+                user_id = %user_id,
+                chunk_index,
+                chunk_count,
+                error = %err,
+                "failed to deliver StateSyncChunk"
+            );
+            return false;
+        }
+    }
+
+    let end = ServerEvent::StateSyncEnd(StateSyncEndPayload { object_count, checksum });
+    if let Err(err) = send_event(socket, &end).await {
+        tracing::warn!(
+            event_type = "StateSyncEnd",
+            session_id = %session.session_id,
+            user_id = %user_id,
+            error = %err,
+            "failed to deliver StateSyncEnd"
+        );
+        return false;
+    }
+
+    tracing::info!(
+        event_type = "StateSyncEnd",
+        session_id = %session.session_id,
+        user_id = %user_id,
+        object_count,
+        chunk_count,
+        "streamed chunked state sync"
+    );
+    true
+}
+
+async fn send_text(socket: &mut WebSocket, connection_id: Uuid, json: String) -> bool {
+    if let Err(err) = socket.send(Message::Text(json.into())).await {
+        tracing::debug!(connection_id = %connection_id, error = %err, "failed to deliver held broadcast");
+        return false;
+    }
+    true
+}
+
+/// Deliver what was held back while send_state_sync wrote to a connection: errors among the
+/// messages its snapshot already covered, anything queued on its channel since, then the held
+/// broadcasts in order. A backlog that outgrew SYNC_BACKLOG_LIMIT is thrown away and the sync
+/// sent again from a fresh snapshot.
+/// Returns false if the socket failed.
+pub async fn flush_sync_backlog(
+    socket: &mut WebSocket,
+    state: &AppState,
+    connection_id: Uuid,
+    rx: &mut mpsc::Receiver<String>,
+) -> bool {
+    loop {
+        let (stale, held) = match state.sync_backlogs.get_mut(&connection_id) {
+            Some(mut backlog) => (std::mem::take(&mut backlog.stale), backlog.held.replace(Vec::new())),
+            None => return true,
+        };
+        for _ in 0..stale {
+            let Ok(json) = rx.try_recv() else {
+                break;
+            };
+            if matches!(serde_json::from_str(&json), Ok(ServerEvent::Error(_)))
+                && !send_text(socket, connection_id, json).await
+            {
+                return false;
+            }
+        }
+        while let Ok(json) = rx.try_recv() {
+            if !send_text(socket, connection_id, json).await {
+                return false;
+            }
+        }
+
+        match held {
+            Some(batch) if batch.is_empty() => {
+                // Only stop holding once nothing arrived since the batch was taken.
+                state
+                    .sync_backlogs
+                    .remove_if(&connection_id, |_, backlog| backlog.held.as_ref().is_some_and(Vec::is_empty));
+            }
+            Some(batch) => {
+                for json in batch {
+                    if !send_text(socket, connection_id, json).await {
+                        return false;
+                    }
+                }
+            }
+            None => {
+                let member = state.connection_meta.get(&connection_id).map(|meta| meta.value().clone());
+                let session = member
+                    .as_ref()
+                    .and_then(|(sid, _)| state.sessions.get(sid).map(|s| Arc::clone(s.value())));
+                let (Some((sid, uid)), Some(session)) = (member, session) else {
+                    state.sync_backlogs.remove(&connection_id);
+                    return true;
+                };
+                tracing::warn!(
+                    connection_id = %connection_id,
+                    session_id = %sid,
+                    limit = SYNC_BACKLOG_LIMIT,
+                    "too many broadcasts held during state sync; resending the sync"
+                );
+                if !send_state_sync(socket, state, connection_id, &session, uid).await {
+                    return false;
+                }
+            }
+        }
+    }
+}
@@ -1,27 +1,29 @@
 use axum::extract::ws::{Message, WebSocket};
+use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
-    messages::{ErrorPayload, FullStateSyncPayload, JoinSessionPayload, ServerEvent, UserJoinedPayload},
+    messages::{ErrorPayload, JoinSessionPayload, ServerEvent, UserJoinedPayload},
     types::AppState,
 };
 
-use super::helpers::{add_user_to_session, broadcast, cleanup_stale_membership};
+use super::helpers::{add_user_to_session, broadcast, cleanup_stale_membership, send_state_sync};
 
 // join_session handler is responsible for:
 // 1) Looking up existing session by ID, rejecting if not found.
 // 2) Verifying the password against the stored bcrypt hash, rejecting if wrong.
 // 3) Cleaning up stale membership if this connection was already tracked.
 // 4) Adding the user to the session's user list.
-// 5) Sending FullStateSync to the joining user.
+// 5) Sending the session state to the joining user (FullStateSync, or a chunked stream for large scenes).
 // 6) Broadcasting UserJoined to all other users in the session.
 
 pub async fn handle(socket :&mut WebSocket, state: &AppState, connection_id: Uuid, payload: JoinSessionPayload) {
     // Re-join safety: if this connection was already tracked, clean old membership first.
     cleanup_stale_membership(state, connection_id, &payload.session_id);
 
+    // Clone the Arc out so the DashMap shard guard is not held across the state sync sends.
     let session = match state.sessions.get(&payload.session_id) {
-        Some(s) => s,
+        Some(s) => Arc::clone(s.value()),
         None => {
             let err_json = serde_json::to_string(&ServerEvent::Error(ErrorPayload {
                 code: "SESSION_NOT_FOUND".to_string(),
@@ -56,33 +58,7 @@ pub async fn handle(socket :&mut WebSocket, state: &AppState, connection_id: Uui
         state, &session, connection_id, &payload.session_id, &payload.display_name,
     );
 
-    let sync_json = match serde_json::to_string(&ServerEvent::FullStateSync(FullStateSyncPayload {
-        session: (session.session_snapshot()),
-        your_user_id: user_id,
-    })) {
-        Ok(json) => json,
-        Err(err) => {
-            tracing::error!(
-                event_type = "FullStateSync",
-                session_id = %payload.session_id,
-                user_id = %user_id,
-                connection_id = %connection_id,
-                error = %err,
-                "failed to serialize FullStateSync"
-            );
-            return;
-        }
-    };
-    if let Err(err) = socket.send(Message::Text(sync_json.into())).await {
-        tracing::warn!(
-            event_type = "FullStateSync",
-            session_id = %payload.session_id,
-            user_id = %user_id,
-            connection_id = %connection_id,
-            error = %err,
-            "failed to send FullStateSync to joining client"
-        );
-    }
+    send_state_sync(socket, state, connection_id, &session, user_id).await;
 
     let joined_json = match serde_json::to_string(&ServerEvent::UserJoined(UserJoinedPayload {
         user_id,
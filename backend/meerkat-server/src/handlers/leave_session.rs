@@ -5,7 +5,7 @@ use crate::{
     types::AppState,
 };
 
-use super::helpers::broadcast;
+use super::helpers::{broadcast, reclaim_session};
 
 pub async fn handle(state: &AppState, connection_id: Uuid) {
     let Some((_, (sid, uid))) = state.connection_meta.remove(&connection_id) else {
@@ -21,7 +21,7 @@ pub async fn handle(state: &AppState, connection_id: Uuid) {
         state.session_connections.remove(&sid);
     }
 
-    let mut reclaim_session = false;
+    let mut reclaim = false;
     if let Some(session) = state.sessions.get(&sid) {
         let mut users = match session.users.write(){ 
             Ok(guard) => guard, 
@@ -31,12 +31,14 @@ pub async fn handle(state: &AppState, connection_id: Uuid) {
             }
         };
         users.remove(&uid);
-        reclaim_session = users.is_empty();
+        reclaim = users.is_empty() && !session.pinned;
+        session.lock_history().forget_user(uid);
+        session.forget_uploads(connection_id);
     }
 
     // reclaim session in memory if it is empty 
-    if reclaim_session {
-        state.sessions.remove(&sid);
+    if reclaim {
+        reclaim_session(state, &sid);
         tracing::info!(
             event_type = "SessionReclaimed",
             session_id = %sid,
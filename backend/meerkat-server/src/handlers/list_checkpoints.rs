@@ -0,0 +1,63 @@
+use axum::extract::ws::{Message, WebSocket};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    messages::{CheckpointInfo, CheckpointListPayload, ServerEvent},
+    types::AppState,
+};
+
+pub async fn handle(socket: &mut WebSocket, state: &AppState, connection_id: Uuid) {
+    let Some((sid, _uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let checkpoints: Vec<CheckpointInfo> = match session.checkpoints.read() {
+        Ok(guard) => guard.iter().map(CheckpointInfo::from).collect(),
+        Err(poisoned) => {
+            tracing::warn!("Session checkpoints lock poisoned, recovering");
+            poisoned.into_inner().iter().map(CheckpointInfo::from).collect()
+        }
+    };
+
+    let checkpoint_count = checkpoints.len();
+    let json = match serde_json::to_string(&ServerEvent::CheckpointList(CheckpointListPayload { checkpoints })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "CheckpointList",
+                session_id = %sid,
+                connection_id = %connection_id,
+                error = %err,
+                "failed to serialize CheckpointList"
+            );
+            return;
+        }
+    };
+
+    if let Err(err) = socket.send(Message::Text(json.into())).await {
+        tracing::warn!(
+            event_type = "CheckpointList",
+            session_id = %sid,
+            connection_id = %connection_id,
+            error = %err,
+            "failed to send CheckpointList to requesting client"
+        );
+        return;
+    }
+
+    tracing::info!(
+        event_type = "ListCheckpoints",
+        session_id = %sid,
+        checkpoint_count,
+        "sent CheckpointList to requesting client"
+    );
+}
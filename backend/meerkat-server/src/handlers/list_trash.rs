@@ -0,0 +1,73 @@
+use axum::extract::ws::{Message, WebSocket};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    messages::{ServerEvent, TrashItem, TrashListPayload},
+    trash::{expires_at, purge_expired},
+    types::AppState,
+};
+
+use super::helpers::now_ms;
+
+pub async fn handle(socket: &mut WebSocket, state: &AppState, connection_id: Uuid) {
+    let Some((sid, _uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    // Purge first so nothing past its retention is offered for restore.
+    let retention = state.config.trash_retention;
+    purge_expired(&session, now_ms(), retention);
+    let mut items: Vec<TrashItem> = session
+        .lock_trash()
+        .values()
+        .map(|trashed| TrashItem {
+            object: trashed.object.clone(),
+            deleted_by: trashed.deleted_by,
+            deleted_at: trashed.deleted_at,
+            expires_at: expires_at(trashed.deleted_at, retention),
+        })
+        .collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.deleted_at));
+
+    let item_count = items.len();
+    let json = match serde_json::to_string(&ServerEvent::TrashList(TrashListPayload { items })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "TrashList",
+                session_id = %sid,
+                connection_id = %connection_id,
+                error = %err,
+                "failed to serialize TrashList"
+            );
+            return;
+        }
+    };
+
+    if let Err(err) = socket.send(Message::Text(json.into())).await {
+        tracing::warn!(
+            event_type = "TrashList",
+            session_id = %sid,
+            connection_id = %connection_id,
+            error = %err,
+            "failed to send TrashList to requesting client"
+        );
+        return;
+    }
+
+    tracing::info!(
+        event_type = "ListTrash",
+        session_id = %sid,
+        item_count,
+        "sent TrashList to requesting client"
+    );
+}
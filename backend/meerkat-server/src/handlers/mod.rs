@@ -6,7 +6,36 @@ pub mod delete_object;
 pub mod update_transform;
 pub mod update_properties;
 pub mod update_name;
+pub mod set_parent;
+pub mod create_collection;
+pub mod update_collection;
+pub mod delete_collection;
+pub mod set_object_collections;
+pub mod set_custom_property;
+pub mod remove_custom_property;
+pub mod patch_properties;
+pub mod create_material;
+pub mod update_material;
+pub mod set_object_materials;
+pub mod upload_mesh_chunk;
+pub mod set_object_mesh;
+pub mod request_mesh;
+pub mod add_modifier;
+pub mod remove_modifier;
+pub mod reorder_modifiers;
+pub mod update_modifier;
 pub mod select_object;
 pub mod request_state_sync;
+pub mod request_checksum;
+pub mod request_objects_sync;
 pub mod update_cursor;
 pub mod create_session;
+pub mod transaction;
+pub mod undo;
+pub mod create_checkpoint;
+pub mod list_checkpoints;
+pub mod restore_checkpoint;
+pub mod fork_session;
+pub mod list_trash;
+pub mod restore_object;
+pub mod query_audit_log;
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    history::ObjectChange,
+    messages::{PatchPropertiesPayload, PropertiesPatchedPayload, ServerEvent},
+    property_patch,
+    types::AppState,
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+/// Apply path-addressed patches to an object's property document. Malformed paths, values a
+/// known schema refuses and oversized documents are rejected with INVALID_PROPERTY_PATCH, and
+/// nothing is applied; patches that change nothing are not broadcast.
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: PatchPropertiesPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let now = now_ms();
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let outcome = property_patch::validate(&payload.patches).and_then(|()| {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let Some(object) = objects.get_mut(&payload.object_id) else {
+            tracing::debug!(
+                object_id = %payload.object_id,
+                session_id = %sid,
+                "object not found for property patch"
+            );
+            return Ok(false);
+        };
+        let before = object.clone();
+        if !property_patch::apply(object, &payload.patches)? {
+            return Ok(false);
+        }
+        object.last_updated_by = uid;
+        object.last_updated_at = now;
+        let after = object.clone();
+        session.record_edit(uid, "PatchProperties", now, vec![ObjectChange {
+            object_id: payload.object_id,
+            before: Some(before),
+            after: Some(after),
+        }]);
+        Ok(true)
+    });
+
+    match outcome {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(reason) => {
+            tracing::warn!(
+                event_type = "PatchProperties",
+                session_id = %sid,
+                user_id = %uid,
+                object_id = %payload.object_id,
+                reason = %reason,
+                "property patch rejected"
+            );
+            send_error(state, connection_id, "INVALID_PROPERTY_PATCH", format!("PatchProperties rejected: {}", reason));
+            return;
+        }
+    }
+
+    tracing::info!(
+        event_type = "PatchProperties",
+        session_id = %sid,
+        user_id = %uid,
+        object_id = %payload.object_id,
+        patch_count = payload.patches.len(),
+        "properties patched"
+    );
+
+    let json = match serde_json::to_string(&ServerEvent::PropertiesPatched(PropertiesPatchedPayload {
+        object_id: payload.object_id,
+        patches: payload.patches,
+        updated_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "PropertiesPatched",
+                session_id = %sid,
+                user_id = %uid,
+                object_id = %payload.object_id,
+                error = %err,
+                "failed to serialize PropertiesPatched event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "PropertiesPatched",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast PropertiesPatched"
+    );
+}
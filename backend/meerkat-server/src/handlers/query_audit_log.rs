@@ -0,0 +1,59 @@
+use axum::extract::ws::{Message, WebSocket};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    audit::AuditQuery,
+    messages::{AuditLogPayload, ServerEvent},
+    types::AppState,
+};
+
+pub async fn handle(socket: &mut WebSocket, state: &AppState, connection_id: Uuid, payload: AuditQuery) {
+    let Some((sid, _uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let (entries, truncated) = session.lock_audit().query(&payload);
+
+    let entry_count = entries.len();
+    let json = match serde_json::to_string(&ServerEvent::AuditLog(AuditLogPayload { entries, truncated })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "AuditLog",
+                session_id = %sid,
+                connection_id = %connection_id,
+                error = %err,
+                "failed to serialize AuditLog"
+            );
+            return;
+        }
+    };
+
+    if let Err(err) = socket.send(Message::Text(json.into())).await {
+        tracing::warn!(
+            event_type = "AuditLog",
+            session_id = %sid,
+            connection_id = %connection_id,
+            error = %err,
+            "failed to send AuditLog to requesting client"
+        );
+        return;
+    }
+
+    tracing::info!(
+        event_type = "QueryAuditLog",
+        session_id = %sid,
+        entry_count,
+        truncated,
+        "sent AuditLog to requesting client"
+    );
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    history::ObjectChange,
+    messages::{CustomPropertyUpdatedPayload, RemoveCustomPropertyPayload, ServerEvent},
+    types::AppState,
+};
+
+use super::helpers::{broadcast, now_ms};
+
+/// Remove a custom property. Removing a key the object does not have changes nothing and is
+/// not broadcast.
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: RemoveCustomPropertyPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let now = now_ms();
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let Some(object) = objects.get_mut(&payload.object_id) else {
+            tracing::debug!(
+                object_id = %payload.object_id,
+                session_id = %sid,
+                "object not found for custom property removal"
+            );
+            return;
+        };
+        let before = object.clone();
+        if object.custom_properties.remove(&payload.key).is_none() {
+            tracing::debug!(
+                object_id = %payload.object_id,
+                session_id = %sid,
+                key = %payload.key,
+                "custom property already absent"
+            );
+            return;
+        }
+        object.last_updated_by = uid;
+        object.last_updated_at = now;
+        let after = object.clone();
+        session.record_edit(uid, "RemoveCustomProperty", now, vec![ObjectChange {
+            object_id: payload.object_id,
+            before: Some(before),
+            after: Some(after),
+        }]);
+    }
+
+    tracing::info!(
+        event_type = "RemoveCustomProperty",
+        session_id = %sid,
+        user_id = %uid,
+        object_id = %payload.object_id,
+        key = %payload.key,
+        "custom property removed"
+    );
+
+    let json = match serde_json::to_string(&ServerEvent::CustomPropertyUpdated(CustomPropertyUpdatedPayload {
+        object_id: payload.object_id,
+        key: payload.key,
+        value: None,
+        updated_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "CustomPropertyUpdated",
+                session_id = %sid,
+                user_id = %uid,
+                object_id = %payload.object_id,
+                error = %err,
+                "failed to serialize CustomPropertyUpdated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "CustomPropertyUpdated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast CustomPropertyUpdated"
+    );
+}
@@ -0,0 +1,14 @@
+use uuid::Uuid;
+
+use crate::{messages::RemoveModifierPayload, modifiers, types::AppState};
+
+use super::add_modifier::edit_modifiers;
+
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: RemoveModifierPayload) {
+    edit_modifiers(state, connection_id, payload.object_id, "RemoveModifier", |stack| {
+        let index = modifiers::position(stack, payload.modifier_id)?;
+        stack.remove(index);
+        Ok(())
+    })
+    .await;
+}
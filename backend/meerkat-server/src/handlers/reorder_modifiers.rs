@@ -0,0 +1,33 @@
+use uuid::Uuid;
+
+use crate::{
+    messages::ReorderModifiersPayload,
+    modifiers::{self, StackError},
+    types::AppState,
+};
+
+use super::add_modifier::edit_modifiers;
+
+/// Put an object's modifiers in the order given, which must name each of them exactly once.
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: ReorderModifiersPayload) {
+    edit_modifiers(state, connection_id, payload.object_id, "ReorderModifiers", |stack| {
+        let mut remaining = std::mem::take(stack);
+        for modifier_id in &payload.modifier_ids {
+            let index = match modifiers::position(&remaining, *modifier_id) {
+                Ok(index) => index,
+                Err(_) if stack.iter().any(|modifier| modifier.modifier_id == *modifier_id) => {
+                    return Err(StackError::Invalid(format!("modifier {} is listed more than once", modifier_id)));
+                }
+                Err(err) => return Err(err),
+            };
+            stack.push(remaining.remove(index));
+        }
+        match remaining.first() {
+            Some(missing) => {
+                Err(StackError::Invalid(format!("the new order leaves out modifier {}", missing.modifier_id)))
+            }
+            None => Ok(()),
+        }
+    })
+    .await;
+}
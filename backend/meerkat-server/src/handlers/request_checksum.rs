@@ -0,0 +1,58 @@
+use axum::extract::ws::{Message, WebSocket};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    checksum::checksum_payload,
+    messages::{RequestChecksumPayload, ServerEvent},
+    types::AppState,
+};
+
+pub async fn handle(socket: &mut WebSocket, state: &AppState, connection_id: Uuid, payload: RequestChecksumPayload) {
+    let Some((sid, _uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let checksum = checksum_payload(&session, payload.include_objects);
+    let version = checksum.version;
+    let json = match serde_json::to_string(&ServerEvent::StateChecksum(checksum)) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "StateChecksum",
+                session_id = %sid,
+                connection_id = %connection_id,
+                error = %err,
+                "failed to serialize StateChecksum"
+            );
+            return;
+        }
+    };
+
+    if let Err(err) = socket.send(Message::Text(json.into())).await {
+        tracing::warn!(
+            event_type = "StateChecksum",
+            session_id = %sid,
+            connection_id = %connection_id,
+            error = %err,
+            "failed to send StateChecksum to requesting client"
+        );
+        return;
+    }
+
+    tracing::info!(
+        event_type = "RequestChecksum",
+        session_id = %sid,
+        version,
+        include_objects = payload.include_objects,
+        "sent StateChecksum to requesting client"
+    );
+}
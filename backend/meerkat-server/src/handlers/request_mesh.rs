@@ -0,0 +1,74 @@
+use axum::extract::ws::{Message, WebSocket};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    messages::{MeshChunkPayload, RequestMeshPayload, ServerEvent},
+    types::AppState,
+};
+
+use super::helpers::send_error;
+
+/// Send a stored mesh to the requesting client as MeshChunk frames.
+pub async fn handle(socket: &mut WebSocket, state: &AppState, connection_id: Uuid, payload: RequestMeshPayload) {
+    let Some((sid, _uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let chunks = match session.meshes.read() {
+        Ok(meshes) => meshes.get(&payload.hash).map(|mesh| mesh.chunks()),
+        Err(poisoned) => poisoned.into_inner().get(&payload.hash).map(|mesh| mesh.chunks()),
+    };
+    let Some(chunks) = chunks else {
+        send_error(state, connection_id, "MESH_NOT_FOUND", format!("Mesh {} not found", payload.hash));
+        return;
+    };
+
+    let chunk_count = chunks.len() as u32;
+    for (chunk_index, data) in chunks.into_iter().enumerate() {
+        let json = match serde_json::to_string(&ServerEvent::MeshChunk(MeshChunkPayload {
+            hash: payload.hash.clone(),
+            chunk_index: chunk_index as u32,
+            chunk_count,
+            data,
+        })) {
+            Ok(json) => json,
+            Err(err) => {
+                tracing::error!(
+                    event_type = "MeshChunk",
+                    session_id = %sid,
+                    connection_id = %connection_id,
+                    error = %err,
+                    "failed to serialize MeshChunk"
+                );
+                return;
+            }
+        };
+        if let Err(err) = socket.send(Message::Text(json.into())).await {
+            tracing::warn!(
+                event_type = "MeshChunk",
+                session_id = %sid,
+                connection_id = %connection_id,
+                error = %err,
+                "failed to send MeshChunk to requesting client"
+            );
+            return;
+        }
+    }
+
+    tracing::info!(
+        event_type = "RequestMesh",
+        session_id = %sid,
+        hash = %payload.hash,
+        chunk_count,
+        "sent mesh to requesting client"
+    );
+}
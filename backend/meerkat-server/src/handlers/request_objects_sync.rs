@@ -0,0 +1,80 @@
+use axum::extract::ws::{Message, WebSocket};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    messages::{ObjectsSyncedPayload, RequestObjectsSyncPayload, ServerEvent},
+    types::AppState,
+};
+
+pub async fn handle(socket: &mut WebSocket, state: &AppState, connection_id: Uuid, payload: RequestObjectsSyncPayload) {
+    let Some((sid, _uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let synced = {
+        let objects = match session.objects.read() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let mut found = Vec::new();
+        let mut missing = Vec::new();
+        for object_id in &payload.object_ids {
+            match objects.get(object_id) {
+                Some(object) => found.push(object.clone()),
+                None => missing.push(*object_id),
+            }
+        }
+        ObjectsSyncedPayload {
+            version: session.current_version(),
+            objects: found,
+            missing,
+        }
+    };
+
+    let found_count = synced.objects.len();
+    let missing_count = synced.missing.len();
+    let json = match serde_json::to_string(&ServerEvent::ObjectsSynced(synced)) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "ObjectsSynced",
+                session_id = %sid,
+                connection_id = %connection_id,
+                error = %err,
+                "failed to serialize ObjectsSynced"
+            );
+            return;
+        }
+    };
+
+    if let Err(err) = socket.send(Message::Text(json.into())).await {
+        tracing::warn!(
+            event_type = "ObjectsSynced",
+            session_id = %sid,
+            connection_id = %connection_id,
+            error = %err,
+            "failed to send ObjectsSynced to requesting client"
+        );
+        return;
+    }
+
+    tracing::info!(
+        event_type = "RequestObjectsSync",
+        session_id = %sid,
+        found_count,
+        missing_count,
+        "sent ObjectsSynced to requesting client"
+    );
+}
@@ -1,12 +1,20 @@
 use axum::extract::ws::{Message, WebSocket};
+use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
-    messages::{FullStateSyncPayload, ServerEvent},
-    types::AppState,
+    messages::{RequestStateSyncPayload, ServerEvent, StateDeltaPayload},
+    types::{AppState, ChangeKind, SessionHandle},
 };
 
-pub async fn handle(socket: &mut WebSocket, state: &AppState, connection_id: Uuid) {
+use super::helpers::send_state_sync;
+
+pub async fn handle(
+    socket: &mut WebSocket,
+    state: &AppState,
+    connection_id: Uuid,
+    payload: Option<RequestStateSyncPayload>,
+) {
     let Some((sid, uid)) = state
         .connection_meta
         .get(&connection_id)
@@ -15,38 +23,119 @@ pub async fn handle(socket: &mut WebSocket, state: &AppState, connection_id: Uui
         return;
     };
 
-    if let Some(session) = state.sessions.get(&sid) {
-        let sync_json = match serde_json::to_string(&ServerEvent::FullStateSync(FullStateSyncPayload {
-            session: session.session_snapshot(),
-            your_user_id: uid,
-        })) {
-            Ok(json) => json,
-            Err(err) => {
-                tracing::error!(
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    if let Some(since_version) = payload.map(|p| p.since_version) {
+        match build_delta(&session, since_version) {
+            Some(delta) => {
+                let upserted_count = delta.upserted.len();
+                let deleted_count = delta.deleted.len();
+                let delta_json = match serde_json::to_string(&ServerEvent::StateDelta(delta)) {
+                    Ok(json) => json,
+                    Err(err) => {
+                        tracing::error!(
+                            event_type = "StateDelta",
+                            session_id = %sid,
+                            connection_id = %connection_id,
+                            error = %err,
+                            "failed to serialize StateDelta"
+                        );
+                        return;
+                    }
+                };
+                if let Err(err) = socket.send(Message::Text(delta_json.into())).await {
+                    tracing::warn!(
+                        event_type = "StateDelta",
+                        session_id = %sid,
+                        connection_id = %connection_id,
+                        error = %err,
+                        "failed to send StateDelta to requesting client"
+                    );
+                    return;
+                }
+                tracing::info!(
                     event_type = "RequestStateSync",
                     session_id = %sid,
-                    connection_id = %connection_id,
-                    error = %err,
-                    "failed to serialize FullStateSync"
+                    since_version,
+                    upserted_count,
+                    deleted_count,
+                    "sent StateDelta to requesting client"
                 );
                 return;
             }
-        };
-
-        if let Err(err) = socket.send(Message::Text(sync_json.into())).await {
-            tracing::warn!(
-                event_type = "RequestStateSync",
-                session_id = %sid,
-                connection_id = %connection_id,
-                error = %err,
-                "failed to send FullStateSync to requesting client"
-            );
+            None => {
+                tracing::info!(
+                    event_type = "RequestStateSync",
+                    session_id = %sid,
+                    since_version,
+                    "change history unavailable for requested version; falling back to full sync"
+                );
+            }
         }
+    }
 
+    if send_state_sync(socket, state, connection_id, &session, uid).await {
         tracing::info!(
             event_type = "RequestStateSync",
             session_id = %sid,
-            "sent FullStateSync to requesting client"
+            connection_id = %connection_id,
+            "sent state sync to requesting client"
         );
     }
 }
+
+// Objects, collections and materials are read before the change log (same order as writers)
+// so the delta and its to_version describe the same moment in the session.
+fn build_delta(session: &SessionHandle, since_version: u64) -> Option<StateDeltaPayload> {
+    let objects = match session.objects.read() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            tracing::warn!("Session objects lock poisoned, recovering");
+            poisoned.into_inner()
+        }
+    };
+    let collections = match session.collections.read() {
+        Ok(guard) => guard.clone(),
+        Err(poisoned) => {
+            tracing::warn!("Session collections lock poisoned, recovering");
+            poisoned.into_inner().clone()
+        }
+    };
+    let materials = match session.materials.read() {
+        Ok(guard) => guard.clone(),
+        Err(poisoned) => {
+            tracing::warn!("Session materials lock poisoned, recovering");
+            poisoned.into_inner().clone()
+        }
+    };
+    let changes = match session.changes.read() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            tracing::warn!("Session change log lock poisoned, recovering");
+            poisoned.into_inner()
+        }
+    };
+
+    let net = changes.changes_since(since_version)?;
+    let mut upserted = Vec::new();
+    let mut deleted = Vec::new();
+    for (object_id, kind) in net {
+        match (kind, objects.get(&object_id)) {
+            (ChangeKind::Upserted, Some(object)) => upserted.push(object.clone()),
+            // An upsert whose object is gone means a later delete was trimmed; treat it as a tombstone.
+            (ChangeKind::Upserted, None) | (ChangeKind::Deleted, _) => deleted.push(object_id),
+        }
+    }
+
+    Some(StateDeltaPayload {
+        from_version: since_version,
+        to_version: changes.version,
+        upserted,
+        deleted,
+        collections,
+        materials,
+    })
+}
@@ -0,0 +1,120 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    history,
+    messages::{CheckpointInfo, CheckpointRestoredPayload, RestoreCheckpointPayload, ServerEvent},
+    types::AppState,
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+/// Roll the session back to a checkpoint. Only objects that differ from the checkpoint are
+/// touched, and the restore is recorded as a single undoable step for the user who ran it.
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: RestoreCheckpointPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let now = now_ms();
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let restored = {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let checkpoints = match session.checkpoints.read() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session checkpoints lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        checkpoints
+            .iter()
+            .find(|c| c.checkpoint_id == payload.checkpoint_id)
+            .map(|checkpoint| {
+                let mut changes = history::changes_between(&objects, &checkpoint.objects);
+                // Collections deleted since the checkpoint was taken do not come back with it.
+                let collections = match session.collections.read() {
+                    Ok(guard) => guard,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+                for object in changes.iter_mut().filter_map(|change| change.after.as_mut()) {
+                    history::retain_existing_collections(object, &collections);
+                }
+                drop(collections);
+                changes.retain(|change| change.before != change.after);
+                history::apply_changes(&mut objects, &changes);
+                let touched: Vec<Uuid> = changes.iter().map(|c| c.object_id).collect();
+                session.discard_pending_transforms(&touched);
+                let diff = history::object_diff(&changes);
+                let version = if changes.is_empty() {
+                    session.current_version()
+                } else {
+                    session.record_edit(uid, "RestoreCheckpoint", now, changes)
+                };
+                (CheckpointInfo::from(checkpoint), diff, version)
+            })
+    };
+
+    let Some((checkpoint, diff, version)) = restored else {
+        send_error(
+            state,
+            connection_id,
+            "CHECKPOINT_NOT_FOUND",
+            format!("No checkpoint {} in session {}", payload.checkpoint_id, sid),
+        );
+        return;
+    };
+
+    tracing::info!(
+        event_type = "RestoreCheckpoint",
+        session_id = %sid,
+        user_id = %uid,
+        checkpoint_id = %checkpoint.checkpoint_id,
+        created_count = diff.created.len(),
+        updated_count = diff.updated.len(),
+        deleted_count = diff.deleted.len(),
+        version,
+        "checkpoint restored"
+    );
+
+    let json = match serde_json::to_string(&ServerEvent::CheckpointRestored(CheckpointRestoredPayload {
+        checkpoint,
+        restored_by: uid,
+        diff,
+        version,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "CheckpointRestored",
+                session_id = %sid,
+                user_id = %uid,
+                error = %err,
+                "failed to serialize CheckpointRestored event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "CheckpointRestored",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast CheckpointRestored"
+    );
+}
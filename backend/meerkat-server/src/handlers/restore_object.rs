@@ -0,0 +1,114 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    history::{retain_existing_collections, ObjectChange},
+    messages::{ObjectCreatedPayload, RestoreObjectPayload, ServerEvent},
+    trash::expires_at,
+    types::AppState,
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: RestoreObjectPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+    let now = now_ms();
+
+    let restored = {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let trashed = session
+            .lock_trash()
+            .remove(&payload.object_id)
+            .filter(|t| expires_at(t.deleted_at, state.config.trash_retention) > now);
+        match trashed {
+            Some(trashed) if !objects.contains_key(&payload.object_id) => {
+                let mut object = trashed.object;
+                let collections = match session.collections.read() {
+                    Ok(guard) => guard,
+                    Err(poisoned) => poisoned.into_inner(),
+                };
+                retain_existing_collections(&mut object, &collections);
+                drop(collections);
+                object.last_updated_by = uid;
+                object.last_updated_at = now;
+                objects.insert(payload.object_id, object.clone());
+                session.record_edit(uid, "RestoreObject", now, vec![ObjectChange {
+                    object_id: payload.object_id,
+                    before: None,
+                    after: Some(object.clone()),
+                }]);
+                Some(object)
+            }
+            _ => None,
+        }
+    };
+
+    let Some(object) = restored else {
+        tracing::debug!(
+            event_type = "RestoreObject",
+            session_id = %sid,
+            user_id = %uid,
+            object_id = %payload.object_id,
+            "object not in trash"
+        );
+        send_error(
+            state,
+            connection_id,
+            "TRASH_ITEM_NOT_FOUND",
+            format!("Object {} is not in the trash (never deleted, already restored, or expired)", payload.object_id),
+        );
+        return;
+    };
+
+    tracing::info!(
+        event_type = "RestoreObject",
+        session_id = %sid,
+        user_id = %uid,
+        object_id = %payload.object_id,
+        "object restored from trash"
+    );
+
+    let json = match serde_json::to_string(&ServerEvent::ObjectCreated(ObjectCreatedPayload {
+        created_by: object.created_by,
+        restored_by: Some(uid),
+        object,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "ObjectCreated",
+                session_id = %sid,
+                user_id = %uid,
+                object_id = %payload.object_id,
+                error = %err,
+                "failed to serialize ObjectCreated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "ObjectCreated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast restored ObjectCreated"
+    );
+}
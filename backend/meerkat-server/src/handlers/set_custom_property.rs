@@ -0,0 +1,122 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    custom_properties,
+    history::ObjectChange,
+    messages::{CustomPropertyUpdatedPayload, ServerEvent, SetCustomPropertyPayload},
+    types::{AppState, MAX_CUSTOM_PROPERTIES},
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+/// Add or replace a custom property. Bad keys and oversized values are refused with
+/// INVALID_CUSTOM_PROPERTY; a new key past MAX_CUSTOM_PROPERTIES with CUSTOM_PROPERTY_LIMIT.
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: SetCustomPropertyPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let now = now_ms();
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    if let Err(reason) = custom_properties::validate(&payload.key, &payload.value) {
+        tracing::warn!(
+            event_type = "SetCustomProperty",
+            session_id = %sid,
+            user_id = %uid,
+            object_id = %payload.object_id,
+            reason = %reason,
+            "custom property rejected"
+        );
+        send_error(state, connection_id, "INVALID_CUSTOM_PROPERTY", format!("SetCustomProperty rejected: {}", reason));
+        return;
+    }
+
+    let outcome = {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let Some(object) = objects.get_mut(&payload.object_id) else {
+            tracing::debug!(
+                object_id = %payload.object_id,
+                session_id = %sid,
+                "object not found for custom property update"
+            );
+            return;
+        };
+        if !object.custom_properties.contains_key(&payload.key) && object.custom_properties.len() >= MAX_CUSTOM_PROPERTIES {
+            Err(())
+        } else {
+            let before = object.clone();
+            object.custom_properties.insert(payload.key.clone(), payload.value.clone());
+            object.last_updated_by = uid;
+            object.last_updated_at = now;
+            let after = object.clone();
+            session.record_edit(uid, "SetCustomProperty", now, vec![ObjectChange {
+                object_id: payload.object_id,
+                before: Some(before),
+                after: Some(after),
+            }]);
+            Ok(())
+        }
+    };
+
+    if outcome.is_err() {
+        send_error(
+            state,
+            connection_id,
+            "CUSTOM_PROPERTY_LIMIT",
+            format!("Object {} already has {} custom properties", payload.object_id, MAX_CUSTOM_PROPERTIES),
+        );
+        return;
+    }
+
+    tracing::info!(
+        event_type = "SetCustomProperty",
+        session_id = %sid,
+        user_id = %uid,
+        object_id = %payload.object_id,
+        key = %payload.key,
+        "custom property set"
+    );
+
+    let json = match serde_json::to_string(&ServerEvent::CustomPropertyUpdated(CustomPropertyUpdatedPayload {
+        object_id: payload.object_id,
+        key: payload.key,
+        value: Some(payload.value),
+        updated_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "CustomPropertyUpdated",
+                session_id = %sid,
+                user_id = %uid,
+                object_id = %payload.object_id,
+                error = %err,
+                "failed to serialize CustomPropertyUpdated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "CustomPropertyUpdated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast CustomPropertyUpdated"
+    );
+}
@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    history::ObjectChange,
+    messages::{ObjectCollectionsUpdatedPayload, ServerEvent, SetObjectCollectionsPayload},
+    types::AppState,
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+/// Move an object between collections. Every listed collection must exist; repeats are dropped.
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: SetObjectCollectionsPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let now = now_ms();
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let mut collection_ids = Vec::with_capacity(payload.collection_ids.len());
+    for collection_id in payload.collection_ids {
+        if !collection_ids.contains(&collection_id) {
+            collection_ids.push(collection_id);
+        }
+    }
+
+    let outcome = {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let missing = {
+            let collections = match session.collections.read() {
+                Ok(guard) => guard,
+                Err(poisoned) => {
+                    tracing::warn!("Session collections lock poisoned, recovering");
+                    poisoned.into_inner()
+                }
+            };
+            collection_ids.iter().find(|id| !collections.contains_key(id)).copied()
+        };
+        let Some(object) = objects.get_mut(&payload.object_id) else {
+            tracing::debug!(
+                object_id = %payload.object_id,
+                session_id = %sid,
+                "object not found for collection update"
+            );
+            return;
+        };
+        match missing {
+            Some(collection_id) => Err(collection_id),
+            None => {
+                let before = object.clone();
+                object.collections = collection_ids.clone();
+                object.last_updated_by = uid;
+                object.last_updated_at = now;
+                let after = object.clone();
+                session.record_edit(uid, "SetObjectCollections", now, vec![ObjectChange {
+                    object_id: payload.object_id,
+                    before: Some(before),
+                    after: Some(after),
+                }]);
+                Ok(())
+            }
+        }
+    };
+
+    if let Err(collection_id) = outcome {
+        send_error(
+            state,
+            connection_id,
+            "COLLECTION_NOT_FOUND",
+            format!("Collection {} not found", collection_id),
+        );
+        return;
+    }
+
+    tracing::info!(
+        event_type = "SetObjectCollections",
+        session_id = %sid,
+        user_id = %uid,
+        object_id = %payload.object_id,
+        collection_count = collection_ids.len(),
+        "object collections updated"
+    );
+
+    let json = match serde_json::to_string(&ServerEvent::ObjectCollectionsUpdated(ObjectCollectionsUpdatedPayload {
+        object_id: payload.object_id,
+        collection_ids,
+        updated_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "ObjectCollectionsUpdated",
+                session_id = %sid,
+                user_id = %uid,
+                object_id = %payload.object_id,
+                error = %err,
+                "failed to serialize ObjectCollectionsUpdated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "ObjectCollectionsUpdated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast ObjectCollectionsUpdated"
+    );
+}
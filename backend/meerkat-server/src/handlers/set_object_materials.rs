@@ -0,0 +1,127 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    history::ObjectChange,
+    messages::{ObjectMaterialsUpdatedPayload, ServerEvent, SetObjectMaterialsPayload},
+    types::{AppState, MAX_MATERIAL_SLOTS},
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+/// Replace an object's material slots. Every assigned material must exist; empty slots are kept.
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: SetObjectMaterialsPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let now = now_ms();
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    if payload.material_slots.len() > MAX_MATERIAL_SLOTS {
+        send_error(
+            state,
+            connection_id,
+            "INVALID_MATERIAL_SLOTS",
+            format!("Objects may have at most {} material slots", MAX_MATERIAL_SLOTS),
+        );
+        return;
+    }
+
+    let outcome = {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let missing = {
+            let materials = match session.materials.read() {
+                Ok(guard) => guard,
+                Err(poisoned) => {
+                    tracing::warn!("Session materials lock poisoned, recovering");
+                    poisoned.into_inner()
+                }
+            };
+            payload.material_slots.iter().flatten().find(|id| !materials.contains_key(id)).copied()
+        };
+        let Some(object) = objects.get_mut(&payload.object_id) else {
+            tracing::debug!(
+                object_id = %payload.object_id,
+                session_id = %sid,
+                "object not found for material update"
+            );
+            return;
+        };
+        match missing {
+            Some(material_id) => Err(material_id),
+            None => {
+                let before = object.clone();
+                object.material_slots = payload.material_slots.clone();
+                object.last_updated_by = uid;
+                object.last_updated_at = now;
+                let after = object.clone();
+                session.record_edit(uid, "SetObjectMaterials", now, vec![ObjectChange {
+                    object_id: payload.object_id,
+                    before: Some(before),
+                    after: Some(after),
+                }]);
+                Ok(())
+            }
+        }
+    };
+
+    if let Err(material_id) = outcome {
+        send_error(
+            state,
+            connection_id,
+            "MATERIAL_NOT_FOUND",
+            format!("Material {} not found", material_id),
+        );
+        return;
+    }
+
+    tracing::info!(
+        event_type = "SetObjectMaterials",
+        session_id = %sid,
+        user_id = %uid,
+        object_id = %payload.object_id,
+        slot_count = payload.material_slots.len(),
+        "object materials updated"
+    );
+
+    let json = match serde_json::to_string(&ServerEvent::ObjectMaterialsUpdated(ObjectMaterialsUpdatedPayload {
+        object_id: payload.object_id,
+        material_slots: payload.material_slots,
+        updated_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "ObjectMaterialsUpdated",
+                session_id = %sid,
+                user_id = %uid,
+                object_id = %payload.object_id,
+                error = %err,
+                "failed to serialize ObjectMaterialsUpdated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "ObjectMaterialsUpdated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast ObjectMaterialsUpdated"
+    );
+}
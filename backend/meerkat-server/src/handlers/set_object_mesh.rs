@@ -0,0 +1,148 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    history::ObjectChange,
+    messages::{ObjectMeshUpdatedPayload, ServerEvent, SetObjectMeshPayload},
+    types::{AppState, MeshRef, ObjectType},
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+enum Refused {
+    NotFound(String),
+    NotGeometry,
+    Conflict(u64),
+}
+
+/// Point an object at an uploaded mesh, or back at its primitive. Each change bumps the
+/// object's mesh version; an edit made against an older version is refused so it cannot
+/// silently replace a peer's newer geometry.
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: SetObjectMeshPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let now = now_ms();
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let outcome = {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let stored = payload.hash.as_ref().is_none_or(|hash| match session.meshes.read() {
+            Ok(meshes) => meshes.contains_key(hash),
+            Err(poisoned) => poisoned.into_inner().contains_key(hash),
+        });
+        let Some(object) = objects.get_mut(&payload.object_id) else {
+            tracing::debug!(
+                object_id = %payload.object_id,
+                session_id = %sid,
+                "object not found for mesh update"
+            );
+            return;
+        };
+        let current = object.mesh_version;
+        if let Some(hash) = payload.hash.as_ref().filter(|_| !stored) {
+            Err(Refused::NotFound(hash.clone()))
+        } else if payload.hash.is_some()
+            && matches!(
+                object.object_type,
+                ObjectType::Camera
+                    | ObjectType::PointLight
+                    | ObjectType::SpotLight
+                    | ObjectType::AreaLight
+                    | ObjectType::SunLight
+            )
+        {
+            Err(Refused::NotGeometry)
+        } else if payload.base_version.unwrap_or(0) != current {
+            Err(Refused::Conflict(current))
+        } else if object.mesh.as_ref().map(|mesh| &mesh.hash) == payload.hash.as_ref() {
+            Ok(None)
+        } else {
+            let before = object.clone();
+            object.mesh = payload.hash.clone().map(|hash| MeshRef { hash });
+            object.mesh_version = current + 1;
+            object.last_updated_by = uid;
+            object.last_updated_at = now;
+            let after = object.clone();
+            session.record_edit(uid, "SetObjectMesh", now, vec![ObjectChange {
+                object_id: payload.object_id,
+                before: Some(before),
+                after: Some(after),
+            }]);
+            Ok(Some((object.mesh.clone(), object.mesh_version)))
+        }
+    };
+
+    let (mesh, mesh_version) = match outcome {
+        Ok(Some(updated)) => updated,
+        Ok(None) => return,
+        Err(Refused::NotFound(hash)) => {
+            send_error(state, connection_id, "MESH_NOT_FOUND", format!("Mesh {} not found", hash));
+            return;
+        }
+        Err(Refused::NotGeometry) => {
+            send_error(state, connection_id, "INVALID_MESH", "Cameras and lights cannot have mesh data".to_string());
+            return;
+        }
+        Err(Refused::Conflict(current)) => {
+            send_error(
+                state,
+                connection_id,
+                "MESH_VERSION_CONFLICT",
+                format!("Object {} is at mesh version {}", payload.object_id, current),
+            );
+            return;
+        }
+    };
+
+    tracing::info!(
+        event_type = "SetObjectMesh",
+        session_id = %sid,
+        user_id = %uid,
+        object_id = %payload.object_id,
+        mesh_version,
+        "object mesh updated"
+    );
+
+    let json = match serde_json::to_string(&ServerEvent::ObjectMeshUpdated(ObjectMeshUpdatedPayload {
+        object_id: payload.object_id,
+        mesh,
+        mesh_version,
+        updated_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "ObjectMeshUpdated",
+                session_id = %sid,
+                user_id = %uid,
+                object_id = %payload.object_id,
+                error = %err,
+                "failed to serialize ObjectMeshUpdated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "ObjectMeshUpdated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast ObjectMeshUpdated"
+    );
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    hierarchy,
+    history::ObjectChange,
+    messages::{ParentUpdatedPayload, ServerEvent, SetParentPayload},
+    types::AppState,
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+/// Parent or unparent an object. A parent that does not exist, or that would put the object
+/// inside its own subtree, is refused with INVALID_PARENT and nothing changes.
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: SetParentPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let now = now_ms();
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let outcome = {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let Some(before) = objects.get(&payload.object_id).cloned() else {
+            tracing::debug!(
+                object_id = %payload.object_id,
+                session_id = %sid,
+                "object not found for parent update"
+            );
+            return;
+        };
+        hierarchy::validate_parent(&*objects, payload.object_id, payload.parent_id).map(|()| {
+            let transform = match &payload.transform {
+                Some(transform) => transform.clone(),
+                None => hierarchy::local_transform(&*objects, &hierarchy::world_matrix(&*objects, &before), payload.parent_id),
+            };
+            let mut after = before.clone();
+            after.parent_id = payload.parent_id;
+            after.transform = transform.clone();
+            after.last_updated_by = uid;
+            after.last_updated_at = now;
+            objects.insert(payload.object_id, after.clone());
+            session.record_edit(uid, "SetParent", now, vec![ObjectChange {
+                object_id: payload.object_id,
+                before: Some(before),
+                after: Some(after),
+            }]);
+            session.discard_pending_transforms(&[payload.object_id]);
+            transform
+        })
+    };
+
+    let transform = match outcome {
+        Ok(transform) => transform,
+        Err(reason) => {
+            tracing::warn!(
+                event_type = "SetParent",
+                session_id = %sid,
+                user_id = %uid,
+                object_id = %payload.object_id,
+                reason = %reason,
+                "parent rejected"
+            );
+            send_error(state, connection_id, "INVALID_PARENT", format!("SetParent rejected: {}", reason));
+            return;
+        }
+    };
+
+    tracing::info!(
+        event_type = "SetParent",
+        session_id = %sid,
+        user_id = %uid,
+        object_id = %payload.object_id,
+        parent_id = ?payload.parent_id,
+        "parent updated"
+    );
+
+    let json = match serde_json::to_string(&ServerEvent::ParentUpdated(ParentUpdatedPayload {
+        object_id: payload.object_id,
+        parent_id: payload.parent_id,
+        transform,
+        updated_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "ParentUpdated",
+                session_id = %sid,
+                user_id = %uid,
+                object_id = %payload.object_id,
+                error = %err,
+                "failed to serialize ParentUpdated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "ParentUpdated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast ParentUpdated"
+    );
+}
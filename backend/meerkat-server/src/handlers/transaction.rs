@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    hierarchy::{self, Scene},
+    messages::{
+        DeleteObjectPayload, ServerEvent, SetParentPayload, TransactionAppliedPayload, TransactionOp, TransactionPayload,
+    },
+    history::ObjectChange,
+    types::{AppState, SceneObject},
+    xform::IDENTITY,
+};
+
+use super::helpers::{broadcast, created_object, now_ms, send_error};
+
+/// Upper bound on operations in one transaction; the whole batch is applied under the
+/// session's objects write lock, so this also bounds how long other edits can be held up.
+pub const MAX_TRANSACTION_OPS: usize = 1_000;
+
+impl TransactionOp {
+    pub fn object_id(&self) -> Uuid {
+        match self {
+            TransactionOp::CreateObject(p) => p.object_id,
+            TransactionOp::DeleteObject(p) => p.object_id,
+            TransactionOp::UpdateTransform(p) => p.object_id,
+            TransactionOp::UpdateProperties(p) => p.object_id,
+            TransactionOp::UpdateName(p) => p.object_id,
+            TransactionOp::SetParent(p) => p.object_id,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransactionOp::CreateObject(_) => "CreateObject",
+            TransactionOp::DeleteObject(_) => "DeleteObject",
+            TransactionOp::UpdateTransform(_) => "UpdateTransform",
+            TransactionOp::UpdateProperties(_) => "UpdateProperties",
+            TransactionOp::UpdateName(_) => "UpdateName",
+            TransactionOp::SetParent(_) => "SetParent",
+        }
+    }
+}
+
+/// Resulting state of every object a transaction touches, in first-touch order.
+/// `None` means the object ends up deleted.
+pub(crate) type Staged = Vec<(Uuid, Option<SceneObject>)>;
+
+/// The session's objects as a transaction sees them part way through: staged results over
+/// the objects it has not touched yet.
+struct StagedScene<'a> {
+    objects: &'a HashMap<Uuid, SceneObject>,
+    staged: Staged,
+    index: HashMap<Uuid, usize>,
+}
+
+impl StagedScene<'_> {
+    fn slot(&mut self, object_id: Uuid) -> &mut Option<SceneObject> {
+        let slot = match self.index.get(&object_id) {
+            Some(&slot) => slot,
+            None => {
+                self.staged.push((object_id, self.objects.get(&object_id).cloned()));
+                self.index.insert(object_id, self.staged.len() - 1);
+                self.staged.len() - 1
+            }
+        };
+        &mut self.staged[slot].1
+    }
+}
+
+impl Scene for StagedScene<'_> {
+    fn object(&self, object_id: Uuid) -> Option<&SceneObject> {
+        match self.index.get(&object_id) {
+            Some(&slot) => self.staged[slot].1.as_ref(),
+            None => self.objects.get(&object_id),
+        }
+    }
+
+    fn for_each_object(&self, f: &mut dyn FnMut(&SceneObject)) {
+        self.objects.values().filter(|object| !self.index.contains_key(&object.object_id)).for_each(&mut *f);
+        self.staged.iter().filter_map(|(_, object)| object.as_ref()).for_each(f);
+    }
+}
+
+/// Validate `operations` in order against `objects` without mutating it. Later operations see
+/// the effect of earlier ones, so a transaction may create an object and then move it.
+/// Returns the staged end state and the operations as applied (see TransactionAppliedPayload),
+/// or a message naming the first operation that failed.
+pub(crate) fn stage_operations(
+    objects: &HashMap<Uuid, SceneObject>,
+    operations: &[TransactionOp],
+    uid: Uuid,
+    now: u64,
+) -> Result<(Staged, Vec<TransactionOp>), String> {
+    let mut scene = StagedScene { objects, staged: Vec::new(), index: HashMap::new() };
+    let mut applied = Vec::with_capacity(operations.len());
+
+    for (i, op) in operations.iter().enumerate() {
+        let object_id = op.object_id();
+        let fail = |reason: String| format!("operation {} ({}): {}", i, op.name(), reason);
+        match (op, scene.object(object_id)) {
+            (TransactionOp::CreateObject(_), Some(_)) => {
+                return Err(fail(format!("object_id {} already exists", object_id)));
+            }
+            (TransactionOp::CreateObject(_), None) | (_, Some(_)) => {}
+            (_, None) => return Err(fail(format!("object {} not found", object_id))),
+        }
+
+        match op {
+            TransactionOp::CreateObject(p) => {
+                hierarchy::validate_parent(&scene, object_id, p.parent_id).map_err(fail)?;
+                *scene.slot(object_id) = Some(created_object(p, uid, now));
+            }
+            TransactionOp::DeleteObject(p) => {
+                let cascade = hierarchy::delete_cascade(&scene, object_id, p.delete_children);
+                for (child_id, parent_id, transform) in cascade.reparented {
+                    if let Some(child) = scene.slot(child_id) {
+                        let child = touch(child, uid, now);
+                        child.parent_id = parent_id;
+                        child.transform = transform.clone();
+                    }
+                    applied.push(TransactionOp::SetParent(SetParentPayload {
+                        object_id: child_id,
+                        parent_id,
+                        transform: Some(transform),
+                    }));
+                }
+                for descendant in cascade.deleted {
+                    *scene.slot(descendant) = None;
+                    applied.push(TransactionOp::DeleteObject(DeleteObjectPayload {
+                        object_id: descendant,
+                        delete_children: false,
+                    }));
+                }
+                *scene.slot(object_id) = None;
+            }
+            TransactionOp::SetParent(p) => {
+                hierarchy::validate_parent(&scene, object_id, p.parent_id).map_err(fail)?;
+                let transform = match &p.transform {
+                    Some(transform) => transform.clone(),
+                    None => {
+                        let world = scene.object(object_id).map_or(IDENTITY, |obj| hierarchy::world_matrix(&scene, obj));
+                        hierarchy::local_transform(&scene, &world, p.parent_id)
+                    }
+                };
+                if let Some(obj) = scene.slot(object_id) {
+                    let obj = touch(obj, uid, now);
+                    obj.parent_id = p.parent_id;
+                    obj.transform = transform.clone();
+                }
+                applied.push(TransactionOp::SetParent(SetParentPayload {
+                    object_id,
+                    parent_id: p.parent_id,
+                    transform: Some(transform),
+                }));
+                continue;
+            }
+            TransactionOp::UpdateTransform(p) => {
+                if let Some(obj) = scene.slot(object_id) {
+                    touch(obj, uid, now).transform = p.transform.clone();
+                }
+            }
+            TransactionOp::UpdateProperties(p) => {
+                if let Some(obj) = scene.slot(object_id) {
+                    touch(obj, uid, now).properties = Some(p.properties.clone());
+                }
+            }
+            TransactionOp::UpdateName(p) => {
+                if let Some(obj) = scene.slot(object_id) {
+                    touch(obj, uid, now).name = p.name.clone();
+                }
+            }
+        }
+        applied.push(op.clone());
+    }
+
+    Ok((scene.staged, applied))
+}
+
+fn touch(object: &mut SceneObject, uid: Uuid, now: u64) -> &mut SceneObject {
+    object.last_updated_by = uid;
+    object.last_updated_at = now;
+    object
+}
+
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: TransactionPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let now = now_ms();
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let outcome = if payload.operations.len() > MAX_TRANSACTION_OPS {
+        Err(format!(
+            "transaction has {} operations, limit is {}",
+            payload.operations.len(),
+            MAX_TRANSACTION_OPS
+        ))
+    } else {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        stage_operations(&objects, &payload.operations, uid, now).map(|(staged, applied)| {
+            // Only queued transforms this transaction overwrote or deleted are stale; one for an
+            // object that was merely renamed still has to reach peers.
+            let overwritten: Vec<Uuid> = applied
+                .iter()
+                .filter(|op| {
+                    matches!(
+                        op,
+                        TransactionOp::UpdateTransform(_) | TransactionOp::SetParent(_) | TransactionOp::DeleteObject(_)
+                    )
+                })
+                .map(TransactionOp::object_id)
+                .collect();
+            let changes: Vec<ObjectChange> = staged
+                .into_iter()
+                .map(|(object_id, after)| {
+                    let before = match &after {
+                        Some(object) => objects.insert(object_id, object.clone()),
+                        None => objects.remove(&object_id),
+                    };
+                    ObjectChange { object_id, before, after }
+                })
+                .collect();
+            let deleted: Vec<SceneObject> = changes
+                .iter()
+                .filter(|change| change.after.is_none())
+                .filter_map(|change| change.before.clone())
+                .collect();
+            let version = session.record_edit(uid, "Transaction", now, changes);
+            for object in deleted {
+                session.move_to_trash(object, uid, now);
+            }
+            session.discard_pending_transforms(&overwritten);
+            (version, applied)
+        })
+    };
+
+    let (version, operations) = match outcome {
+        Ok(outcome) => outcome,
+        Err(reason) => {
+            tracing::warn!(
+                event_type = "Transaction",
+                session_id = %sid,
+                user_id = %uid,
+                transaction_id = %payload.transaction_id,
+                reason = %reason,
+                "transaction rejected"
+            );
+            send_error(
+                state,
+                connection_id,
+                "TRANSACTION_REJECTED",
+                format!("Transaction {} rejected, nothing applied: {}", payload.transaction_id, reason),
+            );
+            return;
+        }
+    };
+
+    tracing::info!(
+        event_type = "Transaction",
+        session_id = %sid,
+        user_id = %uid,
+        transaction_id = %payload.transaction_id,
+        operation_count = operations.len(),
+        version,
+        "transaction applied"
+    );
+
+    let json = match serde_json::to_string(&ServerEvent::TransactionApplied(TransactionAppliedPayload {
+        transaction_id: payload.transaction_id,
+        operations,
+        applied_by: uid,
+        version,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "TransactionApplied",
+                session_id = %sid,
+                user_id = %uid,
+                transaction_id = %payload.transaction_id,
+                error = %err,
+                "failed to serialize TransactionApplied event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "TransactionApplied",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast TransactionApplied"
+    );
+}
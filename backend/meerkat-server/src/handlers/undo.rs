@@ -0,0 +1,183 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    history::{self, HistoryEntry},
+    journal::JournalOp,
+    messages::{
+        CollectionCreatedPayload, CollectionDeletedPayload, HistoryAction, HistoryAppliedPayload, ServerEvent,
+    },
+    types::AppState,
+};
+
+use super::helpers::{broadcast, now_ms, send_error};
+
+/// Undo or redo the sender's most recent step. Objects someone else has touched since are
+/// skipped and reported in `conflicts`; if every object conflicts the step is dropped and the
+/// sender gets an UNDO_CONFLICT error instead of a broadcast. Collections the step brings
+/// back or removes are announced with CollectionCreated before, or CollectionDeleted after,
+/// the HistoryApplied.
+pub async fn handle(state: &AppState, connection_id: Uuid, action: HistoryAction) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let now = now_ms();
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+    let event_type = match action {
+        HistoryAction::Undo => "Undo",
+        HistoryAction::Redo => "Redo",
+    };
+
+    let outcome = {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session objects lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        let mut collections = match session.collections.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session collections lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        // History is locked only around take/push_inverse: auditing reads `users`, which
+        // disconnect cleanup holds while it takes `history`.
+        let taken = session.lock_history().take(uid, action);
+        taken.map(|entry| {
+            let reverted = history::revert(&mut objects, &mut collections, &entry);
+            for change in &reverted.collections {
+                session.append_journal(|| JournalOp::Collection {
+                    collection_id: change.collection_id,
+                    after: change.after.clone(),
+                });
+            }
+            let version = session.record_object_changes(&reverted.inverse);
+            session.record_audit(uid, event_type, now, &reverted.inverse);
+            let touched: Vec<Uuid> = reverted.inverse.iter().map(|c| c.object_id).collect();
+            session.discard_pending_transforms(&touched);
+            if !reverted.is_empty() {
+                session.lock_history().push_inverse(uid, action, HistoryEntry {
+                    event_type,
+                    at_ms: now,
+                    changes: reverted.inverse.clone(),
+                    collections: reverted.collections.clone(),
+                });
+            }
+            (reverted, version)
+        })
+    };
+
+    let Some((reverted, version)) = outcome else {
+        tracing::debug!(event_type, session_id = %sid, user_id = %uid, "nothing to apply");
+        let code = match action {
+            HistoryAction::Undo => "NOTHING_TO_UNDO",
+            HistoryAction::Redo => "NOTHING_TO_REDO",
+        };
+        send_error(state, connection_id, code, format!("{} has no step to apply", event_type));
+        return;
+    };
+
+    if reverted.is_empty() {
+        tracing::info!(
+            event_type,
+            session_id = %sid,
+            user_id = %uid,
+            conflict_count = reverted.conflicts.len(),
+            "step dropped, every object was changed by someone else"
+        );
+        send_error(
+            state,
+            connection_id,
+            "UNDO_CONFLICT",
+            format!(
+                "{} skipped: {} object(s) were changed by other users since",
+                event_type,
+                reverted.conflicts.len()
+            ),
+        );
+        return;
+    }
+
+    tracing::info!(
+        event_type,
+        session_id = %sid,
+        user_id = %uid,
+        object_count = reverted.inverse.len(),
+        conflict_count = reverted.conflicts.len(),
+        version,
+        "history step applied"
+    );
+
+    let mut restored_collections = Vec::new();
+    let mut removed_collections = Vec::new();
+    for change in &reverted.collections {
+        let (event, list) = match &change.after {
+            Some(collection) => (
+                ServerEvent::CollectionCreated(CollectionCreatedPayload { collection: collection.clone(), created_by: uid }),
+                &mut restored_collections,
+            ),
+            None => (
+                ServerEvent::CollectionDeleted(CollectionDeletedPayload {
+                    collection_id: change.collection_id,
+                    deleted_by: uid,
+                }),
+                &mut removed_collections,
+            ),
+        };
+        match serde_json::to_string(&event) {
+            Ok(json) => list.push(json),
+            Err(err) => tracing::error!(
+                event_type,
+                session_id = %sid,
+                collection_id = %change.collection_id,
+                error = %err,
+                "failed to serialize collection event"
+            ),
+        }
+    }
+
+    let json = match serde_json::to_string(&ServerEvent::HistoryApplied(HistoryAppliedPayload {
+        user_id: uid,
+        action,
+        diff: history::object_diff(&reverted.inverse),
+        conflicts: reverted.conflicts,
+        version,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "HistoryApplied",
+                session_id = %sid,
+                user_id = %uid,
+                error = %err,
+                "failed to serialize HistoryApplied event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    for collection_json in &restored_collections {
+        broadcast(state, &sid, collection_json, None);
+    }
+    let count = broadcast(state, &sid, &json, None);
+    for collection_json in &removed_collections {
+        broadcast(state, &sid, collection_json, None);
+    }
+    tracing::info!(
+        event_type = "HistoryApplied",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast HistoryApplied"
+    );
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    journal::JournalOp,
+    messages::{CollectionUpdatedPayload, ServerEvent, UpdateCollectionPayload},
+    types::{AppState, MAX_COLLECTION_NAME_LEN},
+};
+
+use super::create_collection::collection_name;
+use super::helpers::{broadcast, now_ms, send_error};
+
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: UpdateCollectionPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let name = match payload.name.as_deref().map(collection_name) {
+        Some(None) => {
+            send_error(
+                state,
+                connection_id,
+                "INVALID_COLLECTION_NAME",
+                format!("Collection names must be 1 to {} characters", MAX_COLLECTION_NAME_LEN),
+            );
+            return;
+        }
+        Some(Some(name)) => Some(name),
+        None => None,
+    };
+
+    let updated = {
+        let mut collections = match session.collections.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session collections lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        collections.get_mut(&payload.collection_id).map(|collection| {
+            if let Some(name) = name {
+                collection.name = name;
+            }
+            if let Some(color_tag) = payload.color_tag {
+                collection.color_tag = color_tag;
+            }
+            if let Some(visible) = payload.visible {
+                collection.visible = visible;
+            }
+            if let Some(selectable) = payload.selectable {
+                collection.selectable = selectable;
+            }
+            collection.last_updated_by = uid;
+            collection.last_updated_at = now_ms();
+            session.append_journal(|| JournalOp::Collection {
+                collection_id: collection.collection_id,
+                after: Some(collection.clone()),
+            });
+            collection.clone()
+        })
+    };
+
+    let Some(collection) = updated else {
+        send_error(
+            state,
+            connection_id,
+            "COLLECTION_NOT_FOUND",
+            format!("Collection {} not found", payload.collection_id),
+        );
+        return;
+    };
+
+    tracing::info!(
+        event_type = "UpdateCollection",
+        session_id = %sid,
+        user_id = %uid,
+        collection_id = %collection.collection_id,
+        "collection updated"
+    );
+
+    let collection_id = collection.collection_id;
+    let json = match serde_json::to_string(&ServerEvent::CollectionUpdated(CollectionUpdatedPayload {
+        collection,
+        updated_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "CollectionUpdated",
+                session_id = %sid,
+                collection_id = %collection_id,
+                error = %err,
+                "failed to serialize CollectionUpdated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "CollectionUpdated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast CollectionUpdated"
+    );
+}
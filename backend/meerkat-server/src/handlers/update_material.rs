@@ -0,0 +1,141 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    journal::JournalOp,
+    messages::{MaterialUpdatedPayload, ServerEvent, UpdateMaterialPayload},
+    types::{AppState, MAX_MATERIAL_NAME_LEN},
+};
+
+use super::create_material::{material_name, validate_settings};
+use super::helpers::{broadcast, now_ms, send_error};
+
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: UpdateMaterialPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    let name = match payload.name.as_deref().map(material_name) {
+        Some(None) => {
+            send_error(
+                state,
+                connection_id,
+                "INVALID_MATERIAL",
+                format!("Material names must be 1 to {} characters", MAX_MATERIAL_NAME_LEN),
+            );
+            return;
+        }
+        Some(Some(name)) => Some(name),
+        None => None,
+    };
+
+    let updated = {
+        let mut materials = match session.materials.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session materials lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        match materials.get_mut(&payload.material_id) {
+            None => Err(("MATERIAL_NOT_FOUND", format!("Material {} not found", payload.material_id))),
+            Some(material) => {
+                // Checked on a copy so a rejected update leaves the material as it was.
+                let mut settings = material.settings.clone();
+                if let Some(base_color) = payload.base_color {
+                    settings.base_color = base_color;
+                }
+                if let Some(metallic) = payload.metallic {
+                    settings.metallic = metallic;
+                }
+                if let Some(roughness) = payload.roughness {
+                    settings.roughness = roughness;
+                }
+                if let Some(emission_color) = payload.emission_color {
+                    settings.emission_color = emission_color;
+                }
+                if let Some(emission_strength) = payload.emission_strength {
+                    settings.emission_strength = emission_strength;
+                }
+                if let Some(alpha) = payload.alpha {
+                    settings.alpha = alpha;
+                }
+                if let Some(textures) = payload.textures {
+                    settings.textures = textures;
+                }
+                match validate_settings(&settings) {
+                    Err(reason) => Err(("INVALID_MATERIAL", format!("UpdateMaterial rejected: {}", reason))),
+                    Ok(()) => {
+                        if let Some(name) = name {
+                            material.name = name;
+                        }
+                        material.settings = settings;
+                        material.last_updated_by = uid;
+                        material.last_updated_at = now_ms();
+                        session.append_journal(|| JournalOp::Material { material: material.clone() });
+                        Ok(material.clone())
+                    }
+                }
+            }
+        }
+    };
+
+    let material = match updated {
+        Ok(material) => material,
+        Err((code, message)) => {
+            tracing::warn!(
+                event_type = "UpdateMaterial",
+                session_id = %sid,
+                user_id = %uid,
+                material_id = %payload.material_id,
+                code,
+                "material update rejected"
+            );
+            send_error(state, connection_id, code, message);
+            return;
+        }
+    };
+
+    tracing::info!(
+        event_type = "UpdateMaterial",
+        session_id = %sid,
+        user_id = %uid,
+        material_id = %material.material_id,
+        "material updated"
+    );
+
+    let material_id = material.material_id;
+    let json = match serde_json::to_string(&ServerEvent::MaterialUpdated(MaterialUpdatedPayload {
+        material,
+        updated_by: uid,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "MaterialUpdated",
+                session_id = %sid,
+                material_id = %material_id,
+                error = %err,
+                "failed to serialize MaterialUpdated event"
+            );
+            return;
+        }
+    };
+
+    session.commit_journal().await;
+    let count = broadcast(state, &sid, &json, None);
+    tracing::info!(
+        event_type = "MaterialUpdated",
+        session_id = %sid,
+        recipient_count = count,
+        "broadcast MaterialUpdated"
+    );
+}
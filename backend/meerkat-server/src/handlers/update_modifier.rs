@@ -0,0 +1,30 @@
+use uuid::Uuid;
+
+use crate::{
+    messages::UpdateModifierPayload,
+    modifiers::{self, StackError},
+    types::AppState,
+};
+
+use super::add_modifier::edit_modifiers;
+
+/// Replace a modifier's name, visibility and settings in place. Blender cannot change a
+/// modifier's type, so neither can this.
+pub async fn handle(state: &AppState, connection_id: Uuid, payload: UpdateModifierPayload) {
+    let UpdateModifierPayload { object_id, modifier } = payload;
+    edit_modifiers(state, connection_id, object_id, "UpdateModifier", |stack| {
+        let index = modifiers::position(stack, modifier.modifier_id)?;
+        let current = stack[index].kind.blender_type();
+        if current != modifier.kind.blender_type() {
+            return Err(StackError::Invalid(format!(
+                "modifier {} is {} and cannot become {}",
+                modifier.modifier_id,
+                current,
+                modifier.kind.blender_type()
+            )));
+        }
+        stack[index] = modifier;
+        Ok(())
+    })
+    .await;
+}
@@ -2,6 +2,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
+    history::ObjectChange,
     messages::{NameUpdatedPayload, ServerEvent, UpdateNamePayload},
     types::AppState,
 };
@@ -31,9 +32,15 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: UpdateNamePa
             }
         };
         if let Some(obj) = objects.get_mut(&payload.object_id) {
+            let before = obj.clone();
             obj.name = payload.name.clone();
             obj.last_updated_by = uid;
             obj.last_updated_at = now;
+            session.record_edit(uid, "UpdateName", now, vec![ObjectChange {
+                object_id: payload.object_id,
+                before: Some(before),
+                after: Some(obj.clone()),
+            }]);
         } else {
             tracing::debug!(
                 object_id = %payload.object_id,
@@ -71,6 +78,7 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: UpdateNamePa
         }
     };
 
+    session.commit_journal().await;
     let count = broadcast(state, &sid, &json, None);
     tracing::info!(
         event_type = "NameUpdated",
@@ -2,6 +2,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
+    history::ObjectChange,
     messages::{PropertiesUpdatedPayload, ServerEvent, UpdatePropertiesPayload},
     types::AppState,
 };
@@ -38,9 +39,15 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: UpdateProper
             );
             return;
         };
+        let before = obj.clone();
         obj.properties = Some(payload.properties.clone());
         obj.last_updated_by = uid;
         obj.last_updated_at = now;
+        session.record_edit(uid, "UpdateProperties", now, vec![ObjectChange {
+            object_id: payload.object_id,
+            before: Some(before),
+            after: Some(obj.clone()),
+        }]);
     }
 
     tracing::info!(
@@ -70,6 +77,7 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: UpdateProper
         }
     };
 
+    session.commit_journal().await;
     let count = broadcast(state, &sid, &json, None);
     tracing::info!(
         event_type = "PropertiesUpdated",
@@ -1,12 +1,20 @@
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 use uuid::Uuid;
 
 use crate::{
+    history::ObjectChange,
     messages::{ServerEvent, TransformUpdatedPayload, UpdateTransformPayload},
-    types::AppState,
+    types::{AppState, PendingTransform, SessionHandle},
 };
 
-use super::helpers::{broadcast, now_ms};
+use super::helpers::{announce_storage_failure, broadcast, now_ms};
+
+/// Transforms are applied to the session immediately but broadcast at most once per object
+/// per window, carrying the latest value. Keeps a user scrubbing a heavy rig from flooding
+/// every peer's queue with intermediate positions.
+pub const TRANSFORM_COALESCE_WINDOW: Duration = Duration::from_millis(25);
 
 pub async fn handle(state: &AppState, connection_id: Uuid, payload: UpdateTransformPayload) {
     let Some((sid, uid)) = state
@@ -22,7 +30,7 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: UpdateTransf
         None => return,
     };
 
-    {
+    let journal_seq = {
         let mut objects = match session.objects.write() {
             Ok(guard) => guard,
             Err(poisoned) => {
@@ -38,12 +46,19 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: UpdateTransf
             );
             return;
         };
+        let before = obj.clone();
         obj.transform = payload.transform.clone();
         obj.last_updated_by = uid;
         obj.last_updated_at = now;
-    }
+        session.record_edit(uid, "UpdateTransform", now, vec![ObjectChange {
+            object_id: payload.object_id,
+            before: Some(before),
+            after: Some(obj.clone()),
+        }]);
+        session.journal_seq()
+    };
 
-    tracing::info!(
+    tracing::debug!(
         event_type = "UpdateTransform",
         session_id = %sid,
         user_id = %uid,
@@ -51,30 +66,115 @@ pub async fn handle(state: &AppState, connection_id: Uuid, payload: UpdateTransf
         "transform updated"
     );
 
-    let json = match serde_json::to_string(&ServerEvent::TransformUpdated(TransformUpdatedPayload {
-        object_id: payload.object_id,
-        transform: payload.transform,
-        updated_by: uid,
-    })) {
-        Ok(json) => json,
-        Err(err) => {
-            tracing::error!(
-                event_type = "TransformUpdated",
-                session_id = %sid,
-                user_id = %uid,
-                object_id = %payload.object_id,
-                error = %err,
-                "failed to serialize TransformUpdated event"
-            );
-            return;
+    {
+        let mut pending = match session.pending_transforms.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session pending transforms lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        pending.insert(
+            payload.object_id,
+            PendingTransform {
+                transform: payload.transform,
+                updated_by: uid,
+                journal_seq,
+            },
+        );
+    }
+
+    if !session.transform_flush_scheduled.swap(true, Ordering::AcqRel) {
+        let state = state.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(TRANSFORM_COALESCE_WINDOW).await;
+            loop {
+                session.commit_journal().await;
+                if flush_pending_transforms(&state, &session) {
+                    break;
+                }
+            }
+            announce_storage_failure(&state, &session);
+        });
+    }
+}
+
+/// Broadcast the latest pending transform for every object touched since the last flush, as
+/// far as each is durable in the session's journal. Returns false when some are left queued
+/// because the journal has not reached them; the caller commits the journal and tries again.
+/// Once the session's storage has failed, whatever is queued is dropped unsent.
+pub fn flush_pending_transforms(state: &AppState, session: &SessionHandle) -> bool {
+    // The lock is held through the broadcasts so a mutation that discards pending transforms
+    // cannot overtake a flush already in flight.
+    let mut pending = match session.pending_transforms.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            tracing::warn!("Session pending transforms lock poisoned, recovering");
+            poisoned.into_inner()
         }
     };
+    if session.storage_failed() {
+        // Never made durable, so never acknowledged; clients hear the session is read-only.
+        if !pending.is_empty() {
+            tracing::warn!(
+                session_id = %session.session_id,
+                object_count = pending.len(),
+                "dropping coalesced transforms that were never journaled"
+            );
+            pending.clear();
+        }
+        session.transform_flush_scheduled.store(false, Ordering::Release);
+        return true;
+    }
+    // Each queued transform waits for its own journal record only, so a steady stream of
+    // other edits moving the journal ahead cannot hold it back indefinitely.
+    let ready: Vec<Uuid> = pending
+        .iter()
+        .filter(|(_, update)| session.journal_synced_through(update.journal_seq))
+        .map(|(object_id, _)| *object_id)
+        .collect();
+    let done = ready.len() == pending.len();
+    if done {
+        // Clear the flag before draining: an update queued after this point schedules a new
+        // flush, so nothing is stranded in the map.
+        session.transform_flush_scheduled.store(false, Ordering::Release);
+    }
+    if ready.is_empty() {
+        return done;
+    }
+
+    let sid = &session.session_id;
+    let object_count = ready.len();
+    let mut recipient_count = 0;
+    for (object_id, update) in ready.into_iter().filter_map(|id| pending.remove(&id).map(|update| (id, update))) {
+        let json = match serde_json::to_string(&ServerEvent::TransformUpdated(TransformUpdatedPayload {
+            object_id,
+            transform: update.transform,
+            updated_by: update.updated_by,
+        })) {
+            Ok(json) => json,
+            Err(err) => {
+                tracing::error!(
+                    event_type = "TransformUpdated",
+                    session_id = %sid,
+                    user_id = %update.updated_by,
+                    object_id = %object_id,
+                    error = %err,
+                    "failed to serialize TransformUpdated event"
+                );
+                continue;
+            }
+        };
+        recipient_count = broadcast(state, sid, &json, None);
+    }
+    drop(pending);
 
-    let count = broadcast(state, &sid, &json, None);
     tracing::info!(
         event_type = "TransformUpdated",
         session_id = %sid,
-        recipient_count = count,
-        "broadcast TransformUpdated"
+        object_count,
+        recipient_count,
+        "broadcast coalesced TransformUpdated"
     );
+    done
 }
@@ -0,0 +1,180 @@
+use axum::extract::ws::{Message, WebSocket};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    journal::JournalOp,
+    mesh::{check_chunk, trim_uploads, MeshUpload},
+    messages::{MeshChunkPayload, MeshStoredPayload, ServerEvent},
+    types::{AppState, MAX_SESSION_MESH_BYTES},
+};
+
+use super::helpers::{now_ms, send_error};
+
+/// Buffer one chunk of a mesh upload, apart from anyone else's upload of the same mesh. Once
+/// every chunk is in, the mesh is checked against its hash, stored, and the uploader is sent
+/// MeshStored. Meshes no object can get back are pruned
+/// when the session's mesh budget runs out.
+pub async fn handle(socket: &mut WebSocket, state: &AppState, connection_id: Uuid, payload: MeshChunkPayload) {
+    let Some((sid, uid)) = state
+        .connection_meta
+        .get(&connection_id)
+        .map(|r| r.value().clone())
+    else {
+        return;
+    };
+    let session = match state.sessions.get(&sid) {
+        Some(s) => Arc::clone(s.value()),
+        None => return,
+    };
+
+    if let Err(message) = check_chunk(&payload.hash, payload.chunk_index, payload.chunk_count) {
+        send_error(state, connection_id, "INVALID_MESH", message);
+        return;
+    }
+
+    let stored = match session.meshes.read() {
+        Ok(meshes) => meshes.contains_key(&payload.hash),
+        Err(poisoned) => poisoned.into_inner().contains_key(&payload.hash),
+    };
+    let key = (connection_id, payload.hash.clone());
+    let upload = {
+        let mut uploads = match session.mesh_uploads.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session mesh uploads lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        if stored {
+            // Someone uploaded the same mesh first; confirm once the sender has sent everything.
+            if payload.chunk_index + 1 < payload.chunk_count {
+                return;
+            }
+            uploads.remove(&key);
+            None
+        } else {
+            if uploads.get(&key).is_none_or(|upload| upload.chunk_count() != payload.chunk_count) {
+                uploads.remove(&key);
+                trim_uploads(&mut uploads);
+                uploads.insert(key.clone(), MeshUpload::new(payload.chunk_count, now_ms()));
+            }
+            let added = match uploads.get_mut(&key) {
+                Some(upload) => upload.add(payload.chunk_index, &payload.data),
+                None => return,
+            };
+            match added {
+                Ok(false) => return,
+                Ok(true) => uploads.remove(&key),
+                Err(message) => {
+                    uploads.remove(&key);
+                    drop(uploads);
+                    send_error(state, connection_id, "INVALID_MESH", message);
+                    return;
+                }
+            }
+        }
+    };
+
+    if let Some(upload) = upload {
+        let hash = payload.hash.clone();
+        let mesh = match tokio::task::spawn_blocking(move || upload.finish(&hash)).await {
+            Ok(Ok(mesh)) => mesh,
+            Ok(Err(message)) => {
+                send_error(state, connection_id, "INVALID_MESH", message);
+                return;
+            }
+            Err(err) => {
+                tracing::error!(session_id = %sid, error = %err, "mesh check task failed");
+                return;
+            }
+        };
+
+        let outcome = {
+            let objects = match session.objects.read() {
+                Ok(guard) => guard,
+                Err(poisoned) => {
+                    tracing::warn!("Session objects lock poisoned, recovering");
+                    poisoned.into_inner()
+                }
+            };
+            let referenced = session.referenced_meshes(&objects);
+            let mut meshes = match session.meshes.write() {
+                Ok(guard) => guard,
+                Err(poisoned) => {
+                    tracing::warn!("Session meshes lock poisoned, recovering");
+                    poisoned.into_inner()
+                }
+            };
+            let mut total: usize = meshes.values().map(|m| m.byte_len()).sum();
+            if !meshes.contains_key(&payload.hash) && total + mesh.byte_len() > MAX_SESSION_MESH_BYTES {
+                let unused: Vec<String> = meshes.keys().filter(|hash| !referenced.contains(*hash)).cloned().collect();
+                for hash in unused {
+                    if total + mesh.byte_len() <= MAX_SESSION_MESH_BYTES {
+                        break;
+                    }
+                    if let Some(pruned) = meshes.remove(&hash) {
+                        total -= pruned.byte_len();
+                        session.append_journal(|| JournalOp::Mesh { hash: hash.clone(), mesh: None });
+                        tracing::info!(session_id = %sid, hash = %hash, "pruned unused mesh");
+                    }
+                }
+            }
+            if meshes.contains_key(&payload.hash) {
+                Ok(false)
+            } else if total + mesh.byte_len() > MAX_SESSION_MESH_BYTES {
+                Err(total)
+            } else {
+                session.append_journal(|| JournalOp::Mesh { hash: payload.hash.clone(), mesh: Some(mesh.clone()) });
+                meshes.insert(payload.hash.clone(), mesh);
+                Ok(true)
+            }
+        };
+
+        match outcome {
+            Ok(true) => tracing::info!(
+                event_type = "UploadMeshChunk",
+                session_id = %sid,
+                user_id = %uid,
+                hash = %payload.hash,
+                chunk_count = payload.chunk_count,
+                "mesh stored"
+            ),
+            Ok(false) => {}
+            Err(total) => {
+                tracing::warn!(session_id = %sid, stored_bytes = total, "session mesh budget exhausted");
+                send_error(
+                    state,
+                    connection_id,
+                    "MESH_STORE_FULL",
+                    format!("Sessions may hold at most {} bytes of mesh data", MAX_SESSION_MESH_BYTES),
+                );
+                return;
+            }
+        }
+        session.commit_journal().await;
+    }
+
+    let json = match serde_json::to_string(&ServerEvent::MeshStored(MeshStoredPayload { hash: payload.hash.clone() })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(
+                event_type = "MeshStored",
+                session_id = %sid,
+                connection_id = %connection_id,
+                error = %err,
+                "failed to serialize MeshStored"
+            );
+            return;
+        }
+    };
+    if let Err(err) = socket.send(Message::Text(json.into())).await {
+        tracing::warn!(
+            event_type = "MeshStored",
+            session_id = %sid,
+            connection_id = %connection_id,
+            error = %err,
+            "failed to send MeshStored to uploading client"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::types::{SceneObject, Transform};
+use crate::xform::{decompose, invert, mat_mul, transform_matrix, IDENTITY};
+
+// Parent/child rules shared by the live handlers, transactions and importers. An object's
+// transform is relative to its parent; a `parent_id` naming an object the scene does not
+// have (an undo took the parent away, say) counts as no parent until it comes back.
+
+/// Read access to a scene's objects, so the same rules apply to a session's objects and to a
+/// transaction's staged view of them.
+pub trait Scene {
+    fn object(&self, object_id: Uuid) -> Option<&SceneObject>;
+    fn for_each_object(&self, f: &mut dyn FnMut(&SceneObject));
+}
+
+impl Scene for HashMap<Uuid, SceneObject> {
+    fn object(&self, object_id: Uuid) -> Option<&SceneObject> {
+        self.get(&object_id)
+    }
+
+    fn for_each_object(&self, f: &mut dyn FnMut(&SceneObject)) {
+        self.values().for_each(f);
+    }
+}
+
+/// The object's parent, if the scene has it.
+pub fn parent(scene: &impl Scene, object: &SceneObject) -> Option<Uuid> {
+    object.parent_id.filter(|&parent_id| scene.object(parent_id).is_some())
+}
+
+/// The object's parent, if the scene has it and the chain above it ends at a root; a stored
+/// cycle gives none. Exporters nest objects under this.
+pub fn acyclic_parent(scene: &impl Scene, object: &SceneObject) -> Option<Uuid> {
+    let parent_id = parent(scene, object)?;
+    let mut visited = HashSet::from([object.object_id]);
+    let mut current = scene.object(parent_id)?;
+    loop {
+        if !visited.insert(current.object_id) {
+            return None;
+        }
+        match current.parent_id.and_then(|id| scene.object(id)) {
+            Some(next) => current = next,
+            None => return Some(parent_id),
+        }
+    }
+}
+
+/// Ids of the objects whose parent is `object_id`, sorted so cascades are deterministic.
+pub fn children(scene: &impl Scene, object_id: Uuid) -> Vec<Uuid> {
+    let mut children = Vec::new();
+    scene.for_each_object(&mut |object| {
+        if object.parent_id == Some(object_id) {
+            children.push(object.object_id);
+        }
+    });
+    children.sort();
+    children
+}
+
+/// Every object below `object_id`, children before their parents.
+pub fn descendants(scene: &impl Scene, object_id: Uuid) -> Vec<Uuid> {
+    let mut by_parent: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    scene.for_each_object(&mut |object| {
+        if let Some(parent_id) = object.parent_id {
+            by_parent.entry(parent_id).or_default().push(object.object_id);
+        }
+    });
+    let mut ordered = Vec::new();
+    let mut seen = HashSet::from([object_id]);
+    let mut stack = vec![(object_id, false)];
+    while let Some((id, expanded)) = stack.pop() {
+        if expanded {
+            if id != object_id {
+                ordered.push(id);
+            }
+            continue;
+        }
+        stack.push((id, true));
+        if let Some(children) = by_parent.get_mut(&id) {
+            children.sort();
+            stack.extend(children.iter().rev().filter(|&&child| seen.insert(child)).map(|&child| (child, false)));
+        }
+    }
+    ordered
+}
+
+/// Matrix taking an object's local space to the world.
+pub fn world_matrix(scene: &impl Scene, object: &SceneObject) -> [f64; 16] {
+    let mut matrix = transform_matrix(&object.transform);
+    // Handlers never let a cycle form, but a hand-edited snapshot could hold one.
+    let mut visited = HashSet::from([object.object_id]);
+    let mut current = object;
+    while let Some(parent) = current.parent_id.and_then(|parent_id| scene.object(parent_id)) {
+        if !visited.insert(parent.object_id) {
+            break;
+        }
+        matrix = mat_mul(&transform_matrix(&parent.transform), &matrix);
+        current = parent;
+    }
+    matrix
+}
+
+pub fn world_transform(scene: &impl Scene, object: &SceneObject) -> Transform {
+    decompose(&world_matrix(scene, object))
+}
+
+/// The transform that puts an object at `world` once parented to `parent_id` (the world
+/// itself for no parent). Shear the parent's non-uniform scale would need is dropped.
+pub fn local_transform(scene: &impl Scene, world: &[f64; 16], parent_id: Option<Uuid>) -> Transform {
+    let parent_world = parent_id
+        .and_then(|parent_id| scene.object(parent_id))
+        .map_or(IDENTITY, |parent| world_matrix(scene, parent));
+    decompose(&mat_mul(&invert(&parent_world), world))
+}
+
+/// Check that `object_id` may be parented to `parent_id`: the parent exists and is neither the
+/// object itself nor one of its descendants.
+pub fn validate_parent(scene: &impl Scene, object_id: Uuid, parent_id: Option<Uuid>) -> Result<(), String> {
+    let Some(parent_id) = parent_id else {
+        return Ok(());
+    };
+    if parent_id == object_id {
+        return Err(format!("object {object_id} cannot be its own parent"));
+    }
+    let mut current = scene.object(parent_id).ok_or_else(|| format!("parent {parent_id} not found"))?;
+    let mut visited = HashSet::from([parent_id]);
+    while let Some(ancestor) = current.parent_id.and_then(|id| scene.object(id)) {
+        if ancestor.object_id == object_id {
+            return Err(format!("parent {parent_id} is a descendant of object {object_id}"));
+        }
+        if !visited.insert(ancestor.object_id) {
+            break;
+        }
+        current = ancestor;
+    }
+    Ok(())
+}
+
+/// What deleting an object does to the objects below it.
+#[derive(Debug, Default)]
+pub struct DeleteCascade {
+    /// Direct children moved up to the deleted object's parent, with the transforms that keep
+    /// them where they were.
+    pub reparented: Vec<(Uuid, Option<Uuid>, Transform)>,
+    /// Descendants deleted along with it, children before their parents.
+    pub deleted: Vec<Uuid>,
+}
+
+pub fn delete_cascade(scene: &impl Scene, object_id: Uuid, delete_children: bool) -> DeleteCascade {
+    let Some(object) = scene.object(object_id) else {
+        return DeleteCascade::default();
+    };
+    if delete_children {
+        return DeleteCascade { reparented: Vec::new(), deleted: descendants(scene, object_id) };
+    }
+    let new_parent = parent(scene, object);
+    let reparented = children(scene, object_id)
+        .into_iter()
+        .filter_map(|child_id| scene.object(child_id))
+        .map(|child| (child.object_id, new_parent, local_transform(scene, &world_matrix(scene, child), new_parent)))
+        .collect();
+    DeleteCascade { reparented, deleted: Vec::new() }
+}
+
+/// Turn objects read from a file, whose transforms are all world transforms, into a hierarchy:
+/// a `parent_id` naming another object in the batch keeps it and gets a transform relative to
+/// it, any other is dropped. Links that would form a cycle are dropped too.
+pub fn link_imported(objects: &mut [SceneObject]) {
+    let index: HashMap<Uuid, usize> = objects.iter().enumerate().map(|(i, object)| (object.object_id, i)).collect();
+    let worlds: Vec<[f64; 16]> = objects.iter().map(|object| transform_matrix(&object.transform)).collect();
+    for i in 0..objects.len() {
+        let Some(parent_id) = objects[i].parent_id else { continue };
+        let mut ancestor = Some(parent_id);
+        let mut steps = 0;
+        let cyclic = loop {
+            match ancestor.and_then(|id| index.get(&id)) {
+                Some(&j) if j == i => break true,
+                Some(&j) if steps < objects.len() => {
+                    ancestor = objects[j].parent_id;
+                    steps += 1;
+                }
+                _ => break false,
+            }
+        };
+        match index.get(&parent_id) {
+            Some(&j) if !cyclic => {
+                objects[i].transform = decompose(&mat_mul(&invert(&worlds[j]), &worlds[i]));
+            }
+            _ => objects[i].parent_id = None,
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::{HashMap, VecDeque};
+
+use uuid::Uuid;
+
+use crate::messages::{HistoryAction, ObjectDiff};
+use crate::types::{Collection, SceneObject};
+
+/// Undo steps kept per user; the oldest fall off first.
+pub const UNDO_HISTORY_DEPTH: usize = 100;
+
+/// Consecutive transform updates to the same object closer together than this collapse into
+/// one undo step, so a drag is undone as a whole rather than one 20 Hz tick at a time.
+pub const TRANSFORM_UNDO_MERGE_MS: u64 = 1_000;
+
+/// One object's state on either side of an edit. `None` means the object did not exist.
+#[derive(Clone, Debug)]
+pub struct ObjectChange {
+    pub object_id: Uuid,
+    pub before: Option<SceneObject>,
+    pub after: Option<SceneObject>,
+}
+
+/// A collection's state on either side of an edit. `None` means it did not exist.
+#[derive(Clone, Debug)]
+pub struct CollectionChange {
+    pub collection_id: Uuid,
+    pub before: Option<Collection>,
+    pub after: Option<Collection>,
+}
+
+/// A single user action (one event, or a whole transaction) as a unit of undo.
+#[derive(Clone, Debug)]
+pub struct HistoryEntry {
+    pub event_type: &'static str,
+    pub at_ms: u64,
+    pub changes: Vec<ObjectChange>,
+    /// Collections the action created or deleted, brought back or removed along with its objects.
+    pub collections: Vec<CollectionChange>,
+}
+
+impl HistoryEntry {
+    fn absorbs(&self, next: &HistoryEntry) -> bool {
+        self.event_type == "UpdateTransform"
+            && next.event_type == "UpdateTransform"
+            && self.changes.len() == 1
+            && next.changes.len() == 1
+            && self.changes[0].object_id == next.changes[0].object_id
+            && next.at_ms.saturating_sub(self.at_ms) < TRANSFORM_UNDO_MERGE_MS
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct UserHistory {
+    pub undo: VecDeque<HistoryEntry>,
+    pub redo: Vec<HistoryEntry>,
+}
+
+/// Undo/redo stacks per user_id. A user's stacks only ever contain their own edits.
+#[derive(Default, Debug)]
+pub struct UndoHistory {
+    pub users: HashMap<Uuid, UserHistory>,
+}
+
+impl UndoHistory {
+    /// Push a fresh edit. Clears the user's redo stack, like any editor does after a new change.
+    pub fn record(&mut self, user_id: Uuid, mut entry: HistoryEntry) {
+        let user = self.users.entry(user_id).or_default();
+        user.redo.clear();
+        if let Some(top) = user.undo.back_mut()
+            && top.absorbs(&entry)
+        {
+            top.at_ms = entry.at_ms;
+            top.changes[0].after = entry.changes.pop().and_then(|c| c.after);
+            return;
+        }
+        user.undo.push_back(entry);
+        while user.undo.len() > UNDO_HISTORY_DEPTH {
+            user.undo.pop_front();
+        }
+    }
+
+    /// Pop the entry the next Undo (or Redo) would apply.
+    pub fn take(&mut self, user_id: Uuid, action: HistoryAction) -> Option<HistoryEntry> {
+        let user = self.users.get_mut(&user_id)?;
+        match action {
+            HistoryAction::Undo => user.undo.pop_back(),
+            HistoryAction::Redo => user.redo.pop(),
+        }
+    }
+
+    /// Push the inverse of an applied Undo onto the redo stack, or of a Redo onto the undo stack.
+    pub fn push_inverse(&mut self, user_id: Uuid, action: HistoryAction, entry: HistoryEntry) {
+        let user = self.users.entry(user_id).or_default();
+        match action {
+            HistoryAction::Undo => user.redo.push(entry),
+            HistoryAction::Redo => {
+                user.undo.push_back(entry);
+                while user.undo.len() > UNDO_HISTORY_DEPTH {
+                    user.undo.pop_front();
+                }
+            }
+        }
+    }
+
+    pub fn forget_user(&mut self, user_id: Uuid) {
+        self.users.remove(&user_id);
+    }
+
+    /// Every object state an Undo or Redo could bring back.
+    pub fn objects(&self) -> impl Iterator<Item = &SceneObject> {
+        self.users
+            .values()
+            .flat_map(|user| user.undo.iter().chain(&user.redo))
+            .flat_map(|entry| &entry.changes)
+            .flat_map(|change| change.before.iter().chain(&change.after))
+    }
+}
+
+/// Outcome of rolling an entry back.
+#[derive(Debug, Default)]
+pub struct Reverted {
+    /// Changes actually made, in the order applied. Reverting these redoes the entry.
+    pub inverse: Vec<ObjectChange>,
+    /// Collection changes actually made; reverting these redoes them too.
+    pub collections: Vec<CollectionChange>,
+    /// Objects and collections skipped because someone changed them after the entry was recorded.
+    pub conflicts: Vec<Uuid>,
+}
+
+impl Reverted {
+    pub fn is_empty(&self) -> bool {
+        self.inverse.is_empty() && self.collections.is_empty()
+    }
+}
+
+/// Roll `entry` back on `objects` and `collections`, newest change first. An object or
+/// collection is only restored to its `before` state if it still matches the entry's `after`
+/// exactly; anything modified since (by anyone) is reported as a conflict and left alone.
+/// Objects are restored verbatim, attribution included, so the step beneath this one still
+/// matches and can be undone next, except for memberships of collections that no longer
+/// exist and the mesh version, which moves on rather than back (see `carry_mesh_version`).
+/// Objects still in a collection the revert removes are taken out of it.
+pub fn revert(
+    objects: &mut HashMap<Uuid, SceneObject>,
+    collections: &mut HashMap<Uuid, Collection>,
+    entry: &HistoryEntry,
+) -> Reverted {
+    let mut reverted = Reverted::default();
+    let mut removed = Vec::new();
+    for change in entry.collections.iter().rev() {
+        if collections.get(&change.collection_id) != change.after.as_ref() {
+            reverted.conflicts.push(change.collection_id);
+            continue;
+        }
+        match &change.before {
+            Some(collection) => collections.insert(change.collection_id, collection.clone()),
+            None => {
+                removed.push(change.collection_id);
+                collections.remove(&change.collection_id)
+            }
+        };
+        reverted.collections.push(CollectionChange {
+            collection_id: change.collection_id,
+            before: change.after.clone(),
+            after: change.before.clone(),
+        });
+    }
+
+    for change in entry.changes.iter().rev() {
+        let current = objects.get(&change.object_id);
+        if !matches_recorded(current, change.after.as_ref()) {
+            reverted.conflicts.push(change.object_id);
+            continue;
+        }
+        let mut restored = change.before.clone();
+        let before = current.cloned();
+        match &mut restored {
+            Some(object) => {
+                carry_mesh_version(object, before.as_ref());
+                retain_existing_collections(object, collections);
+                objects.insert(change.object_id, object.clone())
+            }
+            None => objects.remove(&change.object_id),
+        };
+        reverted.inverse.push(ObjectChange { object_id: change.object_id, before, after: restored });
+    }
+
+    if !removed.is_empty() {
+        for object in objects.values_mut().filter(|object| object.collections.iter().any(|id| removed.contains(id))) {
+            let before = object.clone();
+            object.collections.retain(|id| !removed.contains(id));
+            reverted.inverse.push(ObjectChange {
+                object_id: object.object_id,
+                before: Some(before),
+                after: Some(object.clone()),
+            });
+        }
+    }
+    reverted
+}
+
+/// Whether `current` is the state an entry recorded, mesh version aside: undoing a step beneath
+/// one that changed the mesh finds a newer version than it recorded.
+fn matches_recorded(current: Option<&SceneObject>, recorded: Option<&SceneObject>) -> bool {
+    match (current, recorded) {
+        (Some(current), Some(recorded)) => {
+            *current == SceneObject { mesh_version: current.mesh_version, ..recorded.clone() }
+        }
+        (current, recorded) => current == recorded,
+    }
+}
+
+/// Give `restored`, an older state of an object about to replace `current`, a mesh version
+/// that does not go back: a client that saw the newer state must not have its stale edits
+/// pass the version check once the older mesh returns.
+pub fn carry_mesh_version(restored: &mut SceneObject, current: Option<&SceneObject>) {
+    if let Some(current) = current {
+        restored.mesh_version = current.mesh_version + u64::from(restored.mesh != current.mesh);
+    }
+}
+
+/// Take `object` out of collections that no longer exist. Trash entries, checkpoints and undo
+/// steps keep object states from before a DeleteCollection, so whatever brings one back calls
+/// this first. Returns whether anything was dropped.
+pub fn retain_existing_collections(object: &mut SceneObject, collections: &HashMap<Uuid, Collection>) -> bool {
+    let count = object.collections.len();
+    object.collections.retain(|id| collections.contains_key(id));
+    object.collections.len() != count
+}
+
+/// The changes that turn `current` into `target`: one per object that differs between them.
+/// Objects kept carry their mesh version forward.
+pub fn changes_between(
+    current: &HashMap<Uuid, SceneObject>,
+    target: &HashMap<Uuid, SceneObject>,
+) -> Vec<ObjectChange> {
+    let mut changes: Vec<ObjectChange> = current
+        .iter()
+        .filter(|(object_id, object)| !matches_recorded(Some(*object), target.get(*object_id)))
+        .map(|(object_id, object)| {
+            let mut after = target.get(object_id).cloned();
+            if let Some(after) = &mut after {
+                carry_mesh_version(after, Some(object));
+            }
+            ObjectChange { object_id: *object_id, before: Some(object.clone()), after }
+        })
+        .collect();
+    changes.extend(
+        target
+            .iter()
+            .filter(|(object_id, _)| !current.contains_key(*object_id))
+            .map(|(object_id, object)| ObjectChange {
+                object_id: *object_id,
+                before: None,
+                after: Some(object.clone()),
+            }),
+    );
+    changes
+}
+
+/// Move `objects` to the `after` side of every change.
+pub fn apply_changes(objects: &mut HashMap<Uuid, SceneObject>, changes: &[ObjectChange]) {
+    for change in changes {
+        match &change.after {
+            Some(object) => objects.insert(change.object_id, object.clone()),
+            None => objects.remove(&change.object_id),
+        };
+    }
+}
+
+/// Split applied changes into the created/updated/deleted lists clients apply.
+pub fn object_diff(changes: &[ObjectChange]) -> ObjectDiff {
+    let mut diff = ObjectDiff::default();
+    for change in changes {
+        match (&change.before, &change.after) {
+            (None, Some(object)) => diff.created.push(object.clone()),
+            (Some(_), Some(object)) => diff.updated.push(object.clone()),
+            (Some(_), None) => diff.deleted.push(change.object_id),
+            (None, None) => {}
+        }
+    }
+    diff
+}
@@ -0,0 +1,249 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::encryption::{decode_line, encode_line, is_key_error, Keyring};
+use crate::mesh::StoredMesh;
+use crate::types::{Checkpoint, Collection, Material, SceneObject, TrashedObject};
+
+/// The journal being appended to, inside the session's storage directory.
+pub const JOURNAL_FILE_NAME: &str = "journal.log";
+/// A snapshot rotates the live journal to `journal-<last seq>.log`; the rotated file is
+/// deleted once the snapshot covering it is on disk.
+const ROTATED_PREFIX: &str = "journal-";
+const ROTATED_SUFFIX: &str = ".log";
+/// Associated data for sealed journal records.
+const JOURNAL_AAD: &[u8] = b"meerkat journal";
+
+/// New state of one object after a mutation; `None` means it was deleted.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalChange {
+    pub object_id: Uuid,
+    pub after: Option<SceneObject>,
+}
+
+/// One applied mutation. Objects carry their full new state so replaying is idempotent
+/// and does not depend on the handler that made the change.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "op")]
+pub enum JournalOp {
+    /// Everything `record_object_changes` saw, and the session version it ended at.
+    Objects { version: u64, changes: Vec<JournalChange> },
+    Checkpoint { checkpoint: Checkpoint },
+    Trashed { item: Box<TrashedObject> },
+    /// New state of a collection; `None` means it was deleted.
+    Collection { collection_id: Uuid, after: Option<Collection> },
+    /// New state of a material.
+    Material { material: Material },
+    /// A stored mesh; `None` means it was pruned.
+    Mesh { hash: String, mesh: Option<StoredMesh> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalRecord {
+    pub seq: u64,
+    pub op: JournalOp,
+}
+
+/// Append-only log of a session's mutations since its last snapshot, one JSON record per
+/// line, or one base64 sealed record per line when encryption is on. Appends go to a buffer
+/// under the session's locks; `SessionHandle::commit_journal` writes and fsyncs them in
+/// batches before the mutations are broadcast.
+#[derive(Debug)]
+pub struct Journal {
+    dir: PathBuf,
+    file: BufWriter<File>,
+    /// Rotated files that may hold records not yet on disk; the next sync covers them too.
+    rotated_unsynced: Vec<File>,
+    keyring: Option<Keyring>,
+    /// Seq of the last record appended (0 before the first).
+    last_seq: u64,
+    /// Seq of the last record known to be on disk.
+    synced_seq: u64,
+    /// Seq covered by the last snapshot.
+    snapshot_seq: u64,
+}
+
+impl Journal {
+    /// Start an empty journal in `dir` whose records follow `last_seq`, which the session's
+    /// snapshot must already cover. Records are sealed with `keyring` when given.
+    pub fn create(dir: &Path, last_seq: u64, keyring: Option<Keyring>) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new().create(true).write(true).truncate(true).open(dir.join(JOURNAL_FILE_NAME))?;
+        Ok(Journal {
+            dir: dir.to_path_buf(),
+            file: BufWriter::new(file),
+            rotated_unsynced: Vec::new(),
+            keyring,
+            last_seq,
+            synced_seq: last_seq,
+            snapshot_seq: last_seq,
+        })
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    pub fn synced_seq(&self) -> u64 {
+        self.synced_seq
+    }
+
+    pub fn snapshot_seq(&self) -> u64 {
+        self.snapshot_seq
+    }
+
+    /// Buffer one record. A failed write leaves the journal unusable; the session drops it.
+    pub fn append(&mut self, op: JournalOp) -> io::Result<()> {
+        self.last_seq += 1;
+        let record = JournalRecord { seq: self.last_seq, op };
+        self.write_record(&record).inspect_err(|err| {
+            tracing::error!(dir = %self.dir.display(), seq = self.last_seq, error = %err, "failed to append to journal");
+        })
+    }
+
+    fn write_record(&mut self, record: &JournalRecord) -> io::Result<()> {
+        let line = encode_line(self.keyring.as_ref(), JOURNAL_AAD, serde_json::to_vec(record)?)?;
+        self.file.write_all(&line)?;
+        self.file.write_all(b"\n")
+    }
+
+    /// Hand buffered records to the OS and return what an fsync of the returned handles makes
+    /// durable. The fsync itself happens outside the session's locks.
+    pub fn begin_sync(&mut self) -> io::Result<(u64, Vec<File>)> {
+        self.file.flush()?;
+        let mut files = std::mem::take(&mut self.rotated_unsynced);
+        files.push(self.file.get_ref().try_clone()?);
+        Ok((self.last_seq, files))
+    }
+
+    pub fn mark_synced(&mut self, seq: u64) {
+        self.synced_seq = self.synced_seq.max(seq);
+    }
+
+    /// Close the live file as `journal-<last seq>.log` and start a fresh one, so a snapshot
+    /// taken at or before `last_seq` can later drop everything up to it. Nothing is fsynced
+    /// here: records of the rotated file not yet on disk go with the next `begin_sync`.
+    pub fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let rotated = self.dir.join(format!("{ROTATED_PREFIX}{}{ROTATED_SUFFIX}", self.last_seq));
+        fs::rename(self.dir.join(JOURNAL_FILE_NAME), rotated)?;
+        let file = OpenOptions::new().create(true).write(true).truncate(true).open(self.dir.join(JOURNAL_FILE_NAME))?;
+        let previous = std::mem::replace(&mut self.file, BufWriter::new(file));
+        if self.synced_seq < self.last_seq {
+            self.rotated_unsynced.push(previous.into_inner().map_err(|err| err.into_error())?);
+        }
+        Ok(())
+    }
+
+    pub fn mark_snapshot(&mut self, seq: u64) {
+        self.snapshot_seq = self.snapshot_seq.max(seq);
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        if let Err(err) = self.file.flush() {
+            tracing::error!(dir = %self.dir.display(), error = %err, "failed to flush journal");
+        }
+    }
+}
+
+fn rotated_seq(name: &str) -> Option<u64> {
+    name.strip_prefix(ROTATED_PREFIX)?.strip_suffix(ROTATED_SUFFIX)?.parse().ok()
+}
+
+/// Rotated journals in `dir` with the last seq each holds, oldest first.
+fn rotated_journals(dir: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+    let mut rotated = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(rotated),
+        Err(err) => return Err(err),
+    };
+    for entry in entries {
+        let entry = entry?;
+        if let Some(seq) = entry.file_name().to_str().and_then(rotated_seq) {
+            rotated.push((seq, entry.path()));
+        }
+    }
+    rotated.sort();
+    Ok(rotated)
+}
+
+/// Every record left in `dir`, in order. A torn final line of the newest journal (crash
+/// mid-append) is skipped: its mutation was never acknowledged. Any other record that cannot
+/// be read is an error, since replaying the records after it would build a state that never
+/// existed; so are records the keyring cannot decrypt.
+pub fn read_journals(dir: &Path, keyring: Option<&Keyring>) -> io::Result<Vec<JournalRecord>> {
+    let mut paths: Vec<PathBuf> = rotated_journals(dir)?.into_iter().map(|(_, path)| path).collect();
+    paths.push(dir.join(JOURNAL_FILE_NAME));
+    let mut files = Vec::new();
+    for path in paths {
+        match File::open(&path) {
+            Ok(file) => files.push((path, file)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    let newest = files.len().saturating_sub(1);
+    let mut records = Vec::new();
+    for (file_index, (path, file)) in files.into_iter().enumerate() {
+        let mut lines = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                lines.push((index + 1, line));
+            }
+        }
+        let last = lines.len().saturating_sub(1);
+        for (position, (line_number, line)) in lines.iter().enumerate() {
+            let record = decode_line(keyring, JOURNAL_AAD, line.trim())
+                .and_then(|json| serde_json::from_slice::<JournalRecord>(&json).map_err(io::Error::from));
+            match record {
+                Ok(record) => records.push(record),
+                Err(err) if is_key_error(&err) => return Err(err),
+                Err(err) if file_index == newest && position == last => tracing::warn!(
+                    path = %path.display(),
+                    line = line_number,
+                    error = %err,
+                    "skipping torn final journal record"
+                ),
+                Err(err) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} line {line_number}: unreadable journal record: {err}", path.display()),
+                    ));
+                }
+            }
+        }
+    }
+    records.sort_by_key(|record| record.seq);
+    Ok(records)
+}
+
+/// Delete rotated journals that a snapshot at `snapshot_seq` fully covers.
+pub fn remove_compacted(dir: &Path, snapshot_seq: u64) -> io::Result<usize> {
+    let mut removed = 0;
+    for (seq, path) in rotated_journals(dir)? {
+        if seq <= snapshot_seq {
+            fs::remove_file(path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Delete every journal file in `dir`, live and rotated.
+pub fn remove_all(dir: &Path) -> io::Result<()> {
+    for (_, path) in rotated_journals(dir)? {
+        fs::remove_file(path)?;
+    }
+    match fs::remove_file(dir.join(JOURNAL_FILE_NAME)) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
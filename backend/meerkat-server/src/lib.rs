@@ -1,4 +1,29 @@
+pub mod admin;
+pub mod archive;
+pub mod audit;
+pub mod checksum;
+pub mod config;
+pub mod custom_properties;
+pub mod diff;
+pub mod encryption;
+pub mod gltf;
 pub mod handlers;
+pub mod hierarchy;
+pub mod history;
+pub mod journal;
+pub mod mesh;
+pub mod modifiers;
 pub mod messages;
+pub mod primitive_mesh;
+pub mod property_patch;
+pub mod rate_limit;
+pub mod recording;
+pub mod replay;
+pub mod rewrap;
+pub mod snapshot;
+pub mod storage;
+pub mod trash;
 pub mod types;
+pub mod usd;
 pub mod websocket;
+pub mod xform;
@@ -1,8 +1,23 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use dashmap::DashMap;
 use axum::{routing::any, Router};
 use tokio::net::TcpListener;
-use meerkat_server::{types::AppState, websocket::tcp_socket_upgrade};
+use meerkat_server::{
+    admin,
+    archive::{read_archive, write_archive, SessionArchive},
+    audit::{self, AUDIT_FILE_NAME},
+    checksum::run_checksum_broadcaster, config::{ServerConfig, DATA_DIR_ENV}, diff,
+    encryption::{Keyring, ENCRYPTION_KEY_ENV, ENCRYPTION_KEY_FILE_ENV},
+    gltf::{export_scene, import_objects, Gltf, GltfFormat},
+    handlers::helpers::now_ms,
+    replay::{self, ReplayOptions, Timeline},
+    rewrap::rewrap_data_dir,
+    snapshot::{self, restore_sessions, run_snapshotter, SNAPSHOT_FILE_NAME},
+    trash::run_trash_sweeper, types::{AppState, SceneObject, SessionHandle},
+    usd::{self, Layer},
+    websocket::tcp_socket_upgrade,
+};
 
 pub fn logging_init() { 
     tracing_subscriber::fmt()
@@ -10,9 +25,328 @@ pub fn logging_init() {
         .init();
 }
 
+const USAGE: &str = "usage: meerkat-server [diff <before.json> <after.json> [--json]]
+       meerkat-server replay <recording.mrec> [--speed N] [--from MS] [--paused] [--addr HOST:PORT] [--password P]
+       meerkat-server export <session-id> <archive.mka>
+       meerkat-server import <archive.mka> <session-id> --password P
+       meerkat-server gltf <session-id> <out.glb|out.gltf>
+       meerkat-server gltf-import <in.glb|in.gltf> <session-id> --password P
+       meerkat-server usd <session-id> <out.usda>
+       meerkat-server usd-import <in.usda> <session-id> --password P
+       meerkat-server gen-key
+       meerkat-server rewrap";
+
+const DEFAULT_REPLAY_ADDR: &str = "127.0.0.1:8000";
+
+/// `meerkat-server diff`: compare two saved snapshots (Session, Checkpoint or FullStateSync JSON).
+fn run_diff(args: &[String]) -> Result<(), String> {
+    let json = args.iter().any(|a| a == "--json");
+    let paths: Vec<&String> = args.iter().filter(|a| *a != "--json").collect();
+    let [before_path, after_path] = paths.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    let load = |path: &str| {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        diff::objects_from_json(&text).map_err(|e| format!("{path}: {e}"))
+    };
+    let scene_diff = diff::diff_objects(&load(before_path)?, &load(after_path)?);
+    if json {
+        let out = serde_json::to_string_pretty(&scene_diff).map_err(|e| e.to_string())?;
+        println!("{out}");
+    } else {
+        print!("{}", scene_diff.summary());
+    }
+    Ok(())
+}
+
+struct ReplayArgs {
+    path: String,
+    options: ReplayOptions,
+    addr: String,
+    password: String,
+}
+
+fn parse_replay_args(args: &[String]) -> Result<ReplayArgs, String> {
+    let mut path = None;
+    let mut options = ReplayOptions::default();
+    let mut addr = DEFAULT_REPLAY_ADDR.to_string();
+    let mut password = String::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value\n{USAGE}"));
+        match arg.as_str() {
+            "--speed" => options.speed = value()?.parse().map_err(|e| format!("--speed: {e}"))?,
+            "--from" => options.start_at_ms = value()?.parse().map_err(|e| format!("--from: {e}"))?,
+            "--paused" => options.paused = true,
+            "--addr" => addr = value()?.clone(),
+            "--password" => password = value()?.clone(),
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.clone()),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let path = path.ok_or_else(|| USAGE.to_string())?;
+    Ok(ReplayArgs { path, options, addr, password })
+}
+
+/// `meerkat-server replay`: serve a recorded session, read-only, to viewers that join it.
+fn run_replay(args: &[String]) -> Result<(), String> {
+    let args = parse_replay_args(args)?;
+    let keyring = Keyring::from_env()?;
+    let timeline = Timeline::load(std::path::Path::new(&args.path), keyring.as_ref())
+        .map_err(|e| format!("{}: {e}", args.path))?;
+    let password_hash = bcrypt::hash(&args.password, bcrypt::DEFAULT_COST).map_err(|e| e.to_string())?;
+    serve_replay(args, timeline, password_hash)
+}
+
+#[tokio::main]
+async fn serve_replay(args: ReplayArgs, timeline: Timeline, password_hash: String) -> Result<(), String> {
+    logging_init();
+
+    let state = AppState::with_config(ServerConfig { read_only: true, ..ServerConfig::default() });
+    let session_id = timeline.session_id.clone();
+    let control = replay::start(&state, timeline, password_hash, args.options)?;
+
+    let app: Router = Router::new()
+        .route("/ws", any(tcp_socket_upgrade))
+        .with_state(state)
+        .merge(replay::router(control));
+    let listener = TcpListener::bind(&args.addr).await.map_err(|e| format!("failed to bind to {}: {e}", args.addr))?;
+    tracing::info!(addr = %args.addr, session_id = %session_id, "replay server listening");
+    axum::serve(listener, app).await.map_err(|e| e.to_string())
+}
+
+/// The persisted-session settings the offline subcommands work on.
+fn storage_config() -> Result<ServerConfig, String> {
+    let config = ServerConfig { encryption: Keyring::from_env()?, ..ServerConfig::from_env() };
+    if config.data_dir.is_none() {
+        return Err(format!("{DATA_DIR_ENV} must be set"));
+    }
+    Ok(config)
+}
+
+/// `meerkat-server export`: archive a session persisted under the data directory. A session
+/// is only on disk while it is live (or after a crash): once its last user leaves, the server
+/// deletes its snapshot and journal, so export it through the admin API before then.
+fn run_export(args: &[String]) -> Result<(), String> {
+    let [session_id, out_path] = args else {
+        return Err(USAGE.to_string());
+    };
+    let config = storage_config()?;
+    let keyring = config.encryption.as_ref();
+    let dir = config.session_dir(session_id).expect("data dir is set");
+    let snapshot = snapshot::recover(&dir, keyring)
+        .map_err(|e| format!("{}: {e}", dir.display()))?
+        .ok_or_else(|| {
+            format!(
+                "no persisted session '{session_id}' in {}; sessions are deleted from disk once their last user leaves",
+                dir.display()
+            )
+        })?;
+    let audit_path = dir.join(AUDIT_FILE_NAME);
+    let audit = if audit_path.exists() {
+        audit::read_file(&audit_path, keyring).map_err(|e| format!("{}: {e}", audit_path.display()))?
+    } else {
+        Vec::new()
+    };
+    // The archive metadata (source id, export time, server version, counts) comes from the
+    // snapshot; nobody is connected to a session read from disk, so there are no users.
+    let archive = SessionArchive::new(snapshot, HashMap::new(), audit);
+    write_archive(std::path::Path::new(out_path), &archive).map_err(|e| format!("{out_path}: {e}"))?;
+    println!(
+        "exported '{}' (server {}): {} objects, {} checkpoints, {} audit entries",
+        archive.metadata.source_session_id,
+        archive.metadata.server_version,
+        archive.metadata.object_count,
+        archive.metadata.checkpoint_count,
+        archive.metadata.audit_entry_count
+    );
+    Ok(())
+}
+
+/// `meerkat-server rewrap`: re-encrypt everything under the data directory with the current key,
+/// encrypting what predates encryption. Run with the server stopped.
+fn run_rewrap(args: &[String]) -> Result<(), String> {
+    if !args.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    let config = storage_config()?;
+    let keyring = config
+        .encryption
+        .ok_or_else(|| format!("{ENCRYPTION_KEY_ENV} or {ENCRYPTION_KEY_FILE_ENV} must be set"))?;
+    let data_dir = config.data_dir.expect("data dir is set");
+    let summary = rewrap_data_dir(&data_dir, &keyring).map_err(|e| e.to_string())?;
+    println!(
+        "re-encrypted with key {}: {} sessions, {} audit logs, {} recordings",
+        keyring.key_ids()[0],
+        summary.sessions,
+        summary.audit_logs,
+        summary.recordings
+    );
+    Ok(())
+}
+
+/// Storage directory for a session the CLI is about to create; an error if one is persisted there.
+fn new_session_dir(config: &ServerConfig, session_id: &str) -> Result<std::path::PathBuf, String> {
+    let dir = config.session_dir(session_id).expect("data dir is set");
+    if dir.join(SNAPSHOT_FILE_NAME).exists() {
+        return Err(format!("session '{session_id}' already exists in {}", dir.display()));
+    }
+    Ok(dir)
+}
+
+/// Write a new session's snapshot and audit log, as the server does when it is created.
+fn persist_new_session(config: &ServerConfig, session: &SessionHandle, dir: &std::path::Path) -> Result<(), String> {
+    snapshot::start_journal(session, dir, 0, config.encryption.as_ref()).map_err(|e| format!("{}: {e}", dir.display()))?;
+    session
+        .lock_audit()
+        .attach(&dir.join(AUDIT_FILE_NAME), config.encryption.clone())
+        .map_err(|e| format!("{}: {e}", dir.display()))
+}
+
+/// `meerkat-server import`: persist an archived session under the data directory, where the
+/// server picks it up on its next start. Use the admin API to import into a running server.
+fn run_import(args: &[String]) -> Result<(), String> {
+    let (path, session_id, password) = match args {
+        [path, session_id, flag, password] if flag == "--password" => (path, session_id.trim(), password),
+        _ => return Err(USAGE.to_string()),
+    };
+    if session_id.is_empty() || password.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    let config = storage_config()?;
+    let dir = new_session_dir(&config, session_id)?;
+    let archive = read_archive(std::path::Path::new(path)).map_err(|e| format!("{path}: {e}"))?;
+    let password_hash = bcrypt::hash(password, bcrypt::DEFAULT_COST).map_err(|e| e.to_string())?;
+    let metadata = archive.metadata.clone();
+    let session = archive.into_session(session_id.to_string(), password_hash);
+    persist_new_session(&config, &session, &dir)?;
+    println!(
+        "imported '{}' as '{session_id}': {} objects, {} checkpoints, {} audit entries",
+        metadata.source_session_id, metadata.object_count, metadata.checkpoint_count, metadata.audit_entry_count
+    );
+    Ok(())
+}
+
+/// A session persisted under the data directory.
+fn persisted_session(session_id: &str) -> Result<snapshot::SessionSnapshot, String> {
+    let config = storage_config()?;
+    let dir = config.session_dir(session_id).expect("data dir is set");
+    snapshot::recover(&dir, config.encryption.as_ref())
+        .map_err(|e| format!("{}: {e}", dir.display()))?
+        .ok_or_else(|| format!("no persisted session '{session_id}' in {}", dir.display()))
+}
+
+/// Objects of a session persisted under the data directory.
+fn persisted_objects(session_id: &str) -> Result<HashMap<uuid::Uuid, SceneObject>, String> {
+    Ok(persisted_session(session_id)?.objects)
+}
+
+/// `meerkat-server gltf`: convert a session persisted under the data directory to glTF 2.0,
+/// as `.glb` or `.gltf` by the output's extension.
+fn run_gltf(args: &[String]) -> Result<(), String> {
+    let [session_id, out_path] = args else {
+        return Err(USAGE.to_string());
+    };
+    let out = std::path::Path::new(out_path);
+    let format = out
+        .extension()
+        .and_then(|extension| GltfFormat::parse(&extension.to_string_lossy()))
+        .ok_or_else(|| format!("{out_path}: expected a .glb or .gltf file name"))?;
+    let snapshot = persisted_session(session_id)?;
+    let objects = snapshot.objects;
+    let bytes = export_scene(session_id, &objects, &snapshot.materials).to_bytes(format).map_err(|e| e.to_string())?;
+    std::fs::write(out, bytes).map_err(|e| format!("{out_path}: {e}"))?;
+    println!("exported {} objects from '{session_id}' to {out_path}", objects.len());
+    Ok(())
+}
+
+/// `meerkat-server usd`: convert a session persisted under the data directory to a `.usda`
+/// layout layer.
+fn run_usd(args: &[String]) -> Result<(), String> {
+    let [session_id, out_path] = args else {
+        return Err(USAGE.to_string());
+    };
+    let objects = persisted_objects(session_id)?;
+    std::fs::write(out_path, usd::export_objects(session_id, &objects).to_usda()).map_err(|e| format!("{out_path}: {e}"))?;
+    println!("exported {} objects from '{session_id}' to {out_path}", objects.len());
+    Ok(())
+}
+
+/// Create a persisted session holding objects read from the scene file at `args[0]` by
+/// `read`, picked up on the server's next start. The admin API imports into a running server,
+/// also into existing sessions.
+fn import_scene_file(args: &[String], read: impl FnOnce(&[u8]) -> Result<Vec<SceneObject>, String>) -> Result<(), String> {
+    let (path, session_id, password) = match args {
+        [path, session_id, flag, password] if flag == "--password" => (path, session_id.trim(), password),
+        _ => return Err(USAGE.to_string()),
+    };
+    if session_id.is_empty() || password.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    let config = storage_config()?;
+    let dir = new_session_dir(&config, session_id)?;
+    let bytes = std::fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+    let objects = read(&bytes).map_err(|e| format!("{path}: {e}"))?;
+    let password_hash = bcrypt::hash(password, bcrypt::DEFAULT_COST).map_err(|e| e.to_string())?;
+    let session = SessionHandle::new(session_id.to_string(), password_hash);
+    let object_count = objects.len();
+    {
+        let mut scene = session.objects.write().expect("session is not shared yet");
+        scene.extend(objects.into_iter().map(|object| (object.object_id, object)));
+    }
+    persist_new_session(&config, &session, &dir)?;
+    println!("imported {object_count} objects from {path} into new session '{session_id}'");
+    Ok(())
+}
+
+/// `meerkat-server gltf-import`: a new persisted session holding a glTF file's nodes.
+fn run_gltf_import(args: &[String]) -> Result<(), String> {
+    import_scene_file(args, |bytes| {
+        let gltf = Gltf::from_bytes(bytes).map_err(|e| e.to_string())?;
+        Ok(import_objects(&gltf.document, now_ms()))
+    })
+}
+
+/// `meerkat-server usd-import`: a new persisted session holding a `.usda` layer's prims.
+fn run_usd_import(args: &[String]) -> Result<(), String> {
+    import_scene_file(args, |bytes| {
+        let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+        let layer = Layer::parse(text).map_err(|e| e.to_string())?;
+        Ok(usd::import_objects(&layer, now_ms()))
+    })
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        None => Keyring::from_env().and_then(serve),
+        Some("diff") => run_diff(&args[1..]),
+        Some("replay") => run_replay(&args[1..]),
+        Some("export") => run_export(&args[1..]),
+        Some("import") => run_import(&args[1..]),
+        Some("gltf") => run_gltf(&args[1..]),
+        Some("gltf-import") => run_gltf_import(&args[1..]),
+        Some("usd") => run_usd(&args[1..]),
+        Some("usd-import") => run_usd_import(&args[1..]),
+        Some("gen-key") => {
+            println!("{}", Keyring::generate_key());
+            Ok(())
+        }
+        Some("rewrap") => run_rewrap(&args[1..]),
+        Some(_) => Err(USAGE.to_string()),
+    };
+    if let Err(message) = result {
+        eprintln!("{message}");
+        std::process::exit(1);
+    }
+}
+
 #[tokio::main(flavor = "multi_thread", worker_threads = 10)]
-async fn main() {
+async fn serve(encryption: Option<Keyring>) -> Result<(), String> {
     logging_init();
+    if let Some(keyring) = &encryption {
+        tracing::info!(key_ids = ?keyring.key_ids(), "encrypting persisted sessions");
+    }
 
     let state = AppState {
         sessions: Arc::new(DashMap::new()),              // K: session_id: String | V: Arc<SessionHandle>
@@ -20,23 +354,40 @@ async fn main() {
         connection_meta: Arc::new(DashMap::new()),       // K: connection_id: Uuid | V: (session id string user id uuid) 
         connection_backpressure: Arc::new(DashMap::new()), // K: connection_id: Uuid | V: LagState {strikes: u8, last_full_at_ms: u64}
         session_connections: Arc::new(DashMap::new()),   // K: session_id: String | V: HashSet<connection_id: Uuid>
+        rate_limits: Arc::new(DashMap::new()),           // K: connection_id: Uuid | V: HashMap<RateClass, TokenBucket>
+        sync_backlogs: Arc::new(DashMap::new()),         // K: connection_id: Uuid | V: broadcasts held during a state sync
+        config: Arc::new(ServerConfig { encryption, ..ServerConfig::from_env() }), // MEERKAT_* env vars
     };
 
-    let app: Router = Router::new()
+    if state.config.data_dir.is_some() {
+        let restored = restore_sessions(&state).map_err(|e| format!("failed to restore persisted sessions: {e}"))?;
+        tracing::info!(restored, "restored persisted sessions");
+        tokio::spawn(run_snapshotter(state.clone()));
+    }
+    tokio::spawn(run_checksum_broadcaster(state.clone()));
+    tokio::spawn(run_trash_sweeper(state.clone()));
+
+    let mut app: Router = Router::new()
         .route("/ws", any(tcp_socket_upgrade))
-        .with_state(state);
+        .with_state(state.clone());
+    let addr = state.config.bind_addr.clone();
+    match admin::token_from_env() {
+        Some(token) => app = app.merge(admin::router(state, token)),
+        None => tracing::info!("{} not set, admin API disabled", admin::ADMIN_TOKEN_ENV),
+    }
 
-    let listener = match TcpListener::bind("0.0.0.0:8000").await {
+    let listener = match TcpListener::bind(&addr).await {
         Ok(l) => l,
         Err(e) => {
-            tracing::error!(error = %e, "failed to bind to 0.0.0.0:8000");
-            return;
+            tracing::error!(error = %e, addr = %addr, "failed to bind");
+            return Ok(());
         }
     };
 
-    tracing::info!(addr = "0.0.0.0:8000", "server listening");
+    tracing::info!(addr = %addr, "server listening");
 
     if let Err(e) = axum::serve(listener, app).await {
         tracing::error!(error = %e, "server error");
     }
+    Ok(())
 }
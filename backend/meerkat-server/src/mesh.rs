@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::types::{MAX_MESH_CHUNKS, MAX_MESH_CORNERS, MAX_MESH_VERTICES, MESH_CHUNK_LEN};
+
+// Mesh data objects share by content: a mesh is stored once per session under the SHA-256 of
+// its binary encoding, whichever objects use it, and travels deflated in base64 chunks.
+
+pub const MESH_MAGIC: &[u8; 4] = b"MKMS";
+pub const MESH_FORMAT_VERSION: u32 = 1;
+/// Magic, format version and five u32 counts.
+const HEADER_LEN: usize = 4 + 4 * 6;
+/// The largest encoding the limits allow; faces never outnumber corners.
+const MAX_ENCODED_LEN: usize = HEADER_LEN + MAX_MESH_VERTICES * 12 + MAX_MESH_CORNERS * (4 + 4 + 8 + 12);
+/// Uploads a session buffers at once; starting another drops the oldest.
+pub const MAX_PENDING_UPLOADS: usize = 8;
+
+/// Geometry laid out as Blender keeps it: a position per vertex, faces as a corner count each
+/// plus the vertex index at every corner (Blender's polygons and loops), and optional UVs and
+/// normals per corner.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshGeometry {
+    pub positions: Vec<[f32; 3]>,
+    pub face_sizes: Vec<u32>,
+    pub face_vertices: Vec<u32>,
+    pub uvs: Vec<[f32; 2]>,
+    pub normals: Vec<[f32; 3]>,
+}
+
+impl MeshGeometry {
+    /// Little-endian: MESH_MAGIC, the format version, u32 counts of vertices, faces, corners,
+    /// UVs and normals, then the positions, face sizes, corner vertex indices, UVs and normals.
+    /// The content hash is taken over these bytes.
+    pub fn encode(&self) -> Vec<u8> {
+        let floats = self.positions.len() * 3 + self.uvs.len() * 2 + self.normals.len() * 3;
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 4 * (floats + self.face_sizes.len() + self.face_vertices.len()));
+        bytes.extend_from_slice(MESH_MAGIC);
+        let counts = [
+            MESH_FORMAT_VERSION as usize,
+            self.positions.len(),
+            self.face_sizes.len(),
+            self.face_vertices.len(),
+            self.uvs.len(),
+            self.normals.len(),
+        ];
+        for count in counts {
+            bytes.extend_from_slice(&(count as u32).to_le_bytes());
+        }
+        let positions = self.positions.iter().flatten();
+        for value in positions {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in self.face_sizes.iter().chain(&self.face_vertices) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in self.uvs.iter().flatten().chain(self.normals.iter().flatten()) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Read an `encode`d mesh. The counts are checked against the limits and the length
+    /// before anything is allocated; see `validate` for the rest.
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MESH_MAGIC {
+            return Err("not a mesh (bad magic)".to_string());
+        }
+        let mut words = bytes[4..].chunks_exact(4).map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]));
+        let mut next = || words.next().unwrap_or_default();
+        let version = next();
+        if version != MESH_FORMAT_VERSION {
+            return Err(format!("unsupported mesh format version {version}"));
+        }
+        let [vertices, faces, corners, uvs, normals] = [next(), next(), next(), next(), next()].map(|n| n as usize);
+        if vertices > MAX_MESH_VERTICES {
+            return Err(format!("meshes may have at most {MAX_MESH_VERTICES} vertices"));
+        }
+        if corners > MAX_MESH_CORNERS {
+            return Err(format!("meshes may have at most {MAX_MESH_CORNERS} face corners"));
+        }
+        if faces > corners {
+            return Err("more faces than face corners".to_string());
+        }
+        if (uvs != 0 && uvs != corners) || (normals != 0 && normals != corners) {
+            return Err("UVs and normals must be given for every face corner or not at all".to_string());
+        }
+        let expected = HEADER_LEN + 4 * (vertices * 3 + faces + corners + uvs * 2 + normals * 3);
+        if bytes.len() != expected {
+            return Err(format!("mesh is {} bytes, its counts need {expected}", bytes.len()));
+        }
+
+        let mut words = bytes[HEADER_LEN..].chunks_exact(4).map(|word| [word[0], word[1], word[2], word[3]]);
+        let mut u32s = |n: usize| (&mut words).take(n).map(u32::from_le_bytes).collect::<Vec<u32>>();
+        let positions = u32s(vertices * 3);
+        let face_sizes = u32s(faces);
+        let face_vertices = u32s(corners);
+        let uv_values = u32s(uvs * 2);
+        let normal_values = u32s(normals * 3);
+        let floats = |values: &[u32]| values.iter().map(|bits| f32::from_bits(*bits)).collect::<Vec<f32>>();
+        Ok(MeshGeometry {
+            positions: floats(&positions).chunks_exact(3).map(|p| [p[0], p[1], p[2]]).collect(),
+            face_sizes,
+            face_vertices,
+            uvs: floats(&uv_values).chunks_exact(2).map(|uv| [uv[0], uv[1]]).collect(),
+            normals: floats(&normal_values).chunks_exact(3).map(|n| [n[0], n[1], n[2]]).collect(),
+        })
+    }
+
+    /// Faces have at least three corners that add up to the corner count, every corner
+    /// names an existing vertex, and every number is finite.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.positions.len() > MAX_MESH_VERTICES {
+            return Err(format!("meshes may have at most {MAX_MESH_VERTICES} vertices"));
+        }
+        if self.face_vertices.len() > MAX_MESH_CORNERS {
+            return Err(format!("meshes may have at most {MAX_MESH_CORNERS} face corners"));
+        }
+        if self.face_sizes.iter().any(|&size| size < 3) {
+            return Err("faces need at least three corners".to_string());
+        }
+        if self.face_sizes.iter().map(|&size| u64::from(size)).sum::<u64>() != self.face_vertices.len() as u64 {
+            return Err("face sizes do not add up to the face corners".to_string());
+        }
+        if self.face_vertices.iter().any(|&index| index as usize >= self.positions.len()) {
+            return Err("a face corner names a vertex the mesh does not have".to_string());
+        }
+        let corners = self.face_vertices.len();
+        if (!self.uvs.is_empty() && self.uvs.len() != corners) || (!self.normals.is_empty() && self.normals.len() != corners) {
+            return Err("UVs and normals must be given for every face corner or not at all".to_string());
+        }
+        let mut values = self.positions.iter().flatten().chain(self.uvs.iter().flatten()).chain(self.normals.iter().flatten());
+        if !values.all(|value| value.is_finite()) {
+            return Err("mesh data must be finite numbers".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Lowercase hex SHA-256 of a mesh encoding.
+pub fn content_hash(encoded: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(encoded))
+}
+
+/// Whether `hash` looks like a `content_hash`.
+pub fn is_content_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// A mesh as a session keeps it: the encoding, deflated. Serialized as a base64 string.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredMesh {
+    pub compressed: Vec<u8>,
+}
+
+impl StoredMesh {
+    /// Store `geometry`, returning its content hash too.
+    pub fn new(geometry: &MeshGeometry) -> (String, StoredMesh) {
+        let encoded = geometry.encode();
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        // Writing to a Vec cannot fail.
+        let _ = encoder.write_all(&encoded);
+        let compressed = encoder.finish().unwrap_or_default();
+        (content_hash(&encoded), StoredMesh { compressed })
+    }
+
+    /// The encoding, inflated, refusing anything larger than the limits allow.
+    pub fn inflate(&self) -> Result<Vec<u8>, String> {
+        let mut encoded = Vec::new();
+        DeflateDecoder::new(self.compressed.as_slice())
+            .take(MAX_ENCODED_LEN as u64 + 1)
+            .read_to_end(&mut encoded)
+            .map_err(|err| format!("mesh data does not inflate: {err}"))?;
+        if encoded.len() > MAX_ENCODED_LEN {
+            return Err("mesh data inflates past the mesh limits".to_string());
+        }
+        Ok(encoded)
+    }
+
+    pub fn geometry(&self) -> Result<MeshGeometry, String> {
+        MeshGeometry::decode(&self.inflate()?)
+    }
+
+    pub fn byte_len(&self) -> usize {
+        self.compressed.len()
+    }
+
+    /// The compressed bytes as base64 chunks of at most MESH_CHUNK_LEN bytes each.
+    pub fn chunks(&self) -> Vec<String> {
+        self.compressed.chunks(MESH_CHUNK_LEN).map(|chunk| BASE64.encode(chunk)).collect()
+    }
+}
+
+impl Serialize for StoredMesh {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(&self.compressed))
+    }
+}
+
+impl<'de> Deserialize<'de> for StoredMesh {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let compressed = BASE64.decode(text.as_bytes()).map_err(de::Error::custom)?;
+        Ok(StoredMesh { compressed })
+    }
+}
+
+/// Chunks of a mesh upload received so far.
+#[derive(Clone, Debug)]
+pub struct MeshUpload {
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+    pub started_at: u64, // unix timestamp ms
+}
+
+impl MeshUpload {
+    pub fn new(chunk_count: u32, started_at: u64) -> Self {
+        MeshUpload { chunks: vec![None; chunk_count as usize], received: 0, started_at }
+    }
+
+    pub fn chunk_count(&self) -> u32 {
+        self.chunks.len() as u32
+    }
+
+    /// Keep one base64 chunk; a chunk sent twice replaces the first. True once all are in.
+    pub fn add(&mut self, chunk_index: u32, data: &str) -> Result<bool, String> {
+        let bytes = BASE64.decode(data.as_bytes()).map_err(|err| format!("chunk is not base64: {err}"))?;
+        if bytes.is_empty() || bytes.len() > MESH_CHUNK_LEN {
+            return Err(format!("chunks must hold 1 to {MESH_CHUNK_LEN} bytes"));
+        }
+        let chunk_count = self.chunks.len();
+        let slot = self
+            .chunks
+            .get_mut(chunk_index as usize)
+            .ok_or_else(|| format!("chunk {chunk_index} is past the upload's {chunk_count} chunks"))?;
+        if slot.replace(bytes).is_none() {
+            self.received += 1;
+        }
+        Ok(self.received == self.chunks.len())
+    }
+
+    /// The complete upload, checked: it must inflate to a valid mesh whose hash is `hash`.
+    pub fn finish(self, hash: &str) -> Result<StoredMesh, String> {
+        let compressed: Vec<u8> = self.chunks.into_iter().flatten().flatten().collect();
+        let stored = StoredMesh { compressed };
+        let encoded = stored.inflate()?;
+        if content_hash(&encoded) != hash {
+            return Err("mesh data does not match its hash".to_string());
+        }
+        MeshGeometry::decode(&encoded)?.validate()?;
+        Ok(stored)
+    }
+}
+
+/// Validate the framing of an uploaded chunk before it is buffered.
+pub fn check_chunk(hash: &str, chunk_index: u32, chunk_count: u32) -> Result<(), String> {
+    if !is_content_hash(hash) {
+        return Err("mesh hashes are 64 lowercase hex digits".to_string());
+    }
+    if chunk_count == 0 || chunk_count > MAX_MESH_CHUNKS {
+        return Err(format!("uploads have 1 to {MAX_MESH_CHUNKS} chunks"));
+    }
+    if chunk_index >= chunk_count {
+        return Err(format!("chunk {chunk_index} is past the upload's {chunk_count} chunks"));
+    }
+    Ok(())
+}
+
+/// Make room for one more upload by dropping the oldest ones.
+pub fn trim_uploads(uploads: &mut HashMap<(Uuid, String), MeshUpload>) {
+    while uploads.len() >= MAX_PENDING_UPLOADS {
+        let Some(oldest) = uploads.iter().min_by_key(|(_, upload)| upload.started_at).map(|(key, _)| key.clone()) else {
+            return;
+        };
+        uploads.remove(&oldest);
+    }
+}
@@ -1,5 +1,10 @@
-use crate::types::{ObjectProperties, ObjectType, SceneObject, Session, Transform};
+use crate::audit::{AuditEntry, AuditQuery};
+use crate::types::{
+    Checkpoint, Collection, ColorTag, CustomValue, Material, MaterialSettings, MaterialTextures, MeshRef,
+    Modifier, ObjectProperties, ObjectType, SceneObject, Session, Transform, User,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 // ── Envelope ──────────────────────────────────────────────────────────────────
@@ -50,6 +55,21 @@ impl std::fmt::Debug for CreateSessionPayload {
     }
 }
 
+/// Copies the sender's current session into `new_session_id` and moves the sender there.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ForkSessionPayload {
+    pub new_session_id: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for ForkSessionPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ForkSessionPayload")
+            .field("new_session_id", &self.new_session_id)
+            .field("password", &"***REDACTED***")
+            .finish()
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CreateObjectPayload {
@@ -58,13 +78,20 @@ pub struct CreateObjectPayload {
     pub object_type: ObjectType,
     pub asset_id: Option<String>,
     pub asset_library: Option<String>,
+    /// Creates the object already parented; `transform` is then relative to the parent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<Uuid>,
     pub transform: Transform,
     pub properties: Option<ObjectProperties>,
 }
 
+/// Children of a deleted object move up to its parent, keeping their world placement, unless
+/// `delete_children` is set, in which case its whole subtree goes with it.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct DeleteObjectPayload {
     pub object_id: Uuid,
+    #[serde(default)]
+    pub delete_children: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -85,11 +112,244 @@ pub struct UpdateNamePayload {
     pub name: String,
 }
 
+/// Parent an object to `parent_id`, or unparent it with `None`. Without a `transform` the
+/// object keeps its world placement; with one, that becomes its transform relative to the new
+/// parent.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SetParentPayload {
+    pub object_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<Transform>,
+}
+
+/// New collections are visible and selectable unless the client says otherwise.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CreateCollectionPayload {
+    pub collection_id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub color_tag: ColorTag,
+    #[serde(default = "default_true")]
+    pub visible: bool,
+    #[serde(default = "default_true")]
+    pub selectable: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Rename a collection or change its flags; fields left out keep their value.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UpdateCollectionPayload {
+    pub collection_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_tag: Option<ColorTag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visible: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selectable: Option<bool>,
+}
+
+/// Delete a collection. Its objects stay in the scene, just no longer in it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeleteCollectionPayload {
+    pub collection_id: Uuid,
+}
+
+/// Replace the collections an object belongs to; an empty list takes it out of all of them.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SetObjectCollectionsPayload {
+    pub object_id: Uuid,
+    pub collection_ids: Vec<Uuid>,
+}
+
+/// Add or replace one of an object's custom properties.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SetCustomPropertyPayload {
+    pub object_id: Uuid,
+    pub key: String,
+    pub value: CustomValue,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RemoveCustomPropertyPayload {
+    pub object_id: Uuid,
+    pub key: String,
+}
+
+/// Add a material to the session's table; settings left out take Blender's defaults.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CreateMaterialPayload {
+    pub material_id: Uuid,
+    pub name: String,
+    #[serde(flatten)]
+    pub settings: MaterialSettings,
+}
+
+/// Rename a material or change its settings; fields left out keep their value. `textures`,
+/// when present, replaces all of the material's textures.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct UpdateMaterialPayload {
+    pub material_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_color: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metallic: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roughness: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emission_color: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emission_strength: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub textures: Option<MaterialTextures>,
+}
+
+/// Replace an object's material slots; None leaves a slot empty.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SetObjectMaterialsPayload {
+    pub object_id: Uuid,
+    pub material_slots: Vec<Option<Uuid>>,
+}
+
+/// One piece of a mesh's deflated encoding (see mesh.rs), base64. Uploads send every chunk
+/// of a mesh before SetObjectMesh references it; downloads answer RequestMesh the same way.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MeshChunkPayload {
+    pub hash: String,
+    pub chunk_index: u32,
+    pub chunk_count: u32,
+    pub data: String,
+}
+
+/// Point an object at a stored mesh, or back at its primitive with `hash: None`.
+/// `base_version` is the mesh version the edit was made against; a stale one is refused.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SetObjectMeshPayload {
+    pub object_id: Uuid,
+    pub hash: Option<String>,
+    #[serde(default)]
+    pub base_version: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RequestMeshPayload {
+    pub hash: String,
+}
+
+/// Insert a modifier into an object's stack at `index`, or at the end when it is left out.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AddModifierPayload {
+    pub object_id: Uuid,
+    pub modifier: Modifier,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RemoveModifierPayload {
+    pub object_id: Uuid,
+    pub modifier_id: Uuid,
+}
+
+/// Put an object's modifiers in a new order; `modifier_ids` lists every one of them once.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReorderModifiersPayload {
+    pub object_id: Uuid,
+    pub modifier_ids: Vec<Uuid>,
+}
+
+/// Replace the modifier with the same id, keeping its place in the stack. Its type cannot
+/// change, as in Blender.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UpdateModifierPayload {
+    pub object_id: Uuid,
+    pub modifier: Modifier,
+}
+
+/// One edit to an object's property document (see property_patch): `value` goes at the
+/// dot-separated `path`, e.g. `spot.angle`, creating objects along the way. A null or missing
+/// value removes what is there.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PropertyPatch {
+    pub path: String,
+    #[serde(default)]
+    pub value: Option<serde_json::Value>,
+}
+
+/// Apply patches to an object's properties, in order and all or none.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PatchPropertiesPayload {
+    pub object_id: Uuid,
+    pub patches: Vec<PropertyPatch>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SelectObjectPayload {
     pub object_id: Option<Uuid>, // None means deselect
 }
 
+/// Optional RequestStateSync payload. With `since_version` set, the server answers with a
+/// StateDelta when it still has history back to that version, otherwise with a full sync.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RequestStateSyncPayload {
+    pub since_version: u64,
+}
+
+/// One step of a Transaction. Same payloads as the standalone events.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "op", content = "payload")]
+pub enum TransactionOp {
+    CreateObject(CreateObjectPayload),
+    DeleteObject(DeleteObjectPayload),
+    UpdateTransform(UpdateTransformPayload),
+    UpdateProperties(UpdatePropertiesPayload),
+    UpdateName(UpdateNamePayload),
+    SetParent(SetParentPayload),
+}
+
+/// Ordered operations applied all-or-nothing under a single session mutation.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TransactionPayload {
+    pub transaction_id: Uuid,
+    pub operations: Vec<TransactionOp>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CreateCheckpointPayload {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RestoreCheckpointPayload {
+    pub checkpoint_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RestoreObjectPayload {
+    pub object_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RequestChecksumPayload {
+    #[serde(default)]
+    pub include_objects: bool, // also return per-object hashes
+}
+
+/// Targeted resync of objects a client found to differ after comparing checksums.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RequestObjectsSyncPayload {
+    pub object_ids: Vec<Uuid>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CursorPayload {
     pub position: [f64; 3],
@@ -113,9 +373,89 @@ pub enum ClientEvent {
     UpdateTransform(UpdateTransformPayload),
     UpdateProperties(UpdatePropertiesPayload),
     UpdateName(UpdateNamePayload),
+    SetParent(SetParentPayload),
+    CreateCollection(CreateCollectionPayload),
+    UpdateCollection(UpdateCollectionPayload),
+    DeleteCollection(DeleteCollectionPayload),
+    SetObjectCollections(SetObjectCollectionsPayload),
+    SetCustomProperty(SetCustomPropertyPayload),
+    RemoveCustomProperty(RemoveCustomPropertyPayload),
+    PatchProperties(PatchPropertiesPayload),
+    CreateMaterial(CreateMaterialPayload),
+    UpdateMaterial(UpdateMaterialPayload),
+    SetObjectMaterials(SetObjectMaterialsPayload),
+    UploadMeshChunk(MeshChunkPayload),
+    SetObjectMesh(SetObjectMeshPayload),
+    RequestMesh(RequestMeshPayload),
+    AddModifier(AddModifierPayload),
+    RemoveModifier(RemoveModifierPayload),
+    ReorderModifiers(ReorderModifiersPayload),
+    UpdateModifier(UpdateModifierPayload),
     SelectObject(SelectObjectPayload),
-    RequestStateSync,
+    RequestStateSync(Option<RequestStateSyncPayload>), // None requests a full sync
     UpdateCursor(CursorPayload),
+    RequestChecksum(RequestChecksumPayload),
+    RequestObjectsSync(RequestObjectsSyncPayload),
+    Transaction(TransactionPayload),
+    Undo, // reverts the sender's most recent change
+    Redo,
+    CreateCheckpoint(CreateCheckpointPayload),
+    ListCheckpoints,
+    RestoreCheckpoint(RestoreCheckpointPayload),
+    ForkSession(ForkSessionPayload),
+    ListTrash,
+    RestoreObject(RestoreObjectPayload), // brings a trashed object back
+    QueryAuditLog(AuditQuery),
+}
+
+impl ClientEvent {
+    /// True for events that change a scene or bring a session into existence; a read-only
+    /// server (replay mode) refuses these.
+    pub fn is_mutation(&self) -> bool {
+        match self {
+            ClientEvent::CreateSession(_)
+            | ClientEvent::ForkSession(_)
+            | ClientEvent::CreateObject(_)
+            | ClientEvent::DeleteObject(_)
+            | ClientEvent::UpdateTransform(_)
+            | ClientEvent::UpdateProperties(_)
+            | ClientEvent::UpdateName(_)
+            | ClientEvent::SetParent(_)
+            | ClientEvent::CreateCollection(_)
+            | ClientEvent::UpdateCollection(_)
+            | ClientEvent::DeleteCollection(_)
+            | ClientEvent::SetObjectCollections(_)
+            | ClientEvent::SetCustomProperty(_)
+            | ClientEvent::RemoveCustomProperty(_)
+            | ClientEvent::PatchProperties(_)
+            | ClientEvent::CreateMaterial(_)
+            | ClientEvent::UpdateMaterial(_)
+            | ClientEvent::SetObjectMaterials(_)
+            | ClientEvent::UploadMeshChunk(_)
+            | ClientEvent::SetObjectMesh(_)
+            | ClientEvent::AddModifier(_)
+            | ClientEvent::RemoveModifier(_)
+            | ClientEvent::ReorderModifiers(_)
+            | ClientEvent::UpdateModifier(_)
+            | ClientEvent::Transaction(_)
+            | ClientEvent::Undo
+            | ClientEvent::Redo
+            | ClientEvent::CreateCheckpoint(_)
+            | ClientEvent::RestoreCheckpoint(_)
+            | ClientEvent::RestoreObject(_) => true,
+            ClientEvent::JoinSession(_)
+            | ClientEvent::LeaveSession
+            | ClientEvent::SelectObject(_)
+            | ClientEvent::RequestStateSync(_)
+            | ClientEvent::UpdateCursor(_)
+            | ClientEvent::RequestChecksum(_)
+            | ClientEvent::RequestObjectsSync(_)
+            | ClientEvent::RequestMesh(_)
+            | ClientEvent::ListCheckpoints
+            | ClientEvent::ListTrash
+            | ClientEvent::QueryAuditLog(_) => false,
+        }
+    }
 }
 
 // ── Server → Client payloads ──────────────────────────────────────────────────
@@ -126,10 +466,77 @@ pub struct FullStateSyncPayload {
     pub your_user_id: Uuid,
 }
 
+/// First frame of a streamed state sync, sent instead of FullStateSync when the scene
+/// is too large for a single frame. Objects follow in `chunk_count` StateSyncChunk frames.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StateSyncBeginPayload {
+    pub session_id: String,
+    pub your_user_id: Uuid,
+    pub users: HashMap<Uuid, User>,
+    #[serde(default)]
+    pub collections: HashMap<Uuid, Collection>,
+    #[serde(default)]
+    pub materials: HashMap<Uuid, Material>,
+    pub object_count: usize,
+    pub chunk_count: usize,
+    pub version: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StateSyncChunkPayload {
+    pub chunk_index: usize,
+    pub objects: Vec<SceneObject>,
+}
+
+/// Closes a streamed state sync. `checksum` covers every object sent in the chunks
+/// (see checksum::objects_checksum) so the client can verify it reassembled the scene.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StateSyncEndPayload {
+    pub object_count: usize,
+    pub checksum: String,
+}
+
+/// Incremental answer to RequestStateSync: objects created or updated since `from_version`
+/// (current state) and tombstones for objects deleted since then. Collections and materials
+/// are not versioned, so the whole current sets come along.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StateDeltaPayload {
+    pub from_version: u64,
+    pub to_version: u64,
+    pub upserted: Vec<SceneObject>,
+    pub deleted: Vec<Uuid>,
+    #[serde(default)]
+    pub collections: HashMap<Uuid, Collection>,
+    #[serde(default)]
+    pub materials: HashMap<Uuid, Material>,
+}
+
+/// Order-independent hash of the session objects (see checksum::object_hash for the
+/// canonical per-object form). Broadcast periodically and sent on RequestChecksum.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StateChecksumPayload {
+    pub version: u64,
+    pub object_count: usize,
+    pub checksum: String,
+    pub object_hashes: Option<HashMap<Uuid, String>>,
+}
+
+/// Answer to RequestObjectsSync: current state of the requested objects.
+/// Ids the server does not know are listed in `missing` so the client can drop them.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObjectsSyncedPayload {
+    pub version: u64,
+    pub objects: Vec<SceneObject>,
+    pub missing: Vec<Uuid>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ObjectCreatedPayload {
     pub object: SceneObject,
     pub created_by: Uuid,
+    /// Set when the object came back from the trash; `created_by` is then the original creator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restored_by: Option<Uuid>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -159,6 +566,214 @@ pub struct NameUpdatedPayload {
     pub updated_by: Uuid,
 }
 
+/// Broadcast when an object is parented or unparented, including children moved up by a
+/// DeleteObject, with its new transform relative to the new parent.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ParentUpdatedPayload {
+    pub object_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub transform: Transform,
+    pub updated_by: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CollectionCreatedPayload {
+    pub collection: Collection,
+    pub created_by: Uuid,
+}
+
+/// Broadcast when a collection is renamed or has its color tag or flags changed, with its
+/// full new state.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CollectionUpdatedPayload {
+    pub collection: Collection,
+    pub updated_by: Uuid,
+}
+
+/// Broadcast when a collection is deleted. Clients drop it from every object's collections;
+/// the server already has.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CollectionDeletedPayload {
+    pub collection_id: Uuid,
+    pub deleted_by: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObjectCollectionsUpdatedPayload {
+    pub object_id: Uuid,
+    pub collection_ids: Vec<Uuid>,
+    pub updated_by: Uuid,
+}
+
+/// Broadcast when a custom property is set or, with `value` None, removed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CustomPropertyUpdatedPayload {
+    pub object_id: Uuid,
+    pub key: String,
+    pub value: Option<CustomValue>,
+    pub updated_by: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MaterialCreatedPayload {
+    pub material: Material,
+    pub created_by: Uuid,
+}
+
+/// Broadcast when a material is renamed or its settings change, with its full new state.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MaterialUpdatedPayload {
+    pub material: Material,
+    pub updated_by: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObjectMaterialsUpdatedPayload {
+    pub object_id: Uuid,
+    pub material_slots: Vec<Option<Uuid>>,
+    pub updated_by: Uuid,
+}
+
+/// Sent to the uploader once every chunk of a mesh is in and the mesh is stored.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MeshStoredPayload {
+    pub hash: String,
+}
+
+/// Broadcast when an object's mesh changes; `mesh` is None when it went back to its primitive.
+/// `mesh_version` is what the next SetObjectMesh for the object must name as its base.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObjectMeshUpdatedPayload {
+    pub object_id: Uuid,
+    pub mesh: Option<MeshRef>,
+    #[serde(default)]
+    pub mesh_version: u64,
+    pub updated_by: Uuid,
+}
+
+/// Broadcast after any edit to an object's modifier stack, with the whole stack in order.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ObjectModifiersUpdatedPayload {
+    pub object_id: Uuid,
+    pub modifiers: Vec<Modifier>,
+    pub updated_by: Uuid,
+}
+
+/// Broadcast when PatchProperties changed an object, with the patches as sent.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PropertiesPatchedPayload {
+    pub object_id: Uuid,
+    pub patches: Vec<PropertyPatch>,
+    pub updated_by: Uuid,
+}
+
+/// Broadcast once per accepted Transaction, in place of the individual per-object events.
+/// `operations` are as applied: a DeleteObject's effect on children is spelled out as the
+/// SetParent or DeleteObject operations before it, and every SetParent carries its transform.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TransactionAppliedPayload {
+    pub transaction_id: Uuid,
+    pub operations: Vec<TransactionOp>,
+    pub applied_by: Uuid,
+    pub version: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryAction {
+    Undo,
+    Redo,
+}
+
+/// Net per-object result of a server-side bulk change such as an undo.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ObjectDiff {
+    pub created: Vec<SceneObject>,
+    pub updated: Vec<SceneObject>,
+    pub deleted: Vec<Uuid>,
+}
+
+/// Broadcast when a user's Undo/Redo is applied. Unlike the per-object events, the author's
+/// own client applies this too, since the change did not originate in its scene.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryAppliedPayload {
+    pub user_id: Uuid,
+    pub action: HistoryAction,
+    #[serde(flatten)]
+    pub diff: ObjectDiff,
+    /// Objects the step could not revert because someone else changed them since.
+    pub conflicts: Vec<Uuid>,
+    pub version: u64,
+}
+
+/// Checkpoint metadata without the object copy.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CheckpointInfo {
+    pub checkpoint_id: Uuid,
+    pub name: String,
+    pub created_by: Uuid,
+    pub created_at: u64,
+    pub version: u64,
+    pub object_count: usize,
+}
+
+impl From<&Checkpoint> for CheckpointInfo {
+    fn from(checkpoint: &Checkpoint) -> Self {
+        CheckpointInfo {
+            checkpoint_id: checkpoint.checkpoint_id,
+            name: checkpoint.name.clone(),
+            created_by: checkpoint.created_by,
+            created_at: checkpoint.created_at,
+            version: checkpoint.version,
+            object_count: checkpoint.objects.len(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CheckpointListPayload {
+    pub checkpoints: Vec<CheckpointInfo>,
+}
+
+/// Broadcast after RestoreCheckpoint with only the objects that had to change.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CheckpointRestoredPayload {
+    pub checkpoint: CheckpointInfo,
+    pub restored_by: Uuid,
+    #[serde(flatten)]
+    pub diff: ObjectDiff,
+    pub version: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TrashItem {
+    pub object: SceneObject,
+    pub deleted_by: Uuid,
+    pub deleted_at: u64,
+    pub expires_at: u64,
+}
+
+/// Reply to ListTrash, most recently deleted first.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TrashListPayload {
+    pub items: Vec<TrashItem>,
+}
+
+/// Reply to QueryAuditLog, newest first. `truncated` means older matches were left out.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuditLogPayload {
+    pub entries: Vec<AuditEntry>,
+    pub truncated: bool,
+}
+
+/// Told to the source session when someone branches it; the password is not shared.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionForkedPayload {
+    pub source_session_id: String,
+    pub new_session_id: String,
+    pub forked_by: Uuid,
+    pub object_count: usize,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct UserJoinedPayload {
     pub user_id: Uuid,
@@ -177,6 +792,16 @@ pub struct UserSelectedPayload {
     pub object_id: Option<Uuid>,
 }
 
+/// A client event refused without being applied, sent back whole so the client can send it
+/// again after `retry_after_ms`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EventRejectedPayload {
+    pub code: String,
+    pub message: String,
+    pub retry_after_ms: u64,
+    pub event: ClientEvent,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ErrorPayload {
     pub code: String,
@@ -185,19 +810,51 @@ pub struct ErrorPayload {
 
 // ── Server event enum ─────────────────────────────────────────────────────────
 
+// Events are serialized as soon as they are built and never stored, so the size of the
+// variants carrying a whole SceneObject is not worth boxing them for.
+#[allow(clippy::large_enum_variant)]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "event_type", content = "payload")]
 pub enum ServerEvent {
     FullStateSync(FullStateSyncPayload),
+    StateSyncBegin(StateSyncBeginPayload),
+    StateSyncChunk(StateSyncChunkPayload),
+    StateSyncEnd(StateSyncEndPayload),
+    StateDelta(StateDeltaPayload),
+    StateChecksum(StateChecksumPayload),
+    ObjectsSynced(ObjectsSyncedPayload),
     ObjectCreated(ObjectCreatedPayload),
     ObjectDeleted(ObjectDeletedPayload),
     TransformUpdated(TransformUpdatedPayload),
     PropertiesUpdated(PropertiesUpdatedPayload),
     NameUpdated(NameUpdatedPayload),
+    ParentUpdated(ParentUpdatedPayload),
+    CollectionCreated(CollectionCreatedPayload),
+    CollectionUpdated(CollectionUpdatedPayload),
+    CollectionDeleted(CollectionDeletedPayload),
+    ObjectCollectionsUpdated(ObjectCollectionsUpdatedPayload),
+    CustomPropertyUpdated(CustomPropertyUpdatedPayload),
+    PropertiesPatched(PropertiesPatchedPayload),
+    MaterialCreated(MaterialCreatedPayload),
+    MaterialUpdated(MaterialUpdatedPayload),
+    ObjectMaterialsUpdated(ObjectMaterialsUpdatedPayload),
+    MeshStored(MeshStoredPayload),
+    MeshChunk(MeshChunkPayload),
+    ObjectMeshUpdated(ObjectMeshUpdatedPayload),
+    ObjectModifiersUpdated(ObjectModifiersUpdatedPayload),
+    TransactionApplied(TransactionAppliedPayload),
+    HistoryApplied(HistoryAppliedPayload),
+    CheckpointCreated(CheckpointInfo),
+    CheckpointList(CheckpointListPayload),
+    CheckpointRestored(CheckpointRestoredPayload),
+    SessionForked(SessionForkedPayload),
+    TrashList(TrashListPayload),
+    AuditLog(AuditLogPayload),
     UserJoined(UserJoinedPayload),
     UserLeft(UserLeftPayload),
     UserSelected(UserSelectedPayload),
     CursorUpdated(UpdatedCursor),
+    EventRejected(EventRejectedPayload),
     Error(ErrorPayload),
 }
 
@@ -222,7 +879,7 @@ pub fn parse_client_message(raw: &str) -> Result<ClientEvent, serde_json::Error>
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{ObjectType, Transform};
+    use crate::types::{ArrayModifier, ModifierKind, ObjectType, SubdivisionModifier, Transform};
     use uuid::Uuid;
 
     fn round_trip_client(event: &ClientEvent) {
@@ -250,6 +907,19 @@ mod tests {
 
     // ── Client events ──────────────────────────────────────────────────────
 
+    #[test]
+    fn test_object_type_wire_names_match_serde() {
+        let all = [
+            ObjectType::Cube, ObjectType::Sphere, ObjectType::Cylinder, ObjectType::Plane, ObjectType::Circle,
+            ObjectType::Icosphere, ObjectType::Cone, ObjectType::Torus, ObjectType::Grid, ObjectType::Monkey,
+            ObjectType::Camera, ObjectType::PointLight, ObjectType::SpotLight, ObjectType::AreaLight,
+            ObjectType::SunLight, ObjectType::AssetRef, ObjectType::Mesh,
+        ];
+        for object_type in all {
+            assert_eq!(serde_json::to_value(&object_type).unwrap(), object_type.wire_name());
+        }
+    }
+
     #[test]
     fn test_join_session() {
         round_trip_client(&ClientEvent::JoinSession(JoinSessionPayload {
@@ -264,6 +934,77 @@ mod tests {
         round_trip_client(&ClientEvent::LeaveSession);
     }
 
+    #[test]
+    fn test_undo_redo() {
+        round_trip_client(&ClientEvent::Undo);
+        round_trip_client(&ClientEvent::Redo);
+    }
+
+    #[test]
+    fn test_checkpoint_events() {
+        round_trip_client(&ClientEvent::CreateCheckpoint(CreateCheckpointPayload {
+            name: "before layout pass".to_string(),
+        }));
+        round_trip_client(&ClientEvent::ListCheckpoints);
+        round_trip_client(&ClientEvent::RestoreCheckpoint(RestoreCheckpointPayload {
+            checkpoint_id: Uuid::new_v4(),
+        }));
+    }
+
+    #[test]
+    fn test_checkpoint_restored_flattens_diff() {
+        let event = ServerEvent::CheckpointRestored(CheckpointRestoredPayload {
+            checkpoint: CheckpointInfo {
+                checkpoint_id: Uuid::new_v4(),
+                name: "v1".to_string(),
+                created_by: Uuid::new_v4(),
+                created_at: 1,
+                version: 3,
+                object_count: 0,
+            },
+            restored_by: Uuid::new_v4(),
+            diff: ObjectDiff { deleted: vec![Uuid::new_v4()], ..ObjectDiff::default() },
+            version: 4,
+        });
+        round_trip_server(&event);
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["payload"]["deleted"].as_array().map(Vec::len), Some(1));
+    }
+
+    #[test]
+    fn test_fork_session_hides_password_in_debug() {
+        let event = ClientEvent::ForkSession(ForkSessionPayload {
+            new_session_id: "shot-01-alt".to_string(),
+            password: "hunter2".to_string(),
+        });
+        round_trip_client(&event);
+        assert!(!format!("{:?}", event).contains("hunter2"));
+    }
+
+    #[test]
+    fn test_trash_events() {
+        round_trip_client(&ClientEvent::ListTrash);
+        round_trip_client(&ClientEvent::RestoreObject(RestoreObjectPayload { object_id: Uuid::new_v4() }));
+        // Plain creates omit restored_by, and payloads from older servers without it still parse.
+        let json = r#"{"event_type":"ObjectCreated","payload":{"object":{"object_id":"00000000-0000-0000-0000-000000000001","name":"Cube","object_type":"Cube","asset_id":null,"asset_library":null,"transform":{"position":[0,0,0],"rotation":[0,0,0],"scale":[1,1,1]},"properties":null,"created_by":"00000000-0000-0000-0000-000000000002","last_updated_by":"00000000-0000-0000-0000-000000000002","last_updated_at":0},"created_by":"00000000-0000-0000-0000-000000000002"}}"#;
+        let event: ServerEvent = serde_json::from_str(json).unwrap();
+        assert!(matches!(&event, ServerEvent::ObjectCreated(p) if p.restored_by.is_none()));
+        round_trip_server(&event);
+    }
+
+    #[test]
+    fn test_query_audit_log() {
+        round_trip_client(&ClientEvent::QueryAuditLog(AuditQuery {
+            object_id: Some(Uuid::new_v4()),
+            since: Some(1_700_000_000_000),
+            ..AuditQuery::default()
+        }));
+        // Every filter is optional.
+        let event = parse_client_message(r#"{"event_type":"QueryAuditLog","timestamp":0,"source_user_id":"00000000-0000-0000-0000-000000000000","payload":{}}"#)
+            .expect("empty filter should parse");
+        assert!(matches!(event, ClientEvent::QueryAuditLog(q) if q.object_id.is_none() && q.limit.is_none()));
+    }
+
     #[test]
     fn test_create_object() {
         round_trip_client(&ClientEvent::CreateObject(CreateObjectPayload {
@@ -272,6 +1013,7 @@ mod tests {
             object_type: ObjectType::Cube,
             asset_id: None,
             asset_library: None,
+            parent_id: None,
             transform: dummy_transform(),
             properties: None,
         }));
@@ -281,7 +1023,205 @@ mod tests {
     fn test_delete_object() {
         round_trip_client(&ClientEvent::DeleteObject(DeleteObjectPayload {
             object_id: Uuid::new_v4(),
+            delete_children: true,
+        }));
+        // Older clients leave delete_children out and get the reparenting delete.
+        let event = parse_client_message(r#"{"event_type":"DeleteObject","timestamp":0,"source_user_id":"00000000-0000-0000-0000-000000000000","payload":{"object_id":"00000000-0000-0000-0000-000000000001"}}"#)
+            .expect("DeleteObject without delete_children should parse");
+        assert!(matches!(event, ClientEvent::DeleteObject(p) if !p.delete_children));
+    }
+
+    #[test]
+    fn test_set_parent() {
+        round_trip_client(&ClientEvent::SetParent(SetParentPayload {
+            object_id: Uuid::new_v4(),
+            parent_id: Some(Uuid::new_v4()),
+            transform: None,
+        }));
+        round_trip_server(&ServerEvent::ParentUpdated(ParentUpdatedPayload {
+            object_id: Uuid::new_v4(),
+            parent_id: None,
+            transform: dummy_transform(),
+            updated_by: Uuid::new_v4(),
+        }));
+    }
+
+    #[test]
+    fn test_custom_property_events() {
+        round_trip_client(&ClientEvent::SetCustomProperty(SetCustomPropertyPayload {
+            object_id: Uuid::new_v4(),
+            key: "tint".to_string(),
+            value: CustomValue::Vector(vec![1.0, 0.5, 0.25]),
+        }));
+        round_trip_client(&ClientEvent::RemoveCustomProperty(RemoveCustomPropertyPayload {
+            object_id: Uuid::new_v4(),
+            key: "tint".to_string(),
+        }));
+        let event = ServerEvent::CustomPropertyUpdated(CustomPropertyUpdatedPayload {
+            object_id: Uuid::new_v4(),
+            key: "tags".to_string(),
+            value: Some(CustomValue::StringList(vec!["hero".to_string()])),
+            updated_by: Uuid::new_v4(),
+        });
+        round_trip_server(&event);
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["payload"]["value"], serde_json::json!({"StringList": ["hero"]}));
+    }
+
+    #[test]
+    fn test_property_patch_events() {
+        round_trip_client(&ClientEvent::PatchProperties(PatchPropertiesPayload {
+            object_id: Uuid::new_v4(),
+            patches: vec![PropertyPatch { path: "spot.angle".to_string(), value: Some(serde_json::json!(0.7)) }],
+        }));
+        // A patch without a value removes the path.
+        let event = parse_client_message(r#"{"event_type":"PatchProperties","timestamp":0,"source_user_id":"00000000-0000-0000-0000-000000000000","payload":{"object_id":"00000000-0000-0000-0000-000000000001","patches":[{"path":"render.holdout"}]}}"#)
+            .expect("PatchProperties without a value should parse");
+        assert!(matches!(event, ClientEvent::PatchProperties(p) if p.patches[0].value.is_none()));
+        round_trip_server(&ServerEvent::PropertiesPatched(PropertiesPatchedPayload {
+            object_id: Uuid::new_v4(),
+            patches: vec![PropertyPatch { path: "render.holdout".to_string(), value: Some(serde_json::json!(true)) }],
+            updated_by: Uuid::new_v4(),
+        }));
+    }
+
+    #[test]
+    fn test_material_events() {
+        round_trip_client(&ClientEvent::UpdateMaterial(UpdateMaterialPayload {
+            material_id: Uuid::new_v4(),
+            roughness: Some(0.2),
+            ..UpdateMaterialPayload::default()
+        }));
+        round_trip_client(&ClientEvent::SetObjectMaterials(SetObjectMaterialsPayload {
+            object_id: Uuid::new_v4(),
+            material_slots: vec![Some(Uuid::new_v4()), None],
+        }));
+
+        // Settings left out of CreateMaterial take Blender's defaults.
+        let raw = r#"{"event_type":"CreateMaterial","timestamp":0,"source_user_id":"00000000-0000-0000-0000-000000000000","payload":{"material_id":"00000000-0000-0000-0000-000000000001","name":"Clay","roughness":0.9}}"#;
+        match parse_client_message(raw) {
+            Ok(ClientEvent::CreateMaterial(p)) => {
+                assert_eq!(p.settings, MaterialSettings { roughness: 0.9, ..MaterialSettings::default() });
+            }
+            other => panic!("expected CreateMaterial, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_mesh_events() {
+        let hash = "ab".repeat(32);
+        round_trip_client(&ClientEvent::UploadMeshChunk(MeshChunkPayload {
+            hash: hash.clone(),
+            chunk_index: 0,
+            chunk_count: 2,
+            data: "AAEC".to_string(),
+        }));
+        round_trip_client(&ClientEvent::RequestMesh(RequestMeshPayload { hash: hash.clone() }));
+        round_trip_server(&ServerEvent::ObjectMeshUpdated(ObjectMeshUpdatedPayload {
+            object_id: Uuid::new_v4(),
+            mesh: Some(MeshRef { hash }),
+            mesh_version: 3,
+            updated_by: Uuid::new_v4(),
+        }));
+
+        // A missing base_version means the sender did not know of any mesh.
+        let raw = r#"{"event_type":"SetObjectMesh","timestamp":0,"source_user_id":"00000000-0000-0000-0000-000000000000","payload":{"object_id":"00000000-0000-0000-0000-000000000001","hash":null}}"#;
+        match parse_client_message(raw) {
+            Ok(ClientEvent::SetObjectMesh(p)) => {
+                assert_eq!(p.hash, None);
+                assert_eq!(p.base_version, None);
+            }
+            other => panic!("expected SetObjectMesh, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_modifier_events() {
+        let modifier = Modifier {
+            modifier_id: Uuid::new_v4(),
+            name: "Array".to_string(),
+            show_viewport: true,
+            show_render: false,
+            kind: ModifierKind::Array(ArrayModifier { count: 4, ..ArrayModifier::default() }),
+        };
+        let object_id = Uuid::new_v4();
+        round_trip_client(&ClientEvent::AddModifier(AddModifierPayload {
+            object_id,
+            modifier: modifier.clone(),
+            index: Some(0),
+        }));
+        round_trip_client(&ClientEvent::RemoveModifier(RemoveModifierPayload {
+            object_id,
+            modifier_id: modifier.modifier_id,
+        }));
+        round_trip_client(&ClientEvent::ReorderModifiers(ReorderModifiersPayload {
+            object_id,
+            modifier_ids: vec![modifier.modifier_id],
+        }));
+        round_trip_client(&ClientEvent::UpdateModifier(UpdateModifierPayload { object_id, modifier: modifier.clone() }));
+        let event = ServerEvent::ObjectModifiersUpdated(ObjectModifiersUpdatedPayload {
+            object_id,
+            modifiers: vec![modifier],
+            updated_by: Uuid::new_v4(),
+        });
+        round_trip_server(&event);
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["payload"]["modifiers"][0]["modifier_type"], "Array");
+        assert_eq!(value["payload"]["modifiers"][0]["count"], 4);
+
+        // Settings left out take Blender's defaults, and other modifiers carry their parameters.
+        let raw = r#"{"event_type":"AddModifier","timestamp":0,"source_user_id":"00000000-0000-0000-0000-000000000000","payload":{"object_id":"00000000-0000-0000-0000-000000000001","modifier":{"modifier_id":"00000000-0000-0000-0000-000000000002","name":"Subdivision","modifier_type":"Subdivision","levels":2}}}"#;
+        match parse_client_message(raw) {
+            Ok(ClientEvent::AddModifier(p)) => {
+                assert_eq!(p.index, None);
+                assert!(p.modifier.show_viewport && p.modifier.show_render);
+                assert_eq!(p.modifier.kind, ModifierKind::Subdivision(SubdivisionModifier {
+                    levels: 2,
+                    ..SubdivisionModifier::default()
+                }));
+            }
+            other => panic!("expected AddModifier, got {:?}", other),
+        }
+        let raw = r#"{"event_type":"UpdateModifier","timestamp":0,"source_user_id":"00000000-0000-0000-0000-000000000000","payload":{"object_id":"00000000-0000-0000-0000-000000000001","modifier":{"modifier_id":"00000000-0000-0000-0000-000000000002","name":"Solidify","modifier_type":"Other","blender_type":"SOLIDIFY","parameters":{"thickness":{"Number":0.05}}}}}"#;
+        match parse_client_message(raw) {
+            Ok(ClientEvent::UpdateModifier(p)) => match p.modifier.kind {
+                ModifierKind::Other { blender_type, parameters } => {
+                    assert_eq!(blender_type, "SOLIDIFY");
+                    assert_eq!(parameters["thickness"], CustomValue::Number(0.05));
+                }
+                other => panic!("expected an Other modifier, got {:?}", other),
+            },
+            other => panic!("expected UpdateModifier, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_collection_events() {
+        round_trip_client(&ClientEvent::UpdateCollection(UpdateCollectionPayload {
+            collection_id: Uuid::new_v4(),
+            name: None,
+            color_tag: Some(ColorTag::Color05),
+            visible: Some(false),
+            selectable: None,
+        }));
+        round_trip_client(&ClientEvent::SetObjectCollections(SetObjectCollectionsPayload {
+            object_id: Uuid::new_v4(),
+            collection_ids: vec![Uuid::new_v4()],
         }));
+        round_trip_server(&ServerEvent::CollectionDeleted(CollectionDeletedPayload {
+            collection_id: Uuid::new_v4(),
+            deleted_by: Uuid::new_v4(),
+        }));
+
+        // Omitted flags leave a new collection visible and selectable, untagged.
+        let raw = r#"{"event_type":"CreateCollection","timestamp":0,"source_user_id":"00000000-0000-0000-0000-000000000000","payload":{"collection_id":"00000000-0000-0000-0000-000000000001","name":"Props"}}"#;
+        match parse_client_message(raw) {
+            Ok(ClientEvent::CreateCollection(p)) => {
+                assert_eq!(p.color_tag, ColorTag::None);
+                assert!(p.visible && p.selectable);
+            }
+            other => panic!("expected CreateCollection, got {:?}", other),
+        }
     }
 
     #[test]
@@ -300,6 +1240,47 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn test_request_state_sync() {
+        round_trip_client(&ClientEvent::RequestStateSync(None));
+        round_trip_client(&ClientEvent::RequestStateSync(Some(RequestStateSyncPayload {
+            since_version: 42,
+        })));
+    }
+
+    #[test]
+    fn test_request_state_sync_null_payload() {
+        // The Blender plugin sends `"payload": null` for a plain full sync.
+        let raw = r#"{"event_type":"RequestStateSync","timestamp":0,"source_user_id":"00000000-0000-0000-0000-000000000000","payload":null}"#;
+        assert!(matches!(parse_client_message(raw), Ok(ClientEvent::RequestStateSync(None))));
+    }
+
+    #[test]
+    fn test_request_checksum_defaults_to_summary_only() {
+        let raw = r#"{"event_type":"RequestChecksum","timestamp":0,"source_user_id":"00000000-0000-0000-0000-000000000000","payload":{}}"#;
+        match parse_client_message(raw) {
+            Ok(ClientEvent::RequestChecksum(p)) => assert!(!p.include_objects),
+            other => panic!("expected RequestChecksum, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_transaction() {
+        round_trip_client(&ClientEvent::Transaction(TransactionPayload {
+            transaction_id: Uuid::new_v4(),
+            operations: vec![
+                TransactionOp::UpdateTransform(UpdateTransformPayload {
+                    object_id: Uuid::new_v4(),
+                    transform: dummy_transform(),
+                }),
+                TransactionOp::DeleteObject(DeleteObjectPayload {
+                    object_id: Uuid::new_v4(),
+                    delete_children: false,
+                }),
+            ],
+        }));
+    }
+
     #[test]
     fn test_select_object() {
         round_trip_client(&ClientEvent::SelectObject(SelectObjectPayload {
@@ -357,6 +1338,22 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn test_state_sync_chunk_server() {
+        round_trip_server(&ServerEvent::StateSyncChunk(StateSyncChunkPayload {
+            chunk_index: 2,
+            objects: Vec::new(),
+        }));
+    }
+
+    #[test]
+    fn test_state_sync_end_server() {
+        round_trip_server(&ServerEvent::StateSyncEnd(StateSyncEndPayload {
+            object_count: 512,
+            checksum: "00000000deadbeef".to_string(),
+        }));
+    }
+
     #[test]
     fn test_error_server() {
         round_trip_server(&ServerEvent::Error(ErrorPayload {
@@ -364,4 +1361,14 @@ mod tests {
             message: "Session has reached max users".to_string(),
         }));
     }
+
+    #[test]
+    fn test_event_rejected_server() {
+        round_trip_server(&ServerEvent::EventRejected(EventRejectedPayload {
+            code: "RATE_LIMITED".to_string(),
+            message: "Too many Edit events; retry later".to_string(),
+            retry_after_ms: 10,
+            event: ClientEvent::UpdateName(UpdateNamePayload { object_id: Uuid::new_v4(), name: "Cube".to_string() }),
+        }));
+    }
 }
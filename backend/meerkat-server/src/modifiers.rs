@@ -0,0 +1,165 @@
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::custom_properties;
+use crate::types::{
+    Modifier, ModifierKind, ObjectType, SceneObject, MAX_ARRAY_COUNT, MAX_BEVEL_SEGMENTS, MAX_MODIFIERS,
+    MAX_MODIFIER_NAME_LEN, MAX_MODIFIER_PARAMETERS, MAX_SUBDIVISION_LEVELS,
+};
+
+// Rules for object modifier stacks. A stack is checked as a whole after every edit, so the
+// handlers only have to say how the stack changes.
+
+/// Types with typed settings; an `Other` modifier may not claim one of them.
+const TYPED: [&str; 4] = ["ARRAY", "MIRROR", "BEVEL", "SUBSURF"];
+const MAX_BLENDER_TYPE_LEN: usize = 64;
+
+/// Why an edit to a stack was refused, with the error code it is reported under.
+pub enum StackError {
+    Invalid(String),
+    NotFound(Uuid),
+    Duplicate(Uuid),
+    Limit,
+}
+
+impl StackError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            StackError::Invalid(_) => "INVALID_MODIFIER",
+            StackError::NotFound(_) => "MODIFIER_NOT_FOUND",
+            StackError::Duplicate(_) => "DUPLICATE_MODIFIER",
+            StackError::Limit => "MODIFIER_LIMIT",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            StackError::Invalid(reason) => reason.clone(),
+            StackError::NotFound(id) => format!("Modifier {} not found", id),
+            StackError::Duplicate(id) => format!("Modifier {} already exists", id),
+            StackError::Limit => format!("Objects may have at most {} modifiers", MAX_MODIFIERS),
+        }
+    }
+}
+
+/// Cameras and lights have no geometry to modify.
+pub fn supports_modifiers(object_type: &ObjectType) -> bool {
+    !matches!(
+        object_type,
+        ObjectType::Camera
+            | ObjectType::PointLight
+            | ObjectType::SpotLight
+            | ObjectType::AreaLight
+            | ObjectType::SunLight
+    )
+}
+
+/// One modifier's own settings: a name within Blender's limit, finite numbers in the ranges
+/// Blender's UI allows, and for other modifiers a type identifier and valid parameters.
+pub fn validate(modifier: &Modifier) -> Result<(), String> {
+    let name = modifier.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_MODIFIER_NAME_LEN || name != modifier.name {
+        return Err(format!(
+            "modifier names must be 1 to {MAX_MODIFIER_NAME_LEN} characters without surrounding spaces"
+        ));
+    }
+    match &modifier.kind {
+        ModifierKind::Array(array) => {
+            if !(1..=MAX_ARRAY_COUNT).contains(&array.count) {
+                return Err(format!("array counts must be 1 to {MAX_ARRAY_COUNT}"));
+            }
+            let offsets = array.relative_offset_displace.into_iter().chain(array.constant_offset_displace);
+            if !offsets.chain([array.merge_threshold]).all(f32::is_finite) || array.merge_threshold < 0.0 {
+                return Err("array offsets must be finite and the merge distance not negative".to_string());
+            }
+        }
+        ModifierKind::Mirror(mirror) => {
+            if !mirror.merge_threshold.is_finite() || mirror.merge_threshold < 0.0 {
+                return Err("the mirror merge distance must be finite and not negative".to_string());
+            }
+        }
+        ModifierKind::Bevel(bevel) => {
+            if !bevel.width.is_finite() || bevel.width < 0.0 {
+                return Err("bevel widths must be finite and not negative".to_string());
+            }
+            if !(1..=MAX_BEVEL_SEGMENTS).contains(&bevel.segments) {
+                return Err(format!("bevel segments must be 1 to {MAX_BEVEL_SEGMENTS}"));
+            }
+            if !(0.0..=std::f32::consts::PI).contains(&bevel.angle_limit) {
+                return Err("the bevel angle limit must be between 0 and pi".to_string());
+            }
+        }
+        ModifierKind::Subdivision(subdivision) => {
+            if subdivision.levels > MAX_SUBDIVISION_LEVELS || subdivision.render_levels > MAX_SUBDIVISION_LEVELS {
+                return Err(format!("subdivision levels must be 0 to {MAX_SUBDIVISION_LEVELS}"));
+            }
+        }
+        ModifierKind::Other { blender_type, parameters } => {
+            if blender_type.is_empty()
+                || blender_type.len() > MAX_BLENDER_TYPE_LEN
+                || !blender_type.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+            {
+                return Err(format!("'{blender_type}' is not a Blender modifier type"));
+            }
+            if TYPED.contains(&blender_type.as_str()) {
+                return Err(format!("{blender_type} modifiers must use their typed settings"));
+            }
+            if parameters.len() > MAX_MODIFIER_PARAMETERS {
+                return Err(format!("modifiers may have at most {MAX_MODIFIER_PARAMETERS} parameters"));
+            }
+            for (key, value) in parameters {
+                custom_properties::validate(key, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A whole stack after an edit to `object`'s: within MAX_MODIFIERS, ids and names unique, and
+/// every modifier not in the previous stack valid. A mirror object must be another object of
+/// the session; one deleted since it was set is left alone.
+pub fn validate_stack(
+    object: &SceneObject,
+    stack: &[Modifier],
+    objects: &HashMap<Uuid, SceneObject>,
+) -> Result<(), StackError> {
+    if !stack.is_empty() && !supports_modifiers(&object.object_type) {
+        return Err(StackError::Invalid("Cameras and lights cannot have modifiers".to_string()));
+    }
+    if stack.len() > MAX_MODIFIERS {
+        return Err(StackError::Limit);
+    }
+    let mut ids = HashSet::new();
+    let mut names = HashSet::new();
+    for modifier in stack {
+        if !ids.insert(modifier.modifier_id) {
+            return Err(StackError::Duplicate(modifier.modifier_id));
+        }
+        if !names.insert(modifier.name.as_str()) {
+            return Err(StackError::Invalid(format!(
+                "Object {} already has a modifier named '{}'",
+                object.object_id, modifier.name
+            )));
+        }
+        if object.modifiers.contains(modifier) {
+            continue;
+        }
+        validate(modifier).map_err(|reason| StackError::Invalid(format!("Modifier '{}': {}", modifier.name, reason)))?;
+        if let ModifierKind::Mirror(mirror) = &modifier.kind
+            && let Some(target) = mirror.mirror_object
+            && (target == object.object_id || !objects.contains_key(&target))
+        {
+            return Err(StackError::Invalid(format!(
+                "Modifier '{}' mirrors across {}, which is not another object",
+                modifier.name, target
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Where a modifier sits in a stack.
+pub fn position(stack: &[Modifier], modifier_id: Uuid) -> Result<usize, StackError> {
+    stack.iter().position(|modifier| modifier.modifier_id == modifier_id).ok_or(StackError::NotFound(modifier_id))
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+use crate::types::ObjectType;
+
+/// Triangle (or, for `Circle`, line loop) geometry for one primitive, in the object's local
+/// Blender space: Z up, metres.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshData {
+    pub positions: Vec<[f32; 3]>,
+    /// Empty for line geometry.
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+    /// `indices` is one closed outline rather than triangles.
+    pub line_loop: bool,
+}
+
+impl MeshData {
+    fn vertex(&mut self, position: [f32; 3], normal: [f32; 3]) -> u32 {
+        self.positions.push(position);
+        self.normals.push(normal);
+        (self.positions.len() - 1) as u32
+    }
+
+    /// A planar face with its own vertices, so it shades flat. Corners go counter-clockwise
+    /// seen from outside.
+    fn flat_face(&mut self, corners: &[[f32; 3]]) {
+        let normal = normalize(cross(sub(corners[1], corners[0]), sub(corners[2], corners[0])));
+        let first = self.positions.len() as u32;
+        for &corner in corners {
+            self.vertex(corner, normal);
+        }
+        for i in 1..corners.len() as u32 - 1 {
+            self.indices.extend([first, first + i, first + i + 1]);
+        }
+    }
+
+    /// A smooth surface over a `columns` x `rows` grid; `point(u, v)` gives the position and
+    /// normal at `u, v` in `0..=1`, with `u` running counter-clockwise seen from outside.
+    /// Triangles collapsed to a point edge (a sphere's poles) are left out.
+    fn surface(&mut self, columns: u32, rows: u32, point: impl Fn(f32, f32) -> ([f32; 3], [f32; 3])) {
+        let first = self.positions.len() as u32;
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let (position, normal) = point(column as f32 / columns as f32, row as f32 / rows as f32);
+                self.vertex(position, normal);
+            }
+        }
+        let stride = columns + 1;
+        for row in 0..rows {
+            for column in 0..columns {
+                let a = first + row * stride + column;
+                let b = a + 1;
+                let c = a + stride + 1;
+                let d = a + stride;
+                for triangle in [[a, b, c], [a, c, d]] {
+                    let [pa, pb, pc] = triangle.map(|i| self.positions[i as usize]);
+                    if pa != pb && pb != pc && pc != pa {
+                        self.indices.extend(triangle);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Axis-aligned bounds of the positions, `None` when there are none.
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let first = *self.positions.first()?;
+        Some(self.positions.iter().fold((first, first), |(min, max), p| {
+            (
+                [min[0].min(p[0]), min[1].min(p[1]), min[2].min(p[2])],
+                [max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])],
+            )
+        }))
+    }
+}
+
+/// Segment counts and sizes match Blender's "Add Mesh" defaults, so exported blocking reads at
+/// the same scale as in the viewport. `None` for types that are not meshes.
+pub fn primitive_mesh(object_type: &ObjectType) -> Option<MeshData> {
+    let mut mesh = MeshData::default();
+    match object_type {
+        ObjectType::Cube => cube(&mut mesh),
+        ObjectType::Sphere => uv_sphere(&mut mesh, 32, 16),
+        ObjectType::Cylinder => ring_solid(&mut mesh, 32, 1.0, 1.0),
+        ObjectType::Cone => ring_solid(&mut mesh, 32, 1.0, 0.0),
+        ObjectType::Plane => mesh.flat_face(&[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0]]),
+        ObjectType::Grid => grid(&mut mesh, 10),
+        ObjectType::Circle => circle(&mut mesh, 32),
+        ObjectType::Icosphere => icosphere(&mut mesh, 1),
+        ObjectType::Torus => torus(&mut mesh, 48, 12, 1.0, 0.25),
+        // Suzanne's geometry is Blender's own; a sphere of the same size stands in for her.
+        ObjectType::Monkey => icosphere(&mut mesh, 1),
+        ObjectType::Camera
+        | ObjectType::PointLight
+        | ObjectType::SpotLight
+        | ObjectType::AreaLight
+        | ObjectType::SunLight
+        | ObjectType::AssetRef
+        | ObjectType::Mesh => return None,
+    }
+    Some(mesh)
+}
+
+fn cube(mesh: &mut MeshData) {
+    for axis in 0..3 {
+        for sign in [-1.0f32, 1.0] {
+            // Two in-plane axes ordered so the face winds counter-clockwise seen from outside.
+            let (u, v) = if sign > 0.0 { ((axis + 1) % 3, (axis + 2) % 3) } else { ((axis + 2) % 3, (axis + 1) % 3) };
+            let corners: Vec<[f32; 3]> = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                .into_iter()
+                .map(|(a, b)| {
+                    let mut p = [0.0; 3];
+                    p[axis] = sign;
+                    p[u] = a;
+                    p[v] = b;
+                    p
+                })
+                .collect();
+            mesh.flat_face(&corners);
+        }
+    }
+}
+
+fn uv_sphere(mesh: &mut MeshData, segments: u32, rings: u32) {
+    mesh.surface(segments, rings, |u, v| {
+        let (theta, phi) = (u * TAU, PI - v * PI);
+        let normal = match v {
+            0.0 => [0.0, 0.0, -1.0],
+            1.0 => [0.0, 0.0, 1.0],
+            _ => [phi.sin() * theta.cos(), phi.sin() * theta.sin(), phi.cos()],
+        };
+        (normal, normal)
+    });
+}
+
+/// A capped cylinder from radius `bottom` at z = -1 to `top` at z = 1 (0 for a cone).
+fn ring_solid(mesh: &mut MeshData, vertices: u32, bottom: f32, top: f32) {
+    let slope = (bottom - top) / 2.0;
+    mesh.surface(vertices, 1, |u, v| {
+        let theta = u * TAU;
+        let (cos, sin) = (theta.cos(), theta.sin());
+        let radius = bottom + (top - bottom) * v;
+        let normal = normalize([cos, sin, slope]);
+        ([radius * cos, radius * sin, 2.0 * v - 1.0], normal)
+    });
+    let ring = |radius: f32, z: f32| -> Vec<[f32; 3]> {
+        (0..vertices)
+            .map(|i| {
+                let theta = i as f32 / vertices as f32 * TAU;
+                [radius * theta.cos(), radius * theta.sin(), z]
+            })
+            .collect()
+    };
+    let mut bottom_cap = ring(bottom, -1.0);
+    bottom_cap.reverse();
+    mesh.flat_face(&bottom_cap);
+    if top > 0.0 {
+        mesh.flat_face(&ring(top, 1.0));
+    }
+}
+
+fn grid(mesh: &mut MeshData, subdivisions: u32) {
+    mesh.surface(subdivisions, subdivisions, |u, v| ([2.0 * u - 1.0, 2.0 * v - 1.0, 0.0], [0.0, 0.0, 1.0]));
+}
+
+fn circle(mesh: &mut MeshData, vertices: u32) {
+    for i in 0..vertices {
+        let theta = i as f32 / vertices as f32 * TAU;
+        mesh.positions.push([theta.cos(), theta.sin(), 0.0]);
+        mesh.indices.push(i);
+    }
+    mesh.line_loop = true;
+}
+
+fn torus(mesh: &mut MeshData, major_segments: u32, minor_segments: u32, major_radius: f32, minor_radius: f32) {
+    mesh.surface(major_segments, minor_segments, |u, v| {
+        let (theta, phi) = (u * TAU, v * TAU);
+        let normal = [phi.cos() * theta.cos(), phi.cos() * theta.sin(), phi.sin()];
+        let ring = major_radius + minor_radius * phi.cos();
+        ([ring * theta.cos(), ring * theta.sin(), minor_radius * phi.sin()], normal)
+    });
+}
+
+/// Unit icosahedron split `subdivisions` times, each triangle into four.
+fn icosphere(mesh: &mut MeshData, subdivisions: u32) {
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let mut points: Vec<[f32; 3]> = [
+        [-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+        [0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+        [t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0],
+    ]
+    .into_iter()
+    .map(normalize)
+    .collect();
+    let mut faces: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32, points: &mut Vec<[f32; 3]>| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let (pa, pb) = (points[a as usize], points[b as usize]);
+                points.push(normalize([(pa[0] + pb[0]) / 2.0, (pa[1] + pb[1]) / 2.0, (pa[2] + pb[2]) / 2.0]));
+                (points.len() - 1) as u32
+            })
+        };
+        faces = faces
+            .into_iter()
+            .flat_map(|[a, b, c]| {
+                let ab = midpoint(a, b, &mut points);
+                let bc = midpoint(b, c, &mut points);
+                let ca = midpoint(c, a, &mut points);
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+    let first = mesh.positions.len() as u32;
+    for point in points {
+        mesh.vertex(point, point);
+    }
+    mesh.indices.extend(faces.into_iter().flatten().map(|index| first + index));
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if length == 0.0 {
+        return [0.0, 0.0, 1.0];
+    }
+    [v[0] / length, v[1] / length, v[2] / length]
+}
@@ -0,0 +1,196 @@
+use serde_json::{Map, Value};
+
+use crate::messages::PropertyPatch;
+use crate::types::{
+    AreaLightProperties, CameraProperties, ObjectProperties, ObjectType, PointLightProperties, SceneObject,
+    SpotLightProperties, SunLightProperties, MAX_CUSTOM_PROPERTY_KEY_LEN, MAX_EXTRA_PROPERTIES_BYTES,
+    MAX_PROPERTY_PATCHES, MAX_PROPERTY_PATH_DEPTH,
+};
+
+// An object's property document is its typed `properties`, written out under their schema's
+// key (`spot` for a SpotLight), with `extra_properties` laid over it. PatchProperties edits it
+// by path: `spot.angle` lands in the typed struct, `spot.spread` or `render.holdout` in the
+// extra properties. Whatever a schema below knows is type-checked against it; anything else is
+// kept as plain JSON, so clients can sync settings the server has no struct for yet.
+
+/// A typed schema the document is checked against: the key it lives under and the
+/// `ObjectProperties` variant it reads into.
+pub struct Schema {
+    pub key: &'static str,
+    pub object_type: ObjectType,
+    variant: &'static str,
+    defaults: fn() -> ObjectProperties,
+}
+
+pub static SCHEMAS: [Schema; 5] = [
+    Schema {
+        key: "camera",
+        object_type: ObjectType::Camera,
+        variant: "Camera",
+        defaults: || ObjectProperties::Camera(CameraProperties::default()),
+    },
+    Schema {
+        key: "point",
+        object_type: ObjectType::PointLight,
+        variant: "PointLight",
+        defaults: || ObjectProperties::PointLight(PointLightProperties::default()),
+    },
+    Schema {
+        key: "spot",
+        object_type: ObjectType::SpotLight,
+        variant: "SpotLight",
+        defaults: || ObjectProperties::SpotLight(SpotLightProperties::default()),
+    },
+    Schema {
+        key: "area",
+        object_type: ObjectType::AreaLight,
+        variant: "AreaLight",
+        defaults: || ObjectProperties::AreaLight(AreaLightProperties::default()),
+    },
+    Schema {
+        key: "sun",
+        object_type: ObjectType::SunLight,
+        variant: "SunLight",
+        defaults: || ObjectProperties::SunLight(SunLightProperties::default()),
+    },
+];
+
+/// The schema an object's typed properties belong to: that of their variant, or of the
+/// object's type when it has none.
+pub fn schema_for(object: &SceneObject) -> Option<&'static Schema> {
+    match &object.properties {
+        Some(properties) => {
+            let variant = fields(properties).0;
+            SCHEMAS.iter().find(|schema| schema.variant == variant)
+        }
+        None => SCHEMAS.iter().find(|schema| schema.object_type == object.object_type),
+    }
+}
+
+/// Variant name and fields of typed properties, as they serialize.
+fn fields(properties: &ObjectProperties) -> (String, Map<String, Value>) {
+    match serde_json::to_value(properties) {
+        Ok(Value::Object(wrapped)) => match wrapped.into_iter().next() {
+            Some((variant, Value::Object(fields))) => (variant, fields),
+            _ => (String::new(), Map::new()),
+        },
+        _ => (String::new(), Map::new()),
+    }
+}
+
+/// Typed properties from a document entry; fields it leaves out keep Blender's defaults and
+/// fields the schema does not have are ignored.
+fn parse(schema: &Schema, value: &Value) -> Result<ObjectProperties, String> {
+    let Value::Object(entry) = value else {
+        return Err(format!("'{}' must be an object", schema.key));
+    };
+    let mut merged = fields(&(schema.defaults)()).1;
+    merged.extend(entry.iter().map(|(key, value)| (key.clone(), value.clone())));
+    let wrapped = Map::from_iter([(schema.variant.to_string(), Value::Object(merged))]);
+    serde_json::from_value(Value::Object(wrapped)).map_err(|err| format!("'{}' does not match its schema: {err}", schema.key))
+}
+
+/// The object's whole property document.
+pub fn document(object: &SceneObject) -> Map<String, Value> {
+    let mut document = object.extra_properties.clone();
+    if let Some(properties) = &object.properties
+        && let Some(schema) = schema_for(object)
+        && let Value::Object(entry) = document.entry(schema.key).or_insert_with(|| Value::Object(Map::new()))
+    {
+        entry.extend(fields(properties).1);
+    }
+    document
+}
+
+/// Check what can be checked without the object: how many patches there are and that every
+/// path is well formed.
+pub fn validate(patches: &[PropertyPatch]) -> Result<(), String> {
+    if patches.len() > MAX_PROPERTY_PATCHES {
+        return Err(format!("at most {MAX_PROPERTY_PATCHES} patches per event"));
+    }
+    patches.iter().try_for_each(|patch| validate_path(&patch.path))
+}
+
+fn validate_path(path: &str) -> Result<(), String> {
+    let segments: Vec<&str> = path.split('.').collect();
+    if segments.len() > MAX_PROPERTY_PATH_DEPTH {
+        return Err(format!("path '{path}' is deeper than {MAX_PROPERTY_PATH_DEPTH} levels"));
+    }
+    // Segments are Blender property names: identifier characters, at most 63 of them.
+    let valid = |segment: &&str| {
+        !segment.is_empty()
+            && segment.len() <= MAX_CUSTOM_PROPERTY_KEY_LEN
+            && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    if !segments.iter().all(valid) {
+        return Err(format!("path '{path}' must be dot-separated names of letters, digits or underscores"));
+    }
+    Ok(())
+}
+
+/// Set or remove the value at a path. Removing something that is not there does nothing.
+fn patch_document(document: &mut Map<String, Value>, patch: &PropertyPatch) -> Result<(), String> {
+    let segments: Vec<&str> = patch.path.split('.').collect();
+    let Some((last, parents)) = segments.split_last() else { return Ok(()) };
+    let mut map = document;
+    for (depth, segment) in parents.iter().enumerate() {
+        let entry = match &patch.value {
+            Some(_) => map.entry(*segment).or_insert_with(|| Value::Object(Map::new())),
+            None => match map.get_mut(*segment) {
+                Some(entry) => entry,
+                None => return Ok(()),
+            },
+        };
+        let Value::Object(child) = entry else {
+            return Err(format!("'{}' is not an object", segments[..=depth].join(".")));
+        };
+        map = child;
+    }
+    match &patch.value {
+        Some(value) => map.insert(last.to_string(), value.clone()),
+        None => map.remove(*last),
+    };
+    Ok(())
+}
+
+/// Apply patches to an object, all or none. Returns whether anything changed. Fails when a
+/// patch runs through something that is not an object, when a schema's entry no longer reads
+/// as its typed properties, or when the extra properties would grow past
+/// MAX_EXTRA_PROPERTIES_BYTES.
+pub fn apply(object: &mut SceneObject, patches: &[PropertyPatch]) -> Result<bool, String> {
+    let mut document = document(object);
+    for patch in patches {
+        patch_document(&mut document, patch)?;
+    }
+    for schema in &SCHEMAS {
+        if let Some(value) = document.get(schema.key) {
+            parse(schema, value)?;
+        }
+    }
+
+    // The object's own schema entry goes back into `properties`, minus what is left over;
+    // removing the entry altogether leaves the object without typed properties.
+    let properties = match schema_for(object) {
+        Some(schema) => {
+            let properties = document.get(schema.key).map(|value| parse(schema, value)).transpose()?;
+            if let Some(Value::Object(entry)) = document.get_mut(schema.key) {
+                let typed = fields(&(schema.defaults)()).1;
+                entry.retain(|key, _| !typed.contains_key(key));
+                if entry.is_empty() {
+                    document.remove(schema.key);
+                }
+            }
+            properties
+        }
+        None => object.properties.clone(),
+    };
+
+    let size = serde_json::to_string(&document).map_or(usize::MAX, |json| json.len());
+    if size > MAX_EXTRA_PROPERTIES_BYTES {
+        return Err(format!("extra properties would exceed {MAX_EXTRA_PROPERTIES_BYTES} bytes"));
+    }
+    let changed = properties != object.properties || document != object.extra_properties;
+    object.properties = properties;
+    object.extra_properties = document;
+    Ok(changed)
+}
@@ -0,0 +1,159 @@
+use uuid::Uuid;
+
+use crate::messages::ClientEvent;
+use crate::types::AppState;
+
+/// Event classes that share a token bucket. Each connection gets one bucket per class.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RateClass {
+    /// Join/Create/Fork/Leave: bcrypt work per attempt, so kept tight.
+    Session,
+    /// UpdateTransform: high frequency while dragging, also coalesced per session. Over the limit
+    /// the latest transform per object is held and applied once tokens are back.
+    Transform,
+    Cursor,
+    /// Object edits: create, delete, properties, name, parent, collections, custom properties,
+    /// materials, mesh uploads, modifiers, selection. Over the limit they are refused with an
+    /// EventRejected the client can retry, since the sender has already applied them locally.
+    Edit,
+    /// Full/partial state requests that snapshot the whole session.
+    Sync,
+}
+
+/// A limited client is told at most once per this window, however long the flood lasts.
+pub const RATE_LIMIT_NOTIFY_COOLDOWN_MS: u64 = 5_000;
+
+/// How often a connection retries the transforms it holds back while over its limit.
+pub const HELD_TRANSFORM_RETRY_MS: u64 = 25;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: f64,
+}
+
+pub const fn limit_for(class: RateClass) -> RateLimit {
+    match class {
+        RateClass::Session => RateLimit { per_second: 2.0, burst: 5.0 },
+        // A 20-object group drag at Blender's 20 Hz transform timer is 400/s.
+        RateClass::Transform => RateLimit { per_second: 400.0, burst: 800.0 },
+        RateClass::Cursor => RateLimit { per_second: 60.0, burst: 120.0 },
+        RateClass::Edit => RateLimit { per_second: 100.0, burst: 500.0 },
+        RateClass::Sync => RateLimit { per_second: 10.0, burst: 20.0 },
+    }
+}
+
+pub fn classify(event: &ClientEvent) -> RateClass {
+    match event {
+        ClientEvent::JoinSession(_)
+        | ClientEvent::CreateSession(_)
+        | ClientEvent::ForkSession(_)
+        | ClientEvent::LeaveSession => RateClass::Session,
+        ClientEvent::UpdateTransform(_) => RateClass::Transform,
+        ClientEvent::UpdateCursor(_) => RateClass::Cursor,
+        ClientEvent::CreateObject(_)
+        | ClientEvent::DeleteObject(_)
+        | ClientEvent::UpdateProperties(_)
+        | ClientEvent::UpdateName(_)
+        | ClientEvent::SetParent(_)
+        | ClientEvent::CreateCollection(_)
+        | ClientEvent::UpdateCollection(_)
+        | ClientEvent::DeleteCollection(_)
+        | ClientEvent::SetObjectCollections(_)
+        | ClientEvent::SetCustomProperty(_)
+        | ClientEvent::RemoveCustomProperty(_)
+        | ClientEvent::PatchProperties(_)
+        | ClientEvent::CreateMaterial(_)
+        | ClientEvent::UpdateMaterial(_)
+        | ClientEvent::SetObjectMaterials(_)
+        | ClientEvent::UploadMeshChunk(_)
+        | ClientEvent::SetObjectMesh(_)
+        | ClientEvent::AddModifier(_)
+        | ClientEvent::RemoveModifier(_)
+        | ClientEvent::ReorderModifiers(_)
+        | ClientEvent::UpdateModifier(_)
+        | ClientEvent::SelectObject(_)
+        | ClientEvent::Transaction(_)
+        | ClientEvent::Undo
+        | ClientEvent::Redo
+        | ClientEvent::RestoreObject(_) => RateClass::Edit,
+        // Checkpoints copy or rewrite the whole scene, so they share the sync budget.
+        ClientEvent::RequestStateSync(_)
+        | ClientEvent::RequestChecksum(_)
+        | ClientEvent::RequestObjectsSync(_)
+        | ClientEvent::RequestMesh(_)
+        | ClientEvent::CreateCheckpoint(_)
+        | ClientEvent::ListCheckpoints
+        | ClientEvent::RestoreCheckpoint(_)
+        | ClientEvent::ListTrash
+        | ClientEvent::QueryAuditLog(_) => RateClass::Sync,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TokenBucket {
+    pub tokens: f64,
+    pub last_refill_ms: u64,
+    /// When the client was last told it is being limited, if ever.
+    pub last_notified_ms: Option<u64>,
+}
+
+impl TokenBucket {
+    fn full(limit: RateLimit, now_ms: u64) -> Self {
+        TokenBucket { tokens: limit.burst, last_refill_ms: now_ms, last_notified_ms: None }
+    }
+
+    fn try_take(&mut self, limit: RateLimit, now_ms: u64) -> bool {
+        let elapsed_s = now_ms.saturating_sub(self.last_refill_ms) as f64 / 1000.0;
+        self.tokens = (self.tokens + elapsed_s * limit.per_second).min(limit.burst);
+        self.last_refill_ms = now_ms;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Milliseconds until the next token, after a failed try_take.
+    fn retry_after_ms(&self, limit: RateLimit) -> u64 {
+        ((1.0 - self.tokens).max(0.0) / limit.per_second * 1000.0).ceil() as u64
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RateDecision {
+    Allow,
+    Drop,
+    /// Dropped, and the client has not been told about this class within the cooldown.
+    DropAndNotify,
+    /// A transform to hold back, replacing any older one held for the same object.
+    Coalesce,
+    /// An edit to refuse with an EventRejected; a token is due after `retry_after_ms`.
+    Reject { retry_after_ms: u64 },
+}
+
+/// Take a token for the event's class on this connection.
+pub fn check(state: &AppState, connection_id: Uuid, event: &ClientEvent, now_ms: u64) -> (RateClass, RateDecision) {
+    let class = classify(event);
+    let limit = limit_for(class);
+    let mut buckets = state.rate_limits.entry(connection_id).or_default();
+    let bucket = buckets.entry(class).or_insert_with(|| TokenBucket::full(limit, now_ms));
+
+    let decision = if bucket.try_take(limit, now_ms) {
+        RateDecision::Allow
+    } else if class == RateClass::Transform {
+        RateDecision::Coalesce
+    } else if class == RateClass::Edit {
+        RateDecision::Reject { retry_after_ms: bucket.retry_after_ms(limit) }
+    } else if bucket
+        .last_notified_ms
+        .is_some_and(|at| now_ms.saturating_sub(at) < RATE_LIMIT_NOTIFY_COOLDOWN_MS)
+    {
+        RateDecision::Drop
+    } else {
+        bucket.last_notified_ms = Some(now_ms);
+        RateDecision::DropAndNotify
+    };
+    (class, decision)
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use uuid::Uuid;
+
+use crate::encryption::{EncryptionError, Keyring};
+use crate::messages::ClientEvent;
+use crate::types::{SceneObject, User};
+
+/// `.mrec` layout: `MREC`, a little-endian u16 format version, then blocks. Each block is a
+/// little-endian u32 byte length followed by that many bytes of raw deflate, which inflate to
+/// a run of frames, each a u32 length plus one JSON `RecordedFrame`. Blocks are independent,
+/// so a recording cut short by a crash is readable up to its last complete block.
+pub const RECORDING_MAGIC: &[u8; 4] = b"MREC";
+/// Same layout, but each block's deflate bytes are sealed with the session encryption key.
+pub const ENCRYPTED_RECORDING_MAGIC: &[u8; 4] = b"MRCE";
+pub const RECORDING_FORMAT_VERSION: u16 = 1;
+pub const RECORDING_EXTENSION: &str = "mrec";
+pub const RECORDINGS_DIR_NAME: &str = "recordings";
+
+/// A block is written once this many frames are buffered...
+pub const FRAMES_PER_BLOCK: usize = 512;
+/// ...or once the oldest buffered frame is this old, whichever comes first.
+pub const BLOCK_FLUSH_INTERVAL_MS: u64 = 1_000;
+
+/// Associated data for sealed recording blocks.
+const RECORDING_AAD: &[u8] = b"meerkat recording";
+
+/// Upper bound on one block, so a corrupt length cannot make the reader allocate gigabytes.
+const MAX_BLOCK_LEN: usize = 64 * 1024 * 1024;
+
+/// Externally tagged (`{"Server":{...}}`): serde cannot hand a `RawValue` through the buffering
+/// an internally tagged enum needs.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum RecordedFrame {
+    /// Always first: the scene as it was when recording began.
+    Start {
+        session_id: String,
+        started_at: u64,
+        objects: HashMap<Uuid, SceneObject>,
+        users: HashMap<Uuid, User>,
+    },
+    /// A client event that passed parsing and rate limiting, before it was handled.
+    Client { at_ms: u64, user_id: Uuid, event: ClientEvent },
+    /// A ServerEvent broadcast to the session, stored exactly as sent.
+    Server { at_ms: u64, event: Box<RawValue> },
+}
+
+/// Passwords never go into a recording.
+pub fn redacted(event: &ClientEvent) -> ClientEvent {
+    let mut event = event.clone();
+    match &mut event {
+        ClientEvent::JoinSession(p) => p.password.clear(),
+        ClientEvent::CreateSession(p) => p.password.clear(),
+        ClientEvent::ForkSession(p) => p.password.clear(),
+        _ => {}
+    }
+    event
+}
+
+/// Appends frames to an open `.mrec` file. Dropping it writes whatever is still buffered.
+#[derive(Debug)]
+pub struct Recorder {
+    path: PathBuf,
+    file: BufWriter<File>,
+    keyring: Option<Keyring>,
+    pending: Vec<u8>,
+    pending_frames: usize,
+    oldest_pending_ms: u64,
+}
+
+impl Recorder {
+    /// Create `<dir>/<started_at>.mrec` and write the Start frame. Blocks are sealed with
+    /// `keyring` when given.
+    pub fn create(dir: &Path, start: RecordedFrame, keyring: Option<Keyring>) -> io::Result<Self> {
+        let RecordedFrame::Start { started_at, .. } = &start else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "a recording must begin with a Start frame"));
+        };
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{started_at}.{RECORDING_EXTENSION}"));
+        let mut file = BufWriter::new(File::create_new(&path)?);
+        file.write_all(if keyring.is_some() { ENCRYPTED_RECORDING_MAGIC } else { RECORDING_MAGIC })?;
+        file.write_all(&RECORDING_FORMAT_VERSION.to_le_bytes())?;
+        let mut recorder = Recorder { path, file, keyring, pending: Vec::new(), pending_frames: 0, oldest_pending_ms: 0 };
+        recorder.push(&start, *started_at)?;
+        recorder.flush_block()?;
+        Ok(recorder)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record_client(&mut self, at_ms: u64, user_id: Uuid, event: &ClientEvent) {
+        let frame = RecordedFrame::Client { at_ms, user_id, event: redacted(event) };
+        self.record(&frame, at_ms);
+    }
+
+    pub fn record_server(&mut self, at_ms: u64, json: &str) {
+        match RawValue::from_string(json.to_owned()) {
+            Ok(event) => self.record(&RecordedFrame::Server { at_ms, event }, at_ms),
+            Err(err) => tracing::error!(error = %err, "not recording unparseable ServerEvent"),
+        }
+    }
+
+    fn record(&mut self, frame: &RecordedFrame, at_ms: u64) {
+        let result = self.push(frame, at_ms).and_then(|_| {
+            if self.pending_frames >= FRAMES_PER_BLOCK
+                || at_ms.saturating_sub(self.oldest_pending_ms) >= BLOCK_FLUSH_INTERVAL_MS
+            {
+                self.flush_block()
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(err) = result {
+            tracing::error!(path = %self.path.display(), error = %err, "failed to write recording");
+        }
+    }
+
+    fn push(&mut self, frame: &RecordedFrame, at_ms: u64) -> io::Result<()> {
+        let json = serde_json::to_vec(frame)?;
+        if self.pending_frames == 0 {
+            self.oldest_pending_ms = at_ms;
+        }
+        self.pending.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        self.pending.extend_from_slice(&json);
+        self.pending_frames += 1;
+        Ok(())
+    }
+
+    /// Compress the buffered frames into one block and write it out.
+    pub fn flush_block(&mut self) -> io::Result<()> {
+        if self.pending_frames == 0 {
+            return Ok(());
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&self.pending)?;
+        let mut block = encoder.finish()?;
+        if let Some(keyring) = &self.keyring {
+            block = keyring.seal(RECORDING_AAD, &block)?;
+        }
+        self.file.write_all(&(block.len() as u32).to_le_bytes())?;
+        self.file.write_all(&block)?;
+        self.file.flush()?;
+        self.pending.clear();
+        self.pending_frames = 0;
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush_block() {
+            tracing::error!(path = %self.path.display(), error = %err, "failed to write final recording block");
+        }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Read every frame of a recording, decrypting it with `keyring` if it is encrypted. A block
+/// torn by a crash ends the recording early (with a warning) rather than failing it.
+pub fn read_recording(path: &Path, keyring: Option<&Keyring>) -> io::Result<Vec<RecordedFrame>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut header = [0u8; 6];
+    reader.read_exact(&mut header).map_err(|_| invalid("file is too short to be a recording"))?;
+    let keyring = match &header[..4] {
+        magic if magic == RECORDING_MAGIC => {
+            if keyring.is_some_and(Keyring::refuses_plaintext) {
+                return Err(EncryptionError::Unsealed.into());
+            }
+            None
+        }
+        magic if magic == ENCRYPTED_RECORDING_MAGIC => Some(keyring.ok_or(EncryptionError::NoKey)?),
+        _ => return Err(invalid("not a .mrec recording (bad magic)")),
+    };
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version != RECORDING_FORMAT_VERSION {
+        return Err(invalid(format!(
+            "unsupported recording format version {version} (expected {RECORDING_FORMAT_VERSION})"
+        )));
+    }
+
+    let mut frames = Vec::new();
+    loop {
+        let mut len = [0u8; 4];
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_BLOCK_LEN {
+            return Err(invalid(format!("block of {len} bytes exceeds the {MAX_BLOCK_LEN} byte limit")));
+        }
+        let mut block = vec![0u8; len];
+        if reader.read_exact(&mut block).is_err() {
+            tracing::warn!(path = %path.display(), "recording ends in a truncated block; ignoring it");
+            break;
+        }
+        if let Some(keyring) = keyring {
+            block = keyring.open(RECORDING_AAD, &block)?;
+        }
+        let mut raw = Vec::new();
+        DeflateDecoder::new(block.as_slice()).read_to_end(&mut raw)?;
+        parse_frames(&raw, &mut frames)?;
+    }
+
+    if !matches!(frames.first(), Some(RecordedFrame::Start { .. })) {
+        return Err(invalid("recording does not begin with a Start frame"));
+    }
+    Ok(frames)
+}
+
+/// Write `frames` as a complete recording replacing the one at `path`, sealed with `keyring`
+/// when given. How rewrap re-encrypts a finished recording.
+pub fn rewrite_recording(path: &Path, frames: &[RecordedFrame], keyring: Option<&Keyring>) -> io::Result<()> {
+    let tmp = path.with_extension(format!("{RECORDING_EXTENSION}.tmp"));
+    let mut recorder = Recorder {
+        path: tmp.clone(),
+        file: BufWriter::new(File::create(&tmp)?),
+        keyring: keyring.cloned(),
+        pending: Vec::new(),
+        pending_frames: 0,
+        oldest_pending_ms: 0,
+    };
+    recorder.file.write_all(if keyring.is_some() { ENCRYPTED_RECORDING_MAGIC } else { RECORDING_MAGIC })?;
+    recorder.file.write_all(&RECORDING_FORMAT_VERSION.to_le_bytes())?;
+    for frame in frames {
+        recorder.push(frame, 0)?;
+        if recorder.pending_frames >= FRAMES_PER_BLOCK {
+            recorder.flush_block()?;
+        }
+    }
+    recorder.flush_block()?;
+    recorder.file.get_ref().sync_all()?;
+    drop(recorder);
+    fs::rename(&tmp, path)
+}
+
+fn parse_frames(mut raw: &[u8], frames: &mut Vec<RecordedFrame>) -> io::Result<()> {
+    while !raw.is_empty() {
+        let (len, rest) = raw.split_at_checked(4).ok_or_else(|| invalid("truncated frame length"))?;
+        let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        let (json, rest) = rest.split_at_checked(len).ok_or_else(|| invalid("truncated frame"))?;
+        frames.push(serde_json::from_slice(json)?);
+        raw = rest;
+    }
+    Ok(())
+}
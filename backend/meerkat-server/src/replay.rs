@@ -0,0 +1,497 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::admin::{error, AdminError};
+use crate::handlers::helpers::broadcast;
+use crate::handlers::transaction::stage_operations;
+use crate::history;
+use crate::messages::{FullStateSyncPayload, ObjectDiff, ServerEvent};
+use crate::property_patch;
+use crate::encryption::Keyring;
+use crate::recording::{read_recording, RecordedFrame};
+use crate::types::{AppState, ChangeKind, SceneObject, SessionHandle, User};
+
+/// Fastest playback the controls accept.
+pub const MAX_REPLAY_SPEED: f64 = 1_000.0;
+
+/// One broadcast from a recording, due `offset_ms` after the recording started.
+#[derive(Debug)]
+pub struct ReplayFrame {
+    pub offset_ms: u64,
+    pub at_ms: u64,
+    pub json: String,
+    pub event: ServerEvent,
+}
+
+/// A recording prepared for playback: the starting scene plus every broadcast that changes
+/// what viewers see. Client frames and periodic checksums are left out.
+#[derive(Debug)]
+pub struct Timeline {
+    pub session_id: String,
+    pub started_at: u64,
+    pub objects: HashMap<Uuid, SceneObject>,
+    pub users: HashMap<Uuid, User>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Timeline {
+    pub fn load(path: &Path, keyring: Option<&Keyring>) -> io::Result<Self> {
+        let mut frames = read_recording(path, keyring)?.into_iter();
+        let Some(RecordedFrame::Start { session_id, started_at, objects, users }) = frames.next() else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "recording does not begin with a Start frame"));
+        };
+        let mut timeline = Timeline { session_id, started_at, objects, users, frames: Vec::new() };
+        for frame in frames {
+            let RecordedFrame::Server { at_ms, event } = frame else {
+                continue;
+            };
+            let parsed: ServerEvent = match serde_json::from_str(event.get()) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    tracing::warn!(at_ms, error = %err, "skipping unreadable recorded ServerEvent");
+                    continue;
+                }
+            };
+            if matches!(parsed, ServerEvent::StateChecksum(_)) {
+                continue;
+            }
+            timeline.frames.push(ReplayFrame {
+                offset_ms: at_ms.saturating_sub(started_at),
+                at_ms,
+                json: event.get().to_string(),
+                event: parsed,
+            });
+        }
+        Ok(timeline)
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        self.frames.last().map_or(0, |frame| frame.offset_ms)
+    }
+
+    /// The scene as it stood `offset_ms` into the recording.
+    pub fn objects_at(&self, offset_ms: u64) -> HashMap<Uuid, SceneObject> {
+        let mut objects = self.objects.clone();
+        for frame in self.frames.iter().take_while(|frame| frame.offset_ms <= offset_ms) {
+            apply_server_event(&mut objects, &frame.event, frame.at_ms);
+        }
+        objects
+    }
+}
+
+/// Apply a broadcast ServerEvent to a scene the way a client would. Returns the ids of the
+/// objects it touched; events that do not change objects touch none.
+pub fn apply_server_event(objects: &mut HashMap<Uuid, SceneObject>, event: &ServerEvent, at_ms: u64) -> Vec<Uuid> {
+    fn touch(objects: &mut HashMap<Uuid, SceneObject>, object_id: Uuid, by: Uuid, at_ms: u64) -> Option<&mut SceneObject> {
+        let object = objects.get_mut(&object_id)?;
+        object.last_updated_by = by;
+        object.last_updated_at = at_ms;
+        Some(object)
+    }
+    fn apply_diff(objects: &mut HashMap<Uuid, SceneObject>, diff: &ObjectDiff) -> Vec<Uuid> {
+        let mut touched = Vec::new();
+        for object in diff.created.iter().chain(&diff.updated) {
+            objects.insert(object.object_id, object.clone());
+            touched.push(object.object_id);
+        }
+        for object_id in &diff.deleted {
+            objects.remove(object_id);
+            touched.push(*object_id);
+        }
+        touched
+    }
+
+    match event {
+        ServerEvent::ObjectCreated(p) => {
+            objects.insert(p.object.object_id, p.object.clone());
+            vec![p.object.object_id]
+        }
+        ServerEvent::ObjectDeleted(p) => {
+            objects.remove(&p.object_id);
+            vec![p.object_id]
+        }
+        ServerEvent::TransformUpdated(p) => {
+            if let Some(object) = touch(objects, p.object_id, p.updated_by, at_ms) {
+                object.transform = p.transform.clone();
+            }
+            vec![p.object_id]
+        }
+        ServerEvent::PropertiesUpdated(p) => {
+            if let Some(object) = touch(objects, p.object_id, p.updated_by, at_ms) {
+                object.properties = Some(p.properties.clone());
+            }
+            vec![p.object_id]
+        }
+        ServerEvent::NameUpdated(p) => {
+            if let Some(object) = touch(objects, p.object_id, p.updated_by, at_ms) {
+                object.name = p.name.clone();
+            }
+            vec![p.object_id]
+        }
+        ServerEvent::ParentUpdated(p) => {
+            if let Some(object) = touch(objects, p.object_id, p.updated_by, at_ms) {
+                object.parent_id = p.parent_id;
+                object.transform = p.transform.clone();
+            }
+            vec![p.object_id]
+        }
+        ServerEvent::ObjectCollectionsUpdated(p) => {
+            if let Some(object) = touch(objects, p.object_id, p.updated_by, at_ms) {
+                object.collections = p.collection_ids.clone();
+            }
+            vec![p.object_id]
+        }
+        ServerEvent::ObjectMaterialsUpdated(p) => {
+            if let Some(object) = touch(objects, p.object_id, p.updated_by, at_ms) {
+                object.material_slots = p.material_slots.clone();
+            }
+            vec![p.object_id]
+        }
+        ServerEvent::ObjectMeshUpdated(p) => {
+            if let Some(object) = touch(objects, p.object_id, p.updated_by, at_ms) {
+                object.mesh = p.mesh.clone();
+                object.mesh_version = p.mesh_version;
+            }
+            vec![p.object_id]
+        }
+        ServerEvent::ObjectModifiersUpdated(p) => {
+            if let Some(object) = touch(objects, p.object_id, p.updated_by, at_ms) {
+                object.modifiers = p.modifiers.clone();
+            }
+            vec![p.object_id]
+        }
+        ServerEvent::CustomPropertyUpdated(p) => {
+            if let Some(object) = touch(objects, p.object_id, p.updated_by, at_ms) {
+                match &p.value {
+                    Some(value) => object.custom_properties.insert(p.key.clone(), value.clone()),
+                    None => object.custom_properties.remove(&p.key),
+                };
+            }
+            vec![p.object_id]
+        }
+        ServerEvent::PropertiesPatched(p) => {
+            if let Some(object) = touch(objects, p.object_id, p.updated_by, at_ms)
+                && let Err(reason) = property_patch::apply(object, &p.patches)
+            {
+                tracing::warn!(object_id = %p.object_id, reason = %reason, "recorded property patch no longer applies");
+            }
+            vec![p.object_id]
+        }
+        ServerEvent::CollectionDeleted(p) => {
+            let members: Vec<Uuid> = objects
+                .values()
+                .filter(|object| object.collections.contains(&p.collection_id))
+                .map(|object| object.object_id)
+                .collect();
+            for object_id in &members {
+                if let Some(object) = touch(objects, *object_id, p.deleted_by, at_ms) {
+                    object.collections.retain(|id| *id != p.collection_id);
+                }
+            }
+            members
+        }
+        ServerEvent::TransactionApplied(p) => match stage_operations(objects, &p.operations, p.applied_by, at_ms) {
+            Ok((staged, _)) => staged
+                .into_iter()
+                .map(|(object_id, result)| {
+                    match result {
+                        Some(object) => objects.insert(object_id, object),
+                        None => objects.remove(&object_id),
+                    };
+                    object_id
+                })
+                .collect(),
+            Err(err) => {
+                tracing::warn!(transaction_id = %p.transaction_id, error = %err, "recorded transaction does not apply");
+                Vec::new()
+            }
+        },
+        ServerEvent::HistoryApplied(p) => apply_diff(objects, &p.diff),
+        ServerEvent::CheckpointRestored(p) => apply_diff(objects, &p.diff),
+        _ => Vec::new(),
+    }
+}
+
+/// Playback state reported by `GET /replay`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplayStatus {
+    pub session_id: String,
+    pub position_ms: u64,
+    pub duration_ms: u64,
+    pub speed: f64,
+    pub paused: bool,
+    pub frames_played: usize,
+    pub frame_count: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ReplayOptions {
+    /// 1.0 is real time.
+    pub speed: f64,
+    /// Where playback starts, in ms from the start of the recording.
+    pub start_at_ms: u64,
+    pub paused: bool,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        ReplayOptions { speed: 1.0, start_at_ms: 0, paused: false }
+    }
+}
+
+/// Shared between the playback task and the HTTP controls. Every change wakes the task.
+pub struct ReplayControl {
+    status: Mutex<ReplayStatus>,
+    seek_to: Mutex<Option<u64>>,
+    changed: Notify,
+}
+
+impl ReplayControl {
+    fn lock_status(&self) -> MutexGuard<'_, ReplayStatus> {
+        match self.status.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn lock_seek(&self) -> MutexGuard<'_, Option<u64>> {
+        match self.seek_to.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    pub fn status(&self) -> ReplayStatus {
+        self.lock_status().clone()
+    }
+
+    /// Jump to `offset_ms`; everyone watching is resynced to the scene at that point.
+    pub fn seek(&self, offset_ms: u64) {
+        *self.lock_seek() = Some(offset_ms);
+        self.changed.notify_one();
+    }
+
+    pub fn set_speed(&self, speed: f64) -> Result<(), String> {
+        if !speed.is_finite() || speed <= 0.0 || speed > MAX_REPLAY_SPEED {
+            return Err(format!("speed must be greater than 0 and at most {MAX_REPLAY_SPEED}"));
+        }
+        self.lock_status().speed = speed;
+        self.changed.notify_one();
+        Ok(())
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.lock_status().paused = paused;
+        self.changed.notify_one();
+    }
+}
+
+/// Re-create the recorded session in `state`, pinned so it outlives its viewers, and start
+/// playing it back. Viewers join it like any session, with `password_hash`'s password.
+pub fn start(
+    state: &AppState,
+    timeline: Timeline,
+    password_hash: String,
+    options: ReplayOptions,
+) -> Result<Arc<ReplayControl>, String> {
+    if state.sessions.contains_key(&timeline.session_id) {
+        return Err(format!("session '{}' already exists", timeline.session_id));
+    }
+    let start_at = options.start_at_ms.min(timeline.duration_ms());
+    let mut session = SessionHandle::new(timeline.session_id.clone(), password_hash);
+    session.objects = RwLock::new(timeline.objects_at(start_at));
+    session.pinned = true;
+    let session = Arc::new(session);
+    state.sessions.insert(timeline.session_id.clone(), Arc::clone(&session));
+
+    let control = Arc::new(ReplayControl {
+        status: Mutex::new(ReplayStatus {
+            session_id: timeline.session_id.clone(),
+            position_ms: start_at,
+            duration_ms: timeline.duration_ms(),
+            speed: 1.0,
+            paused: options.paused,
+            frames_played: timeline.frames.partition_point(|frame| frame.offset_ms <= start_at),
+            frame_count: timeline.frames.len(),
+        }),
+        seek_to: Mutex::new(None),
+        changed: Notify::new(),
+    });
+    control.set_speed(options.speed)?;
+
+    tracing::info!(
+        event_type = "ReplayStarted",
+        session_id = %timeline.session_id,
+        frame_count = timeline.frames.len(),
+        duration_ms = timeline.duration_ms(),
+        speed = options.speed,
+        "replaying recording"
+    );
+    tokio::spawn(play(state.clone(), session, timeline, Arc::clone(&control)));
+    Ok(control)
+}
+
+async fn play(state: AppState, session: Arc<SessionHandle>, timeline: Timeline, control: Arc<ReplayControl>) {
+    let (mut position, mut next) = {
+        let status = control.status();
+        (status.position_ms, status.frames_played)
+    };
+    loop {
+        if let Some(target) = control.lock_seek().take() {
+            position = target.min(timeline.duration_ms());
+            next = timeline.frames.partition_point(|frame| frame.offset_ms <= position);
+            reset_scene(&state, &session, timeline.objects_at(position));
+            let mut status = control.lock_status();
+            status.position_ms = position;
+            status.frames_played = next;
+            continue;
+        }
+
+        let (speed, paused) = {
+            let status = control.lock_status();
+            (status.speed, status.paused)
+        };
+        let Some(frame) = timeline.frames.get(next).filter(|_| !paused) else {
+            // Paused or at the end: nothing to do until someone seeks, resumes or changes speed.
+            control.changed.notified().await;
+            continue;
+        };
+
+        let wait = Duration::from_secs_f64(frame.offset_ms.saturating_sub(position) as f64 / 1_000.0 / speed);
+        let waiting_since = Instant::now();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {
+                play_frame(&state, &session, frame);
+                position = frame.offset_ms;
+                next += 1;
+            }
+            _ = control.changed.notified() => {
+                let elapsed = (waiting_since.elapsed().as_secs_f64() * 1_000.0 * speed) as u64;
+                position = (position + elapsed).min(frame.offset_ms);
+            }
+        }
+        let mut status = control.lock_status();
+        status.position_ms = position;
+        status.frames_played = next;
+    }
+}
+
+/// Apply one recorded broadcast to the replay session and send it to the viewers as recorded.
+fn play_frame(state: &AppState, session: &SessionHandle, frame: &ReplayFrame) {
+    {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        for object_id in apply_server_event(&mut objects, &frame.event, frame.at_ms) {
+            let kind = if objects.contains_key(&object_id) { ChangeKind::Upserted } else { ChangeKind::Deleted };
+            session.record_change(object_id, kind);
+        }
+    }
+    broadcast(state, &session.session_id, &frame.json, None);
+}
+
+/// Replace the replay session's scene and send every viewer a FullStateSync of it.
+fn reset_scene(state: &AppState, session: &SessionHandle, target: HashMap<Uuid, SceneObject>) {
+    {
+        let mut objects = match session.objects.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let changes = history::changes_between(&objects, &target);
+        history::apply_changes(&mut objects, &changes);
+        session.record_object_changes(&changes);
+    }
+
+    let sid = &session.session_id;
+    let viewers: Vec<Uuid> = state
+        .session_connections
+        .get(sid)
+        .map(|conns| conns.iter().copied().collect())
+        .unwrap_or_default();
+    for connection_id in viewers {
+        let Some(user_id) = state.connection_meta.get(&connection_id).map(|meta| meta.value().1) else {
+            continue;
+        };
+        let event = ServerEvent::FullStateSync(FullStateSyncPayload {
+            session: session.session_snapshot(),
+            your_user_id: user_id,
+        });
+        let json = match serde_json::to_string(&event) {
+            Ok(json) => json,
+            Err(err) => {
+                tracing::error!(event_type = "FullStateSync", session_id = %sid, error = %err, "failed to serialize replay resync");
+                return;
+            }
+        };
+        if let Some(tx) = state.connections.get(&connection_id)
+            && tx.try_send(json).is_err()
+        {
+            tracing::warn!(session_id = %sid, connection_id = %connection_id, "failed to deliver replay resync");
+        }
+    }
+}
+
+// ── HTTP controls ─────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+struct SeekQuery {
+    to_ms: u64,
+}
+
+#[derive(Deserialize)]
+struct SpeedQuery {
+    factor: f64,
+}
+
+/// `GET /replay` for status; `POST /replay/{seek?to_ms=,speed?factor=,pause,resume}` to steer.
+pub fn router(control: Arc<ReplayControl>) -> Router {
+    Router::new()
+        .route("/replay", get(status))
+        .route("/replay/seek", post(seek))
+        .route("/replay/speed", post(speed))
+        .route("/replay/pause", post(pause))
+        .route("/replay/resume", post(resume))
+        .with_state(control)
+}
+
+async fn status(State(control): State<Arc<ReplayControl>>) -> Json<ReplayStatus> {
+    Json(control.status())
+}
+
+async fn seek(State(control): State<Arc<ReplayControl>>, Query(query): Query<SeekQuery>) -> Json<ReplayStatus> {
+    control.seek(query.to_ms);
+    Json(control.status())
+}
+
+async fn speed(
+    State(control): State<Arc<ReplayControl>>,
+    Query(query): Query<SpeedQuery>,
+) -> Result<Json<ReplayStatus>, AdminError> {
+    control.set_speed(query.factor).map_err(|message| error(StatusCode::BAD_REQUEST, message))?;
+    Ok(Json(control.status()))
+}
+
+async fn pause(State(control): State<Arc<ReplayControl>>) -> Json<ReplayStatus> {
+    control.set_paused(true);
+    Json(control.status())
+}
+
+async fn resume(State(control): State<Arc<ReplayControl>>) -> Json<ReplayStatus> {
+    control.set_paused(false);
+    Json(control.status())
+}
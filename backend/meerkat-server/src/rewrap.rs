@@ -0,0 +1,71 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::audit::{self, AUDIT_FILE_NAME};
+use crate::encryption::Keyring;
+use crate::journal;
+use crate::recording::{self, RECORDINGS_DIR_NAME, RECORDING_EXTENSION};
+use crate::snapshot;
+use crate::storage::SESSIONS_DIR_NAME;
+
+/// What `rewrap_data_dir` rewrote.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RewrapSummary {
+    pub sessions: usize,
+    pub audit_logs: usize,
+    pub recordings: usize,
+}
+
+/// Re-encrypt everything persisted under `data_dir` with `keyring`'s current key: each
+/// session's snapshot (with its journal folded in), audit log and recordings. Anything sealed
+/// with a configured key is read, and so is plaintext: this is the one explicit way to encrypt
+/// data written before encryption was turned on. Afterwards keys other than the current one
+/// can be dropped. The server must not be running.
+pub fn rewrap_data_dir(data_dir: &Path, keyring: &Keyring) -> io::Result<RewrapSummary> {
+    let reader = keyring.clone().accepting_plaintext();
+    let mut summary = RewrapSummary::default();
+    let entries = match fs::read_dir(data_dir.join(SESSIONS_DIR_NAME)) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(summary),
+        Err(err) => return Err(err),
+    };
+    for entry in entries {
+        let dir = entry?.path();
+        if !dir.is_dir() {
+            continue;
+        }
+        if let Some(snapshot) = snapshot::recover(&dir, Some(&reader)).map_err(at(&dir))? {
+            snapshot::write_snapshot(&dir, &snapshot, Some(keyring)).map_err(at(&dir))?;
+            journal::remove_all(&dir).map_err(at(&dir))?;
+            summary.sessions += 1;
+        }
+
+        let audit_path = dir.join(AUDIT_FILE_NAME);
+        if audit_path.exists() {
+            let entries = audit::read_file(&audit_path, Some(&reader)).map_err(at(&audit_path))?;
+            audit::write_file(&audit_path, &entries, Some(keyring)).map_err(at(&audit_path))?;
+            summary.audit_logs += 1;
+        }
+
+        let recordings = match fs::read_dir(dir.join(RECORDINGS_DIR_NAME)) {
+            Ok(recordings) => recordings,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        for recording in recordings {
+            let path = recording?.path();
+            if path.extension().is_none_or(|extension| extension != RECORDING_EXTENSION) {
+                continue;
+            }
+            let frames = recording::read_recording(&path, Some(&reader)).map_err(at(&path))?;
+            recording::rewrite_recording(&path, &frames, Some(keyring)).map_err(at(&path))?;
+            summary.recordings += 1;
+        }
+    }
+    Ok(summary)
+}
+
+fn at(path: &Path) -> impl FnOnce(io::Error) -> io::Error + '_ {
+    move |err| io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+}
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::encryption::{is_key_error, open_with, seal_with, Keyring};
+use crate::handlers::helpers::{attach_session_storage, now_ms};
+use crate::journal::{self, Journal, JournalOp, JournalRecord};
+use crate::mesh::StoredMesh;
+use crate::storage::{decode_session_id, SESSIONS_DIR_NAME};
+use crate::types::{
+    AppState, ChangeLog, Checkpoint, Collection, Material, SceneObject, SessionHandle, TrashedObject, MAX_TRASH_ITEMS,
+};
+
+/// A session's durable state, inside its storage directory.
+pub const SNAPSHOT_FILE_NAME: &str = "snapshot.json";
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+/// Associated data for sealed snapshots.
+const SNAPSHOT_AAD: &[u8] = b"meerkat snapshot";
+
+/// Everything needed to bring a session back after a restart. Users and undo stacks are
+/// not kept: nobody is connected to a session that was just restored.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionSnapshot {
+    pub format_version: u32,
+    pub session_id: String,
+    pub password_hash: String,
+    pub taken_at: u64,
+    pub version: u64,
+    /// Last journal record folded into this snapshot.
+    pub journal_seq: u64,
+    pub objects: HashMap<Uuid, SceneObject>,
+    #[serde(default)]
+    pub collections: HashMap<Uuid, Collection>,
+    #[serde(default)]
+    pub materials: HashMap<Uuid, Material>,
+    #[serde(default)]
+    pub meshes: HashMap<String, StoredMesh>,
+    pub checkpoints: Vec<Checkpoint>,
+    pub trash: Vec<TrashedObject>,
+}
+
+impl SessionSnapshot {
+    /// Copy a session's state. Callers pass the guards so the copy is taken under the
+    /// session's lock order and stays consistent with `journal_seq`.
+    #[allow(clippy::too_many_arguments)]
+    fn capture(
+        session: &SessionHandle,
+        objects: &HashMap<Uuid, SceneObject>,
+        collections: &HashMap<Uuid, Collection>,
+        materials: &HashMap<Uuid, Material>,
+        meshes: &HashMap<String, StoredMesh>,
+        version: u64,
+        checkpoints: &[Checkpoint],
+        trash: &HashMap<Uuid, TrashedObject>,
+        journal_seq: u64,
+    ) -> Self {
+        SessionSnapshot {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            session_id: session.session_id.clone(),
+            password_hash: session.password_hash.clone(),
+            taken_at: now_ms(),
+            version,
+            journal_seq,
+            objects: objects.clone(),
+            collections: collections.clone(),
+            materials: materials.clone(),
+            meshes: meshes.clone(),
+            checkpoints: checkpoints.to_vec(),
+            trash: trash.values().cloned().collect(),
+        }
+    }
+
+    /// A consistent copy of a live session, labelled as covering journal seq `journal_seq`.
+    pub fn of(session: &SessionHandle, journal_seq: u64) -> Self {
+        let objects = session.objects.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let version = session.current_version();
+        let collections = session.collections.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let materials = session.materials.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let meshes = session.meshes.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let checkpoints = session.checkpoints.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let trash = session.lock_trash();
+        SessionSnapshot::capture(session, &objects, &collections, &materials, &meshes, version, &checkpoints, &trash, journal_seq)
+    }
+
+    /// Fold one journal record in; records the snapshot already covers are ignored.
+    pub fn apply(&mut self, record: JournalRecord) {
+        if record.seq <= self.journal_seq {
+            return;
+        }
+        self.journal_seq = record.seq;
+        match record.op {
+            JournalOp::Objects { version, changes } => {
+                for change in changes {
+                    match change.after {
+                        Some(object) => {
+                            self.trash.retain(|item| item.object.object_id != change.object_id);
+                            self.objects.insert(change.object_id, object);
+                        }
+                        None => {
+                            self.objects.remove(&change.object_id);
+                        }
+                    }
+                }
+                self.version = self.version.max(version);
+            }
+            JournalOp::Checkpoint { checkpoint } => {
+                self.checkpoints.retain(|c| c.checkpoint_id != checkpoint.checkpoint_id);
+                self.checkpoints.push(checkpoint);
+            }
+            JournalOp::Trashed { item } => {
+                self.trash.retain(|t| t.object.object_id != item.object.object_id);
+                self.trash.push(*item);
+                if self.trash.len() > MAX_TRASH_ITEMS {
+                    self.trash.sort_by_key(|t| std::cmp::Reverse(t.deleted_at));
+                    self.trash.truncate(MAX_TRASH_ITEMS);
+                }
+            }
+            JournalOp::Collection { collection_id, after } => match after {
+                Some(collection) => {
+                    self.collections.insert(collection_id, collection);
+                }
+                None => {
+                    self.collections.remove(&collection_id);
+                }
+            },
+            JournalOp::Material { material } => {
+                self.materials.insert(material.material_id, material);
+            }
+            JournalOp::Mesh { hash, mesh } => match mesh {
+                Some(mesh) => {
+                    self.meshes.insert(hash, mesh);
+                }
+                None => {
+                    self.meshes.remove(&hash);
+                }
+            },
+        }
+    }
+
+    /// A fresh in-memory session holding this state, not yet attached to storage.
+    pub fn into_session(self) -> SessionHandle {
+        let mut session = SessionHandle::new(self.session_id, self.password_hash);
+        session.objects = RwLock::new(self.objects);
+        session.collections = RwLock::new(self.collections);
+        session.materials = RwLock::new(self.materials);
+        session.meshes = RwLock::new(self.meshes);
+        session.changes = RwLock::new(ChangeLog { version: self.version, ..ChangeLog::default() });
+        session.checkpoints = RwLock::new(self.checkpoints);
+        session.trash = std::sync::Mutex::new(self.trash.into_iter().map(|t| (t.object.object_id, t)).collect());
+        session
+    }
+}
+
+/// Write `snapshot` to `dir` atomically: a temp file, fsynced, renamed over the old one.
+/// Sealed with the current key when a keyring is given.
+pub fn write_snapshot(dir: &Path, snapshot: &SessionSnapshot, keyring: Option<&Keyring>) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let bytes = seal_with(keyring, SNAPSHOT_AAD, serde_json::to_vec(snapshot)?)?;
+    let tmp = dir.join(format!("{SNAPSHOT_FILE_NAME}.tmp"));
+    let mut file = File::create(&tmp)?;
+    file.write_all(&bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp, dir.join(SNAPSHOT_FILE_NAME))?;
+    // Make the rename itself durable.
+    File::open(dir)?.sync_all()
+}
+
+pub fn read_snapshot(dir: &Path, keyring: Option<&Keyring>) -> io::Result<Option<SessionSnapshot>> {
+    let bytes = match fs::read(dir.join(SNAPSHOT_FILE_NAME)) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let bytes = open_with(keyring, SNAPSHOT_AAD, bytes)?;
+    let snapshot: SessionSnapshot = serde_json::from_slice(&bytes)?;
+    if snapshot.format_version != SNAPSHOT_FORMAT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported snapshot format version {}", snapshot.format_version),
+        ));
+    }
+    Ok(Some(snapshot))
+}
+
+/// The session persisted in `dir` as of its last acknowledged edit: the snapshot with every
+/// later journal record applied. `None` when nothing is persisted there.
+pub fn recover(dir: &Path, keyring: Option<&Keyring>) -> io::Result<Option<SessionSnapshot>> {
+    let Some(mut snapshot) = read_snapshot(dir, keyring)? else {
+        return Ok(None);
+    };
+    for record in journal::read_journals(dir, keyring)? {
+        snapshot.apply(record);
+    }
+    Ok(Some(snapshot))
+}
+
+/// Persist a session that is not yet shared: write its full state as the snapshot covering
+/// journal seq `last_seq`, drop any older journal, and start journaling from there. Everything
+/// is (re)written with the keyring's current key, which is how a rotated key takes over.
+pub fn start_journal(session: &SessionHandle, dir: &Path, last_seq: u64, keyring: Option<&Keyring>) -> io::Result<()> {
+    let snapshot = SessionSnapshot::of(session, last_seq);
+    write_snapshot(dir, &snapshot, keyring)?;
+    journal::remove_all(dir)?;
+    *session.lock_journal() = Some(Journal::create(dir, last_seq, keyring.cloned())?);
+    Ok(())
+}
+
+/// Start journaling a session that is shared before its first snapshot is on disk: clear
+/// whatever an earlier session of the same id left in `dir` and open an empty journal from
+/// seq 0. Records appended from here on replay over a snapshot taken before the session was
+/// shared, which the caller writes next; until it does, nothing in `dir` is recoverable.
+pub fn open_journal(session: &SessionHandle, dir: &Path, keyring: Option<&Keyring>) -> io::Result<()> {
+    discard(session, dir)?;
+    *session.lock_journal() = Some(Journal::create(dir, 0, keyring.cloned())?);
+    Ok(())
+}
+
+/// Snapshot a journaled session and compact its journal. Returns false when nothing was
+/// journaled since the last snapshot. Blocks on disk I/O, none of it under the session's locks.
+pub fn snapshot_session(session: &SessionHandle, dir: &Path, keyring: Option<&Keyring>) -> io::Result<bool> {
+    // Every journal append happens under one of these locks, so holding them all pins the
+    // copy to exactly the records up to the journal's last seq. Later records may land in the
+    // file rotated below; replay skips whatever the snapshot already covers.
+    let snapshot = {
+        let objects = session.objects.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let version = session.current_version();
+        let collections = session.collections.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let materials = session.materials.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let meshes = session.meshes.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let checkpoints = session.checkpoints.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let trash = session.lock_trash();
+        let mut journal = session.lock_journal();
+        let Some(journal) = journal.as_mut() else {
+            return Ok(false);
+        };
+        if journal.last_seq() == journal.snapshot_seq() {
+            return Ok(false);
+        }
+        SessionSnapshot::capture(
+            session,
+            &objects,
+            &collections,
+            &materials,
+            &meshes,
+            version,
+            &checkpoints,
+            &trash,
+            journal.last_seq(),
+        )
+    };
+    if let Some(journal) = session.lock_journal().as_mut() {
+        journal.rotate()?;
+    }
+    write_snapshot(dir, &snapshot, keyring)?;
+    if let Some(journal) = session.lock_journal().as_mut() {
+        journal.mark_snapshot(snapshot.journal_seq);
+    }
+    journal::remove_compacted(dir, snapshot.journal_seq)?;
+    Ok(true)
+}
+
+/// Stop persisting a session and delete its snapshot and journal. The audit log and
+/// recordings stay behind.
+pub fn discard(session: &SessionHandle, dir: &Path) -> io::Result<()> {
+    *session.lock_journal() = None;
+    journal::remove_all(dir)?;
+    match fs::remove_file(dir.join(SNAPSHOT_FILE_NAME)) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Bring back every session persisted under the data dir, as of its last acknowledged edit.
+/// Called once at startup, before the listener opens. Returns how many were restored. A
+/// damaged session is logged and skipped; data the configured keys cannot decrypt is an
+/// error, so the server does not start and overwrite it.
+pub fn restore_sessions(state: &AppState) -> io::Result<usize> {
+    let Some(data_dir) = state.config.data_dir.as_deref() else {
+        return Ok(0);
+    };
+    let keyring = state.config.encryption.as_ref();
+    let sessions_dir = data_dir.join(SESSIONS_DIR_NAME);
+    let entries = match fs::read_dir(&sessions_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err),
+    };
+
+    let mut restored = 0;
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        let Some(session_id) = entry.file_name().to_str().and_then(decode_session_id) else {
+            tracing::warn!(path = %dir.display(), "skipping directory that is not a session");
+            continue;
+        };
+        let snapshot = match recover(&dir, keyring) {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => continue,
+            Err(err) if is_key_error(&err) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("cannot restore session {session_id:?} from {}: {err}", dir.display()),
+                ));
+            }
+            Err(err) => {
+                tracing::error!(session_id = %session_id, path = %dir.display(), error = %err, "failed to restore session");
+                continue;
+            }
+        };
+        if snapshot.session_id != session_id {
+            tracing::error!(
+                session_id = %session_id,
+                snapshot_session_id = %snapshot.session_id,
+                "snapshot belongs to another session; not restoring it"
+            );
+            continue;
+        }
+        let last_seq = snapshot.journal_seq;
+        let object_count = snapshot.objects.len();
+        let session = snapshot.into_session();
+        attach_session_storage(state, &session, last_seq);
+        state.sessions.insert(session_id.clone(), Arc::new(session));
+        restored += 1;
+        tracing::info!(
+            event_type = "SessionRestored",
+            session_id = %session_id,
+            object_count,
+            journal_seq = last_seq,
+            "restored persisted session"
+        );
+    }
+    Ok(restored)
+}
+
+/// Background task spawned from main when a data dir is set: snapshots every session with
+/// new journal records and compacts its journal.
+pub async fn run_snapshotter(state: AppState) {
+    let mut ticker = tokio::time::interval(state.config.snapshot_interval);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let sessions: Vec<_> = state.sessions.iter().map(|entry| entry.value().clone()).collect();
+        for session in sessions {
+            let Some(dir) = state.config.session_dir(&session.session_id) else {
+                return;
+            };
+            let sid = session.session_id.clone();
+            let keyring = state.config.encryption.clone();
+            match tokio::task::spawn_blocking(move || snapshot_session(&session, &dir, keyring.as_ref())).await {
+                Ok(Ok(true)) => tracing::debug!(event_type = "SessionSnapshot", session_id = %sid, "snapshot written"),
+                Ok(Ok(false)) => {}
+                Ok(Err(err)) => tracing::error!(session_id = %sid, error = %err, "failed to snapshot session"),
+                Err(err) => tracing::error!(session_id = %sid, error = %err, "snapshot task failed"),
+            }
+        }
+    }
+}
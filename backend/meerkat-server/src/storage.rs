@@ -0,0 +1,42 @@
+use std::path::{Path, PathBuf};
+
+/// Subdirectory of the data dir holding one directory per session.
+pub const SESSIONS_DIR_NAME: &str = "sessions";
+
+/// Session ids are free-form, so directory names keep `[A-Za-z0-9_-]` and percent-encode
+/// every other byte. That keeps them readable while ruling out `..`, separators and
+/// case-insensitive collisions on other bytes.
+pub fn encode_session_id(session_id: &str) -> String {
+    let mut encoded = String::with_capacity(session_id.len());
+    for byte in session_id.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-' {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// Inverse of `encode_session_id`; `None` for names it could not have produced.
+pub fn decode_session_id(name: &str) -> Option<String> {
+    let bytes = name.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = name.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Where everything persisted for `session_id` lives.
+pub fn session_dir(data_dir: &Path, session_id: &str) -> PathBuf {
+    data_dir.join(SESSIONS_DIR_NAME).join(encode_session_id(session_id))
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use crate::handlers::helpers::now_ms;
+use crate::types::{AppState, SessionHandle};
+
+/// How often the sweeper looks for trash past the retention window.
+pub const TRASH_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// When a trashed object deleted at `deleted_at` stops being restorable.
+pub fn expires_at(deleted_at: u64, retention: Duration) -> u64 {
+    deleted_at.saturating_add(retention.as_millis() as u64)
+}
+
+/// Drop trash entries whose retention ran out by `now`. Returns how many were purged.
+pub fn purge_expired(session: &SessionHandle, now: u64, retention: Duration) -> usize {
+    let mut trash = session.lock_trash();
+    let before = trash.len();
+    trash.retain(|_, item| expires_at(item.deleted_at, retention) > now);
+    before - trash.len()
+}
+
+/// Background task spawned from main: purges expired trash in every session.
+pub async fn run_trash_sweeper(state: AppState) {
+    let mut ticker = tokio::time::interval(TRASH_SWEEP_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let sessions: Vec<_> = state.sessions.iter().map(|entry| entry.value().clone()).collect();
+        let now = now_ms();
+        for session in sessions {
+            let purged = purge_expired(&session, now, state.config.trash_retention);
+            if purged > 0 {
+                tracing::info!(
+                    event_type = "TrashPurged",
+                    session_id = %session.session_id,
+                    purged,
+                    "purged expired trash"
+                );
+            }
+        }
+    }
+}
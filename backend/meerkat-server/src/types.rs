@@ -1,10 +1,19 @@
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+use crate::audit::{self, AuditLog};
+use crate::config::ServerConfig;
+use crate::history::{CollectionChange, HistoryEntry, ObjectChange, UndoHistory};
+use crate::journal::{Journal, JournalChange, JournalOp};
+use crate::mesh::{MeshUpload, StoredMesh};
+use crate::rate_limit::{RateClass, TokenBucket};
+use crate::recording::Recorder;
+
 pub const COLOR_PALETTE: [[u8; 3]; 10] = [
     [231, 76, 60],   // red
     [46, 204, 113],  // green
@@ -27,6 +36,47 @@ pub struct AppState {
     /// Per-connection lag tracking for bounded queue backpressure decisions.
     pub connection_backpressure: Arc<DashMap<Uuid, LagState>>,
     pub session_connections: Arc<DashMap<String, HashSet<Uuid>>>,
+    /// Per-connection token buckets, one per rate class.
+    pub rate_limits: Arc<DashMap<Uuid, HashMap<RateClass, TokenBucket>>>,
+    /// Broadcasts held back from connections in the middle of a state sync, replayed once the
+    /// sync is written (see helpers::send_state_sync).
+    pub sync_backlogs: Arc<DashMap<Uuid, SyncBacklog>>,
+    pub config: Arc<ServerConfig>,
+}
+
+/// What a connection in the middle of a state sync has to be sent once the sync is written.
+#[derive(Clone, Debug, Default)]
+pub struct SyncBacklog {
+    /// Messages already on the connection's channel when the sync's snapshot was taken. The
+    /// snapshot covers them, so all but errors are dropped instead of applied a second time.
+    pub stale: usize,
+    /// Broadcasts since the snapshot, in order. None once more piled up than are kept.
+    pub held: Option<Vec<String>>,
+}
+
+impl AppState {
+    pub fn new() -> Self {
+        Self::with_config(ServerConfig::default())
+    }
+
+    pub fn with_config(config: ServerConfig) -> Self {
+        AppState {
+            sessions: Arc::new(DashMap::new()),
+            connections: Arc::new(DashMap::new()),
+            connection_meta: Arc::new(DashMap::new()),
+            connection_backpressure: Arc::new(DashMap::new()),
+            session_connections: Arc::new(DashMap::new()),
+            rate_limits: Arc::new(DashMap::new()),
+            sync_backlogs: Arc::new(DashMap::new()),
+            config: Arc::new(config),
+        }
+    }
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -35,20 +85,185 @@ pub struct LagState {
     pub last_full_at_ms: u64,
 }
 
+/// Named checkpoints a session can hold before CreateCheckpoint is refused.
+pub const MAX_CHECKPOINTS: usize = 50;
+pub const MAX_CHECKPOINT_NAME_LEN: usize = 128;
+
+/// Collections a session can hold before CreateCollection is refused.
+pub const MAX_COLLECTIONS: usize = 256;
+pub const MAX_COLLECTION_NAME_LEN: usize = 128;
+
+/// Materials a session can hold before CreateMaterial is refused, and material slots per object.
+pub const MAX_MATERIALS: usize = 1_024;
+pub const MAX_MATERIAL_NAME_LEN: usize = 128;
+pub const MAX_MATERIAL_SLOTS: usize = 64;
+
+/// Limits on mesh data (see mesh): vertices, face corners, and the compressed bytes a session
+/// stores across all its meshes. Meshes travel as base64 chunks of MESH_CHUNK_LEN compressed
+/// bytes, at most MAX_MESH_CHUNKS of them.
+pub const MAX_MESH_VERTICES: usize = 1_000_000;
+pub const MAX_MESH_CORNERS: usize = 4_000_000;
+pub const MESH_CHUNK_LEN: usize = 256 * 1024;
+pub const MAX_MESH_CHUNKS: u32 = 256;
+pub const MAX_SESSION_MESH_BYTES: usize = 512 * 1024 * 1024;
+
+/// Limits on modifier stacks (see modifiers): entries per object, generic parameters per
+/// entry, and the ranges Blender's own UI allows for the typed settings.
+pub const MAX_MODIFIERS: usize = 32;
+/// Blender's limit on modifier names.
+pub const MAX_MODIFIER_NAME_LEN: usize = 63;
+pub const MAX_MODIFIER_PARAMETERS: usize = 64;
+pub const MAX_ARRAY_COUNT: u32 = 1_000;
+pub const MAX_BEVEL_SEGMENTS: u32 = 100;
+pub const MAX_SUBDIVISION_LEVELS: u32 = 6;
+
+/// Custom properties an object can carry, and how large each may be (see custom_properties).
+pub const MAX_CUSTOM_PROPERTIES: usize = 64;
+/// Blender's limit on ID property names.
+pub const MAX_CUSTOM_PROPERTY_KEY_LEN: usize = 63;
+pub const MAX_CUSTOM_STRING_LEN: usize = 1_024;
+pub const MAX_CUSTOM_VECTOR_LEN: usize = 16;
+pub const MAX_CUSTOM_LIST_LEN: usize = 64;
+
+/// Limits on PatchProperties (see property_patch): patches per event, dot-separated segments
+/// per path, and the size of an object's extra properties as JSON text.
+pub const MAX_PROPERTY_PATCHES: usize = 64;
+pub const MAX_PROPERTY_PATH_DEPTH: usize = 8;
+pub const MAX_EXTRA_PROPERTIES_BYTES: usize = 16 * 1024;
+
+/// Deleted objects a session's trash holds; the oldest are purged first beyond this.
+pub const MAX_TRASH_ITEMS: usize = 1_000;
+
+/// How many object changes a session remembers for incremental sync before older
+/// history is dropped and clients behind it fall back to a full sync.
+pub const CHANGE_LOG_CAPACITY: usize = 10_000;
+
 // Session related logic is simplified by using a separate struct that contains RwLocks for interior mutability,
 // which the AppState holds Arc references to for shared ownership across connections.
 pub struct SessionHandle {
     pub objects: RwLock<HashMap<Uuid, SceneObject>>,
     pub users: RwLock<HashMap<Uuid, User>>,
+    /// Version counter plus recent object changes, used to answer incremental RequestStateSync.
+    /// Always acquired after `objects` so the version stays consistent with the objects map.
+    pub changes: RwLock<ChangeLog>,
+    /// Latest transform per object waiting for the next coalesced TransformUpdated flush.
+    pub pending_transforms: Mutex<HashMap<Uuid, PendingTransform>>,
+    /// True while a flush task is scheduled for `pending_transforms`.
+    pub transform_flush_scheduled: AtomicBool,
+    /// Set once the journal failed to reach the disk. The session refuses edits from then on,
+    /// since they could no longer survive a crash.
+    pub storage_failed: AtomicBool,
+    /// Set once the session's clients have been told about `storage_failed`.
+    pub storage_failure_announced: AtomicBool,
+    /// Per-user undo/redo stacks. Acquired after `objects`.
+    pub history: Mutex<UndoHistory>,
+    /// Collections objects are grouped into. Acquired after `objects`.
+    pub collections: RwLock<HashMap<Uuid, Collection>>,
+    /// The session's material table. Acquired after `objects` and `collections`.
+    pub materials: RwLock<HashMap<Uuid, Material>>,
+    /// Mesh data objects reference, by content hash. Acquired after `objects` and `materials`.
+    pub meshes: RwLock<HashMap<String, StoredMesh>>,
+    /// Mesh uploads still waiting for chunks, by uploading connection and content hash: two
+    /// clients sending the same mesh compress it their own way. Leaf lock.
+    pub mesh_uploads: Mutex<HashMap<(Uuid, String), MeshUpload>>,
+    /// Named copies of `objects`, oldest first. Acquired after `objects`.
+    pub checkpoints: RwLock<Vec<Checkpoint>>,
+    /// Deleted objects still restorable with RestoreObject. Acquired after `objects`.
+    pub trash: Mutex<HashMap<Uuid, TrashedObject>>,
+    /// Who changed what, per object. Acquired after `objects`; never while holding `history`.
+    pub audit: Mutex<AuditLog>,
+    /// Running `.mrec` recording, when enabled. Leaf lock: nothing else is taken while holding it.
+    pub recorder: Mutex<Option<Recorder>>,
+    /// Write-ahead journal of applied mutations, when persistence is enabled. Leaf lock.
+    pub journal: Mutex<Option<Journal>>,
+    /// Serializes journal fsyncs so concurrent edits share one instead of queueing several.
+    pub journal_sync: tokio::sync::Mutex<()>,
+    /// Kept in memory when its last user leaves (the session a replay server plays into).
+    pub pinned: bool,
     pub session_id: String,
     pub password_hash: String, 
 }
 
+#[derive(Clone, Debug)]
+pub struct PendingTransform {
+    pub transform: Transform,
+    pub updated_by: Uuid,
+    /// Journal seq the update was appended at; broadcast once the journal is synced that far.
+    pub journal_seq: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Upserted,
+    Deleted,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChangeEntry {
+    pub version: u64,
+    pub object_id: Uuid,
+    pub kind: ChangeKind,
+}
+
+#[derive(Default, Debug)]
+pub struct ChangeLog {
+    pub version: u64,
+    pub entries: VecDeque<ChangeEntry>,
+}
+
+impl ChangeLog {
+    /// Net change per object since `since_version`, or None if that history has been
+    /// trimmed (or the version is from a different incarnation of the session).
+    pub fn changes_since(&self, since_version: u64) -> Option<HashMap<Uuid, ChangeKind>> {
+        if since_version > self.version {
+            return None;
+        }
+        if since_version < self.version {
+            let first_retained = self.entries.front().map(|e| e.version)?;
+            if since_version + 1 < first_retained {
+                return None;
+            }
+        }
+        let mut net = HashMap::new();
+        for entry in self.entries.iter().filter(|e| e.version > since_version) {
+            net.insert(entry.object_id, entry.kind);
+        }
+        Some(net)
+    }
+}
+
+/// A named copy of the session's objects, restorable with RestoreCheckpoint.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Checkpoint {
+    pub checkpoint_id: Uuid,
+    pub name: String,
+    pub created_by: Uuid,
+    pub created_at: u64, // unix timestamp ms
+    /// Session version the copy was taken at.
+    pub version: u64,
+    pub objects: HashMap<Uuid, SceneObject>,
+}
+
+/// An object removed by DeleteObject, with who removed it and when.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TrashedObject {
+    pub object: SceneObject,
+    pub deleted_by: Uuid,
+    pub deleted_at: u64, // unix timestamp ms
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Session {
     pub session_id: String,
     pub objects: HashMap<Uuid, SceneObject>,
+    #[serde(default)]
+    pub collections: HashMap<Uuid, Collection>,
+    #[serde(default)]
+    pub materials: HashMap<Uuid, Material>,
     pub users: HashMap<Uuid, User>,
+    /// Change-log version this snapshot reflects; pass it back as `since_version` to resync incrementally.
+    #[serde(default)]
+    pub version: u64,
 }
 
 // Helper function to handle poisoned locks by logging a warning and recovering with a new lock containing default data. This prevents the entire session from becoming inaccessible due to one poisoned lock, at the cost of potentially losing some data.
@@ -64,27 +279,342 @@ fn recover_read<T: Clone>(lock: &std::sync::RwLock<T>, name: &str) -> T {
 
 // Build a session by acquiring read locks on the SessionHandle's internal state and cloning the data into a new Session struct.
 impl SessionHandle {
+    pub fn new(session_id: String, password_hash: String) -> Self {
+        SessionHandle {
+            objects: RwLock::new(HashMap::new()),
+            users: RwLock::new(HashMap::new()),
+            collections: RwLock::new(HashMap::new()),
+            materials: RwLock::new(HashMap::new()),
+            meshes: RwLock::new(HashMap::new()),
+            mesh_uploads: Mutex::new(HashMap::new()),
+            changes: RwLock::new(ChangeLog::default()),
+            pending_transforms: Mutex::new(HashMap::new()),
+            transform_flush_scheduled: AtomicBool::new(false),
+            storage_failed: AtomicBool::new(false),
+            storage_failure_announced: AtomicBool::new(false),
+            history: Mutex::new(UndoHistory::default()),
+            checkpoints: RwLock::new(Vec::new()),
+            trash: Mutex::new(HashMap::new()),
+            audit: Mutex::new(AuditLog::default()),
+            recorder: Mutex::new(None),
+            journal: Mutex::new(None),
+            journal_sync: tokio::sync::Mutex::new(()),
+            pinned: false,
+            session_id,
+            password_hash,
+        }
+    }
+
     pub fn session_snapshot(&self) -> Session {
         // If any of the locks are poisoned, we log a warning and recover by creating a new lock with empty data. This prevents the entire session from becoming inaccessible due to one poisoned lock, at the cost of potentially losing some data.
-        let objects = recover_read(&self.objects, "objects");
+        // Objects and version are read under the same objects guard so the snapshot matches its version.
+        let (objects, collections, materials, version) = {
+            let objects = match self.objects.read() {
+                Ok(guard) => guard,
+                Err(poisoned) => {
+                    tracing::warn!(lock = "objects", "RwLock poisoned, recovering anyway");
+                    poisoned.into_inner()
+                }
+            };
+            (
+                objects.clone(),
+                recover_read(&self.collections, "collections"),
+                recover_read(&self.materials, "materials"),
+                self.current_version(),
+            )
+        };
         let users = recover_read(&self.users, "users");
         let session_id = self.session_id.clone();
         Session {
             session_id,
             objects,
+            collections,
+            materials,
             users,
+            version,
+        }
+    }
+
+    pub fn current_version(&self) -> u64 {
+        match self.changes.read() {
+            Ok(guard) => guard.version,
+            Err(poisoned) => poisoned.into_inner().version,
+        }
+    }
+
+    /// Drop queued coalesced transforms for objects that were just deleted or overwritten by
+    /// another mutation, so a late flush cannot broadcast a stale position after it.
+    pub fn discard_pending_transforms(&self, object_ids: &[Uuid]) {
+        let mut pending = match self.pending_transforms.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session pending transforms lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        for object_id in object_ids {
+            pending.remove(object_id);
+        }
+    }
+
+    pub fn lock_history(&self) -> std::sync::MutexGuard<'_, UndoHistory> {
+        match self.history.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session history lock poisoned, recovering");
+                poisoned.into_inner()
+            }
         }
     }
+
+    /// Drop the mesh uploads a connection leaves unfinished when it goes.
+    pub fn forget_uploads(&self, connection_id: Uuid) {
+        let mut uploads = match self.mesh_uploads.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session mesh uploads lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        uploads.retain(|(uploader, _), _| *uploader != connection_id);
+    }
+
+    pub fn lock_trash(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, TrashedObject>> {
+        match self.trash.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session trash lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        }
+    }
+
+    /// Keep a deleted object restorable. Callers must hold the objects write lock.
+    pub fn move_to_trash(&self, object: SceneObject, deleted_by: Uuid, deleted_at: u64) {
+        let mut trash = self.lock_trash();
+        let item = TrashedObject { object, deleted_by, deleted_at };
+        self.append_journal(|| JournalOp::Trashed { item: Box::new(item.clone()) });
+        trash.insert(item.object.object_id, item);
+        while trash.len() > MAX_TRASH_ITEMS {
+            let Some(oldest) = trash.values().min_by_key(|t| t.deleted_at).map(|t| t.object.object_id) else {
+                break;
+            };
+            trash.remove(&oldest);
+        }
+    }
+
+    /// Hashes of every mesh an object uses or could get back: through a checkpoint, the
+    /// trash or an undo. Callers pass the objects guard they hold; the other locks are taken
+    /// one at a time and released, so the meshes lock can be taken after this returns.
+    pub fn referenced_meshes(&self, objects: &HashMap<Uuid, SceneObject>) -> HashSet<String> {
+        let mut hashes: HashSet<String> = objects.values().filter_map(|o| o.mesh.as_ref().map(|m| m.hash.clone())).collect();
+        {
+            let checkpoints = self.checkpoints.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let saved = checkpoints.iter().flat_map(|c| c.objects.values());
+            hashes.extend(saved.filter_map(|o| o.mesh.as_ref().map(|m| m.hash.clone())));
+        }
+        {
+            let trash = self.lock_trash();
+            hashes.extend(trash.values().filter_map(|t| t.object.mesh.as_ref().map(|m| m.hash.clone())));
+        }
+        let history = self.lock_history();
+        hashes.extend(history.objects().filter_map(|o| o.mesh.as_ref().map(|m| m.hash.clone())));
+        hashes
+    }
+
+    pub fn lock_audit(&self) -> std::sync::MutexGuard<'_, AuditLog> {
+        match self.audit.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session audit lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        }
+    }
+
+    pub fn lock_recorder(&self) -> std::sync::MutexGuard<'_, Option<Recorder>> {
+        match self.recorder.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session recorder lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        }
+    }
+
+    pub fn lock_journal(&self) -> std::sync::MutexGuard<'_, Option<Journal>> {
+        match self.journal.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session journal lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        }
+    }
+
+    /// Buffer a journal record, if the session is journaled. Callers must hold the lock that
+    /// guards the state the record describes, so records land in mutation order.
+    pub fn append_journal(&self, op: impl FnOnce() -> JournalOp) {
+        let result = match self.lock_journal().as_mut() {
+            Some(journal) => journal.append(op()),
+            None => return,
+        };
+        if let Err(err) = result {
+            self.abandon_journal(&err.to_string());
+        }
+    }
+
+    /// Seq of the last journal record appended, or 0 when nothing is journaled.
+    pub fn journal_seq(&self) -> u64 {
+        self.lock_journal().as_ref().map_or(0, Journal::last_seq)
+    }
+
+    /// True when the journal record appended at `seq` is on disk (or the session is not
+    /// persisted). False once storage has failed: the record may never have reached the disk.
+    pub fn journal_synced_through(&self, seq: u64) -> bool {
+        !self.storage_failed() && self.lock_journal().as_ref().is_none_or(|journal| journal.synced_seq() >= seq)
+    }
+
+    /// Wait until every journal record appended so far is on disk. Handlers call this after
+    /// mutating and before broadcasting, so an edit is never acknowledged before it is durable.
+    /// Concurrent callers share fsyncs: whoever gets the sync lock flushes everything buffered.
+    pub async fn commit_journal(&self) {
+        let target = match self.lock_journal().as_ref() {
+            Some(journal) if journal.synced_seq() < journal.last_seq() => journal.last_seq(),
+            _ => return,
+        };
+        let _sync = self.journal_sync.lock().await;
+        let (seq, files) = {
+            let mut guard = self.lock_journal();
+            let Some(journal) = guard.as_mut() else {
+                return;
+            };
+            if journal.synced_seq() >= target {
+                return;
+            }
+            match journal.begin_sync() {
+                Ok(pending) => pending,
+                Err(err) => {
+                    drop(guard);
+                    self.abandon_journal(&err.to_string());
+                    return;
+                }
+            }
+        };
+        match tokio::task::spawn_blocking(move || files.iter().try_for_each(std::fs::File::sync_data)).await {
+            Ok(Ok(())) => {
+                if let Some(journal) = self.lock_journal().as_mut() {
+                    journal.mark_synced(seq);
+                }
+            }
+            Ok(Err(err)) => self.abandon_journal(&err.to_string()),
+            Err(err) => self.abandon_journal(&err.to_string()),
+        }
+    }
+
+    /// True once the session's journal has failed and edits are refused.
+    pub fn storage_failed(&self) -> bool {
+        self.storage_failed.load(Ordering::Acquire)
+    }
+
+    /// A journal that cannot reach the disk is dropped and the session turns read-only: what
+    /// is already synced survives a crash, and nothing after it is acknowledged as if it would.
+    fn abandon_journal(&self, error: &str) {
+        *self.lock_journal() = None;
+        self.storage_failed.store(true, Ordering::Release);
+        tracing::error!(session_id = %self.session_id, error, "failed to write journal, session is now read-only");
+    }
+
+    /// Append one audit entry per change, attributed to the user's current display name.
+    /// Callers must hold the objects write lock and must not hold `history`.
+    pub fn record_audit(&self, user_id: Uuid, event_type: &str, at_ms: u64, changes: &[ObjectChange]) {
+        let display_name = match self.users.read() {
+            Ok(users) => users.get(&user_id).map(|u| u.display_name.clone()),
+            Err(poisoned) => poisoned.into_inner().get(&user_id).map(|u| u.display_name.clone()),
+        }
+        .unwrap_or_default();
+        let mut log = self.lock_audit();
+        for entry in audit::entries_for(user_id, &display_name, event_type, at_ms, changes) {
+            log.record(entry);
+        }
+    }
+
+    /// Record a user's edit: bumps the version for every touched object, appends it to the
+    /// audit log and pushes it onto that user's undo stack. Callers must hold the objects write lock.
+    pub fn record_edit(&self, user_id: Uuid, event_type: &'static str, at_ms: u64, changes: Vec<ObjectChange>) -> u64 {
+        self.record_collection_edit(user_id, event_type, at_ms, changes, Vec::new())
+    }
+
+    /// `record_edit` for an edit that also created or deleted collections, which undo brings
+    /// back or removes along with the objects. The collection changes must already be journaled.
+    pub fn record_collection_edit(
+        &self,
+        user_id: Uuid,
+        event_type: &'static str,
+        at_ms: u64,
+        changes: Vec<ObjectChange>,
+        collections: Vec<CollectionChange>,
+    ) -> u64 {
+        let version = if changes.is_empty() { self.current_version() } else { self.record_object_changes(&changes) };
+        self.record_audit(user_id, event_type, at_ms, &changes);
+        self.lock_history().record(user_id, HistoryEntry { event_type, at_ms, changes, collections });
+        version
+    }
+
+    /// Bump the version for each change and journal it, without touching undo history. Callers
+    /// must hold the objects write lock. Objects that come back (undo, checkpoint restore) leave the trash.
+    pub fn record_object_changes(&self, changes: &[ObjectChange]) -> u64 {
+        let mut version = self.current_version();
+        {
+            let mut trash = self.lock_trash();
+            if !trash.is_empty() {
+                for change in changes.iter().filter(|c| c.after.is_some()) {
+                    trash.remove(&change.object_id);
+                }
+            }
+        }
+        for change in changes {
+            let kind = if change.after.is_some() { ChangeKind::Upserted } else { ChangeKind::Deleted };
+            version = self.record_change(change.object_id, kind);
+        }
+        self.append_journal(|| JournalOp::Objects {
+            version,
+            changes: changes
+                .iter()
+                .map(|c| JournalChange { object_id: c.object_id, after: c.after.clone() })
+                .collect(),
+        });
+        version
+    }
+
+    /// Record an object change and return the new session version.
+    /// Callers must still hold the objects write lock so versions are assigned in mutation order.
+    pub fn record_change(&self, object_id: Uuid, kind: ChangeKind) -> u64 {
+        let mut changes = match self.changes.write() {
+            Ok(guard) => guard,
+            Err(poisoned) => {
+                tracing::warn!("Session change log lock poisoned, recovering");
+                poisoned.into_inner()
+            }
+        };
+        changes.version += 1;
+        let version = changes.version;
+        changes.entries.push_back(ChangeEntry { version, object_id, kind });
+        while changes.entries.len() > CHANGE_LOG_CAPACITY {
+            changes.entries.pop_front();
+        }
+        version
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Transform {
     pub position: [f64; 3], // initializing an array of 3 float64 values (xyz)
     pub rotation: [f64; 3],
     pub scale: [f64; 3],
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 // enum for object type
 pub enum ObjectType {
     Cube,
@@ -103,15 +633,41 @@ pub enum ObjectType {
     AreaLight,  // needs struct
     SunLight,   // needs struct
     AssetRef,   // asset ref being a reference to an asset inside of a library
+    Mesh,       // geometry comes only from the object's mesh data
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+impl ObjectType {
+    /// The name the type is serialized under, which is what clients see, hash and export.
+    pub fn wire_name(&self) -> &'static str {
+        match self {
+            ObjectType::Cube => "Cube",
+            ObjectType::Sphere => "Sphere",
+            ObjectType::Cylinder => "Cylinder",
+            ObjectType::Plane => "Plane",
+            ObjectType::Circle => "Circle",
+            ObjectType::Icosphere => "Icosphere",
+            ObjectType::Cone => "Cone",
+            ObjectType::Torus => "Torus",
+            ObjectType::Grid => "Grid",
+            ObjectType::Monkey => "Monkey",
+            ObjectType::Camera => "Camera",
+            ObjectType::PointLight => "PointLight",
+            ObjectType::SpotLight => "SpotLight",
+            ObjectType::AreaLight => "AreaLight",
+            ObjectType::SunLight => "SunLight",
+            ObjectType::AssetRef => "AssetRef",
+            ObjectType::Mesh => "Mesh",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum LensType {
     Perspective,
     Orthographic,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum SensorFit {
     // blender sends the camera properties in all caps
@@ -120,7 +676,7 @@ pub enum SensorFit {
     Vertical,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct CameraProperties {
     // Lens
     pub lens_type: LensType,
@@ -142,7 +698,7 @@ pub struct CameraProperties {
     pub sensor_height: f64,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct PointLightProperties {
     pub color: [f32; 3],
     pub use_temperature: bool, // gates temperature value (Blender greys it out when false)
@@ -168,7 +724,7 @@ pub struct PointLightProperties {
     pub cutoff_distance: f32,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct SpotLightProperties {
     pub color: [f32; 3],
     pub use_temperature: bool,
@@ -197,7 +753,7 @@ pub struct SpotLightProperties {
     pub cutoff_distance: f32,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct AreaLightProperties {
     // Common
     pub color: [f32; 3],
@@ -227,7 +783,7 @@ pub struct AreaLightProperties {
     pub cutoff_distance: f32,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum AreaLightShape {
     // different shapes that an area light can have since blender has fixed options for the shape of an area light
     RECTANGLE, 
@@ -236,7 +792,7 @@ pub enum AreaLightShape {
     ELLIPSE,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct SunLightProperties {
     // Common
     pub color: [f32; 3],
@@ -260,7 +816,7 @@ pub struct SunLightProperties {
     pub volume_factor: f32,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum ObjectProperties {
     Camera(CameraProperties),
     PointLight(PointLightProperties),
@@ -269,21 +825,516 @@ pub enum ObjectProperties {
     SunLight(SunLightProperties),
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+// Defaults are what Blender gives a newly added camera or light; importers fill in whatever
+// the source format does not say with them.
+
+impl Default for CameraProperties {
+    fn default() -> Self {
+        CameraProperties {
+            lens_type: LensType::Perspective,
+            focal_length: 50.0,
+            orthographic_scale: 6.0,
+            shift_x: 0.0,
+            shift_y: 0.0,
+            clip_start: 0.1,
+            clip_end: 100.0,
+            focal_distance: 10.0,
+            aperture_fstop: 2.8,
+            aperture_blades: 0,
+            aperture_rotation: 0.0,
+            aperture_ratio: 1.0,
+            sensor_fit: SensorFit::Auto,
+            sensor_width: 36.0,
+            sensor_height: 24.0,
+        }
+    }
+}
+
+impl Default for PointLightProperties {
+    fn default() -> Self {
+        PointLightProperties {
+            color: [1.0, 1.0, 1.0],
+            use_temperature: false,
+            temperature: 6500.0,
+            exposure: 0.0,
+            power: 1000.0,
+            radius: 0.1,
+            soft_falloff: true,
+            normalize: true,
+            cast_shadow: true,
+            shadow_jitter: false,
+            shadow_jitter_overblur: 10.0,
+            shadow_filter_radius: 1.0,
+            shadow_maximum_resolution: 0.001,
+            diffuse_factor: 1.0,
+            specular_factor: 1.0,
+            transmission_factor: 1.0,
+            volume_factor: 1.0,
+            use_custom_distance: false,
+            cutoff_distance: 40.0,
+        }
+    }
+}
+
+impl Default for SpotLightProperties {
+    fn default() -> Self {
+        SpotLightProperties {
+            color: [1.0, 1.0, 1.0],
+            use_temperature: false,
+            temperature: 6500.0,
+            exposure: 0.0,
+            normalize: true,
+            power: 1000.0,
+            radius: 0.1,
+            soft_falloff: true,
+            angle: std::f32::consts::FRAC_PI_4,
+            blend: 0.15,
+            show_cone: false,
+            cast_shadow: true,
+            shadow_jitter: false,
+            shadow_jitter_overblur: 10.0,
+            shadow_filter_radius: 1.0,
+            shadow_maximum_resolution: 0.001,
+            diffuse_factor: 1.0,
+            specular_factor: 1.0,
+            transmission_factor: 1.0,
+            volume_factor: 1.0,
+            use_custom_distance: false,
+            cutoff_distance: 40.0,
+        }
+    }
+}
+
+impl Default for AreaLightProperties {
+    fn default() -> Self {
+        AreaLightProperties {
+            color: [1.0, 1.0, 1.0],
+            use_temperature: false,
+            temperature: 6500.0,
+            exposure: 0.0,
+            normalize: true,
+            power: 1000.0,
+            shape: AreaLightShape::SQUARE,
+            size_x: 1.0,
+            size_y: 1.0,
+            size: 1.0,
+            cast_shadow: true,
+            shadow_jitter: false,
+            shadow_jitter_overblur: 10.0,
+            shadow_filter_radius: 1.0,
+            shadow_maximum_resolution: 0.001,
+            diffuse_factor: 1.0,
+            specular_factor: 1.0,
+            transmission_factor: 1.0,
+            volume_factor: 1.0,
+            use_custom_distance: false,
+            cutoff_distance: 40.0,
+        }
+    }
+}
+
+impl Default for SunLightProperties {
+    fn default() -> Self {
+        SunLightProperties {
+            color: [1.0, 1.0, 1.0],
+            use_temperature: false,
+            temperature: 6500.0,
+            exposure: 0.0,
+            normalize: true,
+            strength: 1.0,
+            angle: 0.00918,
+            cast_shadow: true,
+            shadow_jitter: false,
+            shadow_jitter_overblur: 10.0,
+            shadow_filter_radius: 1.0,
+            shadow_maximum_resolution: 0.001,
+            diffuse_factor: 1.0,
+            specular_factor: 1.0,
+            transmission_factor: 1.0,
+            volume_factor: 1.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct SceneObject {
     pub object_id: Uuid,
     pub name: String,
     pub object_type: ObjectType,
     pub asset_id: Option<String>,      // None for non-asset objects
     pub asset_library: Option<String>, // None for non-asset objects
+    /// Object this one is parented to. `transform` is then relative to the parent's space, and
+    /// a parent the scene does not have counts as none (see hierarchy).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<Uuid>,
     pub transform: Transform,
     pub properties: Option<ObjectProperties>, // None for primitives/asset refs
+    /// Collections the object belongs to, in no particular order. Ids the session has no
+    /// collection for are ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collections: Vec<Uuid>,
+    /// Pipeline metadata carried along with the object, like Blender's custom properties.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom_properties: BTreeMap<String, CustomValue>,
+    /// Properties the typed `properties` have no field for, as a JSON document edited by path
+    /// (see property_patch). Lets clients sync settings the server has no struct for yet.
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra_properties: serde_json::Map<String, serde_json::Value>,
+    /// The object's material slots in order, as Blender has them; None is an empty slot. Ids
+    /// the session has no material for are treated as empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub material_slots: Vec<Option<Uuid>>,
+    /// The object's own geometry, replacing what its type would generate. None for untouched
+    /// primitives, cameras and lights.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<MeshRef>,
+    /// Counts every change to `mesh`, clearing it and undoing it included, and never goes down,
+    /// so an edit made against older geometry can be told apart and refused.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub mesh_version: u64,
+    /// The object's modifier stack, evaluated first to last as Blender does.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifiers: Vec<Modifier>,
     pub created_by: Uuid,
     pub last_updated_by: Uuid,
     pub last_updated_at: u64, // unix timestamp ms
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+impl SceneObject {
+    /// A bare object at the origin, created by `created_by` at `now`: no properties, memberships,
+    /// materials, mesh or modifiers yet.
+    pub fn new(object_id: Uuid, name: String, object_type: ObjectType, created_by: Uuid, now: u64) -> Self {
+        SceneObject {
+            object_id,
+            name,
+            object_type,
+            asset_id: None,
+            asset_library: None,
+            parent_id: None,
+            transform: Transform { position: [0.0; 3], rotation: [0.0; 3], scale: [1.0; 3] },
+            properties: None,
+            collections: Vec::new(),
+            custom_properties: BTreeMap::new(),
+            extra_properties: serde_json::Map::new(),
+            material_slots: Vec::new(),
+            mesh: None,
+            mesh_version: 0,
+            modifiers: Vec::new(),
+            created_by,
+            last_updated_by: created_by,
+            last_updated_at: now,
+        }
+    }
+}
+
+/// Which stored mesh an object uses.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MeshRef {
+    pub hash: String,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// One entry of an object's modifier stack. The id is the client's and, like the name, unique
+/// within the stack.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Modifier {
+    pub modifier_id: Uuid,
+    pub name: String,
+    #[serde(default = "shown")]
+    pub show_viewport: bool,
+    #[serde(default = "shown")]
+    pub show_render: bool,
+    #[serde(flatten)]
+    pub kind: ModifierKind,
+}
+
+fn shown() -> bool {
+    true
+}
+
+/// What a modifier does. The common blocking modifiers have typed settings, left-out fields
+/// taking Blender's defaults; any other modifier travels by its Blender type identifier (such
+/// as `SOLIDIFY`) with its settings as generic values.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "modifier_type")]
+pub enum ModifierKind {
+    Array(ArrayModifier),
+    Mirror(MirrorModifier),
+    Bevel(BevelModifier),
+    Subdivision(SubdivisionModifier),
+    Other {
+        blender_type: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        parameters: BTreeMap<String, CustomValue>,
+    },
+}
+
+impl ModifierKind {
+    /// Blender's identifier for the modifier type.
+    pub fn blender_type(&self) -> &str {
+        match self {
+            ModifierKind::Array(_) => "ARRAY",
+            ModifierKind::Mirror(_) => "MIRROR",
+            ModifierKind::Bevel(_) => "BEVEL",
+            ModifierKind::Subdivision(_) => "SUBSURF",
+            ModifierKind::Other { blender_type, .. } => blender_type,
+        }
+    }
+}
+
+/// Offsets are in multiples of the object's size (relative) or in object units (constant).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ArrayModifier {
+    pub count: u32,
+    pub use_relative_offset: bool,
+    pub relative_offset_displace: [f32; 3],
+    pub use_constant_offset: bool,
+    pub constant_offset_displace: [f32; 3],
+    pub use_merge_vertices: bool,
+    pub merge_threshold: f32,
+}
+
+impl Default for ArrayModifier {
+    fn default() -> Self {
+        ArrayModifier {
+            count: 2,
+            use_relative_offset: true,
+            relative_offset_displace: [1.0, 0.0, 0.0],
+            use_constant_offset: false,
+            constant_offset_displace: [0.0; 3],
+            use_merge_vertices: false,
+            merge_threshold: 0.01,
+        }
+    }
+}
+
+/// Axes are x, y, z. `mirror_object` mirrors across another session object instead of the
+/// object's own origin.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MirrorModifier {
+    pub use_axis: [bool; 3],
+    pub use_bisect_axis: [bool; 3],
+    pub use_clip: bool,
+    pub use_mirror_merge: bool,
+    pub merge_threshold: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirror_object: Option<Uuid>,
+}
+
+impl Default for MirrorModifier {
+    fn default() -> Self {
+        MirrorModifier {
+            use_axis: [true, false, false],
+            use_bisect_axis: [false; 3],
+            use_clip: false,
+            use_mirror_merge: true,
+            merge_threshold: 0.001,
+            mirror_object: None,
+        }
+    }
+}
+
+/// `angle_limit` is in radians and only applies with the angle limit method.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BevelModifier {
+    pub affect: BevelAffect,
+    pub width: f32,
+    pub segments: u32,
+    pub limit_method: BevelLimitMethod,
+    pub angle_limit: f32,
+}
+
+impl Default for BevelModifier {
+    fn default() -> Self {
+        BevelModifier {
+            affect: BevelAffect::Edges,
+            width: 0.1,
+            segments: 1,
+            limit_method: BevelLimitMethod::Angle,
+            angle_limit: 30f32.to_radians(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BevelAffect {
+    #[serde(rename = "VERTICES")]
+    Vertices,
+    #[serde(rename = "EDGES")]
+    Edges,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BevelLimitMethod {
+    #[serde(rename = "NONE")]
+    None,
+    #[serde(rename = "ANGLE")]
+    Angle,
+    #[serde(rename = "WEIGHT")]
+    Weight,
+    #[serde(rename = "VGROUP")]
+    VertexGroup,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SubdivisionModifier {
+    pub levels: u32,
+    pub render_levels: u32,
+    pub subdivision_type: SubdivisionType,
+}
+
+impl Default for SubdivisionModifier {
+    fn default() -> Self {
+        SubdivisionModifier { levels: 1, render_levels: 2, subdivision_type: SubdivisionType::CatmullClark }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubdivisionType {
+    #[serde(rename = "CATMULL_CLARK")]
+    CatmullClark,
+    #[serde(rename = "SIMPLE")]
+    Simple,
+}
+
+/// A custom property value. Numbers are all f64, as JSON has them; a vector is a short list of
+/// numbers such as a color or an offset.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum CustomValue {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Vector(Vec<f64>),
+    StringList(Vec<String>),
+}
+
+/// Blender's collection color tags.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorTag {
+    #[default]
+    #[serde(rename = "NONE")]
+    None,
+    #[serde(rename = "COLOR_01")]
+    Color01,
+    #[serde(rename = "COLOR_02")]
+    Color02,
+    #[serde(rename = "COLOR_03")]
+    Color03,
+    #[serde(rename = "COLOR_04")]
+    Color04,
+    #[serde(rename = "COLOR_05")]
+    Color05,
+    #[serde(rename = "COLOR_06")]
+    Color06,
+    #[serde(rename = "COLOR_07")]
+    Color07,
+    #[serde(rename = "COLOR_08")]
+    Color08,
+}
+
+/// A named group of objects, like a Blender collection. Hiding one or making it unselectable
+/// is synced state every client applies; the server does not act on the flags itself.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Collection {
+    pub collection_id: Uuid,
+    pub name: String,
+    pub color_tag: ColorTag,
+    pub visible: bool,
+    pub selectable: bool,
+    pub created_by: Uuid,
+    pub last_updated_by: Uuid,
+    pub last_updated_at: u64, // unix timestamp ms
+}
+
+/// A surface material from the session's table, a basic PBR subset of Blender's Principled
+/// BSDF. Objects use materials through their `material_slots`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Material {
+    pub material_id: Uuid,
+    pub name: String,
+    #[serde(flatten)]
+    pub settings: MaterialSettings,
+    pub created_by: Uuid,
+    pub last_updated_by: Uuid,
+    pub last_updated_at: u64, // unix timestamp ms
+}
+
+/// What a material looks like. Colors are linear RGB in 0..=1, like the factors; emission
+/// strength is unbounded. Fields left out of a message take Blender's defaults.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct MaterialSettings {
+    pub base_color: [f32; 3],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emission_color: [f32; 3],
+    pub emission_strength: f32,
+    pub alpha: f32,
+    #[serde(skip_serializing_if = "MaterialTextures::is_empty")]
+    pub textures: MaterialTextures,
+}
+
+impl Default for MaterialSettings {
+    fn default() -> Self {
+        MaterialSettings {
+            base_color: [0.8, 0.8, 0.8],
+            metallic: 0.0,
+            roughness: 0.5,
+            emission_color: [1.0, 1.0, 1.0],
+            emission_strength: 0.0,
+            alpha: 1.0,
+            textures: MaterialTextures::default(),
+        }
+    }
+}
+
+/// Image textures a material samples, each an asset reference like an AssetRef object's.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct MaterialTextures {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_color: Option<TextureRef>,
+    /// Metallic in blue and roughness in green, as glTF packs them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metallic_roughness: Option<TextureRef>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normal: Option<TextureRef>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emission: Option<TextureRef>,
+}
+
+impl MaterialTextures {
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// The textures that are set, with their slot names.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &TextureRef)> {
+        [
+            ("base_color", &self.base_color),
+            ("metallic_roughness", &self.metallic_roughness),
+            ("normal", &self.normal),
+            ("emission", &self.emission),
+        ]
+        .into_iter()
+        .filter_map(|(slot, texture)| Some((slot, texture.as_ref()?)))
+    }
+}
+
+/// An image in an asset library, by its file name there.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TextureRef {
+    pub asset_library: String,
+    pub asset_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct User {
     pub display_name: String,
     pub color: [u8; 3],
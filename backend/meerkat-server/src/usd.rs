@@ -0,0 +1,1783 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::f64::consts::PI;
+use std::io;
+
+use serde_json::Value as Json;
+use uuid::Uuid;
+
+use crate::gltf::{fitted_film, fitted_sensor};
+use crate::custom_properties::{self, USD_NAMESPACE};
+use crate::handlers::helpers::IMPORT_USER_ID;
+use crate::hierarchy;
+use crate::primitive_mesh::primitive_mesh;
+use crate::types::{
+    AreaLightProperties, AreaLightShape, CameraProperties, CustomValue, LensType, ObjectProperties, ObjectType, PointLightProperties,
+    SceneObject, SpotLightProperties, SunLightProperties, Transform,
+};
+use crate::xform::{axis_rotation_matrix, decompose, mat_mul, trs_matrix, IDENTITY, Y_UP_TO_Z_UP, Z_UP_TO_Y_UP_MATRIX};
+
+pub const USDA_HEADER: &str = "#usda 1.0";
+pub const USDA_EXTENSION: &str = "usda";
+pub const USDA_CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+
+/// Largest layer accepted for import (the admin upload limit).
+pub const MAX_USDA_LEN: usize = 64 * 1024 * 1024;
+
+/// The root prim every exported object is a child of, and the layer's `defaultPrim`.
+pub const ROOT_PRIM: &str = "World";
+/// `customData` entry holding what a prim needs to come back as the exact object it was.
+pub const CUSTOM_DATA_KEY: &str = "meerkat";
+
+/// UsdGeomCamera lens and filmback attributes are in tenths of a scene unit; at one metre per
+/// unit, that is 100 mm.
+const MM_PER_TENTH_METRE: f64 = 100.0;
+
+/// The ops every exported prim is placed with, mirroring Blender's location, XYZ Euler
+/// rotation and scale.
+const XFORM_OP_ORDER: [&str; 3] = ["xformOp:translate", "xformOp:rotateXYZ", "xformOp:scale"];
+
+const LIST_OPS: [&str; 5] = ["add", "append", "delete", "prepend", "reorder"];
+
+// The subset of the Sdf text format Meerkat reads and writes. Composition arcs (references,
+// payloads, inherits) are kept as written and never resolved; variant sets, relationships
+// and attribute connections are skipped when reading.
+
+/// A `.usda` layer: its metadata and root prims.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Layer {
+    pub metadata: Vec<Metadatum>,
+    pub prims: Vec<Prim>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Specifier {
+    Def,
+    Over,
+    Class,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prim {
+    pub specifier: Specifier,
+    /// Schema, e.g. `Xform` or `SphereLight`; `None` for a typeless prim.
+    pub type_name: Option<String>,
+    pub name: String,
+    pub metadata: Vec<Metadatum>,
+    pub attributes: Vec<Attribute>,
+    pub children: Vec<Prim>,
+}
+
+/// `key = value`, with its list operation (`prepend`, `append`, ...) for list-edited fields
+/// such as `references`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metadatum {
+    pub list_op: Option<String>,
+    pub key: String,
+    pub value: Value,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attribute {
+    pub custom: bool,
+    pub uniform: bool,
+    /// Value type, e.g. `double3` or `token[]`.
+    pub type_name: String,
+    pub name: String,
+    pub default: Option<Value>,
+    /// `(time, value)` in the order written.
+    pub time_samples: Vec<(f64, Value)>,
+    pub metadata: Vec<Metadatum>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// Numbers and bools; the declared type decides how one is written.
+    Number(f64),
+    /// A quoted string or token.
+    String(String),
+    /// `@path@`, with the prim path that may follow it in a reference.
+    Asset(String, Option<String>),
+    /// `</Some/Prim>`.
+    Path(String),
+    /// A bare word such as `None`.
+    Word(String),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+    Dictionary(Vec<DictionaryEntry>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DictionaryEntry {
+    pub type_name: String,
+    pub key: String,
+    pub value: Value,
+}
+
+impl Value {
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// The value as declared `type_name`: `float` and `half` numbers are held at the single
+    /// precision they are written with, so a layer reads back equal to the one written.
+    fn typed(mut self, type_name: &str) -> Self {
+        fn narrow(value: &mut Value) {
+            match value {
+                Value::Number(number) => *number = *number as f32 as f64,
+                Value::Tuple(values) | Value::List(values) => values.iter_mut().for_each(narrow),
+                _ => {}
+            }
+        }
+        if let NumberKind::Float = NumberKind::of(type_name) {
+            narrow(&mut self);
+        }
+        self
+    }
+
+    /// Every number in a (possibly nested) tuple or list, in order.
+    fn numbers(&self) -> Vec<f64> {
+        match self {
+            Value::Number(number) => vec![*number],
+            Value::Tuple(values) | Value::List(values) => values.iter().flat_map(Value::numbers).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn vec3(&self) -> Option<[f64; 3]> {
+        self.numbers().try_into().ok()
+    }
+
+    fn tuple(values: impl IntoIterator<Item = f64>) -> Value {
+        Value::Tuple(values.into_iter().map(Value::Number).collect())
+    }
+
+    fn bool(value: bool) -> Value {
+        Value::Number(if value { 1.0 } else { 0.0 })
+    }
+}
+
+impl Metadatum {
+    pub fn new(key: &str, value: Value) -> Self {
+        Metadatum { list_op: None, key: key.to_string(), value }
+    }
+}
+
+impl Attribute {
+    pub fn new(type_name: &str, name: &str, value: Value) -> Self {
+        Attribute {
+            custom: false,
+            uniform: false,
+            type_name: type_name.to_string(),
+            name: name.to_string(),
+            default: Some(value.typed(type_name)),
+            time_samples: Vec::new(),
+            metadata: Vec::new(),
+        }
+    }
+
+    fn uniform(self) -> Self {
+        Attribute { uniform: true, ..self }
+    }
+
+    /// The default value, or else the first time sample.
+    pub fn value(&self) -> Option<&Value> {
+        self.default.as_ref().or_else(|| self.time_samples.first().map(|(_, value)| value))
+    }
+}
+
+impl Prim {
+    pub fn new(type_name: &str, name: &str) -> Self {
+        Prim {
+            specifier: Specifier::Def,
+            type_name: Some(type_name.to_string()),
+            name: name.to_string(),
+            metadata: Vec::new(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&Value> {
+        self.attributes.iter().find(|attribute| attribute.name == name)?.value()
+    }
+
+    /// A metadata field, unless the layer only deletes from it.
+    pub fn metadatum(&self, key: &str) -> Option<&Value> {
+        self.metadata
+            .iter()
+            .find(|metadatum| metadatum.key == key && metadatum.list_op.as_deref() != Some("delete"))
+            .map(|metadatum| &metadatum.value)
+    }
+
+    fn number(&self, name: &str) -> Option<f64> {
+        self.attribute(name).and_then(Value::as_number)
+    }
+
+    fn set(&mut self, type_name: &str, name: &str, value: Value) {
+        self.attributes.push(Attribute::new(type_name, name, value));
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Writing.
+
+impl Layer {
+    pub fn to_usda(&self) -> String {
+        let mut out = format!("{USDA_HEADER}\n");
+        if !self.metadata.is_empty() {
+            out.push_str("(\n");
+            for metadatum in &self.metadata {
+                write_metadatum(&mut out, metadatum, 1);
+            }
+            out.push_str(")\n");
+        }
+        for prim in &self.prims {
+            out.push('\n');
+            write_prim(&mut out, prim, 0);
+        }
+        out
+    }
+}
+
+fn indent(out: &mut String, depth: usize) {
+    for _ in 0..depth {
+        out.push_str("    ");
+    }
+}
+
+fn write_metadatum(out: &mut String, metadatum: &Metadatum, depth: usize) {
+    indent(out, depth);
+    if let Some(list_op) = &metadatum.list_op {
+        out.push_str(list_op);
+        out.push(' ');
+    }
+    out.push_str(&metadatum.key);
+    out.push_str(" = ");
+    write_value(out, &metadatum.value, NumberKind::Double, depth);
+    out.push('\n');
+}
+
+fn write_metadata_block(out: &mut String, metadata: &[Metadatum], depth: usize) {
+    out.push_str(" (\n");
+    for metadatum in metadata {
+        write_metadatum(out, metadatum, depth + 1);
+    }
+    indent(out, depth);
+    out.push(')');
+}
+
+fn write_prim(out: &mut String, prim: &Prim, depth: usize) {
+    indent(out, depth);
+    out.push_str(match prim.specifier {
+        Specifier::Def => "def",
+        Specifier::Over => "over",
+        Specifier::Class => "class",
+    });
+    if let Some(type_name) = &prim.type_name {
+        out.push(' ');
+        out.push_str(type_name);
+    }
+    out.push(' ');
+    write_string(out, &prim.name);
+    if !prim.metadata.is_empty() {
+        write_metadata_block(out, &prim.metadata, depth);
+    }
+    out.push('\n');
+    indent(out, depth);
+    out.push_str("{\n");
+    for attribute in &prim.attributes {
+        write_attribute(out, attribute, depth + 1);
+    }
+    for (index, child) in prim.children.iter().enumerate() {
+        if index > 0 || !prim.attributes.is_empty() {
+            out.push('\n');
+        }
+        write_prim(out, child, depth + 1);
+    }
+    indent(out, depth);
+    out.push_str("}\n");
+}
+
+fn write_attribute(out: &mut String, attribute: &Attribute, depth: usize) {
+    let kind = NumberKind::of(&attribute.type_name);
+    let declare = |out: &mut String| {
+        indent(out, depth);
+        if attribute.custom {
+            out.push_str("custom ");
+        }
+        if attribute.uniform {
+            out.push_str("uniform ");
+        }
+        out.push_str(&attribute.type_name);
+        out.push(' ');
+        out.push_str(&attribute.name);
+    };
+    if attribute.default.is_some() || attribute.time_samples.is_empty() {
+        declare(out);
+        if let Some(value) = &attribute.default {
+            out.push_str(" = ");
+            write_value(out, value, kind, depth);
+        }
+        if !attribute.metadata.is_empty() {
+            write_metadata_block(out, &attribute.metadata, depth);
+        }
+        out.push('\n');
+    }
+    if !attribute.time_samples.is_empty() {
+        declare(out);
+        out.push_str(".timeSamples = {\n");
+        for (time, value) in &attribute.time_samples {
+            indent(out, depth + 1);
+            out.push_str(&format_number(*time, NumberKind::Double));
+            out.push_str(": ");
+            write_value(out, value, kind, depth + 1);
+            out.push_str(",\n");
+        }
+        indent(out, depth);
+        out.push_str("}\n");
+    }
+}
+
+/// How a number is written, by the scalar type it is declared as.
+#[derive(Clone, Copy)]
+enum NumberKind {
+    Int,
+    Float,
+    Double,
+}
+
+impl NumberKind {
+    fn of(type_name: &str) -> Self {
+        let scalar = type_name.trim_end_matches("[]").trim_end_matches(|c: char| c.is_ascii_digit());
+        match scalar {
+            "bool" | "int" | "uint" | "int64" | "uint64" | "uchar" => NumberKind::Int,
+            "float" | "half" => NumberKind::Float,
+            _ if scalar.ends_with('f') || scalar.ends_with('h') => NumberKind::Float,
+            _ => NumberKind::Double,
+        }
+    }
+}
+
+fn format_number(value: f64, kind: NumberKind) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    match kind {
+        NumberKind::Int => format!("{}", value as i64),
+        NumberKind::Float => format!("{}", value as f32),
+        NumberKind::Double => format!("{value}"),
+    }
+}
+
+fn write_string(out: &mut String, string: &str) {
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_value(out: &mut String, value: &Value, kind: NumberKind, depth: usize) {
+    let write_sequence = |out: &mut String, values: &[Value], open: char, close: char| {
+        out.push(open);
+        for (index, value) in values.iter().enumerate() {
+            if index > 0 {
+                out.push_str(", ");
+            }
+            write_value(out, value, kind, depth);
+        }
+        out.push(close);
+    };
+    match value {
+        Value::Number(number) => out.push_str(&format_number(*number, kind)),
+        Value::String(string) => write_string(out, string),
+        Value::Asset(path, prim_path) => {
+            let delimiter = if path.contains('@') { "@@@" } else { "@" };
+            out.push_str(delimiter);
+            out.push_str(path);
+            out.push_str(delimiter);
+            if let Some(prim_path) = prim_path {
+                out.push('<');
+                out.push_str(prim_path);
+                out.push('>');
+            }
+        }
+        Value::Path(path) => {
+            out.push('<');
+            out.push_str(path);
+            out.push('>');
+        }
+        Value::Word(word) => out.push_str(word),
+        Value::Tuple(values) => write_sequence(out, values, '(', ')'),
+        Value::List(values) => write_sequence(out, values, '[', ']'),
+        Value::Dictionary(entries) => {
+            out.push_str("{\n");
+            for entry in entries {
+                indent(out, depth + 1);
+                out.push_str(&entry.type_name);
+                out.push(' ');
+                if is_identifier(&entry.key) {
+                    out.push_str(&entry.key);
+                } else {
+                    write_string(out, &entry.key);
+                }
+                out.push_str(" = ");
+                write_value(out, &entry.value, NumberKind::of(&entry.type_name), depth + 1);
+                out.push('\n');
+            }
+            indent(out, depth);
+            out.push('}');
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Reading.
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Punct(char),
+    Word(String),
+    Number(f64),
+    String(String),
+    Asset(String),
+    Path(String),
+}
+
+fn tokenize(text: &str) -> io::Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut at, mut line) = (0, 1);
+    let error = |line: usize, message: &str| invalid(format!("line {line}: {message}"));
+    while let Some(&c) = chars.get(at) {
+        let next = chars.get(at + 1).copied().unwrap_or('\0');
+        let start_line = line;
+        match c {
+            '\n' => {
+                line += 1;
+                at += 1;
+            }
+            c if c.is_whitespace() || c == '\u{feff}' => at += 1,
+            '#' => {
+                while chars.get(at).is_some_and(|&c| c != '\n') {
+                    at += 1;
+                }
+            }
+            '"' | '\'' => {
+                let triple = chars.get(at..at + 3).is_some_and(|quote| quote.iter().all(|&q| q == c));
+                at += if triple { 3 } else { 1 };
+                let mut string = String::new();
+                loop {
+                    let Some(&d) = chars.get(at) else { return Err(error(start_line, "unterminated string")) };
+                    if triple && chars.get(at..at + 3).is_some_and(|quote| quote.iter().all(|&q| q == c)) {
+                        at += 3;
+                        break;
+                    }
+                    if !triple && d == c {
+                        at += 1;
+                        break;
+                    }
+                    if d == '\n' {
+                        if !triple {
+                            return Err(error(start_line, "unterminated string"));
+                        }
+                        line += 1;
+                    }
+                    if d == '\\' {
+                        at += 1;
+                        match chars.get(at) {
+                            Some('n') => string.push('\n'),
+                            Some('r') => string.push('\r'),
+                            Some('t') => string.push('\t'),
+                            Some(&escaped) => string.push(escaped),
+                            None => return Err(error(start_line, "unterminated string")),
+                        }
+                    } else {
+                        string.push(d);
+                    }
+                    at += 1;
+                }
+                tokens.push((Token::String(string), start_line));
+            }
+            '@' => {
+                let delimiter: &[char] = if chars.get(at..at + 3) == Some(&['@', '@', '@']) { &['@', '@', '@'] } else { &['@'] };
+                at += delimiter.len();
+                let start = at;
+                while chars.get(at..at + delimiter.len()).is_some_and(|end| end != delimiter) {
+                    at += 1;
+                }
+                if at + delimiter.len() > chars.len() {
+                    return Err(error(start_line, "unterminated asset path"));
+                }
+                tokens.push((Token::Asset(chars[start..at].iter().collect()), start_line));
+                at += delimiter.len();
+            }
+            '<' => {
+                let start = at + 1;
+                while chars.get(at).is_some_and(|&c| c != '>' && c != '\n') {
+                    at += 1;
+                }
+                if chars.get(at) != Some(&'>') {
+                    return Err(error(start_line, "unterminated path"));
+                }
+                tokens.push((Token::Path(chars[start..at].iter().collect()), start_line));
+                at += 1;
+            }
+            '(' | ')' | '[' | ']' | '{' | '}' | '=' | ',' | ':' | ';' => {
+                tokens.push((Token::Punct(c), line));
+                at += 1;
+            }
+            c if c.is_ascii_digit() || (matches!(c, '-' | '+' | '.') && (next.is_ascii_digit() || next == '.')) => {
+                let start = at;
+                at += 1;
+                while let Some(&d) = chars.get(at) {
+                    let exponent_sign = matches!(d, '-' | '+') && matches!(chars[at - 1], 'e' | 'E');
+                    if !(d.is_ascii_digit() || matches!(d, '.' | 'e' | 'E') || exponent_sign) {
+                        break;
+                    }
+                    at += 1;
+                }
+                let text: String = chars[start..at].iter().collect();
+                let number = text.parse().map_err(|_| error(line, &format!("invalid number '{text}'")))?;
+                tokens.push((Token::Number(number), line));
+            }
+            c if c.is_alphabetic() || c == '_' || (c == '-' && next.is_alphabetic()) => {
+                let start = at;
+                at += 1;
+                while chars.get(at).is_some_and(|&c| c.is_alphanumeric() || matches!(c, '_' | ':' | '.')) {
+                    at += 1;
+                }
+                tokens.push((Token::Word(chars[start..at].iter().collect()), line));
+            }
+            other => return Err(error(line, &format!("unexpected character '{other}'"))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at).map(|(token, _)| token)
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> io::Error {
+        match self.tokens.get(self.at.min(self.tokens.len().saturating_sub(1))) {
+            Some((_, line)) => invalid(format!("line {line}: {message}")),
+            None => invalid(message.to_string()),
+        }
+    }
+
+    fn next(&mut self) -> io::Result<Token> {
+        let token = self.peek().cloned().ok_or_else(|| self.error("unexpected end of layer"))?;
+        self.at += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(&Token::Punct(c));
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char) -> io::Result<()> {
+        if self.eat(c) { Ok(()) } else { Err(self.error(format!("expected '{c}'"))) }
+    }
+
+    fn word(&mut self) -> io::Result<String> {
+        match self.next()? {
+            Token::Word(word) => Ok(word),
+            other => {
+                self.at -= 1;
+                Err(self.error(format!("expected a name, found {other:?}")))
+            }
+        }
+    }
+
+    /// Fields up to the closing `)`, the opening one already consumed.
+    fn metadata(&mut self) -> io::Result<Vec<Metadatum>> {
+        let mut metadata = Vec::new();
+        loop {
+            match self.next()? {
+                Token::Punct(')') => return Ok(metadata),
+                Token::Punct(';') => {}
+                Token::String(doc) => metadata.push(Metadatum::new("doc", Value::String(doc))),
+                Token::Word(word) => {
+                    let (list_op, key) =
+                        if LIST_OPS.contains(&word.as_str()) { (Some(word), self.word()?) } else { (None, word) };
+                    self.expect('=')?;
+                    metadata.push(Metadatum { list_op, key, value: self.value()? });
+                }
+                other => {
+                    self.at -= 1;
+                    return Err(self.error(format!("unexpected {other:?} in metadata")));
+                }
+            }
+        }
+    }
+
+    fn prim(&mut self) -> io::Result<Prim> {
+        let specifier = match self.word()?.as_str() {
+            "def" => Specifier::Def,
+            "over" => Specifier::Over,
+            "class" => Specifier::Class,
+            other => {
+                self.at -= 1;
+                return Err(self.error(format!("expected def, over or class, found '{other}'")));
+            }
+        };
+        let type_name = match self.peek() {
+            Some(Token::Word(_)) => Some(self.word()?),
+            _ => None,
+        };
+        let name = match self.next()? {
+            Token::String(name) => name,
+            _ => return Err(self.error("expected a quoted prim name")),
+        };
+        let metadata = if self.eat('(') { self.metadata()? } else { Vec::new() };
+        self.expect('{')?;
+        let mut prim = Prim { specifier, type_name, name, metadata, attributes: Vec::new(), children: Vec::new() };
+        loop {
+            match self.peek() {
+                Some(Token::Punct('}')) => {
+                    self.at += 1;
+                    return Ok(prim);
+                }
+                Some(Token::Punct(';')) => self.at += 1,
+                Some(Token::Word(word)) => match word.as_str() {
+                    "def" | "over" | "class" => prim.children.push(self.prim()?),
+                    "variantSet" => {
+                        self.at += 2;
+                        self.expect('=')?;
+                        self.skip_block()?;
+                    }
+                    "reorder" => {
+                        self.at += 2;
+                        self.expect('=')?;
+                        self.value()?;
+                    }
+                    _ => {
+                        if let Some(attribute) = self.property()? {
+                            match prim.attributes.iter_mut().find(|existing| existing.name == attribute.name) {
+                                // A default and time samples are declared separately.
+                                Some(existing) => {
+                                    existing.default = existing.default.take().or(attribute.default);
+                                    existing.time_samples.extend(attribute.time_samples);
+                                    existing.metadata.extend(attribute.metadata);
+                                }
+                                None => prim.attributes.push(attribute),
+                            }
+                        }
+                    }
+                },
+                Some(other) => return Err(self.error(format!("unexpected {other:?} in prim '{}'", prim.name))),
+                None => return Err(self.error(format!("prim '{}' is not closed", prim.name))),
+            }
+        }
+    }
+
+    /// An attribute, or `None` for relationships, connections and other properties Meerkat
+    /// does not read.
+    fn property(&mut self) -> io::Result<Option<Attribute>> {
+        let (mut custom, mut uniform) = (false, false);
+        let mut type_name = self.word()?;
+        loop {
+            match type_name.as_str() {
+                "custom" => custom = true,
+                "uniform" => uniform = true,
+                "varying" | "config" => {}
+                _ => break,
+            }
+            type_name = self.word()?;
+        }
+        if type_name == "rel" {
+            self.word()?;
+            if self.eat('=') {
+                self.value()?;
+            }
+            if self.eat('(') {
+                self.metadata()?;
+            }
+            return Ok(None);
+        }
+        if self.eat('[') {
+            self.expect(']')?;
+            type_name.push_str("[]");
+        }
+        let name = self.word()?;
+        let mut attribute = Attribute { custom, uniform, type_name, name, default: None, time_samples: Vec::new(), metadata: Vec::new() };
+        if let Some(name) = attribute.name.strip_suffix(".timeSamples") {
+            attribute.name = name.to_string();
+            self.expect('=')?;
+            self.expect('{')?;
+            while !self.eat('}') {
+                let time = match self.next()? {
+                    Token::Number(time) => time,
+                    _ => return Err(self.error("expected a sample time")),
+                };
+                self.expect(':')?;
+                let value = self.typed_value(&attribute.type_name)?;
+                attribute.time_samples.push((time, value));
+                self.eat(',');
+            }
+            return Ok(Some(attribute));
+        }
+        if attribute.name.contains('.') {
+            // `.connect` and other property fields.
+            if self.eat('=') {
+                self.value()?;
+            }
+            return Ok(None);
+        }
+        if self.eat('=') {
+            attribute.default = Some(self.typed_value(&attribute.type_name)?);
+        }
+        if self.eat('(') {
+            attribute.metadata = self.metadata()?;
+        }
+        Ok(Some(attribute))
+    }
+
+    fn typed_value(&mut self, type_name: &str) -> io::Result<Value> {
+        Ok(self.value()?.typed(type_name))
+    }
+
+    fn value(&mut self) -> io::Result<Value> {
+        Ok(match self.next()? {
+            Token::Number(number) => Value::Number(number),
+            Token::String(string) => Value::String(string),
+            Token::Asset(path) => {
+                let prim_path = match self.peek() {
+                    Some(Token::Path(prim_path)) => {
+                        let prim_path = prim_path.clone();
+                        self.at += 1;
+                        Some(prim_path)
+                    }
+                    _ => None,
+                };
+                // A reference's layer offset, `(offset = 10; scale = 2)`.
+                if self.eat('(') {
+                    self.metadata()?;
+                }
+                Value::Asset(path, prim_path)
+            }
+            Token::Path(path) => Value::Path(path),
+            Token::Word(word) => match word.as_str() {
+                "true" => Value::Number(1.0),
+                "false" => Value::Number(0.0),
+                "inf" => Value::Number(f64::INFINITY),
+                "-inf" => Value::Number(f64::NEG_INFINITY),
+                "nan" => Value::Number(f64::NAN),
+                _ => Value::Word(word),
+            },
+            Token::Punct('(') => Value::Tuple(self.sequence(')')?),
+            Token::Punct('[') => Value::List(self.sequence(']')?),
+            Token::Punct('{') => {
+                let mut entries = Vec::new();
+                while !self.eat('}') {
+                    if self.eat(';') {
+                        continue;
+                    }
+                    let mut type_name = self.word()?;
+                    if self.eat('[') {
+                        self.expect(']')?;
+                        type_name.push_str("[]");
+                    }
+                    let key = match self.next()? {
+                        Token::Word(key) | Token::String(key) => key,
+                        _ => return Err(self.error("expected a dictionary key")),
+                    };
+                    self.expect('=')?;
+                    let value = self.typed_value(&type_name)?;
+                    entries.push(DictionaryEntry { type_name, key, value });
+                }
+                Value::Dictionary(entries)
+            }
+            other => {
+                self.at -= 1;
+                return Err(self.error(format!("expected a value, found {other:?}")));
+            }
+        })
+    }
+
+    fn sequence(&mut self, close: char) -> io::Result<Vec<Value>> {
+        let mut values = Vec::new();
+        while !self.eat(close) {
+            values.push(self.value()?);
+            if !self.eat(',') {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(values)
+    }
+
+    fn skip_block(&mut self) -> io::Result<()> {
+        self.expect('{')?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Token::Punct('{') => depth += 1,
+                Token::Punct('}') => depth -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Layer {
+    /// Parse a `.usda` layer. Binary `.usdc` and `.usdz` packages are not supported.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        if !text.trim_start_matches('\u{feff}').starts_with("#usda") {
+            return Err(invalid("not a .usda layer: missing the #usda header".to_string()));
+        }
+        let mut parser = Parser { tokens: tokenize(text)?, at: 0 };
+        let mut layer = Layer::default();
+        if parser.eat('(') {
+            layer.metadata = parser.metadata()?;
+        }
+        while parser.peek().is_some() {
+            layer.prims.push(parser.prim()?);
+        }
+        Ok(layer)
+    }
+
+    pub fn metadatum(&self, key: &str) -> Option<&Value> {
+        self.metadata.iter().find(|metadatum| metadatum.key == key).map(|metadatum| &metadatum.value)
+    }
+}
+
+// Export.
+
+/// Convert a session's objects into a USD layout layer for the session named `session_name`.
+///
+/// The layer is Z-up at one metre per unit, like Blender, so transforms carry over unchanged:
+/// every object is a prim under its parent's, or under `/World` if it has none, placed with
+/// translate, rotateXYZ and scale ops.
+/// Primitives become UsdGeom gprims (or meshes and curves where UsdGeom has no schema),
+/// cameras and lights their UsdGeomCamera and UsdLux equivalents, and asset references
+/// Xforms with a payload on `<asset_library>/<asset_id>.usd`. Each prim's `customData` keeps
+/// the object's id, name, type, asset reference and full properties, and custom properties are
+/// `userProperties:` attributes, so nothing Meerkat knows is lost.
+pub fn export_objects(session_name: &str, objects: &HashMap<Uuid, SceneObject>) -> Layer {
+    let mut sorted: Vec<&SceneObject> = objects.values().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name).then(a.object_id.cmp(&b.object_id)));
+
+    let mut root = Prim::new("Xform", ROOT_PRIM);
+    root.metadata.push(Metadatum::new("kind", Value::String("assembly".to_string())));
+    let index: HashMap<Uuid, usize> = sorted.iter().enumerate().map(|(i, object)| (object.object_id, i)).collect();
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); sorted.len()];
+    let mut roots = Vec::new();
+    for (i, object) in sorted.iter().enumerate() {
+        match hierarchy::acyclic_parent(objects, object).and_then(|parent_id| index.get(&parent_id)) {
+            Some(&parent) => children[parent].push(i),
+            None => roots.push(i),
+        }
+    }
+    let mut taken = HashSet::new();
+    for i in roots {
+        root.children.push(object_prim(&sorted, &children, i, &mut taken));
+    }
+
+    Layer {
+        metadata: vec![
+            Metadatum::new("defaultPrim", Value::String(ROOT_PRIM.to_string())),
+            Metadatum::new("doc", Value::String(format!("Meerkat session {session_name}"))),
+            Metadatum::new("metersPerUnit", Value::Number(1.0)),
+            Metadatum::new("upAxis", Value::String("Z".to_string())),
+        ],
+        prims: vec![root],
+    }
+}
+
+/// The prim for `sorted[i]`, with the prims of its children below it.
+fn object_prim(sorted: &[&SceneObject], children: &[Vec<usize>], i: usize, taken: &mut HashSet<String>) -> Prim {
+    let object = sorted[i];
+    let mut prim = match &object.object_type {
+        ObjectType::Camera => camera_prim(&match &object.properties {
+            Some(ObjectProperties::Camera(camera)) => camera.clone(),
+            _ => CameraProperties::default(),
+        }),
+        ObjectType::PointLight | ObjectType::SpotLight | ObjectType::AreaLight | ObjectType::SunLight => {
+            light_prim(&light_properties(object))
+        }
+        ObjectType::AssetRef => asset_prim(object),
+        object_type => geometry_prim(object_type),
+    };
+    prim.name = prim_name(&object.name, taken);
+    prim.metadata.push(Metadatum::new("customData", custom_data(object)));
+    for (key, value) in &object.custom_properties {
+        let (type_name, value) = match value {
+            CustomValue::String(string) => ("string", Value::String(string.clone())),
+            CustomValue::Number(number) => ("double", Value::Number(*number)),
+            CustomValue::Bool(flag) => ("bool", Value::bool(*flag)),
+            CustomValue::Vector(values) => ("double[]", Value::List(values.iter().copied().map(Value::Number).collect())),
+            CustomValue::StringList(strings) => ("string[]", Value::List(strings.iter().cloned().map(Value::String).collect())),
+        };
+        let name = format!("{USD_NAMESPACE}{key}");
+        prim.attributes.push(Attribute { custom: true, ..Attribute::new(type_name, &name, value) });
+    }
+    let Transform { position, rotation, scale } = &object.transform;
+    prim.set("double3", XFORM_OP_ORDER[0], Value::tuple(*position));
+    prim.set("double3", XFORM_OP_ORDER[1], Value::tuple(rotation.map(f64::to_degrees)));
+    prim.set("double3", XFORM_OP_ORDER[2], Value::tuple(*scale));
+    let order = XFORM_OP_ORDER.iter().map(|op| Value::String(op.to_string())).collect();
+    prim.attributes.push(Attribute::new("token[]", "xformOpOrder", Value::List(order)).uniform());
+    let mut taken = HashSet::new();
+    for &child in &children[i] {
+        prim.children.push(object_prim(sorted, children, child, &mut taken));
+    }
+    prim
+}
+
+/// A valid prim name for `name`, unique among its siblings.
+fn prim_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let mut base: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    if !base.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        base.insert(0, '_');
+    }
+    let mut candidate = base.clone();
+    for suffix in 1.. {
+        if taken.insert(candidate.clone()) {
+            break;
+        }
+        candidate = format!("{base}_{suffix}");
+    }
+    candidate
+}
+
+fn custom_data(object: &SceneObject) -> Value {
+    let string = |key: &str, value: &str| DictionaryEntry {
+        type_name: "string".to_string(),
+        key: key.to_string(),
+        value: Value::String(value.to_string()),
+    };
+    let mut entries = Vec::new();
+    if let Some(asset_id) = &object.asset_id {
+        entries.push(string("assetId", asset_id));
+    }
+    if let Some(asset_library) = &object.asset_library {
+        entries.push(string("assetLibrary", asset_library));
+    }
+    entries.push(string("name", &object.name));
+    entries.push(string("objectId", &object.object_id.to_string()));
+    entries.push(string("objectType", object.object_type.wire_name()));
+    // Through JSON text so f32 fields are written at their own precision.
+    let properties = serde_json::to_string(&object.properties).and_then(|json| serde_json::from_str::<Json>(&json));
+    if let Ok(Json::Object(properties)) = properties {
+        entries.push(DictionaryEntry {
+            type_name: "dictionary".to_string(),
+            key: "properties".to_string(),
+            value: json_to_dictionary(&properties),
+        });
+    }
+    if !object.extra_properties.is_empty() {
+        entries.push(DictionaryEntry {
+            type_name: "dictionary".to_string(),
+            key: "extraProperties".to_string(),
+            value: json_to_dictionary(&object.extra_properties),
+        });
+    }
+    Value::Dictionary(vec![DictionaryEntry {
+        type_name: "dictionary".to_string(),
+        key: CUSTOM_DATA_KEY.to_string(),
+        value: Value::Dictionary(entries),
+    }])
+}
+
+fn json_to_dictionary(map: &serde_json::Map<String, Json>) -> Value {
+    let entries = map
+        .iter()
+        .filter_map(|(key, value)| {
+            let (type_name, value) = match value {
+                Json::Bool(flag) => ("bool", Value::bool(*flag)),
+                Json::Number(number) if number.is_f64() => ("double", Value::Number(number.as_f64()?)),
+                Json::Number(number) => ("int64", Value::Number(number.as_f64()?)),
+                Json::String(string) => ("string", Value::String(string.clone())),
+                Json::Array(items) => ("double[]", Value::List(items.iter().filter_map(Json::as_f64).map(Value::Number).collect())),
+                Json::Object(map) => ("dictionary", json_to_dictionary(map)),
+                Json::Null => return None,
+            };
+            Some(DictionaryEntry { type_name: type_name.to_string(), key: key.clone(), value })
+        })
+        .collect();
+    Value::Dictionary(entries)
+}
+
+fn dictionary_to_json(entries: &[DictionaryEntry]) -> Json {
+    let map = entries
+        .iter()
+        .filter_map(|entry| {
+            let value = match (&entry.value, NumberKind::of(&entry.type_name)) {
+                (Value::Number(number), _) if entry.type_name == "bool" => Json::Bool(*number != 0.0),
+                (Value::Number(number), NumberKind::Int) => Json::from(*number as i64),
+                (Value::Number(number), _) => serde_json::Number::from_f64(*number).map(Json::Number)?,
+                (Value::String(string), _) => Json::String(string.clone()),
+                (Value::Tuple(values) | Value::List(values), _) => {
+                    Json::Array(values.iter().filter_map(Value::as_number).filter_map(serde_json::Number::from_f64).map(Json::Number).collect())
+                }
+                (Value::Dictionary(entries), _) => dictionary_to_json(entries),
+                _ => return None,
+            };
+            Some((entry.key.clone(), value))
+        })
+        .collect();
+    Json::Object(map)
+}
+
+/// The properties a light is exported with: its own, or Blender's defaults for its type.
+fn light_properties(object: &SceneObject) -> ObjectProperties {
+    match (&object.object_type, &object.properties) {
+        (_, Some(properties)) if !matches!(properties, ObjectProperties::Camera(_)) => properties.clone(),
+        (ObjectType::SpotLight, _) => ObjectProperties::SpotLight(SpotLightProperties::default()),
+        (ObjectType::AreaLight, _) => ObjectProperties::AreaLight(AreaLightProperties::default()),
+        (ObjectType::SunLight, _) => ObjectProperties::SunLight(SunLightProperties::default()),
+        _ => ObjectProperties::PointLight(PointLightProperties::default()),
+    }
+}
+
+fn geometry_prim(object_type: &ObjectType) -> Prim {
+    let axis = || Attribute::new("token", "axis", Value::String("Z".to_string())).uniform();
+    let mesh = primitive_mesh(object_type).unwrap_or_default();
+    let points = || Value::List(mesh.positions.iter().map(|p| Value::tuple(p.map(f64::from))).collect());
+    let mut prim;
+    match object_type {
+        // UsdGeom's own gprims, sized like Blender's.
+        ObjectType::Cube => {
+            prim = Prim::new("Cube", "");
+            prim.set("double", "size", Value::Number(2.0));
+        }
+        ObjectType::Sphere => {
+            prim = Prim::new("Sphere", "");
+            prim.set("double", "radius", Value::Number(1.0));
+        }
+        ObjectType::Cylinder | ObjectType::Cone => {
+            prim = Prim::new(if *object_type == ObjectType::Cone { "Cone" } else { "Cylinder" }, "");
+            prim.attributes.push(axis());
+            prim.set("double", "height", Value::Number(2.0));
+            prim.set("double", "radius", Value::Number(1.0));
+        }
+        ObjectType::Plane => {
+            prim = Prim::new("Plane", "");
+            prim.attributes.push(axis());
+            prim.set("double", "length", Value::Number(2.0));
+            prim.set("double", "width", Value::Number(2.0));
+        }
+        ObjectType::Circle => {
+            prim = Prim::new("BasisCurves", "");
+            prim.set("int[]", "curveVertexCounts", Value::List(vec![Value::Number(mesh.positions.len() as f64)]));
+            prim.set("point3f[]", "points", points());
+            prim.attributes.push(Attribute::new("token", "type", Value::String("linear".to_string())).uniform());
+            prim.attributes.push(Attribute::new("token", "wrap", Value::String("periodic".to_string())).uniform());
+        }
+        _ => {
+            prim = Prim::new("Mesh", "");
+            let triangles = mesh.indices.len() / 3;
+            prim.set("int[]", "faceVertexCounts", Value::List(vec![Value::Number(3.0); triangles]));
+            prim.set("int[]", "faceVertexIndices", Value::List(mesh.indices.iter().map(|&i| Value::Number(i as f64)).collect()));
+            let mut normals = Attribute::new(
+                "normal3f[]",
+                "normals",
+                Value::List(mesh.normals.iter().map(|n| Value::tuple(n.map(f64::from))).collect()),
+            );
+            normals.metadata.push(Metadatum::new("interpolation", Value::String("vertex".to_string())));
+            prim.attributes.push(normals);
+            prim.set("point3f[]", "points", points());
+            prim.attributes.push(Attribute::new("token", "subdivisionScheme", Value::String("none".to_string())).uniform());
+        }
+    }
+    if let Some((min, max)) = mesh.bounds() {
+        prim.set("float3[]", "extent", Value::List(vec![Value::tuple(min.map(f64::from)), Value::tuple(max.map(f64::from))]));
+    }
+    prim
+}
+
+fn camera_prim(camera: &CameraProperties) -> Prim {
+    let (projection, (horizontal, vertical)) = match camera.lens_type {
+        LensType::Perspective => {
+            let (horizontal, vertical) = fitted_film(camera, fitted_sensor(camera));
+            ("perspective", (horizontal / MM_PER_TENTH_METRE, vertical / MM_PER_TENTH_METRE))
+        }
+        LensType::Orthographic => {
+            let (horizontal, vertical) = fitted_film(camera, camera.orthographic_scale);
+            ("orthographic", (horizontal * 10.0, vertical * 10.0))
+        }
+    };
+    let mut prim = Prim::new("Camera", "");
+    prim.set("float2", "clippingRange", Value::tuple([camera.clip_start, camera.clip_end]));
+    prim.set("float", "focalLength", Value::Number(camera.focal_length / MM_PER_TENTH_METRE));
+    prim.set("float", "focusDistance", Value::Number(camera.focal_distance));
+    prim.set("float", "fStop", Value::Number(camera.aperture_fstop));
+    // Blender's shift is a fraction of the frame's larger side.
+    prim.set("float", "horizontalAperture", Value::Number(horizontal));
+    prim.set("float", "horizontalApertureOffset", Value::Number(camera.shift_x * horizontal));
+    prim.set("token", "projection", Value::String(projection.to_string()));
+    prim.set("float", "verticalAperture", Value::Number(vertical));
+    prim.set("float", "verticalApertureOffset", Value::Number(camera.shift_y * horizontal));
+    prim
+}
+
+/// The inputs every UsdLux light shares.
+struct LightInputs {
+    color: [f32; 3],
+    intensity: f32,
+    exposure: f32,
+    normalize: bool,
+    use_temperature: bool,
+    temperature: f32,
+    diffuse: f32,
+    specular: f32,
+    shadow: bool,
+}
+
+impl LightInputs {
+    fn write(&self, prim: &mut Prim) {
+        prim.set("color3f", "inputs:color", Value::tuple(self.color.map(f64::from)));
+        prim.set("float", "inputs:colorTemperature", Value::Number(self.temperature.into()));
+        prim.set("float", "inputs:diffuse", Value::Number(self.diffuse.into()));
+        prim.set("bool", "inputs:enableColorTemperature", Value::bool(self.use_temperature));
+        prim.set("float", "inputs:exposure", Value::Number(self.exposure.into()));
+        prim.set("float", "inputs:intensity", Value::Number(self.intensity.into()));
+        prim.set("bool", "inputs:normalize", Value::bool(self.normalize));
+        prim.set("bool", "inputs:shadow:enable", Value::bool(self.shadow));
+        prim.set("float", "inputs:specular", Value::Number(self.specular.into()));
+    }
+
+    /// A light's inputs, with UsdLux's fallbacks for any not authored.
+    fn read(prim: &Prim) -> Self {
+        let number = |name: &str, fallback: f32| prim.number(name).map_or(fallback, |value| value as f32);
+        let color = prim.attribute("inputs:color").and_then(Value::vec3).map_or([1.0; 3], |color| color.map(|c| c as f32));
+        LightInputs {
+            color,
+            intensity: number("inputs:intensity", 1.0),
+            exposure: number("inputs:exposure", 0.0),
+            normalize: number("inputs:normalize", 0.0) != 0.0,
+            use_temperature: number("inputs:enableColorTemperature", 0.0) != 0.0,
+            temperature: number("inputs:colorTemperature", 6500.0),
+            diffuse: number("inputs:diffuse", 1.0),
+            specular: number("inputs:specular", 1.0),
+            shadow: number("inputs:shadow:enable", 1.0) != 0.0,
+        }
+    }
+
+    /// Blender power in watts for an emitter of `area` square metres. Exports are normalized,
+    /// with intensity = power / π, so that a light's brightness does not depend on its size.
+    fn watts(&self, area: f64) -> f32 {
+        let area = if self.normalize || area <= 0.0 { 1.0 } else { area };
+        (self.intensity as f64 * PI * area) as f32
+    }
+}
+
+fn light_prim(properties: &ObjectProperties) -> Prim {
+    let intensity = |power: f32| (power as f64 / PI) as f32;
+    let mut schemas = vec!["ShadowAPI"];
+    let (mut prim, inputs) = match properties {
+        ObjectProperties::PointLight(point) => {
+            let mut prim = Prim::new("SphereLight", "");
+            prim.set("float", "inputs:radius", Value::Number(point.radius.into()));
+            prim.set("bool", "treatAsPoint", Value::bool(point.radius == 0.0));
+            let inputs = LightInputs {
+                color: point.color,
+                intensity: intensity(point.power),
+                exposure: point.exposure,
+                normalize: true,
+                use_temperature: point.use_temperature,
+                temperature: point.temperature,
+                diffuse: point.diffuse_factor,
+                specular: point.specular_factor,
+                shadow: point.cast_shadow,
+            };
+            (prim, inputs)
+        }
+        ObjectProperties::SpotLight(spot) => {
+            let mut prim = Prim::new("SphereLight", "");
+            prim.set("float", "inputs:radius", Value::Number(spot.radius.into()));
+            // Blender's spot size is the full cone angle; ShapingAPI's is the half angle.
+            prim.set("float", "inputs:shaping:cone:angle", Value::Number((spot.angle as f64 / 2.0).to_degrees()));
+            prim.set("float", "inputs:shaping:cone:softness", Value::Number(spot.blend.into()));
+            prim.set("bool", "treatAsPoint", Value::bool(spot.radius == 0.0));
+            schemas.push("ShapingAPI");
+            let inputs = LightInputs {
+                color: spot.color,
+                intensity: intensity(spot.power),
+                exposure: spot.exposure,
+                normalize: true,
+                use_temperature: spot.use_temperature,
+                temperature: spot.temperature,
+                diffuse: spot.diffuse_factor,
+                specular: spot.specular_factor,
+                shadow: spot.cast_shadow,
+            };
+            (prim, inputs)
+        }
+        ObjectProperties::AreaLight(area) => {
+            let prim = match area.shape {
+                AreaLightShape::RECTANGLE | AreaLightShape::SQUARE => {
+                    let (width, height) =
+                        if area.shape == AreaLightShape::SQUARE { (area.size, area.size) } else { (area.size_x, area.size_y) };
+                    let mut prim = Prim::new("RectLight", "");
+                    prim.set("float", "inputs:height", Value::Number(height.into()));
+                    prim.set("float", "inputs:width", Value::Number(width.into()));
+                    prim
+                }
+                // UsdLux has no ellipse; the disk covers it.
+                AreaLightShape::DISK | AreaLightShape::ELLIPSE => {
+                    let diameter = if area.shape == AreaLightShape::DISK { area.size } else { area.size_x.max(area.size_y) };
+                    let mut prim = Prim::new("DiskLight", "");
+                    prim.set("float", "inputs:radius", Value::Number((diameter / 2.0).into()));
+                    prim
+                }
+            };
+            let inputs = LightInputs {
+                color: area.color,
+                intensity: intensity(area.power),
+                exposure: area.exposure,
+                normalize: true,
+                use_temperature: area.use_temperature,
+                temperature: area.temperature,
+                diffuse: area.diffuse_factor,
+                specular: area.specular_factor,
+                shadow: area.cast_shadow,
+            };
+            (prim, inputs)
+        }
+        ObjectProperties::SunLight(sun) => {
+            let mut prim = Prim::new("DistantLight", "");
+            prim.set("float", "inputs:angle", Value::Number((sun.angle as f64).to_degrees()));
+            let inputs = LightInputs {
+                color: sun.color,
+                intensity: sun.strength,
+                exposure: sun.exposure,
+                normalize: true,
+                use_temperature: sun.use_temperature,
+                temperature: sun.temperature,
+                diffuse: sun.diffuse_factor,
+                specular: sun.specular_factor,
+                shadow: sun.cast_shadow,
+            };
+            (prim, inputs)
+        }
+        ObjectProperties::Camera(_) => unreachable!("light_properties never returns a camera"),
+    };
+    inputs.write(&mut prim);
+    prim.attributes.sort_by(|a, b| a.name.cmp(&b.name));
+    let schemas = schemas.into_iter().map(|schema| Value::String(schema.to_string())).collect();
+    prim.metadata.push(Metadatum { list_op: Some("prepend".to_string()), key: "apiSchemas".to_string(), value: Value::List(schemas) });
+    prim
+}
+
+/// Where an asset reference's layer is looked for: a search path, so pipelines resolve it
+/// against their asset library root.
+pub fn asset_path(asset_id: &str, asset_library: Option<&str>) -> String {
+    match asset_library {
+        Some(library) => format!("{library}/{asset_id}.usd"),
+        None => format!("{asset_id}.usd"),
+    }
+}
+
+fn asset_prim(object: &SceneObject) -> Prim {
+    let mut prim = Prim::new("Xform", "");
+    prim.metadata.push(Metadatum::new("kind", Value::String("component".to_string())));
+    if let Some(asset_id) = &object.asset_id {
+        prim.metadata.push(Metadatum {
+            list_op: Some("prepend".to_string()),
+            key: "payload".to_string(),
+            value: Value::Asset(asset_path(asset_id, object.asset_library.as_deref()), None),
+        });
+    }
+    prim
+}
+
+// Import.
+
+/// How a layer's units and up axis map onto Blender's metres and Z up.
+struct Stage {
+    y_up: bool,
+    meters_per_unit: f64,
+}
+
+impl Stage {
+    fn of(layer: &Layer) -> Self {
+        Stage {
+            y_up: layer.metadatum("upAxis").and_then(Value::as_str) == Some("Y"),
+            meters_per_unit: layer.metadatum("metersPerUnit").and_then(Value::as_number).filter(|m| *m > 0.0).unwrap_or(1.0),
+        }
+    }
+
+    fn is_blender(&self) -> bool {
+        !self.y_up && self.meters_per_unit == 1.0
+    }
+
+    /// Blender world matrix for a prim's world matrix in stage space.
+    fn to_blender(&self, world: &[f64; 16], frame: &Frame) -> [f64; 16] {
+        let no_rotation = [0.0, 0.0, 0.0, 1.0];
+        let units = trs_matrix([0.0; 3], no_rotation, [self.meters_per_unit; 3]);
+        let mut matrix = mat_mul(&units, world);
+        match frame {
+            Frame::Placement => {
+                matrix = mat_mul(&matrix, &trs_matrix([0.0; 3], no_rotation, [1.0 / self.meters_per_unit; 3]));
+            }
+            Frame::Shape(shape) => matrix = mat_mul(&matrix, shape),
+            Frame::Asset => {}
+        }
+        if self.y_up {
+            matrix = mat_mul(&Y_UP_TO_Z_UP, &matrix);
+            if matches!(frame, Frame::Asset) {
+                matrix = mat_mul(&matrix, &Z_UP_TO_Y_UP_MATRIX);
+            }
+        }
+        matrix
+    }
+}
+
+/// How a prim's own space relates to the Blender object it becomes.
+enum Frame {
+    /// Cameras and lights look down local -Z in both conventions and carry their sizes in
+    /// properties, so only their placement is converted.
+    Placement,
+    /// A gprim: the matrix taking Blender's primitive to the gprim's size and axis, in stage units.
+    Shape([f64; 16]),
+    /// Referenced assets and meshes are authored in the stage's units and up axis, which the
+    /// whole object is converted from.
+    Asset,
+}
+
+/// The objects a USD layer describes, in prim order.
+///
+/// `def` prims are walked from the root with their transforms composed, converted from the
+/// layer's up axis and units to Blender's; `over` and `class` prims are skipped. Each object is
+/// parented to the object of the nearest ancestor prim that became one, with its transform
+/// made relative to it; prims under one that references or payloads an asset override that
+/// asset and are not imported. Prims
+/// written by `export_objects` come back as the objects they were, ids included; callers must
+/// give any ids already taken in the target session fresh ones. Otherwise, prims with a
+/// reference or payload become asset references to that layer, UsdGeomCamera and UsdLux
+/// prims cameras and lights, the Cube, Sphere, Cylinder, Cone and Plane gprims those
+/// primitives, and meshes asset references named after the prim.
+pub fn import_objects(layer: &Layer, now: u64) -> Vec<SceneObject> {
+    let stage = Stage::of(layer);
+    let mut objects = Vec::new();
+    let mut parents = Vec::new();
+    for prim in &layer.prims {
+        import_prim(prim, &IDENTITY, None, &stage, now, &mut objects, &mut parents);
+    }
+    let mut seen = HashSet::new();
+    for object in &mut objects {
+        if !seen.insert(object.object_id) {
+            object.object_id = Uuid::new_v4();
+        }
+    }
+    for (i, parent) in parents.into_iter().enumerate() {
+        objects[i].parent_id = parent.map(|parent| objects[parent].object_id);
+    }
+    hierarchy::link_imported(&mut objects);
+    objects
+}
+
+/// Import `prim` and what is below it, recording in `parents` the index of each object's
+/// parent object.
+fn import_prim(
+    prim: &Prim,
+    parent: &[f64; 16],
+    parent_object: Option<usize>,
+    stage: &Stage,
+    now: u64,
+    objects: &mut Vec<SceneObject>,
+    parents: &mut Vec<Option<usize>>,
+) {
+    if prim.specifier != Specifier::Def {
+        return;
+    }
+    let (local, resets) = local_matrix(prim);
+    let parent = if resets { &IDENTITY } else { parent };
+    let world = mat_mul(parent, &local);
+    let mut parent_object = parent_object;
+    if let Some(object) = import_object(prim, &world, stage, *parent == IDENTITY, now) {
+        objects.push(object);
+        parents.push(parent_object);
+        // Whatever is under a referencing prim overrides the referenced asset.
+        if composed_asset(prim).is_some() && meerkat_data(prim).is_none() {
+            return;
+        }
+        parent_object = Some(objects.len() - 1);
+    }
+    for child in &prim.children {
+        import_prim(child, &world, parent_object, stage, now, objects, parents);
+    }
+}
+
+fn import_object(prim: &Prim, world: &[f64; 16], stage: &Stage, unparented: bool, now: u64) -> Option<SceneObject> {
+    let name = prim.metadatum("displayName").and_then(Value::as_str).unwrap_or(&prim.name).to_string();
+    let mut object = SceneObject::new(Uuid::new_v4(), name, ObjectType::AssetRef, IMPORT_USER_ID, now);
+    object.custom_properties = user_properties(prim);
+    let mut frame = Frame::Placement;
+    let type_name = prim.type_name.as_deref().unwrap_or("");
+    let meters = stage.meters_per_unit;
+    if let Some(imported) = meerkat_data(prim) {
+        object.object_id = imported.object_id;
+        object.name = imported.name;
+        object.object_type = imported.object_type;
+        object.asset_id = imported.asset_id;
+        object.asset_library = imported.asset_library;
+        object.properties = imported.properties;
+        object.extra_properties = imported.extra_properties;
+        // Exactly as written, Euler angles outside ±180° included.
+        if stage.is_blender()
+            && unparented
+            && let Some(transform) = export_transform(prim)
+        {
+            object.transform = transform;
+            return Some(object);
+        }
+    } else if let Some(path) = composed_asset(prim) {
+        let path = path.trim_start_matches("./");
+        let (library, file) = match path.rsplit_once('/') {
+            Some((library, file)) => (Some(library), file),
+            None => (None, path),
+        };
+        let stem = file.rsplit_once('.').map_or(file, |(stem, _)| stem);
+        object.asset_id = Some(stem.to_string());
+        object.asset_library = library.filter(|library| !library.is_empty()).map(str::to_string);
+        frame = Frame::Asset;
+    } else {
+        match type_name {
+            "Camera" => {
+                object.object_type = ObjectType::Camera;
+                object.properties = Some(ObjectProperties::Camera(import_camera(prim, meters)));
+            }
+            "SphereLight" | "RectLight" | "DiskLight" | "DistantLight" => {
+                let (object_type, properties) = import_light(prim, type_name, meters);
+                object.object_type = object_type;
+                object.properties = Some(properties);
+            }
+            "Cube" | "Sphere" | "Cylinder" | "Cone" | "Plane" => {
+                let number = |name: &str, fallback: f64| prim.number(name).unwrap_or(fallback);
+                let (object_type, scale) = match type_name {
+                    "Cube" => (ObjectType::Cube, [number("size", 2.0) / 2.0; 3]),
+                    "Sphere" => (ObjectType::Sphere, [number("radius", 1.0); 3]),
+                    "Plane" => (ObjectType::Plane, [number("width", 2.0) / 2.0, number("length", 2.0) / 2.0, 1.0]),
+                    _ => {
+                        let object_type = if type_name == "Cone" { ObjectType::Cone } else { ObjectType::Cylinder };
+                        let radius = number("radius", 1.0);
+                        (object_type, [radius, radius, number("height", 2.0) / 2.0])
+                    }
+                };
+                // Blender's primitives run along Z; the gprim's axis may be another.
+                let axis = match prim.attribute("axis").and_then(Value::as_str) {
+                    Some("X") => axis_rotation_matrix(1, PI / 2.0),
+                    Some("Y") => axis_rotation_matrix(0, -PI / 2.0),
+                    _ => IDENTITY,
+                };
+                object.object_type = object_type;
+                frame = Frame::Shape(mat_mul(&axis, &trs_matrix([0.0; 3], [0.0, 0.0, 0.0, 1.0], scale)));
+            }
+            "Mesh" => {
+                object.asset_id = Some(prim.name.clone());
+                frame = Frame::Asset;
+            }
+            _ => return None,
+        }
+    }
+    object.transform = decompose(&stage.to_blender(world, &frame));
+    Some(object)
+}
+
+/// The prim's `userProperties:` attributes that make valid custom properties, which is where
+/// Blender keeps custom properties too.
+fn user_properties(prim: &Prim) -> BTreeMap<String, CustomValue> {
+    let entries = prim.attributes.iter().filter_map(|attribute| {
+        let key = attribute.name.strip_prefix(USD_NAMESPACE)?;
+        let value = match attribute.value()? {
+            Value::Number(number) if attribute.type_name == "bool" => CustomValue::Bool(*number != 0.0),
+            Value::Number(number) => CustomValue::Number(*number),
+            Value::String(string) => CustomValue::String(string.clone()),
+            Value::Tuple(values) | Value::List(values) if values.iter().all(|value| value.as_str().is_some()) => {
+                CustomValue::StringList(values.iter().filter_map(Value::as_str).map(str::to_string).collect())
+            }
+            value @ (Value::Tuple(_) | Value::List(_)) => CustomValue::Vector(value.numbers()),
+            _ => return None,
+        };
+        Some((key.to_string(), value))
+    });
+    custom_properties::collect_valid(entries)
+}
+
+/// What `export_objects` wrote into a prim's `customData`, when it did.
+struct MeerkatData {
+    object_id: Uuid,
+    name: String,
+    object_type: ObjectType,
+    asset_id: Option<String>,
+    asset_library: Option<String>,
+    properties: Option<ObjectProperties>,
+    extra_properties: serde_json::Map<String, Json>,
+}
+
+fn dictionary_entry<'a>(entries: &'a [DictionaryEntry], key: &str) -> Option<&'a Value> {
+    entries.iter().find(|entry| entry.key == key).map(|entry| &entry.value)
+}
+
+fn meerkat_data(prim: &Prim) -> Option<MeerkatData> {
+    let Value::Dictionary(custom_data) = prim.metadatum("customData")? else { return None };
+    let Value::Dictionary(entries) = dictionary_entry(custom_data, CUSTOM_DATA_KEY)? else { return None };
+    let string = |key: &str| dictionary_entry(entries, key).and_then(Value::as_str).map(str::to_string);
+    let object_type = serde_json::from_value(Json::String(string("objectType")?)).ok()?;
+    let properties = match dictionary_entry(entries, "properties") {
+        Some(Value::Dictionary(properties)) => serde_json::from_value(dictionary_to_json(properties)).ok(),
+        _ => None,
+    };
+    let extra_properties = match dictionary_entry(entries, "extraProperties") {
+        Some(Value::Dictionary(extra)) => dictionary_to_json(extra).as_object().cloned().unwrap_or_default(),
+        _ => serde_json::Map::new(),
+    };
+    Some(MeerkatData {
+        object_id: string("objectId").and_then(|id| Uuid::parse_str(&id).ok()).unwrap_or_else(Uuid::new_v4),
+        name: string("name").unwrap_or_else(|| prim.name.clone()),
+        object_type,
+        asset_id: string("assetId"),
+        asset_library: string("assetLibrary"),
+        properties,
+        extra_properties,
+    })
+}
+
+/// The layer a prim references or payloads, first one listed.
+fn composed_asset(prim: &Prim) -> Option<&str> {
+    ["references", "payload"].into_iter().find_map(|key| {
+        let value = prim.metadatum(key)?;
+        let first = match value {
+            Value::List(values) => values.first()?,
+            value => value,
+        };
+        match first {
+            Value::Asset(path, _) if !path.is_empty() => Some(path.as_str()),
+            _ => None,
+        }
+    })
+}
+
+fn op_order(prim: &Prim) -> Vec<&str> {
+    match prim.attribute("xformOpOrder") {
+        Some(Value::List(ops)) => ops.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The transform of a prim placed the way `export_objects` places one, read straight from its
+/// ops rather than through a matrix.
+fn export_transform(prim: &Prim) -> Option<Transform> {
+    if op_order(prim) != XFORM_OP_ORDER {
+        return None;
+    }
+    let op = |index: usize| prim.attribute(XFORM_OP_ORDER[index]).and_then(Value::vec3);
+    Some(Transform { position: op(0)?, rotation: op(1)?.map(f64::to_radians), scale: op(2)? })
+}
+
+/// A prim's local matrix from its `xformOpOrder`, and whether it resets the parent transform.
+fn local_matrix(prim: &Prim) -> ([f64; 16], bool) {
+    let mut matrix = IDENTITY;
+    let mut resets = false;
+    for op in op_order(prim) {
+        if op == "!resetXformStack!" {
+            (matrix, resets) = (IDENTITY, true);
+            continue;
+        }
+        let (inverse, name) = match op.strip_prefix("!invert!") {
+            Some(name) => (true, name),
+            None => (false, op),
+        };
+        if let Some(op_matrix) = prim.attribute(name).and_then(|value| op_matrix(name, value, inverse)) {
+            matrix = mat_mul(&matrix, &op_matrix);
+        }
+    }
+    (matrix, resets)
+}
+
+/// The matrix of one xform op, e.g. `xformOp:rotateXYZ:pivot`. Angles are in degrees.
+fn op_matrix(name: &str, value: &Value, inverse: bool) -> Option<[f64; 16]> {
+    let kind = name.split(':').nth(1)?;
+    let no_rotation = [0.0, 0.0, 0.0, 1.0];
+    let sign = if inverse { -1.0 } else { 1.0 };
+    let matrix = match kind {
+        "translate" => trs_matrix(value.vec3()?.map(|t| t * sign), no_rotation, [1.0; 3]),
+        "scale" => trs_matrix([0.0; 3], no_rotation, value.vec3()?.map(|s| if inverse { 1.0 / s } else { s })),
+        "rotateX" | "rotateY" | "rotateZ" => {
+            let axis = (kind.as_bytes()[6] - b'X') as usize;
+            axis_rotation_matrix(axis, sign * value.as_number()?.to_radians())
+        }
+        "orient" => {
+            // Quaternions are written real part first.
+            let [w, x, y, z]: [f64; 4] = value.numbers().try_into().ok()?;
+            trs_matrix([0.0; 3], [sign * x, sign * y, sign * z, w], [1.0; 3])
+        }
+        "transform" if !inverse => value.numbers().try_into().ok()?,
+        _ if kind.len() == 9 && kind.starts_with("rotate") && kind[6..].bytes().all(|axis| matches!(axis, b'X'..=b'Z')) => {
+            // The first axis named is applied first.
+            let angles = value.vec3()?;
+            let mut rotations: Vec<[f64; 16]> = kind[6..]
+                .bytes()
+                .zip(angles)
+                .map(|(axis, angle)| axis_rotation_matrix((axis - b'X') as usize, sign * angle.to_radians()))
+                .collect();
+            if !inverse {
+                rotations.reverse();
+            }
+            rotations.iter().fold(IDENTITY, |matrix, rotation| mat_mul(&matrix, rotation))
+        }
+        _ => return None,
+    };
+    Some(matrix)
+}
+
+/// Inverse of `camera_prim`, falling back to UsdGeomCamera's defaults for what is not authored.
+fn import_camera(prim: &Prim, meters_per_unit: f64) -> CameraProperties {
+    let mut camera = CameraProperties::default();
+    let number = |name: &str| prim.number(name);
+    let horizontal = number("horizontalAperture").unwrap_or(20.955);
+    let vertical = number("verticalAperture").unwrap_or(15.2908);
+    if prim.attribute("projection").and_then(Value::as_str) == Some("orthographic") {
+        camera.lens_type = LensType::Orthographic;
+        camera.orthographic_scale = horizontal.max(vertical) / 10.0 * meters_per_unit;
+    } else {
+        let millimetres = MM_PER_TENTH_METRE * meters_per_unit;
+        camera.focal_length = number("focalLength").unwrap_or(50.0) * millimetres;
+        camera.sensor_width = horizontal * millimetres;
+        camera.sensor_height = vertical * millimetres;
+    }
+    if horizontal > 0.0 {
+        camera.shift_x = number("horizontalApertureOffset").unwrap_or(0.0) / horizontal;
+        camera.shift_y = number("verticalApertureOffset").unwrap_or(0.0) / horizontal;
+    }
+    if let Some([start, end]) = prim.attribute("clippingRange").and_then(|range| <[f64; 2]>::try_from(range.numbers()).ok()) {
+        camera.clip_start = start * meters_per_unit;
+        camera.clip_end = end * meters_per_unit;
+    }
+    if let Some(distance) = number("focusDistance").filter(|distance| *distance > 0.0) {
+        camera.focal_distance = distance * meters_per_unit;
+    }
+    if let Some(f_stop) = number("fStop").filter(|f_stop| *f_stop > 0.0) {
+        camera.aperture_fstop = f_stop;
+    }
+    camera
+}
+
+/// Inverse of `light_prim`, falling back to UsdLux's defaults for what is not authored.
+fn import_light(prim: &Prim, type_name: &str, meters_per_unit: f64) -> (ObjectType, ObjectProperties) {
+    let inputs = LightInputs::read(prim);
+    let length = |name: &str, fallback: f64| prim.number(name).unwrap_or(fallback) * meters_per_unit;
+    match type_name {
+        "SphereLight" => {
+            let treat_as_point = prim.number("treatAsPoint").is_some_and(|flag| flag != 0.0);
+            let radius = if treat_as_point { 0.0 } else { length("inputs:radius", 0.5) };
+            let power = inputs.watts(4.0 * PI * radius * radius);
+            if let Some(cone) = prim.number("inputs:shaping:cone:angle") {
+                let spot = SpotLightProperties {
+                    color: inputs.color,
+                    use_temperature: inputs.use_temperature,
+                    temperature: inputs.temperature,
+                    exposure: inputs.exposure,
+                    power,
+                    radius: radius as f32,
+                    angle: (2.0 * cone.to_radians()).clamp(0.0, PI) as f32,
+                    blend: prim.number("inputs:shaping:cone:softness").unwrap_or(0.0).clamp(0.0, 1.0) as f32,
+                    cast_shadow: inputs.shadow,
+                    diffuse_factor: inputs.diffuse,
+                    specular_factor: inputs.specular,
+                    ..SpotLightProperties::default()
+                };
+                return (ObjectType::SpotLight, ObjectProperties::SpotLight(spot));
+            }
+            let point = PointLightProperties {
+                color: inputs.color,
+                use_temperature: inputs.use_temperature,
+                temperature: inputs.temperature,
+                exposure: inputs.exposure,
+                power,
+                radius: radius as f32,
+                cast_shadow: inputs.shadow,
+                diffuse_factor: inputs.diffuse,
+                specular_factor: inputs.specular,
+                ..PointLightProperties::default()
+            };
+            (ObjectType::PointLight, ObjectProperties::PointLight(point))
+        }
+        "DistantLight" => {
+            let sun = SunLightProperties {
+                color: inputs.color,
+                use_temperature: inputs.use_temperature,
+                temperature: inputs.temperature,
+                exposure: inputs.exposure,
+                strength: inputs.intensity,
+                angle: prim.number("inputs:angle").unwrap_or(0.53).to_radians() as f32,
+                cast_shadow: inputs.shadow,
+                diffuse_factor: inputs.diffuse,
+                specular_factor: inputs.specular,
+                ..SunLightProperties::default()
+            };
+            (ObjectType::SunLight, ObjectProperties::SunLight(sun))
+        }
+        _ => {
+            let mut area = AreaLightProperties {
+                color: inputs.color,
+                use_temperature: inputs.use_temperature,
+                temperature: inputs.temperature,
+                exposure: inputs.exposure,
+                cast_shadow: inputs.shadow,
+                diffuse_factor: inputs.diffuse,
+                specular_factor: inputs.specular,
+                ..AreaLightProperties::default()
+            };
+            if type_name == "DiskLight" {
+                let radius = length("inputs:radius", 0.5);
+                area.shape = AreaLightShape::DISK;
+                area.size = (2.0 * radius) as f32;
+                area.power = inputs.watts(PI * radius * radius);
+            } else {
+                let (width, height) = (length("inputs:width", 1.0), length("inputs:height", 1.0));
+                if width == height {
+                    area.shape = AreaLightShape::SQUARE;
+                    area.size = width as f32;
+                } else {
+                    area.shape = AreaLightShape::RECTANGLE;
+                    (area.size_x, area.size_y) = (width as f32, height as f32);
+                }
+                area.power = inputs.watts(width * height);
+            }
+            (ObjectType::AreaLight, ObjectProperties::AreaLight(area))
+        }
+    }
+}
@@ -5,19 +5,29 @@ use axum::{
     },
     response::Response,
 };
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::select; 
+use tokio::time::{interval, MissedTickBehavior};
 use uuid::Uuid;
 
 
 use crate::{
     handlers::{
         self,
-        helpers::broadcast,
+        helpers::{
+            announce_storage_failure, broadcast, flush_sync_backlog, now_ms, reclaim_session, record_client_event,
+        },
     },
 
-    messages::{ClientEvent, ServerEvent, UserLeftPayload, parse_client_message},
-    types::AppState,
+    messages::{
+        ClientEvent, ErrorPayload, EventRejectedPayload, HistoryAction, ServerEvent, UpdateTransformPayload,
+        UserLeftPayload, parse_client_message,
+    },
+    rate_limit::{self, RateClass, RateDecision, HELD_TRANSFORM_RETRY_MS},
+    types::{AppState, SessionHandle},
 };
 
 const EVICTED_CLOSE_CODE: CloseCode = 4008;
@@ -39,6 +49,10 @@ pub async fn handle_connection(mut socket: WebSocket, state: AppState) {
     let connection_id = Uuid::new_v4();
     let (tx, mut rx) = mpsc::channel::<String>(64);
     state.connections.insert(connection_id, tx); 
+    // Latest over-limit UpdateTransform per object, retried every HELD_TRANSFORM_RETRY_MS.
+    let mut held_transforms: HashMap<Uuid, UpdateTransformPayload> = HashMap::new();
+    let mut held_retry = interval(Duration::from_millis(HELD_TRANSFORM_RETRY_MS));
+    held_retry.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
     tracing::info!(connection_id = %connection_id, "connection opened");
 
@@ -56,7 +70,35 @@ pub async fn handle_connection(mut socket: WebSocket, state: AppState) {
                                 } else {
                                     tracing::info!(connection_id = %connection_id, event_type = ?event, "parsed client event");
                                 }
-                                dispatch(&mut socket, &state, connection_id, event).await
+                                match rate_limit::check(&state, connection_id, &event, now_ms()) {
+                                    (_, RateDecision::Allow) => {
+                                        if let ClientEvent::UpdateTransform(p) = &event {
+                                            // Newer than anything held back for the object.
+                                            held_transforms.remove(&p.object_id);
+                                        }
+                                        record_client_event(&state, connection_id, &event);
+                                        dispatch(&mut socket, &state, connection_id, event).await;
+                                        if !flush_sync_backlog(&mut socket, &state, connection_id, &mut rx).await {
+                                            break;
+                                        }
+                                    }
+                                    (_, RateDecision::Coalesce) => {
+                                        if let ClientEvent::UpdateTransform(p) = event {
+                                            held_transforms.insert(p.object_id, p);
+                                        }
+                                    }
+                                    (class, RateDecision::Reject { retry_after_ms }) => {
+                                        tracing::debug!(connection_id = %connection_id, rate_class = ?class, retry_after_ms, "rate limited client event rejected");
+                                        reject_rate_limited(&mut socket, class, retry_after_ms, event).await;
+                                    }
+                                    (class, RateDecision::Drop) => {
+                                        tracing::trace!(connection_id = %connection_id, rate_class = ?class, "rate limited client event dropped");
+                                    }
+                                    (class, RateDecision::DropAndNotify) => {
+                                        tracing::warn!(connection_id = %connection_id, rate_class = ?class, "client exceeded rate limit; dropping events");
+                                        notify_rate_limited(&mut socket, class).await;
+                                    }
+                                }
                             },
                             Err(e) => {
                                 tracing::warn!(
@@ -92,7 +134,25 @@ pub async fn handle_connection(mut socket: WebSocket, state: AppState) {
                     }
                 }
             }
-            // Branch 2 server sends to client 
+            // Branch 2 transforms held back by the rate limit go out once tokens are back
+            _ = held_retry.tick(), if !held_transforms.is_empty() => {
+                let held: Vec<_> = held_transforms.drain().map(|(_, p)| p).collect();
+                for p in held {
+                    let event = ClientEvent::UpdateTransform(p);
+                    match rate_limit::check(&state, connection_id, &event, now_ms()) {
+                        (_, RateDecision::Allow) => {
+                            record_client_event(&state, connection_id, &event);
+                            dispatch(&mut socket, &state, connection_id, event).await;
+                        }
+                        _ => {
+                            if let ClientEvent::UpdateTransform(p) = event {
+                                held_transforms.insert(p.object_id, p);
+                            }
+                        }
+                    }
+                }
+            }
+            // Branch 3 server sends to client 
             msg = rx.recv() => {
                 match msg { 
                     Some (text) => {
@@ -115,6 +175,8 @@ pub async fn handle_connection(mut socket: WebSocket, state: AppState) {
     // ── Disconnect cleanup ────────────────────────────────────────────────────
     state.connections.remove(&connection_id);
     state.connection_backpressure.remove(&connection_id);
+    state.rate_limits.remove(&connection_id);
+    state.sync_backlogs.remove(&connection_id);
 
     // If the client was in a session (did not call LeaveSession cleanly), clean up now.
     if let Some((_, (sid, uid))) = state.connection_meta.remove(&connection_id) {
@@ -127,7 +189,7 @@ pub async fn handle_connection(mut socket: WebSocket, state: AppState) {
             state.session_connections.remove(&sid);
         }
 
-        let mut reclaim_session = false;
+        let mut reclaim = false;
         if let Some(session) = state.sessions.get(&sid) {
             let mut users = match session.users.write() {
                 Ok(guard) => guard,
@@ -137,11 +199,13 @@ pub async fn handle_connection(mut socket: WebSocket, state: AppState) {
                 }
             };
             users.remove(&uid);
-            reclaim_session = users.is_empty();
+            reclaim = users.is_empty() && !session.pinned;
+            session.lock_history().forget_user(uid);
+            session.forget_uploads(connection_id);
         }
 
-        if reclaim_session {
-            state.sessions.remove(&sid);
+        if reclaim {
+            reclaim_session(&state, &sid);
             tracing::info!(
                 event_type = "SessionReclaimed",
                 session_id = %sid,
@@ -177,6 +241,50 @@ pub async fn handle_connection(mut socket: WebSocket, state: AppState) {
     }
 }
 
+async fn notify_rate_limited(socket: &mut WebSocket, class: RateClass) {
+    let json = match serde_json::to_string(&ServerEvent::Error(ErrorPayload {
+        code: "RATE_LIMITED".to_string(),
+        message: format!("Too many {:?} events; some were dropped", class),
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(error = %err, "failed to serialize RATE_LIMITED error");
+            return;
+        }
+    };
+    let _ = socket.send(Message::Text(json.into())).await;
+}
+
+async fn reject_rate_limited(socket: &mut WebSocket, class: RateClass, retry_after_ms: u64, event: ClientEvent) {
+    let json = match serde_json::to_string(&ServerEvent::EventRejected(EventRejectedPayload {
+        code: "RATE_LIMITED".to_string(),
+        message: format!("Too many {:?} events; this one was not applied", class),
+        retry_after_ms,
+        event,
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(error = %err, "failed to serialize EventRejected");
+            return;
+        }
+    };
+    let _ = socket.send(Message::Text(json.into())).await;
+}
+
+async fn refuse_mutation(socket: &mut WebSocket, code: &str, message: &str) {
+    let json = match serde_json::to_string(&ServerEvent::Error(ErrorPayload {
+        code: code.to_string(),
+        message: message.to_string(),
+    })) {
+        Ok(json) => json,
+        Err(err) => {
+            tracing::error!(code, error = %err, "failed to serialize refusal");
+            return;
+        }
+    };
+    let _ = socket.send(Message::Text(json.into())).await;
+}
+
 // ── Event dispatcher ──────────────────────────────────────────────────────────
 
 async fn dispatch(
@@ -185,6 +293,21 @@ async fn dispatch(
     connection_id: Uuid,
     event: ClientEvent,
 ) {
+    if state.config.read_only && event.is_mutation() {
+        refuse_mutation(socket, "READ_ONLY", "This server is read-only; scene edits are refused").await;
+        return;
+    }
+    // A session whose storage failed only refuses edits to itself: its clients can still
+    // create or fork their way into a session that is saved.
+    let session = connection_session(state, connection_id);
+    if let Some(session) = &session
+        && session.storage_failed()
+        && event.is_mutation()
+        && !matches!(event, ClientEvent::CreateSession(_) | ClientEvent::ForkSession(_))
+    {
+        refuse_mutation(socket, "SESSION_READ_ONLY", "This session can no longer be saved; edits are refused").await;
+        return;
+    }
     match event {
         ClientEvent::JoinSession(p)      => handlers::join_session::handle(socket, state, connection_id, p).await,
         ClientEvent::CreateSession(p)    => handlers::create_session::handle(socket, state, connection_id, p).await,
@@ -194,8 +317,46 @@ async fn dispatch(
         ClientEvent::UpdateTransform(p)  => handlers::update_transform::handle(state, connection_id, p).await,
         ClientEvent::UpdateProperties(p) => handlers::update_properties::handle(state, connection_id, p).await,
         ClientEvent::UpdateName(p)       => handlers::update_name::handle(state, connection_id, p).await,
+        ClientEvent::SetParent(p)        => handlers::set_parent::handle(state, connection_id, p).await,
+        ClientEvent::CreateCollection(p) => handlers::create_collection::handle(state, connection_id, p).await,
+        ClientEvent::UpdateCollection(p) => handlers::update_collection::handle(state, connection_id, p).await,
+        ClientEvent::DeleteCollection(p) => handlers::delete_collection::handle(state, connection_id, p).await,
+        ClientEvent::SetObjectCollections(p) => handlers::set_object_collections::handle(state, connection_id, p).await,
+        ClientEvent::SetCustomProperty(p) => handlers::set_custom_property::handle(state, connection_id, p).await,
+        ClientEvent::RemoveCustomProperty(p) => handlers::remove_custom_property::handle(state, connection_id, p).await,
+        ClientEvent::PatchProperties(p) => handlers::patch_properties::handle(state, connection_id, p).await,
+        ClientEvent::CreateMaterial(p) => handlers::create_material::handle(state, connection_id, p).await,
+        ClientEvent::UpdateMaterial(p) => handlers::update_material::handle(state, connection_id, p).await,
+        ClientEvent::SetObjectMaterials(p) => handlers::set_object_materials::handle(state, connection_id, p).await,
+        ClientEvent::UploadMeshChunk(p) => handlers::upload_mesh_chunk::handle(socket, state, connection_id, p).await,
+        ClientEvent::SetObjectMesh(p)    => handlers::set_object_mesh::handle(state, connection_id, p).await,
+        ClientEvent::RequestMesh(p)      => handlers::request_mesh::handle(socket, state, connection_id, p).await,
+        ClientEvent::AddModifier(p)      => handlers::add_modifier::handle(state, connection_id, p).await,
+        ClientEvent::RemoveModifier(p)   => handlers::remove_modifier::handle(state, connection_id, p).await,
+        ClientEvent::ReorderModifiers(p) => handlers::reorder_modifiers::handle(state, connection_id, p).await,
+        ClientEvent::UpdateModifier(p)   => handlers::update_modifier::handle(state, connection_id, p).await,
         ClientEvent::SelectObject(p)     => handlers::select_object::handle(state, connection_id, p).await,
-        ClientEvent::RequestStateSync    => handlers::request_state_sync::handle(socket, state, connection_id).await,
+        ClientEvent::RequestStateSync(p) => handlers::request_state_sync::handle(socket, state, connection_id, p).await,
         ClientEvent::UpdateCursor(p)     => handlers::update_cursor::handle(state, connection_id, p).await,
+        ClientEvent::RequestChecksum(p)  => handlers::request_checksum::handle(socket, state, connection_id, p).await,
+        ClientEvent::RequestObjectsSync(p) => handlers::request_objects_sync::handle(socket, state, connection_id, p).await,
+        ClientEvent::Transaction(p)      => handlers::transaction::handle(state, connection_id, p).await,
+        ClientEvent::Undo                => handlers::undo::handle(state, connection_id, HistoryAction::Undo).await,
+        ClientEvent::Redo                => handlers::undo::handle(state, connection_id, HistoryAction::Redo).await,
+        ClientEvent::CreateCheckpoint(p) => handlers::create_checkpoint::handle(state, connection_id, p).await,
+        ClientEvent::ListCheckpoints     => handlers::list_checkpoints::handle(socket, state, connection_id).await,
+        ClientEvent::RestoreCheckpoint(p) => handlers::restore_checkpoint::handle(state, connection_id, p).await,
+        ClientEvent::ForkSession(p)      => handlers::fork_session::handle(socket, state, connection_id, p).await,
+        ClientEvent::ListTrash           => handlers::list_trash::handle(socket, state, connection_id).await,
+        ClientEvent::RestoreObject(p)    => handlers::restore_object::handle(state, connection_id, p).await,
+        ClientEvent::QueryAuditLog(p)    => handlers::query_audit_log::handle(socket, state, connection_id, p).await,
+    }
+    if let Some(session) = session {
+        announce_storage_failure(state, &session);
     }
 }
+
+fn connection_session(state: &AppState, connection_id: Uuid) -> Option<Arc<SessionHandle>> {
+    let session_id = state.connection_meta.get(&connection_id)?.0.clone();
+    state.sessions.get(&session_id).map(|session| session.clone())
+}
@@ -0,0 +1,118 @@
+use std::f64::consts::FRAC_1_SQRT_2;
+
+use crate::types::Transform;
+
+// Transform math shared by the scene interchange formats. Quaternions are `[x, y, z, w]` and
+// 4x4 matrices are column-major, as glTF stores them; USD's row-vector matrices read the same.
+
+/// Rotation taking Blender's Z-up world to a Y-up one (-90° about X).
+pub const Z_UP_TO_Y_UP: [f64; 4] = [-FRAC_1_SQRT_2, 0.0, 0.0, FRAC_1_SQRT_2];
+
+pub const IDENTITY: [f64; 16] = [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+/// `Z_UP_TO_Y_UP` as a matrix: (x, y, z) -> (x, z, -y).
+pub const Z_UP_TO_Y_UP_MATRIX: [f64; 16] =
+    [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+/// Its inverse: (x, y, z) -> (x, -z, y).
+pub const Y_UP_TO_Z_UP: [f64; 16] = [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+
+/// Quaternion for Blender's XYZ Euler rotation (X applied first).
+pub fn euler_xyz_to_quat([x, y, z]: [f64; 3]) -> [f64; 4] {
+    let axis = |angle: f64, i: usize| {
+        let mut q = [0.0, 0.0, 0.0, (angle / 2.0).cos()];
+        q[i] = (angle / 2.0).sin();
+        q
+    };
+    quat_mul(axis(z, 2), quat_mul(axis(y, 1), axis(x, 0)))
+}
+
+pub fn quat_mul(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    let [ax, ay, az, aw] = a;
+    let [bx, by, bz, bw] = b;
+    [
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+        aw * bw - ax * bx - ay * by - az * bz,
+    ]
+}
+
+pub fn mat_mul(a: &[f64; 16], b: &[f64; 16]) -> [f64; 16] {
+    let mut out = [0.0; 16];
+    for column in 0..4 {
+        for row in 0..4 {
+            out[column * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[column * 4 + k]).sum();
+        }
+    }
+    out
+}
+
+/// Translation, then rotation, then scale, applied to a point right to left.
+pub fn trs_matrix([tx, ty, tz]: [f64; 3], [x, y, z, w]: [f64; 4], [sx, sy, sz]: [f64; 3]) -> [f64; 16] {
+    [
+        (1.0 - 2.0 * (y * y + z * z)) * sx, 2.0 * (x * y + z * w) * sx, 2.0 * (x * z - y * w) * sx, 0.0,
+        2.0 * (x * y - z * w) * sy, (1.0 - 2.0 * (x * x + z * z)) * sy, 2.0 * (y * z + x * w) * sy, 0.0,
+        2.0 * (x * z + y * w) * sz, 2.0 * (y * z - x * w) * sz, (1.0 - 2.0 * (x * x + y * y)) * sz, 0.0,
+        tx, ty, tz, 1.0,
+    ]
+}
+
+/// The matrix of an object's transform: scale, then Blender's XYZ Euler rotation, then location.
+pub fn transform_matrix(transform: &Transform) -> [f64; 16] {
+    trs_matrix(transform.position, euler_xyz_to_quat(transform.rotation), transform.scale)
+}
+
+/// Inverse of an affine matrix. A singular one (a zero scale) has no inverse and gives the
+/// identity, which leaves whatever it is applied to where it was.
+pub fn invert(m: &[f64; 16]) -> [f64; 16] {
+    let a = |row: usize, col: usize| m[col * 4 + row];
+    let cofactor = |row: usize, col: usize| {
+        let (r0, r1) = ((row + 1) % 3, (row + 2) % 3);
+        let (c0, c1) = ((col + 1) % 3, (col + 2) % 3);
+        a(r0, c0) * a(r1, c1) - a(r0, c1) * a(r1, c0)
+    };
+    let determinant = a(0, 0) * cofactor(0, 0) + a(0, 1) * cofactor(0, 1) + a(0, 2) * cofactor(0, 2);
+    if determinant.abs() < 1e-12 {
+        return IDENTITY;
+    }
+    let mut out = IDENTITY;
+    for row in 0..3 {
+        for col in 0..3 {
+            // The inverse of the linear part is the transposed cofactor matrix over the determinant.
+            out[col * 4 + row] = cofactor(col, row) / determinant;
+        }
+    }
+    for row in 0..3 {
+        out[12 + row] = -(0..3).map(|k| out[k * 4 + row] * m[12 + k]).sum::<f64>();
+    }
+    out
+}
+
+/// Rotation by `angle` radians about axis `axis` (0 = X, 1 = Y, 2 = Z).
+pub fn axis_rotation_matrix(axis: usize, angle: f64) -> [f64; 16] {
+    let mut q = [0.0, 0.0, 0.0, (angle / 2.0).cos()];
+    q[axis] = (angle / 2.0).sin();
+    trs_matrix([0.0; 3], q, [1.0; 3])
+}
+
+/// Location, XYZ Euler rotation and scale of an affine matrix. Shear is dropped; a mirrored
+/// matrix gets a negative X scale.
+pub fn decompose(m: &[f64; 16]) -> Transform {
+    let column = |c: usize| [m[c * 4], m[c * 4 + 1], m[c * 4 + 2]];
+    let length = |v: [f64; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    let (c0, c1, c2) = (column(0), column(1), column(2));
+    let determinant = c0[0] * (c1[1] * c2[2] - c1[2] * c2[1]) - c1[0] * (c0[1] * c2[2] - c0[2] * c2[1])
+        + c2[0] * (c0[1] * c1[2] - c0[2] * c1[1]);
+    let mut scale = [length(c0), length(c1), length(c2)];
+    if determinant < 0.0 {
+        scale[0] = -scale[0];
+    }
+    // r(row, col) of the pure rotation.
+    let r = |row: usize, col: usize| if scale[col] == 0.0 { 0.0 } else { m[col * 4 + row] / scale[col] };
+    let y = (-r(2, 0)).clamp(-1.0, 1.0).asin();
+    let (x, z) = if y.cos() > 1e-6 {
+        (r(2, 1).atan2(r(2, 2)), r(1, 0).atan2(r(0, 0)))
+    } else {
+        ((-r(1, 2)).atan2(r(1, 1)), 0.0)
+    };
+    Transform { position: [m[12], m[13], m[14]], rotation: [x, y, z], scale }
+}
@@ -0,0 +1,159 @@
+use std::process::Command;
+
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    admin::SESSION_PASSWORD_HEADER,
+    archive::{read_archive, ArchiveMetadata, SessionArchive, ARCHIVE_MAGIC},
+    config::{ServerConfig, DATA_DIR_ENV},
+    messages::{ClientEvent, CreateCheckpointPayload, DeleteObjectPayload, JoinSessionPayload, ServerEvent},
+    snapshot::{recover, restore_sessions, SNAPSHOT_FILE_NAME},
+    storage::session_dir,
+    types::AppState,
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, http_request, http_request_with_headers, recv, send,
+    start_admin_test_server_with_config, temp_data_dir, WsStream, TEST_ADMIN_TOKEN,
+};
+
+/// A session with two cubes, a checkpoint and one of the cubes in the trash.
+async fn populate(ws: &mut WsStream, session_id: &str) -> (Uuid, Uuid) {
+    create_session(ws, session_id, "Alice").await;
+    let kept = Uuid::new_v4();
+    let deleted = Uuid::new_v4();
+    for id in [kept, deleted] {
+        send(ws, ClientEvent::CreateObject(cube_payload(id))).await;
+        recv(ws).await; // ObjectCreated
+    }
+    send(ws, ClientEvent::CreateCheckpoint(CreateCheckpointPayload { name: "two cubes".to_string() })).await;
+    recv(ws).await; // CheckpointCreated
+    send(ws, ClientEvent::DeleteObject(DeleteObjectPayload { object_id: deleted, delete_children: false })).await;
+    recv(ws).await; // ObjectDeleted
+    (kept, deleted)
+}
+
+/// Export a session over the admin API, import it under a new ID and password, and find its
+/// scene, checkpoints, trash and audit history there.
+#[tokio::test]
+async fn test_admin_export_and_import() {
+    let data_dir = temp_data_dir();
+    let config = ServerConfig { data_dir: Some(data_dir.clone()), ..ServerConfig::default() };
+    let (url, addr, state) = start_admin_test_server_with_config(config).await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    let (kept, deleted) = populate(&mut ws, "original").await;
+
+    let (status, archive) = http_request(&addr, "GET", "/admin/sessions/original/export", Some(TEST_ADMIN_TOKEN), &[]).await;
+    assert_eq!(status, 200);
+    assert!(archive.starts_with(ARCHIVE_MAGIC));
+    let (status, _) = http_request(&addr, "GET", "/admin/sessions/missing/export", Some(TEST_ADMIN_TOKEN), &[]).await;
+    assert_eq!(status, 404);
+
+    let import = |session_id: &'static str, headers: &'static [(&'static str, &'static str)], body: Vec<u8>| {
+        let addr = addr.clone();
+        async move {
+            let path = format!("/admin/sessions/{session_id}/import");
+            http_request_with_headers(&addr, "POST", &path, Some(TEST_ADMIN_TOKEN), headers, &body).await
+        }
+    };
+    let password = &[(SESSION_PASSWORD_HEADER, "copy-password")];
+    let (status, body) = import("copy", password, archive.clone()).await;
+    assert_eq!(status, 201, "{}", String::from_utf8_lossy(&body));
+    let metadata: ArchiveMetadata = serde_json::from_slice(&body).unwrap();
+    assert_eq!(metadata.source_session_id, "original");
+    assert_eq!((metadata.object_count, metadata.checkpoint_count, metadata.trash_count), (1, 1, 1));
+    assert_eq!(metadata.audit_entry_count, 3, "create, create, delete");
+
+    assert_eq!(import("copy", password, archive.clone()).await.0, 409);
+    assert_eq!(import("original", password, archive.clone()).await.0, 409);
+    assert_eq!(import("other", &[], archive.clone()).await.0, 400, "password is required");
+    assert_eq!(import("other", password, b"not an archive".to_vec()).await.0, 400);
+    let mut future = archive.clone();
+    future[4] = 99;
+    assert_eq!(import("other", password, future).await.0, 400, "unknown format version");
+    assert!(!state.sessions.contains_key("other"));
+
+    // The copy answers to its own password only.
+    let (mut copy_ws, _) = connect_async(&url).await.unwrap();
+    for (password, accepted) in [(common::TEST_PASSWORD, false), ("copy-password", true)] {
+        send(&mut copy_ws, ClientEvent::JoinSession(JoinSessionPayload {
+            session_id: "copy".to_string(),
+            display_name: "Bob".to_string(),
+            password: password.to_string(),
+        })).await;
+        match recv(&mut copy_ws).await {
+            ServerEvent::FullStateSync(p) if accepted => {
+                assert_eq!(p.session.session_id, "copy");
+                assert_eq!(p.session.objects.keys().collect::<Vec<_>>(), [&kept]);
+            }
+            ServerEvent::Error(_) if !accepted => {}
+            other => panic!("unexpected reply to password {password}: {other:?}"),
+        }
+    }
+
+    let copy = state.sessions.get("copy").unwrap().clone();
+    assert_eq!(copy.checkpoints.read().unwrap()[0].objects.len(), 2);
+    assert!(copy.trash.lock().unwrap().contains_key(&deleted));
+    assert_eq!(copy.lock_audit().entries().count(), 3);
+    assert_eq!(copy.current_version(), state.sessions.get("original").unwrap().current_version());
+
+    // The import is persisted like any new session.
+    let recovered = recover(&session_dir(&data_dir, "copy"), None).unwrap().unwrap();
+    assert_eq!(recovered.objects.len(), 1);
+    assert_eq!(recovered.checkpoints.len(), 1);
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
+
+/// The CLI exports a persisted session from one data directory and imports it into another,
+/// where the next server start restores it.
+#[tokio::test]
+async fn test_cli_export_and_import() {
+    let source_dir = temp_data_dir();
+    let config = ServerConfig { data_dir: Some(source_dir.clone()), ..ServerConfig::default() };
+    let (url, _addr, _state) = start_admin_test_server_with_config(config).await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    let (kept, _) = populate(&mut ws, "on-disk").await;
+
+    let archive_path = source_dir.join("on-disk.mka");
+    let cli = |data_dir: &std::path::Path, args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_meerkat-server"))
+            .env(DATA_DIR_ENV, data_dir)
+            .args(args)
+            .output()
+            .expect("failed to run meerkat-server")
+    };
+    let archive_arg = archive_path.to_str().unwrap();
+    let output = cli(&source_dir, &["export", "on-disk", archive_arg]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let archive: SessionArchive = read_archive(&archive_path).unwrap();
+    assert_eq!(archive.session.objects.len(), 1);
+    assert_eq!(archive.audit.len(), 3);
+    assert_eq!(archive.metadata.source_session_id, "on-disk");
+    assert_eq!(archive.metadata.server_version, env!("CARGO_PKG_VERSION"));
+    assert!(archive.metadata.exported_at > 0);
+    assert_eq!((archive.metadata.object_count, archive.metadata.audit_entry_count), (1, 3));
+    assert!(!cli(&source_dir, &["export", "on-disk", archive_arg]).status.success(), "never overwrites");
+
+    let target_dir = temp_data_dir();
+    let output = cli(&target_dir, &["import", archive_arg, "restored", "--password", "secret"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(session_dir(&target_dir, "restored").join(SNAPSHOT_FILE_NAME).exists());
+    let output = cli(&target_dir, &["import", archive_arg, "restored", "--password", "secret"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+
+    let state = AppState::with_config(ServerConfig { data_dir: Some(target_dir.clone()), ..ServerConfig::default() });
+    assert_eq!(restore_sessions(&state).unwrap(), 1);
+    let restored = state.sessions.get("restored").unwrap().clone();
+    assert!(restored.objects.read().unwrap().contains_key(&kept));
+    assert_eq!(restored.checkpoints.read().unwrap().len(), 1);
+    assert_eq!(restored.lock_audit().entries().count(), 3);
+    assert!(bcrypt::verify("secret", &restored.password_hash).unwrap());
+
+    let _ = std::fs::remove_dir_all(&source_dir);
+    let _ = std::fs::remove_dir_all(&target_dir);
+}
@@ -0,0 +1,150 @@
+use tokio::time::{sleep, Duration};
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    audit::{AuditQuery, AUDIT_FILE_NAME},
+    config::ServerConfig,
+    messages::{AuditLogPayload, ClientEvent, ServerEvent, UpdateNamePayload, UpdateTransformPayload},
+    storage::session_dir,
+    types::Transform,
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, http_get, join_session, recv, send, start_admin_test_server_with_config,
+    start_test_server_with_state, temp_data_dir, TEST_ADMIN_TOKEN,
+};
+
+fn move_to(object_id: Uuid, x: f64) -> UpdateTransformPayload {
+    UpdateTransformPayload {
+        object_id,
+        transform: Transform { position: [x, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+    }
+}
+
+/// "Who moved the hero camera?": per-object history with names, merged drags and undo.
+#[tokio::test]
+async fn test_query_audit_log_by_object_and_user() {
+    let (url, _state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "audit-query", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "audit-query", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let camera = Uuid::new_v4();
+    let mut payload = cube_payload(camera);
+    payload.name = "Hero Camera".to_string();
+    send(&mut ws_a, ClientEvent::CreateObject(payload)).await;
+    recv(&mut ws_a).await; // ObjectCreated
+    recv(&mut ws_b).await; // ObjectCreated
+    send(&mut ws_a, ClientEvent::CreateObject(cube_payload(Uuid::new_v4()))).await;
+    recv(&mut ws_a).await; // ObjectCreated
+    recv(&mut ws_b).await; // ObjectCreated
+
+    // One drag of three ticks.
+    for x in [1.0, 2.0, 3.0] {
+        send(&mut ws_b, ClientEvent::UpdateTransform(move_to(camera, x))).await;
+        recv(&mut ws_a).await; // TransformUpdated
+        recv(&mut ws_b).await; // TransformUpdated
+    }
+    send(&mut ws_a, ClientEvent::UpdateName(UpdateNamePayload { object_id: camera, name: "Hero Cam".to_string() })).await;
+    recv(&mut ws_a).await; // NameUpdated
+    recv(&mut ws_b).await; // NameUpdated
+    send(&mut ws_a, ClientEvent::Undo).await;
+    recv(&mut ws_a).await; // HistoryApplied
+    recv(&mut ws_b).await; // HistoryApplied
+
+    send(&mut ws_b, ClientEvent::QueryAuditLog(AuditQuery { object_id: Some(camera), ..AuditQuery::default() })).await;
+    let entries = match recv(&mut ws_b).await {
+        ServerEvent::AuditLog(p) => {
+            assert!(!p.truncated);
+            p.entries
+        }
+        other => panic!("expected AuditLog, got {:?}", other),
+    };
+    let summary: Vec<(&str, &str)> = entries.iter().map(|e| (e.event_type.as_str(), e.display_name.as_str())).collect();
+    assert_eq!(summary, vec![
+        ("Undo", "Alice"),
+        ("UpdateName", "Alice"),
+        ("UpdateTransform", "Bob"),
+        ("CreateObject", "Alice"),
+    ]);
+    let drag = &entries[2];
+    assert_eq!(drag.before.as_ref().unwrap().transform.position[0], 0.0);
+    assert_eq!(drag.after.as_ref().unwrap().transform.position[0], 3.0);
+    assert!(entries[3].before.is_none());
+    assert!(entries.windows(2).all(|w| w[0].seq > w[1].seq), "newest first");
+
+    let bob_id = drag.user_id;
+    send(&mut ws_a, ClientEvent::QueryAuditLog(AuditQuery {
+        user_id: Some(bob_id),
+        ..AuditQuery::default()
+    })).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::AuditLog(p) => assert_eq!(p.entries.len(), 1),
+        other => panic!("expected AuditLog, got {:?}", other),
+    }
+
+    send(&mut ws_a, ClientEvent::QueryAuditLog(AuditQuery {
+        since: Some(drag.timestamp + 1),
+        limit: Some(1),
+        ..AuditQuery::default()
+    })).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::AuditLog(p) => {
+            assert_eq!(p.entries.len(), 1);
+            assert!(p.entries[0].timestamp > drag.timestamp);
+        }
+        other => panic!("expected AuditLog, got {:?}", other),
+    }
+}
+
+/// The log is written to the session's directory and stays queryable over the admin API
+/// after the session is reclaimed.
+#[tokio::test]
+async fn test_audit_log_is_persisted_and_served_by_admin_api() {
+    let data_dir = temp_data_dir();
+    let config = ServerConfig { data_dir: Some(data_dir.clone()), ..ServerConfig::default() };
+    let (url, addr, state) = start_admin_test_server_with_config(config).await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, "audit/persisted", "Alice").await;
+
+    let cube = Uuid::new_v4();
+    send(&mut ws, ClientEvent::CreateObject(cube_payload(cube))).await;
+    recv(&mut ws).await; // ObjectCreated
+    send(&mut ws, ClientEvent::UpdateTransform(move_to(cube, 4.0))).await;
+    recv(&mut ws).await; // TransformUpdated
+
+    let path = "/admin/sessions/audit%2Fpersisted/audit";
+    let (status, body) = http_get(&addr, &format!("{path}?object_id={cube}"), Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, 200, "{body}");
+    let live: AuditLogPayload = serde_json::from_str(&body).unwrap();
+    assert_eq!(live.entries.len(), 2);
+
+    ws.close(None).await.unwrap();
+    for _ in 0..50 {
+        if !state.sessions.contains_key("audit/persisted") {
+            break;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    assert!(!state.sessions.contains_key("audit/persisted"), "session should be reclaimed");
+
+    let file = session_dir(&data_dir, "audit/persisted").join(AUDIT_FILE_NAME);
+    let lines = std::fs::read_to_string(&file).unwrap();
+    assert_eq!(lines.lines().count(), 2, "the open drag is written when the session goes away");
+
+    let (status, body) = http_get(&addr, path, Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, 200, "{body}");
+    let persisted: AuditLogPayload = serde_json::from_str(&body).unwrap();
+    assert_eq!(persisted.entries[0].event_type, "UpdateTransform");
+    assert_eq!(persisted.entries[0].after.as_ref().unwrap().transform.position[0], 4.0);
+
+    let (status, _) = http_get(&addr, "/admin/sessions/never-existed/audit", Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, 404);
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
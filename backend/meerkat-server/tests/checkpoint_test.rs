@@ -0,0 +1,125 @@
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    messages::{
+        ClientEvent, CreateCheckpointPayload, DeleteObjectPayload, RestoreCheckpointPayload, ServerEvent,
+        UpdateTransformPayload,
+    },
+    types::Transform,
+};
+
+mod common;
+
+use common::{create_session, cube_payload, join_session, recv, send, start_test_server_with_state};
+
+/// Restoring a checkpoint broadcasts only the diff back to the tagged state, and the restore
+/// itself can be undone by the user who ran it.
+#[tokio::test]
+async fn test_restore_checkpoint_broadcasts_diff() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "checkpoint-restore", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "checkpoint-restore", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let moved = Uuid::new_v4();
+    let removed = Uuid::new_v4();
+    let untouched = Uuid::new_v4();
+    for id in [moved, removed, untouched] {
+        send(&mut ws_a, ClientEvent::CreateObject(cube_payload(id))).await;
+        recv(&mut ws_a).await; // ObjectCreated
+        recv(&mut ws_b).await; // ObjectCreated
+    }
+
+    send(&mut ws_a, ClientEvent::CreateCheckpoint(CreateCheckpointPayload { name: "before layout".to_string() })).await;
+    let checkpoint_id = match recv(&mut ws_b).await {
+        ServerEvent::CheckpointCreated(info) => {
+            assert_eq!(info.name, "before layout");
+            assert_eq!(info.object_count, 3);
+            assert_eq!(info.version, 3);
+            info.checkpoint_id
+        }
+        other => panic!("expected CheckpointCreated, got {:?}", other),
+    };
+    recv(&mut ws_a).await; // CheckpointCreated
+
+    send(&mut ws_b, ClientEvent::UpdateTransform(UpdateTransformPayload {
+        object_id: moved,
+        transform: Transform { position: [3.0, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+    })).await;
+    recv(&mut ws_a).await; // TransformUpdated
+    recv(&mut ws_b).await; // TransformUpdated
+    send(&mut ws_b, ClientEvent::DeleteObject(DeleteObjectPayload { object_id: removed, delete_children: false })).await;
+    recv(&mut ws_a).await; // ObjectDeleted
+    recv(&mut ws_b).await; // ObjectDeleted
+    let added = Uuid::new_v4();
+    send(&mut ws_b, ClientEvent::CreateObject(cube_payload(added))).await;
+    recv(&mut ws_a).await; // ObjectCreated
+    recv(&mut ws_b).await; // ObjectCreated
+
+    send(&mut ws_a, ClientEvent::ListCheckpoints).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::CheckpointList(p) => {
+            assert_eq!(p.checkpoints.len(), 1);
+            assert_eq!(p.checkpoints[0].checkpoint_id, checkpoint_id);
+        }
+        other => panic!("expected CheckpointList, got {:?}", other),
+    }
+
+    send(&mut ws_a, ClientEvent::RestoreCheckpoint(RestoreCheckpointPayload { checkpoint_id })).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::CheckpointRestored(p) => {
+                assert_eq!(p.checkpoint.checkpoint_id, checkpoint_id);
+                assert_eq!(p.diff.created.len(), 1);
+                assert_eq!(p.diff.created[0].object_id, removed);
+                assert_eq!(p.diff.updated.len(), 1);
+                assert_eq!(p.diff.updated[0].object_id, moved);
+                assert_eq!(p.diff.updated[0].transform.position, [0.0, 0.0, 0.0]);
+                assert_eq!(p.diff.deleted, vec![added]);
+            }
+            other => panic!("expected CheckpointRestored, got {:?}", other),
+        }
+    }
+
+    {
+        let session = state.sessions.get("checkpoint-restore").unwrap();
+        let objects = session.objects.read().unwrap();
+        let mut ids: Vec<Uuid> = objects.keys().copied().collect();
+        ids.sort();
+        let mut expected = vec![moved, removed, untouched];
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
+
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_b).await {
+        ServerEvent::HistoryApplied(p) => {
+            assert_eq!(p.diff.created.len(), 1);
+            assert_eq!(p.diff.created[0].object_id, added);
+            assert_eq!(p.diff.deleted, vec![removed]);
+        }
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_checkpoint_errors() {
+    let (url, _state) = start_test_server_with_state().await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, "checkpoint-errors", "Alice").await;
+
+    send(&mut ws, ClientEvent::CreateCheckpoint(CreateCheckpointPayload { name: "   ".to_string() })).await;
+    match recv(&mut ws).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "INVALID_CHECKPOINT_NAME"),
+        other => panic!("expected INVALID_CHECKPOINT_NAME, got {:?}", other),
+    }
+
+    send(&mut ws, ClientEvent::RestoreCheckpoint(RestoreCheckpointPayload { checkpoint_id: Uuid::new_v4() })).await;
+    match recv(&mut ws).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "CHECKPOINT_NOT_FOUND"),
+        other => panic!("expected CHECKPOINT_NOT_FOUND, got {:?}", other),
+    }
+}
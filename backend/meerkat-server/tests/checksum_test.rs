@@ -0,0 +1,169 @@
+use std::collections::{BTreeMap, HashMap};
+
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    checksum::{broadcast_session_checksums, format_checksum, object_hash, objects_checksum},
+    messages::{ClientEvent, RequestChecksumPayload, RequestObjectsSyncPayload, ServerEvent},
+    types::{CustomValue, Modifier, ModifierKind, ObjectType, SceneObject, Transform},
+};
+
+mod common;
+
+use common::{create_session, cube_payload, recv, send, start_test_server_with_state, try_recv};
+
+fn object_at(position: [f64; 3]) -> SceneObject {
+    SceneObject {
+        object_id: Uuid::new_v4(),
+        name: "Cube".to_string(),
+        object_type: ObjectType::Cube,
+        asset_id: None,
+        asset_library: None,
+        parent_id: None,
+        transform: Transform { position, rotation: [0.0; 3], scale: [1.0; 3] },
+        properties: None,
+        collections: Vec::new(),
+        custom_properties: BTreeMap::new(),
+        extra_properties: serde_json::Map::new(),
+        material_slots: Vec::new(),
+        mesh: None,
+        mesh_version: 0,
+        modifiers: Vec::new(),
+        created_by: Uuid::nil(),
+        last_updated_by: Uuid::nil(),
+        last_updated_at: 0,
+    }
+}
+
+#[test]
+fn checksum_is_order_independent_and_tolerates_f32_round_trip() {
+    let a = object_at([1.0, 2.0, 3.0]);
+    let b = object_at([-4.0, 0.5, 9.25]);
+    assert_eq!(objects_checksum([&a, &b]), objects_checksum([&b, &a]));
+
+    // Blender stores transforms as f32; the value it echoes back must hash identically.
+    let mut drifted = a.clone();
+    drifted.transform.position[0] = f64::from(1.000_000_1_f32);
+    assert_eq!(object_hash(&a), object_hash(&drifted));
+
+    // A real move is detected.
+    drifted.transform.position[0] = 1.01;
+    assert_ne!(object_hash(&a), object_hash(&drifted));
+}
+
+/// The Blender plugin rebuilds this hash on its side (blender_plugin/tests/test_checksum.py pins
+/// the same value), so the canonical form must not change by accident.
+#[test]
+fn object_hash_matches_pinned_canonical_form() {
+    let mut object = object_at([1.0, 2.0, 3.0]);
+    object.object_id = Uuid::from_u128(1);
+    assert_eq!(format_checksum(object_hash(&object)), "1e6f832c8c0c9c27");
+}
+
+/// Material slots, custom property keys and modifier ids are part of the hash; property values
+/// and modifier settings are not.
+#[test]
+fn object_hash_covers_slots_property_keys_and_modifiers() {
+    let object = object_at([0.0; 3]);
+    let hash = object_hash(&object);
+
+    let mut slotted = object.clone();
+    slotted.material_slots = vec![None];
+    assert_ne!(object_hash(&slotted), hash);
+    slotted.material_slots = vec![Some(Uuid::from_u128(7))];
+    assert_ne!(object_hash(&slotted), hash);
+
+    let mut tagged = object.clone();
+    tagged.custom_properties.insert("rating".to_string(), CustomValue::Number(1.0));
+    let tagged_hash = object_hash(&tagged);
+    assert_ne!(tagged_hash, hash);
+    tagged.custom_properties.insert("rating".to_string(), CustomValue::Number(2.5));
+    assert_eq!(object_hash(&tagged), tagged_hash);
+
+    let mut modified = object.clone();
+    modified.modifiers.push(Modifier {
+        modifier_id: Uuid::from_u128(9),
+        name: "Weld".to_string(),
+        show_viewport: true,
+        show_render: true,
+        kind: ModifierKind::Other { blender_type: "WELD".to_string(), parameters: BTreeMap::new() },
+    });
+    let modified_hash = object_hash(&modified);
+    assert_ne!(modified_hash, hash);
+    modified.modifiers[0].name = "Weld.001".to_string();
+    assert_eq!(object_hash(&modified), modified_hash);
+}
+
+/// RequestChecksum returns the session checksum (and per-object hashes on demand), and
+/// RequestObjectsSync returns just the requested objects with unknown ids flagged as missing.
+#[tokio::test]
+async fn test_checksum_request_and_targeted_resync() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, "checksum-test", "Alice").await;
+
+    let first = Uuid::new_v4();
+    let second = Uuid::new_v4();
+    for id in [first, second] {
+        send(&mut ws, ClientEvent::CreateObject(cube_payload(id))).await;
+        recv(&mut ws).await; // ObjectCreated
+    }
+
+    let expected_hashes: HashMap<Uuid, String> = {
+        let session = state.sessions.get("checksum-test").unwrap();
+        let objects = session.objects.read().unwrap();
+        objects.values().map(|o| (o.object_id, format_checksum(object_hash(o)))).collect()
+    };
+
+    send(&mut ws, ClientEvent::RequestChecksum(RequestChecksumPayload { include_objects: true })).await;
+    match recv(&mut ws).await {
+        ServerEvent::StateChecksum(p) => {
+            assert_eq!(p.version, 2);
+            assert_eq!(p.object_count, 2);
+            assert_eq!(p.object_hashes.as_ref(), Some(&expected_hashes));
+            let summed = expected_hashes
+                .values()
+                .map(|h| u64::from_str_radix(h, 16).unwrap())
+                .fold(0u64, u64::wrapping_add);
+            assert_eq!(p.checksum, format_checksum(summed));
+        }
+        other => panic!("expected StateChecksum, got {:?}", other),
+    }
+
+    let unknown = Uuid::new_v4();
+    send(&mut ws, ClientEvent::RequestObjectsSync(RequestObjectsSyncPayload {
+        object_ids: vec![second, unknown],
+    })).await;
+    match recv(&mut ws).await {
+        ServerEvent::ObjectsSynced(p) => {
+            assert_eq!(p.objects.len(), 1);
+            assert_eq!(p.objects[0].object_id, second);
+            assert_eq!(p.missing, vec![unknown]);
+        }
+        other => panic!("expected ObjectsSynced, got {:?}", other),
+    }
+}
+
+/// The periodic broadcaster only sends when a session's version moved since the last round.
+#[tokio::test]
+async fn test_periodic_checksum_skips_unchanged_sessions() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, "checksum-periodic", "Alice").await;
+    send(&mut ws, ClientEvent::CreateObject(cube_payload(Uuid::new_v4()))).await;
+    recv(&mut ws).await; // ObjectCreated
+
+    let mut last_sent = HashMap::new();
+    broadcast_session_checksums(&state, &mut last_sent);
+    match recv(&mut ws).await {
+        ServerEvent::StateChecksum(p) => {
+            assert_eq!(p.version, 1);
+            assert!(p.object_hashes.is_none(), "periodic checksums carry only the summary");
+        }
+        other => panic!("expected StateChecksum, got {:?}", other),
+    }
+
+    broadcast_session_checksums(&state, &mut last_sent);
+    assert!(try_recv(&mut ws).await.is_none(), "unchanged session should not be re-broadcast");
+}
@@ -0,0 +1,375 @@
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    config::ServerConfig,
+    messages::{
+        ClientEvent, CreateCheckpointPayload, CreateCollectionPayload, DeleteCollectionPayload, DeleteObjectPayload,
+        JoinSessionPayload, RestoreCheckpointPayload, RestoreObjectPayload, ServerEvent, SetObjectCollectionsPayload,
+        UpdateCollectionPayload,
+    },
+    snapshot::{recover, snapshot_session},
+    storage::session_dir,
+    types::{ColorTag, MAX_COLLECTION_NAME_LEN},
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, join_session, recv, send, start_admin_test_server_with_config,
+    start_test_server_with_state, temp_data_dir, try_recv, WsStream, TEST_PASSWORD,
+};
+
+fn collection_payload(collection_id: Uuid, name: &str) -> CreateCollectionPayload {
+    CreateCollectionPayload {
+        collection_id,
+        name: name.to_string(),
+        color_tag: ColorTag::Color03,
+        visible: true,
+        selectable: true,
+    }
+}
+
+async fn create_collection(ws: &mut WsStream, collection_id: Uuid, name: &str) {
+    send(ws, ClientEvent::CreateCollection(collection_payload(collection_id, name))).await;
+    match recv(ws).await {
+        ServerEvent::CollectionCreated(p) => assert_eq!(p.collection.collection_id, collection_id),
+        other => panic!("expected CollectionCreated, got {:?}", other),
+    }
+}
+
+async fn create_cube(ws: &mut WsStream, object_id: Uuid) {
+    send(ws, ClientEvent::CreateObject(cube_payload(object_id))).await;
+    match recv(ws).await {
+        ServerEvent::ObjectCreated(p) => assert_eq!(p.object.object_id, object_id),
+        other => panic!("expected ObjectCreated, got {:?}", other),
+    }
+}
+
+/// Creating and updating a collection reaches every client; bad names, duplicates and unknown
+/// collections are refused without a broadcast.
+#[tokio::test]
+async fn test_collection_lifecycle_broadcasts() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "coll-life", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "coll-life", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let props = Uuid::new_v4();
+    send(&mut ws_a, ClientEvent::CreateCollection(collection_payload(props, "  Props  "))).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::CollectionCreated(p) => {
+                assert_eq!(p.collection.name, "Props");
+                assert_eq!(p.collection.color_tag, ColorTag::Color03);
+                assert!(p.collection.visible && p.collection.selectable);
+            }
+            other => panic!("expected CollectionCreated, got {:?}", other),
+        }
+    }
+
+    send(&mut ws_b, ClientEvent::UpdateCollection(UpdateCollectionPayload {
+        collection_id: props,
+        name: None,
+        color_tag: None,
+        visible: Some(false),
+        selectable: None,
+    })).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::CollectionUpdated(p) => {
+                assert_eq!(p.collection.name, "Props");
+                assert!(!p.collection.visible);
+                assert!(p.collection.selectable);
+            }
+            other => panic!("expected CollectionUpdated, got {:?}", other),
+        }
+    }
+
+    let rejected = [
+        (ClientEvent::CreateCollection(collection_payload(Uuid::new_v4(), "   ")), "INVALID_COLLECTION_NAME"),
+        (
+            ClientEvent::CreateCollection(collection_payload(Uuid::new_v4(), &"x".repeat(MAX_COLLECTION_NAME_LEN + 1))),
+            "INVALID_COLLECTION_NAME",
+        ),
+        (ClientEvent::CreateCollection(collection_payload(props, "Again")), "DUPLICATE_COLLECTION"),
+        (
+            ClientEvent::UpdateCollection(UpdateCollectionPayload {
+                collection_id: Uuid::new_v4(),
+                name: Some("Ghost".to_string()),
+                color_tag: None,
+                visible: None,
+                selectable: None,
+            }),
+            "COLLECTION_NOT_FOUND",
+        ),
+        (ClientEvent::DeleteCollection(DeleteCollectionPayload { collection_id: Uuid::new_v4() }), "COLLECTION_NOT_FOUND"),
+    ];
+    for (event, code) in rejected {
+        send(&mut ws_a, event).await;
+        match recv(&mut ws_a).await {
+            ServerEvent::Error(e) => assert_eq!(e.code, code),
+            other => panic!("expected {code}, got {:?}", other),
+        }
+    }
+    assert!(try_recv(&mut ws_b).await.is_none(), "rejected changes are not broadcast");
+
+    let session = state.sessions.get("coll-life").unwrap();
+    let collections = session.collections.read().unwrap();
+    assert_eq!(collections.len(), 1);
+    assert!(!collections[&props].visible);
+}
+
+/// Membership only names existing collections, drops repeats, and is undoable like any edit.
+#[tokio::test]
+async fn test_set_object_collections() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "coll-members", "Alice").await;
+
+    let (props, lights, cube) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    create_collection(&mut ws_a, props, "Props").await;
+    create_collection(&mut ws_a, lights, "Lights").await;
+    create_cube(&mut ws_a, cube).await;
+
+    send(&mut ws_a, ClientEvent::SetObjectCollections(SetObjectCollectionsPayload {
+        object_id: cube,
+        collection_ids: vec![props, lights, props],
+    })).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::ObjectCollectionsUpdated(p) => {
+            assert_eq!(p.object_id, cube);
+            assert_eq!(p.collection_ids, [props, lights]);
+        }
+        other => panic!("expected ObjectCollectionsUpdated, got {:?}", other),
+    }
+
+    send(&mut ws_a, ClientEvent::SetObjectCollections(SetObjectCollectionsPayload {
+        object_id: cube,
+        collection_ids: vec![props, Uuid::new_v4()],
+    })).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "COLLECTION_NOT_FOUND"),
+        other => panic!("expected COLLECTION_NOT_FOUND, got {:?}", other),
+    }
+
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::HistoryApplied(p) => {
+            assert!(p.diff.updated.iter().any(|object| object.object_id == cube && object.collections.is_empty()));
+        }
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+    let session = state.sessions.get("coll-members").unwrap();
+    assert!(session.objects.read().unwrap()[&cube].collections.is_empty());
+}
+
+/// Deleting a collection takes every object out of it but leaves the objects alone.
+#[tokio::test]
+async fn test_delete_collection_strips_membership() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "coll-delete", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "coll-delete", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let (props, lights, cube) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    for (collection_id, name) in [(props, "Props"), (lights, "Lights")] {
+        create_collection(&mut ws_a, collection_id, name).await;
+        recv(&mut ws_b).await; // CollectionCreated
+    }
+    create_cube(&mut ws_a, cube).await;
+    recv(&mut ws_b).await; // ObjectCreated
+    send(&mut ws_a, ClientEvent::SetObjectCollections(SetObjectCollectionsPayload {
+        object_id: cube,
+        collection_ids: vec![props, lights],
+    })).await;
+    recv(&mut ws_a).await; // ObjectCollectionsUpdated
+    recv(&mut ws_b).await; // ObjectCollectionsUpdated
+
+    send(&mut ws_b, ClientEvent::DeleteCollection(DeleteCollectionPayload { collection_id: props })).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::CollectionDeleted(p) => assert_eq!(p.collection_id, props),
+            other => panic!("expected CollectionDeleted, got {:?}", other),
+        }
+    }
+
+    let session = state.sessions.get("coll-delete").unwrap();
+    assert!(!session.collections.read().unwrap().contains_key(&props));
+    assert_eq!(session.objects.read().unwrap()[&cube].collections, [lights]);
+}
+
+/// Undoing a DeleteCollection brings the collection back ahead of its members; redoing it
+/// deletes it again after they have left.
+#[tokio::test]
+async fn test_delete_collection_is_undoable() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "coll-undo", "Alice").await;
+
+    let (props, cube) = (Uuid::new_v4(), Uuid::new_v4());
+    create_collection(&mut ws_a, props, "Props").await;
+    create_cube(&mut ws_a, cube).await;
+    send(&mut ws_a, ClientEvent::SetObjectCollections(SetObjectCollectionsPayload {
+        object_id: cube,
+        collection_ids: vec![props],
+    })).await;
+    recv(&mut ws_a).await; // ObjectCollectionsUpdated
+    send(&mut ws_a, ClientEvent::DeleteCollection(DeleteCollectionPayload { collection_id: props })).await;
+    recv(&mut ws_a).await; // CollectionDeleted
+
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::CollectionCreated(p) => assert_eq!(p.collection.name, "Props"),
+        other => panic!("expected CollectionCreated, got {:?}", other),
+    }
+    match recv(&mut ws_a).await {
+        ServerEvent::HistoryApplied(p) => {
+            assert!(p.diff.updated.iter().any(|object| object.object_id == cube && object.collections == [props]));
+        }
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+    {
+        let session = state.sessions.get("coll-undo").unwrap();
+        assert!(session.collections.read().unwrap().contains_key(&props));
+        assert_eq!(session.objects.read().unwrap()[&cube].collections, [props]);
+    }
+
+    send(&mut ws_a, ClientEvent::Redo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::HistoryApplied(p) => {
+            assert!(p.diff.updated.iter().any(|object| object.object_id == cube && object.collections.is_empty()));
+        }
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+    match recv(&mut ws_a).await {
+        ServerEvent::CollectionDeleted(p) => assert_eq!(p.collection_id, props),
+        other => panic!("expected CollectionDeleted, got {:?}", other),
+    }
+    let session = state.sessions.get("coll-undo").unwrap();
+    assert!(session.collections.read().unwrap().is_empty());
+    assert!(session.objects.read().unwrap()[&cube].collections.is_empty());
+}
+
+/// Objects brought back from the trash, a checkpoint or an undo leave collections deleted in
+/// the meantime.
+#[tokio::test]
+async fn test_restored_objects_drop_deleted_collections() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "coll-restore", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "coll-restore", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let (props, trashed, undone) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    create_collection(&mut ws_a, props, "Props").await;
+    recv(&mut ws_b).await; // CollectionCreated
+    for cube in [trashed, undone] {
+        create_cube(&mut ws_a, cube).await;
+        recv(&mut ws_b).await; // ObjectCreated
+        send(&mut ws_a, ClientEvent::SetObjectCollections(SetObjectCollectionsPayload {
+            object_id: cube,
+            collection_ids: vec![props],
+        })).await;
+        recv(&mut ws_a).await; // ObjectCollectionsUpdated
+        recv(&mut ws_b).await; // ObjectCollectionsUpdated
+    }
+    send(&mut ws_a, ClientEvent::CreateCheckpoint(CreateCheckpointPayload { name: "in props".to_string() })).await;
+    let checkpoint_id = match recv(&mut ws_a).await {
+        ServerEvent::CheckpointCreated(info) => info.checkpoint_id,
+        other => panic!("expected CheckpointCreated, got {:?}", other),
+    };
+    recv(&mut ws_b).await; // CheckpointCreated
+    for cube in [trashed, undone] {
+        send(&mut ws_a, ClientEvent::DeleteObject(DeleteObjectPayload { object_id: cube, delete_children: false })).await;
+        recv(&mut ws_a).await; // ObjectDeleted
+        recv(&mut ws_b).await; // ObjectDeleted
+    }
+    send(&mut ws_b, ClientEvent::DeleteCollection(DeleteCollectionPayload { collection_id: props })).await;
+    recv(&mut ws_a).await; // CollectionDeleted
+    recv(&mut ws_b).await; // CollectionDeleted
+
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::HistoryApplied(p) => {
+            assert!(p.diff.created.iter().any(|object| object.object_id == undone && object.collections.is_empty()));
+        }
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+    recv(&mut ws_b).await; // HistoryApplied
+
+    send(&mut ws_a, ClientEvent::RestoreObject(RestoreObjectPayload { object_id: trashed })).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::ObjectCreated(p) => {
+            assert_eq!(p.object.object_id, trashed);
+            assert!(p.object.collections.is_empty());
+        }
+        other => panic!("expected ObjectCreated, got {:?}", other),
+    }
+    recv(&mut ws_b).await; // ObjectCreated
+
+    send(&mut ws_a, ClientEvent::RestoreCheckpoint(RestoreCheckpointPayload { checkpoint_id })).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::CheckpointRestored(p) => {
+            assert!(p.diff.created.iter().chain(&p.diff.updated).all(|object| object.collections.is_empty()));
+        },
+        other => panic!("expected CheckpointRestored, got {:?}", other),
+    }
+
+    let session = state.sessions.get("coll-restore").unwrap();
+    let objects = session.objects.read().unwrap();
+    assert!(objects[&trashed].collections.is_empty() && objects[&undone].collections.is_empty());
+    assert!(session.collections.read().unwrap().is_empty());
+}
+
+/// Collections and membership are journaled and come back on recovery and with a FullStateSync.
+#[tokio::test]
+async fn test_collections_persist() {
+    let data_dir = temp_data_dir();
+    let config = ServerConfig { data_dir: Some(data_dir.clone()), ..ServerConfig::default() };
+    let (url, _addr, state) = start_admin_test_server_with_config(config).await;
+    let dir = session_dir(&data_dir, "coll-persist");
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "coll-persist", "Alice").await;
+
+    let (props, lights, cube) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    create_collection(&mut ws_a, props, "Props").await;
+    let session = state.sessions.get("coll-persist").unwrap().clone();
+    snapshot_session(&session, &dir, None).unwrap();
+
+    create_collection(&mut ws_a, lights, "Lights").await;
+    create_cube(&mut ws_a, cube).await;
+    send(&mut ws_a, ClientEvent::SetObjectCollections(SetObjectCollectionsPayload {
+        object_id: cube,
+        collection_ids: vec![lights],
+    })).await;
+    recv(&mut ws_a).await; // ObjectCollectionsUpdated
+    send(&mut ws_a, ClientEvent::DeleteCollection(DeleteCollectionPayload { collection_id: props })).await;
+    recv(&mut ws_a).await; // CollectionDeleted
+
+    let recovered = recover(&dir, None).unwrap().unwrap();
+    assert_eq!(recovered.collections.keys().collect::<Vec<_>>(), [&lights]);
+    assert_eq!(recovered.collections[&lights].name, "Lights");
+    assert_eq!(recovered.objects[&cube].collections, [lights]);
+
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    send(&mut ws_b, ClientEvent::JoinSession(JoinSessionPayload {
+        session_id: "coll-persist".to_string(),
+        display_name: "Bob".to_string(),
+        password: TEST_PASSWORD.to_string(),
+    })).await;
+    match recv(&mut ws_b).await {
+        ServerEvent::FullStateSync(p) => {
+            assert_eq!(p.session.collections.len(), 1);
+            assert_eq!(p.session.objects[&cube].collections, [lights]);
+        }
+        other => panic!("expected FullStateSync, got {:?}", other),
+    }
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
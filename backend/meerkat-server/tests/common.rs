@@ -1,8 +1,12 @@
-use std::sync::Arc;
+// Shared by every integration test binary; not every binary uses every helper.
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
 
 use axum::{routing::any, Router};
-use dashmap::DashMap;
 use futures_util::{SinkExt, StreamExt};
+use secrecy::SecretString;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::time::{timeout, Duration};
 use tokio_tungstenite::{
@@ -13,11 +17,14 @@ use uuid::Uuid;
 
 use meerkat_server::{
     messages::{ClientEvent, CreateObjectPayload, CreateSessionPayload, JoinSessionPayload, ServerEvent},
-    types::{AppState, ObjectType, Transform},
+    admin,
+    config::ServerConfig,
+    types::{AppState, ObjectProperties, ObjectType, SceneObject, Transform},
     websocket::tcp_socket_upgrade,
 };
 
 pub const TEST_PASSWORD: &str = "testpassword123";
+pub const TEST_ADMIN_TOKEN: &str = "test-admin-token";
 
 pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
@@ -26,13 +33,7 @@ pub async fn start_test_server() -> String {
 }
 
 pub async fn start_test_server_with_state() -> (String, AppState) {
-    let state = AppState {
-        sessions: Arc::new(DashMap::new()),
-        connections: Arc::new(DashMap::new()),
-        connection_meta: Arc::new(DashMap::new()),
-        connection_backpressure: Arc::new(DashMap::new()),
-        session_connections: Arc::new(DashMap::new()),
-    };
+    let state = AppState::new();
     let app = Router::new().route("/ws", any(tcp_socket_upgrade)).with_state(state.clone());
     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
     let port = listener.local_addr().unwrap().port();
@@ -42,6 +43,72 @@ pub async fn start_test_server_with_state() -> (String, AppState) {
     (format!("ws://127.0.0.1:{}/ws", port), state)
 }
 
+/// Like `start_test_server_with_state`, with the admin API mounted. Returns (ws url, http host:port, state).
+pub async fn start_admin_test_server() -> (String, String, AppState) {
+    start_admin_test_server_with_config(ServerConfig::default()).await
+}
+
+pub async fn start_admin_test_server_with_config(config: ServerConfig) -> (String, String, AppState) {
+    let state = AppState::with_config(config);
+    let app = Router::new()
+        .route("/ws", any(tcp_socket_upgrade))
+        .with_state(state.clone())
+        .merge(admin::router(state.clone(), SecretString::from(TEST_ADMIN_TOKEN)));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (format!("ws://{}/ws", addr), addr, state)
+}
+
+/// Minimal HTTP/1.1 client for the admin API. Returns (status, body).
+pub async fn http_request(addr: &str, method: &str, path: &str, token: Option<&str>, body: &[u8]) -> (u16, Vec<u8>) {
+    http_request_with_headers(addr, method, path, token, &[], body).await
+}
+
+pub async fn http_request_with_headers(
+    addr: &str,
+    method: &str,
+    path: &str,
+    token: Option<&str>,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> (u16, Vec<u8>) {
+    let mut stream = TcpStream::connect(addr).await.expect("connect failed");
+    let mut request = format!("{method} {path} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\nContent-Length: {}\r\n", body.len());
+    if let Some(token) = token {
+        request.push_str(&format!("Authorization: Bearer {token}\r\n"));
+    }
+    for (name, value) in headers {
+        request.push_str(&format!("{name}: {value}\r\n"));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await.unwrap();
+    stream.write_all(body).await.unwrap();
+    let mut response = Vec::new();
+    timeout(Duration::from_secs(5), stream.read_to_end(&mut response))
+        .await
+        .expect("http response timed out")
+        .unwrap();
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n").expect("malformed HTTP response");
+    let head = String::from_utf8_lossy(&response[..split]).to_string();
+    let status = head.split_whitespace().nth(1).and_then(|s| s.parse().ok()).expect("missing status");
+    (status, response[split + 4..].to_vec())
+}
+
+pub async fn http_get(addr: &str, path: &str, token: Option<&str>) -> (u16, String) {
+    let (status, body) = http_request(addr, "GET", path, token, &[]).await;
+    (status, String::from_utf8(body).expect("non-UTF-8 response body"))
+}
+
+/// Fresh empty directory under the system temp dir for tests that persist sessions.
+pub fn temp_data_dir() -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("meerkat-test-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
 pub async fn send(ws: &mut WsStream, event: ClientEvent) {
     // Server expects MessageEnvelope format: {event_type, timestamp, source_user_id, payload}
     // Serialize the ClientEvent to get {event_type, payload}, then inject envelope fields.
@@ -85,6 +152,7 @@ pub fn cube_payload(object_id: Uuid) -> CreateObjectPayload {
         object_type: ObjectType::Cube,
         asset_id: None,
         asset_library: None,
+        parent_id: None,
         transform: Transform { position: [0.0; 3], rotation: [0.0; 3], scale: [1.0; 3] },
         properties: None,
     }
@@ -97,12 +165,37 @@ pub fn asset_payload(object_id: Uuid, name : &str,  asset_id : Option<String>,
         object_type: ObjectType::AssetRef,
         asset_id,
         asset_library,
+        parent_id: None,
         transform: Transform { position: [0.0; 3], rotation: [0.0; 3], scale: [1.0; 3] },
         properties: None,
 
     }
 }
 
+/// An object as the server stores it, at the origin.
+pub fn scene_object(name: &str, object_type: ObjectType, properties: Option<ObjectProperties>) -> SceneObject {
+    SceneObject {
+        object_id: Uuid::new_v4(),
+        name: name.to_string(),
+        object_type,
+        asset_id: None,
+        asset_library: None,
+        parent_id: None,
+        transform: Transform { position: [0.0; 3], rotation: [0.0; 3], scale: [1.0; 3] },
+        properties,
+        collections: Vec::new(),
+        custom_properties: BTreeMap::new(),
+        extra_properties: serde_json::Map::new(),
+        material_slots: Vec::new(),
+        mesh: None,
+        mesh_version: 0,
+        modifiers: Vec::new(),
+        created_by: Uuid::nil(),
+        last_updated_by: Uuid::nil(),
+        last_updated_at: 0,
+    }
+}
+
 pub fn extract_object_id(event: ServerEvent) -> Uuid {
     match event {
         ServerEvent::ObjectCreated(p) => p.object.object_id,
@@ -53,6 +53,7 @@ async fn test_duplicate_object_id_is_rejected() {
             object_type: ObjectType::Cube,
             asset_id: None,
             asset_library: None,
+            parent_id: None,
             transform: Transform {
                 position: [0.0, 0.0, 0.0],
                 rotation: [0.0, 0.0, 0.0],
@@ -77,6 +78,7 @@ async fn test_duplicate_object_id_is_rejected() {
             object_type: ObjectType::Cube,
             asset_id: None,
             asset_library: None,
+            parent_id: None,
             transform: Transform {
                 position: [3.0, 3.0, 3.0],
                 rotation: [0.0, 0.0, 0.0],
@@ -100,7 +102,7 @@ async fn test_duplicate_object_id_is_rejected() {
     );
 
     // Canonical object should still be the original one.
-    send(&mut ws_a, ClientEvent::RequestStateSync).await;
+    send(&mut ws_a, ClientEvent::RequestStateSync(None)).await;
     let sync_after = recv(&mut ws_a).await;
     match sync_after {
         ServerEvent::FullStateSync(p) => {
@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::json;
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    gltf::{self, Document},
+    messages::{ClientEvent, JoinSessionPayload, RemoveCustomPropertyPayload, ServerEvent, SetCustomPropertyPayload},
+    types::{CustomValue, ObjectType, MAX_CUSTOM_PROPERTIES, MAX_CUSTOM_STRING_LEN},
+    usd,
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, join_session, recv, scene_object, send, start_test_server_with_state, try_recv,
+    WsStream, TEST_PASSWORD,
+};
+
+fn set(object_id: Uuid, key: &str, value: CustomValue) -> ClientEvent {
+    ClientEvent::SetCustomProperty(SetCustomPropertyPayload { object_id, key: key.to_string(), value })
+}
+
+async fn create_cube(ws: &mut WsStream, object_id: Uuid) {
+    send(ws, ClientEvent::CreateObject(cube_payload(object_id))).await;
+    match recv(ws).await {
+        ServerEvent::ObjectCreated(p) => assert_eq!(p.object.object_id, object_id),
+        other => panic!("expected ObjectCreated, got {:?}", other),
+    }
+}
+
+fn sample_properties() -> BTreeMap<String, CustomValue> {
+    BTreeMap::from([
+        ("shot".to_string(), CustomValue::String("010".to_string())),
+        ("lod_bias".to_string(), CustomValue::Number(1.5)),
+        ("approved".to_string(), CustomValue::Bool(true)),
+        ("tint".to_string(), CustomValue::Vector(vec![1.0, 0.5, 0.25])),
+        ("tags".to_string(), CustomValue::StringList(vec!["hero".to_string(), "prop".to_string()])),
+    ])
+}
+
+/// Setting and removing a custom property reaches every client and lands on the object.
+#[tokio::test]
+async fn test_set_and_remove_custom_property() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "custom-set", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "custom-set", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let cube = Uuid::new_v4();
+    create_cube(&mut ws_a, cube).await;
+    recv(&mut ws_b).await; // ObjectCreated
+
+    for (key, value) in sample_properties() {
+        send(&mut ws_a, set(cube, &key, value.clone())).await;
+        for ws in [&mut ws_a, &mut ws_b] {
+            match recv(ws).await {
+                ServerEvent::CustomPropertyUpdated(p) => {
+                    assert_eq!((p.object_id, p.key.as_str()), (cube, key.as_str()));
+                    assert_eq!(p.value.as_ref(), Some(&value));
+                }
+                other => panic!("expected CustomPropertyUpdated, got {:?}", other),
+            }
+        }
+    }
+    {
+        let session = state.sessions.get("custom-set").unwrap();
+        assert_eq!(session.objects.read().unwrap()[&cube].custom_properties, sample_properties());
+    }
+
+    send(&mut ws_b, ClientEvent::RemoveCustomProperty(RemoveCustomPropertyPayload { object_id: cube, key: "shot".to_string() })).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::CustomPropertyUpdated(p) => assert_eq!((p.key.as_str(), p.value), ("shot", None)),
+            other => panic!("expected CustomPropertyUpdated, got {:?}", other),
+        }
+    }
+
+    // Removing it again changes nothing and is not broadcast.
+    send(&mut ws_b, ClientEvent::RemoveCustomProperty(RemoveCustomPropertyPayload { object_id: cube, key: "shot".to_string() })).await;
+    assert!(try_recv(&mut ws_a).await.is_none());
+
+    // A late joiner gets them with the object.
+    let (mut ws_c, _) = connect_async(&url).await.unwrap();
+    send(&mut ws_c, ClientEvent::JoinSession(JoinSessionPayload {
+        session_id: "custom-set".to_string(),
+        display_name: "Carol".to_string(),
+        password: TEST_PASSWORD.to_string(),
+    })).await;
+    match recv(&mut ws_c).await {
+        ServerEvent::FullStateSync(p) => {
+            let custom = &p.session.objects[&cube].custom_properties;
+            assert_eq!(custom.len(), 4);
+            assert_eq!(custom["tags"], CustomValue::StringList(vec!["hero".to_string(), "prop".to_string()]));
+        }
+        other => panic!("expected FullStateSync, got {:?}", other),
+    }
+}
+
+/// Bad keys, oversized values and keys past the limit are refused without a broadcast.
+#[tokio::test]
+async fn test_invalid_custom_properties_are_rejected() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "custom-reject", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "custom-reject", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let cube = Uuid::new_v4();
+    create_cube(&mut ws_a, cube).await;
+    recv(&mut ws_b).await; // ObjectCreated
+
+    let rejected = [
+        set(cube, "", CustomValue::Bool(true)),
+        set(cube, "2nd_pass", CustomValue::Bool(true)),
+        set(cube, "shot name", CustomValue::Bool(true)),
+        set(cube, "meerkat_id", CustomValue::String("spoofed".to_string())),
+        set(cube, &"k".repeat(64), CustomValue::Bool(true)),
+        set(cube, "notes", CustomValue::String("x".repeat(MAX_CUSTOM_STRING_LEN + 1))),
+        set(cube, "tint", CustomValue::Vector(Vec::new())),
+        set(cube, "tint", CustomValue::Vector(vec![0.0; 17])),
+        set(cube, "tags", CustomValue::StringList(vec![String::new(); 65])),
+    ];
+    for event in rejected {
+        send(&mut ws_a, event).await;
+        match recv(&mut ws_a).await {
+            ServerEvent::Error(e) => assert_eq!(e.code, "INVALID_CUSTOM_PROPERTY"),
+            other => panic!("expected INVALID_CUSTOM_PROPERTY, got {:?}", other),
+        }
+    }
+
+    {
+        let session = state.sessions.get("custom-reject").unwrap();
+        let mut objects = session.objects.write().unwrap();
+        let object = objects.get_mut(&cube).unwrap();
+        for i in 0..MAX_CUSTOM_PROPERTIES {
+            object.custom_properties.insert(format!("key_{i}"), CustomValue::Number(i as f64));
+        }
+    }
+    send(&mut ws_a, set(cube, "one_more", CustomValue::Number(1.0))).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "CUSTOM_PROPERTY_LIMIT"),
+        other => panic!("expected CUSTOM_PROPERTY_LIMIT, got {:?}", other),
+    }
+    assert!(try_recv(&mut ws_b).await.is_none(), "rejected changes are not broadcast");
+
+    // Replacing an existing key is still fine at the limit.
+    send(&mut ws_a, set(cube, "key_0", CustomValue::Number(42.0))).await;
+    assert!(matches!(recv(&mut ws_a).await, ServerEvent::CustomPropertyUpdated(_)));
+}
+
+/// Custom property edits are undoable like any other object edit.
+#[tokio::test]
+async fn test_undo_custom_property() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "custom-undo", "Alice").await;
+
+    let cube = Uuid::new_v4();
+    create_cube(&mut ws_a, cube).await;
+    send(&mut ws_a, set(cube, "status", CustomValue::String("wip".to_string()))).await;
+    recv(&mut ws_a).await; // CustomPropertyUpdated
+    send(&mut ws_a, set(cube, "status", CustomValue::String("approved".to_string()))).await;
+    recv(&mut ws_a).await; // CustomPropertyUpdated
+
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::HistoryApplied(p) => {
+            assert_eq!(p.diff.updated[0].custom_properties["status"], CustomValue::String("wip".to_string()));
+        }
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+    let session = state.sessions.get("custom-undo").unwrap();
+    assert_eq!(session.objects.read().unwrap()[&cube].custom_properties["status"], CustomValue::String("wip".to_string()));
+}
+
+/// glTF and USD exports keep custom properties, and imports pick up Blender's own.
+#[test]
+fn test_exports_round_trip_custom_properties() {
+    let mut object = scene_object("Crate", ObjectType::Cube, None);
+    object.custom_properties = sample_properties();
+    let objects = HashMap::from([(object.object_id, object.clone())]);
+
+    let exported = gltf::export_objects("custom", &objects);
+    let imported = gltf::import_objects(&exported.document, 0);
+    assert_eq!(imported[0].custom_properties, sample_properties());
+
+    let layer = usd::export_objects("custom", &objects);
+    let text = layer.to_usda();
+    assert!(text.contains("custom string userProperties:shot = \"010\""), "{text}");
+    let imported = usd::import_objects(&usd::Layer::parse(&text).unwrap(), 0);
+    assert_eq!(imported[0].custom_properties, sample_properties());
+
+    // Blender writes custom properties into extras directly; entries that are not valid
+    // custom properties are skipped.
+    let document: Document = serde_json::from_value(json!({
+        "asset": {"version": "2.0"},
+        "scene": 0,
+        "scenes": [{"nodes": [0]}],
+        "nodes": [{
+            "name": "Shot Camera",
+            "camera": 0,
+            "extras": {"shot": "010", "approved": true, "offset": [1, 2, 3], "meerkat_id": "x", "nested": {"a": 1}},
+        }],
+        "cameras": [{"type": "perspective", "perspective": {"yfov": 0.8, "znear": 0.1}}],
+    }))
+    .unwrap();
+    let imported = gltf::import_objects(&document, 0);
+    assert_eq!(imported[0].custom_properties, BTreeMap::from([
+        ("approved".to_string(), CustomValue::Bool(true)),
+        ("offset".to_string(), CustomValue::Vector(vec![1.0, 2.0, 3.0])),
+        ("shot".to_string(), CustomValue::String("010".to_string())),
+    ]));
+
+    let layer = usd::Layer::parse(r#"#usda 1.0
+
+def Xform "World"
+{
+    def Camera "Shot"
+    {
+        custom bool userProperties:approved = 1
+        custom double3 userProperties:offset = (1, 2, 3)
+        custom token[] userProperties:tags = ["hero", "prop"]
+        custom string userProperties:meerkat_id = "x"
+    }
+}
+"#)
+    .unwrap();
+    let imported = usd::import_objects(&layer, 0);
+    assert_eq!(imported[0].custom_properties, BTreeMap::from([
+        ("approved".to_string(), CustomValue::Bool(true)),
+        ("offset".to_string(), CustomValue::Vector(vec![1.0, 2.0, 3.0])),
+        ("tags".to_string(), CustomValue::StringList(vec!["hero".to_string(), "prop".to_string()])),
+    ]));
+}
@@ -0,0 +1,136 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::json;
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    diff::{diff_objects, objects_from_json, SceneDiff},
+    messages::{ClientEvent, CreateCheckpointPayload, DeleteObjectPayload, UpdateNamePayload, UpdateTransformPayload},
+    types::{ObjectType, SceneObject, Transform},
+};
+
+mod common;
+
+use common::{create_session, cube_payload, http_get, recv, send, start_admin_test_server, TEST_ADMIN_TOKEN};
+
+fn cube(object_id: Uuid, name: &str, position: [f64; 3]) -> SceneObject {
+    SceneObject {
+        object_id,
+        name: name.to_string(),
+        object_type: ObjectType::Cube,
+        asset_id: None,
+        asset_library: None,
+        parent_id: None,
+        transform: Transform { position, rotation: [0.0; 3], scale: [1.0; 3] },
+        properties: None,
+        collections: Vec::new(),
+        custom_properties: BTreeMap::new(),
+        extra_properties: serde_json::Map::new(),
+        material_slots: Vec::new(),
+        mesh: None,
+        mesh_version: 0,
+        modifiers: Vec::new(),
+        created_by: Uuid::nil(),
+        last_updated_by: Uuid::nil(),
+        last_updated_at: 0,
+    }
+}
+
+#[test]
+fn diff_reports_per_field_changes() {
+    let kept = Uuid::new_v4();
+    let drifted = Uuid::new_v4();
+    let removed = Uuid::new_v4();
+    let added = Uuid::new_v4();
+
+    let before: HashMap<Uuid, SceneObject> = [
+        (kept, cube(kept, "Crate", [0.0, 0.0, 0.0])),
+        (drifted, cube(drifted, "Pillar", [1.0, 2.0, 3.0])),
+        (removed, cube(removed, "Barrel", [0.0; 3])),
+    ]
+    .into();
+    let mut after = before.clone();
+    after.remove(&removed);
+    after.insert(added, cube(added, "Lamp", [0.0; 3]));
+    let crate_after = after.get_mut(&kept).unwrap();
+    crate_after.name = "Crate.001".to_string();
+    crate_after.transform.position[2] = 4.5;
+    crate_after.asset_id = Some("props/crate".to_string());
+    crate_after.last_updated_by = Uuid::new_v4();
+    crate_after.last_updated_at = 99;
+    // f32 round-trip noise and attribution alone are not changes.
+    let pillar_after = after.get_mut(&drifted).unwrap();
+    pillar_after.transform.position[0] = 1.000_000_1;
+    pillar_after.last_updated_at = 42;
+
+    let diff = diff_objects(&before, &after);
+    assert_eq!(diff.added.iter().map(|o| o.object_id).collect::<Vec<_>>(), vec![added]);
+    assert_eq!(diff.removed.iter().map(|o| o.object_id).collect::<Vec<_>>(), vec![removed]);
+    assert_eq!(diff.changed.len(), 1);
+    let changed = &diff.changed[0];
+    assert_eq!(changed.object_id, kept);
+    let paths: Vec<&str> = changed.fields.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, vec!["asset_id", "name", "transform.position.z"]);
+    assert_eq!(changed.fields[0].before, json!(null));
+    assert_eq!(changed.fields[1].after, json!("Crate.001"));
+
+    let summary = diff.summary();
+    assert!(summary.starts_with("1 added, 1 removed, 1 changed"));
+    assert!(summary.contains("transform.position.z: 0.0 -> 4.5"), "{summary}");
+
+    let wrapped = json!({ "session": { "session_id": "s", "objects": before } }).to_string();
+    assert_eq!(objects_from_json(&wrapped).unwrap().len(), 3);
+}
+
+/// The admin endpoint compares a checkpoint with the live scene, as JSON or text, behind the token.
+#[tokio::test]
+async fn test_admin_diff_against_checkpoint() {
+    let (url, addr, _state) = start_admin_test_server().await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, "diff-layout", "Alice").await;
+
+    let moved = Uuid::new_v4();
+    let deleted = Uuid::new_v4();
+    for id in [moved, deleted] {
+        send(&mut ws, ClientEvent::CreateObject(cube_payload(id))).await;
+        recv(&mut ws).await; // ObjectCreated
+    }
+    send(&mut ws, ClientEvent::CreateCheckpoint(CreateCheckpointPayload { name: "blocking".to_string() })).await;
+    recv(&mut ws).await; // CheckpointCreated
+
+    send(&mut ws, ClientEvent::UpdateTransform(UpdateTransformPayload {
+        object_id: moved,
+        transform: Transform { position: [0.0, 2.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+    })).await;
+    recv(&mut ws).await; // TransformUpdated
+    send(&mut ws, ClientEvent::UpdateName(UpdateNamePayload { object_id: moved, name: "Hero Crate".to_string() })).await;
+    recv(&mut ws).await; // NameUpdated
+    send(&mut ws, ClientEvent::DeleteObject(DeleteObjectPayload { object_id: deleted, delete_children: false })).await;
+    recv(&mut ws).await; // ObjectDeleted
+
+    let path = "/admin/sessions/diff-layout/diff?from=blocking";
+    let (status, _) = http_get(&addr, path, None).await;
+    assert_eq!(status, 401);
+    let (status, _) = http_get(&addr, path, Some("wrong-token")).await;
+    assert_eq!(status, 401);
+
+    let (status, body) = http_get(&addr, path, Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, 200, "{body}");
+    let diff: SceneDiff = serde_json::from_str(&body).unwrap();
+    assert!(diff.added.is_empty());
+    assert_eq!(diff.removed[0].object_id, deleted);
+    assert_eq!(diff.changed[0].object_id, moved);
+    let paths: Vec<&str> = diff.changed[0].fields.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, vec!["name", "transform.position.y"]);
+
+    let (status, text) = http_get(&addr, &format!("{path}&format=text"), Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, 200);
+    assert!(text.contains("~ Hero Crate"), "{text}");
+    assert!(text.contains("name: \"Cube\" -> \"Hero Crate\""), "{text}");
+
+    let (status, _) = http_get(&addr, "/admin/sessions/diff-layout/diff?from=missing", Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, 404);
+    let (status, _) = http_get(&addr, "/admin/sessions/nope/diff?from=current", Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, 404);
+}
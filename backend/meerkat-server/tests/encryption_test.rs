@@ -0,0 +1,198 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    audit::{self, AUDIT_FILE_NAME},
+    config::ServerConfig,
+    encryption::{is_key_error, Keyring, ENCRYPTION_KEY_ENV, SEALED_MAGIC},
+    journal::JOURNAL_FILE_NAME,
+    messages::{ClientEvent, CreateCheckpointPayload},
+    recording::{read_recording, ENCRYPTED_RECORDING_MAGIC, RECORDINGS_DIR_NAME},
+    rewrap::rewrap_data_dir,
+    snapshot::{recover, restore_sessions, snapshot_session, SNAPSHOT_FILE_NAME},
+    storage::session_dir,
+    types::AppState,
+};
+
+mod common;
+
+use common::{create_session, cube_payload, recv, send, start_admin_test_server_with_config, temp_data_dir, WsStream};
+
+const SECRET_NAME: &str = "Nightingale_Hero_Prop";
+
+fn new_keyring() -> (String, Keyring) {
+    let key = Keyring::generate_key();
+    let keyring = Keyring::parse(&key).unwrap();
+    (key, keyring)
+}
+
+fn files_under(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(files_under(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Start a persisting server, create `session_id` holding one secretly named cube, and leave
+/// the connection open so the session is not reclaimed.
+async fn persist_session(data_dir: &Path, session_id: &str, encryption: Option<Keyring>) -> (WsStream, AppState) {
+    let config = ServerConfig { data_dir: Some(data_dir.to_path_buf()), record_sessions: true, encryption, ..ServerConfig::default() };
+    let (url, _addr, state) = start_admin_test_server_with_config(config).await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, session_id, "Alice").await;
+    let mut cube = cube_payload(Uuid::new_v4());
+    cube.name = SECRET_NAME.to_string();
+    send(&mut ws, ClientEvent::CreateObject(cube)).await;
+    recv(&mut ws).await; // ObjectCreated
+    (ws, state)
+}
+
+/// Nothing a session holds reaches the disk in plaintext, and only the right key reads it back.
+#[tokio::test]
+async fn test_persisted_session_is_encrypted() {
+    let data_dir = temp_data_dir();
+    let (_, keyring) = new_keyring();
+    let (mut ws, state) = persist_session(&data_dir, "nda", Some(keyring.clone())).await;
+    let dir = session_dir(&data_dir, "nda");
+    send(&mut ws, ClientEvent::CreateCheckpoint(CreateCheckpointPayload { name: SECRET_NAME.to_string() })).await;
+    recv(&mut ws).await; // CheckpointCreated
+
+    // Snapshot the scene, then journal one more edit on top, and push out the recording.
+    let session = state.sessions.get("nda").unwrap().clone();
+    assert!(snapshot_session(&session, &dir, Some(&keyring)).unwrap());
+    send(&mut ws, ClientEvent::CreateObject(cube_payload(Uuid::new_v4()))).await;
+    recv(&mut ws).await; // ObjectCreated
+    session.lock_recorder().as_mut().unwrap().flush_block().unwrap();
+
+    let files = files_under(&dir);
+    assert!(files.len() >= 4, "snapshot, journal, audit log and recording: {files:?}");
+    for path in &files {
+        let bytes = std::fs::read(path).unwrap();
+        assert!(
+            !bytes.windows(SECRET_NAME.len()).any(|window| window == SECRET_NAME.as_bytes()),
+            "{} holds plaintext scene data",
+            path.display()
+        );
+    }
+    assert!(std::fs::read(dir.join(SNAPSHOT_FILE_NAME)).unwrap().starts_with(SEALED_MAGIC));
+
+    let recovered = recover(&dir, Some(&keyring)).unwrap().unwrap();
+    assert_eq!(recovered.objects.len(), 2);
+    assert!(recovered.objects.values().any(|object| object.name == SECRET_NAME));
+    assert_eq!(recovered.checkpoints[0].name, SECRET_NAME);
+    assert_eq!(audit::read_file(&dir.join(AUDIT_FILE_NAME), Some(&keyring)).unwrap().len(), 2);
+    let recording = files.iter().find(|path| path.starts_with(dir.join(RECORDINGS_DIR_NAME))).unwrap();
+    assert!(std::fs::read(recording).unwrap().starts_with(ENCRYPTED_RECORDING_MAGIC));
+    assert!(read_recording(recording, Some(&keyring)).unwrap().len() > 1);
+
+    // The wrong key and no key both fail loudly, naming what is missing.
+    let (_, wrong) = new_keyring();
+    let err = recover(&dir, Some(&wrong)).unwrap_err();
+    assert!(is_key_error(&err));
+    assert!(err.to_string().contains(&keyring.key_ids()[0]), "{err}");
+    let err = recover(&dir, None).unwrap_err();
+    assert!(is_key_error(&err));
+    assert!(err.to_string().contains(ENCRYPTION_KEY_ENV), "{err}");
+    assert!(read_recording(recording, None).is_err());
+
+    let wrong_state = AppState::with_config(ServerConfig {
+        data_dir: Some(data_dir.clone()),
+        encryption: Some(wrong),
+        ..ServerConfig::default()
+    });
+    let err = restore_sessions(&wrong_state).unwrap_err();
+    assert!(err.to_string().contains("not configured"), "{err}");
+    assert!(wrong_state.sessions.is_empty());
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
+
+/// Turning encryption on takes an explicit rewrap of the plaintext already on disk; rotating
+/// the key re-encrypts a session's state when the server restarts, and a rewrap moves audit
+/// logs and recordings over too, after which the previous key is no longer needed.
+#[tokio::test]
+async fn test_enabling_and_rotating_keys() {
+    let data_dir = temp_data_dir();
+    let dir = session_dir(&data_dir, "rotated");
+    let (_ws, live) = persist_session(&data_dir, "rotated", None).await;
+    assert!(std::fs::read(dir.join(SNAPSHOT_FILE_NAME)).unwrap().starts_with(b"{"));
+    let session = live.sessions.get("rotated").unwrap().clone();
+    session.lock_recorder().as_mut().unwrap().flush_block().unwrap();
+
+    let restart = |encryption: Keyring| {
+        let state = AppState::with_config(ServerConfig {
+            data_dir: Some(data_dir.clone()),
+            encryption: Some(encryption),
+            ..ServerConfig::default()
+        });
+        restore_sessions(&state).map(|restored| (restored, state))
+    };
+
+    let (old_key, old) = new_keyring();
+    let Err(err) = restart(old.clone()) else {
+        panic!("plaintext data was read with a key configured");
+    };
+    assert!(err.to_string().contains("rewrap"), "{err}");
+    let summary = rewrap_data_dir(&data_dir, &old).unwrap();
+    assert_eq!((summary.sessions, summary.audit_logs, summary.recordings), (1, 1, 1));
+    assert_eq!(restart(old.clone()).map(|(restored, _)| restored).unwrap(), 1);
+    assert!(std::fs::read(dir.join(SNAPSHOT_FILE_NAME)).unwrap().starts_with(SEALED_MAGIC));
+    assert_eq!(recover(&dir, Some(&old)).unwrap().unwrap().objects.len(), 1);
+
+    let (new_key, new) = new_keyring();
+    let rotated = Keyring::parse(&format!("# current\n{new_key}\n# previous\n{old_key}\n")).unwrap();
+    assert_eq!(rotated.key_ids(), [new.key_ids()[0].clone(), old.key_ids()[0].clone()]);
+    let Ok((_, state)) = restart(rotated.clone()) else {
+        panic!("the rotated keyring reads the session");
+    };
+    assert_eq!(state.sessions.get("rotated").unwrap().objects.read().unwrap().len(), 1);
+    assert!(recover(&dir, Some(&new)).unwrap().is_some(), "the new key alone now reads the session");
+    assert!(recover(&dir, Some(&old)).is_err());
+    drop(state);
+
+    let audit_path = dir.join(AUDIT_FILE_NAME);
+    assert!(audit::read_file(&audit_path, Some(&new)).is_err(), "audit lines still need the old key");
+    rewrap_data_dir(&data_dir, &rotated).unwrap();
+    assert_eq!(audit::read_file(&audit_path, Some(&new)).unwrap().len(), 1);
+    let recording = files_under(&dir.join(RECORDINGS_DIR_NAME)).remove(0);
+    assert!(read_recording(&recording, Some(&new)).unwrap().len() > 1);
+    assert!(read_recording(&recording, Some(&old)).is_err());
+
+    assert!(Keyring::parse("not-a-key").is_err());
+    assert!(Keyring::parse("# nothing here\n").is_err());
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
+
+/// With a key configured, a plaintext record planted in an encrypted session's journal or
+/// audit log is refused rather than replayed.
+#[tokio::test]
+async fn test_plaintext_is_refused_once_encrypted() {
+    let data_dir = temp_data_dir();
+    let (_, keyring) = new_keyring();
+    let (_ws, state) = persist_session(&data_dir, "sealed", Some(keyring.clone())).await;
+    let dir = session_dir(&data_dir, "sealed");
+    let session = state.sessions.get("sealed").unwrap().clone();
+    session.lock_recorder().as_mut().unwrap().flush_block().unwrap();
+    assert!(recover(&dir, Some(&keyring)).is_ok());
+
+    let mut journal = std::fs::OpenOptions::new().append(true).open(dir.join(JOURNAL_FILE_NAME)).unwrap();
+    writeln!(journal, "{{\"seq\":99,\"op\":{{\"Objects\":{{\"version\":99,\"changes\":[]}}}}}}").unwrap();
+    let err = recover(&dir, Some(&keyring)).unwrap_err();
+    assert!(is_key_error(&err), "{err}");
+
+    let mut audit_file = std::fs::OpenOptions::new().append(true).open(dir.join(AUDIT_FILE_NAME)).unwrap();
+    writeln!(audit_file, "{{\"seq\":99}}").unwrap();
+    assert!(is_key_error(&audit::read_file(&dir.join(AUDIT_FILE_NAME), Some(&keyring)).unwrap_err()));
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
@@ -0,0 +1,96 @@
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    messages::{ClientEvent, ForkSessionPayload, JoinSessionPayload, ServerEvent, UpdateTransformPayload},
+    types::Transform,
+};
+
+mod common;
+
+use common::{create_session, cube_payload, join_session, recv, send, start_test_server_with_state, try_recv};
+
+const FORK_PASSWORD: &str = "alternate-blocking";
+
+/// The fork starts with the source objects (ids and attribution intact), has its own password,
+/// and edits in either session stay in that session.
+#[tokio::test]
+async fn test_fork_copies_objects_into_independent_session() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "fork-main", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "fork-main", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let bobs_cube = Uuid::new_v4();
+    send(&mut ws_b, ClientEvent::CreateObject(cube_payload(bobs_cube))).await;
+    recv(&mut ws_a).await; // ObjectCreated
+    let bob_id = match recv(&mut ws_b).await {
+        ServerEvent::ObjectCreated(p) => p.created_by,
+        other => panic!("expected ObjectCreated, got {:?}", other),
+    };
+
+    send(&mut ws_a, ClientEvent::ForkSession(ForkSessionPayload {
+        new_session_id: "fork-alt".to_string(),
+        password: FORK_PASSWORD.to_string(),
+    })).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::FullStateSync(p) => {
+            assert_eq!(p.session.session_id, "fork-alt");
+            assert_eq!(p.session.users.len(), 1, "only the forking user moves over");
+            let copied = &p.session.objects[&bobs_cube];
+            assert_eq!(copied.created_by, bob_id, "attribution is preserved");
+        }
+        other => panic!("expected FullStateSync for the fork, got {:?}", other),
+    }
+
+    match recv(&mut ws_b).await {
+        ServerEvent::SessionForked(p) => {
+            assert_eq!(p.source_session_id, "fork-main");
+            assert_eq!(p.new_session_id, "fork-alt");
+            assert_eq!(p.object_count, 1);
+        }
+        other => panic!("expected SessionForked, got {:?}", other),
+    }
+    assert!(matches!(recv(&mut ws_b).await, ServerEvent::UserLeft(_)), "Alice left the main session");
+
+    // The fork has its own password.
+    let (mut ws_c, _) = connect_async(&url).await.unwrap();
+    send(&mut ws_c, ClientEvent::JoinSession(JoinSessionPayload {
+        session_id: "fork-alt".to_string(),
+        display_name: "Carol".to_string(),
+        password: FORK_PASSWORD.to_string(),
+    })).await;
+    assert!(matches!(recv(&mut ws_c).await, ServerEvent::FullStateSync(_)));
+
+    send(&mut ws_c, ClientEvent::UpdateTransform(UpdateTransformPayload {
+        object_id: bobs_cube,
+        transform: Transform { position: [2.0, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+    })).await;
+    recv(&mut ws_c).await; // TransformUpdated
+    assert!(try_recv(&mut ws_b).await.is_none(), "edits in the fork do not reach the main session");
+
+    let main = state.sessions.get("fork-main").unwrap();
+    assert_eq!(main.objects.read().unwrap()[&bobs_cube].transform.position, [0.0, 0.0, 0.0]);
+    let fork = state.sessions.get("fork-alt").unwrap();
+    assert_eq!(fork.objects.read().unwrap()[&bobs_cube].transform.position, [2.0, 0.0, 0.0]);
+}
+
+#[tokio::test]
+async fn test_fork_into_existing_session_is_rejected() {
+    let (url, _state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "fork-taken-a", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_b, "fork-taken-b", "Bob").await;
+
+    send(&mut ws_a, ClientEvent::ForkSession(ForkSessionPayload {
+        new_session_id: "fork-taken-b".to_string(),
+        password: FORK_PASSWORD.to_string(),
+    })).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "SESSION_ALREADY_EXISTS"),
+        other => panic!("expected SESSION_ALREADY_EXISTS, got {:?}", other),
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    admin::{SceneImportSummary, SESSION_PASSWORD_HEADER},
+    gltf::{export_objects, import_objects, Document, Gltf, GltfFormat, GLB_MAGIC},
+    handlers::helpers::IMPORT_USER_ID,
+    messages::{ClientEvent, CreateCheckpointPayload, ServerEvent},
+    primitive_mesh::primitive_mesh,
+    types::{
+        AreaLightProperties, CameraProperties, LensType, ObjectProperties, ObjectType, PointLightProperties, SceneObject,
+        SensorFit, SpotLightProperties, SunLightProperties, Transform,
+    },
+    xform::quat_mul,
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, http_request, http_request_with_headers, join_session, recv, scene_object, send,
+    start_admin_test_server, TEST_ADMIN_TOKEN,
+};
+
+/// `v` rotated by the unit quaternion `q` (`[x, y, z, w]`).
+fn rotate(q: [f64; 4], v: [f64; 3]) -> [f64; 3] {
+    let conjugate = [-q[0], -q[1], -q[2], q[3]];
+    let [x, y, z, _] = quat_mul(quat_mul(q, [v[0], v[1], v[2], 0.0]), conjugate);
+    [x, y, z]
+}
+
+fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert!(
+        actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-6),
+        "expected {expected:?}, got {actual:?}"
+    );
+}
+
+/// The JSON chunk of a `.glb` and its binary chunk.
+fn split_glb(glb: &[u8]) -> (Document, Vec<u8>) {
+    let word = |at: usize| u32::from_le_bytes(glb[at..at + 4].try_into().unwrap()) as usize;
+    assert_eq!(&glb[..4], GLB_MAGIC);
+    assert_eq!(word(4), 2);
+    assert_eq!(word(8), glb.len());
+    let json_len = word(12);
+    let document = serde_json::from_slice(&glb[20..20 + json_len]).unwrap();
+    let bin_start = 20 + json_len;
+    let binary = glb[bin_start + 8..bin_start + 8 + word(bin_start)].to_vec();
+    (document, binary)
+}
+
+fn object_map(objects: Vec<SceneObject>) -> HashMap<Uuid, SceneObject> {
+    objects.into_iter().map(|object| (object.object_id, object)).collect()
+}
+
+/// Every object type lands in glTF as its own kind of node, placed Y-up, with its Meerkat
+/// identity in `extras`.
+#[test]
+fn test_export_maps_each_object_type() {
+    let mut cube = scene_object("Crate", ObjectType::Cube, None);
+    cube.transform.position = [1.0, 2.0, 3.0];
+    let second_cube = scene_object("Crate.001", ObjectType::Cube, None);
+    let circle = scene_object("Marker", ObjectType::Circle, None);
+    let camera = scene_object("Shot Cam", ObjectType::Camera, Some(ObjectProperties::Camera(CameraProperties::default())));
+    let ortho = CameraProperties { lens_type: LensType::Orthographic, sensor_fit: SensorFit::Vertical, ..CameraProperties::default() };
+    let ortho = scene_object("Top Cam", ObjectType::Camera, Some(ObjectProperties::Camera(ortho)));
+    let point = scene_object("Bulb", ObjectType::PointLight, Some(ObjectProperties::PointLight(PointLightProperties::default())));
+    let spot = scene_object("Key", ObjectType::SpotLight, Some(ObjectProperties::SpotLight(SpotLightProperties::default())));
+    let sun = scene_object("Sun", ObjectType::SunLight, Some(ObjectProperties::SunLight(SunLightProperties::default())));
+    let area = scene_object("Softbox", ObjectType::AreaLight, Some(ObjectProperties::AreaLight(AreaLightProperties::default())));
+    let mut tree = scene_object("Oak", ObjectType::AssetRef, None);
+    tree.asset_id = Some("oak_large".to_string());
+    tree.asset_library = Some("Vegetation".to_string());
+    let ids = [cube.object_id, spot.object_id, tree.object_id];
+    let objects = object_map(vec![cube, second_cube, circle, camera, ortho, point, spot, sun, area, tree]);
+
+    let gltf = export_objects("layout", &objects);
+    let (document, binary) = split_glb(&gltf.to_bytes(GltfFormat::Glb).unwrap());
+    assert_eq!(document.asset.version, "2.0");
+    assert_eq!(document.scenes[0].name.as_deref(), Some("layout"));
+    assert_eq!(document.scenes[0].nodes.len(), 10);
+    assert_eq!(document.buffers[0].byte_length, binary.len());
+    assert_eq!(document.extensions_used, ["KHR_lights_punctual"]);
+    let node = |name: &str| document.nodes.iter().find(|node| node.name.as_deref() == Some(name)).unwrap();
+
+    // Two cubes share one generated mesh; its bounds are Blender's default 2 m cube.
+    assert_eq!(document.meshes.len(), 2, "one cube mesh and one circle");
+    assert_eq!(node("Crate").mesh, node("Crate.001").mesh);
+    let cube_mesh = &document.meshes[node("Crate").mesh.unwrap()];
+    let positions = &document.accessors[cube_mesh.primitives[0].attributes["POSITION"]];
+    assert_eq!((positions.min.clone().unwrap(), positions.max.clone().unwrap()), (vec![-1.0; 3], vec![1.0; 3]));
+    assert_eq!(document.meshes[node("Marker").mesh.unwrap()].primitives[0].mode, Some(2), "circles are outlines");
+    for mesh in &document.meshes {
+        let primitive = &mesh.primitives[0];
+        let vertices = document.accessors[primitive.attributes["POSITION"]].count as u32;
+        let indices = &document.accessors[primitive.indices.unwrap()];
+        let view = &document.buffer_views[indices.buffer_view.unwrap()];
+        let bytes = &binary[view.byte_offset..view.byte_offset + view.byte_length];
+        assert!(bytes.chunks(4).all(|i| u32::from_le_bytes(i.try_into().unwrap()) < vertices));
+    }
+
+    // Z-up (1, 2, 3) is Y-up (1, 3, -2); Blender's up is glTF's up.
+    let crate_node = node("Crate");
+    assert_close(&crate_node.translation.unwrap(), &[1.0, 3.0, -2.0]);
+    assert_close(&rotate(crate_node.rotation.unwrap(), [0.0, 0.0, 1.0]), &[0.0, 1.0, 0.0]);
+    let extras = crate_node.extras.as_ref().unwrap();
+    assert_eq!(extras["object_id"], ids[0].to_string());
+    assert_eq!(extras["object_type"], "Cube");
+
+    // A 50 mm lens on a 36 mm sensor at 16:9.
+    let camera = &document.cameras[node("Shot Cam").camera.unwrap()];
+    let perspective = camera.perspective.as_ref().unwrap();
+    let yfov = 2.0 * ((18.0f64 / 50.0) / (16.0 / 9.0)).atan();
+    assert_close(&[perspective.yfov, perspective.znear, perspective.zfar.unwrap()], &[yfov, 0.1, 100.0]);
+    let orthographic = document.cameras[node("Top Cam").camera.unwrap()].orthographic.as_ref().unwrap();
+    assert_close(&[orthographic.xmag, orthographic.ymag], &[3.0 * 16.0 / 9.0, 3.0]);
+
+    let lights = &document.extensions.as_ref().unwrap().lights_punctual.as_ref().unwrap().lights;
+    let light = |name: &str| &lights[node(name).extensions.as_ref().unwrap().lights_punctual.as_ref().unwrap().light];
+    assert_eq!(light("Bulb").kind, "point");
+    assert_eq!(light("Sun").kind, "directional");
+    let key = light("Key");
+    assert_eq!(key.kind, "spot");
+    let cone = key.spot.as_ref().unwrap();
+    assert!((cone.outer_cone_angle.unwrap() - std::f32::consts::FRAC_PI_8).abs() < 1e-6);
+    assert!(cone.inner_cone_angle.unwrap() < cone.outer_cone_angle.unwrap());
+    assert!((key.intensity.unwrap() - 1000.0 * 683.0 / (4.0 * std::f32::consts::PI)).abs() < 0.1);
+    // An unrotated Blender spot points straight down, in glTF too.
+    assert_close(&rotate(node("Key").rotation.unwrap(), [0.0, 0.0, -1.0]), &[0.0, -1.0, 0.0]);
+    assert_eq!(node("Key").extras.as_ref().unwrap()["object_id"], ids[1].to_string());
+
+    // No glTF equivalent: empty nodes that keep what they are in extras.
+    let softbox = node("Softbox");
+    assert!(softbox.mesh.is_none() && softbox.extensions.is_none());
+    assert!(softbox.extras.as_ref().unwrap()["properties"]["AreaLight"].is_object());
+    let oak = node("Oak");
+    assert!(oak.mesh.is_none() && oak.camera.is_none());
+    let extras = oak.extras.as_ref().unwrap();
+    assert_eq!((extras["asset_id"].as_str(), extras["asset_library"].as_str()), (Some("oak_large"), Some("Vegetation")));
+    assert_eq!(extras["object_id"], ids[2].to_string());
+
+    // The .gltf flavour embeds the same buffer as a data URI.
+    let document: Document = serde_json::from_slice(&gltf.to_bytes(GltfFormat::Gltf).unwrap()).unwrap();
+    assert!(document.buffers[0].uri.as_ref().unwrap().starts_with("data:application/octet-stream;base64,"));
+}
+
+/// Generated solids wind counter-clockwise seen from outside, with normals to match, so they
+/// are not culled inside out.
+#[test]
+fn test_primitive_meshes_face_outward() {
+    for object_type in [ObjectType::Cube, ObjectType::Sphere, ObjectType::Icosphere, ObjectType::Cylinder, ObjectType::Cone] {
+        let mesh = primitive_mesh(&object_type).unwrap();
+        assert_eq!(mesh.positions.len(), mesh.normals.len());
+        for triangle in mesh.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.positions[triangle[i] as usize].map(f64::from));
+            let (ab, ac) = ([b[0] - a[0], b[1] - a[1], b[2] - a[2]], [c[0] - a[0], c[1] - a[1], c[2] - a[2]]);
+            let face = [ab[1] * ac[2] - ab[2] * ac[1], ab[2] * ac[0] - ab[0] * ac[2], ab[0] * ac[1] - ab[1] * ac[0]];
+            let centroid = [0, 1, 2].map(|axis| (a[axis] + b[axis] + c[axis]) / 3.0);
+            let outward: f64 = (0..3).map(|axis| face[axis] * centroid[axis]).sum();
+            assert!(outward >= -1e-9, "{object_type:?} has an inward-facing triangle");
+            let normal = mesh.normals[triangle[0] as usize].map(f64::from);
+            assert!((0..3).map(|axis| normal[axis] * centroid[axis]).sum::<f64>() > 0.0, "{object_type:?} normal points inward");
+        }
+    }
+    assert!(primitive_mesh(&ObjectType::Camera).is_none());
+}
+
+/// The admin API serves the live scene, or a checkpoint of it, as .glb or .gltf.
+#[tokio::test]
+async fn test_gltf_download() {
+    let (url, addr, _state) = start_admin_test_server().await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, "gltf", "Alice").await;
+    send(&mut ws, ClientEvent::CreateObject(cube_payload(Uuid::new_v4()))).await;
+    recv(&mut ws).await; // ObjectCreated
+    send(&mut ws, ClientEvent::CreateCheckpoint(CreateCheckpointPayload { name: "one cube".to_string() })).await;
+    recv(&mut ws).await; // CheckpointCreated
+    send(&mut ws, ClientEvent::CreateObject(cube_payload(Uuid::new_v4()))).await;
+    recv(&mut ws).await; // ObjectCreated
+
+    let get = |path: &'static str| {
+        let addr = addr.clone();
+        async move { http_request(&addr, "GET", path, Some(TEST_ADMIN_TOKEN), &[]).await }
+    };
+    let (status, glb) = get("/admin/sessions/gltf/gltf").await;
+    assert_eq!(status, 200);
+    assert_eq!(split_glb(&glb).0.nodes.len(), 2);
+    let (status, json) = get("/admin/sessions/gltf/gltf?format=gltf&at=one%20cube").await;
+    assert_eq!(status, 200);
+    let document: Document = serde_json::from_slice(&json).unwrap();
+    assert_eq!(document.nodes.len(), 1);
+
+    assert_eq!(get("/admin/sessions/gltf/gltf?format=fbx").await.0, 400);
+    assert_eq!(get("/admin/sessions/gltf/gltf?at=nope").await.0, 404);
+    assert_eq!(get("/admin/sessions/missing/gltf").await.0, 404);
+    assert_eq!(http_request(&addr, "GET", "/admin/sessions/gltf/gltf", None, &[]).await.0, 401);
+}
+
+/// A layout as another DCC writes it: Y-up, nested nodes, a matrix, and no Meerkat extras.
+const FOREIGN_GLTF: &str = r#"{
+    "asset": {"version": "2.0", "generator": "Other DCC"},
+    "scene": 0,
+    "scenes": [{"nodes": [0, 2, 3, 4, 5, 6]}],
+    "nodes": [
+        {"name": "Set", "translation": [0, 0, -5], "children": [1]},
+        {"name": "Tree_01", "mesh": 0, "translation": [1, 0, 0], "scale": [2, 3, 4]},
+        {"name": "Camera", "camera": 0, "translation": [0, 1.5, 5]},
+        {"name": "Key", "translation": [0, 4, 0], "rotation": [-0.7071067811865476, 0, 0, 0.7071067811865476],
+         "extensions": {"KHR_lights_punctual": {"light": 0}}},
+        {"name": "Fill", "matrix": [1,0,0,0, 0,1,0,0, 0,0,1,0, 3,2,1,1],
+         "extensions": {"KHR_lights_punctual": {"light": 1}}},
+        {"name": "Sun", "extensions": {"KHR_lights_punctual": {"light": 2}}},
+        {"name": "Locator"}
+    ],
+    "meshes": [{"name": "oak_tree", "primitives": [{"attributes": {"POSITION": 0}}]}],
+    "cameras": [{"type": "perspective", "perspective": {"yfov": 0.6, "aspectRatio": 1.5, "znear": 0.05, "zfar": 500}}],
+    "accessors": [{"componentType": 5126, "count": 0, "type": "VEC3"}],
+    "extensionsUsed": ["KHR_lights_punctual"],
+    "extensions": {"KHR_lights_punctual": {"lights": [
+        {"type": "spot", "intensity": 500, "spot": {"innerConeAngle": 0.3, "outerConeAngle": 0.6}},
+        {"type": "point", "color": [1, 0.5, 0.25], "intensity": 100, "range": 12},
+        {"type": "directional", "intensity": 2049}
+    ]}}
+}"#;
+
+/// Nodes from another DCC become objects placed Z-up; meshes become asset references.
+#[test]
+fn test_import_foreign_gltf() {
+    let gltf = Gltf::from_bytes(FOREIGN_GLTF.as_bytes()).unwrap();
+    let objects = import_objects(&gltf.document, 42);
+    let names: Vec<&str> = objects.iter().map(|object| object.name.as_str()).collect();
+    assert_eq!(names, ["Tree_01", "Camera", "Key", "Fill", "Sun"], "empty nodes are skipped");
+    let object = |name: &str| objects.iter().find(|object| object.name == name).unwrap();
+    assert!(objects.iter().all(|object| object.created_by == IMPORT_USER_ID && object.last_updated_at == 42));
+
+    // Y-up (1, 0, -5) under its parent is Z-up (1, 5, 0); the upright mesh stays upright.
+    let tree = object("Tree_01");
+    assert_eq!(tree.object_type, ObjectType::AssetRef);
+    assert_eq!(tree.asset_id.as_deref(), Some("oak_tree"));
+    assert_close(&tree.transform.position, &[1.0, 5.0, 0.0]);
+    assert_close(&tree.transform.rotation, &[0.0; 3]);
+    assert_close(&tree.transform.scale, &[2.0, 4.0, 3.0]);
+
+    // A camera looking down glTF's -Z looks down Blender's +Y: 90° about X.
+    let camera = object("Camera");
+    assert_close(&camera.transform.position, &[0.0, -5.0, 1.5]);
+    assert_close(&camera.transform.rotation, &[std::f64::consts::FRAC_PI_2, 0.0, 0.0]);
+    let Some(ObjectProperties::Camera(lens)) = &camera.properties else { panic!("{:?}", camera.properties) };
+    assert_close(&[lens.focal_length, lens.clip_start, lens.clip_end], &[18.0 / (0.3f64.tan() * 1.5), 0.05, 500.0]);
+
+    // Pointing straight down in glTF is pointing straight down in Blender: no rotation.
+    let key = object("Key");
+    assert_eq!(key.object_type, ObjectType::SpotLight);
+    assert_close(&key.transform.position, &[0.0, 0.0, 4.0]);
+    assert_close(&key.transform.rotation, &[0.0; 3]);
+    let Some(ObjectProperties::SpotLight(spot)) = &key.properties else { panic!() };
+    assert!((spot.angle - 1.2).abs() < 1e-6 && (spot.blend - 0.5).abs() < 1e-6);
+
+    let fill = object("Fill");
+    assert_close(&fill.transform.position, &[3.0, -1.0, 2.0]);
+    let Some(ObjectProperties::PointLight(point)) = &fill.properties else { panic!() };
+    assert_eq!(point.color, [1.0, 0.5, 0.25]);
+    assert!(point.use_custom_distance && point.cutoff_distance == 12.0);
+    assert!((point.power - 100.0 * 4.0 * std::f32::consts::PI / 683.0).abs() < 1e-3);
+    let Some(ObjectProperties::SunLight(sun)) = &object("Sun").properties else { panic!() };
+    assert!((sun.strength - 3.0).abs() < 1e-6);
+
+    assert!(Gltf::from_bytes(b"{\"asset\": {\"version\": \"1.0\"}}").is_err());
+    assert!(Gltf::from_bytes(b"glTF\x02\0\0\0").is_err());
+}
+
+/// What Meerkat exports comes back as the same objects.
+#[test]
+fn test_export_import_round_trip() {
+    let mut cube = scene_object("Crate", ObjectType::Cube, None);
+    cube.transform = Transform { position: [1.0, -2.0, 3.0], rotation: [0.3, -0.4, 1.2], scale: [1.0, 2.0, 0.5] };
+    let mut camera = scene_object("Shot Cam", ObjectType::Camera, Some(ObjectProperties::Camera(CameraProperties::default())));
+    camera.transform.rotation = [1.2, 0.0, 0.7];
+    let area = scene_object("Softbox", ObjectType::AreaLight, Some(ObjectProperties::AreaLight(AreaLightProperties::default())));
+    let mut tree = scene_object("Oak", ObjectType::AssetRef, None);
+    tree.asset_id = Some("oak_large".to_string());
+    tree.asset_library = Some("Vegetation".to_string());
+    tree.transform.position = [4.0, 5.0, 6.0];
+    let objects = object_map(vec![cube, camera, area, tree]);
+
+    let glb = export_objects("layout", &objects).to_bytes(GltfFormat::Glb).unwrap();
+    let imported = import_objects(&Gltf::from_bytes(&glb).unwrap().document, 0);
+    assert_eq!(imported.len(), objects.len());
+    for object in imported {
+        let original = &objects[&object.object_id];
+        assert_eq!(
+            (&object.name, &object.object_type, &object.asset_id, &object.asset_library, &object.properties),
+            (&original.name, &original.object_type, &original.asset_id, &original.asset_library, &original.properties)
+        );
+        assert_close(&object.transform.position, &original.transform.position);
+        assert_close(&object.transform.rotation, &original.transform.rotation);
+        assert_close(&object.transform.scale, &original.transform.scale);
+    }
+}
+
+/// Importing over the admin API adds the nodes to a live session, announced to everyone in it,
+/// or creates the session when given a password.
+#[tokio::test]
+async fn test_admin_gltf_import() {
+    let (url, addr, state) = start_admin_test_server().await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, "seeded", "Alice").await;
+    let existing = Uuid::new_v4();
+    send(&mut ws, ClientEvent::CreateObject(cube_payload(existing))).await;
+    recv(&mut ws).await; // ObjectCreated
+
+    // Re-importing an export of the session itself must not clobber what is there.
+    let (_, glb) = http_request(&addr, "GET", "/admin/sessions/seeded/gltf", Some(TEST_ADMIN_TOKEN), &[]).await;
+    let post = |path: &'static str, headers: &'static [(&'static str, &'static str)], body: Vec<u8>| {
+        let addr = addr.clone();
+        async move { http_request_with_headers(&addr, "POST", path, Some(TEST_ADMIN_TOKEN), headers, &body).await }
+    };
+    let (status, body) = post("/admin/sessions/seeded/gltf", &[], glb).await;
+    assert_eq!(status, 200, "{}", String::from_utf8_lossy(&body));
+    let summary: SceneImportSummary = serde_json::from_slice(&body).unwrap();
+    assert!(!summary.created);
+    assert_eq!(summary.object_ids.len(), 1);
+    assert_ne!(summary.object_ids[0], existing);
+    match recv(&mut ws).await {
+        ServerEvent::ObjectCreated(p) => {
+            assert_eq!(p.object.object_id, summary.object_ids[0]);
+            assert_eq!(p.created_by, IMPORT_USER_ID);
+        }
+        other => panic!("expected ObjectCreated, got {other:?}"),
+    }
+
+    let (status, body) = post("/admin/sessions/seeded/gltf", &[], FOREIGN_GLTF.as_bytes().to_vec()).await;
+    assert_eq!(status, 200);
+    let summary: SceneImportSummary = serde_json::from_slice(&body).unwrap();
+    assert_eq!(summary.object_ids.len(), 5);
+    for _ in 0..5 {
+        assert!(matches!(recv(&mut ws).await, ServerEvent::ObjectCreated(_)));
+    }
+    let session = state.sessions.get("seeded").unwrap().clone();
+    assert_eq!(session.objects.read().unwrap().len(), 7);
+    let audit: Vec<_> = session.lock_audit().entries().filter(|entry| entry.event_type == "ImportGltf").cloned().collect();
+    assert_eq!(audit.len(), 6);
+    assert_eq!(audit[0].display_name, "glTF import");
+
+    // A session that does not exist yet needs a password to be created with.
+    assert_eq!(post("/admin/sessions/fresh/gltf", &[], FOREIGN_GLTF.as_bytes().to_vec()).await.0, 404);
+    let password = &[(SESSION_PASSWORD_HEADER, common::TEST_PASSWORD)];
+    let (status, body) = post("/admin/sessions/fresh/gltf", password, FOREIGN_GLTF.as_bytes().to_vec()).await;
+    assert_eq!(status, 201);
+    assert!(serde_json::from_slice::<SceneImportSummary>(&body).unwrap().created);
+    let (mut joiner, _) = connect_async(&url).await.unwrap();
+    join_session(&mut joiner, "fresh", "Bob").await;
+    assert_eq!(state.sessions.get("fresh").unwrap().objects.read().unwrap().len(), 5);
+
+    assert_eq!(post("/admin/sessions/seeded/gltf", &[], b"not gltf".to_vec()).await.0, 400);
+}
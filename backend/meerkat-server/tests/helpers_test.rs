@@ -1,13 +1,12 @@
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 
 use dashmap::DashMap;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
 use meerkat_server::{
-    handlers::helpers::broadcast,
-    types::{AppState, SessionHandle},
+    handlers::helpers::{broadcast, SYNC_BACKLOG_LIMIT},
+    types::{AppState, SessionHandle, SyncBacklog},
 };
 
 #[test]
@@ -19,12 +18,7 @@ fn broadcast_drops_when_connection_queue_is_full() {
     let sessions = Arc::new(DashMap::new());
     sessions.insert(
         session_id.clone(),
-        Arc::new(SessionHandle {
-            objects: RwLock::new(HashMap::new()),
-            users: RwLock::new(HashMap::new()),
-            session_id: session_id.clone(),
-            password_hash: "not_a_real_hash".to_string(),
-        }),
+        Arc::new(SessionHandle::new(session_id.clone(), "not_a_real_hash".to_string())),
     );
 
     let connections = Arc::new(DashMap::new());
@@ -41,8 +35,8 @@ fn broadcast_drops_when_connection_queue_is_full() {
         sessions,
         connections,
         connection_meta,
-        connection_backpressure: Arc::new(DashMap::new()),
         session_connections,
+        ..AppState::new()
     };
 
     for _ in 0..32 {
@@ -75,12 +69,7 @@ fn broadcast_evicts_after_three_full_strikes() {
     let sessions = Arc::new(DashMap::new());
     sessions.insert(
         session_id.clone(),
-        Arc::new(SessionHandle {
-            objects: RwLock::new(HashMap::new()),
-            users: RwLock::new(HashMap::new()),
-            session_id: session_id.clone(),
-            password_hash: "not_a_real_hash".to_string(),
-        }),
+        Arc::new(SessionHandle::new(session_id.clone(), "not_a_real_hash".to_string())),
     );
     let connections = Arc::new(DashMap::new());
     let connection_meta = Arc::new(DashMap::new());
@@ -93,8 +82,8 @@ fn broadcast_evicts_after_three_full_strikes() {
         sessions,
         connections,
         connection_meta,
-        connection_backpressure: Arc::new(DashMap::new()),
         session_connections,
+        ..AppState::new()
     };
 
     let (tx, _rx) = mpsc::channel::<String>(1);
@@ -126,3 +115,58 @@ fn broadcast_evicts_after_three_full_strikes() {
         "lag state should be removed on eviction"
     );
 }
+
+#[test]
+fn broadcast_holds_messages_while_connection_is_syncing() {
+    let session_id = "sync-backlog-test".to_string();
+    let connection_id = Uuid::new_v4();
+    let user_id = Uuid::new_v4();
+
+    let sessions = Arc::new(DashMap::new());
+    sessions.insert(
+        session_id.clone(),
+        Arc::new(SessionHandle::new(session_id.clone(), "not_a_real_hash".to_string())),
+    );
+    let connections = Arc::new(DashMap::new());
+    let (tx, _rx) = mpsc::channel::<String>(1);
+    tx.try_send("prefill".to_string())
+        .expect("prefill should succeed");
+    connections.insert(connection_id, tx);
+
+    let connection_meta = Arc::new(DashMap::new());
+    connection_meta.insert(connection_id, (session_id.clone(), user_id));
+
+    let session_connections = Arc::new(DashMap::new());
+    session_connections.insert(session_id.clone(), [connection_id].into_iter().collect());
+
+    let state = AppState {
+        sessions,
+        connections,
+        connection_meta,
+        session_connections,
+        ..AppState::new()
+    };
+    state.sync_backlogs.insert(connection_id, SyncBacklog { stale: 0, held: Some(Vec::new()) });
+
+    for index in 0..10 {
+        let json = format!("{{\"event_type\":\"Test\",\"index\":{index}}}");
+        assert_eq!(broadcast(&state, &session_id, &json, None), 1);
+    }
+    assert!(
+        state.connections.get(&connection_id).is_some(),
+        "a syncing connection should not be evicted for a full queue"
+    );
+    let held = state.sync_backlogs.get(&connection_id).and_then(|backlog| backlog.held.clone());
+    let held = held.expect("messages should be held");
+    assert_eq!(held.len(), 10);
+    assert!(held[9].contains("\"index\":9"), "held messages keep their order");
+
+    for _ in 0..SYNC_BACKLOG_LIMIT {
+        broadcast(&state, &session_id, "{\"event_type\":\"Test\"}", None);
+    }
+    assert!(
+        state.sync_backlogs.get(&connection_id).is_some_and(|backlog| backlog.held.is_none()),
+        "an overflowing backlog is dropped so the sync is resent"
+    );
+    assert!(state.connections.get(&connection_id).is_some());
+}
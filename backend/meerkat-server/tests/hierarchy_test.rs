@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    gltf, hierarchy,
+    messages::{ClientEvent, DeleteObjectPayload, ServerEvent, SetParentPayload, TransactionOp, TransactionPayload},
+    types::{ObjectType, SceneObject, Transform},
+    usd,
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, join_session, recv, scene_object, send, start_test_server_with_state, try_recv,
+    WsStream,
+};
+
+fn at(x: f64) -> Transform {
+    Transform { position: [x, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] }
+}
+
+fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert!(
+        actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-6),
+        "expected {expected:?}, got {actual:?}"
+    );
+}
+
+/// Create a cube at `x`, parented to `parent_id`, and drain the ObjectCreated from every socket.
+async fn create(sockets: &mut [&mut WsStream], object_id: Uuid, x: f64, parent_id: Option<Uuid>) {
+    let mut payload = cube_payload(object_id);
+    payload.transform = at(x);
+    payload.parent_id = parent_id;
+    send(sockets[0], ClientEvent::CreateObject(payload)).await;
+    for ws in sockets.iter_mut() {
+        match recv(ws).await {
+            ServerEvent::ObjectCreated(p) => assert_eq!(p.object.parent_id, parent_id),
+            other => panic!("expected ObjectCreated, got {:?}", other),
+        }
+    }
+}
+
+/// Parenting without a transform keeps the object where it was in the world.
+#[tokio::test]
+async fn test_set_parent_keeps_world_placement() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "hier-parent", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "hier-parent", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let (table, cup) = (Uuid::new_v4(), Uuid::new_v4());
+    create(&mut [&mut ws_a, &mut ws_b], table, 1.0, None).await;
+    create(&mut [&mut ws_a, &mut ws_b], cup, 3.0, None).await;
+
+    send(&mut ws_a, ClientEvent::SetParent(SetParentPayload { object_id: cup, parent_id: Some(table), transform: None })).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::ParentUpdated(p) => {
+                assert_eq!((p.object_id, p.parent_id), (cup, Some(table)));
+                assert_close(&p.transform.position, &[2.0, 0.0, 0.0]);
+            }
+            other => panic!("expected ParentUpdated, got {:?}", other),
+        }
+    }
+    {
+        let session = state.sessions.get("hier-parent").unwrap();
+        let objects = session.objects.read().unwrap();
+        assert_close(&hierarchy::world_transform(&*objects, &objects[&cup]).position, &[3.0, 0.0, 0.0]);
+    }
+
+    // An explicit transform is taken as given, and unparenting works the same way.
+    send(&mut ws_a, ClientEvent::SetParent(SetParentPayload { object_id: cup, parent_id: None, transform: Some(at(5.0)) })).await;
+    match recv(&mut ws_b).await {
+        ServerEvent::ParentUpdated(p) => {
+            assert_eq!(p.parent_id, None);
+            assert_eq!(p.transform.position, [5.0, 0.0, 0.0]);
+        }
+        other => panic!("expected ParentUpdated, got {:?}", other),
+    }
+}
+
+/// Self-parenting, missing parents and cycles are refused and nothing is broadcast.
+#[tokio::test]
+async fn test_invalid_parents_are_rejected() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "hier-reject", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "hier-reject", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let (root, child) = (Uuid::new_v4(), Uuid::new_v4());
+    create(&mut [&mut ws_a, &mut ws_b], root, 0.0, None).await;
+    create(&mut [&mut ws_a, &mut ws_b], child, 0.0, Some(root)).await;
+
+    for (object_id, parent_id) in [(root, root), (root, Uuid::new_v4()), (root, child)] {
+        send(&mut ws_a, ClientEvent::SetParent(SetParentPayload { object_id, parent_id: Some(parent_id), transform: None })).await;
+        match recv(&mut ws_a).await {
+            ServerEvent::Error(e) => assert_eq!(e.code, "INVALID_PARENT"),
+            other => panic!("expected INVALID_PARENT, got {:?}", other),
+        }
+    }
+
+    let mut orphan = cube_payload(Uuid::new_v4());
+    orphan.parent_id = Some(Uuid::new_v4());
+    send(&mut ws_a, ClientEvent::CreateObject(orphan)).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "INVALID_PARENT"),
+        other => panic!("expected INVALID_PARENT, got {:?}", other),
+    }
+    assert!(try_recv(&mut ws_b).await.is_none(), "rejected changes are not broadcast");
+
+    let session = state.sessions.get("hier-reject").unwrap();
+    let objects = session.objects.read().unwrap();
+    assert_eq!(objects.len(), 2);
+    assert_eq!(objects[&root].parent_id, None);
+}
+
+/// Deleting a parent moves its children up in place; `delete_children` takes the subtree with
+/// it, and one undo brings the whole subtree back.
+#[tokio::test]
+async fn test_delete_reparents_or_cascades() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "hier-delete", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "hier-delete", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let (shelf, book) = (Uuid::new_v4(), Uuid::new_v4());
+    create(&mut [&mut ws_a, &mut ws_b], shelf, 1.0, None).await;
+    create(&mut [&mut ws_a, &mut ws_b], book, 2.0, Some(shelf)).await;
+
+    send(&mut ws_a, ClientEvent::DeleteObject(DeleteObjectPayload { object_id: shelf, delete_children: false })).await;
+    match recv(&mut ws_b).await {
+        ServerEvent::ParentUpdated(p) => {
+            assert_eq!((p.object_id, p.parent_id), (book, None));
+            assert_close(&p.transform.position, &[3.0, 0.0, 0.0]);
+        }
+        other => panic!("expected ParentUpdated, got {:?}", other),
+    }
+    match recv(&mut ws_b).await {
+        ServerEvent::ObjectDeleted(p) => assert_eq!(p.object_id, shelf),
+        other => panic!("expected ObjectDeleted, got {:?}", other),
+    }
+    recv(&mut ws_a).await; // ParentUpdated
+    recv(&mut ws_a).await; // ObjectDeleted
+
+    let (crate_id, bottle, cork) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    create(&mut [&mut ws_a, &mut ws_b], crate_id, 0.0, None).await;
+    create(&mut [&mut ws_a, &mut ws_b], bottle, 1.0, Some(crate_id)).await;
+    create(&mut [&mut ws_a, &mut ws_b], cork, 1.0, Some(bottle)).await;
+
+    send(&mut ws_a, ClientEvent::DeleteObject(DeleteObjectPayload { object_id: crate_id, delete_children: true })).await;
+    let mut deleted = Vec::new();
+    for _ in 0..3 {
+        match recv(&mut ws_b).await {
+            ServerEvent::ObjectDeleted(p) => deleted.push(p.object_id),
+            other => panic!("expected ObjectDeleted, got {:?}", other),
+        }
+        recv(&mut ws_a).await; // ObjectDeleted
+    }
+    assert_eq!(deleted, vec![cork, bottle, crate_id], "children go before their parents");
+    assert_eq!(state.sessions.get("hier-delete").unwrap().objects.read().unwrap().len(), 1);
+
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_b).await {
+        ServerEvent::HistoryApplied(p) => assert_eq!(p.diff.created.len(), 3),
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+    let session = state.sessions.get("hier-delete").unwrap();
+    let objects = session.objects.read().unwrap();
+    assert_eq!(objects[&cork].parent_id, Some(bottle));
+    assert_eq!(objects[&bottle].parent_id, Some(crate_id));
+}
+
+/// A transaction is broadcast as applied: a delete's reparenting is spelled out and every
+/// SetParent carries the transform it resolved to.
+#[tokio::test]
+async fn test_transaction_broadcasts_resolved_hierarchy_operations() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "hier-txn", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "hier-txn", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let (old_parent, new_parent, child) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    create(&mut [&mut ws_a, &mut ws_b], old_parent, 1.0, None).await;
+    create(&mut [&mut ws_a, &mut ws_b], new_parent, 4.0, None).await;
+    create(&mut [&mut ws_a, &mut ws_b], child, 1.0, Some(old_parent)).await;
+
+    send(&mut ws_a, ClientEvent::Transaction(TransactionPayload {
+        transaction_id: Uuid::new_v4(),
+        operations: vec![
+            TransactionOp::DeleteObject(DeleteObjectPayload { object_id: old_parent, delete_children: false }),
+            TransactionOp::SetParent(SetParentPayload { object_id: child, parent_id: Some(new_parent), transform: None }),
+        ],
+    })).await;
+    match recv(&mut ws_b).await {
+        ServerEvent::TransactionApplied(p) => {
+            assert_eq!(p.operations.len(), 3);
+            match &p.operations[0] {
+                TransactionOp::SetParent(op) => {
+                    assert_eq!((op.object_id, op.parent_id), (child, None));
+                    assert_close(&op.transform.as_ref().unwrap().position, &[2.0, 0.0, 0.0]);
+                }
+                other => panic!("expected the reparenting first, got {:?}", other),
+            }
+            assert!(matches!(&p.operations[1], TransactionOp::DeleteObject(op) if op.object_id == old_parent));
+            match &p.operations[2] {
+                TransactionOp::SetParent(op) => {
+                    assert_eq!(op.parent_id, Some(new_parent));
+                    assert_close(&op.transform.as_ref().unwrap().position, &[-2.0, 0.0, 0.0]);
+                }
+                other => panic!("expected SetParent, got {:?}", other),
+            }
+        }
+        other => panic!("expected TransactionApplied, got {:?}", other),
+    }
+
+    let session = state.sessions.get("hier-txn").unwrap();
+    let objects = session.objects.read().unwrap();
+    assert_eq!(objects[&child].parent_id, Some(new_parent));
+    assert_close(&hierarchy::world_transform(&*objects, &objects[&child]).position, &[2.0, 0.0, 0.0]);
+}
+
+fn nested_scene() -> (HashMap<Uuid, SceneObject>, Uuid, Uuid) {
+    let mut parent = scene_object("Table", ObjectType::Cube, None);
+    parent.transform = Transform { position: [1.0, 2.0, 0.5], rotation: [0.0, 0.0, 0.7], scale: [2.0; 3] };
+    let mut child = scene_object("Lamp", ObjectType::Sphere, None);
+    child.parent_id = Some(parent.object_id);
+    child.transform = Transform { position: [0.25, 0.0, 0.5], rotation: [0.1, 0.0, 0.0], scale: [0.5; 3] };
+    let (parent_id, child_id) = (parent.object_id, child.object_id);
+    (HashMap::from([(parent_id, parent), (child_id, child)]), parent_id, child_id)
+}
+
+fn assert_same_placement(imported: &[SceneObject], original: &HashMap<Uuid, SceneObject>, child_id: Uuid, parent_id: Uuid) {
+    let imported: HashMap<Uuid, SceneObject> = imported.iter().map(|object| (object.object_id, object.clone())).collect();
+    assert_eq!(imported[&child_id].parent_id, Some(parent_id));
+    assert_eq!(imported[&parent_id].parent_id, None);
+    for id in [parent_id, child_id] {
+        let expected = hierarchy::world_matrix(original, &original[&id]);
+        assert_close(&hierarchy::world_matrix(&imported, &imported[&id]), &expected);
+    }
+}
+
+/// Exports nest children under their parents and imports rebuild the hierarchy.
+#[test]
+fn test_exports_round_trip_the_hierarchy() {
+    let (objects, parent_id, child_id) = nested_scene();
+
+    let exported = gltf::export_objects("nested", &objects);
+    assert_eq!(exported.document.scenes[0].nodes.len(), 1, "only the parent is a scene root");
+    let imported = gltf::import_objects(&exported.document, 0);
+    assert_same_placement(&imported, &objects, child_id, parent_id);
+
+    let layer = usd::export_objects("nested", &objects);
+    assert_eq!(layer.prims[0].children.len(), 1, "only the parent sits under /World");
+    assert_eq!(layer.prims[0].children[0].children.len(), 1);
+    let imported = usd::import_objects(&usd::Layer::parse(&layer.to_usda()).unwrap(), 0);
+    assert_same_placement(&imported, &objects, child_id, parent_id);
+}
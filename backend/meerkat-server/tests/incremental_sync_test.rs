@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    messages::{ClientEvent, DeleteObjectPayload, RequestStateSyncPayload, ServerEvent, UpdateTransformPayload},
+    types::{ChangeEntry, ChangeKind, ChangeLog, Transform},
+};
+
+mod common;
+
+use common::{create_session, cube_payload, recv, send, start_test_server};
+
+/// A client that already holds version N receives only what changed after N:
+/// current state for created/updated objects and tombstones for deleted ones.
+#[tokio::test]
+async fn test_request_state_sync_since_version_returns_delta() {
+    let url = start_test_server().await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, "delta-sync", "Alice").await;
+
+    let moved = Uuid::new_v4();
+    let removed = Uuid::new_v4();
+    for id in [moved, removed] {
+        send(&mut ws, ClientEvent::CreateObject(cube_payload(id))).await;
+        recv(&mut ws).await; // ObjectCreated
+    }
+
+    send(&mut ws, ClientEvent::RequestStateSync(None)).await;
+    let known_version = match recv(&mut ws).await {
+        ServerEvent::FullStateSync(p) => {
+            assert_eq!(p.session.objects.len(), 2);
+            p.session.version
+        }
+        other => panic!("expected FullStateSync, got {:?}", other),
+    };
+    assert_eq!(known_version, 2);
+
+    send(&mut ws, ClientEvent::UpdateTransform(UpdateTransformPayload {
+        object_id: moved,
+        transform: Transform { position: [4.0, 5.0, 6.0], rotation: [0.0; 3], scale: [1.0; 3] },
+    })).await;
+    recv(&mut ws).await; // TransformUpdated
+    send(&mut ws, ClientEvent::DeleteObject(DeleteObjectPayload { object_id: removed, delete_children: false })).await;
+    recv(&mut ws).await; // ObjectDeleted
+    let added = Uuid::new_v4();
+    send(&mut ws, ClientEvent::CreateObject(cube_payload(added))).await;
+    recv(&mut ws).await; // ObjectCreated
+
+    send(&mut ws, ClientEvent::RequestStateSync(Some(RequestStateSyncPayload { since_version: known_version }))).await;
+    match recv(&mut ws).await {
+        ServerEvent::StateDelta(p) => {
+            assert_eq!(p.from_version, known_version);
+            assert_eq!(p.to_version, 5);
+            assert_eq!(p.deleted, vec![removed]);
+            assert_eq!(p.upserted.len(), 2);
+            let moved_obj = p.upserted.iter().find(|o| o.object_id == moved).expect("moved object in delta");
+            assert_eq!(moved_obj.transform.position, [4.0, 5.0, 6.0]);
+            assert!(p.upserted.iter().any(|o| o.object_id == added));
+        }
+        other => panic!("expected StateDelta, got {:?}", other),
+    }
+
+    // Already up to date: empty delta rather than a full resend.
+    send(&mut ws, ClientEvent::RequestStateSync(Some(RequestStateSyncPayload { since_version: 5 }))).await;
+    match recv(&mut ws).await {
+        ServerEvent::StateDelta(p) => assert!(p.upserted.is_empty() && p.deleted.is_empty()),
+        other => panic!("expected empty StateDelta, got {:?}", other),
+    }
+
+    // A version the server never issued (e.g. from a previous incarnation) falls back to full sync.
+    send(&mut ws, ClientEvent::RequestStateSync(Some(RequestStateSyncPayload { since_version: 99 }))).await;
+    match recv(&mut ws).await {
+        ServerEvent::FullStateSync(p) => {
+            assert_eq!(p.session.version, 5);
+            assert_eq!(p.session.objects.len(), 2);
+        }
+        other => panic!("expected FullStateSync fallback, got {:?}", other),
+    }
+}
+
+#[test]
+fn change_log_reports_unavailable_history_after_trim() {
+    let object_id = Uuid::new_v4();
+    // Versions 1..=3 were trimmed; only 4 and 5 remain.
+    let log = ChangeLog {
+        version: 5,
+        entries: VecDeque::from(vec![
+            ChangeEntry { version: 4, object_id, kind: ChangeKind::Upserted },
+            ChangeEntry { version: 5, object_id, kind: ChangeKind::Deleted },
+        ]),
+    };
+
+    assert!(log.changes_since(2).is_none(), "history before the trim point is gone");
+    let net = log.changes_since(3).expect("history from version 3 onward is retained");
+    assert_eq!(net.get(&object_id), Some(&ChangeKind::Deleted), "last change per object wins");
+    assert!(log.changes_since(5).expect("up to date").is_empty());
+}
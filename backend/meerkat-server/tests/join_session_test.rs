@@ -1,7 +1,4 @@
-use std::sync::Arc;
-
 use axum::{routing::any, Router};
-use dashmap::DashMap;
 use futures_util::{SinkExt, StreamExt};
 use tokio::net::TcpStream;
 use tokio::time::{timeout, Duration};
@@ -22,13 +19,7 @@ use meerkat_server::{
 type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
 async fn start_test_server() -> String {
-    let state = AppState {
-        sessions: Arc::new(DashMap::new()),
-        connections: Arc::new(DashMap::new()),
-        connection_meta: Arc::new(DashMap::new()),
-        connection_backpressure: Arc::new(DashMap::new()),
-        session_connections: Arc::new(DashMap::new()),
-    };
+    let state = AppState::new();
 
     let app = Router::new().route("/ws", any(tcp_socket_upgrade)).with_state(state);
     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -82,6 +73,7 @@ fn cube_payload(object_id: Uuid) -> CreateObjectPayload {
         object_type: ObjectType::Cube,
         asset_id: None,
         asset_library: None,
+        parent_id: None,
         transform: Transform {
             position: [0.0; 3],
             rotation: [0.0; 3],
@@ -0,0 +1,328 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration};
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    config::{ServerConfig, BIND_ADDR_ENV, DATA_DIR_ENV},
+    handlers::{helpers::insert_new_session, update_transform::flush_pending_transforms},
+    history::ObjectChange,
+    journal::{read_journals, Journal, JournalOp, JOURNAL_FILE_NAME},
+    messages::{ClientEvent, CreateCheckpointPayload, DeleteObjectPayload, JoinSessionPayload, ServerEvent},
+    snapshot::{recover, snapshot_session, write_snapshot, SessionSnapshot, SNAPSHOT_FILE_NAME},
+    storage::session_dir,
+    types::{AppState, ObjectType, PendingTransform, SessionHandle, Transform},
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, join_session, recv, scene_object, send, start_admin_test_server_with_config,
+    start_test_server_with_state, temp_data_dir, WsStream, TEST_PASSWORD,
+};
+
+fn free_addr() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+fn spawn_server(data_dir: &Path, addr: &str) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_meerkat-server"))
+        .env(DATA_DIR_ENV, data_dir)
+        .env(BIND_ADDR_ENV, addr)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to start meerkat-server")
+}
+
+async fn connect(addr: &str) -> WsStream {
+    for _ in 0..100 {
+        if let Ok((ws, _)) = connect_async(format!("ws://{addr}/ws")).await {
+            return ws;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    panic!("server at {addr} never came up");
+}
+
+/// Kill -9 the server while creates are still streaming in; after a restart every create the
+/// client saw acknowledged is still there.
+#[tokio::test]
+async fn test_acknowledged_edits_survive_kill() {
+    let data_dir = temp_data_dir();
+    let addr = free_addr();
+    let mut server = spawn_server(&data_dir, &addr);
+    let mut ws = connect(&addr).await;
+    create_session(&mut ws, "wal", "Alice").await;
+
+    // A second editor keeps creates streaming in while the first counts acknowledgements,
+    // one create at a time so the newest acknowledged edit is always the latest journaled.
+    let mut streamer = connect(&addr).await;
+    join_session(&mut streamer, "wal", "Bob").await;
+    recv(&mut ws).await; // UserJoined
+    tokio::spawn(async move {
+        for _ in 0..200 {
+            send(&mut streamer, ClientEvent::CreateObject(cube_payload(Uuid::new_v4()))).await;
+            sleep(Duration::from_millis(2)).await;
+        }
+        sleep(Duration::from_secs(10)).await;
+    });
+    let mut acked = HashSet::new();
+    for _ in 0..30 {
+        let id = Uuid::new_v4();
+        send(&mut ws, ClientEvent::CreateObject(cube_payload(id))).await;
+        loop {
+            if let ServerEvent::ObjectCreated(p) = recv(&mut ws).await {
+                acked.insert(p.object.object_id);
+                if p.object.object_id == id {
+                    break;
+                }
+            }
+        }
+    }
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    let mut server = spawn_server(&data_dir, &addr);
+    let mut ws = connect(&addr).await;
+    send(&mut ws, ClientEvent::JoinSession(JoinSessionPayload {
+        session_id: "wal".to_string(),
+        display_name: "Carol".to_string(),
+        password: TEST_PASSWORD.to_string(),
+    })).await;
+    let objects = match recv(&mut ws).await {
+        ServerEvent::FullStateSync(p) => p.session.objects,
+        other => panic!("expected FullStateSync, got {:?}", other),
+    };
+    let lost: Vec<_> = acked.iter().filter(|id| !objects.contains_key(id)).collect();
+    assert!(lost.is_empty(), "{} acknowledged creates lost: {lost:?}", lost.len());
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
+
+/// A torn final record is dropped, but an unreadable record with others after it fails the
+/// read instead of replaying the later records without it.
+#[test]
+fn test_corrupt_journal_record_is_an_error() {
+    let dir = temp_data_dir();
+    let mut journal = Journal::create(&dir, 0, None).unwrap();
+    for version in 1..=3 {
+        journal.append(JournalOp::Objects { version, changes: Vec::new() }).unwrap();
+    }
+    drop(journal);
+    let path = dir.join(JOURNAL_FILE_NAME);
+    let written = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(read_journals(&dir, None).unwrap().len(), 3);
+
+    let torn = format!("{}\n{}\n{}", lines[0], lines[1], &lines[2][..lines[2].len() / 2]);
+    std::fs::write(&path, torn).unwrap();
+    let records = read_journals(&dir, None).unwrap();
+    assert_eq!(records.iter().map(|record| record.seq).collect::<Vec<_>>(), [1, 2]);
+
+    let corrupt = format!("{}\n{{\"seq\":2,\"op\n{}\n", lines[0], lines[2]);
+    std::fs::write(&path, corrupt).unwrap();
+    let err = read_journals(&dir, None).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("line 2"), "{err}");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// A snapshot folds the journal in and empties it; later edits are journaled again, and a
+/// reclaimed session leaves nothing to restore.
+#[tokio::test]
+async fn test_snapshot_compacts_journal() {
+    let data_dir = temp_data_dir();
+    let config = ServerConfig { data_dir: Some(data_dir.clone()), ..ServerConfig::default() };
+    let (url, _addr, state) = start_admin_test_server_with_config(config).await;
+    let dir = session_dir(&data_dir, "compacted");
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, "compacted", "Alice").await;
+
+    let kept = Uuid::new_v4();
+    let doomed = Uuid::new_v4();
+    for id in [kept, doomed] {
+        send(&mut ws, ClientEvent::CreateObject(cube_payload(id))).await;
+        recv(&mut ws).await; // ObjectCreated
+    }
+    send(&mut ws, ClientEvent::CreateCheckpoint(CreateCheckpointPayload { name: "two cubes".to_string() })).await;
+    recv(&mut ws).await; // CheckpointCreated
+    send(&mut ws, ClientEvent::DeleteObject(DeleteObjectPayload { object_id: doomed, delete_children: false })).await;
+    recv(&mut ws).await; // ObjectDeleted
+    assert_eq!(read_journals(&dir, None).unwrap().len(), 5, "create, create, checkpoint, delete, trash");
+
+    let session = state.sessions.get("compacted").unwrap().clone();
+    assert!(snapshot_session(&session, &dir, None).unwrap());
+    assert!(!snapshot_session(&session, &dir, None).unwrap(), "nothing new to snapshot");
+    assert!(read_journals(&dir, None).unwrap().is_empty());
+    let files: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("journal"))
+        .collect();
+    assert_eq!(files, [JOURNAL_FILE_NAME]);
+
+    send(&mut ws, ClientEvent::CreateObject(cube_payload(Uuid::new_v4()))).await;
+    recv(&mut ws).await; // ObjectCreated
+    let recovered = recover(&dir, None).unwrap().unwrap();
+    assert_eq!(recovered.objects.len(), 2);
+    assert!(recovered.objects.contains_key(&kept));
+    assert_eq!(recovered.checkpoints.len(), 1);
+    assert_eq!(recovered.trash.len(), 1);
+    assert_eq!(recovered.version, session.current_version());
+
+    ws.close(None).await.unwrap();
+    for _ in 0..50 {
+        if !state.sessions.contains_key("compacted") {
+            break;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    assert!(!dir.join(SNAPSHOT_FILE_NAME).exists());
+    assert!(recover(&dir, None).unwrap().is_none());
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
+
+/// A coalesced transform goes out once its own journal record is synced, even while later
+/// records are still waiting for an fsync, and not at all once the journal has failed.
+#[tokio::test]
+async fn test_transform_flush_waits_only_for_its_own_record() {
+    let dir = temp_data_dir();
+    let session = Arc::new(SessionHandle::new("flush-seq".to_string(), "not_a_real_hash".to_string()));
+    *session.lock_journal() = Some(Journal::create(&dir, 0, None).unwrap());
+
+    let state = AppState::new();
+    let connection_id = Uuid::new_v4();
+    let (tx, mut rx) = mpsc::channel::<String>(8);
+    state.connections.insert(connection_id, tx);
+    state.connection_meta.insert(connection_id, ("flush-seq".to_string(), Uuid::new_v4()));
+    state.session_connections.insert("flush-seq".to_string(), [connection_id].into_iter().collect());
+    state.sessions.insert("flush-seq".to_string(), session.clone());
+
+    let durable = Uuid::new_v4();
+    let buffered = Uuid::new_v4();
+    session.append_journal(|| JournalOp::Objects { version: 1, changes: Vec::new() });
+    session.commit_journal().await;
+    session.append_journal(|| JournalOp::Objects { version: 2, changes: Vec::new() });
+    {
+        let mut pending = session.pending_transforms.lock().unwrap();
+        for (object_id, journal_seq) in [(durable, 1), (buffered, 2)] {
+            pending.insert(object_id, PendingTransform {
+                transform: Transform { position: [1.0, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+                updated_by: Uuid::nil(),
+                journal_seq,
+            });
+        }
+    }
+    session.transform_flush_scheduled.store(true, Ordering::Release);
+
+    assert!(!flush_pending_transforms(&state, &session), "the second record is not synced yet");
+    let sent = rx.try_recv().expect("the durable transform is broadcast");
+    assert!(sent.contains("TransformUpdated") && sent.contains(&durable.to_string()));
+    assert!(rx.try_recv().is_err());
+    assert!(session.transform_flush_scheduled.load(Ordering::Acquire), "the flush stays scheduled");
+
+    session.commit_journal().await;
+    assert!(flush_pending_transforms(&state, &session));
+    let sent = rx.try_recv().expect("the rest follows once synced");
+    assert!(sent.contains(&buffered.to_string()));
+    assert!(session.pending_transforms.lock().unwrap().is_empty());
+
+    // A transform whose record the journal lost when it failed is never broadcast.
+    let full = temp_data_dir();
+    std::os::unix::fs::symlink("/dev/full", full.join(JOURNAL_FILE_NAME)).unwrap();
+    *session.lock_journal() = Some(Journal::create(&full, 2, None).unwrap());
+    session.append_journal(|| JournalOp::Objects { version: 3, changes: Vec::new() });
+    session.pending_transforms.lock().unwrap().insert(durable, PendingTransform {
+        transform: Transform { position: [2.0, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+        updated_by: Uuid::nil(),
+        journal_seq: 3,
+    });
+    session.transform_flush_scheduled.store(true, Ordering::Release);
+    session.commit_journal().await;
+    assert!(session.storage_failed());
+    assert!(flush_pending_transforms(&state, &session));
+    assert!(rx.try_recv().is_err(), "an unjournaled transform is not broadcast");
+    assert!(session.pending_transforms.lock().unwrap().is_empty());
+    assert!(!session.transform_flush_scheduled.load(Ordering::Acquire));
+
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::remove_dir_all(&full);
+}
+
+/// Once the journal cannot be written the session turns read-only and says so, instead of
+/// acknowledging edits that would not survive a crash.
+#[tokio::test]
+async fn test_journal_failure_makes_session_read_only() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, "disk-full", "Alice").await;
+
+    let dir = temp_data_dir();
+    std::os::unix::fs::symlink("/dev/full", dir.join(JOURNAL_FILE_NAME)).unwrap();
+    let session = state.sessions.get("disk-full").unwrap().clone();
+    *session.lock_journal() = Some(Journal::create(&dir, 0, None).unwrap());
+
+    send(&mut ws, ClientEvent::CreateObject(cube_payload(Uuid::new_v4()))).await;
+    let mut codes = Vec::new();
+    for _ in 0..2 {
+        if let ServerEvent::Error(e) = recv(&mut ws).await {
+            codes.push(e.code);
+        }
+    }
+    assert_eq!(codes, ["SESSION_STORAGE_FAILED"], "clients are told once the journal fails");
+    assert!(session.storage_failed());
+
+    send(&mut ws, ClientEvent::CreateObject(cube_payload(Uuid::new_v4()))).await;
+    match recv(&mut ws).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "SESSION_READ_ONLY"),
+        other => panic!("expected SESSION_READ_ONLY, got {:?}", other),
+    }
+    assert_eq!(session.objects.read().unwrap().len(), 1, "the refused edit is not applied");
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// A new session is published before its first snapshot is written; edits made on it from
+/// then on are journaled over that snapshot, and nothing an earlier session left is replayed.
+#[tokio::test]
+async fn test_new_session_is_persisted_with_later_edits() {
+    let data_dir = temp_data_dir();
+    let state = AppState::with_config(ServerConfig { data_dir: Some(data_dir.clone()), ..ServerConfig::default() });
+    let dir = session_dir(&data_dir, "forked");
+    let stale = SessionHandle::new("forked".to_string(), "not_a_real_hash".to_string());
+    let leftover = scene_object("Leftover", ObjectType::Cube, None);
+    stale.objects.write().unwrap().insert(leftover.object_id, leftover);
+    write_snapshot(&dir, &SessionSnapshot::of(&stale, 7), None).unwrap();
+
+    let copied = scene_object("Copied", ObjectType::Cube, None);
+    let session = SessionHandle::new("forked".to_string(), "not_a_real_hash".to_string());
+    session.objects.write().unwrap().insert(copied.object_id, copied.clone());
+    let session = insert_new_session(&state, session).await.expect("the id is free");
+    assert!(insert_new_session(&state, SessionHandle::new("forked".to_string(), String::new())).await.is_none());
+
+    let added = scene_object("Added", ObjectType::Cube, None);
+    session.objects.write().unwrap().insert(added.object_id, added.clone());
+    session.record_object_changes(&[ObjectChange { object_id: added.object_id, before: None, after: Some(added.clone()) }]);
+    session.commit_journal().await;
+
+    let recovered = recover(&dir, None).unwrap().unwrap();
+    let mut ids: Vec<Uuid> = recovered.objects.keys().copied().collect();
+    ids.sort();
+    let mut expected = vec![copied.object_id, added.object_id];
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    config::ServerConfig,
+    gltf::{self, KHR_MATERIALS_EMISSIVE_STRENGTH},
+    messages::{
+        ClientEvent, CreateMaterialPayload, JoinSessionPayload, ServerEvent, SetObjectMaterialsPayload,
+        UpdateMaterialPayload,
+    },
+    snapshot::{recover, snapshot_session},
+    storage::session_dir,
+    types::{
+        Material, MaterialSettings, MaterialTextures, ObjectType, TextureRef, MAX_MATERIAL_NAME_LEN, MAX_MATERIAL_SLOTS,
+    },
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, join_session, recv, scene_object, send, start_admin_test_server_with_config,
+    start_test_server_with_state, temp_data_dir, try_recv, WsStream, TEST_PASSWORD,
+};
+
+fn material_payload(material_id: Uuid, name: &str) -> CreateMaterialPayload {
+    CreateMaterialPayload {
+        material_id,
+        name: name.to_string(),
+        settings: MaterialSettings { base_color: [0.9, 0.1, 0.1], roughness: 0.3, ..MaterialSettings::default() },
+    }
+}
+
+async fn create_material(ws: &mut WsStream, material_id: Uuid, name: &str) {
+    send(ws, ClientEvent::CreateMaterial(material_payload(material_id, name))).await;
+    match recv(ws).await {
+        ServerEvent::MaterialCreated(p) => assert_eq!(p.material.material_id, material_id),
+        other => panic!("expected MaterialCreated, got {:?}", other),
+    }
+}
+
+async fn create_cube(ws: &mut WsStream, object_id: Uuid) {
+    send(ws, ClientEvent::CreateObject(cube_payload(object_id))).await;
+    match recv(ws).await {
+        ServerEvent::ObjectCreated(p) => assert_eq!(p.object.object_id, object_id),
+        other => panic!("expected ObjectCreated, got {:?}", other),
+    }
+}
+
+fn set_materials(object_id: Uuid, material_slots: Vec<Option<Uuid>>) -> ClientEvent {
+    ClientEvent::SetObjectMaterials(SetObjectMaterialsPayload { object_id, material_slots })
+}
+
+/// Creating, updating and assigning a material reaches every client, and a late joiner gets
+/// the material table and slots in its FullStateSync.
+#[tokio::test]
+async fn test_material_sync_and_assignment() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "mat-sync", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "mat-sync", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let (red, cube) = (Uuid::new_v4(), Uuid::new_v4());
+    send(&mut ws_a, ClientEvent::CreateMaterial(material_payload(red, "  Red Paint "))).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::MaterialCreated(p) => {
+                assert_eq!(p.material.name, "Red Paint");
+                assert_eq!(p.material.settings.base_color, [0.9, 0.1, 0.1]);
+                assert_eq!(p.material.settings.alpha, 1.0);
+            }
+            other => panic!("expected MaterialCreated, got {:?}", other),
+        }
+    }
+
+    let wood = TextureRef { asset_library: "textures".to_string(), asset_id: "wood_albedo.png".to_string() };
+    send(&mut ws_b, ClientEvent::UpdateMaterial(UpdateMaterialPayload {
+        material_id: red,
+        metallic: Some(1.0),
+        textures: Some(MaterialTextures { base_color: Some(wood.clone()), ..MaterialTextures::default() }),
+        ..UpdateMaterialPayload::default()
+    })).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::MaterialUpdated(p) => {
+                assert_eq!(p.material.name, "Red Paint");
+                assert_eq!(p.material.settings.metallic, 1.0);
+                assert_eq!(p.material.settings.roughness, 0.3);
+                assert_eq!(p.material.settings.textures.base_color, Some(wood.clone()));
+            }
+            other => panic!("expected MaterialUpdated, got {:?}", other),
+        }
+    }
+
+    create_cube(&mut ws_a, cube).await;
+    recv(&mut ws_b).await; // ObjectCreated
+    send(&mut ws_a, set_materials(cube, vec![None, Some(red)])).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::ObjectMaterialsUpdated(p) => {
+                assert_eq!(p.object_id, cube);
+                assert_eq!(p.material_slots, [None, Some(red)]);
+            }
+            other => panic!("expected ObjectMaterialsUpdated, got {:?}", other),
+        }
+    }
+    {
+        let session = state.sessions.get("mat-sync").unwrap();
+        assert_eq!(session.objects.read().unwrap()[&cube].material_slots, [None, Some(red)]);
+    }
+
+    let (mut ws_c, _) = connect_async(&url).await.unwrap();
+    send(&mut ws_c, ClientEvent::JoinSession(JoinSessionPayload {
+        session_id: "mat-sync".to_string(),
+        display_name: "Carol".to_string(),
+        password: TEST_PASSWORD.to_string(),
+    })).await;
+    match recv(&mut ws_c).await {
+        ServerEvent::FullStateSync(p) => {
+            assert_eq!(p.session.materials[&red].settings.metallic, 1.0);
+            assert_eq!(p.session.objects[&cube].material_slots, [None, Some(red)]);
+        }
+        other => panic!("expected FullStateSync, got {:?}", other),
+    }
+}
+
+/// Bad names and values, duplicates, unknown materials and too many slots are refused
+/// without a broadcast, and a rejected update leaves the material untouched.
+#[tokio::test]
+async fn test_invalid_materials_are_rejected() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "mat-reject", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "mat-reject", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let (red, cube) = (Uuid::new_v4(), Uuid::new_v4());
+    create_material(&mut ws_a, red, "Red").await;
+    recv(&mut ws_b).await; // MaterialCreated
+    create_cube(&mut ws_a, cube).await;
+    recv(&mut ws_b).await; // ObjectCreated
+
+    let blank_texture = TextureRef { asset_library: "textures".to_string(), asset_id: " ".to_string() };
+    let rejected = [
+        (ClientEvent::CreateMaterial(material_payload(Uuid::new_v4(), "   ")), "INVALID_MATERIAL"),
+        (
+            ClientEvent::CreateMaterial(material_payload(Uuid::new_v4(), &"m".repeat(MAX_MATERIAL_NAME_LEN + 1))),
+            "INVALID_MATERIAL",
+        ),
+        (ClientEvent::CreateMaterial(material_payload(red, "Red again")), "DUPLICATE_MATERIAL"),
+        (
+            ClientEvent::UpdateMaterial(UpdateMaterialPayload {
+                material_id: red,
+                base_color: Some([1.5, 0.0, 0.0]),
+                ..UpdateMaterialPayload::default()
+            }),
+            "INVALID_MATERIAL",
+        ),
+        (
+            ClientEvent::UpdateMaterial(UpdateMaterialPayload {
+                material_id: red,
+                metallic: Some(1.0),
+                emission_strength: Some(-1.0),
+                ..UpdateMaterialPayload::default()
+            }),
+            "INVALID_MATERIAL",
+        ),
+        (
+            ClientEvent::UpdateMaterial(UpdateMaterialPayload {
+                material_id: red,
+                textures: Some(MaterialTextures { normal: Some(blank_texture), ..MaterialTextures::default() }),
+                ..UpdateMaterialPayload::default()
+            }),
+            "INVALID_MATERIAL",
+        ),
+        (
+            ClientEvent::UpdateMaterial(UpdateMaterialPayload {
+                material_id: Uuid::new_v4(),
+                alpha: Some(0.5),
+                ..UpdateMaterialPayload::default()
+            }),
+            "MATERIAL_NOT_FOUND",
+        ),
+        (set_materials(cube, vec![Some(red), Some(Uuid::new_v4())]), "MATERIAL_NOT_FOUND"),
+        (set_materials(cube, vec![None; MAX_MATERIAL_SLOTS + 1]), "INVALID_MATERIAL_SLOTS"),
+    ];
+    for (event, code) in rejected {
+        send(&mut ws_a, event).await;
+        match recv(&mut ws_a).await {
+            ServerEvent::Error(e) => assert_eq!(e.code, code),
+            other => panic!("expected {}, got {:?}", code, other),
+        }
+    }
+    assert!(try_recv(&mut ws_b).await.is_none(), "rejected material edits are not broadcast");
+
+    let session = state.sessions.get("mat-reject").unwrap();
+    assert_eq!(session.materials.read().unwrap()[&red].settings, material_payload(red, "Red").settings);
+    assert!(session.objects.read().unwrap()[&cube].material_slots.is_empty());
+}
+
+/// Material assignments are undoable; materials are journaled and survive recovery.
+#[tokio::test]
+async fn test_material_undo_and_persistence() {
+    let data_dir = temp_data_dir();
+    let config = ServerConfig { data_dir: Some(data_dir.clone()), ..ServerConfig::default() };
+    let (url, _addr, state) = start_admin_test_server_with_config(config).await;
+    let dir = session_dir(&data_dir, "mat-persist");
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "mat-persist", "Alice").await;
+    let session = state.sessions.get("mat-persist").unwrap().clone();
+    snapshot_session(&session, &dir, None).unwrap();
+
+    let (red, blue, cube) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    create_material(&mut ws_a, red, "Red").await;
+    create_material(&mut ws_a, blue, "Blue").await;
+    create_cube(&mut ws_a, cube).await;
+    send(&mut ws_a, set_materials(cube, vec![Some(red)])).await;
+    recv(&mut ws_a).await; // ObjectMaterialsUpdated
+    send(&mut ws_a, set_materials(cube, vec![Some(blue)])).await;
+    recv(&mut ws_a).await; // ObjectMaterialsUpdated
+
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::HistoryApplied(p) => assert_eq!(p.diff.updated[0].material_slots, [Some(red)]),
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+
+    let recovered = recover(&dir, None).unwrap().unwrap();
+    assert_eq!(recovered.materials.len(), 2);
+    assert_eq!(recovered.materials[&blue].name, "Blue");
+    assert_eq!(recovered.objects[&cube].material_slots, [Some(red)]);
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
+
+/// Materials export as glTF PBR materials on the primitives of the objects using them.
+#[test]
+fn test_gltf_material_export() {
+    let user = Uuid::new_v4();
+    let material = |name: &str, settings: MaterialSettings| Material {
+        material_id: Uuid::new_v4(),
+        name: name.to_string(),
+        settings,
+        created_by: user,
+        last_updated_by: user,
+        last_updated_at: 0,
+    };
+    let glass = material("Glass", MaterialSettings {
+        alpha: 0.25,
+        textures: MaterialTextures {
+            normal: Some(TextureRef { asset_library: "textures".to_string(), asset_id: "ripples.png".to_string() }),
+            ..MaterialTextures::default()
+        },
+        ..MaterialSettings::default()
+    });
+    let lamp = material("Lamp", MaterialSettings {
+        emission_color: [1.0, 0.5, 0.0],
+        emission_strength: 4.0,
+        ..MaterialSettings::default()
+    });
+    let materials = HashMap::from([(glass.material_id, glass.clone()), (lamp.material_id, lamp.clone())]);
+
+    let mut a = scene_object("A", ObjectType::Cube, None);
+    a.material_slots = vec![Some(glass.material_id)];
+    let mut b = scene_object("B", ObjectType::Cube, None);
+    b.material_slots = vec![Some(lamp.material_id), Some(glass.material_id)];
+    let c = scene_object("C", ObjectType::Cube, None);
+    let objects = HashMap::from([(a.object_id, a), (b.object_id, b), (c.object_id, c)]);
+
+    let document = gltf::export_scene("mats", &objects, &materials).document;
+    assert_eq!(document.materials.len(), 2);
+    let exported_glass = &document.materials[0];
+    assert_eq!(exported_glass.name.as_deref(), Some("Glass"));
+    assert_eq!(exported_glass.alpha_mode.as_deref(), Some("BLEND"));
+    let pbr = exported_glass.pbr_metallic_roughness.as_ref().unwrap();
+    assert_eq!(pbr.base_color_factor, Some([0.8, 0.8, 0.8, 0.25]));
+    let normal = exported_glass.normal_texture.as_ref().unwrap();
+    let image = document.textures[normal.index].source.unwrap();
+    assert_eq!(document.images[image].uri.as_deref(), Some("textures/ripples.png"));
+
+    let exported_lamp = &document.materials[1];
+    assert_eq!(exported_lamp.emissive_factor, Some([1.0, 0.5, 0.0]));
+    assert_eq!(exported_lamp.extensions.as_ref().unwrap().emissive_strength.as_ref().unwrap().emissive_strength, 4.0);
+    assert!(document.extensions_used.contains(&KHR_MATERIALS_EMISSIVE_STRENGTH.to_string()));
+
+    // Each cube's mesh carries its first slot's material; cubes sharing a material share a mesh.
+    let material_of = |name: &str| {
+        let node = document.nodes.iter().find(|node| node.name.as_deref() == Some(name)).unwrap();
+        document.meshes[node.mesh.unwrap()].primitives[0].material
+    };
+    assert_eq!(material_of("A"), Some(0));
+    assert_eq!(material_of("B"), Some(1));
+    assert_eq!(material_of("C"), None);
+    assert_eq!(document.meshes.len(), 3);
+}
@@ -0,0 +1,375 @@
+use std::io::Write;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use flate2::{write::DeflateEncoder, Compression};
+use tokio::time::{sleep, Duration};
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    config::ServerConfig,
+    mesh::{content_hash, MeshGeometry, StoredMesh},
+    messages::{ClientEvent, MeshChunkPayload, RequestMeshPayload, ServerEvent, SetObjectMeshPayload},
+    snapshot::{recover, snapshot_session},
+    storage::session_dir,
+    types::MeshRef,
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, join_session, recv, send, start_admin_test_server_with_config,
+    start_test_server_with_state, temp_data_dir, try_recv, WsStream,
+};
+
+/// A unit quad and a triangle sharing its top edge, with UVs on every corner.
+fn roof() -> MeshGeometry {
+    MeshGeometry {
+        positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0], [0.5, 1.5, 0.5]],
+        face_sizes: vec![4, 3],
+        face_vertices: vec![0, 1, 2, 3, 3, 2, 4],
+        uvs: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 1.0], [1.0, 1.0], [0.5, 1.5]],
+        normals: Vec::new(),
+    }
+}
+
+/// The roof with its apex raised, as after an edit-mode change.
+fn raised_roof() -> MeshGeometry {
+    let mut geometry = roof();
+    geometry.positions[4] = [0.5, 1.5, 2.0];
+    geometry
+}
+
+/// Upload `geometry` in `pieces` chunks, last chunk first, and wait for MeshStored.
+async fn upload(ws: &mut WsStream, geometry: &MeshGeometry, pieces: usize) -> String {
+    let (hash, stored) = StoredMesh::new(geometry);
+    let chunk_len = stored.compressed.len().div_ceil(pieces);
+    let chunks: Vec<&[u8]> = stored.compressed.chunks(chunk_len).collect();
+    for (chunk_index, chunk) in chunks.iter().enumerate().rev() {
+        send(ws, ClientEvent::UploadMeshChunk(MeshChunkPayload {
+            hash: hash.clone(),
+            chunk_index: chunk_index as u32,
+            chunk_count: chunks.len() as u32,
+            data: BASE64.encode(chunk),
+        }))
+        .await;
+    }
+    match recv(ws).await {
+        ServerEvent::MeshStored(p) => assert_eq!(p.hash, hash),
+        other => panic!("expected MeshStored, got {:?}", other),
+    }
+    hash
+}
+
+async fn create_cube(ws: &mut WsStream, object_id: Uuid) {
+    send(ws, ClientEvent::CreateObject(cube_payload(object_id))).await;
+    match recv(ws).await {
+        ServerEvent::ObjectCreated(p) => assert_eq!(p.object.object_id, object_id),
+        other => panic!("expected ObjectCreated, got {:?}", other),
+    }
+}
+
+fn set_mesh(object_id: Uuid, hash: Option<&str>, base_version: Option<u64>) -> ClientEvent {
+    ClientEvent::SetObjectMesh(SetObjectMeshPayload { object_id, hash: hash.map(str::to_string), base_version })
+}
+
+/// Meshes encode to a stable binary form, hash by content and survive compression.
+#[test]
+fn test_mesh_encoding() {
+    let geometry = roof();
+    geometry.validate().unwrap();
+    let encoded = geometry.encode();
+    assert_eq!(MeshGeometry::decode(&encoded).unwrap(), geometry);
+
+    let (hash, stored) = StoredMesh::new(&geometry);
+    assert_eq!(hash, content_hash(&encoded));
+    // The plugin's mesh_data.py encodes the same bytes; this pins the format both sides hash.
+    assert_eq!(hash, "762e0a48d8b2b150487d4b62b2f0f026face38746697540358577716d8bd1d17");
+    assert_eq!(stored.geometry().unwrap(), geometry);
+    assert_eq!(StoredMesh::new(&roof()).0, hash, "identical meshes share a hash");
+    assert_ne!(StoredMesh::new(&raised_roof()).0, hash);
+
+    assert!(MeshGeometry::decode(&encoded[..encoded.len() - 4]).is_err(), "truncated data is refused");
+    let broken = [
+        MeshGeometry { face_vertices: vec![0, 1, 2, 3, 3, 2, 9], ..roof() },
+        MeshGeometry { face_sizes: vec![5, 2], ..roof() },
+        MeshGeometry { face_sizes: vec![4, 4], ..roof() },
+        MeshGeometry { uvs: vec![[0.0, 0.0]], ..roof() },
+        MeshGeometry { positions: vec![[f32::NAN, 0.0, 0.0]; 5], ..roof() },
+    ];
+    for geometry in broken {
+        assert!(geometry.validate().is_err(), "{:?} should not validate", geometry);
+    }
+}
+
+/// An uploaded mesh is stored once, assigned with a version, broadcast by reference, and
+/// downloaded by peers on request.
+#[tokio::test]
+async fn test_mesh_upload_and_sync() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "mesh-sync", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "mesh-sync", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let cube = Uuid::new_v4();
+    send(&mut ws_a, ClientEvent::CreateObject(cube_payload(cube))).await;
+    recv(&mut ws_a).await; // ObjectCreated
+    recv(&mut ws_b).await; // ObjectCreated
+
+    let hash = upload(&mut ws_a, &roof(), 3).await;
+    assert!(try_recv(&mut ws_b).await.is_none(), "uploads are not broadcast");
+
+    send(&mut ws_a, set_mesh(cube, Some(&hash), None)).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::ObjectMeshUpdated(p) => {
+                assert_eq!(p.object_id, cube);
+                assert_eq!(p.mesh, Some(MeshRef { hash: hash.clone() }));
+                assert_eq!(p.mesh_version, 1);
+            }
+            other => panic!("expected ObjectMeshUpdated, got {:?}", other),
+        }
+    }
+
+    send(&mut ws_b, ClientEvent::RequestMesh(RequestMeshPayload { hash: hash.clone() })).await;
+    let mut compressed = Vec::new();
+    loop {
+        match recv(&mut ws_b).await {
+            ServerEvent::MeshChunk(p) => {
+                assert_eq!(p.hash, hash);
+                compressed.extend(BASE64.decode(&p.data).unwrap());
+                if p.chunk_index + 1 == p.chunk_count {
+                    break;
+                }
+            }
+            other => panic!("expected MeshChunk, got {:?}", other),
+        }
+    }
+    assert_eq!(StoredMesh { compressed }.geometry().unwrap(), roof());
+
+    // A mesh the session already has is confirmed without being stored again.
+    let (_, stored) = StoredMesh::new(&roof());
+    send(&mut ws_b, ClientEvent::UploadMeshChunk(MeshChunkPayload {
+        hash: hash.clone(),
+        chunk_index: 0,
+        chunk_count: 1,
+        data: BASE64.encode(&stored.compressed),
+    }))
+    .await;
+    match recv(&mut ws_b).await {
+        ServerEvent::MeshStored(p) => assert_eq!(p.hash, hash),
+        other => panic!("expected MeshStored, got {:?}", other),
+    }
+
+    // Bob edits the geometry against the version he has.
+    let raised = upload(&mut ws_b, &raised_roof(), 1).await;
+    send(&mut ws_b, set_mesh(cube, Some(&raised), Some(1))).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::ObjectMeshUpdated(p) => {
+                assert_eq!(p.mesh, Some(MeshRef { hash: raised.clone() }));
+                assert_eq!(p.mesh_version, 2);
+            }
+            other => panic!("expected ObjectMeshUpdated, got {:?}", other),
+        }
+    }
+
+    let session = state.sessions.get("mesh-sync").unwrap();
+    assert_eq!(session.meshes.read().unwrap().len(), 2);
+    assert_eq!(session.objects.read().unwrap()[&cube].mesh.as_ref().unwrap().hash, raised);
+}
+
+/// Bad uploads, unknown meshes and stale versions are refused and nothing is broadcast.
+#[tokio::test]
+async fn test_mesh_rejections() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "mesh-reject", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "mesh-reject", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let cube = Uuid::new_v4();
+    create_cube(&mut ws_a, cube).await;
+    recv(&mut ws_b).await; // ObjectCreated
+    let hash = upload(&mut ws_a, &roof(), 1).await;
+    send(&mut ws_a, set_mesh(cube, Some(&hash), None)).await;
+    recv(&mut ws_a).await; // ObjectMeshUpdated
+    recv(&mut ws_b).await; // ObjectMeshUpdated
+
+    let (raised, raised_mesh) = StoredMesh::new(&raised_roof());
+    let chunk = |hash: &str, data: &[u8]| {
+        ClientEvent::UploadMeshChunk(MeshChunkPayload {
+            hash: hash.to_string(),
+            chunk_index: 0,
+            chunk_count: 1,
+            data: BASE64.encode(data),
+        })
+    };
+    let rejected = vec![
+        (chunk("not-a-hash", &raised_mesh.compressed), "INVALID_MESH"),
+        (chunk(&raised, b"not deflate data"), "INVALID_MESH"),
+        (chunk(&"0".repeat(64), &raised_mesh.compressed), "INVALID_MESH"),
+        (set_mesh(cube, Some(&raised), Some(1)), "MESH_NOT_FOUND"),
+        (set_mesh(cube, Some(&hash), Some(0)), "MESH_VERSION_CONFLICT"),
+        (set_mesh(cube, None, None), "MESH_VERSION_CONFLICT"),
+        (ClientEvent::RequestMesh(RequestMeshPayload { hash: raised.clone() }), "MESH_NOT_FOUND"),
+    ];
+    for (event, code) in rejected {
+        send(&mut ws_a, event).await;
+        match recv(&mut ws_a).await {
+            ServerEvent::Error(e) => assert_eq!(e.code, code),
+            other => panic!("expected {}, got {:?}", code, other),
+        }
+    }
+    assert!(try_recv(&mut ws_b).await.is_none(), "rejected mesh edits are not broadcast");
+
+    let session = state.sessions.get("mesh-reject").unwrap();
+    assert_eq!(session.meshes.read().unwrap().len(), 1);
+    let objects = session.objects.read().unwrap();
+    assert_eq!(objects[&cube].mesh, Some(MeshRef { hash }));
+    assert_eq!(objects[&cube].mesh_version, 1);
+}
+
+/// Two clients uploading the same mesh at once, compressed differently, each finish their own
+/// upload; a client that goes away leaves no upload behind.
+#[tokio::test]
+async fn test_concurrent_uploads_of_one_mesh() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "mesh-concurrent", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "mesh-concurrent", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let (hash, fast) = StoredMesh::new(&roof());
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::none());
+    encoder.write_all(&roof().encode()).unwrap();
+    let plain = encoder.finish().unwrap();
+    assert_ne!(plain, fast.compressed);
+    let halves = |bytes: &[u8]| -> Vec<String> {
+        bytes.chunks(bytes.len().div_ceil(2)).map(|chunk| BASE64.encode(chunk)).collect()
+    };
+    let chunk = |chunk_index: u32, data: &str| {
+        ClientEvent::UploadMeshChunk(MeshChunkPayload {
+            hash: hash.clone(),
+            chunk_index,
+            chunk_count: 2,
+            data: data.to_string(),
+        })
+    };
+    let (alice, bob) = (halves(&fast.compressed), halves(&plain));
+
+    send(&mut ws_a, chunk(0, &alice[0])).await;
+    send(&mut ws_b, chunk(0, &bob[0])).await;
+    send(&mut ws_a, chunk(1, &alice[1])).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::MeshStored(p) => assert_eq!(p.hash, hash),
+        other => panic!("expected MeshStored, got {:?}", other),
+    }
+    send(&mut ws_b, chunk(1, &bob[1])).await;
+    match recv(&mut ws_b).await {
+        ServerEvent::MeshStored(p) => assert_eq!(p.hash, hash),
+        other => panic!("expected MeshStored, got {:?}", other),
+    }
+
+    let session = state.sessions.get("mesh-concurrent").unwrap().clone();
+    assert!(session.mesh_uploads.lock().unwrap().is_empty());
+    let (raised, _) = StoredMesh::new(&raised_roof());
+    send(&mut ws_b, ClientEvent::UploadMeshChunk(MeshChunkPayload {
+        hash: raised,
+        chunk_index: 0,
+        chunk_count: 2,
+        data: bob[0].clone(),
+    }))
+    .await;
+    ws_b.close(None).await.unwrap();
+    for _ in 0..50 {
+        if session.mesh_uploads.lock().unwrap().is_empty() {
+            break;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    assert!(session.mesh_uploads.lock().unwrap().is_empty(), "Bob's unfinished upload should be dropped");
+}
+
+/// Clearing a mesh still moves its version on, so an edit made before the clear is refused
+/// after the mesh is set again.
+#[tokio::test]
+async fn test_stale_mesh_edit_refused_after_clear() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "mesh-aba", "Alice").await;
+
+    let cube = Uuid::new_v4();
+    create_cube(&mut ws_a, cube).await;
+    let flat = upload(&mut ws_a, &roof(), 1).await;
+    let raised = upload(&mut ws_a, &raised_roof(), 1).await;
+    for (hash, base_version, mesh_version) in [(Some(&flat), 0, 1), (None, 1, 2), (Some(&flat), 2, 3)] {
+        send(&mut ws_a, set_mesh(cube, hash.map(String::as_str), Some(base_version))).await;
+        match recv(&mut ws_a).await {
+            ServerEvent::ObjectMeshUpdated(p) => assert_eq!(p.mesh_version, mesh_version),
+            other => panic!("expected ObjectMeshUpdated, got {:?}", other),
+        }
+    }
+
+    // Made against the first assignment, before the clear.
+    send(&mut ws_a, set_mesh(cube, Some(&raised), Some(1))).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "MESH_VERSION_CONFLICT"),
+        other => panic!("expected MESH_VERSION_CONFLICT, got {:?}", other),
+    }
+    let session = state.sessions.get("mesh-aba").unwrap();
+    assert_eq!(session.objects.read().unwrap()[&cube].mesh, Some(MeshRef { hash: flat }));
+}
+
+/// Mesh assignments are undoable; mesh data is journaled and survives recovery.
+#[tokio::test]
+async fn test_mesh_undo_and_persistence() {
+    let data_dir = temp_data_dir();
+    let config = ServerConfig { data_dir: Some(data_dir.clone()), ..ServerConfig::default() };
+    let (url, _addr, state) = start_admin_test_server_with_config(config).await;
+    let dir = session_dir(&data_dir, "mesh-persist");
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "mesh-persist", "Alice").await;
+    let session = state.sessions.get("mesh-persist").unwrap().clone();
+    snapshot_session(&session, &dir, None).unwrap();
+
+    let cube = Uuid::new_v4();
+    create_cube(&mut ws_a, cube).await;
+    let flat = upload(&mut ws_a, &roof(), 2).await;
+    send(&mut ws_a, set_mesh(cube, Some(&flat), None)).await;
+    recv(&mut ws_a).await; // ObjectMeshUpdated
+    let raised = upload(&mut ws_a, &raised_roof(), 1).await;
+    send(&mut ws_a, set_mesh(cube, Some(&raised), Some(1))).await;
+    recv(&mut ws_a).await; // ObjectMeshUpdated
+
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::HistoryApplied(p) => {
+            assert_eq!(p.diff.updated[0].mesh, Some(MeshRef { hash: flat.clone() }));
+            assert_eq!(p.diff.updated[0].mesh_version, 3, "undo moves the mesh version on");
+        }
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::HistoryApplied(p) => {
+            assert!(p.conflicts.is_empty());
+            assert_eq!(p.diff.updated[0].mesh, None);
+            assert_eq!(p.diff.updated[0].mesh_version, 4);
+        }
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+    send(&mut ws_a, ClientEvent::Redo).await;
+    recv(&mut ws_a).await; // HistoryApplied
+
+    let recovered = recover(&dir, None).unwrap().unwrap();
+    assert_eq!(recovered.meshes.len(), 2);
+    assert_eq!(recovered.meshes[&raised].geometry().unwrap(), raised_roof());
+    assert_eq!(recovered.objects[&cube].mesh.as_ref().unwrap().hash, flat);
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    config::ServerConfig,
+    messages::{
+        AddModifierPayload, ClientEvent, CreateObjectPayload, JoinSessionPayload, RemoveModifierPayload,
+        ReorderModifiersPayload, ServerEvent, UpdateModifierPayload,
+    },
+    snapshot::{recover, snapshot_session},
+    storage::session_dir,
+    types::{
+        ArrayModifier, BevelModifier, CustomValue, MirrorModifier, Modifier, ModifierKind, ObjectType,
+        SubdivisionModifier, MAX_MODIFIERS,
+    },
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, join_session, recv, send, start_admin_test_server_with_config,
+    start_test_server_with_state, temp_data_dir, try_recv, WsStream, TEST_PASSWORD,
+};
+
+fn modifier(name: &str, kind: ModifierKind) -> Modifier {
+    Modifier { modifier_id: Uuid::new_v4(), name: name.to_string(), show_viewport: true, show_render: true, kind }
+}
+
+fn array(count: u32) -> Modifier {
+    modifier("Array", ModifierKind::Array(ArrayModifier { count, ..ArrayModifier::default() }))
+}
+
+fn subdivision(levels: u32) -> Modifier {
+    modifier("Subdivision", ModifierKind::Subdivision(SubdivisionModifier { levels, ..SubdivisionModifier::default() }))
+}
+
+fn add(object_id: Uuid, modifier: &Modifier, index: Option<usize>) -> ClientEvent {
+    ClientEvent::AddModifier(AddModifierPayload { object_id, modifier: modifier.clone(), index })
+}
+
+async fn create_object(ws: &mut WsStream, payload: CreateObjectPayload) {
+    let object_id = payload.object_id;
+    send(ws, ClientEvent::CreateObject(payload)).await;
+    match recv(ws).await {
+        ServerEvent::ObjectCreated(p) => assert_eq!(p.object.object_id, object_id),
+        other => panic!("expected ObjectCreated, got {:?}", other),
+    }
+}
+
+async fn recv_stack(ws: &mut WsStream) -> Vec<Modifier> {
+    match recv(ws).await {
+        ServerEvent::ObjectModifiersUpdated(p) => p.modifiers,
+        other => panic!("expected ObjectModifiersUpdated, got {:?}", other),
+    }
+}
+
+fn ids(stack: &[Modifier]) -> Vec<Uuid> {
+    stack.iter().map(|modifier| modifier.modifier_id).collect()
+}
+
+/// Adding, updating, reordering and removing modifiers reaches every client as the whole
+/// stack, and a late joiner gets the stack in its FullStateSync.
+#[tokio::test]
+async fn test_modifier_stack_sync() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "mod-sync", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "mod-sync", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let cube = Uuid::new_v4();
+    create_object(&mut ws_a, cube_payload(cube)).await;
+    recv(&mut ws_b).await; // ObjectCreated
+
+    let (array, bevel, subdivision) =
+        (array(3), modifier("Bevel", ModifierKind::Bevel(BevelModifier::default())), subdivision(2));
+    send(&mut ws_a, add(cube, &array, None)).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        assert_eq!(ids(&recv_stack(ws).await), [array.modifier_id]);
+    }
+    send(&mut ws_a, add(cube, &subdivision, None)).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        assert_eq!(ids(&recv_stack(ws).await), [array.modifier_id, subdivision.modifier_id]);
+    }
+    send(&mut ws_b, add(cube, &bevel, Some(1))).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        assert_eq!(ids(&recv_stack(ws).await), [array.modifier_id, bevel.modifier_id, subdivision.modifier_id]);
+    }
+
+    let solidify = modifier("Solidify", ModifierKind::Other {
+        blender_type: "SOLIDIFY".to_string(),
+        parameters: BTreeMap::from([("thickness".to_string(), CustomValue::Number(0.05))]),
+    });
+    send(&mut ws_b, add(cube, &solidify, None)).await;
+    recv_stack(&mut ws_a).await;
+    recv_stack(&mut ws_b).await;
+
+    let mut wider = bevel.clone();
+    wider.show_render = false;
+    wider.kind = ModifierKind::Bevel(BevelModifier { width: 0.25, segments: 3, ..BevelModifier::default() });
+    send(&mut ws_a, ClientEvent::UpdateModifier(UpdateModifierPayload {
+        object_id: cube,
+        modifier: wider.clone(),
+    })).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        assert_eq!(recv_stack(ws).await[1], wider);
+    }
+
+    let order = vec![solidify.modifier_id, subdivision.modifier_id, array.modifier_id, bevel.modifier_id];
+    send(&mut ws_b, ClientEvent::ReorderModifiers(ReorderModifiersPayload {
+        object_id: cube,
+        modifier_ids: order.clone(),
+    })).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        assert_eq!(ids(&recv_stack(ws).await), order);
+    }
+
+    send(&mut ws_a, ClientEvent::RemoveModifier(RemoveModifierPayload {
+        object_id: cube,
+        modifier_id: array.modifier_id,
+    })).await;
+    let expected = vec![solidify.clone(), subdivision.clone(), wider.clone()];
+    for ws in [&mut ws_a, &mut ws_b] {
+        assert_eq!(recv_stack(ws).await, expected);
+    }
+    {
+        let session = state.sessions.get("mod-sync").unwrap();
+        assert_eq!(session.objects.read().unwrap()[&cube].modifiers, expected);
+    }
+
+    let (mut ws_c, _) = connect_async(&url).await.unwrap();
+    send(&mut ws_c, ClientEvent::JoinSession(JoinSessionPayload {
+        session_id: "mod-sync".to_string(),
+        display_name: "Carol".to_string(),
+        password: TEST_PASSWORD.to_string(),
+    })).await;
+    match recv(&mut ws_c).await {
+        ServerEvent::FullStateSync(p) => assert_eq!(p.session.objects[&cube].modifiers, expected),
+        other => panic!("expected FullStateSync, got {:?}", other),
+    }
+}
+
+/// Out-of-range settings, duplicate ids and names, unknown modifiers, type changes, bad
+/// orders, cameras and full stacks are refused without a broadcast.
+#[tokio::test]
+async fn test_invalid_modifiers_are_rejected() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "mod-reject", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "mod-reject", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let (cube, camera, other_cube) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    create_object(&mut ws_a, cube_payload(cube)).await;
+    create_object(&mut ws_a, CreateObjectPayload { object_type: ObjectType::Camera, ..cube_payload(camera) }).await;
+    for _ in 0..2 {
+        recv(&mut ws_b).await; // ObjectCreated
+    }
+    let existing = array(2);
+    send(&mut ws_a, add(cube, &existing, None)).await;
+    recv_stack(&mut ws_a).await;
+    recv_stack(&mut ws_b).await;
+
+    let mirror = |target| {
+        let settings = MirrorModifier { mirror_object: Some(target), ..MirrorModifier::default() };
+        modifier("Mirror", ModifierKind::Mirror(settings))
+    };
+    let other = |blender_type: &str, key: &str| {
+        modifier("Other", ModifierKind::Other {
+            blender_type: blender_type.to_string(),
+            parameters: BTreeMap::from([(key.to_string(), CustomValue::Bool(true))]),
+        })
+    };
+    let mut renamed = array(2);
+    renamed.name = "Array ".to_string();
+    let thin_bevel = modifier("Bevel", ModifierKind::Bevel(BevelModifier { width: -1.0, ..BevelModifier::default() }));
+    let update = |modifier| ClientEvent::UpdateModifier(UpdateModifierPayload { object_id: cube, modifier });
+    let mut as_subdivision = subdivision(1);
+    as_subdivision.modifier_id = existing.modifier_id;
+    let rejected = vec![
+        (add(cube, &array(0), None), "INVALID_MODIFIER"),
+        (add(cube, &subdivision(7), None), "INVALID_MODIFIER"),
+        (add(cube, &thin_bevel, None), "INVALID_MODIFIER"),
+        (add(cube, &renamed, None), "INVALID_MODIFIER"),
+        (add(cube, &array(2), None), "INVALID_MODIFIER"), // name taken
+        (add(cube, &subdivision(1), Some(2)), "INVALID_MODIFIER"),
+        (add(cube, &other("SUBSURF", "quality"), None), "INVALID_MODIFIER"),
+        (add(cube, &other("solidify", "thickness"), None), "INVALID_MODIFIER"),
+        (add(cube, &other("SOLIDIFY", "meerkat_id"), None), "INVALID_MODIFIER"),
+        (add(cube, &mirror(cube), None), "INVALID_MODIFIER"),
+        (add(cube, &mirror(other_cube), None), "INVALID_MODIFIER"),
+        (add(camera, &subdivision(1), None), "INVALID_MODIFIER"),
+        (add(cube, &existing, None), "DUPLICATE_MODIFIER"),
+        (update(as_subdivision), "INVALID_MODIFIER"),
+        (update(subdivision(1)), "MODIFIER_NOT_FOUND"),
+        (
+            ClientEvent::RemoveModifier(RemoveModifierPayload { object_id: cube, modifier_id: Uuid::new_v4() }),
+            "MODIFIER_NOT_FOUND",
+        ),
+        (
+            ClientEvent::ReorderModifiers(ReorderModifiersPayload { object_id: cube, modifier_ids: vec![] }),
+            "INVALID_MODIFIER",
+        ),
+        (
+            ClientEvent::ReorderModifiers(ReorderModifiersPayload {
+                object_id: cube,
+                modifier_ids: vec![existing.modifier_id, existing.modifier_id],
+            }),
+            "INVALID_MODIFIER",
+        ),
+    ];
+    for (event, code) in rejected {
+        send(&mut ws_a, event).await;
+        match recv(&mut ws_a).await {
+            ServerEvent::Error(e) => assert_eq!(e.code, code),
+            other => panic!("expected {}, got {:?}", code, other),
+        }
+    }
+    assert!(try_recv(&mut ws_b).await.is_none(), "rejected modifier edits are not broadcast");
+
+    for index in 1..MAX_MODIFIERS {
+        let mut filler = subdivision(1);
+        filler.name = format!("Subdivision {index}");
+        send(&mut ws_a, add(cube, &filler, None)).await;
+        recv_stack(&mut ws_a).await;
+    }
+    send(&mut ws_a, add(cube, &subdivision(1), None)).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "MODIFIER_LIMIT"),
+        other => panic!("expected MODIFIER_LIMIT, got {:?}", other),
+    }
+
+    let session = state.sessions.get("mod-reject").unwrap();
+    let objects = session.objects.read().unwrap();
+    assert_eq!(objects[&cube].modifiers.len(), MAX_MODIFIERS);
+    assert_eq!(objects[&cube].modifiers[0], existing);
+    assert!(objects[&camera].modifiers.is_empty());
+}
+
+/// Modifier edits are undoable and journaled, so they survive recovery.
+#[tokio::test]
+async fn test_modifier_undo_and_persistence() {
+    let data_dir = temp_data_dir();
+    let config = ServerConfig { data_dir: Some(data_dir.clone()), ..ServerConfig::default() };
+    let (url, _addr, state) = start_admin_test_server_with_config(config).await;
+    let dir = session_dir(&data_dir, "mod-persist");
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "mod-persist", "Alice").await;
+    let session = state.sessions.get("mod-persist").unwrap().clone();
+    snapshot_session(&session, &dir, None).unwrap();
+
+    let cube = Uuid::new_v4();
+    create_object(&mut ws_a, cube_payload(cube)).await;
+    let array = array(4);
+    send(&mut ws_a, add(cube, &array, None)).await;
+    recv_stack(&mut ws_a).await;
+    let mut longer = array.clone();
+    longer.kind = ModifierKind::Array(ArrayModifier { count: 8, ..ArrayModifier::default() });
+    send(&mut ws_a, ClientEvent::UpdateModifier(UpdateModifierPayload { object_id: cube, modifier: longer })).await;
+    recv_stack(&mut ws_a).await;
+
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::HistoryApplied(p) => assert_eq!(p.diff.updated[0].modifiers, vec![array.clone()]),
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+
+    let recovered = recover(&dir, None).unwrap().unwrap();
+    assert_eq!(recovered.objects[&cube].modifiers, [array]);
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
@@ -64,6 +64,7 @@ async fn test_phase_1_full_flow() {
         object_type: ObjectType::Cube,
         asset_id: None,
         asset_library: None,
+        parent_id: None,
         transform: Transform {
             position: [1.0, 2.0, 3.0],
             rotation: [0.0, 0.0, 0.0],
@@ -86,7 +87,7 @@ async fn test_phase_1_full_flow() {
     }
 
     // ── Step 4: Client A deletes the Cube ────────────────────────────────────
-    send(&mut ws_a, ClientEvent::DeleteObject(DeleteObjectPayload { object_id })).await;
+    send(&mut ws_a, ClientEvent::DeleteObject(DeleteObjectPayload { object_id, delete_children: false })).await;
 
     let deleted_a = recv(&mut ws_a).await;
     let deleted_b = recv(&mut ws_b).await;
@@ -153,6 +154,7 @@ async fn test_create_all_primitives() {
             object_type,
             asset_id: None,
             asset_library: None,
+            parent_id: None,
             transform: Transform {
                 position: [0.0, 0.0, 0.0],
                 rotation: [0.0, 0.0, 0.0],
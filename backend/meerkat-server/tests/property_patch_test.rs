@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    gltf,
+    messages::{ClientEvent, CreateObjectPayload, PatchPropertiesPayload, PropertyPatch, ServerEvent},
+    property_patch,
+    types::{ObjectProperties, ObjectType, SpotLightProperties, MAX_EXTRA_PROPERTIES_BYTES, MAX_PROPERTY_PATCHES},
+    usd,
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, join_session, recv, scene_object, send, start_test_server_with_state, try_recv,
+    WsStream,
+};
+
+fn patch(path: &str, value: Value) -> PropertyPatch {
+    PropertyPatch { path: path.to_string(), value: Some(value) }
+}
+
+fn remove(path: &str) -> PropertyPatch {
+    PropertyPatch { path: path.to_string(), value: None }
+}
+
+fn patch_event(object_id: Uuid, patches: Vec<PropertyPatch>) -> ClientEvent {
+    ClientEvent::PatchProperties(PatchPropertiesPayload { object_id, patches })
+}
+
+fn spot_light(angle: f32) -> ObjectProperties {
+    ObjectProperties::SpotLight(SpotLightProperties { angle, ..SpotLightProperties::default() })
+}
+
+async fn create_spot_light(ws: &mut WsStream, object_id: Uuid) {
+    send(ws, ClientEvent::CreateObject(CreateObjectPayload {
+        name: "Key".to_string(),
+        object_type: ObjectType::SpotLight,
+        properties: Some(spot_light(0.5)),
+        ..cube_payload(object_id)
+    }))
+    .await;
+    match recv(ws).await {
+        ServerEvent::ObjectCreated(p) => assert_eq!(p.object.object_id, object_id),
+        other => panic!("expected ObjectCreated, got {:?}", other),
+    }
+}
+
+/// Patches to known fields land in the typed properties, anything else in the extra
+/// properties, and every client gets the patches as sent.
+#[tokio::test]
+async fn test_patch_typed_and_extra_properties() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "patch-apply", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "patch-apply", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let light = Uuid::new_v4();
+    create_spot_light(&mut ws_a, light).await;
+    recv(&mut ws_b).await; // ObjectCreated
+
+    let patches = vec![
+        patch("spot.angle", json!(0.7)),
+        patch("spot.spread_hint", json!(0.2)),
+        patch("render.holdout", json!(true)),
+    ];
+    send(&mut ws_a, patch_event(light, patches.clone())).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::PropertiesPatched(p) => {
+                assert_eq!(p.object_id, light);
+                assert_eq!(p.patches, patches);
+            }
+            other => panic!("expected PropertiesPatched, got {:?}", other),
+        }
+    }
+    {
+        let session = state.sessions.get("patch-apply").unwrap();
+        let objects = session.objects.read().unwrap();
+        let object = &objects[&light];
+        assert_eq!(object.properties, Some(spot_light(0.7)));
+        assert_eq!(
+            Value::Object(object.extra_properties.clone()),
+            json!({"spot": {"spread_hint": 0.2}, "render": {"holdout": true}})
+        );
+        let document = property_patch::document(object);
+        assert_eq!(document["spot"]["spread_hint"], json!(0.2));
+        assert_eq!(document["spot"]["show_cone"], json!(false));
+    }
+
+    // Removing a typed field puts Blender's default back; removing an extra one drops it.
+    send(&mut ws_b, patch_event(light, vec![remove("spot.angle"), remove("render")])).await;
+    assert!(matches!(recv(&mut ws_a).await, ServerEvent::PropertiesPatched(_)));
+    {
+        let session = state.sessions.get("patch-apply").unwrap();
+        let objects = session.objects.read().unwrap();
+        assert_eq!(objects[&light].properties, Some(spot_light(SpotLightProperties::default().angle)));
+        assert_eq!(Value::Object(objects[&light].extra_properties.clone()), json!({"spot": {"spread_hint": 0.2}}));
+    }
+    recv(&mut ws_b).await; // PropertiesPatched
+
+    // Patches that change nothing are not broadcast.
+    send(&mut ws_a, patch_event(light, vec![patch("spot.spread_hint", json!(0.2)), remove("missing.path")])).await;
+    assert!(try_recv(&mut ws_b).await.is_none());
+}
+
+/// Bad paths, values a schema refuses and oversized documents are refused as a whole.
+#[tokio::test]
+async fn test_invalid_patches_are_rejected() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "patch-reject", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "patch-reject", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let light = Uuid::new_v4();
+    create_spot_light(&mut ws_a, light).await;
+    recv(&mut ws_b).await; // ObjectCreated
+    send(&mut ws_a, patch_event(light, vec![patch("render.holdout", json!(true))])).await;
+    recv(&mut ws_a).await; // PropertiesPatched
+    recv(&mut ws_b).await; // PropertiesPatched
+
+    let rejected = [
+        vec![patch("", json!(1))],
+        vec![patch("spot..angle", json!(1))],
+        vec![patch("spot.cone angle", json!(1))],
+        vec![patch("a.b.c.d.e.f.g.h.i", json!(1))],
+        vec![patch("spot.angle", json!(0.9)), patch("spot.angle", json!("wide"))],
+        vec![patch("spot", json!(3))],
+        vec![patch("camera.lens_type", json!("Fisheye"))],
+        vec![patch("render.holdout.mask", json!(1))],
+        vec![patch("notes", json!("x".repeat(MAX_EXTRA_PROPERTIES_BYTES)))],
+        (0..=MAX_PROPERTY_PATCHES).map(|i| patch(&format!("k{i}"), json!(i))).collect(),
+    ];
+    for patches in rejected {
+        send(&mut ws_a, patch_event(light, patches)).await;
+        match recv(&mut ws_a).await {
+            ServerEvent::Error(e) => assert_eq!(e.code, "INVALID_PROPERTY_PATCH"),
+            other => panic!("expected INVALID_PROPERTY_PATCH, got {:?}", other),
+        }
+    }
+    assert!(try_recv(&mut ws_b).await.is_none(), "rejected patches are not broadcast");
+
+    let session = state.sessions.get("patch-reject").unwrap();
+    let objects = session.objects.read().unwrap();
+    assert_eq!(objects[&light].properties, Some(spot_light(0.5)));
+    assert_eq!(Value::Object(objects[&light].extra_properties.clone()), json!({"render": {"holdout": true}}));
+}
+
+/// Property patches are undoable like any other object edit.
+#[tokio::test]
+async fn test_undo_property_patch() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "patch-undo", "Alice").await;
+
+    let light = Uuid::new_v4();
+    create_spot_light(&mut ws_a, light).await;
+    send(&mut ws_a, patch_event(light, vec![patch("spot.angle", json!(1.0)), patch("render.holdout", json!(true))])).await;
+    recv(&mut ws_a).await; // PropertiesPatched
+
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::HistoryApplied(p) => {
+            assert_eq!(p.diff.updated[0].properties, Some(spot_light(0.5)));
+            assert!(p.diff.updated[0].extra_properties.is_empty());
+        }
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+    let session = state.sessions.get("patch-undo").unwrap();
+    assert!(session.objects.read().unwrap()[&light].extra_properties.is_empty());
+}
+
+/// Objects without a typed schema keep everything as extra properties, still checked against
+/// the schemas they mention; exports keep extra properties.
+#[test]
+fn test_document_without_schema_and_exports() {
+    let mut object = scene_object("Crate", ObjectType::Cube, None);
+    assert!(property_patch::apply(&mut object, &[patch("render.holdout", json!(true)), patch("bevel.width", json!(0.02))]).unwrap());
+    assert_eq!(object.properties, None);
+    assert!(property_patch::apply(&mut object, &[patch("spot.angle", json!("wide"))]).is_err());
+    assert!(property_patch::apply(&mut object, &[patch("spot.angle", json!(0.3))]).unwrap());
+    assert_eq!(object.properties, None);
+    assert_eq!(object.extra_properties["spot"], json!({"angle": 0.3}));
+
+    let objects = HashMap::from([(object.object_id, object.clone())]);
+    let exported = gltf::export_objects("extra", &objects);
+    let imported = gltf::import_objects(&exported.document, 0);
+    assert_eq!(imported[0].extra_properties, object.extra_properties);
+
+    let layer = usd::export_objects("extra", &objects);
+    let imported = usd::import_objects(&usd::Layer::parse(&layer.to_usda()).unwrap(), 0);
+    assert_eq!(imported[0].extra_properties, object.extra_properties);
+}
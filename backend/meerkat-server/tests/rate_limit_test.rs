@@ -0,0 +1,215 @@
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    messages::{ClientEvent, CursorPayload, ServerEvent, UpdateNamePayload, UpdateTransformPayload},
+    rate_limit::{check, limit_for, RateClass, RateDecision, RATE_LIMIT_NOTIFY_COOLDOWN_MS},
+    types::{AppState, Transform},
+};
+
+mod common;
+
+use common::{create_session, cube_payload, join_session, recv, send, start_test_server, try_recv};
+
+#[test]
+fn token_bucket_drops_after_burst_and_refills_over_time() {
+    let state = AppState::new();
+    let connection_id = Uuid::new_v4();
+    let event = ClientEvent::UpdateCursor(CursorPayload { position: [0.0; 3] });
+    let limit = limit_for(RateClass::Cursor);
+    let start = 1_000_000;
+
+    for _ in 0..limit.burst as usize {
+        assert_eq!(check(&state, connection_id, &event, start).1, RateDecision::Allow);
+    }
+    assert_eq!(check(&state, connection_id, &event, start).1, RateDecision::DropAndNotify);
+    assert_eq!(check(&state, connection_id, &event, start).1, RateDecision::Drop, "client is told only once");
+
+    // One second later the bucket has refilled by `per_second` tokens; still inside the notify cooldown.
+    let later = start + 1_000;
+    for _ in 0..limit.per_second as usize {
+        assert_eq!(check(&state, connection_id, &event, later).1, RateDecision::Allow);
+    }
+    assert_eq!(check(&state, connection_id, &event, later).1, RateDecision::Drop);
+
+    // Once the cooldown has passed a renewed flood is reported again.
+    let much_later = start + RATE_LIMIT_NOTIFY_COOLDOWN_MS;
+    for _ in 0..limit.burst as usize {
+        assert_eq!(check(&state, connection_id, &event, much_later).1, RateDecision::Allow);
+    }
+    assert_eq!(check(&state, connection_id, &event, much_later).1, RateDecision::DropAndNotify);
+
+    // Buckets are per class: transforms are unaffected by the exhausted cursor bucket.
+    let transform = ClientEvent::UpdateTransform(UpdateTransformPayload {
+        object_id: Uuid::new_v4(),
+        transform: Transform { position: [0.0; 3], rotation: [0.0; 3], scale: [1.0; 3] },
+    });
+    assert_eq!(check(&state, connection_id, &transform, much_later), (RateClass::Transform, RateDecision::Allow));
+}
+
+/// A cursor flood is cut off at the bucket size and the sender gets a single RATE_LIMITED error.
+#[tokio::test]
+async fn test_cursor_flood_is_rate_limited() {
+    let url = start_test_server().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "rate-limit", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "rate-limit", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let burst = limit_for(RateClass::Cursor).burst as usize;
+    let sent = burst * 3;
+    for i in 0..sent {
+        send(&mut ws_a, ClientEvent::UpdateCursor(CursorPayload { position: [i as f64, 0.0, 0.0] })).await;
+    }
+
+    match recv(&mut ws_a).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "RATE_LIMITED"),
+        other => panic!("A: expected RATE_LIMITED error, got {:?}", other),
+    }
+    assert!(try_recv(&mut ws_a).await.is_none(), "A: only one RATE_LIMITED error per cooldown window");
+
+    let mut forwarded = 0;
+    while let Some(event) = try_recv(&mut ws_b).await {
+        assert!(matches!(event, ServerEvent::CursorUpdated(_)));
+        forwarded += 1;
+    }
+    // B's own queue may shed some of the burst, but never more than a burst (plus refill) gets through.
+    assert!(forwarded > 0, "B should see the allowed part of the flood");
+    assert!(forwarded <= burst + 30, "B should not see the whole flood, saw {}", forwarded);
+}
+
+/// Rapid transforms on one object collapse into fewer broadcasts carrying the latest value.
+#[tokio::test]
+async fn test_transform_updates_are_coalesced() {
+    let url = start_test_server().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "coalesce", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "coalesce", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let object_id = Uuid::new_v4();
+    send(&mut ws_a, ClientEvent::CreateObject(cube_payload(object_id))).await;
+    recv(&mut ws_a).await; // ObjectCreated
+    recv(&mut ws_b).await; // ObjectCreated
+
+    const MOVES: usize = 50;
+    for i in 1..=MOVES {
+        send(&mut ws_a, ClientEvent::UpdateTransform(UpdateTransformPayload {
+            object_id,
+            transform: Transform { position: [i as f64, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+        })).await;
+    }
+
+    let mut received = Vec::new();
+    while let Some(event) = try_recv(&mut ws_b).await {
+        match event {
+            ServerEvent::TransformUpdated(p) => received.push(p.transform.position[0]),
+            other => panic!("B: expected TransformUpdated, got {:?}", other),
+        }
+    }
+    assert!(!received.is_empty(), "B should receive at least one TransformUpdated");
+    assert!(received.len() < MOVES, "expected coalescing, got {} broadcasts", received.len());
+    assert_eq!(received.last(), Some(&(MOVES as f64)), "last broadcast carries the final position");
+}
+
+#[test]
+fn over_limit_transforms_are_coalesced_and_edits_rejected() {
+    let state = AppState::new();
+    let connection_id = Uuid::new_v4();
+    let start = 1_000_000;
+
+    let transform = ClientEvent::UpdateTransform(UpdateTransformPayload {
+        object_id: Uuid::new_v4(),
+        transform: Transform { position: [0.0; 3], rotation: [0.0; 3], scale: [1.0; 3] },
+    });
+    for _ in 0..limit_for(RateClass::Transform).burst as usize {
+        assert_eq!(check(&state, connection_id, &transform, start).1, RateDecision::Allow);
+    }
+    assert_eq!(check(&state, connection_id, &transform, start).1, RateDecision::Coalesce);
+
+    let rename = ClientEvent::UpdateName(UpdateNamePayload { object_id: Uuid::new_v4(), name: "Cube".to_string() });
+    let edit = limit_for(RateClass::Edit);
+    for _ in 0..edit.burst as usize {
+        assert_eq!(check(&state, connection_id, &rename, start).1, RateDecision::Allow);
+    }
+    let retry_after_ms = (1000.0 / edit.per_second).ceil() as u64;
+    assert_eq!(check(&state, connection_id, &rename, start).1, RateDecision::Reject { retry_after_ms });
+    assert_eq!(
+        check(&state, connection_id, &rename, start).1,
+        RateDecision::Reject { retry_after_ms },
+        "every refused edit is reported, not just the first"
+    );
+    assert_eq!(check(&state, connection_id, &rename, start + retry_after_ms).1, RateDecision::Allow);
+}
+
+/// A drag past the transform limit still ends with peers at the final position.
+#[tokio::test]
+async fn test_over_limit_drag_delivers_final_transform() {
+    let url = start_test_server().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "held-transforms", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "held-transforms", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let object_id = Uuid::new_v4();
+    send(&mut ws_a, ClientEvent::CreateObject(cube_payload(object_id))).await;
+    recv(&mut ws_a).await; // ObjectCreated
+    recv(&mut ws_b).await; // ObjectCreated
+
+    let moves = limit_for(RateClass::Transform).burst as usize + 200;
+    for i in 1..=moves {
+        send(&mut ws_a, ClientEvent::UpdateTransform(UpdateTransformPayload {
+            object_id,
+            transform: Transform { position: [i as f64, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+        })).await;
+    }
+
+    let mut last = None;
+    while let Some(event) = try_recv(&mut ws_b).await {
+        if let ServerEvent::TransformUpdated(p) = event {
+            last = Some(p.transform.position[0]);
+        }
+    }
+    assert_eq!(last, Some(moves as f64), "B ends at the final position of the drag");
+    while let Some(event) = try_recv(&mut ws_a).await {
+        assert!(
+            !matches!(event, ServerEvent::Error(_) | ServerEvent::EventRejected(_)),
+            "held transforms are not reported to A: {:?}",
+            event
+        );
+    }
+}
+
+/// Edits past the limit come back to the sender as EventRejected, carrying the event to retry.
+#[tokio::test]
+async fn test_over_limit_edits_are_rejected_for_retry() {
+    let url = start_test_server().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "rejected-edits", "Alice").await;
+
+    let object_id = Uuid::new_v4();
+    send(&mut ws_a, ClientEvent::CreateObject(cube_payload(object_id))).await;
+    recv(&mut ws_a).await; // ObjectCreated
+
+    let renames = limit_for(RateClass::Edit).burst as usize + 20;
+    for i in 0..renames {
+        send(&mut ws_a, ClientEvent::UpdateName(UpdateNamePayload { object_id, name: format!("Cube {i}") })).await;
+    }
+
+    let mut rejected = Vec::new();
+    while let Some(event) = try_recv(&mut ws_a).await {
+        if let ServerEvent::EventRejected(p) = event {
+            assert_eq!(p.code, "RATE_LIMITED");
+            assert!(p.retry_after_ms > 0);
+            match p.event {
+                ClientEvent::UpdateName(name) => rejected.push(name.name),
+                other => panic!("expected the rejected UpdateName back, got {:?}", other),
+            }
+        }
+    }
+    assert!(!rejected.is_empty(), "some renames should be refused");
+    assert_eq!(rejected.last(), Some(&format!("Cube {}", renames - 1)), "the latest refused rename comes back");
+}
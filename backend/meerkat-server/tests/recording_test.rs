@@ -0,0 +1,180 @@
+use std::path::{Path, PathBuf};
+
+use axum::{routing::any, Router};
+use tokio::time::{sleep, Duration};
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    config::ServerConfig,
+    messages::{ClientEvent, DeleteObjectPayload, JoinSessionPayload, ServerEvent, UpdateTransformPayload},
+    recording::{read_recording, redacted, RecordedFrame, RECORDINGS_DIR_NAME},
+    replay::{self, ReplayOptions, ReplayStatus, Timeline},
+    storage::session_dir,
+    types::{AppState, Transform},
+    websocket::tcp_socket_upgrade,
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, http_get, http_request, join_session, recv, send, start_admin_test_server_with_config,
+    temp_data_dir, try_recv, TEST_PASSWORD,
+};
+
+/// Create a cube, drag it, delete a second one, then leave so the recording is closed.
+/// Returns the recording file and the dragged cube's id.
+async fn record_session(data_dir: &Path, session_id: &str) -> (PathBuf, Uuid) {
+    let config = ServerConfig { data_dir: Some(data_dir.to_path_buf()), record_sessions: true, ..ServerConfig::default() };
+    let (url, _addr, state) = start_admin_test_server_with_config(config).await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, session_id, "Alice").await;
+
+    let cube = Uuid::new_v4();
+    let doomed = Uuid::new_v4();
+    for id in [cube, doomed] {
+        send(&mut ws, ClientEvent::CreateObject(cube_payload(id))).await;
+        recv(&mut ws).await; // ObjectCreated
+    }
+    send(&mut ws, ClientEvent::UpdateTransform(UpdateTransformPayload {
+        object_id: cube,
+        transform: Transform { position: [5.0, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+    })).await;
+    recv(&mut ws).await; // TransformUpdated
+    send(&mut ws, ClientEvent::DeleteObject(DeleteObjectPayload { object_id: doomed, delete_children: false })).await;
+    recv(&mut ws).await; // ObjectDeleted
+
+    ws.close(None).await.unwrap();
+    for _ in 0..50 {
+        if !state.sessions.contains_key(session_id) {
+            break;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    assert!(!state.sessions.contains_key(session_id), "session should be reclaimed");
+
+    let dir = session_dir(data_dir, session_id).join(RECORDINGS_DIR_NAME);
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+    assert_eq!(files.len(), 1, "one recording per session: {files:?}");
+    (files.pop().unwrap(), cube)
+}
+
+/// Accepted client events and the broadcasts they caused end up in the file, in order.
+#[tokio::test]
+async fn test_session_is_recorded() {
+    let data_dir = temp_data_dir();
+    let (path, cube) = record_session(&data_dir, "recorded").await;
+    assert_eq!(path.extension().unwrap(), "mrec");
+
+    let frames = read_recording(&path, None).unwrap();
+    match &frames[0] {
+        RecordedFrame::Start { session_id, objects, .. } => {
+            assert_eq!(session_id, "recorded");
+            assert!(objects.is_empty());
+        }
+        other => panic!("expected Start, got {:?}", other),
+    }
+    let client: Vec<&ClientEvent> = frames
+        .iter()
+        .filter_map(|frame| match frame {
+            RecordedFrame::Client { event, .. } => Some(event),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(client.len(), 4);
+    assert!(matches!(client[2], ClientEvent::UpdateTransform(p) if p.object_id == cube));
+    let server_types: Vec<String> = frames
+        .iter()
+        .filter_map(|frame| match frame {
+            RecordedFrame::Server { event, .. } => {
+                let value: serde_json::Value = serde_json::from_str(event.get()).unwrap();
+                Some(value["event_type"].as_str().unwrap().to_string())
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(server_types, ["ObjectCreated", "ObjectCreated", "TransformUpdated", "ObjectDeleted"]);
+
+    // A crash mid-block loses only that block.
+    let mut torn = std::fs::read(&path).unwrap();
+    torn.extend_from_slice(&[200, 0, 0, 0, 1, 2, 3]);
+    let torn_path = data_dir.join("torn.mrec");
+    std::fs::write(&torn_path, torn).unwrap();
+    assert_eq!(read_recording(&torn_path, None).unwrap().len(), frames.len());
+
+    let join = redacted(&ClientEvent::JoinSession(JoinSessionPayload {
+        session_id: "recorded".to_string(),
+        display_name: "Bob".to_string(),
+        password: TEST_PASSWORD.to_string(),
+    }));
+    assert!(matches!(join, ClientEvent::JoinSession(p) if p.password.is_empty()));
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
+
+/// Viewers of a replay see the recorded edits, can seek, and cannot edit.
+#[tokio::test]
+async fn test_replay_plays_back_and_seeks() {
+    let data_dir = temp_data_dir();
+    let (path, cube) = record_session(&data_dir, "replayed").await;
+    let timeline = Timeline::load(&path, None).unwrap();
+    assert_eq!(timeline.frames.len(), 4);
+    assert_eq!(timeline.objects_at(timeline.duration_ms()).len(), 1);
+
+    let state = AppState::with_config(ServerConfig { read_only: true, ..ServerConfig::default() });
+    let hash = bcrypt::hash(TEST_PASSWORD, 4).unwrap();
+    let options = ReplayOptions { speed: 100.0, paused: true, ..ReplayOptions::default() };
+    let control = replay::start(&state, timeline, hash, options).unwrap();
+    let app = Router::new()
+        .route("/ws", any(tcp_socket_upgrade))
+        .with_state(state.clone())
+        .merge(replay::router(control));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let (mut ws, _) = connect_async(format!("ws://{addr}/ws")).await.unwrap();
+    join_session(&mut ws, "replayed", "Viewer").await;
+
+    send(&mut ws, ClientEvent::CreateObject(cube_payload(Uuid::new_v4()))).await;
+    match recv(&mut ws).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "READ_ONLY"),
+        other => panic!("expected READ_ONLY, got {:?}", other),
+    }
+    assert!(try_recv(&mut ws).await.is_none(), "paused replay sends nothing");
+
+    let (status, _) = http_request(&addr, "POST", "/replay/resume", None, &[]).await;
+    assert_eq!(status, 200);
+    let mut played = Vec::new();
+    for _ in 0..4 {
+        played.push(recv(&mut ws).await);
+    }
+    assert!(matches!(&played[2], ServerEvent::TransformUpdated(p) if p.object_id == cube));
+    assert!(matches!(&played[3], ServerEvent::ObjectDeleted(_)));
+
+    let (_, body) = http_get(&addr, "/replay", None).await;
+    let status: ReplayStatus = serde_json::from_str(&body).unwrap();
+    assert_eq!(status.frames_played, 4);
+    assert_eq!(status.position_ms, status.duration_ms);
+
+    http_request(&addr, "POST", "/replay/pause", None, &[]).await;
+    let (status, _) = http_request(&addr, "POST", "/replay/seek?to_ms=0", None, &[]).await;
+    assert_eq!(status, 200);
+    match recv(&mut ws).await {
+        ServerEvent::FullStateSync(p) => assert!(p.session.objects.is_empty(), "seek to start shows the empty scene"),
+        other => panic!("expected FullStateSync, got {:?}", other),
+    }
+    assert_eq!(state.sessions.get("replayed").unwrap().objects.read().unwrap().len(), 0);
+
+    let (status, _) = http_request(&addr, "POST", "/replay/speed?factor=0", None, &[]).await;
+    assert_eq!(status, 400);
+
+    // The replay session outlives its last viewer.
+    ws.close(None).await.unwrap();
+    sleep(Duration::from_millis(100)).await;
+    assert!(state.sessions.contains_key("replayed"));
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
@@ -0,0 +1,139 @@
+use std::collections::{BTreeMap, HashMap};
+
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    checksum::{format_checksum, objects_checksum},
+    handlers::helpers::STATE_SYNC_CHUNK_SIZE,
+    messages::{ClientEvent, JoinSessionPayload, ServerEvent},
+    types::{ObjectType, SceneObject, Transform},
+};
+
+mod common;
+
+use common::{create_session, cube_payload, recv, send, start_test_server_with_state, try_recv, TEST_PASSWORD};
+
+fn seeded_object(index: usize) -> SceneObject {
+    SceneObject {
+        object_id: Uuid::new_v4(),
+        name: format!("Prop.{:04}", index),
+        object_type: ObjectType::Cube,
+        asset_id: None,
+        asset_library: None,
+        parent_id: None,
+        transform: Transform { position: [index as f64, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+        properties: None,
+        collections: Vec::new(),
+        custom_properties: BTreeMap::new(),
+        extra_properties: serde_json::Map::new(),
+        material_slots: Vec::new(),
+        mesh: None,
+        mesh_version: 0,
+        modifiers: Vec::new(),
+        created_by: Uuid::nil(),
+        last_updated_by: Uuid::nil(),
+        last_updated_at: 0,
+    }
+}
+
+/// A scene larger than one chunk is streamed as Begin / Chunk* / End, and the
+/// reassembled objects match both the object count and the advertised checksum.
+#[tokio::test]
+async fn test_large_scene_is_streamed_in_chunks() {
+    let (url, state) = start_test_server_with_state().await;
+
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "chunked-sync", "Alice").await;
+
+    let object_count = STATE_SYNC_CHUNK_SIZE * 2 + 17;
+    {
+        let session = state.sessions.get("chunked-sync").expect("session should exist");
+        let mut objects = session.objects.write().unwrap();
+        for i in 0..object_count {
+            let object = seeded_object(i);
+            objects.insert(object.object_id, object);
+        }
+    }
+
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    send(&mut ws_b, ClientEvent::JoinSession(JoinSessionPayload {
+        session_id: "chunked-sync".to_string(),
+        display_name: "Bob".to_string(),
+        password: TEST_PASSWORD.to_string(),
+    })).await;
+
+    let chunk_count = match recv(&mut ws_b).await {
+        ServerEvent::StateSyncBegin(p) => {
+            assert_eq!(p.object_count, object_count);
+            assert_eq!(p.users.len(), 2, "Begin should carry the full user list");
+            assert!(p.users.contains_key(&p.your_user_id));
+            p.chunk_count
+        }
+        other => panic!("B: expected StateSyncBegin, got {:?}", other),
+    };
+    assert_eq!(chunk_count, 3);
+
+    let mut received: HashMap<Uuid, SceneObject> = HashMap::new();
+    for expected_index in 0..chunk_count {
+        match recv(&mut ws_b).await {
+            ServerEvent::StateSyncChunk(p) => {
+                assert_eq!(p.chunk_index, expected_index);
+                assert!(p.objects.len() <= STATE_SYNC_CHUNK_SIZE);
+                for object in p.objects {
+                    received.insert(object.object_id, object);
+                }
+            }
+            other => panic!("B: expected StateSyncChunk {}, got {:?}", expected_index, other),
+        }
+    }
+
+    match recv(&mut ws_b).await {
+        ServerEvent::StateSyncEnd(p) => {
+            assert_eq!(p.object_count, object_count);
+            assert_eq!(received.len(), object_count, "every object should arrive exactly once");
+            assert_eq!(p.checksum, format_checksum(objects_checksum(received.values())));
+        }
+        other => panic!("B: expected StateSyncEnd, got {:?}", other),
+    }
+
+    // Live traffic resumes after the stream closes.
+    match recv(&mut ws_a).await {
+        ServerEvent::UserJoined(p) => assert_eq!(p.display_name, "Bob"),
+        other => panic!("A: expected UserJoined, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_sync_does_not_replay_broadcasts_it_already_covers() {
+    let (url, _state) = start_test_server_with_state().await;
+
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "stale-backlog", "Alice").await;
+
+    // Each ObjectCreated is queued for Alice before her sync request is read, so the sync's
+    // snapshot already holds the object whichever the server gets to first.
+    for _ in 0..12 {
+        let object_id = Uuid::new_v4();
+        send(&mut ws_a, ClientEvent::CreateObject(cube_payload(object_id))).await;
+        send(&mut ws_a, ClientEvent::RequestStateSync(None)).await;
+    }
+
+    let mut synced: Vec<Uuid> = Vec::new();
+    let mut syncs = 0;
+    while let Some(event) = try_recv(&mut ws_a).await {
+        match event {
+            ServerEvent::FullStateSync(p) => {
+                syncs += 1;
+                synced = p.session.objects.into_keys().collect();
+            }
+            ServerEvent::ObjectCreated(p) => assert!(
+                !synced.contains(&p.object.object_id),
+                "ObjectCreated arrived after a sync that already held the object"
+            ),
+            other => panic!("A: unexpected {:?}", other),
+        }
+    }
+    assert_eq!(syncs, 12);
+    assert_eq!(synced.len(), 12);
+}
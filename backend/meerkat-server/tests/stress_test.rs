@@ -9,11 +9,9 @@
 ///   2. stress_30_clients_one_session    — broadcast fan-out to N recipients; mpsc channel(32) limit
 ///   3. stress_500_rapid_fire            — sustained throughput; zero message loss with concurrent drain
 ///   4. stress_20_sessions_x_5_clients   — 100 simultaneous connections across isolated sessions
-use std::sync::Arc;
 use std::time::Instant;
 
 use axum::{routing::any, Router};
-use dashmap::DashMap;
 use futures_util::{SinkExt, StreamExt};
 use tokio::net::TcpStream;
 use tokio::task::JoinSet;
@@ -36,13 +34,7 @@ type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 // ── Helpers ───────────────────────────────────────────────────────────────────
 
 async fn start_server() -> String {
-    let state = AppState {
-        sessions: Arc::new(DashMap::new()),
-        connections: Arc::new(DashMap::new()),
-        connection_meta: Arc::new(DashMap::new()),
-        connection_backpressure: Arc::new(DashMap::new()),
-        session_connections: Arc::new(DashMap::new()),
-    };
+    let state = AppState::new();
     let app = Router::new().route("/ws", any(tcp_socket_upgrade)).with_state(state);
     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
     let port = listener.local_addr().unwrap().port();
@@ -136,6 +128,7 @@ fn cube(id: Uuid) -> CreateObjectPayload {
         object_type: ObjectType::Cube,
         asset_id: None,
         asset_library: None,
+        parent_id: None,
         transform: Transform { position: [0.0; 3], rotation: [0.0; 3], scale: [1.0; 3] },
         properties: None,
     }
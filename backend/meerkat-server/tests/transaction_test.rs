@@ -0,0 +1,164 @@
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    handlers::update_transform::flush_pending_transforms,
+    messages::{
+        ClientEvent, DeleteObjectPayload, ServerEvent, TransactionOp, TransactionPayload, UpdateNamePayload,
+        UpdateTransformPayload,
+    },
+    types::{PendingTransform, Transform},
+};
+
+mod common;
+
+use common::{create_session, cube_payload, join_session, recv, send, start_test_server_with_state, try_recv};
+
+fn moved_to(object_id: Uuid, x: f64) -> TransactionOp {
+    TransactionOp::UpdateTransform(UpdateTransformPayload {
+        object_id,
+        transform: Transform { position: [x, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+    })
+}
+
+/// A mixed transaction is applied in order and peers see a single TransactionApplied.
+#[tokio::test]
+async fn test_transaction_applies_all_operations_with_one_broadcast() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "txn-apply", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "txn-apply", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let kept = Uuid::new_v4();
+    let removed = Uuid::new_v4();
+    for id in [kept, removed] {
+        send(&mut ws_a, ClientEvent::CreateObject(cube_payload(id))).await;
+        recv(&mut ws_a).await; // ObjectCreated
+        recv(&mut ws_b).await; // ObjectCreated
+    }
+
+    let added = Uuid::new_v4();
+    let transaction_id = Uuid::new_v4();
+    send(&mut ws_a, ClientEvent::Transaction(TransactionPayload {
+        transaction_id,
+        operations: vec![
+            moved_to(kept, 5.0),
+            TransactionOp::UpdateName(UpdateNamePayload { object_id: kept, name: "Pillar".to_string() }),
+            TransactionOp::DeleteObject(DeleteObjectPayload { object_id: removed, delete_children: false }),
+            TransactionOp::CreateObject(cube_payload(added)),
+            moved_to(added, 7.0),
+        ],
+    })).await;
+
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::TransactionApplied(p) => {
+                assert_eq!(p.transaction_id, transaction_id);
+                assert_eq!(p.operations.len(), 5);
+                assert_eq!(p.version, 5, "three objects touched after two creates");
+            }
+            other => panic!("expected TransactionApplied, got {:?}", other),
+        }
+        assert!(try_recv(ws).await.is_none(), "no per-object events alongside the transaction");
+    }
+
+    let session = state.sessions.get("txn-apply").unwrap();
+    let objects = session.objects.read().unwrap();
+    assert_eq!(objects.len(), 2);
+    assert_eq!(objects[&kept].name, "Pillar");
+    assert_eq!(objects[&kept].transform.position, [5.0, 0.0, 0.0]);
+    assert_eq!(objects[&added].transform.position, [7.0, 0.0, 0.0]);
+    assert!(!objects.contains_key(&removed));
+}
+
+/// One invalid operation rejects the whole transaction; nothing before it is applied.
+#[tokio::test]
+async fn test_invalid_operation_rejects_whole_transaction() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "txn-reject", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "txn-reject", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let existing = Uuid::new_v4();
+    send(&mut ws_a, ClientEvent::CreateObject(cube_payload(existing))).await;
+    recv(&mut ws_a).await; // ObjectCreated
+    recv(&mut ws_b).await; // ObjectCreated
+
+    let cases = vec![
+        // Missing object after a valid move.
+        vec![moved_to(existing, 3.0), moved_to(Uuid::new_v4(), 1.0)],
+        // Delete then touch the same object.
+        vec![TransactionOp::DeleteObject(DeleteObjectPayload { object_id: existing, delete_children: false }), moved_to(existing, 2.0)],
+        // Create over an existing id.
+        vec![moved_to(existing, 4.0), TransactionOp::CreateObject(cube_payload(existing))],
+    ];
+
+    for operations in cases {
+        send(&mut ws_a, ClientEvent::Transaction(TransactionPayload {
+            transaction_id: Uuid::new_v4(),
+            operations,
+        })).await;
+        match recv(&mut ws_a).await {
+            ServerEvent::Error(e) => assert_eq!(e.code, "TRANSACTION_REJECTED"),
+            other => panic!("expected TRANSACTION_REJECTED, got {:?}", other),
+        }
+    }
+    assert!(try_recv(&mut ws_b).await.is_none(), "rejected transactions are not broadcast");
+
+    let session = state.sessions.get("txn-reject").unwrap();
+    assert_eq!(session.current_version(), 1, "no change was recorded");
+    let objects = session.objects.read().unwrap();
+    assert_eq!(objects[&existing].transform.position, [0.0, 0.0, 0.0]);
+}
+
+/// A rename-only transaction while a coalesced transform is still queued must not discard it;
+/// peers still end up at the dragged position once the queue is flushed.
+#[tokio::test]
+async fn test_rename_transaction_keeps_coalesced_transform() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "txn-coalesced", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "txn-coalesced", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let renamed = Uuid::new_v4();
+    let moved = Uuid::new_v4();
+    for id in [renamed, moved] {
+        send(&mut ws_a, ClientEvent::CreateObject(cube_payload(id))).await;
+        recv(&mut ws_a).await; // ObjectCreated
+        recv(&mut ws_b).await; // ObjectCreated
+    }
+
+    // Both objects have a transform waiting out its coalescing window.
+    let session = state.sessions.get("txn-coalesced").map(|s| s.value().clone()).unwrap();
+    for id in [renamed, moved] {
+        session.pending_transforms.lock().unwrap().insert(id, PendingTransform {
+            transform: Transform { position: [4.0, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+            updated_by: Uuid::nil(),
+            journal_seq: 0,
+        });
+    }
+    send(&mut ws_a, ClientEvent::Transaction(TransactionPayload {
+        transaction_id: Uuid::new_v4(),
+        operations: vec![
+            TransactionOp::UpdateName(UpdateNamePayload { object_id: renamed, name: "Crate".to_string() }),
+            moved_to(moved, 9.0),
+        ],
+    })).await;
+    assert!(matches!(recv(&mut ws_b).await, ServerEvent::TransactionApplied(_)));
+
+    assert!(flush_pending_transforms(&state, &session));
+    match recv(&mut ws_b).await {
+        ServerEvent::TransformUpdated(p) => {
+            assert_eq!(p.object_id, renamed, "only the renamed object's transform is still queued");
+            assert_eq!(p.transform.position, [4.0, 0.0, 0.0]);
+        }
+        other => panic!("B: expected TransformUpdated, got {:?}", other),
+    }
+    assert!(try_recv(&mut ws_b).await.is_none(), "the overwritten transform was discarded");
+}
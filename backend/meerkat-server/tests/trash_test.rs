@@ -0,0 +1,113 @@
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    config::DEFAULT_TRASH_RETENTION,
+    messages::{ClientEvent, DeleteObjectPayload, RestoreObjectPayload, ServerEvent, UpdateTransformPayload},
+    trash::purge_expired,
+    types::Transform,
+};
+
+mod common;
+
+use common::{create_session, cube_payload, join_session, recv, send, start_test_server_with_state};
+
+/// A deleted object keeps its dressing in the trash and comes back to everyone as ObjectCreated.
+#[tokio::test]
+async fn test_deleted_object_can_be_restored_from_trash() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "trash-restore", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "trash-restore", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let cube = Uuid::new_v4();
+    send(&mut ws_a, ClientEvent::CreateObject(cube_payload(cube))).await;
+    let alice_id = match recv(&mut ws_a).await {
+        ServerEvent::ObjectCreated(p) => p.created_by,
+        other => panic!("expected ObjectCreated, got {:?}", other),
+    };
+    recv(&mut ws_b).await; // ObjectCreated
+    send(&mut ws_a, ClientEvent::UpdateTransform(UpdateTransformPayload {
+        object_id: cube,
+        transform: Transform { position: [1.0, 2.0, 3.0], rotation: [0.0; 3], scale: [2.0; 3] },
+    })).await;
+    recv(&mut ws_a).await; // TransformUpdated
+    recv(&mut ws_b).await; // TransformUpdated
+
+    send(&mut ws_b, ClientEvent::DeleteObject(DeleteObjectPayload { object_id: cube, delete_children: false })).await;
+    recv(&mut ws_a).await; // ObjectDeleted
+    let bob_id = match recv(&mut ws_b).await {
+        ServerEvent::ObjectDeleted(p) => p.deleted_by,
+        other => panic!("expected ObjectDeleted, got {:?}", other),
+    };
+
+    send(&mut ws_a, ClientEvent::ListTrash).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::TrashList(p) => {
+            assert_eq!(p.items.len(), 1);
+            let item = &p.items[0];
+            assert_eq!(item.object.object_id, cube);
+            assert_eq!(item.deleted_by, bob_id);
+            assert_eq!(item.expires_at - item.deleted_at, DEFAULT_TRASH_RETENTION.as_millis() as u64);
+        }
+        other => panic!("expected TrashList, got {:?}", other),
+    }
+
+    send(&mut ws_a, ClientEvent::RestoreObject(RestoreObjectPayload { object_id: cube })).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::ObjectCreated(p) => {
+                assert_eq!(p.object.object_id, cube);
+                assert_eq!(p.object.transform.position, [1.0, 2.0, 3.0]);
+                assert_eq!(p.created_by, alice_id, "original creator is kept");
+                assert_eq!(p.restored_by, Some(alice_id));
+            }
+            other => panic!("expected ObjectCreated, got {:?}", other),
+        }
+    }
+    assert!(state.sessions.get("trash-restore").unwrap().lock_trash().is_empty());
+
+    send(&mut ws_b, ClientEvent::RestoreObject(RestoreObjectPayload { object_id: cube })).await;
+    match recv(&mut ws_b).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "TRASH_ITEM_NOT_FOUND"),
+        other => panic!("expected TRASH_ITEM_NOT_FOUND, got {:?}", other),
+    }
+}
+
+/// Undoing a delete takes the object back out of the trash; expired items cannot be restored.
+#[tokio::test]
+async fn test_trash_undo_and_expiry() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, "trash-expiry", "Alice").await;
+
+    let undone = Uuid::new_v4();
+    let expired = Uuid::new_v4();
+    for id in [undone, expired] {
+        send(&mut ws, ClientEvent::CreateObject(cube_payload(id))).await;
+        recv(&mut ws).await; // ObjectCreated
+    }
+    send(&mut ws, ClientEvent::DeleteObject(DeleteObjectPayload { object_id: expired, delete_children: false })).await;
+    recv(&mut ws).await; // ObjectDeleted
+    send(&mut ws, ClientEvent::DeleteObject(DeleteObjectPayload { object_id: undone, delete_children: false })).await;
+    recv(&mut ws).await; // ObjectDeleted
+
+    send(&mut ws, ClientEvent::Undo).await;
+    assert!(matches!(recv(&mut ws).await, ServerEvent::HistoryApplied(_)));
+
+    let session = state.sessions.get("trash-expiry").unwrap().clone();
+    assert_eq!(session.lock_trash().keys().copied().collect::<Vec<_>>(), vec![expired]);
+
+    let deleted_at = session.lock_trash()[&expired].deleted_at;
+    let retention = state.config.trash_retention;
+    assert_eq!(purge_expired(&session, deleted_at + 1, retention), 0);
+    assert_eq!(purge_expired(&session, deleted_at + retention.as_millis() as u64, retention), 1);
+
+    send(&mut ws, ClientEvent::RestoreObject(RestoreObjectPayload { object_id: expired })).await;
+    match recv(&mut ws).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "TRASH_ITEM_NOT_FOUND"),
+        other => panic!("expected TRASH_ITEM_NOT_FOUND, got {:?}", other),
+    }
+}
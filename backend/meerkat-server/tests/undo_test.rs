@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    history::{HistoryEntry, ObjectChange, UndoHistory},
+    messages::{
+        ClientEvent, HistoryAction, ServerEvent, TransactionOp, TransactionPayload, UpdateTransformPayload,
+    },
+    types::{ObjectType, SceneObject, Transform},
+};
+
+mod common;
+
+use common::{create_session, cube_payload, join_session, recv, send, start_test_server_with_state, try_recv};
+
+fn move_to(object_id: Uuid, x: f64) -> UpdateTransformPayload {
+    UpdateTransformPayload {
+        object_id,
+        transform: Transform { position: [x, 0.0, 0.0], rotation: [0.0; 3], scale: [1.0; 3] },
+    }
+}
+
+fn cube_at(object_id: Uuid, x: f64) -> SceneObject {
+    SceneObject {
+        object_id,
+        name: "Cube".to_string(),
+        object_type: ObjectType::Cube,
+        asset_id: None,
+        asset_library: None,
+        parent_id: None,
+        transform: move_to(object_id, x).transform,
+        properties: None,
+        collections: Vec::new(),
+        custom_properties: BTreeMap::new(),
+        extra_properties: serde_json::Map::new(),
+        material_slots: Vec::new(),
+        mesh: None,
+        mesh_version: 0,
+        modifiers: Vec::new(),
+        created_by: Uuid::nil(),
+        last_updated_by: Uuid::nil(),
+        last_updated_at: 0,
+    }
+}
+
+fn transform_entry(object_id: Uuid, at_ms: u64, from: f64, to: f64) -> HistoryEntry {
+    HistoryEntry {
+        event_type: "UpdateTransform",
+        at_ms,
+        changes: vec![ObjectChange {
+            object_id,
+            before: Some(cube_at(object_id, from)),
+            after: Some(cube_at(object_id, to)),
+        }],
+        collections: Vec::new(),
+    }
+}
+
+#[test]
+fn drag_ticks_merge_into_one_undo_step() {
+    let user = Uuid::new_v4();
+    let object_id = Uuid::new_v4();
+    let mut history = UndoHistory::default();
+    history.record(user, transform_entry(object_id, 1_000, 0.0, 1.0));
+    history.record(user, transform_entry(object_id, 1_050, 1.0, 2.0));
+    history.record(user, transform_entry(object_id, 1_100, 2.0, 3.0));
+    // A pause longer than the merge window starts a new step.
+    history.record(user, transform_entry(object_id, 5_000, 3.0, 4.0));
+
+    let latest = history.take(user, HistoryAction::Undo).unwrap();
+    assert_eq!(latest.changes[0].before.as_ref().unwrap().transform.position[0], 3.0);
+    let drag = history.take(user, HistoryAction::Undo).unwrap();
+    assert_eq!(drag.changes[0].before.as_ref().unwrap().transform.position[0], 0.0);
+    assert_eq!(drag.changes[0].after.as_ref().unwrap().transform.position[0], 3.0);
+    assert!(history.take(user, HistoryAction::Undo).is_none());
+}
+
+/// Undo/Redo walk back and forth through one user's steps without touching another user's objects.
+#[tokio::test]
+async fn test_undo_and_redo_only_affect_own_changes() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "undo-own", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "undo-own", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let alice_cube = Uuid::new_v4();
+    send(&mut ws_a, ClientEvent::CreateObject(cube_payload(alice_cube))).await;
+    recv(&mut ws_a).await; // ObjectCreated
+    recv(&mut ws_b).await; // ObjectCreated
+    send(&mut ws_a, ClientEvent::UpdateTransform(move_to(alice_cube, 5.0))).await;
+    recv(&mut ws_a).await; // TransformUpdated
+    recv(&mut ws_b).await; // TransformUpdated
+
+    let bob_cube = Uuid::new_v4();
+    send(&mut ws_b, ClientEvent::CreateObject(cube_payload(bob_cube))).await;
+    recv(&mut ws_a).await; // ObjectCreated
+    recv(&mut ws_b).await; // ObjectCreated
+
+    // First undo reverts Alice's move, not Bob's later create.
+    send(&mut ws_a, ClientEvent::Undo).await;
+    for ws in [&mut ws_a, &mut ws_b] {
+        match recv(ws).await {
+            ServerEvent::HistoryApplied(p) => {
+                assert_eq!(p.action, HistoryAction::Undo);
+                assert_eq!(p.diff.updated.len(), 1);
+                assert_eq!(p.diff.updated[0].transform.position, [0.0, 0.0, 0.0]);
+                assert!(p.diff.created.is_empty() && p.diff.deleted.is_empty() && p.conflicts.is_empty());
+            }
+            other => panic!("expected HistoryApplied, got {:?}", other),
+        }
+    }
+
+    // Second undo removes Alice's cube.
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_b).await {
+        ServerEvent::HistoryApplied(p) => assert_eq!(p.diff.deleted, vec![alice_cube]),
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+    recv(&mut ws_a).await; // HistoryApplied
+
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "NOTHING_TO_UNDO"),
+        other => panic!("expected NOTHING_TO_UNDO, got {:?}", other),
+    }
+
+    // Redo brings the cube back, then re-applies the move.
+    send(&mut ws_a, ClientEvent::Redo).await;
+    match recv(&mut ws_b).await {
+        ServerEvent::HistoryApplied(p) => {
+            assert_eq!(p.action, HistoryAction::Redo);
+            assert_eq!(p.diff.created.len(), 1);
+            assert_eq!(p.diff.created[0].object_id, alice_cube);
+        }
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+    recv(&mut ws_a).await; // HistoryApplied
+    send(&mut ws_a, ClientEvent::Redo).await;
+    recv(&mut ws_a).await; // HistoryApplied
+    recv(&mut ws_b).await; // HistoryApplied
+
+    let session = state.sessions.get("undo-own").unwrap();
+    let objects = session.objects.read().unwrap();
+    assert_eq!(objects.len(), 2, "Bob's cube was never touched");
+    assert_eq!(objects[&alice_cube].transform.position, [5.0, 0.0, 0.0]);
+}
+
+/// Objects someone else changed since are skipped and reported; the rest of the step still applies.
+#[tokio::test]
+async fn test_undo_skips_objects_modified_by_others() {
+    let (url, state) = start_test_server_with_state().await;
+    let (mut ws_a, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws_a, "undo-conflict", "Alice").await;
+    let (mut ws_b, _) = connect_async(&url).await.unwrap();
+    join_session(&mut ws_b, "undo-conflict", "Bob").await;
+    recv(&mut ws_a).await; // UserJoined(Bob)
+
+    let first = Uuid::new_v4();
+    let second = Uuid::new_v4();
+    for id in [first, second] {
+        send(&mut ws_b, ClientEvent::CreateObject(cube_payload(id))).await;
+        recv(&mut ws_a).await; // ObjectCreated
+        recv(&mut ws_b).await; // ObjectCreated
+    }
+
+    send(&mut ws_a, ClientEvent::Transaction(TransactionPayload {
+        transaction_id: Uuid::new_v4(),
+        operations: vec![
+            TransactionOp::UpdateTransform(move_to(first, 1.0)),
+            TransactionOp::UpdateTransform(move_to(second, 2.0)),
+        ],
+    })).await;
+    recv(&mut ws_a).await; // TransactionApplied
+    recv(&mut ws_b).await; // TransactionApplied
+
+    send(&mut ws_b, ClientEvent::UpdateTransform(move_to(second, 9.0))).await;
+    recv(&mut ws_a).await; // TransformUpdated
+    recv(&mut ws_b).await; // TransformUpdated
+
+    send(&mut ws_a, ClientEvent::Undo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::HistoryApplied(p) => {
+            assert_eq!(p.diff.updated.len(), 1);
+            assert_eq!(p.diff.updated[0].object_id, first);
+            assert_eq!(p.conflicts, vec![second]);
+        }
+        other => panic!("expected HistoryApplied, got {:?}", other),
+    }
+    recv(&mut ws_b).await; // HistoryApplied
+
+    {
+        let session = state.sessions.get("undo-conflict").unwrap();
+        let objects = session.objects.read().unwrap();
+        assert_eq!(objects[&first].transform.position, [0.0, 0.0, 0.0]);
+        assert_eq!(objects[&second].transform.position, [9.0, 0.0, 0.0], "Bob's move is kept");
+    }
+
+    // Bob now moves `first` too, so the redo conflicts on every object it would touch.
+    send(&mut ws_b, ClientEvent::UpdateTransform(move_to(first, 7.0))).await;
+    recv(&mut ws_a).await; // TransformUpdated
+    recv(&mut ws_b).await; // TransformUpdated
+    send(&mut ws_a, ClientEvent::Redo).await;
+    match recv(&mut ws_a).await {
+        ServerEvent::Error(e) => assert_eq!(e.code, "UNDO_CONFLICT"),
+        other => panic!("expected UNDO_CONFLICT, got {:?}", other),
+    }
+    assert!(try_recv(&mut ws_b).await.is_none(), "a fully conflicting step is not broadcast");
+}
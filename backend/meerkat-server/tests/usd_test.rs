@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+use std::f64::consts::FRAC_PI_2;
+use std::process::Command;
+
+use tokio_tungstenite::connect_async;
+use uuid::Uuid;
+
+use meerkat_server::{
+    admin::{SceneImportSummary, SESSION_PASSWORD_HEADER},
+    config::{ServerConfig, DATA_DIR_ENV},
+    messages::{ClientEvent, ServerEvent},
+    snapshot::{recover, SNAPSHOT_FILE_NAME},
+    storage::session_dir,
+    types::{
+        AreaLightProperties, AreaLightShape, CameraProperties, LensType, ObjectProperties, ObjectType, PointLightProperties,
+        SceneObject, SpotLightProperties, SunLightProperties,
+    },
+    usd::{export_objects, import_objects, Layer, Specifier, Value, USDA_HEADER},
+};
+
+mod common;
+
+use common::{
+    create_session, cube_payload, http_get, http_request_with_headers, recv, scene_object, send,
+    start_admin_test_server_with_config, temp_data_dir, TEST_ADMIN_TOKEN,
+};
+
+fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert!(
+        actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-6),
+        "expected {expected:?}, got {actual:?}"
+    );
+}
+
+fn object_map(objects: Vec<SceneObject>) -> HashMap<Uuid, SceneObject> {
+    objects.into_iter().map(|object| (object.object_id, object)).collect()
+}
+
+/// One object of every kind a layout layer carries.
+fn layout() -> HashMap<Uuid, SceneObject> {
+    let mut cube = scene_object("Crate", ObjectType::Cube, None);
+    cube.transform.position = [1.0, 2.0, 3.0];
+    cube.transform.rotation = [0.1, -0.2, 7.0];
+    cube.transform.scale = [1.0, 2.0, 0.5];
+    cube.object_id = Uuid::from_u128(1);
+    let mut torus = scene_object("Crate", ObjectType::Torus, None);
+    torus.object_id = Uuid::from_u128(2);
+    let circle = scene_object("Marker", ObjectType::Circle, None);
+    let camera = CameraProperties { focal_length: 35.0, shift_x: 0.1, ..CameraProperties::default() };
+    let camera = scene_object("Shot Cam", ObjectType::Camera, Some(ObjectProperties::Camera(camera)));
+    let ortho = CameraProperties { lens_type: LensType::Orthographic, ..CameraProperties::default() };
+    let ortho = scene_object("Top Cam", ObjectType::Camera, Some(ObjectProperties::Camera(ortho)));
+    let point = PointLightProperties { radius: 0.0, color: [1.0, 0.5, 0.25], ..PointLightProperties::default() };
+    let point = scene_object("Bulb", ObjectType::PointLight, Some(ObjectProperties::PointLight(point)));
+    let spot = scene_object("Key", ObjectType::SpotLight, Some(ObjectProperties::SpotLight(SpotLightProperties::default())));
+    let sun = scene_object("Sun", ObjectType::SunLight, Some(ObjectProperties::SunLight(SunLightProperties::default())));
+    let disk = AreaLightProperties { shape: AreaLightShape::DISK, size: 0.5, ..AreaLightProperties::default() };
+    let disk = scene_object("Softbox", ObjectType::AreaLight, Some(ObjectProperties::AreaLight(disk)));
+    let mut tree = scene_object("Oak", ObjectType::AssetRef, None);
+    tree.asset_id = Some("oak_large".to_string());
+    tree.asset_library = Some("Vegetation".to_string());
+    tree.transform.position = [4.0, 5.0, 6.0];
+    object_map(vec![cube, torus, circle, camera, ortho, point, spot, sun, disk, tree])
+}
+
+/// The layer is Z-up in metres under `/World`, with every object as the UsdGeom or UsdLux prim
+/// a USD tool expects and asset references as payloads.
+#[test]
+fn test_export_writes_layout_layer() {
+    let objects = layout();
+    let layer = export_objects("layout", &objects);
+    let text = layer.to_usda();
+    assert!(text.starts_with(USDA_HEADER));
+    for expected in [
+        "defaultPrim = \"World\"",
+        "metersPerUnit = 1",
+        "upAxis = \"Z\"",
+        "def Cube \"Crate\"",
+        "def Mesh \"Crate_1\"",
+        "def BasisCurves \"Marker\"",
+        "def Camera \"Shot_Cam\"",
+        "float focalLength = 0.35",
+        "token projection = \"orthographic\"",
+        "def SphereLight \"Bulb\"",
+        "bool treatAsPoint = 1",
+        "color3f inputs:color = (1, 0.5, 0.25)",
+        "prepend apiSchemas = [\"ShadowAPI\", \"ShapingAPI\"]",
+        "float inputs:shaping:cone:angle = 22.5",
+        "def DistantLight \"Sun\"",
+        "def DiskLight \"Softbox\"",
+        "float inputs:radius = 0.25",
+        "prepend payload = @Vegetation/oak_large.usd@",
+        "double3 xformOp:translate = (4, 5, 6)",
+        "uniform token[] xformOpOrder = [\"xformOp:translate\", \"xformOp:rotateXYZ\", \"xformOp:scale\"]",
+    ] {
+        assert!(text.contains(expected), "missing {expected:?} in\n{text}");
+    }
+
+    let world = &layer.prims[0];
+    assert_eq!((world.name.as_str(), world.type_name.as_deref()), ("World", Some("Xform")));
+    assert_eq!(world.children.len(), objects.len());
+    let crate_prim = world.children.iter().find(|prim| prim.name == "Crate").unwrap();
+    assert_eq!(crate_prim.attribute("size"), Some(&Value::Number(2.0)));
+    let Some(Value::Tuple(degrees)) = crate_prim.attribute("xformOp:rotateXYZ") else { panic!("no rotation") };
+    assert_eq!(degrees[2], Value::Number(7.0f64.to_degrees()));
+
+    // What is written parses back to the same layer.
+    assert_eq!(Layer::parse(&text).unwrap(), layer);
+}
+
+/// Reading an export gives back the session's objects exactly, ids and properties included.
+#[test]
+fn test_export_import_round_trip() {
+    let objects = layout();
+    let text = export_objects("layout", &objects).to_usda();
+    let imported = import_objects(&Layer::parse(&text).unwrap(), 42);
+    assert_eq!(imported.len(), objects.len());
+    for object in imported {
+        let original = &objects[&object.object_id];
+        assert_eq!(
+            (&object.name, &object.object_type, &object.asset_id, &object.asset_library, &object.properties),
+            (&original.name, &original.object_type, &original.asset_id, &original.asset_library, &original.properties)
+        );
+        assert_eq!(object.transform.position, original.transform.position);
+        assert_close(&object.transform.rotation, &original.transform.rotation);
+        assert_eq!(object.transform.scale, original.transform.scale);
+        assert_eq!(object.last_updated_at, 42);
+    }
+}
+
+/// A Y-up, centimetre layout from another tool: a nested, referenced, animated set with a
+/// light, a camera, gprims and meshes, plus prims that describe nothing to place.
+const FOREIGN_USDA: &str = r#"#usda 1.0
+(
+    "Set dressing for shot 010"
+    defaultPrim = "Set"
+    metersPerUnit = 0.01
+    upAxis = "Y"
+)
+
+def Xform "Set" (
+    kind = "assembly"
+    variants = {
+        string lod = "high"
+    }
+    prepend variantSets = "lod"
+)
+{
+    double3 xformOp:translate = (100, 0, 0)
+    uniform token[] xformOpOrder = ["xformOp:translate"]
+
+    def Xform "Chair_01" (
+        displayName = "Chair 01"
+        prepend references = @./props/chair.usda@</Chair> (offset = 10)
+    )
+    {
+        float3 xformOp:rotateXYZ = (0, 90, 0)
+        double3 xformOp:translate.timeSamples = {
+            1: (0, 0, 200),
+            24: (0, 0, 400),
+        }
+        uniform token[] xformOpOrder = ["xformOp:translate", "xformOp:rotateXYZ"]
+
+        over "Seat"
+        {
+            color3f[] primvars:displayColor = [(1, 0, 0)]
+        }
+    }
+
+    def SphereLight "Key" (
+        prepend apiSchemas = ["ShapingAPI"]
+    )
+    {
+        float inputs:intensity = 10
+        float inputs:radius = 10
+        float inputs:shaping:cone:angle = 30
+        float inputs:shaping:cone:softness = 0.25
+        rel light:filters = </Set/Blocker>
+        double3 xformOp:translate = (0, 300, 0)
+        float xformOp:rotateX = -90
+        uniform token[] xformOpOrder = ["xformOp:translate", "xformOp:rotateX"]
+    }
+
+    def Camera "Cam"
+    {
+        float2 clippingRange = (1, 10000)
+        float focalLength = 35
+        float horizontalAperture = 36
+        float verticalAperture = 24
+        token projection = "perspective"
+        double3 xformOp:translate = (0, 0, 500)
+        uniform token[] xformOpOrder = ["xformOp:translate"]
+    }
+
+    def Cube "Box"
+    {
+        double size = 50
+        matrix4d xformOp:transform = ( (1, 0, 0, 0), (0, 1, 0, 0), (0, 0, 1, 0), (0, 25, 0, 1) )
+        uniform token[] xformOpOrder = ["xformOp:transform"]
+    }
+
+    def Mesh "Rock"
+    {
+        int[] faceVertexCounts = [3]
+        int[] faceVertexIndices = [0, 1, 2]
+        point3f[] points = [(0, 0, 0), (1, 0, 0), (0, 1, 0)]
+    }
+
+    def Scope "Looks"
+    {
+        def Material "Wood"
+        {
+            token outputs:surface.connect = </Set/Looks/Wood/Shader.outputs:surface>
+        }
+    }
+
+    variantSet "lod" = {
+        "high" {
+        }
+    }
+}
+
+class "_prototype"
+{
+    def Cube "Template"
+    {
+    }
+}
+
+over "Elsewhere"
+{
+    def Cube "NotOurs"
+    {
+    }
+}
+"#;
+
+/// Foreign layers come in converted to Blender's Z up and metres, with referenced assets,
+/// lights, cameras and gprims mapped onto Meerkat's objects and everything else skipped.
+#[test]
+fn test_import_foreign_usda() {
+    let layer = Layer::parse(FOREIGN_USDA).unwrap();
+    assert_eq!(layer.metadatum("doc"), Some(&Value::String("Set dressing for shot 010".to_string())));
+    assert_eq!(layer.prims.iter().map(|prim| prim.specifier).collect::<Vec<_>>(), [Specifier::Def, Specifier::Class, Specifier::Over]);
+    let objects = import_objects(&layer, 0);
+    let names: Vec<&str> = objects.iter().map(|object| object.name.as_str()).collect();
+    assert_eq!(names, ["Chair 01", "Key", "Cam", "Box", "Rock"]);
+    let object = |name: &str| objects.iter().find(|object| object.name == name).unwrap();
+
+    // Referenced assets keep the stage's centimetres in their scale; their Y up becomes Z up.
+    let chair = object("Chair 01");
+    assert_eq!(chair.object_type, ObjectType::AssetRef);
+    assert_eq!((chair.asset_id.as_deref(), chair.asset_library.as_deref()), (Some("chair"), Some("props")));
+    assert_close(&chair.transform.position, &[1.0, -2.0, 0.0]);
+    assert_close(&chair.transform.rotation, &[0.0, 0.0, FRAC_PI_2]);
+    assert_close(&chair.transform.scale, &[0.01; 3]);
+
+    // The spot pointing down the stage's -Y points down Blender's -Z, sized in metres.
+    let key = object("Key");
+    let Some(ObjectProperties::SpotLight(spot)) = &key.properties else { panic!("expected a spot light") };
+    assert_close(&key.transform.position, &[1.0, 0.0, 3.0]);
+    assert_close(&key.transform.rotation, &[0.0; 3]);
+    assert_close(&key.transform.scale, &[1.0; 3]);
+    assert_close(&[spot.radius as f64, spot.angle as f64, spot.blend as f64], &[0.1, 60f64.to_radians(), 0.25]);
+    let area = 4.0 * std::f64::consts::PI * 0.01;
+    assert!((spot.power as f64 - 10.0 * std::f64::consts::PI * area).abs() < 1e-4);
+
+    // Apertures in tenths of a centimetre are millimetres.
+    let cam = object("Cam");
+    let Some(ObjectProperties::Camera(camera)) = &cam.properties else { panic!("expected a camera") };
+    assert_close(&[camera.focal_length, camera.sensor_width, camera.clip_start, camera.clip_end], &[35.0, 36.0, 0.01, 100.0]);
+    assert_close(&cam.transform.position, &[1.0, -5.0, 0.0]);
+    assert_close(&cam.transform.rotation, &[FRAC_PI_2, 0.0, 0.0]);
+
+    // A 50 cm cube is Blender's 2 m cube at a quarter scale.
+    let cube = object("Box");
+    assert_eq!(cube.object_type, ObjectType::Cube);
+    assert_close(&cube.transform.position, &[1.0, 0.0, 0.25]);
+    assert_close(&cube.transform.scale, &[0.25; 3]);
+
+    let rock = object("Rock");
+    assert_eq!((rock.object_type.clone(), rock.asset_id.as_deref()), (ObjectType::AssetRef, Some("Rock")));
+    assert_close(&rock.transform.position, &[1.0, 0.0, 0.0]);
+
+    assert!(Layer::parse("#sdf 1.4.32\n").is_err(), "only text USD layers");
+    let error = Layer::parse("#usda 1.0\ndef Xform \"Broken\"\n{\n    double3 xformOp:translate = (1, 2\n").unwrap_err();
+    assert!(error.to_string().starts_with("line "), "{error}");
+}
+
+/// The admin API downloads a session as `.usda` and imports layers into live or new sessions;
+/// the CLI does both offline.
+#[tokio::test]
+async fn test_admin_and_cli_usd() {
+    let data_dir = temp_data_dir();
+    let config = ServerConfig { data_dir: Some(data_dir.clone()), ..ServerConfig::default() };
+    let (url, addr, state) = start_admin_test_server_with_config(config).await;
+    let (mut ws, _) = connect_async(&url).await.unwrap();
+    create_session(&mut ws, "stage", "Alice").await;
+    let existing = Uuid::new_v4();
+    send(&mut ws, ClientEvent::CreateObject(cube_payload(existing))).await;
+    recv(&mut ws).await; // ObjectCreated
+
+    let (status, text) = http_get(&addr, "/admin/sessions/stage/usd", Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(status, 200);
+    assert!(text.contains(&existing.to_string()));
+    assert_eq!(http_get(&addr, "/admin/sessions/stage/usd?at=missing", Some(TEST_ADMIN_TOKEN)).await.0, 404);
+
+    let post = |path: &'static str, headers: &'static [(&'static str, &'static str)], body: &'static str| {
+        let addr = addr.clone();
+        async move { http_request_with_headers(&addr, "POST", path, Some(TEST_ADMIN_TOKEN), headers, body.as_bytes()).await }
+    };
+    let (status, body) = post("/admin/sessions/stage/usd", &[], FOREIGN_USDA).await;
+    assert_eq!(status, 200, "{}", String::from_utf8_lossy(&body));
+    let summary: SceneImportSummary = serde_json::from_slice(&body).unwrap();
+    assert!(!summary.created);
+    assert_eq!(summary.object_ids.len(), 5);
+    for object_id in &summary.object_ids {
+        match recv(&mut ws).await {
+            ServerEvent::ObjectCreated(p) => assert_eq!(&p.object.object_id, object_id),
+            other => panic!("expected ObjectCreated, got {other:?}"),
+        }
+    }
+    let session = state.sessions.get("stage").unwrap().clone();
+    assert_eq!(session.lock_audit().entries().filter(|entry| entry.event_type == "ImportUsd").count(), 5);
+
+    assert_eq!(post("/admin/sessions/other/usd", &[], FOREIGN_USDA).await.0, 404);
+    let password = &[(SESSION_PASSWORD_HEADER, common::TEST_PASSWORD)];
+    assert_eq!(post("/admin/sessions/other/usd", password, "not a layer").await.0, 400);
+    assert_eq!(post("/admin/sessions/other/usd", password, FOREIGN_USDA).await.0, 201);
+    assert_eq!(state.sessions.get("other").unwrap().objects.read().unwrap().len(), 5);
+
+    // Offline, from the persisted session into a new one.
+    let usda_path = data_dir.join("stage.usda");
+    let cli = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_meerkat-server"))
+            .env(DATA_DIR_ENV, &data_dir)
+            .args(args)
+            .output()
+            .expect("failed to run meerkat-server")
+    };
+    let output = cli(&["usd", "stage", usda_path.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let exported = Layer::parse(&std::fs::read_to_string(&usda_path).unwrap()).unwrap();
+    assert_eq!(exported.prims[0].children.len(), 6);
+    let output = cli(&["usd-import", usda_path.to_str().unwrap(), "copy", "--password", "secret"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(session_dir(&data_dir, "copy").join(SNAPSHOT_FILE_NAME).exists());
+    let copy = recover(&session_dir(&data_dir, "copy"), None).unwrap().unwrap();
+    assert_eq!(copy.objects.len(), 6);
+    assert!(copy.objects.contains_key(&existing));
+    assert!(!cli(&["usd-import", usda_path.to_str().unwrap(), "copy", "--password", "secret"]).status.success());
+
+    let _ = std::fs::remove_dir_all(&data_dir);
+}
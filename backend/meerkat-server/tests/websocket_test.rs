@@ -28,11 +28,10 @@ fn broadcast_evicts_connection_after_three_full_strikes() {
     session_connections.insert(session_id.clone(), [connection_id].into_iter().collect());
 
     let state = AppState {
-        sessions: Arc::new(DashMap::new()),
         connections,
         connection_meta,
-        connection_backpressure: Arc::new(DashMap::new()),
         session_connections,
+        ..AppState::new()
     };
 
     for _ in 0..2 {
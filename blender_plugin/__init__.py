@@ -1,6 +1,6 @@
 import bpy
 from . import operators, panels, preferences
-from .event_handlers import timer_function, timer_function_transforms, timer_function_cursor
+from .event_handlers import timer_function, timer_function_transforms, timer_function_cursor, on_depsgraph_update
 from .selection_overlay import register_draw_handler, unregister_draw_handler
 
 
@@ -22,6 +22,13 @@ classes = [
     operators.MEERKAT_OT_disconnect,
     operators.MEERKAT_OT_place_asset,
     operators.MEERKAT_OT_cursor_tracker,
+    operators.MEERKAT_OT_undo,
+    operators.MEERKAT_OT_redo,
+    operators.MEERKAT_OT_create_checkpoint,
+    operators.MEERKAT_OT_restore_checkpoint,
+    operators.MEERKAT_OT_restore_object,
+    operators.MEERKAT_OT_object_history,
+    operators.MEERKAT_OT_fork_session,
     operators.MEERKAT_OT_save_scene,
     panels.MEERKAT_PT_main_panel,
 ]
@@ -33,6 +40,7 @@ def register():
     bpy.app.timers.register(timer_function)
     bpy.app.timers.register(timer_function_transforms)
     bpy.app.timers.register(timer_function_cursor)
+    bpy.app.handlers.depsgraph_update_post.append(on_depsgraph_update)
     register_draw_handler()
     bpy.types.Scene.meerkat_room_name = bpy.props.StringProperty(name="Room Name", default="")
     bpy.types.Scene.meerkat_display_name = bpy.props.StringProperty(name="Display Name", default="")
@@ -55,6 +63,7 @@ def unregister():
     bpy.app.timers.unregister(timer_function)
     bpy.app.timers.unregister(timer_function_transforms)
     bpy.app.timers.unregister(timer_function_cursor)
+    bpy.app.handlers.depsgraph_update_post.remove(on_depsgraph_update)
     unregister_draw_handler()
     del bpy.types.Scene.meerkat_room_name
     del bpy.types.Scene.meerkat_display_name
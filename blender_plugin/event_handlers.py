@@ -1,9 +1,15 @@
 # event_handlers.py — server event -> Blender action dispatch
 import bpy
+import math
+import re
 import time
 import queue
+import base64
 import traceback
+from array import array
+from itertools import accumulate
 from uuid import uuid4
+from . import mesh_data
 from .state import PluginState
 from .utils import build_transform
 
@@ -178,6 +184,31 @@ PROPERTY_BUILDERS = {
 }
 
 
+# Key each typed properties variant lives under in the server's property document, so that
+# PatchProperties can address single fields as e.g. "spot.angle".
+PROPERTY_SCHEMA_KEYS = {
+    "Camera": "camera",
+    "PointLight": "point",
+    "SpotLight": "spot",
+    "AreaLight": "area",
+    "SunLight": "sun",
+}
+
+
+def _property_patches(cached, current):
+    """Patches for the fields that differ between two builder results, or None when only a
+    whole UpdateProperties will do (nothing sent yet, or the variant changed)."""
+    if not cached or cached.keys() != current.keys():
+        return None
+    variant = next(iter(current))
+    key = PROPERTY_SCHEMA_KEYS[variant]
+    return [
+        {"path": f"{key}.{field}", "value": value}
+        for field, value in current[variant].items()
+        if cached[variant].get(field) != value
+    ]
+
+
 def _get_property_builder(obj):
     """Return the property builder for this object, or None if it has no synced properties."""
     if obj.type == "CAMERA":
@@ -204,6 +235,54 @@ def timer_function_transforms():
         if gone:
             continue
 
+        # --- Parent polling ---
+        current_parent = _meerkat_parent_id(obj)
+        if current_parent != state.parent_cache.get(meerkat_id):
+            state.parent_cache[meerkat_id] = current_parent
+            # No transform: the server keeps the object where it was in the world and
+            # answers with the local transform in ParentUpdated.
+            state.ws_client.send({
+                "event_type": "SetParent",
+                "payload": {
+                    "object_id": meerkat_id,
+                    "parent_id": current_parent,
+                }
+            })
+            continue
+
+        # --- Collection membership polling ---
+        current_collections = _meerkat_collection_ids(obj)
+        if current_collections != state.membership_cache.get(meerkat_id, []):
+            state.membership_cache[meerkat_id] = current_collections
+            state.ws_client.send({
+                "event_type": "SetObjectCollections",
+                "payload": {
+                    "object_id": meerkat_id,
+                    "collection_ids": current_collections,
+                }
+            })
+
+        # --- Material slot polling ---
+        # None until detect_and_sync_materials has shared every material in the slots.
+        current_materials = _object_material_ids(obj)
+        if current_materials is not None and current_materials != state.material_slot_cache.get(meerkat_id, []):
+            state.material_slot_cache[meerkat_id] = current_materials
+            state.ws_client.send({
+                "event_type": "SetObjectMaterials",
+                "payload": {
+                    "object_id": meerkat_id,
+                    "material_slots": current_materials,
+                }
+            })
+
+        # --- Modifier polling ---
+        cached_modifiers = state.modifier_cache.get(meerkat_id, [])
+        current_modifiers = _read_modifiers(obj, cached_modifiers)
+        if current_modifiers != cached_modifiers:
+            state.modifier_cache[meerkat_id] = current_modifiers
+            for message in _modifier_events(meerkat_id, cached_modifiers, current_modifiers):
+                state.ws_client.send(message)
+
         # --- Transform polling ---
         current = build_transform(obj)
         cached = state.transform_cache.get(meerkat_id)
@@ -225,12 +304,40 @@ def timer_function_transforms():
             cached_props = state.property_cache.get(meerkat_id)
             if current_props != cached_props:
                 state.property_cache[meerkat_id] = current_props
+                patches = _property_patches(cached_props, current_props)
+                if patches is None:
+                    state.ws_client.send({
+                        "event_type": "UpdateProperties",
+                        "payload": {
+                            "object_id": meerkat_id,
+                            "properties": current_props,
+                        }
+                    })
+                else:
+                    # Only the fields that changed.
+                    state.ws_client.send({
+                        "event_type": "PatchProperties",
+                        "payload": {
+                            "object_id": meerkat_id,
+                            "patches": patches,
+                        }
+                    })
+
+        # --- Custom property polling ---
+        current_custom = _read_custom_properties(obj)
+        cached_custom = state.custom_property_cache.get(meerkat_id, {})
+        if current_custom != cached_custom:
+            state.custom_property_cache[meerkat_id] = current_custom
+            for key, value in current_custom.items():
+                if cached_custom.get(key) != value:
+                    state.ws_client.send({
+                        "event_type": "SetCustomProperty",
+                        "payload": {"object_id": meerkat_id, "key": key, "value": value},
+                    })
+            for key in cached_custom.keys() - current_custom.keys():
                 state.ws_client.send({
-                    "event_type": "UpdateProperties",
-                    "payload": {
-                        "object_id": meerkat_id,
-                        "properties": current_props,
-                    }
+                    "event_type": "RemoveCustomProperty",
+                    "payload": {"object_id": meerkat_id, "key": key},
                 })
 
         # --- Name polling ---
@@ -268,13 +375,34 @@ def handle_full_state_sync(payload):
         state.transform_cache.clear() 
         state.property_cache.clear()
         state.name_cache.clear() 
+        state.parent_cache.clear()
+        state.custom_property_cache.clear()
+        state.modifier_cache.clear()
+        _remove_collections()
+        _remove_materials()
+        state.mesh_refs.clear()
+        state.mesh_versions.clear()
+        state.mesh_cache.clear()
+        state.known_meshes.clear()
+        state.dirty_meshes.clear()
+        state.mesh_downloads.clear()
         state.last_selected = None 
+        state.checkpoints.clear()  # refreshed with ListCheckpoints when the restore dialog opens
+        state.trash.clear()
+        state.rejected_events.clear()  # edits to a scene that is being replaced
 
         # 2. Recreate objects from the session snapshot
         session = payload.get("session", {})
+        state.session_id = session.get("session_id", state.session_id)  # changes after ForkSession
+        state.session_version = session.get("version")
+        for collection_id, collection_data in session.get("collections", {}).items():
+            _apply_collection({**collection_data, "collection_id": collection_id})
+        for material_id, material_data in session.get("materials", {}).items():
+            _apply_material({**material_data, "material_id": material_id})
         objects = session.get("objects", {})
         for obj_id, obj_data in objects.items():
             _create_object_from_snapshot(obj_id, obj_data)
+        _link_parents(objects.items())
 
         # 3. Rebuild user list using user ids from session snapshot
         state.users.clear()
@@ -286,12 +414,314 @@ def handle_full_state_sync(payload):
                 "selected_object": user_data.get("selected_object"),
             }
         _redraw_panels()
-        print(f"[Meerkat] FullStateSync: {len(objects)} objects, {len(users)} users, my_id={state.user_id}")
+        print(f"[Meerkat] FullStateSync: {len(objects)} objects, {len(state.collections)} collections, "
+              f"{len(state.materials)} materials, {len(users)} users, my_id={state.user_id}")
 
     finally:
         state.is_applying_remote_update = False
 
 
+def handle_state_sync_begin(payload):
+    """Start buffering a chunked state sync; objects arrive in StateSyncChunk frames."""
+    state = PluginState()
+    state.pending_sync = {
+        "your_user_id": payload.get("your_user_id", ""),
+        "session_id": payload.get("session_id", ""),
+        "users": payload.get("users", {}),
+        "collections": payload.get("collections", {}),
+        "materials": payload.get("materials", {}),
+        "object_count": payload.get("object_count", 0),
+        "version": payload.get("version"),
+        "objects": {},
+    }
+
+
+def handle_state_sync_chunk(payload):
+    state = PluginState()
+    if state.pending_sync is None:
+        print("[Meerkat] StateSyncChunk received without StateSyncBegin, ignoring")
+        return
+    for obj_data in payload.get("objects", []):
+        state.pending_sync["objects"][obj_data.get("object_id")] = obj_data
+
+
+def handle_state_sync_end(payload):
+    """Apply the buffered chunks as if they had arrived in a single FullStateSync."""
+    state = PluginState()
+    pending = state.pending_sync
+    state.pending_sync = None
+    if pending is None:
+        print("[Meerkat] StateSyncEnd received without StateSyncBegin, ignoring")
+        return
+
+    expected = payload.get("object_count", pending["object_count"])
+    if len(pending["objects"]) != expected:
+        print(f"[Meerkat] Chunked sync incomplete ({len(pending['objects'])}/{expected} objects), requesting resync")
+        if state.ws_client:
+            state.ws_client.send({"event_type": "RequestStateSync", "payload": None})
+        return
+
+    handle_full_state_sync({
+        "your_user_id": pending["your_user_id"],
+        "session": {
+            "session_id": pending["session_id"],
+            "objects": pending["objects"],
+            "users": pending["users"],
+            "collections": pending["collections"],
+            "materials": pending["materials"],
+            "version": pending["version"],
+        },
+    })
+
+
+def handle_state_delta(payload):
+    """Apply an incremental sync: update upserted objects in place and drop tombstoned ones."""
+    state = PluginState()
+    state.is_applying_remote_update = True
+    try:
+        # The delta carries the whole collection set: drop the ones that are gone first, so
+        # upserted objects are linked into what is left.
+        collections = payload.get("collections", {})
+        for collection_id in [cid for cid in state.collections if cid not in collections]:
+            _drop_collection(collection_id)
+        for collection_id, collection_data in collections.items():
+            _apply_collection({**collection_data, "collection_id": collection_id})
+        for material_id, material_data in payload.get("materials", {}).items():
+            _apply_material({**material_data, "material_id": material_id})
+
+        for obj_id in payload.get("deleted", []):
+            _remove_object(obj_id)
+
+        _upsert_objects(payload.get("upserted", []))
+
+        # Only now is the scene at to_version; the next RequestStateSync asks from here.
+        if payload.get("to_version") is not None:
+            state.session_version = payload["to_version"]
+        print(f"[Meerkat] StateDelta: {len(payload.get('upserted', []))} upserted, "
+              f"{len(payload.get('deleted', []))} deleted, version={state.session_version}")
+    finally:
+        state.is_applying_remote_update = False
+
+
+def _apply_object_diff(payload):
+    """Apply created/updated/deleted lists from a server-side bulk change, whoever authored it."""
+    state = PluginState()
+    state.is_applying_remote_update = True
+    try:
+        for obj_id in payload.get("deleted", []):
+            _remove_object(obj_id)
+
+        for obj_data in payload.get("created", []):
+            _create_object_from_snapshot(obj_data.get("object_id", ""), obj_data)
+        _link_parents((d.get("object_id", ""), d) for d in payload.get("created", []))
+
+        _upsert_objects(payload.get("updated", []))
+    finally:
+        state.is_applying_remote_update = False
+
+
+def _remove_object(obj_id):
+    """Drop an object the server no longer has, with everything cached about it."""
+    state = PluginState()
+    obj = state.object_map.pop(obj_id, None)
+    state.transform_cache.pop(obj_id, None)
+    state.property_cache.pop(obj_id, None)
+    state.name_cache.pop(obj_id, None)
+    state.parent_cache.pop(obj_id, None)
+    state.membership_cache.pop(obj_id, None)
+    state.custom_property_cache.pop(obj_id, None)
+    state.material_slot_cache.pop(obj_id, None)
+    state.mesh_refs.pop(obj_id, None)
+    state.mesh_versions.pop(obj_id, None)
+    state.mesh_cache.pop(obj_id, None)
+    state.dirty_meshes.discard(obj_id)
+    state.modifier_cache.pop(obj_id, None)
+    if obj and obj.name in bpy.data.objects:
+        bpy.data.objects.remove(obj, do_unlink=True)
+
+
+def _upsert_objects(objects):
+    """Create the objects the scene lacks and update the rest in place, so selection and any
+    Blender-side data Meerkat does not sync survive. Callers set is_applying_remote_update."""
+    state = PluginState()
+    existing = []
+    created = []
+    for obj_data in objects:
+        obj_id = obj_data.get("object_id", "")
+        obj = state.object_map.get(obj_id)
+        if obj is not None and obj.name in bpy.data.objects:
+            existing.append((obj_id, obj, obj_data))
+        else:
+            _create_object_from_snapshot(obj_id, obj_data)
+            created.append((obj_id, obj_data))
+    _link_parents(created)
+
+    for obj_id, obj, obj_data in existing:
+        obj.name = obj_data.get("name", obj.name)
+        _apply_parent(obj_id, obj, obj_data.get("parent_id"))
+        _apply_transform(obj, obj_data.get("transform", {}))
+        _apply_properties(obj, obj_data.get("properties"))
+        _apply_object_collections(obj_id, obj, obj_data.get("collections"))
+        _apply_custom_properties(obj_id, obj, obj_data.get("custom_properties"))
+        _apply_object_materials(obj_id, obj, obj_data.get("material_slots"))
+        _apply_object_mesh(obj_id, obj, obj_data.get("mesh"), obj_data.get("mesh_version", 0))
+        _apply_object_modifiers(obj_id, obj, obj_data.get("modifiers"))
+        # Cache what Blender stored so the timers don't echo the change back.
+        state.name_cache[obj_id] = obj.name
+        state.transform_cache[obj_id] = build_transform(obj)
+        builder = _get_property_builder(obj)
+        if builder:
+            state.property_cache[obj_id] = builder(obj)
+
+
+def handle_history_applied(payload):
+    _apply_object_diff(payload)
+    conflicts = payload.get("conflicts", [])
+    if conflicts:
+        print(f"[Meerkat] {payload.get('action')} skipped {len(conflicts)} object(s) changed by others")
+
+
+def handle_checkpoint_created(payload):
+    state = PluginState()
+    state.checkpoints.append(payload)
+    _redraw_panels()
+
+
+def handle_checkpoint_list(payload):
+    state = PluginState()
+    state.checkpoints = list(payload.get("checkpoints", []))
+    _redraw_panels()
+
+
+def handle_checkpoint_restored(payload):
+    _apply_object_diff(payload)
+    checkpoint = payload.get("checkpoint", {})
+    print(f"[Meerkat] Restored checkpoint '{checkpoint.get('name')}': "
+          f"{len(payload.get('created', []))} created, {len(payload.get('updated', []))} updated, "
+          f"{len(payload.get('deleted', []))} deleted")
+
+
+def handle_trash_list(payload):
+    state = PluginState()
+    state.trash = list(payload.get("items", []))
+    _redraw_panels()
+
+
+def handle_audit_log(payload):
+    state = PluginState()
+    state.audit_entries = list(payload.get("entries", []))
+    for entry in state.audit_entries:
+        when = time.strftime("%Y-%m-%d %H:%M:%S", time.localtime(entry.get("timestamp", 0) / 1000))
+        print(f"[Meerkat] {when} {entry.get('display_name') or entry.get('user_id')}: {entry.get('event_type')}")
+    if payload.get("truncated"):
+        print("[Meerkat] (older entries omitted)")
+
+
+def handle_session_forked(payload):
+    print(f"[Meerkat] Session '{payload.get('source_session_id')}' forked into "
+          f"'{payload.get('new_session_id')}' ({payload.get('object_count')} objects)")
+
+
+# Must match the server's checksum module; see checksum::object_hash.
+FNV_OFFSET_BASIS = 0xcbf29ce484222325
+FNV_PRIME = 0x100000001b3
+TRANSFORM_QUANTIZATION = 10_000.0
+
+
+def _fnv1a(data):
+    value = FNV_OFFSET_BASIS
+    for byte in data:
+        value = ((value ^ byte) * FNV_PRIME) & 0xFFFFFFFFFFFFFFFF
+    return value
+
+
+def _quantize(value):
+    """Scale and round half away from zero, like Rust's f64::round on the server."""
+    scaled = value * TRANSFORM_QUANTIZATION
+    return int(math.copysign(math.floor(abs(scaled) + 0.5), scaled))
+
+
+def _object_hash(obj_id, obj):
+    """The server's per-object hash over the local copy, or None for an asset placeholder,
+    which cannot match what it stands in for. Values come from the sync caches where there
+    are any, so local edits the timers have not sent yet do not count as drift."""
+    if obj.get("meerkat_placeholder") or "meerkat_object_type" not in obj:
+        return None
+    state = PluginState()
+    transform = state.transform_cache.get(obj_id) or build_transform(obj)
+    parts = [
+        obj_id, state.name_cache.get(obj_id, obj.name), obj["meerkat_object_type"],
+        obj.get("meerkat_asset_id", ""), obj.get("meerkat_asset_library", ""),
+    ]
+    parts += [str(_quantize(v)) for key in ("position", "rotation", "scale") for v in transform[key]]
+    parent_id = state.parent_cache.get(obj_id, _meerkat_parent_id(obj))
+    if parent_id:
+        parts.append(parent_id)
+    parts += state.membership_cache.get(obj_id, _meerkat_collection_ids(obj))
+    mesh_ref = state.mesh_refs.get(obj_id)
+    if mesh_ref:
+        parts.append(f"mesh:{mesh_ref['hash']}")
+    material_ids = state.material_slot_cache.get(obj_id, _object_material_ids(obj) or [])
+    parts += [f"slot:{material_id or ''}" for material_id in material_ids]
+    custom = state.custom_property_cache.get(obj_id, _read_custom_properties(obj))
+    parts += [f"prop:{key}" for key in sorted(custom)]
+    parts += [f"modifier:{m['modifier_id']}" for m in state.modifier_cache.get(obj_id, [])]
+    return _fnv1a("|".join(parts).encode())
+
+
+def _local_object_hashes():
+    state = PluginState()
+    return {
+        obj_id: _object_hash(obj_id, obj)
+        for obj_id, obj in state.object_map.items()
+        if obj.name in bpy.data.objects
+    }
+
+
+def handle_state_checksum(payload):
+    """Compare the server's checksum with the scene. On a mismatch ask for per-object hashes;
+    once they are here, resync just the objects that differ or exist on one side only."""
+    state = PluginState()
+    if state.pending_sync is not None or not state.ws_client:
+        return
+    local = _local_object_hashes()
+    server_hashes = payload.get("object_hashes")
+
+    if server_hashes is None:
+        if None not in local.values() and len(local) == payload.get("object_count") \
+                and f"{sum(local.values()) & 0xFFFFFFFFFFFFFFFF:016x}" == payload.get("checksum"):
+            state.session_version = payload.get("version", state.session_version)
+            return
+        print(f"[Meerkat] Checksum mismatch at version {payload.get('version')}, locating drifted objects")
+        state.ws_client.send({"event_type": "RequestChecksum", "payload": {"include_objects": True}})
+        return
+
+    drifted = sorted(
+        obj_id for obj_id in set(local) | set(server_hashes)
+        if obj_id not in local
+        or (local[obj_id] is not None and server_hashes.get(obj_id) != f"{local[obj_id]:016x}")
+    )
+    if not drifted:
+        state.session_version = payload.get("version", state.session_version)
+        return
+    print(f"[Meerkat] {len(drifted)} object(s) drifted from the server, resyncing them")
+    state.ws_client.send({"event_type": "RequestObjectsSync", "payload": {"object_ids": drifted}})
+
+
+def handle_objects_synced(payload):
+    """Apply a targeted resync: refresh the objects sent and drop the ones the server lacks."""
+    state = PluginState()
+    state.is_applying_remote_update = True
+    try:
+        for obj_id in payload.get("missing", []):
+            _remove_object(obj_id)
+        _upsert_objects(payload.get("objects", []))
+    finally:
+        state.is_applying_remote_update = False
+    print(f"[Meerkat] ObjectsSynced: {len(payload.get('objects', []))} refreshed, "
+          f"{len(payload.get('missing', []))} removed")
+
+
 def _apply_transform(obj, transform):
     """Set position, rotation, scale on a Blender object from a transform dict."""
     pos = transform.get("position", [0, 0, 0])
@@ -302,6 +732,575 @@ def _apply_transform(obj, transform):
     obj.scale = scl
 
 
+def _meerkat_parent_id(obj):
+    """Meerkat id of the object's parent, or None when it has no parent Meerkat tracks."""
+    if obj.parent is None:
+        return None
+    return obj.parent.get("meerkat_id")
+
+
+def _apply_parent(obj_id, obj, parent_id):
+    """Parent a Blender object the way the server does, so its transform is local to the parent.
+    A parent not in the scene (yet) leaves it unparented."""
+    state = PluginState()
+    parent = state.object_map.get(parent_id) if parent_id else None
+    if parent is not None and parent.name not in bpy.data.objects:
+        parent = None
+    obj.parent = parent
+    obj.matrix_parent_inverse.identity()
+    state.parent_cache[obj_id] = parent_id if parent is not None else None
+
+
+def _link_parents(objects):
+    """Second pass over (obj_id, obj_data) pairs after creating a batch, once every parent and
+    every mirror modifier's object exists."""
+    state = PluginState()
+    for obj_id, obj_data in objects:
+        obj = state.object_map.get(obj_id)
+        if obj is not None and obj_data.get("parent_id"):
+            _apply_parent(obj_id, obj, obj_data.get("parent_id"))
+        if obj is not None and any(m.get("mirror_object") for m in obj_data.get("modifiers") or []):
+            _apply_object_modifiers(obj_id, obj, obj_data.get("modifiers"))
+
+
+# Keys the server accepts for custom properties; anything else on an object stays local.
+CUSTOM_PROPERTY_KEY = re.compile(r"[A-Za-z][A-Za-z0-9_]{0,62}")
+
+
+def _custom_value(value):
+    """A Blender ID property as the server's tagged custom value, or None if it has no counterpart."""
+    if isinstance(value, bool):
+        return {"Bool": value}
+    if isinstance(value, (int, float)):
+        return {"Number": float(value)}
+    if isinstance(value, str):
+        return {"String": value}
+    if hasattr(value, "to_list"):
+        value = value.to_list()
+    if isinstance(value, list) and value and all(isinstance(v, (int, float)) and not isinstance(v, bool) for v in value):
+        return {"Vector": [float(v) for v in value]}
+    if isinstance(value, list) and all(isinstance(v, str) for v in value):
+        return {"StringList": list(value)}
+    return None
+
+
+def _read_custom_properties(obj):
+    """The object's syncable custom properties, keyed like the server's map."""
+    result = {}
+    for key in obj.keys():
+        if not CUSTOM_PROPERTY_KEY.fullmatch(key) or key.lower().startswith("meerkat"):
+            continue
+        value = _custom_value(obj[key])
+        if value is not None:
+            result[key] = value
+    return result
+
+
+def _write_custom_property(obj, key, tagged):
+    kind, value = next(iter(tagged.items()))
+    if kind == "Number":
+        obj[key] = float(value)
+    elif kind == "Vector":
+        obj[key] = [float(v) for v in value]
+    elif kind == "StringList":
+        obj[key] = list(value)
+    else:
+        obj[key] = value
+
+
+def _apply_custom_properties(obj_id, obj, custom_properties):
+    """Make the object's syncable custom properties exactly the server's."""
+    state = PluginState()
+    custom_properties = custom_properties or {}
+    for key in _read_custom_properties(obj):
+        if key not in custom_properties:
+            del obj[key]
+    for key, tagged in custom_properties.items():
+        _write_custom_property(obj, key, tagged)
+    state.custom_property_cache[obj_id] = _read_custom_properties(obj)
+
+
+def _collection_fields(collection):
+    """The synced fields of a Blender collection, shaped like the server's Collection."""
+    return {
+        "name": collection.name,
+        "color_tag": collection.color_tag,
+        "visible": not collection.hide_viewport,
+        "selectable": not collection.hide_select,
+    }
+
+
+def _apply_collection(data):
+    """Create or update the Blender collection for a server Collection, under the scene root."""
+    state = PluginState()
+    collection_id = data.get("collection_id", "")
+    collection = state.collections.get(collection_id)
+    if collection is None or collection.name not in bpy.data.collections:
+        collection = bpy.data.collections.new(data.get("name", "Collection"))
+        collection["meerkat_collection_id"] = collection_id
+        bpy.context.scene.collection.children.link(collection)
+        state.collections[collection_id] = collection
+    collection.name = data.get("name", collection.name)
+    collection.color_tag = data.get("color_tag", "NONE")
+    collection.hide_viewport = not data.get("visible", True)
+    collection.hide_select = not data.get("selectable", True)
+    # Blender may have suffixed the name; cache what it stored so polling doesn't echo it.
+    state.collection_cache[collection_id] = _collection_fields(collection)
+
+
+def _remove_collections():
+    """Drop every Meerkat-managed collection from the file, ahead of a full resync."""
+    state = PluginState()
+    for collection in list(bpy.data.collections):
+        if "meerkat_collection_id" in collection:
+            bpy.data.collections.remove(collection)
+    state.collections.clear()
+    state.collection_cache.clear()
+    state.membership_cache.clear()
+
+
+def _meerkat_collection_ids(obj):
+    """Sorted ids of the Meerkat collections the object is linked into."""
+    return sorted(c["meerkat_collection_id"] for c in obj.users_collection if "meerkat_collection_id" in c)
+
+
+def _apply_object_collections(obj_id, obj, collection_ids):
+    """Link the object into exactly the given collections, or the scene root when there are none.
+    Ids of collections this client doesn't have are skipped, as on the server."""
+    state = PluginState()
+    targets = [state.collections[cid] for cid in collection_ids or [] if cid in state.collections]
+    if not targets:
+        targets = [bpy.context.scene.collection]
+    for collection in targets:
+        if obj.name not in collection.objects:
+            collection.objects.link(obj)
+    for collection in list(obj.users_collection):
+        if collection not in targets:
+            collection.objects.unlink(obj)
+    state.membership_cache[obj_id] = _meerkat_collection_ids(obj)
+
+
+def _principled_bsdf(material):
+    """The material's Principled BSDF node, or None when it doesn't use one."""
+    if not material.use_nodes or material.node_tree is None:
+        return None
+    return next((node for node in material.node_tree.nodes if node.type == "BSDF_PRINCIPLED"), None)
+
+
+def _unit(value):
+    return min(max(float(value), 0.0), 1.0)
+
+
+def _material_fields(material):
+    """The synced fields of a Blender material, shaped like the server's MaterialSettings plus
+    the name. Read from its Principled BSDF, or the viewport display settings without one;
+    clamped to what the server accepts."""
+    bsdf = _principled_bsdf(material)
+    if bsdf is None:
+        r, g, b, a = material.diffuse_color
+        return {
+            "name": material.name,
+            "base_color": [_unit(r), _unit(g), _unit(b)],
+            "metallic": _unit(material.metallic),
+            "roughness": _unit(material.roughness),
+            "emission_color": [1.0, 1.0, 1.0],
+            "emission_strength": 0.0,
+            "alpha": _unit(a),
+        }
+    inputs = bsdf.inputs
+    # Blender 4 renamed the "Emission" input to "Emission Color".
+    emission = inputs.get("Emission Color") or inputs.get("Emission")
+    return {
+        "name": material.name,
+        "base_color": [_unit(c) for c in inputs["Base Color"].default_value[:3]],
+        "metallic": _unit(inputs["Metallic"].default_value),
+        "roughness": _unit(inputs["Roughness"].default_value),
+        "emission_color": [_unit(c) for c in emission.default_value[:3]] if emission else [1.0, 1.0, 1.0],
+        "emission_strength": max(float(inputs["Emission Strength"].default_value), 0.0)
+        if "Emission Strength" in inputs else 0.0,
+        "alpha": _unit(inputs["Alpha"].default_value),
+    }
+
+
+def _apply_material(data):
+    """Create or update the Blender material for a server Material. Texture references are kept
+    on the material as `meerkat_textures` for the next export; they are not resolved to images."""
+    state = PluginState()
+    material_id = data.get("material_id", "")
+    material = state.materials.get(material_id)
+    if material is None or material.name not in bpy.data.materials:
+        material = bpy.data.materials.new(data.get("name", "Material"))
+        material["meerkat_material_id"] = material_id
+        material.use_nodes = True
+        state.materials[material_id] = material
+    material.name = data.get("name", material.name)
+    base_color = data.get("base_color", [0.8, 0.8, 0.8])
+    alpha = data.get("alpha", 1.0)
+    # The viewport display settings too, so Solid shading shows the material.
+    material.diffuse_color = (*base_color, alpha)
+    material.metallic = data.get("metallic", 0.0)
+    material.roughness = data.get("roughness", 0.5)
+    bsdf = _principled_bsdf(material)
+    if bsdf is not None:
+        inputs = bsdf.inputs
+        inputs["Base Color"].default_value = (*base_color, 1.0)
+        inputs["Metallic"].default_value = data.get("metallic", 0.0)
+        inputs["Roughness"].default_value = data.get("roughness", 0.5)
+        inputs["Alpha"].default_value = alpha
+        emission = inputs.get("Emission Color") or inputs.get("Emission")
+        if emission is not None:
+            emission.default_value = (*data.get("emission_color", [1.0, 1.0, 1.0]), 1.0)
+        if "Emission Strength" in inputs:
+            inputs["Emission Strength"].default_value = data.get("emission_strength", 0.0)
+    if hasattr(material, "blend_method"):
+        material.blend_method = "BLEND" if alpha < 1.0 else "OPAQUE"
+    textures = data.get("textures") or {}
+    if textures:
+        material["meerkat_textures"] = textures
+    elif "meerkat_textures" in material:
+        del material["meerkat_textures"]
+    state.material_cache[material_id] = _material_fields(material)
+
+
+def _remove_materials():
+    """Drop every Meerkat-managed material from the file, ahead of a full resync."""
+    state = PluginState()
+    for material in list(bpy.data.materials):
+        if "meerkat_material_id" in material:
+            bpy.data.materials.remove(material)
+    state.materials.clear()
+    state.material_cache.clear()
+    state.material_slot_cache.clear()
+
+
+def _object_material_ids(obj):
+    """Material ids in the object's slots (None for an empty slot), or None while one of its
+    materials is not shared yet."""
+    ids = []
+    for slot in getattr(obj, "material_slots", []):
+        material = slot.material
+        if material is None:
+            ids.append(None)
+        elif "meerkat_material_id" in material:
+            ids.append(material["meerkat_material_id"])
+        else:
+            return None
+    return ids
+
+
+def _apply_object_materials(obj_id, obj, material_slots):
+    """Give the object exactly the server's material slots. Ids of materials this client
+    doesn't have become empty slots; objects without material slots (cameras, lights) are left alone."""
+    state = PluginState()
+    material_slots = material_slots or []
+    materials = getattr(obj.data, "materials", None)
+    if materials is not None and _object_material_ids(obj) != material_slots:
+        materials.clear()
+        for material_id in material_slots:
+            materials.append(state.materials.get(material_id) if material_id else None)
+    state.material_slot_cache[obj_id] = _object_material_ids(obj) or []
+
+
+def _encode_mesh(mesh):
+    """A Blender mesh in the server's encoding (see mesh_data.py): vertices, faces, the active
+    UV map and, when the mesh has custom split normals, those. Without custom normals, receivers
+    recompute them from the faces."""
+    positions = array("f", bytes(12 * len(mesh.vertices)))
+    mesh.vertices.foreach_get("co", positions)
+    starts = array("i", bytes(4 * len(mesh.polygons)))
+    sizes = array("i", bytes(4 * len(mesh.polygons)))
+    mesh.polygons.foreach_get("loop_start", starts)
+    mesh.polygons.foreach_get("loop_total", sizes)
+    corners = array("i", bytes(4 * len(mesh.loops)))
+    mesh.loops.foreach_get("vertex_index", corners)
+    uvs = None
+    if mesh.uv_layers.active is not None:
+        uvs = array("f", bytes(8 * len(mesh.loops)))
+        mesh.uv_layers.active.data.foreach_get("uv", uvs)
+    normals = None
+    if mesh.has_custom_normals:
+        normals = array("f", bytes(12 * len(mesh.loops)))
+        mesh.corner_normals.foreach_get("vector", normals)
+    # Faces normally own consecutive loops in face order; put the corners in that order if not.
+    if list(starts) != list(accumulate(sizes, initial=0))[:-1] or sum(sizes) != len(corners):
+        order = [loop for start, size in zip(starts, sizes) for loop in range(start, start + size)]
+        corners = [corners[loop] for loop in order]
+        if uvs is not None:
+            uvs = [uvs[2 * loop + k] for loop in order for k in range(2)]
+        if normals is not None:
+            normals = [normals[3 * loop + k] for loop in order for k in range(3)]
+    return mesh_data.encode(positions, sizes, corners, uvs, normals)
+
+
+def _mesh_hash(obj):
+    return mesh_data.content_hash(_encode_mesh(obj.data))
+
+
+def _write_mesh(mesh, geometry):
+    """Replace a Blender mesh's geometry with decoded mesh data. Its materials stay."""
+    positions = geometry["positions"]
+    sizes = geometry["face_sizes"]
+    corners = array("i", geometry["face_vertices"])
+    mesh.clear_geometry()
+    mesh.vertices.add(len(positions) // 3)
+    mesh.vertices.foreach_set("co", positions)
+    mesh.loops.add(len(corners))
+    mesh.loops.foreach_set("vertex_index", corners)
+    mesh.polygons.add(len(sizes))
+    mesh.polygons.foreach_set("loop_start", array("i", list(accumulate(sizes, initial=0))[:-1]))
+    mesh.update(calc_edges=True)
+    if geometry["uvs"]:
+        mesh.uv_layers.new(name="UVMap").data.foreach_set("uv", geometry["uvs"])
+    normals = geometry["normals"]
+    if normals:
+        mesh.normals_split_custom_set([normals[i:i + 3] for i in range(0, len(normals), 3)])
+    mesh.update()
+
+
+def _restore_primitive(obj):
+    """Give the object back the geometry its primitive type is created with."""
+    creator = OBJECT_CREATORS.get(obj.get("meerkat_object_type"))
+    if creator is None:
+        obj.data.clear_geometry()
+        return
+    view_layer = bpy.context.view_layer
+    active = view_layer.objects.active
+    template = creator()
+    template_mesh = template.data
+    try:
+        _write_mesh(obj.data, mesh_data.decode(_encode_mesh(template_mesh)))
+    finally:
+        bpy.data.objects.remove(template, do_unlink=True)
+        bpy.data.meshes.remove(template_mesh)
+        view_layer.objects.active = active
+
+
+def _request_mesh(mesh_hash, obj_id):
+    """Download mesh data for an object; one RequestMesh serves every object waiting on it."""
+    state = PluginState()
+    download = state.mesh_downloads.get(mesh_hash)
+    if download is None:
+        download = state.mesh_downloads[mesh_hash] = {"chunks": {}, "object_ids": set()}
+        if state.ws_client:
+            state.ws_client.send({"event_type": "RequestMesh", "payload": {"hash": mesh_hash}})
+    download["object_ids"].add(obj_id)
+
+
+def _apply_object_mesh(obj_id, obj, mesh_ref, mesh_version):
+    """Bring the object's geometry in line with the server's mesh reference: download mesh data
+    it doesn't have yet, or go back to the primitive's geometry once the reference is gone."""
+    state = PluginState()
+    state.mesh_versions[obj_id] = mesh_version
+    if obj.type != "MESH":
+        return
+    previous = state.mesh_refs.get(obj_id)
+    if mesh_ref is None:
+        state.mesh_refs.pop(obj_id, None)
+        if previous is not None:
+            _restore_primitive(obj)
+        state.mesh_cache[obj_id] = _mesh_hash(obj)
+        return
+    state.mesh_refs[obj_id] = mesh_ref
+    state.known_meshes.add(mesh_ref["hash"])
+    if previous is None or previous.get("hash") != mesh_ref["hash"]:
+        state.mesh_cache.setdefault(obj_id, _mesh_hash(obj))
+        _request_mesh(mesh_ref["hash"], obj_id)
+
+
+def _sync_object_mesh(meerkat_id, obj):
+    """Upload the object's geometry if it changed since it was last synced, then point the
+    object at it. Mesh data the server already has is not sent again."""
+    state = PluginState()
+    encoded = _encode_mesh(obj.data)
+    mesh_hash = mesh_data.content_hash(encoded)
+    if mesh_hash == state.mesh_cache.get(meerkat_id):
+        return
+    state.mesh_cache[meerkat_id] = mesh_hash
+    if mesh_hash not in state.known_meshes:
+        state.known_meshes.add(mesh_hash)
+        chunks = mesh_data.to_chunks(mesh_data.compress(encoded))
+        for index, data in enumerate(chunks):
+            state.ws_client.send({
+                "event_type": "UploadMeshChunk",
+                "payload": {"hash": mesh_hash, "chunk_index": index, "chunk_count": len(chunks), "data": data},
+            })
+    state.ws_client.send({
+        "event_type": "SetObjectMesh",
+        "payload": {
+            "object_id": meerkat_id,
+            "hash": mesh_hash,
+            "base_version": state.mesh_versions.get(meerkat_id, 0),
+        },
+    })
+    print(f"[Meerkat] Sent mesh {mesh_hash[:12]} for {meerkat_id}")
+
+
+# Modifiers with typed settings on the server, by Blender type, and the settings each syncs.
+TYPED_MODIFIERS = {
+    "ARRAY": ("Array", ["count", "use_relative_offset", "relative_offset_displace", "use_constant_offset",
+                        "constant_offset_displace", "use_merge_vertices", "merge_threshold"]),
+    "MIRROR": ("Mirror", ["use_axis", "use_bisect_axis", "use_clip", "use_mirror_merge", "merge_threshold"]),
+    "BEVEL": ("Bevel", ["affect", "width", "segments", "limit_method", "angle_limit"]),
+    "SUBSURF": ("Subdivision", ["levels", "render_levels", "subdivision_type"]),
+}
+# Settings of other modifiers that are Blender's bookkeeping rather than part of the result.
+MODIFIER_SKIPPED_SETTINGS = {
+    "rna_type", "name", "type", "show_viewport", "show_render", "show_in_editmode", "show_on_cage",
+    "show_expanded", "is_active", "is_override_data_editable", "use_pin_to_last", "persistent_uid",
+    "execution_time", "use_apply_on_spline",
+}
+MAX_MODIFIER_PARAMETERS = 64
+
+
+def _modifier_setting(value):
+    """A typed modifier setting as JSON: vectors and flag arrays become lists."""
+    if hasattr(value, "__len__") and not isinstance(value, str):
+        return [v if isinstance(v, bool) else float(v) for v in value]
+    return value
+
+
+def _modifier_parameters(modifier):
+    """Plain settings of a modifier without typed settings, as tagged custom values. Settings
+    pointing at other data (objects, textures, vertex groups by reference) stay local."""
+    parameters = {}
+    for prop in modifier.bl_rna.properties:
+        key = prop.identifier
+        if key in MODIFIER_SKIPPED_SETTINGS or prop.is_readonly:
+            continue
+        if prop.type not in {"BOOLEAN", "INT", "FLOAT", "STRING", "ENUM"}:
+            continue
+        if not CUSTOM_PROPERTY_KEY.fullmatch(key) or key.lower().startswith("meerkat"):
+            continue
+        if prop.type == "ENUM" and getattr(prop, "is_enum_flag", False):
+            continue
+        value = _custom_value(getattr(modifier, key))
+        if value is not None:
+            parameters[key] = value
+        if len(parameters) == MAX_MODIFIER_PARAMETERS:
+            break
+    return parameters
+
+
+def _modifier_fields(modifier, modifier_id):
+    """A Blender modifier in the server's format."""
+    fields = {
+        "modifier_id": modifier_id,
+        "name": modifier.name,
+        "show_viewport": modifier.show_viewport,
+        "show_render": modifier.show_render,
+    }
+    typed = TYPED_MODIFIERS.get(modifier.type)
+    if typed is None:
+        fields.update(modifier_type="Other", blender_type=modifier.type, parameters=_modifier_parameters(modifier))
+        return fields
+    modifier_type, settings = typed
+    fields["modifier_type"] = modifier_type
+    for setting in settings:
+        fields[setting] = _modifier_setting(getattr(modifier, setting))
+    if modifier.type == "MIRROR" and modifier.mirror_object is not None and "meerkat_id" in modifier.mirror_object:
+        fields["mirror_object"] = modifier.mirror_object["meerkat_id"]
+    return fields
+
+
+def _modifier_blender_type(fields):
+    if fields.get("modifier_type") == "Other":
+        return fields.get("blender_type", "")
+    return next((t for t, (name, _) in TYPED_MODIFIERS.items() if name == fields.get("modifier_type")), "")
+
+
+def _read_modifiers(obj, known):
+    """The object's modifier stack in the server's format. Blender modifiers carry no custom
+    properties, so ids come from `known` (a stack in server format) by name and type; a renamed
+    modifier or one that changed type counts as new."""
+    ids = {(m.get("name"), _modifier_blender_type(m)): m.get("modifier_id") for m in known}
+    return [
+        _modifier_fields(modifier, ids.get((modifier.name, modifier.type)) or str(uuid4()))
+        for modifier in obj.modifiers
+    ]
+
+
+def _write_modifier(modifier, fields):
+    """Set a Blender modifier from the server's format. Parameters this Blender doesn't have
+    or won't take are skipped."""
+    state = PluginState()
+    modifier.show_viewport = fields.get("show_viewport", True)
+    modifier.show_render = fields.get("show_render", True)
+    typed = TYPED_MODIFIERS.get(modifier.type)
+    if typed is not None:
+        for setting in typed[1]:
+            if setting in fields:
+                _set_if_attr(modifier, setting, fields[setting])
+        if modifier.type == "MIRROR":
+            target = state.object_map.get(fields.get("mirror_object"))
+            modifier.mirror_object = target if target is not None and target.name in bpy.data.objects else None
+        return
+    for key, tagged in (fields.get("parameters") or {}).items():
+        prop = modifier.bl_rna.properties.get(key)
+        if prop is None or prop.is_readonly:
+            continue
+        kind, value = next(iter(tagged.items()))
+        if prop.type == "INT":
+            value = [int(v) for v in value] if kind == "Vector" else int(value)
+        try:
+            setattr(modifier, key, value)
+        except (AttributeError, TypeError, ValueError):
+            pass
+
+
+def _apply_object_modifiers(obj_id, obj, modifiers):
+    """Give the object exactly the server's modifier stack, reusing modifiers with the same
+    name and type so their local state (such as expanded panels) survives. Types this Blender
+    can't add to the object are left out."""
+    state = PluginState()
+    modifiers = modifiers or []
+    wanted = {(m.get("name"), _modifier_blender_type(m)) for m in modifiers}
+    for modifier in list(obj.modifiers):
+        if (modifier.name, modifier.type) not in wanted:
+            obj.modifiers.remove(modifier)
+    position = 0
+    for fields in modifiers:
+        modifier = obj.modifiers.get(fields.get("name", ""))
+        if modifier is None:
+            try:
+                modifier = obj.modifiers.new(fields.get("name", ""), _modifier_blender_type(fields))
+            except (TypeError, RuntimeError):
+                modifier = None
+            if modifier is None:
+                print(f"[Meerkat] Cannot add {_modifier_blender_type(fields)} modifier to '{obj.name}'")
+                continue
+        _write_modifier(modifier, fields)
+        current = list(obj.modifiers).index(modifier)
+        if current != position:
+            obj.modifiers.move(current, position)
+        position += 1
+    state.modifier_cache[obj_id] = _read_modifiers(obj, modifiers)
+
+
+def _modifier_events(meerkat_id, cached, current):
+    """Messages turning the server's stack `cached` into `current`: removals, updates, additions
+    at the end, then a reorder if the result is not in `current`'s order."""
+    cached_by_id = {m["modifier_id"]: m for m in cached}
+    current_ids = [m["modifier_id"] for m in current]
+    events = [
+        {"event_type": "RemoveModifier", "payload": {"object_id": meerkat_id, "modifier_id": m["modifier_id"]}}
+        for m in cached if m["modifier_id"] not in current_ids
+    ]
+    for modifier in current:
+        previous = cached_by_id.get(modifier["modifier_id"])
+        if previous is not None and previous != modifier:
+            events.append({"event_type": "UpdateModifier", "payload": {"object_id": meerkat_id, "modifier": modifier}})
+    for modifier in current:
+        if modifier["modifier_id"] not in cached_by_id:
+            events.append({"event_type": "AddModifier", "payload": {"object_id": meerkat_id, "modifier": modifier}})
+    kept = [m["modifier_id"] for m in cached if m["modifier_id"] in current_ids]
+    if kept + [i for i in current_ids if i not in cached_by_id] != current_ids:
+        events.append({
+            "event_type": "ReorderModifiers",
+            "payload": {"object_id": meerkat_id, "modifier_ids": current_ids},
+        })
+    return events
+
+
 def _apply_camera_props(obj, p):
     cam = obj.data
     cam.lens = p.get("focal_length", cam.lens)
@@ -461,6 +1460,7 @@ def _create_asset_placeholder(obj_id, asset_id, transform):
     obj.name = f"[MISSING] {asset_id}"
     obj.display_type = 'WIRE'
     obj["meerkat_id"] = obj_id
+    obj["meerkat_placeholder"] = True
     _apply_transform(obj, transform)
     state = PluginState()
     state.object_map[obj_id] = obj
@@ -475,6 +1475,13 @@ def _create_primitive(op):
     return creator
 
 
+def _create_empty_mesh():
+    """A mesh object with no geometry yet, for mesh data that arrives separately."""
+    obj = bpy.data.objects.new("Mesh", bpy.data.meshes.new("Mesh"))
+    bpy.context.collection.objects.link(obj)
+    return obj
+
+
 OBJECT_CREATORS = {
     "Cube":       _create_primitive(bpy.ops.mesh.primitive_cube_add),
     "Sphere":     _create_primitive(bpy.ops.mesh.primitive_uv_sphere_add),
@@ -491,6 +1498,7 @@ OBJECT_CREATORS = {
     "SunLight":   _create_primitive(lambda: bpy.ops.object.light_add(type='SUN')),
     "SpotLight":  _create_primitive(lambda: bpy.ops.object.light_add(type='SPOT')),
     "AreaLight":  _create_primitive(lambda: bpy.ops.object.light_add(type='AREA')),
+    "Mesh":       _create_empty_mesh,
 }
 
 
@@ -563,8 +1571,19 @@ def _create_object_from_snapshot(obj_id, obj_data):
 
     obj.name = name
     obj["meerkat_id"] = obj_id
+    obj["meerkat_object_type"] = obj_type
+    if obj_data.get("asset_id"):
+        obj["meerkat_asset_id"] = obj_data["asset_id"]
+    if obj_data.get("asset_library"):
+        obj["meerkat_asset_library"] = obj_data["asset_library"]
+    _apply_parent(obj_id, obj, obj_data.get("parent_id"))
     _apply_transform(obj, transform)
     _apply_properties(obj, properties)
+    _apply_object_collections(obj_id, obj, obj_data.get("collections"))
+    _apply_custom_properties(obj_id, obj, obj_data.get("custom_properties"))
+    _apply_object_materials(obj_id, obj, obj_data.get("material_slots"))
+    _apply_object_mesh(obj_id, obj, obj_data.get("mesh"), obj_data.get("mesh_version", 0))
+    _apply_object_modifiers(obj_id, obj, obj_data.get("modifiers"))
     state.object_map[obj_id] = obj
     print(f"[Meerkat] Created {obj_type} '{name}' id={obj_id}")
 
@@ -574,8 +1593,9 @@ def handle_object_created(payload):
     obj_data = payload.get("object", {})
     created_by = payload.get("created_by", "")
 
-    # Echo suppression — don't recreate our own objects
-    if created_by == str(state.user_id):
+    # Echo suppression — don't recreate our own objects. Restores from the trash
+    # are never local yet, so they always apply.
+    if created_by == str(state.user_id) and not payload.get("restored_by"):
         return
 
     state.is_applying_remote_update = True
@@ -627,10 +1647,8 @@ def _classify_new_object(obj):
             return "AreaLight", _build_area_light_props(obj)
         return None, None
     if obj.type == "MESH":
-        inferred = _infer_mesh_type(obj.name)
-        if inferred is None:
-            return None, None
-        return inferred, None
+        # Meshes that don't look like a primitive (imports, joins) sync as mesh data.
+        return _infer_mesh_type(obj.name) or "Mesh", None
     return None, None
 
 
@@ -656,6 +1674,7 @@ def detect_and_send_creations():
 
         meerkat_id = str(uuid4())
         obj["meerkat_id"] = meerkat_id
+        obj["meerkat_object_type"] = object_type
         state.object_map[meerkat_id] = obj
 
         state.ws_client.send({
@@ -671,6 +1690,11 @@ def detect_and_send_creations():
             }
         })
         print(f"[Meerkat] Detected native add: {object_type} '{obj.name}' id={meerkat_id}")
+        if object_type == "Mesh":
+            _sync_object_mesh(meerkat_id, obj)
+        elif obj.type == "MESH":
+            # Peers build the same primitive; only later edits need sending.
+            state.mesh_cache[meerkat_id] = _mesh_hash(obj)
 
 
 def detect_and_send_deletions():
@@ -748,6 +1772,351 @@ def handle_transform_updated(payload):
         state.is_applying_remote_update = False
 
 
+def handle_parent_updated(payload):
+    """Reparent with the transform the server resolved. Applied for our own changes too: a
+    delete moves children up that the sender never touched."""
+    state = PluginState()
+    object_id = payload.get("object_id", "")
+
+    state.is_applying_remote_update = True
+    try:
+        obj = state.object_map.get(object_id)
+        if obj is None or obj.name not in bpy.data.objects:
+            return
+
+        _apply_parent(object_id, obj, payload.get("parent_id"))
+        _apply_transform(obj, payload.get("transform", {}))
+        state.transform_cache[object_id] = build_transform(obj)
+    finally:
+        state.is_applying_remote_update = False
+
+
+def handle_collection_created(payload):
+    """Applied for our own collections too; that only tags the existing one with the server's state."""
+    state = PluginState()
+    state.is_applying_remote_update = True
+    try:
+        _apply_collection(payload.get("collection", {}))
+        _redraw_panels()
+    finally:
+        state.is_applying_remote_update = False
+
+
+def handle_collection_updated(payload):
+    state = PluginState()
+    state.is_applying_remote_update = True
+    try:
+        _apply_collection(payload.get("collection", {}))
+    finally:
+        state.is_applying_remote_update = False
+
+
+def _drop_collection(collection_id):
+    """Remove a Meerkat collection; members left in no collection go back to the scene root."""
+    state = PluginState()
+    collection = state.collections.pop(collection_id, None)
+    state.collection_cache.pop(collection_id, None)
+    if collection is None or collection.name not in bpy.data.collections:
+        return
+    for obj in list(collection.objects):
+        collection.objects.unlink(obj)
+        if not obj.users_collection:
+            bpy.context.scene.collection.objects.link(obj)
+        if "meerkat_id" in obj:
+            state.membership_cache[obj["meerkat_id"]] = _meerkat_collection_ids(obj)
+    bpy.data.collections.remove(collection)
+
+
+def handle_collection_deleted(payload):
+    state = PluginState()
+    state.is_applying_remote_update = True
+    try:
+        _drop_collection(payload.get("collection_id", ""))
+        _redraw_panels()
+    finally:
+        state.is_applying_remote_update = False
+
+
+def handle_object_collections_updated(payload):
+    state = PluginState()
+    object_id = payload.get("object_id", "")
+    if payload.get("updated_by") == str(state.user_id):
+        state.membership_cache[object_id] = sorted(payload.get("collection_ids", []))
+        return
+
+    state.is_applying_remote_update = True
+    try:
+        obj = state.object_map.get(object_id)
+        if obj is None or obj.name not in bpy.data.objects:
+            return
+        _apply_object_collections(object_id, obj, payload.get("collection_ids", []))
+    finally:
+        state.is_applying_remote_update = False
+
+
+def detect_and_sync_collections():
+    """Share collections made or removed in Blender's outliner and send their flag changes."""
+    state = PluginState()
+    if not state.connected or not state.ws_client:
+        return
+
+    for collection_id in list(state.collections):
+        collection = state.collections[collection_id]
+        try:
+            gone = collection.name not in bpy.data.collections
+        except ReferenceError:
+            gone = True
+        if gone:
+            state.collections.pop(collection_id, None)
+            state.collection_cache.pop(collection_id, None)
+            state.ws_client.send({
+                "event_type": "DeleteCollection",
+                "payload": {"collection_id": collection_id},
+            })
+            continue
+        current = _collection_fields(collection)
+        if current != state.collection_cache.get(collection_id):
+            state.collection_cache[collection_id] = current
+            state.ws_client.send({
+                "event_type": "UpdateCollection",
+                "payload": {"collection_id": collection_id, **current},
+            })
+
+    for collection in bpy.context.scene.collection.children_recursive:
+        if "meerkat_collection_id" in collection:
+            continue
+        collection_id = str(uuid4())
+        collection["meerkat_collection_id"] = collection_id
+        state.collections[collection_id] = collection
+        state.collection_cache[collection_id] = _collection_fields(collection)
+        state.ws_client.send({
+            "event_type": "CreateCollection",
+            "payload": {"collection_id": collection_id, **state.collection_cache[collection_id]},
+        })
+        print(f"[Meerkat] Sent CreateCollection: '{collection.name}' id={collection_id}")
+
+
+def handle_material_created(payload):
+    """Applied for our own materials too; that only updates the existing one with the server's state."""
+    state = PluginState()
+    state.is_applying_remote_update = True
+    try:
+        _apply_material(payload.get("material", {}))
+    finally:
+        state.is_applying_remote_update = False
+
+
+def handle_material_updated(payload):
+    state = PluginState()
+    state.is_applying_remote_update = True
+    try:
+        _apply_material(payload.get("material", {}))
+    finally:
+        state.is_applying_remote_update = False
+
+
+def handle_object_materials_updated(payload):
+    state = PluginState()
+    object_id = payload.get("object_id", "")
+    if payload.get("updated_by") == str(state.user_id):
+        state.material_slot_cache[object_id] = list(payload.get("material_slots", []))
+        return
+
+    state.is_applying_remote_update = True
+    try:
+        obj = state.object_map.get(object_id)
+        if obj is None or obj.name not in bpy.data.objects:
+            return
+        _apply_object_materials(object_id, obj, payload.get("material_slots", []))
+    finally:
+        state.is_applying_remote_update = False
+
+
+def detect_and_sync_materials():
+    """Share the materials synced objects use and send changes to their settings. Materials
+    removed from the file stay in the session; there is nothing to delete them with."""
+    state = PluginState()
+    if not state.connected or not state.ws_client:
+        return
+
+    for material_id in list(state.materials):
+        material = state.materials[material_id]
+        try:
+            gone = material.name not in bpy.data.materials
+        except ReferenceError:
+            gone = True
+        if gone:
+            state.materials.pop(material_id, None)
+            state.material_cache.pop(material_id, None)
+            continue
+        current = _material_fields(material)
+        if current != state.material_cache.get(material_id):
+            state.material_cache[material_id] = current
+            state.ws_client.send({
+                "event_type": "UpdateMaterial",
+                "payload": {"material_id": material_id, **current},
+            })
+
+    for obj in list(state.object_map.values()):
+        try:
+            slots = list(obj.material_slots)
+        except ReferenceError:
+            continue
+        for slot in slots:
+            material = slot.material
+            if material is None or "meerkat_material_id" in material:
+                continue
+            material_id = str(uuid4())
+            material["meerkat_material_id"] = material_id
+            state.materials[material_id] = material
+            state.material_cache[material_id] = _material_fields(material)
+            state.ws_client.send({
+                "event_type": "CreateMaterial",
+                "payload": {"material_id": material_id, **state.material_cache[material_id]},
+            })
+            print(f"[Meerkat] Sent CreateMaterial: '{material.name}' id={material_id}")
+
+
+def handle_object_modifiers_updated(payload):
+    state = PluginState()
+    if payload.get("updated_by") == str(state.user_id):
+        return  # the stack is already what this client sent
+
+    state.is_applying_remote_update = True
+    try:
+        object_id = payload.get("object_id", "")
+        obj = state.object_map.get(object_id)
+        if obj is None or obj.name not in bpy.data.objects:
+            return
+        _apply_object_modifiers(object_id, obj, payload.get("modifiers", []))
+    finally:
+        state.is_applying_remote_update = False
+
+
+def handle_mesh_stored(payload):
+    PluginState().known_meshes.add(payload.get("hash", ""))
+
+
+def handle_mesh_chunk(payload):
+    """Collect a RequestMesh reply; once complete, write the geometry into every object still
+    pointing at it."""
+    state = PluginState()
+    mesh_hash = payload.get("hash", "")
+    download = state.mesh_downloads.get(mesh_hash)
+    if download is None:
+        return
+    download["chunks"][payload.get("chunk_index", 0)] = base64.b64decode(payload.get("data", ""))
+    if len(download["chunks"]) < payload.get("chunk_count", 1):
+        return
+    del state.mesh_downloads[mesh_hash]
+
+    encoded = mesh_data.inflate(b"".join(download["chunks"][i] for i in sorted(download["chunks"])))
+    if mesh_data.content_hash(encoded) != mesh_hash:
+        print(f"[Meerkat] Mesh {mesh_hash[:12]} arrived damaged, ignoring")
+        return
+    geometry = mesh_data.decode(encoded)
+
+    state.is_applying_remote_update = True
+    try:
+        written = set()
+        for obj_id in download["object_ids"]:
+            obj = state.object_map.get(obj_id)
+            if obj is None or obj.name not in bpy.data.objects:
+                continue
+            if (state.mesh_refs.get(obj_id) or {}).get("hash") != mesh_hash:
+                continue  # moved on to other mesh data meanwhile
+            # Linked duplicates share one Blender mesh; write it once.
+            if obj.data.as_pointer() not in written:
+                _write_mesh(obj.data, geometry)
+                written.add(obj.data.as_pointer())
+            # What Blender stored, which is what the next edit check compares with.
+            state.mesh_cache[obj_id] = _mesh_hash(obj)
+            state.dirty_meshes.discard(obj_id)
+    finally:
+        state.is_applying_remote_update = False
+
+
+def handle_object_mesh_updated(payload):
+    state = PluginState()
+    object_id = payload.get("object_id", "")
+    mesh_ref = payload.get("mesh")
+    if payload.get("updated_by") == str(state.user_id):
+        # Our own geometry is already here; only the version moves on.
+        state.mesh_versions[object_id] = payload.get("mesh_version", 0)
+        if mesh_ref is None:
+            state.mesh_refs.pop(object_id, None)
+        else:
+            state.mesh_refs[object_id] = mesh_ref
+            state.known_meshes.add(mesh_ref["hash"])
+        return
+
+    state.is_applying_remote_update = True
+    try:
+        obj = state.object_map.get(object_id)
+        if obj is None or obj.name not in bpy.data.objects:
+            return
+        _apply_object_mesh(object_id, obj, mesh_ref, payload.get("mesh_version", 0))
+    finally:
+        state.is_applying_remote_update = False
+
+
+def on_depsgraph_update(scene, depsgraph):
+    """Note synced objects whose geometry changed; detect_and_sync_meshes sends it."""
+    state = PluginState()
+    if not state.connected:
+        return
+    for update in depsgraph.updates:
+        if not update.is_updated_geometry or not isinstance(update.id, bpy.types.Object):
+            continue
+        meerkat_id = update.id.original.get("meerkat_id")
+        if meerkat_id in state.object_map:
+            state.dirty_meshes.add(meerkat_id)
+
+
+def detect_and_sync_meshes():
+    """Send the geometry of synced meshes that changed. Objects in Edit Mode wait until the
+    user leaves it, which is when edits reach the mesh data."""
+    state = PluginState()
+    if not state.connected or not state.ws_client:
+        return
+
+    for meerkat_id in list(state.dirty_meshes):
+        obj = state.object_map.get(meerkat_id)
+        try:
+            usable = obj is not None and obj.name in bpy.data.objects and obj.type == "MESH"
+        except ReferenceError:
+            usable = False
+        if not usable:
+            state.dirty_meshes.discard(meerkat_id)
+            continue
+        if obj.mode == "EDIT":
+            continue
+        state.dirty_meshes.discard(meerkat_id)
+        _sync_object_mesh(meerkat_id, obj)
+
+
+# Transaction steps reuse the single-event handlers; each needs its payload plus the author field.
+_TRANSACTION_STEP_HANDLERS = {
+    "CreateObject":     lambda data, by: handle_object_created({"object": data, "created_by": by}),
+    "DeleteObject":     lambda data, by: handle_object_deleted({**data, "deleted_by": by}),
+    "UpdateTransform":  lambda data, by: handle_transform_updated({**data, "updated_by": by}),
+    "UpdateProperties": lambda data, by: handle_properties_updated({**data, "updated_by": by}),
+    "UpdateName":       lambda data, by: handle_name_updated({**data, "updated_by": by}),
+    "SetParent":        lambda data, by: handle_parent_updated({**data, "updated_by": by}),
+}
+
+
+def handle_transaction_applied(payload):
+    """Apply an atomic server transaction step by step, in order."""
+    applied_by = payload.get("applied_by", "")
+    for step in payload.get("operations", []):
+        handler = _TRANSACTION_STEP_HANDLERS.get(step.get("op"))
+        if handler is None:
+            print(f"[Meerkat] Unknown transaction op: {step.get('op')}")
+            continue
+        handler(step.get("payload", {}), applied_by)
+
+
 def handle_user_joined(payload):
     state = PluginState()
     user_id = payload.get("user_id", "")
@@ -794,6 +2163,72 @@ def handle_properties_updated(payload):
         state.is_applying_remote_update = False
 
 
+def handle_properties_patched(payload):
+    """Apply the patches that address this object's typed properties. Other paths, and
+    removals, have no Blender property to go to and are left to the server's document."""
+    state = PluginState()
+    object_id = payload.get("object_id", "")
+
+    if payload.get("updated_by", "") == str(state.user_id):
+        return
+
+    state.is_applying_remote_update = True
+    try:
+        obj = state.object_map.get(object_id)
+        if obj is None or obj.name not in bpy.data.objects:
+            return
+        builder = _get_property_builder(obj)
+        if builder is None:
+            return
+        variant = next(iter(builder(obj)))
+        fields = {}
+        for patch in payload.get("patches", []):
+            head, _, field = patch.get("path", "").partition(".")
+            value = patch.get("value")
+            if head != PROPERTY_SCHEMA_KEYS[variant] or value is None:
+                continue
+            if not field and isinstance(value, dict):
+                fields.update(value)
+            elif field and "." not in field:
+                fields[field] = value
+        if fields:
+            _apply_properties(obj, {variant: fields})
+        # Cache what Blender actually stored to avoid drift re-sends
+        state.property_cache[object_id] = builder(obj)
+    finally:
+        state.is_applying_remote_update = False
+
+
+def handle_custom_property_updated(payload):
+    state = PluginState()
+    object_id = payload.get("object_id", "")
+    key = payload.get("key", "")
+    value = payload.get("value")
+    cache = state.custom_property_cache.setdefault(object_id, {})
+
+    if payload.get("updated_by", "") == str(state.user_id):
+        if value is None:
+            cache.pop(key, None)
+        else:
+            cache[key] = value
+        return
+
+    state.is_applying_remote_update = True
+    try:
+        obj = state.object_map.get(object_id)
+        if obj is None or obj.name not in bpy.data.objects:
+            return
+        if value is None:
+            if key in obj:
+                del obj[key]
+            cache.pop(key, None)
+        else:
+            _write_custom_property(obj, key, value)
+            cache[key] = _custom_value(obj[key])
+    finally:
+        state.is_applying_remote_update = False
+
+
 def handle_name_updated(payload):
     state = PluginState()
     object_id = payload.get("object_id", "")
@@ -856,6 +2291,29 @@ def handle_error(payload):
         state.display_name = ""
         _redraw_panels()
         _popup_error(code, message)
+    elif code == "SESSION_STORAGE_FAILED":
+        # Sent once; the edits refused after it come back as SESSION_READ_ONLY and are only printed.
+        _popup_error(code, message)
+
+
+def handle_event_rejected(payload):
+    """Queue an edit the server refused for rate limiting to be sent again once it has room.
+    The scene already shows the change, so dropping it would leave peers behind."""
+    state = PluginState()
+    event = payload.get("event")
+    if not event:
+        return
+    due = time.monotonic() + payload.get("retry_after_ms", 0) / 1000
+    state.rejected_events.append((due, event))
+
+
+def _resend_rejected_events():
+    """Send the refused edits that are due, oldest first."""
+    state = PluginState()
+    now = time.monotonic()
+    while state.rejected_events and state.rejected_events[0][0] <= now and state.ws_client:
+        _, event = state.rejected_events.pop(0)
+        state.ws_client.send(event)
 
 
 def _popup_error(code, message):
@@ -865,21 +2323,51 @@ def _popup_error(code, message):
         "SESSION_NOT_FOUND": "Session not found",
         "WRONG_PASSWORD": "Wrong password",
         "SESSION_ALREADY_EXISTS": "Session already exists",
+        "SESSION_STORAGE_FAILED": "Session is read-only",
     }.get(code, "Server error")
     bpy.context.window_manager.popup_menu(draw, title=title, icon='ERROR')
 
 
 EVENT_HANDLERS = {
     "FullStateSync": handle_full_state_sync,
+    "StateSyncBegin": handle_state_sync_begin,
+    "StateSyncChunk": handle_state_sync_chunk,
+    "StateSyncEnd": handle_state_sync_end,
+    "StateDelta": handle_state_delta,
     "ObjectCreated": handle_object_created,
     "ObjectDeleted": handle_object_deleted,
     "TransformUpdated": handle_transform_updated,
     "PropertiesUpdated": handle_properties_updated,
+    "PropertiesPatched": handle_properties_patched,
     "NameUpdated": handle_name_updated,
+    "ParentUpdated": handle_parent_updated,
+    "CollectionCreated": handle_collection_created,
+    "CollectionUpdated": handle_collection_updated,
+    "CollectionDeleted": handle_collection_deleted,
+    "ObjectCollectionsUpdated": handle_object_collections_updated,
+    "MaterialCreated": handle_material_created,
+    "MaterialUpdated": handle_material_updated,
+    "ObjectMaterialsUpdated": handle_object_materials_updated,
+    "MeshStored": handle_mesh_stored,
+    "MeshChunk": handle_mesh_chunk,
+    "ObjectMeshUpdated": handle_object_mesh_updated,
+    "ObjectModifiersUpdated": handle_object_modifiers_updated,
+    "CustomPropertyUpdated": handle_custom_property_updated,
+    "TransactionApplied": handle_transaction_applied,
+    "HistoryApplied": handle_history_applied,
+    "CheckpointCreated": handle_checkpoint_created,
+    "CheckpointList": handle_checkpoint_list,
+    "CheckpointRestored": handle_checkpoint_restored,
+    "SessionForked": handle_session_forked,
+    "StateChecksum": handle_state_checksum,
+    "ObjectsSynced": handle_objects_synced,
+    "TrashList": handle_trash_list,
+    "AuditLog": handle_audit_log,
     "UserJoined": handle_user_joined,
     "UserLeft": handle_user_left,
     "UserSelected": handle_user_selected,
     "CursorUpdated": handle_cursor_updated,
+    "EventRejected": handle_event_rejected,
     "Error": handle_error,
 }
 
@@ -920,6 +2408,8 @@ def timer_function():
     if not state.connected:
         return timer
 
+    _resend_rejected_events()
+
     if not state.is_applying_remote_update:
         detect_and_send_creations()
 
@@ -927,6 +2417,11 @@ def timer_function():
     if not state.is_applying_remote_update:
         detect_and_send_deletions()
 
+    if not state.is_applying_remote_update:
+        detect_and_sync_collections()
+        detect_and_sync_materials()
+        detect_and_sync_meshes()
+
     # --- Selection polling ---
     if not state.is_applying_remote_update:
         active = bpy.context.view_layer.objects.active if bpy.context.view_layer else None
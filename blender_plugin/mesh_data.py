@@ -0,0 +1,87 @@
+# mesh_data.py — mesh geometry encoding, identical to the server's mesh.rs
+import base64
+import hashlib
+import struct
+import sys
+import zlib
+from array import array
+
+MESH_MAGIC = b"MKMS"
+MESH_FORMAT_VERSION = 1
+MESH_CHUNK_LEN = 256 * 1024  # compressed bytes per UploadMeshChunk / MeshChunk
+_HEADER = struct.Struct("<4s6I")
+
+
+def _le_bytes(values):
+    """An array's items as little-endian bytes, whatever the machine's byte order."""
+    if sys.byteorder != "little":
+        values = array(values.typecode, values)
+        values.byteswap()
+    return values.tobytes()
+
+
+def _from_le(typecode, data):
+    values = array(typecode)
+    values.frombytes(data)
+    if sys.byteorder != "little":
+        values.byteswap()
+    return values
+
+
+def encode(positions, face_sizes, face_vertices, uvs=None, normals=None):
+    """Encode flat geometry: positions xyz per vertex, a corner count per face, the vertex index
+    at every corner, and optionally uv per corner and xyz normal per corner. The content hash
+    is taken over these bytes, so it must match the server byte for byte."""
+    positions = array("f", positions)
+    uvs = array("f", uvs or [])
+    normals = array("f", normals or [])
+    header = _HEADER.pack(
+        MESH_MAGIC, MESH_FORMAT_VERSION,
+        len(positions) // 3, len(face_sizes), len(face_vertices), len(uvs) // 2, len(normals) // 3,
+    )
+    return b"".join([
+        header,
+        _le_bytes(positions),
+        _le_bytes(array("I", face_sizes)),
+        _le_bytes(array("I", face_vertices)),
+        _le_bytes(uvs),
+        _le_bytes(normals),
+    ])
+
+
+def decode(data):
+    """The flat arrays of an encoded mesh, as a dict keyed like `encode`'s arguments."""
+    if len(data) < _HEADER.size:
+        raise ValueError("mesh data too short")
+    magic, version, vertices, faces, corners, uv_count, normal_count = _HEADER.unpack_from(data)
+    if magic != MESH_MAGIC or version != MESH_FORMAT_VERSION:
+        raise ValueError(f"unsupported mesh data (magic={magic!r}, version={version})")
+    sizes = [vertices * 3, faces, corners, uv_count * 2, normal_count * 3]
+    if len(data) != _HEADER.size + 4 * sum(sizes):
+        raise ValueError("mesh data length does not match its counts")
+    offset = _HEADER.size
+    parts = []
+    for typecode, size in zip("fIIff", sizes):
+        parts.append(_from_le(typecode, data[offset:offset + 4 * size]))
+        offset += 4 * size
+    return dict(zip(["positions", "face_sizes", "face_vertices", "uvs", "normals"], parts))
+
+
+def content_hash(encoded):
+    return hashlib.sha256(encoded).hexdigest()
+
+
+def compress(encoded):
+    """Raw deflate, as the server stores it."""
+    compressor = zlib.compressobj(wbits=-15)
+    return compressor.compress(encoded) + compressor.flush()
+
+
+def inflate(compressed):
+    return zlib.decompress(compressed, wbits=-15)
+
+
+def to_chunks(compressed):
+    """Base64 chunks of at most MESH_CHUNK_LEN compressed bytes each."""
+    pieces = [compressed[i:i + MESH_CHUNK_LEN] for i in range(0, len(compressed), MESH_CHUNK_LEN)]
+    return [base64.b64encode(piece).decode("ascii") for piece in pieces]
@@ -14,6 +14,11 @@ def _send_create_object(obj, object_type, properties=None, asset_id=None, asset_
     state = PluginState()
     meerkat_id = str(uuid4())
     obj["meerkat_id"] = meerkat_id
+    obj["meerkat_object_type"] = object_type
+    if asset_id:
+        obj["meerkat_asset_id"] = asset_id
+    if asset_library:
+        obj["meerkat_asset_library"] = asset_library
     state.object_map[meerkat_id] = obj
 
     state.ws_client.send({
@@ -360,6 +365,185 @@ class MEERKAT_OT_cursor_tracker(bpy.types.Operator):
         return {'RUNNING_MODAL'}
 
 
+# ── Undo / Redo operators ───────────────────────────────────────────────────
+# Server-side history: only reverts this user's own changes, unlike Blender's Ctrl+Z.
+
+class MEERKAT_OT_undo(bpy.types.Operator):
+    bl_idname = "meerkat.undo"
+    bl_label = "Undo My Change"
+    bl_description = "Revert your most recent synced change without touching other users' edits"
+
+    def execute(self, context):
+        state = PluginState()
+        if not state.connected or not state.ws_client:
+            self.report({'ERROR'}, "Not connected to a session")
+            return {'CANCELLED'}
+        state.ws_client.send({"event_type": "Undo", "payload": None})
+        return {'FINISHED'}
+
+
+class MEERKAT_OT_redo(bpy.types.Operator):
+    bl_idname = "meerkat.redo"
+    bl_label = "Redo My Change"
+    bl_description = "Re-apply your most recently undone synced change"
+
+    def execute(self, context):
+        state = PluginState()
+        if not state.connected or not state.ws_client:
+            self.report({'ERROR'}, "Not connected to a session")
+            return {'CANCELLED'}
+        state.ws_client.send({"event_type": "Redo", "payload": None})
+        return {'FINISHED'}
+
+
+# ── Checkpoint operators ────────────────────────────────────────────────────
+
+class MEERKAT_OT_create_checkpoint(bpy.types.Operator):
+    bl_idname = "meerkat.create_checkpoint"
+    bl_label = "Create Checkpoint"
+    bl_description = "Tag the current session state so it can be restored later"
+
+    checkpoint_name: bpy.props.StringProperty(name="Name", default="Checkpoint")
+
+    def invoke(self, context, event):
+        return context.window_manager.invoke_props_dialog(self)
+
+    def execute(self, context):
+        state = PluginState()
+        if not state.connected or not state.ws_client:
+            self.report({'ERROR'}, "Not connected to a session")
+            return {'CANCELLED'}
+        state.ws_client.send({
+            "event_type": "CreateCheckpoint",
+            "payload": {"name": self.checkpoint_name},
+        })
+        return {'FINISHED'}
+
+
+def _get_checkpoint_items(self, context):
+    state = PluginState()
+    if not state.checkpoints:
+        return [("NONE", "No checkpoints", "")]
+    return [(c["checkpoint_id"], c["name"], f"{c['object_count']} objects") for c in reversed(state.checkpoints)]
+
+
+class MEERKAT_OT_restore_checkpoint(bpy.types.Operator):
+    bl_idname = "meerkat.restore_checkpoint"
+    bl_label = "Restore Checkpoint"
+    bl_description = "Roll the whole session back to a checkpoint for every user"
+
+    checkpoint_id: bpy.props.EnumProperty(name="Checkpoint", items=_get_checkpoint_items)
+
+    def invoke(self, context, event):
+        state = PluginState()
+        if state.connected and state.ws_client:
+            state.ws_client.send({"event_type": "ListCheckpoints", "payload": None})
+        return context.window_manager.invoke_props_dialog(self)
+
+    def execute(self, context):
+        state = PluginState()
+        if not state.connected or not state.ws_client:
+            self.report({'ERROR'}, "Not connected to a session")
+            return {'CANCELLED'}
+        if self.checkpoint_id == "NONE":
+            self.report({'WARNING'}, "No checkpoints in this session")
+            return {'CANCELLED'}
+        state.ws_client.send({
+            "event_type": "RestoreCheckpoint",
+            "payload": {"checkpoint_id": self.checkpoint_id},
+        })
+        return {'FINISHED'}
+
+
+# ── Restore from Trash operator ─────────────────────────────────────────────
+
+def _get_trash_items(self, context):
+    state = PluginState()
+    if not state.trash:
+        return [("NONE", "Trash is empty", "")]
+    return [(t["object"]["object_id"], t["object"]["name"], f"{t['object']['object_type']}") for t in state.trash]
+
+
+class MEERKAT_OT_restore_object(bpy.types.Operator):
+    bl_idname = "meerkat.restore_object"
+    bl_label = "Restore Deleted"
+    bl_description = "Bring back an object someone deleted from this session"
+
+    object_id: bpy.props.EnumProperty(name="Object", items=_get_trash_items)
+
+    def invoke(self, context, event):
+        state = PluginState()
+        if state.connected and state.ws_client:
+            state.ws_client.send({"event_type": "ListTrash", "payload": None})
+        return context.window_manager.invoke_props_dialog(self)
+
+    def execute(self, context):
+        state = PluginState()
+        if not state.connected or not state.ws_client:
+            self.report({'ERROR'}, "Not connected to a session")
+            return {'CANCELLED'}
+        if self.object_id == "NONE":
+            self.report({'WARNING'}, "Trash is empty")
+            return {'CANCELLED'}
+        state.ws_client.send({
+            "event_type": "RestoreObject",
+            "payload": {"object_id": self.object_id},
+        })
+        return {'FINISHED'}
+
+
+# ── Object History operator ─────────────────────────────────────────────────
+
+class MEERKAT_OT_object_history(bpy.types.Operator):
+    bl_idname = "meerkat.object_history"
+    bl_label = "Who Changed This?"
+    bl_description = "Show the recent audit history of the active object"
+
+    def execute(self, context):
+        state = PluginState()
+        if not state.connected or not state.ws_client:
+            self.report({'ERROR'}, "Not connected to a session")
+            return {'CANCELLED'}
+        obj = context.active_object
+        meerkat_id = obj.get("meerkat_id") if obj else None
+        if not meerkat_id:
+            self.report({'WARNING'}, "Select a synced object first")
+            return {'CANCELLED'}
+        state.ws_client.send({
+            "event_type": "QueryAuditLog",
+            "payload": {"object_id": meerkat_id, "limit": 20},
+        })
+        return {'FINISHED'}
+
+
+# ── Fork Session operator ───────────────────────────────────────────────────
+
+class MEERKAT_OT_fork_session(bpy.types.Operator):
+    bl_idname = "meerkat.fork_session"
+    bl_label = "Fork Session"
+    bl_description = "Copy this session into a new one with its own password and move there"
+
+    new_session_id: bpy.props.StringProperty(name="New Room Name")
+    password: bpy.props.StringProperty(name="Password", subtype='PASSWORD')
+
+    def invoke(self, context, event):
+        return context.window_manager.invoke_props_dialog(self)
+
+    def execute(self, context):
+        state = PluginState()
+        if not state.connected or not state.ws_client:
+            self.report({'ERROR'}, "Not connected to a session")
+            return {'CANCELLED'}
+        if not self.new_session_id.strip():
+            self.report({'ERROR'}, "Enter a name for the new session")
+            return {'CANCELLED'}
+        state.ws_client.send({
+            "event_type": "ForkSession",
+            "payload": {"new_session_id": self.new_session_id.strip(), "password": self.password},
+        })
+        return {'FINISHED'}
+
+
 # ── Save Scene operator ─────────────────────────────────────────────────────
 
 class MEERKAT_OT_save_scene(bpy.types.Operator):
@@ -373,10 +557,13 @@ class MEERKAT_OT_save_scene(bpy.types.Operator):
             self.report({'ERROR'}, "Not connected to a session")
             return {'CANCELLED'}
 
-        # Request fresh state from the server
+        # Request fresh state from the server; only what changed since our last sync if we know it
+        payload = None
+        if state.session_version is not None:
+            payload = {"since_version": state.session_version}
         state.ws_client.send({
             "event_type": "RequestStateSync",
-            "payload": None,
+            "payload": payload,
         })
 
         # Open file browser to save
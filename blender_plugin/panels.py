@@ -64,6 +64,15 @@ class MEERKAT_PT_main_panel(bpy.types.Panel):
                 layout.operator("meerkat.place_asset")
 
             layout.separator()
+            history_row = layout.row(align=True)
+            history_row.operator("meerkat.undo", icon='LOOP_BACK')
+            history_row.operator("meerkat.redo", icon='LOOP_FORWARDS')
+            checkpoint_row = layout.row(align=True)
+            checkpoint_row.operator("meerkat.create_checkpoint", icon='BOOKMARKS')
+            checkpoint_row.operator("meerkat.restore_checkpoint", icon='RECOVER_LAST')
+            layout.operator("meerkat.restore_object", icon='TRASH')
+            layout.operator("meerkat.object_history", icon='TIME')
+            layout.operator("meerkat.fork_session", icon='DUPLICATE')
             layout.operator("meerkat.save_scene", icon='FILE_TICK')
             disconnect_row = layout.row()
             disconnect_row.alert = len(state.users) <= 1
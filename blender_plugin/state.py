@@ -34,6 +34,27 @@ class PluginState(metaclass=Singleton):
     transform_cache: dict = field(default_factory=dict)        # meerkat_id -> {position, rotation, scale}
     property_cache: dict = field(default_factory=dict)         # meerkat_id -> last sent properties dict
     name_cache: dict = field(default_factory=dict)             # meerkat_id -> last sent obj.name
+    parent_cache: dict = field(default_factory=dict)           # meerkat_id -> last synced parent meerkat_id (or None)
+    collections: dict = field(default_factory=dict)            # collection_id -> bpy.types.Collection
+    collection_cache: dict = field(default_factory=dict)       # collection_id -> last synced {name, color_tag, visible, selectable}
+    membership_cache: dict = field(default_factory=dict)       # meerkat_id -> last synced sorted collection ids
+    custom_property_cache: dict = field(default_factory=dict)  # meerkat_id -> {key: last synced tagged value}
+    materials: dict = field(default_factory=dict)              # material_id -> bpy.types.Material
+    material_cache: dict = field(default_factory=dict)         # material_id -> last synced {name, base_color, ...}
+    material_slot_cache: dict = field(default_factory=dict)    # meerkat_id -> last synced material ids per slot
+    mesh_refs: dict = field(default_factory=dict)              # meerkat_id -> server {hash} of its mesh data
+    mesh_versions: dict = field(default_factory=dict)          # meerkat_id -> server mesh version, kept across clears
+    mesh_cache: dict = field(default_factory=dict)             # meerkat_id -> content hash of its geometry as last synced
+    known_meshes: set = field(default_factory=set)             # content hashes the server already stores
+    dirty_meshes: set = field(default_factory=set)             # meerkat_ids whose geometry changed since the last check
+    mesh_downloads: dict = field(default_factory=dict)         # hash -> {chunks, object_ids} of a RequestMesh in flight
+    modifier_cache: dict = field(default_factory=dict)         # meerkat_id -> last synced modifier stack, server format
+    session_version: int | None = None                           # server change-log version of the last applied sync
+    rejected_events: list = field(default_factory=list)         # (monotonic due time, event) edits the rate limit refused, to resend
+    pending_sync: dict | None = None                             # buffered StateSyncBegin/Chunk data until StateSyncEnd
+    checkpoints: list = field(default_factory=list)             # CheckpointInfo dicts, oldest first
+    trash: list = field(default_factory=list)                   # TrashItem dicts, newest first
+    audit_entries: list = field(default_factory=list)           # last AuditLog reply, newest first
     last_selected: str | None = None                             # meerkat_id of last selected object (or None)
     draw_handler: object | None = None                           # SpaceView3D POST_VIEW handler
     cursor_draw_handler: object | None = None                    # SpaceView3D POST_PIXEL handler
@@ -25,8 +25,28 @@ def reset_state():
     state.transform_cache.clear()
     state.property_cache.clear()
     state.name_cache.clear()
+    state.parent_cache.clear()
+    state.collections.clear()
+    state.collection_cache.clear()
+    state.membership_cache.clear()
+    state.custom_property_cache.clear()
+    state.materials.clear()
+    state.material_cache.clear()
+    state.material_slot_cache.clear()
+    state.mesh_refs.clear()
+    state.mesh_cache.clear()
+    state.known_meshes.clear()
+    state.dirty_meshes.clear()
+    state.mesh_downloads.clear()
+    state.modifier_cache.clear()
     state.asset_library_objects.clear()
     state.last_selected = None
+    state.pending_sync = None
+    state.rejected_events.clear()
+    state.session_version = None
+    state.checkpoints.clear()
+    state.trash.clear()
+    state.audit_entries.clear()
     state.reconnecting = False
     state.reconnect_attempt = 0
     state.intentional_disconnect = False
@@ -37,14 +57,31 @@ def reset_state():
 
 
 def clear_scene():
-    """Remove all objects from the Blender scene and clear stale references."""
+    """Remove all objects, collections and materials from the Blender scene and clear stale references."""
     state = PluginState()
     state.object_map.clear()
     state.transform_cache.clear()
     state.property_cache.clear()
     state.name_cache.clear()
+    state.parent_cache.clear()
+    state.collections.clear()
+    state.collection_cache.clear()
+    state.membership_cache.clear()
+    state.custom_property_cache.clear()
+    state.materials.clear()
+    state.material_cache.clear()
+    state.material_slot_cache.clear()
+    state.mesh_refs.clear()
+    state.mesh_cache.clear()
+    state.dirty_meshes.clear()
+    state.mesh_downloads.clear()
+    state.modifier_cache.clear()
     for obj in list(bpy.data.objects):
         bpy.data.objects.remove(obj, do_unlink=True)
+    for collection in list(bpy.data.collections):
+        bpy.data.collections.remove(collection)
+    for material in list(bpy.data.materials):
+        bpy.data.materials.remove(material)
 
 
 def create_tagged_cube(meerkat_id="cube-001"):
@@ -26,6 +26,13 @@ from blender_plugin.tests import (
     test_presence,
     test_reconnect,
     test_save_scene,
+    test_hierarchy,
+    test_collections,
+    test_custom_properties,
+    test_materials,
+    test_meshes,
+    test_modifiers,
+    test_checksum,
 )
 
 TEST_MODULES = [
@@ -39,6 +46,13 @@ TEST_MODULES = [
     test_full_state_sync,
     test_reconnect,
     test_save_scene,
+    test_hierarchy,
+    test_collections,
+    test_custom_properties,
+    test_materials,
+    test_meshes,
+    test_modifiers,
+    test_checksum,
 ]
 
 
@@ -0,0 +1,122 @@
+"""Tests for drift detection — client-side object hashes, StateChecksum, ObjectsSynced."""
+import bpy
+from blender_plugin.state import PluginState
+from blender_plugin.event_handlers import (
+    _object_hash,
+    handle_full_state_sync,
+    handle_objects_synced,
+    handle_state_checksum,
+)
+from blender_plugin.tests.helpers import (
+    reset_state, clear_scene, TestResult,
+)
+
+CUBE_ID = "00000000-0000-0000-0000-000000000001"
+SPHERE_ID = "00000000-0000-0000-0000-000000000002"
+SERVER_ONLY_ID = "00000000-0000-0000-0000-000000000003"
+
+
+def _sync_scene():
+    handle_full_state_sync({
+        "session": {
+            "objects": {
+                CUBE_ID: {"object_type": "Cube", "name": "Cube",
+                          "transform": {"position": [1, 2, 3], "rotation": [0, 0, 0], "scale": [1, 1, 1]}},
+                SPHERE_ID: {"object_type": "Sphere", "name": "Ball",
+                            "transform": {"position": [0, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]}},
+            },
+            "users": {},
+            "version": 3,
+        },
+    })
+
+
+def run(result):
+    print("\n--- Checksum Tests ---")
+
+    # ── The canonical form matches the server's ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    _sync_scene()
+    cube = state.object_map.get(CUBE_ID)
+    sphere = state.object_map.get(SPHERE_ID)
+    # Pinned in backend tests/checksum_test.rs (object_hash_matches_pinned_canonical_form).
+    cube_hash = f"{_object_hash(CUBE_ID, cube):016x}"
+    if cube_hash == "1e6f832c8c0c9c27":
+        result.ok("object hash matches the server's canonical form")
+    else:
+        result.fail("object hash matches the server's canonical form", f"got {cube_hash}")
+
+    sphere_hash = f"{_object_hash(SPHERE_ID, sphere):016x}"
+    total = (_object_hash(CUBE_ID, cube) + _object_hash(SPHERE_ID, sphere)) & 0xFFFFFFFFFFFFFFFF
+
+    # ── Matching checksum: nothing sent, version advanced ──
+
+    mock_ws.clear()
+    handle_state_checksum({"version": 7, "object_count": 2, "checksum": f"{total:016x}", "object_hashes": None})
+    if not mock_ws.sent_messages and state.session_version == 7:
+        result.ok("matching StateChecksum → no resync, version stored")
+    else:
+        result.fail("matching StateChecksum → no resync, version stored",
+                    f"sent {mock_ws.sent_messages}, version {state.session_version}")
+
+    # ── Mismatch: ask for per-object hashes ──
+
+    handle_state_checksum({"version": 8, "object_count": 2, "checksum": "0000000000000001", "object_hashes": None})
+    requested = mock_ws.get_sent("RequestChecksum")
+    if requested == [{"event_type": "RequestChecksum", "payload": {"include_objects": True}}] \
+            and state.session_version == 7:
+        result.ok("mismatched StateChecksum → RequestChecksum with object hashes")
+    else:
+        result.fail("mismatched StateChecksum → RequestChecksum with object hashes", f"sent {mock_ws.sent_messages}")
+
+    # ── Per-object hashes: resync what differs on either side ──
+
+    mock_ws.clear()
+    handle_state_checksum({
+        "version": 8, "object_count": 2, "checksum": "0000000000000001",
+        "object_hashes": {CUBE_ID: "0000000000000002", SPHERE_ID: sphere_hash, SERVER_ONLY_ID: "0000000000000003"},
+    })
+    synced = mock_ws.get_sent("RequestObjectsSync")
+    if len(synced) == 1 and synced[0]["payload"]["object_ids"] == [CUBE_ID, SERVER_ONLY_ID]:
+        result.ok("object hashes → RequestObjectsSync for drifted and server-only objects")
+    else:
+        result.fail("object hashes → RequestObjectsSync for drifted and server-only objects",
+                    f"sent {mock_ws.sent_messages}")
+
+    mock_ws.clear()
+    handle_state_checksum({"version": 8, "object_count": 1, "checksum": "0000000000000001",
+                           "object_hashes": {CUBE_ID: cube_hash}})
+    synced = mock_ws.get_sent("RequestObjectsSync")
+    if len(synced) == 1 and synced[0]["payload"]["object_ids"] == [SPHERE_ID]:
+        result.ok("object hashes → RequestObjectsSync for client-only objects")
+    else:
+        result.fail("object hashes → RequestObjectsSync for client-only objects", f"sent {mock_ws.sent_messages}")
+
+    # ── ObjectsSynced repairs in place and drops what the server lacks ──
+
+    cube.select_set(True)
+    handle_objects_synced({
+        "version": 8,
+        "objects": [
+            {"object_id": CUBE_ID, "object_type": "Cube", "name": "Cube",
+             "transform": {"position": [5, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]}},
+            {"object_id": SERVER_ONLY_ID, "object_type": "Cone", "name": "Cone",
+             "transform": {"position": [0, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]}},
+        ],
+        "missing": [SPHERE_ID],
+    })
+    if state.object_map.get(CUBE_ID) is cube and abs(cube.location.x - 5.0) < 0.001 and cube.select_get() \
+            and SERVER_ONLY_ID in state.object_map and SPHERE_ID not in state.object_map \
+            and "Ball" not in bpy.data.objects:
+        result.ok("ObjectsSynced → updated in place, created and removed")
+    else:
+        result.fail("ObjectsSynced → updated in place, created and removed",
+                    f"map keys {list(state.object_map.keys())}")
+
+
+if __name__ == "__main__":
+    r = TestResult()
+    run(r)
+    r.summary()
@@ -0,0 +1,164 @@
+"""Tests for collection sync — Collection* events, membership polling, collections in snapshots."""
+import bpy
+from blender_plugin.state import PluginState
+from blender_plugin.event_handlers import (
+    detect_and_sync_collections,
+    handle_collection_created,
+    handle_collection_deleted,
+    handle_collection_updated,
+    handle_full_state_sync,
+    handle_object_collections_updated,
+    timer_function_transforms,
+)
+from blender_plugin.tests.helpers import (
+    reset_state, clear_scene, create_tagged_cube, TestResult,
+)
+
+
+def _collection(collection_id, name, **flags):
+    return {
+        "collection_id": collection_id,
+        "name": name,
+        "color_tag": flags.get("color_tag", "NONE"),
+        "visible": flags.get("visible", True),
+        "selectable": flags.get("selectable", True),
+    }
+
+
+def run(result):
+    print("\n--- Collection Tests ---")
+
+    # ── CollectionCreated / CollectionUpdated build the Blender collection ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    handle_collection_created({
+        "collection": _collection("coll-001", "Props", color_tag="COLOR_04"),
+        "created_by": "other-user",
+    })
+    props = state.collections.get("coll-001")
+    if props is not None and props.name == "Props" and props.color_tag == "COLOR_04" \
+            and props.name in bpy.context.scene.collection.children:
+        result.ok("CollectionCreated → collection under the scene root")
+    else:
+        result.fail("CollectionCreated → collection under the scene root", f"got {props}")
+
+    handle_collection_updated({
+        "collection": _collection("coll-001", "Props", visible=False, selectable=False),
+        "updated_by": "other-user",
+    })
+    if props.hide_viewport and props.hide_select:
+        result.ok("CollectionUpdated → hidden and unselectable")
+    else:
+        result.fail("CollectionUpdated → hidden and unselectable",
+                    f"hide_viewport={props.hide_viewport}, hide_select={props.hide_select}")
+
+    detect_and_sync_collections()
+    if not mock_ws.get_sent("UpdateCollection") and not mock_ws.get_sent("CreateCollection"):
+        result.ok("CollectionUpdated → no echo")
+    else:
+        result.fail("CollectionUpdated → no echo", f"sent {mock_ws.sent_messages}")
+
+    # ── Local collection edits are sent ──
+
+    props.hide_select = False
+    detect_and_sync_collections()
+    sent = mock_ws.get_sent("UpdateCollection")
+    if len(sent) == 1 and sent[0]["payload"]["selectable"] is True and sent[0]["payload"]["visible"] is False:
+        result.ok("toggling selectable → sends UpdateCollection")
+    else:
+        result.fail("toggling selectable → sends UpdateCollection", f"sent {sent}")
+
+    mock_ws.clear()
+    extra = bpy.data.collections.new("Lights")
+    bpy.context.scene.collection.children.link(extra)
+    detect_and_sync_collections()
+    sent = mock_ws.get_sent("CreateCollection")
+    if any(m["payload"]["name"] == "Lights" for m in sent) and "meerkat_collection_id" in extra:
+        result.ok("new outliner collection → sends CreateCollection")
+    else:
+        result.fail("new outliner collection → sends CreateCollection", f"sent {sent}")
+
+    mock_ws.clear()
+    bpy.data.collections.remove(extra)
+    detect_and_sync_collections()
+    if len(mock_ws.get_sent("DeleteCollection")) == 1:
+        result.ok("removed collection → sends DeleteCollection")
+    else:
+        result.fail("removed collection → sends DeleteCollection", f"sent {mock_ws.sent_messages}")
+
+    # ── Membership polling and ObjectCollectionsUpdated ──
+
+    mock_ws.clear()
+    cube = create_tagged_cube("cube-001")
+    props.objects.link(cube)
+    timer_function_transforms()
+    sent = mock_ws.get_sent("SetObjectCollections")
+    if len(sent) == 1 and sent[0]["payload"] == {"object_id": "cube-001", "collection_ids": ["coll-001"]}:
+        result.ok("linking into a collection → sends SetObjectCollections")
+    else:
+        result.fail("linking into a collection → sends SetObjectCollections", f"sent {sent}")
+
+    mock_ws.clear()
+    handle_object_collections_updated({"object_id": "cube-001", "collection_ids": [], "updated_by": "other-user"})
+    timer_function_transforms()
+    if cube.name not in props.objects and cube.name in bpy.context.scene.collection.objects \
+            and not mock_ws.get_sent("SetObjectCollections"):
+        result.ok("ObjectCollectionsUpdated → moved to the scene root, no echo")
+    else:
+        result.fail("ObjectCollectionsUpdated → moved to the scene root, no echo",
+                    f"in={[c.name for c in cube.users_collection]}, sent {mock_ws.sent_messages}")
+
+    # ── CollectionDeleted keeps its members in the scene ──
+
+    handle_object_collections_updated({"object_id": "cube-001", "collection_ids": ["coll-001"], "updated_by": "other-user"})
+    mock_ws.clear()
+    handle_collection_deleted({"collection_id": "coll-001", "deleted_by": "other-user"})
+    timer_function_transforms()
+    detect_and_sync_collections()
+    if "coll-001" not in state.collections and cube.name in bpy.context.scene.collection.objects \
+            and not mock_ws.get_sent("SetObjectCollections") and not mock_ws.get_sent("DeleteCollection"):
+        result.ok("CollectionDeleted → members back at the scene root, no echo")
+    else:
+        result.fail("CollectionDeleted → members back at the scene root, no echo",
+                    f"in={[c.name for c in cube.users_collection]}, sent {mock_ws.sent_messages}")
+
+    # ── Snapshots bring collections and membership ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    handle_full_state_sync({
+        "session": {
+            "objects": {
+                "cube-002": {
+                    "object_type": "Cube",
+                    "name": "Crate",
+                    "collections": ["coll-002"],
+                    "transform": {"position": [0, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]},
+                },
+            },
+            "collections": {"coll-002": _collection("coll-002", "Props", visible=False)},
+            "users": {},
+        },
+        "your_user_id": state.user_id,
+    })
+    crate = state.object_map.get("cube-002")
+    props = state.collections.get("coll-002")
+    if crate is not None and props is not None and crate.name in props.objects and props.hide_viewport:
+        result.ok("FullStateSync → collections and membership restored")
+    else:
+        result.fail("FullStateSync → collections and membership restored", f"object={crate}, collection={props}")
+
+    mock_ws.clear()
+    timer_function_transforms()
+    detect_and_sync_collections()
+    if not mock_ws.get_sent("SetObjectCollections") and not mock_ws.get_sent("CreateCollection"):
+        result.ok("FullStateSync collections → no echo")
+    else:
+        result.fail("FullStateSync collections → no echo", f"sent {mock_ws.sent_messages}")
+
+
+if __name__ == "__main__":
+    r = TestResult()
+    run(r)
+    r.summary()
@@ -0,0 +1,103 @@
+"""Tests for custom property sync — polling, CustomPropertyUpdated, custom properties in snapshots."""
+from blender_plugin.event_handlers import (
+    handle_custom_property_updated,
+    handle_full_state_sync,
+    timer_function_transforms,
+)
+from blender_plugin.tests.helpers import (
+    reset_state, clear_scene, create_tagged_cube, TestResult,
+)
+
+
+def run(result):
+    print("\n--- Custom Property Tests ---")
+
+    # ── Local custom properties are sent, one event per key ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    cube = create_tagged_cube("cube-001")
+    timer_function_transforms()
+    mock_ws.clear()
+
+    cube["shot"] = "010"
+    cube["lod_bias"] = 2
+    cube["tint"] = [1.0, 0.5, 0.25]
+    cube["_private"] = "skip"
+    timer_function_transforms()
+    sent = {m["payload"]["key"]: m["payload"]["value"] for m in mock_ws.get_sent("SetCustomProperty")}
+    expected = {"shot": {"String": "010"}, "lod_bias": {"Number": 2.0}, "tint": {"Vector": [1.0, 0.5, 0.25]}}
+    if sent == expected:
+        result.ok("new custom properties → SetCustomProperty per key")
+    else:
+        result.fail("new custom properties → SetCustomProperty per key", f"sent {sent}")
+
+    if not any(m["payload"]["key"] == "meerkat_id" for m in mock_ws.get_sent("SetCustomProperty")):
+        result.ok("meerkat_id is never synced as a custom property")
+    else:
+        result.fail("meerkat_id is never synced as a custom property", "sent meerkat_id")
+
+    mock_ws.clear()
+    del cube["shot"]
+    timer_function_transforms()
+    sent = mock_ws.get_sent("RemoveCustomProperty")
+    if len(sent) == 1 and sent[0]["payload"] == {"object_id": "cube-001", "key": "shot"} \
+            and not mock_ws.get_sent("SetCustomProperty"):
+        result.ok("deleted custom property → RemoveCustomProperty")
+    else:
+        result.fail("deleted custom property → RemoveCustomProperty", f"sent {mock_ws.sent_messages}")
+
+    # ── CustomPropertyUpdated from someone else is applied without echo ──
+
+    mock_ws.clear()
+    handle_custom_property_updated({
+        "object_id": "cube-001",
+        "key": "tags",
+        "value": {"StringList": ["hero", "prop"]},
+        "updated_by": "other-user",
+    })
+    handle_custom_property_updated({"object_id": "cube-001", "key": "tint", "value": None, "updated_by": "other-user"})
+    timer_function_transforms()
+    if list(cube["tags"]) == ["hero", "prop"] and "tint" not in cube \
+            and not mock_ws.get_sent("SetCustomProperty") and not mock_ws.get_sent("RemoveCustomProperty"):
+        result.ok("CustomPropertyUpdated → applied, no echo")
+    else:
+        result.fail("CustomPropertyUpdated → applied, no echo",
+                    f"keys={list(cube.keys())}, sent {mock_ws.sent_messages}")
+
+    # ── Snapshots bring custom properties ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    handle_full_state_sync({
+        "session": {
+            "objects": {
+                "cube-002": {
+                    "object_type": "Cube",
+                    "name": "Crate",
+                    "custom_properties": {"approved": {"Bool": True}, "shot": {"String": "020"}},
+                    "transform": {"position": [0, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]},
+                },
+            },
+            "users": {},
+        },
+        "your_user_id": state.user_id,
+    })
+    crate = state.object_map.get("cube-002")
+    if crate is not None and crate.get("approved") is True and crate.get("shot") == "020":
+        result.ok("FullStateSync → custom properties restored")
+    else:
+        result.fail("FullStateSync → custom properties restored", f"keys={list(crate.keys()) if crate else None}")
+
+    mock_ws.clear()
+    timer_function_transforms()
+    if not mock_ws.get_sent("SetCustomProperty"):
+        result.ok("FullStateSync custom properties → no echo")
+    else:
+        result.fail("FullStateSync custom properties → no echo", f"sent {mock_ws.sent_messages}")
+
+
+if __name__ == "__main__":
+    r = TestResult()
+    run(r)
+    r.summary()
@@ -56,6 +56,25 @@ def run(result):
     else:
         result.fail("ObjectCreated from other user → object created")
 
+    # ── ObjectCreated for own object restored from the trash is applied ──
+
+    handle_object_created({
+        "object": {
+            "object_id": "restored-cube-001",
+            "object_type": "Cube",
+            "name": "RestoredCube",
+            "transform": {"position": [0, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]},
+            "properties": None,
+        },
+        "created_by": "test-user-123",  # original creator is self
+        "restored_by": "other-user-456",
+    })
+
+    if "restored-cube-001" in state.object_map:
+        result.ok("ObjectCreated restored from trash → applied even for own object")
+    else:
+        result.fail("ObjectCreated restored from trash → applied even for own object")
+
     # ── ObjectDeleted from self is ignored ──
 
     clear_scene()
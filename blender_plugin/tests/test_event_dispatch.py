@@ -11,11 +11,15 @@ from blender_plugin.event_handlers import (
     handle_transform_updated,
     handle_properties_updated,
     handle_name_updated,
+    handle_transaction_applied,
+    handle_history_applied,
     handle_user_joined,
     handle_user_left,
+    handle_event_rejected,
+    timer_function,
 )
 from blender_plugin.panels import _connection_status_lines
-from blender_plugin.tests.helpers import reset_state, clear_scene, TestResult
+from blender_plugin.tests.helpers import reset_state, clear_scene, create_tagged_cube, TestResult
 
 
 def run(result):
@@ -26,7 +30,7 @@ def run(result):
     expected_events = [
         "FullStateSync", "ObjectCreated", "ObjectDeleted",
         "TransformUpdated", "PropertiesUpdated", "NameUpdated",
-        "UserJoined", "UserLeft",
+        "TransactionApplied", "HistoryApplied", "UserJoined", "UserLeft",
     ]
     for event in expected_events:
         if event in EVENT_HANDLERS:
@@ -43,6 +47,8 @@ def run(result):
         "TransformUpdated": handle_transform_updated,
         "PropertiesUpdated": handle_properties_updated,
         "NameUpdated": handle_name_updated,
+        "TransactionApplied": handle_transaction_applied,
+        "HistoryApplied": handle_history_applied,
         "UserJoined": handle_user_joined,
         "UserLeft": handle_user_left,
     }
@@ -137,6 +143,60 @@ def run(result):
     except Exception as e:
         result.fail("UserLeft for nonexistent user → no crash", str(e))
 
+    # ── TransactionApplied runs every step in order ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    obj = create_tagged_cube("cube-txn-001")
+
+    handle_transaction_applied({
+        "transaction_id": "txn-001",
+        "applied_by": "other-user-id",
+        "version": 3,
+        "operations": [
+            {"op": "UpdateName", "payload": {"object_id": "cube-txn-001", "name": "Renamed"}},
+            {"op": "UpdateTransform", "payload": {"object_id": "cube-txn-001", "transform": {
+                "position": [1.0, 2.0, 3.0], "rotation": [0.0, 0.0, 0.0], "scale": [1.0, 1.0, 1.0],
+            }}},
+        ],
+    })
+
+    if obj.name == "Renamed" and tuple(obj.location) == (1.0, 2.0, 3.0):
+        result.ok("TransactionApplied applies rename and transform")
+    else:
+        result.fail("TransactionApplied applies rename and transform", f"{obj.name} {tuple(obj.location)}")
+
+    # ── HistoryApplied is applied even when this user authored it ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    obj = create_tagged_cube("cube-undo-001")
+    create_tagged_cube("cube-undo-002")
+
+    handle_history_applied({
+        "user_id": str(state.user_id),
+        "action": "Undo",
+        "created": [],
+        "updated": [{
+            "object_id": "cube-undo-001", "name": "Cube", "object_type": "Cube",
+            "asset_id": None, "asset_library": None, "properties": None,
+            "transform": {"position": [0.0, 0.0, 4.0], "rotation": [0.0, 0.0, 0.0], "scale": [1.0, 1.0, 1.0]},
+        }],
+        "deleted": ["cube-undo-002"],
+        "conflicts": [],
+        "version": 7,
+    })
+
+    if tuple(obj.location) == (0.0, 0.0, 4.0) and "cube-undo-002" not in state.object_map:
+        result.ok("HistoryApplied applies own undo (update + delete)")
+    else:
+        result.fail("HistoryApplied applies own undo (update + delete)", f"{tuple(obj.location)}")
+
+    if state.transform_cache.get("cube-undo-001", {}).get("position") == [0.0, 0.0, 4.0]:
+        result.ok("HistoryApplied refreshes transform cache (no echo)")
+    else:
+        result.fail("HistoryApplied refreshes transform cache (no echo)")
+
     test_connection_status_lines(result)
 
 
@@ -176,3 +236,17 @@ def test_connection_status_lines(result):
         result.ok("panel status: evicted reconnecting text")
     else:
         result.fail("panel status: evicted reconnecting text", str(lines))
+
+    # ── Rate-limited edits are resent once due, in order ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    first = {"event_type": "UpdateName", "payload": {"object_id": "obj-1", "name": "First"}}
+    second = {"event_type": "UpdateName", "payload": {"object_id": "obj-1", "name": "Second"}}
+    handle_event_rejected({"code": "RATE_LIMITED", "message": "", "retry_after_ms": 0, "event": first})
+    handle_event_rejected({"code": "RATE_LIMITED", "message": "", "retry_after_ms": 60_000, "event": second})
+    timer_function()
+    if mock_ws.get_sent("UpdateName") == [first] and len(state.rejected_events) == 1:
+        result.ok("EventRejected → due edit resent, later one still queued")
+    else:
+        result.fail("EventRejected → due edit resent, later one still queued", f"sent {mock_ws.sent_messages}")
@@ -1,7 +1,10 @@
 """Tests for FullStateSync — scene reconstruction, user_id extraction, clearing old objects."""
 import bpy
 from blender_plugin.state import PluginState
-from blender_plugin.event_handlers import handle_full_state_sync
+from blender_plugin.event_handlers import (
+    handle_full_state_sync, handle_state_sync_begin, handle_state_sync_chunk, handle_state_sync_end,
+    handle_state_delta, timer_function_transforms,
+)
 from blender_plugin.tests.helpers import (
     reset_state, clear_scene, create_tagged_cube, TestResult,
 )
@@ -255,3 +258,102 @@ def run(result):
         result.ok("reconnect: default cube is fully removed")
     else:
         result.fail("reconnect: default cube is fully removed")
+
+    # ── Chunked sync: Begin / Chunk / End reassembles into a full sync ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+
+    handle_state_sync_begin({
+        "session_id": "test-session",
+        "your_user_id": "user-chunk",
+        "users": {"user-chunk": {"display_name": "TestUser", "color": [255, 0, 0], "selected_object": None}},
+        "object_count": 2,
+        "chunk_count": 2,
+    })
+    handle_state_sync_chunk({"chunk_index": 0, "objects": [
+        {"object_id": "chunk-001", "object_type": "Cube", "name": "ChunkCube",
+         "transform": {"position": [1, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]}, "properties": None},
+    ]})
+
+    if len(bpy.data.objects) == 0:
+        result.ok("chunked sync: nothing applied before StateSyncEnd")
+    else:
+        result.fail("chunked sync: nothing applied before StateSyncEnd", f"has {len(bpy.data.objects)}")
+
+    handle_state_sync_chunk({"chunk_index": 1, "objects": [
+        {"object_id": "chunk-002", "object_type": "Sphere", "name": "ChunkSphere",
+         "transform": {"position": [2, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]}, "properties": None},
+    ]})
+    handle_state_sync_end({"object_count": 2, "checksum": "0000000000000000"})
+
+    if "chunk-001" in state.object_map and "chunk-002" in state.object_map:
+        result.ok("chunked sync: all chunk objects created on StateSyncEnd")
+    else:
+        result.fail("chunked sync: all chunk objects created", f"map keys: {list(state.object_map.keys())}")
+
+    if state.user_id == "user-chunk" and state.pending_sync is None:
+        result.ok("chunked sync: user_id applied and buffer cleared")
+    else:
+        result.fail("chunked sync: user_id applied and buffer cleared", f"user_id={state.user_id}")
+
+    # ── Chunked sync with a missing chunk asks for a resync ──
+
+    mock_ws.clear()
+    handle_state_sync_begin({"session_id": "test-session", "your_user_id": "user-chunk",
+                             "users": {}, "object_count": 3, "chunk_count": 2})
+    handle_state_sync_end({"object_count": 3, "checksum": "0000000000000000"})
+
+    if mock_ws.get_sent("RequestStateSync"):
+        result.ok("chunked sync: incomplete stream triggers RequestStateSync")
+    else:
+        result.fail("chunked sync: incomplete stream triggers RequestStateSync", f"sent: {mock_ws.get_sent()}")
+
+    # ── StateDelta updates objects in place ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    handle_full_state_sync({
+        "session": {
+            "objects": {
+                "delta-001": {"object_type": "Cube", "name": "DeltaCube",
+                              "transform": {"position": [0, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]}},
+            },
+            "users": {},
+            "version": 4,
+        },
+    })
+    cube = state.object_map.get("delta-001")
+    cube.select_set(True)
+    cube["local_note"] = "kept"
+    mock_ws.clear()
+
+    handle_state_delta({
+        "from_version": 4,
+        "to_version": 9,
+        "upserted": [
+            {"object_id": "delta-001", "object_type": "Cube", "name": "MovedCube",
+             "transform": {"position": [3, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]}},
+            {"object_id": "delta-002", "object_type": "Sphere", "name": "NewSphere",
+             "transform": {"position": [0, 2, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]}},
+        ],
+        "deleted": [],
+    })
+    updated = state.object_map.get("delta-001")
+    if updated is cube and cube.name == "MovedCube" and abs(cube.location.x - 3.0) < 0.001 \
+            and cube.select_get() and cube.get("local_note") == "kept":
+        result.ok("StateDelta: existing object updated in place, selection and local data kept")
+    else:
+        result.fail("StateDelta: existing object updated in place",
+                    f"same object: {updated is cube}, name: {updated.name if updated else None}")
+
+    if "delta-002" in state.object_map and state.session_version == 9:
+        result.ok("StateDelta: new object created and to_version stored")
+    else:
+        result.fail("StateDelta: new object created and to_version stored", f"version={state.session_version}")
+
+    timer_function_transforms()
+    if not mock_ws.get_sent("UpdateTransform") and not mock_ws.get_sent("UpdateName"):
+        result.ok("StateDelta: in-place update is not echoed")
+    else:
+        result.fail("StateDelta: in-place update is not echoed", f"sent: {mock_ws.get_sent()}")
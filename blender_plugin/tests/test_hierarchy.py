@@ -0,0 +1,95 @@
+"""Tests for parent/child sync — SetParent polling, ParentUpdated, parents in snapshots."""
+import bpy
+from blender_plugin.state import PluginState
+from blender_plugin.event_handlers import (
+    handle_full_state_sync,
+    handle_parent_updated,
+    timer_function_transforms,
+)
+from blender_plugin.tests.helpers import (
+    reset_state, clear_scene, create_tagged_cube, TestResult,
+)
+
+
+def run(result):
+    print("\n--- Hierarchy Tests ---")
+
+    # ── Parenting locally sends SetParent, without a transform ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    table = create_tagged_cube("table-001")
+    cup = create_tagged_cube("cup-001")
+    timer_function_transforms()
+    mock_ws.clear()
+
+    cup.parent = table
+    timer_function_transforms()
+    sent = mock_ws.get_sent("SetParent")
+    if len(sent) == 1 and sent[0]["payload"] == {"object_id": "cup-001", "parent_id": "table-001"}:
+        result.ok("parenting a cube → sends SetParent")
+    else:
+        result.fail("parenting a cube → sends SetParent", f"sent {sent}")
+
+    mock_ws.clear()
+    timer_function_transforms()
+    if not mock_ws.get_sent("SetParent"):
+        result.ok("unchanged parent → no re-send")
+    else:
+        result.fail("unchanged parent → no re-send", "sent SetParent again")
+
+    # ── ParentUpdated applies parent and local transform ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    table = create_tagged_cube("table-001")
+    table.location = (1.0, 0.0, 0.0)
+    cup = create_tagged_cube("cup-001")
+
+    handle_parent_updated({
+        "object_id": "cup-001",
+        "parent_id": "table-001",
+        "transform": {"position": [2.0, 0.0, 0.0], "rotation": [0, 0, 0], "scale": [1, 1, 1]},
+        "updated_by": state.user_id,
+    })
+    bpy.context.view_layer.update()
+    if cup.parent == table and abs(cup.matrix_world.translation.x - 3.0) < 0.001:
+        result.ok("ParentUpdated → parented with local transform")
+    else:
+        result.fail("ParentUpdated → parented with local transform",
+                    f"parent={cup.parent}, world x={cup.matrix_world.translation.x}")
+
+    mock_ws.clear()
+    timer_function_transforms()
+    if not mock_ws.get_sent("SetParent") and not mock_ws.get_sent("UpdateTransform"):
+        result.ok("ParentUpdated → no echo")
+    else:
+        result.fail("ParentUpdated → no echo", f"sent {mock_ws.sent_messages}")
+
+    # ── Snapshots link children to parents listed after them ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    handle_full_state_sync({
+        "session": {
+            "objects": {
+                "child-001": {
+                    "object_type": "Cube",
+                    "name": "Child",
+                    "parent_id": "parent-001",
+                    "transform": {"position": [0, 0, 1], "rotation": [0, 0, 0], "scale": [1, 1, 1]},
+                },
+                "parent-001": {
+                    "object_type": "Cube",
+                    "name": "Parent",
+                    "transform": {"position": [0, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]},
+                },
+            },
+            "users": {},
+        }
+    })
+    child = state.object_map.get("child-001")
+    if child is not None and child.parent == state.object_map.get("parent-001"):
+        result.ok("FullStateSync → child parented")
+    else:
+        result.fail("FullStateSync → child parented", f"parent={child.parent if child else None}")
@@ -0,0 +1,162 @@
+"""Tests for material sync — Material* events, slot polling, materials in snapshots."""
+import bpy
+from blender_plugin.state import PluginState
+from blender_plugin.event_handlers import (
+    detect_and_sync_materials,
+    handle_full_state_sync,
+    handle_material_created,
+    handle_material_updated,
+    handle_object_materials_updated,
+    timer_function_transforms,
+)
+from blender_plugin.tests.helpers import (
+    reset_state, clear_scene, create_tagged_cube, TestResult,
+)
+
+
+def _material(material_id, name, **settings):
+    return {
+        "material_id": material_id,
+        "name": name,
+        "base_color": settings.get("base_color", [0.8, 0.8, 0.8]),
+        "metallic": settings.get("metallic", 0.0),
+        "roughness": settings.get("roughness", 0.5),
+        "emission_color": settings.get("emission_color", [1.0, 1.0, 1.0]),
+        "emission_strength": settings.get("emission_strength", 0.0),
+        "alpha": settings.get("alpha", 1.0),
+    }
+
+
+def _close(a, b):
+    return all(abs(x - y) < 1e-4 for x, y in zip(a, b))
+
+
+def run(result):
+    print("\n--- Material Tests ---")
+
+    # ── MaterialCreated / MaterialUpdated build the Blender material ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    handle_material_created({
+        "material": _material("mat-001", "Red Paint", base_color=[0.9, 0.1, 0.1], roughness=0.3),
+        "created_by": "other-user",
+    })
+    red = state.materials.get("mat-001")
+    bsdf = next((n for n in red.node_tree.nodes if n.type == "BSDF_PRINCIPLED"), None) if red else None
+    if red is not None and red.name == "Red Paint" and bsdf is not None \
+            and _close(bsdf.inputs["Base Color"].default_value[:3], [0.9, 0.1, 0.1]) \
+            and abs(bsdf.inputs["Roughness"].default_value - 0.3) < 1e-4:
+        result.ok("MaterialCreated → Principled BSDF material")
+    else:
+        result.fail("MaterialCreated → Principled BSDF material", f"got {red}")
+
+    handle_material_updated({
+        "material": _material("mat-001", "Red Paint", base_color=[0.9, 0.1, 0.1], metallic=1.0, alpha=0.5),
+        "updated_by": "other-user",
+    })
+    if abs(bsdf.inputs["Metallic"].default_value - 1.0) < 1e-4 and abs(bsdf.inputs["Alpha"].default_value - 0.5) < 1e-4:
+        result.ok("MaterialUpdated → metallic and alpha applied")
+    else:
+        result.fail("MaterialUpdated → metallic and alpha applied",
+                    f"metallic={bsdf.inputs['Metallic'].default_value}, alpha={bsdf.inputs['Alpha'].default_value}")
+
+    detect_and_sync_materials()
+    if not mock_ws.get_sent("UpdateMaterial") and not mock_ws.get_sent("CreateMaterial"):
+        result.ok("MaterialUpdated → no echo")
+    else:
+        result.fail("MaterialUpdated → no echo", f"sent {mock_ws.sent_messages}")
+
+    # ── Local material edits are sent ──
+
+    bsdf.inputs["Roughness"].default_value = 0.8
+    detect_and_sync_materials()
+    sent = mock_ws.get_sent("UpdateMaterial")
+    if len(sent) == 1 and sent[0]["payload"]["material_id"] == "mat-001" \
+            and abs(sent[0]["payload"]["roughness"] - 0.8) < 1e-4:
+        result.ok("editing roughness → sends UpdateMaterial")
+    else:
+        result.fail("editing roughness → sends UpdateMaterial", f"sent {sent}")
+
+    # ── Slot polling shares new materials first, then the assignment ──
+
+    mock_ws.clear()
+    cube = create_tagged_cube("cube-001")
+    timer_function_transforms()
+    mock_ws.clear()
+    wood = bpy.data.materials.new("Wood")
+    cube.data.materials.append(wood)
+    timer_function_transforms()
+    if not mock_ws.get_sent("SetObjectMaterials"):
+        result.ok("unshared material in a slot → assignment waits")
+    else:
+        result.fail("unshared material in a slot → assignment waits", f"sent {mock_ws.sent_messages}")
+
+    detect_and_sync_materials()
+    created = mock_ws.get_sent("CreateMaterial")
+    wood_id = wood.get("meerkat_material_id")
+    if len(created) == 1 and created[0]["payload"]["name"] == "Wood" and created[0]["payload"]["material_id"] == wood_id:
+        result.ok("material used by a synced object → sends CreateMaterial")
+    else:
+        result.fail("material used by a synced object → sends CreateMaterial", f"sent {created}")
+
+    timer_function_transforms()
+    sent = mock_ws.get_sent("SetObjectMaterials")
+    if len(sent) == 1 and sent[0]["payload"] == {"object_id": "cube-001", "material_slots": [wood_id]}:
+        result.ok("assigning a material → sends SetObjectMaterials")
+    else:
+        result.fail("assigning a material → sends SetObjectMaterials", f"sent {sent}")
+
+    mock_ws.clear()
+    handle_object_materials_updated({
+        "object_id": "cube-001",
+        "material_slots": [None, "mat-001"],
+        "updated_by": "other-user",
+    })
+    timer_function_transforms()
+    slots = [slot.material for slot in cube.material_slots]
+    if slots == [None, red] and not mock_ws.get_sent("SetObjectMaterials"):
+        result.ok("ObjectMaterialsUpdated → slots replaced, no echo")
+    else:
+        result.fail("ObjectMaterialsUpdated → slots replaced, no echo", f"slots={slots}, sent {mock_ws.sent_messages}")
+
+    # ── Snapshots bring materials and slots ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    handle_full_state_sync({
+        "session": {
+            "objects": {
+                "cube-002": {
+                    "object_type": "Cube",
+                    "name": "Crate",
+                    "material_slots": ["mat-002"],
+                    "transform": {"position": [0, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]},
+                },
+            },
+            "materials": {"mat-002": _material("mat-002", "Oak", base_color=[0.4, 0.25, 0.1])},
+            "users": {},
+        },
+        "your_user_id": state.user_id,
+    })
+    crate = state.object_map.get("cube-002")
+    oak = state.materials.get("mat-002")
+    if crate is not None and oak is not None and [slot.material for slot in crate.material_slots] == [oak]:
+        result.ok("FullStateSync → materials and slots restored")
+    else:
+        result.fail("FullStateSync → materials and slots restored", f"object={crate}, material={oak}")
+
+    mock_ws.clear()
+    timer_function_transforms()
+    detect_and_sync_materials()
+    if not mock_ws.get_sent("SetObjectMaterials") and not mock_ws.get_sent("CreateMaterial") \
+            and not mock_ws.get_sent("UpdateMaterial"):
+        result.ok("FullStateSync materials → no echo")
+    else:
+        result.fail("FullStateSync materials → no echo", f"sent {mock_ws.sent_messages}")
+
+
+if __name__ == "__main__":
+    r = TestResult()
+    run(r)
+    r.summary()
@@ -0,0 +1,189 @@
+"""Tests for mesh geometry sync — encoding, uploads of edited meshes, ObjectMeshUpdated downloads."""
+import base64
+import bpy
+from blender_plugin import mesh_data
+from blender_plugin.state import PluginState
+from blender_plugin.event_handlers import (
+    detect_and_send_creations,
+    detect_and_sync_meshes,
+    handle_full_state_sync,
+    handle_mesh_chunk,
+    handle_object_created,
+    handle_object_mesh_updated,
+)
+from blender_plugin.tests.helpers import reset_state, clear_scene, TestResult
+
+TRANSFORM = {"position": [0, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]}
+
+# One triangle with UVs.
+TRIANGLE = mesh_data.encode(
+    [0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0],
+    [3],
+    [0, 1, 2],
+    [0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+)
+TRIANGLE_HASH = mesh_data.content_hash(TRIANGLE)
+
+
+def _uploaded(mock_ws):
+    """The geometry assembled from every UploadMeshChunk sent, keyed by hash."""
+    chunks = {}
+    for message in mock_ws.get_sent("UploadMeshChunk"):
+        p = message["payload"]
+        chunks.setdefault(p["hash"], {})[p["chunk_index"]] = base64.b64decode(p["data"])
+    return {h: mesh_data.inflate(b"".join(c[i] for i in sorted(c))) for h, c in chunks.items()}
+
+
+def _send_chunks(mesh_hash, encoded):
+    chunks = mesh_data.to_chunks(mesh_data.compress(encoded))
+    for index, data in enumerate(chunks):
+        handle_mesh_chunk({"hash": mesh_hash, "chunk_index": index, "chunk_count": len(chunks), "data": data})
+
+
+def run(result):
+    print("\n--- Mesh Tests ---")
+
+    # ── Encoding matches the server's format ──
+
+    decoded = mesh_data.decode(TRIANGLE)
+    if list(decoded["face_vertices"]) == [0, 1, 2] and list(decoded["uvs"]) == [0.0, 0.0, 1.0, 0.0, 0.0, 1.0] \
+            and mesh_data.inflate(mesh_data.compress(TRIANGLE)) == TRIANGLE and len(TRIANGLE_HASH) == 64:
+        result.ok("mesh_data encode/decode/compress round trip")
+    else:
+        result.fail("mesh_data encode/decode/compress round trip", f"decoded {decoded}")
+
+    # ── Editing a synced primitive uploads its geometry ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    handle_object_created({
+        "object": {"object_id": "cube-001", "object_type": "Cube", "name": "Cube", "transform": TRANSFORM},
+        "created_by": "other-user",
+    })
+    cube = state.object_map.get("cube-001")
+    state.dirty_meshes.add("cube-001")
+    detect_and_sync_meshes()
+    if not mock_ws.get_sent("UploadMeshChunk") and not mock_ws.get_sent("SetObjectMesh"):
+        result.ok("untouched primitive → no mesh upload")
+    else:
+        result.fail("untouched primitive → no mesh upload", f"sent {mock_ws.sent_messages}")
+
+    cube.data.vertices[0].co.z += 1.0
+    state.dirty_meshes.add("cube-001")
+    detect_and_sync_meshes()
+    sent = mock_ws.get_sent("SetObjectMesh")
+    uploaded = _uploaded(mock_ws)
+    if len(sent) == 1 and sent[0]["payload"]["base_version"] == 0 \
+            and list(uploaded) == [sent[0]["payload"]["hash"]] \
+            and mesh_data.content_hash(uploaded[sent[0]["payload"]["hash"]]) == sent[0]["payload"]["hash"] \
+            and len(mesh_data.decode(uploaded[sent[0]["payload"]["hash"]])["positions"]) == 8 * 3:
+        result.ok("editing a cube → uploads mesh, sends SetObjectMesh")
+    else:
+        result.fail("editing a cube → uploads mesh, sends SetObjectMesh", f"sent {sent}")
+
+    edited_hash = sent[0]["payload"]["hash"] if sent else ""
+    handle_object_mesh_updated({
+        "object_id": "cube-001",
+        "mesh": {"hash": edited_hash},
+        "mesh_version": 1,
+        "updated_by": state.user_id,
+    })
+    mock_ws.clear()
+    cube.data.vertices[0].co.z -= 1.0
+    cube.data.vertices[0].co.z += 1.0
+    state.dirty_meshes.add("cube-001")
+    detect_and_sync_meshes()
+    if not mock_ws.get_sent("SetObjectMesh") and not mock_ws.get_sent("RequestMesh"):
+        result.ok("own ObjectMeshUpdated → no download, unchanged geometry not resent")
+    else:
+        result.fail("own ObjectMeshUpdated → no download, unchanged geometry not resent", f"sent {mock_ws.sent_messages}")
+
+    cube.data.vertices[1].co.z += 1.0
+    state.dirty_meshes.add("cube-001")
+    detect_and_sync_meshes()
+    sent = mock_ws.get_sent("SetObjectMesh")
+    if len(sent) == 1 and sent[0]["payload"]["base_version"] == 1:
+        result.ok("second edit → SetObjectMesh against version 1")
+    else:
+        result.fail("second edit → SetObjectMesh against version 1", f"sent {sent}")
+
+    # ── Remote mesh updates download and apply without echo ──
+
+    mock_ws.clear()
+    handle_object_mesh_updated({
+        "object_id": "cube-001",
+        "mesh": {"hash": TRIANGLE_HASH},
+        "mesh_version": 3,
+        "updated_by": "other-user",
+    })
+    requested = mock_ws.get_sent("RequestMesh")
+    if len(requested) == 1 and requested[0]["payload"]["hash"] == TRIANGLE_HASH:
+        result.ok("ObjectMeshUpdated with unknown mesh → sends RequestMesh")
+    else:
+        result.fail("ObjectMeshUpdated with unknown mesh → sends RequestMesh", f"sent {requested}")
+
+    _send_chunks(TRIANGLE_HASH, TRIANGLE)
+    mesh = cube.data
+    if len(mesh.vertices) == 3 and len(mesh.polygons) == 1 and abs(mesh.vertices[1].co.x - 2.0) < 1e-6 \
+            and mesh.uv_layers.active is not None:
+        result.ok("MeshChunk → geometry written into the object")
+    else:
+        result.fail("MeshChunk → geometry written into the object",
+                    f"{len(mesh.vertices)} vertices, {len(mesh.polygons)} faces")
+
+    mock_ws.clear()
+    state.dirty_meshes.add("cube-001")
+    detect_and_sync_meshes()
+    if not mock_ws.get_sent("SetObjectMesh") and not mock_ws.get_sent("UploadMeshChunk"):
+        result.ok("downloaded mesh → no echo")
+    else:
+        result.fail("downloaded mesh → no echo", f"sent {mock_ws.sent_messages}")
+
+    # ── Meshes that aren't primitives sync as mesh data ──
+
+    mock_ws.clear()
+    imported = bpy.data.objects.new("Scan", bpy.data.meshes.new("Scan"))
+    bpy.context.collection.objects.link(imported)
+    imported.data.from_pydata([(0, 0, 0), (1, 0, 0), (1, 1, 0), (0, 1, 0)], [], [(0, 1, 2, 3)])
+    detect_and_send_creations()
+    created = mock_ws.get_sent("CreateObject")
+    sent = mock_ws.get_sent("SetObjectMesh")
+    if len(created) == 1 and created[0]["payload"]["object_type"] == "Mesh" and len(sent) == 1 \
+            and sent[0]["payload"]["object_id"] == created[0]["payload"]["object_id"] and _uploaded(mock_ws):
+        result.ok("imported mesh → CreateObject Mesh plus its mesh data")
+    else:
+        result.fail("imported mesh → CreateObject Mesh plus its mesh data", f"sent {mock_ws.sent_messages}")
+
+    # ── Snapshots request shared mesh data once ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    mesh_ref = {"hash": TRIANGLE_HASH}
+    handle_full_state_sync({
+        "session": {
+            "objects": {
+                "mesh-001": {"object_type": "Mesh", "name": "Shard", "transform": TRANSFORM, "mesh": mesh_ref},
+                "mesh-002": {"object_type": "Mesh", "name": "Shard Copy", "transform": TRANSFORM, "mesh": mesh_ref},
+            },
+            "users": {},
+        },
+        "your_user_id": state.user_id,
+    })
+    requested = mock_ws.get_sent("RequestMesh")
+    if len(requested) == 1:
+        result.ok("FullStateSync → one RequestMesh per shared mesh")
+    else:
+        result.fail("FullStateSync → one RequestMesh per shared mesh", f"sent {requested}")
+
+    _send_chunks(TRIANGLE_HASH, TRIANGLE)
+    counts = [len(state.object_map[i].data.polygons) for i in ("mesh-001", "mesh-002") if i in state.object_map]
+    if counts == [1, 1]:
+        result.ok("FullStateSync mesh data → applied to every object using it")
+    else:
+        result.fail("FullStateSync mesh data → applied to every object using it", f"face counts {counts}")
+
+
+if __name__ == "__main__":
+    r = TestResult()
+    run(r)
+    r.summary()
@@ -0,0 +1,154 @@
+"""Tests for modifier sync — stack polling, ObjectModifiersUpdated, modifiers in snapshots."""
+import bpy
+from blender_plugin.state import PluginState
+from blender_plugin.event_handlers import (
+    handle_full_state_sync,
+    handle_object_modifiers_updated,
+    timer_function_transforms,
+)
+from blender_plugin.tests.helpers import (
+    reset_state, clear_scene, create_tagged_cube, TestResult,
+)
+
+TRANSFORM = {"position": [0, 0, 0], "rotation": [0, 0, 0], "scale": [1, 1, 1]}
+
+
+def _payloads(mock_ws, event_type):
+    return [message["payload"] for message in mock_ws.get_sent(event_type)]
+
+
+def run(result):
+    print("\n--- Modifier Tests ---")
+
+    # ── Local stack edits are sent ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    cube = create_tagged_cube("cube-001")
+    timer_function_transforms()
+    mock_ws.clear()
+
+    array = cube.modifiers.new("Array", "ARRAY")
+    array.count = 4
+    timer_function_transforms()
+    added = _payloads(mock_ws, "AddModifier")
+    if len(added) == 1 and added[0]["modifier"]["modifier_type"] == "Array" \
+            and added[0]["modifier"]["count"] == 4 and added[0]["modifier"]["relative_offset_displace"] == [1.0, 0.0, 0.0]:
+        result.ok("adding an array modifier → sends AddModifier")
+    else:
+        result.fail("adding an array modifier → sends AddModifier", f"sent {mock_ws.sent_messages}")
+    array_id = added[0]["modifier"]["modifier_id"] if added else ""
+
+    mock_ws.clear()
+    array.count = 6
+    timer_function_transforms()
+    updated = _payloads(mock_ws, "UpdateModifier")
+    if len(updated) == 1 and updated[0]["modifier"]["modifier_id"] == array_id and updated[0]["modifier"]["count"] == 6 \
+            and not mock_ws.get_sent("AddModifier"):
+        result.ok("changing a setting → sends UpdateModifier with the same id")
+    else:
+        result.fail("changing a setting → sends UpdateModifier with the same id", f"sent {mock_ws.sent_messages}")
+
+    mock_ws.clear()
+    solidify = cube.modifiers.new("Solidify", "SOLIDIFY")
+    solidify.thickness = 0.25
+    cube.modifiers.move(1, 0)
+    timer_function_transforms()
+    added = _payloads(mock_ws, "AddModifier")
+    reordered = _payloads(mock_ws, "ReorderModifiers")
+    parameters = added[0]["modifier"].get("parameters", {}) if added else {}
+    if len(added) == 1 and added[0]["modifier"]["blender_type"] == "SOLIDIFY" \
+            and abs(parameters.get("thickness", {}).get("Number", 0) - 0.25) < 1e-6 \
+            and len(reordered) == 1 and reordered[0]["modifier_ids"] == [added[0]["modifier"]["modifier_id"], array_id]:
+        result.ok("adding a solidify modifier on top → AddModifier with parameters, then ReorderModifiers")
+    else:
+        result.fail("adding a solidify modifier on top → AddModifier with parameters, then ReorderModifiers",
+                    f"sent {mock_ws.sent_messages}")
+
+    mock_ws.clear()
+    cube.modifiers.remove(array)
+    timer_function_transforms()
+    removed = _payloads(mock_ws, "RemoveModifier")
+    if removed == [{"object_id": "cube-001", "modifier_id": array_id}] and not mock_ws.get_sent("ReorderModifiers"):
+        result.ok("removing a modifier → sends RemoveModifier")
+    else:
+        result.fail("removing a modifier → sends RemoveModifier", f"sent {mock_ws.sent_messages}")
+
+    # ── Remote stacks are applied without echo ──
+
+    mock_ws.clear()
+    handle_object_modifiers_updated({
+        "object_id": "cube-001",
+        "modifiers": [
+            {"modifier_id": "mod-sub", "name": "Subdivision", "modifier_type": "Subdivision",
+             "levels": 2, "render_levels": 3, "subdivision_type": "SIMPLE"},
+            {"modifier_id": "mod-bevel", "name": "Bevel", "modifier_type": "Bevel", "show_render": False,
+             "affect": "EDGES", "width": 0.2, "segments": 3, "limit_method": "ANGLE", "angle_limit": 0.5},
+        ],
+        "updated_by": "other-user",
+    })
+    timer_function_transforms()
+    stack = [(m.name, m.type) for m in cube.modifiers]
+    bevel = cube.modifiers.get("Bevel")
+    subdivision = cube.modifiers.get("Subdivision")
+    if stack == [("Subdivision", "SUBSURF"), ("Bevel", "BEVEL")] and subdivision.levels == 2 \
+            and subdivision.subdivision_type == "SIMPLE" and bevel.segments == 3 and not bevel.show_render \
+            and not [m for m in mock_ws.sent_messages if "Modifier" in m["event_type"]]:
+        result.ok("ObjectModifiersUpdated → stack rebuilt in order, no echo")
+    else:
+        result.fail("ObjectModifiersUpdated → stack rebuilt in order, no echo",
+                    f"stack {stack}, sent {mock_ws.sent_messages}")
+
+    bevel.width = 0.3
+    timer_function_transforms()
+    updated = _payloads(mock_ws, "UpdateModifier")
+    if len(updated) == 1 and updated[0]["modifier"]["modifier_id"] == "mod-bevel":
+        result.ok("editing a remote modifier → UpdateModifier under the server's id")
+    else:
+        result.fail("editing a remote modifier → UpdateModifier under the server's id", f"sent {updated}")
+
+    mock_ws.clear()
+    handle_object_modifiers_updated({"object_id": "cube-001", "modifiers": [], "updated_by": state.user_id})
+    if len(cube.modifiers) == 2:
+        result.ok("own ObjectModifiersUpdated → ignored")
+    else:
+        result.fail("own ObjectModifiersUpdated → ignored", f"{len(cube.modifiers)} modifiers left")
+
+    # ── Snapshots carry stacks, mirror objects included ──
+
+    clear_scene()
+    state, mock_ws = reset_state()
+    handle_full_state_sync({
+        "session": {
+            "objects": {
+                "cube-001": {
+                    "object_type": "Cube", "name": "Hull", "transform": TRANSFORM,
+                    "modifiers": [{"modifier_id": "mod-mirror", "name": "Mirror", "modifier_type": "Mirror",
+                                   "use_axis": [False, True, False], "mirror_object": "cube-002"}],
+                },
+                "cube-002": {"object_type": "Cube", "name": "Pivot", "transform": TRANSFORM},
+            },
+            "users": {},
+        },
+        "your_user_id": state.user_id,
+    })
+    hull = state.object_map.get("cube-001")
+    mirror = hull.modifiers.get("Mirror") if hull else None
+    if mirror is not None and list(mirror.use_axis) == [False, True, False] \
+            and mirror.mirror_object == state.object_map.get("cube-002") \
+            and state.modifier_cache["cube-001"][0]["mirror_object"] == "cube-002":
+        result.ok("FullStateSync → modifiers applied, mirror object linked")
+    else:
+        result.fail("FullStateSync → modifiers applied, mirror object linked", f"mirror {mirror}")
+
+    timer_function_transforms()
+    if not [m for m in mock_ws.sent_messages if "Modifier" in m["event_type"]]:
+        result.ok("FullStateSync modifiers → no echo")
+    else:
+        result.fail("FullStateSync modifiers → no echo", f"sent {mock_ws.sent_messages}")
+
+
+if __name__ == "__main__":
+    r = TestResult()
+    run(r)
+    r.summary()
@@ -7,6 +7,7 @@ from blender_plugin.event_handlers import (
     _build_sun_light_props,
     _get_property_builder,
     timer_function_transforms,
+    handle_properties_patched,
     handle_properties_updated,
 )
 from blender_plugin.tests.helpers import (
@@ -93,41 +94,45 @@ def run(result):
     else:
         result.fail("_get_property_builder → None for cube")
 
-    # ── Camera property change sends UpdateProperties ──
+    # ── Camera property change sends a PatchProperties with just that field ──
 
     clear_scene()
     state, mock_ws = reset_state()
     obj = create_tagged_camera("cam-poll-001")
 
-    # First tick populates cache
+    # First tick populates cache and sends the whole struct
     timer_function_transforms()
+    if len(mock_ws.get_sent("UpdateProperties")) == 1:
+        result.ok("first camera poll → sends UpdateProperties")
+    else:
+        result.fail("first camera poll → sends UpdateProperties", f"sent {mock_ws.sent_messages}")
     mock_ws.clear()
 
     # Change focal length
     obj.data.lens = 85.0
     timer_function_transforms()
-    sent = mock_ws.get_sent("UpdateProperties")
-    if len(sent) == 1:
-        result.ok("camera focal_length change → sends UpdateProperties")
+    sent = mock_ws.get_sent("PatchProperties")
+    if len(sent) == 1 and not mock_ws.get_sent("UpdateProperties"):
+        result.ok("camera focal_length change → sends PatchProperties")
     else:
-        result.fail("camera focal_length change → sends UpdateProperties", f"sent {len(sent)}")
+        result.fail("camera focal_length change → sends PatchProperties", f"sent {mock_ws.sent_messages}")
 
-    if sent and sent[0]["payload"]["properties"]["Camera"]["focal_length"] == 85.0:
-        result.ok("UpdateProperties payload has correct focal_length")
+    if sent and sent[0]["payload"]["patches"] == [{"path": "camera.focal_length", "value": 85.0}]:
+        result.ok("PatchProperties payload has only focal_length")
     else:
-        result.fail("UpdateProperties payload has correct focal_length")
+        result.fail("PatchProperties payload has only focal_length", f"sent {sent}")
 
     # ── No re-send when camera props unchanged ──
 
     mock_ws.clear()
     timer_function_transforms()
-    sent = mock_ws.get_sent("UpdateProperties")
+    sent = mock_ws.get_sent("UpdateProperties") + mock_ws.get_sent("PatchProperties")
     if len(sent) == 0:
         result.ok("camera props unchanged → no re-send")
     else:
         result.fail("camera props unchanged → no re-send", f"sent {len(sent)}")
 
-    # ── Point light color change sends UpdateProperties ──
+    # ── Point light color change sends PatchProperties ──
 
     clear_scene()
     state, mock_ws = reset_state()
@@ -138,22 +143,22 @@ def run(result):
 
     obj.data.color = (1.0, 0.0, 0.0)
     timer_function_transforms()
-    sent = mock_ws.get_sent("UpdateProperties")
-    if len(sent) == 1:
-        result.ok("light color change → sends UpdateProperties")
+    sent = mock_ws.get_sent("PatchProperties")
+    if len(sent) == 1 and [p["path"] for p in sent[0]["payload"]["patches"]] == ["point.color"]:
+        result.ok("light color change → sends PatchProperties")
     else:
-        result.fail("light color change → sends UpdateProperties", f"sent {len(sent)}")
+        result.fail("light color change → sends PatchProperties", f"sent {sent}")
 
-    # ── Point light power change sends UpdateProperties ──
+    # ── Point light power change sends PatchProperties ──
 
     mock_ws.clear()
     obj.data.energy = 500.0
     timer_function_transforms()
-    sent = mock_ws.get_sent("UpdateProperties")
-    if len(sent) == 1:
-        result.ok("light power change → sends UpdateProperties")
+    sent = mock_ws.get_sent("PatchProperties")
+    if len(sent) == 1 and sent[0]["payload"]["patches"] == [{"path": "point.power", "value": 500.0}]:
+        result.ok("light power change → sends PatchProperties")
     else:
-        result.fail("light power change → sends UpdateProperties", f"sent {len(sent)}")
+        result.fail("light power change → sends PatchProperties", f"sent {sent}")
 
     # ── Cube has no properties to sync ──
 
@@ -162,7 +167,7 @@ def run(result):
     obj = create_tagged_cube("cube-noprop-001")
 
     timer_function_transforms()
-    sent = mock_ws.get_sent("UpdateProperties")
+    sent = mock_ws.get_sent("UpdateProperties") + mock_ws.get_sent("PatchProperties")
     if len(sent) == 0:
         result.ok("cube → no UpdateProperties sent")
     else:
@@ -228,8 +233,34 @@ def run(result):
 
     mock_ws.clear()
     timer_function_transforms()
-    sent = mock_ws.get_sent("UpdateProperties")
+    sent = mock_ws.get_sent("UpdateProperties") + mock_ws.get_sent("PatchProperties")
     if len(sent) == 0:
         result.ok("after property receive → cache updated, no re-send")
     else:
         result.fail("after property receive → cache updated, no re-send", f"sent {len(sent)}")
+
+    # ── PropertiesPatched applies the fields it addresses ──
+
+    handle_properties_patched({
+        "object_id": "pl-recv-001",
+        "updated_by": "other-user-456",
+        "patches": [
+            {"path": "point.power", "value": 750.0},
+            {"path": "sun.angle", "value": 0.1},
+            {"path": "render.holdout", "value": True},
+            {"path": "point.radius", "value": None},
+        ],
+    })
+    if abs(obj.data.energy - 750.0) < 0.001 and abs(obj.data.shadow_soft_size - 3.0) < 0.001:
+        result.ok("PropertiesPatched → applies point.power, ignores other paths")
+    else:
+        result.fail("PropertiesPatched → applies point.power, ignores other paths",
+                    f"energy={obj.data.energy}, radius={obj.data.shadow_soft_size}")
+
+    mock_ws.clear()
+    timer_function_transforms()
+    sent = mock_ws.get_sent("UpdateProperties") + mock_ws.get_sent("PatchProperties")
+    if len(sent) == 0:
+        result.ok("after PropertiesPatched → no echo")
+    else:
+        result.fail("after PropertiesPatched → no echo", f"sent {sent}")